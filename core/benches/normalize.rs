@@ -0,0 +1,56 @@
+//! Timings of `normalize()` on statements of the sizes seen in production
+//! logs, from a short ORM query to a generated INSERT of 10,000 rows.
+//!
+//! Run with `cargo bench -p sqlfp-core`, optionally filtered by case name
+//! (`cargo bench -p sqlfp-core -- insert`). Criterion keeps the last run in
+//! `target/criterion` and reports the change from it.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use sqlfp_core::{normalize, Dialect, Options};
+
+/// A multi-row INSERT of `rows` rows of five literals each
+fn bulk_insert(rows: usize) -> String {
+    let values: Vec<String> = (0..rows).map(|i| format!("({i}, 'name {i}', {i}.5, NULL, TRUE)")).collect();
+    format!("INSERT INTO events (id, name, score, note, active) VALUES {}", values.join(", "))
+}
+
+/// A query with a long IN list, as ORMs generate for batched lookups
+fn in_list(items: usize) -> String {
+    let ids: Vec<String> = (0..items).map(|i| i.to_string()).collect();
+    format!("SELECT id, name FROM users WHERE tenant_id = 7 AND id IN ({}) ORDER BY id", ids.join(", "))
+}
+
+const ORM_QUERY: &str = "SELECT u.id, u.email, o.total FROM users AS u INNER JOIN orders AS o ON o.user_id = u.id \
+     WHERE u.created_at > '2024-01-01' AND o.status IN ('paid', 'shipped') ORDER BY o.total DESC LIMIT 20 OFFSET 40";
+
+fn bench(c: &mut Criterion, name: &str, sql: &str, dialect: &str, options: Options) {
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(sql.len() as u64));
+    // The large statements take milliseconds a call
+    if sql.len() > 10_000 {
+        group.sample_size(20);
+    }
+    group.bench_function(dialect, |b| {
+        b.iter(|| {
+            let dialect = Dialect::new(dialect).expect("known dialect");
+            normalize(black_box(sql), dialect, &options).expect("normalizes")
+        })
+    });
+    group.finish();
+}
+
+fn normalize_benches(c: &mut Criterion) {
+    let insert = bulk_insert(10_000);
+    let lookup = in_list(5_000);
+    bench(c, "orm_query", ORM_QUERY, "postgres", Options::default());
+    bench(c, "in_list_5k", &lookup, "postgres", Options::default());
+    bench(c, "in_list_5k_collapsed", &lookup, "postgres", Options { collapse_in_lists: true, ..Options::default() });
+    bench(c, "insert_10k_rows", &insert, "mysql", Options::default());
+    bench(c, "insert_10k_rows_no_params", &insert, "mysql", Options { collect_params: false, ..Options::default() });
+    bench(c, "insert_10k_rows_collapsed", &insert, "mysql", Options { collapse_values: true, ..Options::default() });
+}
+
+criterion_group!(benches, normalize_benches);
+criterion_main!(benches);
@@ -0,0 +1,75 @@
+#ifndef SQLFP_H
+#define SQLFP_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The statement was normalized
+#define SQLFP_OK 0
+
+// The statement does not parse, or there is none
+#define SQLFP_PARSE_ERROR 1
+
+// An argument is NULL or not UTF-8, or the dialect is not supported
+#define SQLFP_INVALID_ARGUMENT 2
+
+// The statement cannot be normalized with these options
+#define SQLFP_NORMALIZE_ERROR 3
+
+// The normalizer panicked, which is a bug; only returned by a library built
+// to unwind on panic, as with the `release-ffi` profile, which others abort on
+#define SQLFP_INTERNAL_ERROR 4
+
+// The normalized statement or a param holds a NUL byte, as a string literal
+// can, which a C string cannot
+#define SQLFP_NUL_IN_OUTPUT 5
+
+// The fingerprint of a statement, or why there is none
+typedef struct SqlfpResult {
+  // The statement with its literals replaced by `?`
+  char *normalized;
+  // SHA-256 of `normalized`, in hex
+  char *hash;
+  // The literals replaced, without the prefix of a prefixed string
+  // (`'abc'` for `N'abc'`), `params_len` of them (NULL for none)
+  char **params;
+  size_t params_len;
+  // Why the statement was not normalized, NULL on `SQLFP_OK`
+  char *error;
+} SqlfpResult;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Normalize the first statement of `sql` with the grammar of `dialect` (a
+// name `normalize()` takes, NULL for `"generic"`) into `*out_result`; the
+// status, `SQLFP_OK` or the kind of error.
+//
+// # Safety
+//
+// `sql` and `dialect` are NULL or NUL-terminated strings, and `out_result`
+// is NULL or points to a `SqlfpResult` to overwrite, without freeing what it
+// held. Unless `out_result` is NULL, which is `SQLFP_INVALID_ARGUMENT`, the
+// result must be passed to `sqlfp_free_result()` whatever the status.
+int sqlfp_normalize(const char *sql, const char *dialect, struct SqlfpResult *out_result);
+
+// Free the strings of `*result` and set its fields to NULL, so that freeing
+// it again does nothing; NULL is ignored.
+//
+// # Safety
+//
+// `result` is NULL or points to a `SqlfpResult` filled by
+// `sqlfp_normalize()`, or already freed, whose fields were not changed.
+void sqlfp_free_result(struct SqlfpResult *result);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SQLFP_H */
@@ -0,0 +1,111 @@
+// JavaScript wrapper of the sqlfp-core WebAssembly module (the `wasm`
+// feature, see core/src/wasm.rs), for browsers and Node. It imports the glue
+// wasm-bindgen generates into ./pkg:
+//
+//     import { load } from "./sqlfp.js";
+//     const sqlfp = await load(await fetch("pkg/sqlfp_core_bg.wasm"));
+//     sqlfp.normalize("SELECT * FROM users WHERE id = 42", { dialect: "postgres" });
+//     // { normalized: "SELECT * FROM users WHERE id = ?", hash: "...", params: ["42"], warnings: [] }
+
+import init, { normalize as normalizeWasm } from "./pkg/sqlfp_core.js";
+
+/**
+ * The keyword arguments of the Python `normalize()` that do not take
+ * callables, in camelCase (snake_case is taken as well); left out, they keep
+ * the same defaults.
+ *
+ * @typedef {Object} NormalizeOptions
+ * @property {string} [dialect] "generic", "postgres", "mysql", ... or "auto"
+ * @property {string} [placeholder] "?" by default, "{n}" numbering it
+ * @property {number} [placeholderStart]
+ * @property {string} [style] "question", "dollar", "colon" or "pyformat", instead of placeholder
+ * @property {string} [compat]
+ * @property {boolean} [templates]
+ * @property {boolean} [strict]
+ * @property {string[]} [passes]
+ * @property {string[]} [disablePasses]
+ * @property {string[]} [enablePasses]
+ * @property {string} [functionCase]
+ * @property {string} [identifierCase]
+ * @property {boolean} [clauseHashes]
+ * @property {number} [maxSubqueryDepth]
+ * @property {string[]} [routingColumns]
+ * @property {string} [placeholderCollision]
+ * @property {string[]} [ignoreClauses]
+ * @property {Object<string, string[]>} [tableColumns]
+ * @property {Object<string, string>} [functionSynonyms]
+ * @property {boolean} [collectParams]
+ * @property {number} [maxParams]
+ * @property {boolean} [truncatedInput]
+ * @property {boolean} [lenient]
+ * @property {boolean} [collapseInLists]
+ * @property {boolean} [collapseValues]
+ * @property {boolean} [keepLimit]
+ * @property {boolean} [keepOffset]
+ * @property {boolean} [keepNull]
+ * @property {boolean|string[]} [stripQualifiers]
+ * @property {boolean} [ignoreAliases]
+ * @property {boolean} [anonymize]
+ * @property {boolean} [comments]
+ * @property {boolean} [keepHints]
+ * @property {boolean} [unwrapExplain]
+ * @property {boolean} [prepared]
+ * @property {string} [hashAlgorithm]
+ * @property {string} [hashEncoding]
+ */
+
+/**
+ * @typedef {Object} NormalizeResult
+ * @property {string} normalized
+ * @property {string} hash
+ * @property {string[]} params
+ * @property {string[]} warnings
+ */
+
+/** Raised for a statement sqlfp cannot normalize, `kind` telling why */
+export class SqlfpError extends Error {
+  constructor(message, kind) {
+    super(message);
+    this.name = "SqlfpError";
+    /** "parse", "options", "unnormalizable" or "placeholder_collision" */
+    this.kind = kind;
+  }
+}
+
+/** `options` with its keys in snake_case, as the module reads them */
+function snakeCase(options) {
+  return Object.fromEntries(
+    Object.entries(options)
+      .filter(([, value]) => value !== undefined)
+      .map(([key, value]) => [key.replace(/[A-Z]/g, (c) => "_" + c.toLowerCase()), value]),
+  );
+}
+
+/**
+ * Instantiate the module, given as bytes, a `Response`, a URL or a compiled
+ * `WebAssembly.Module`; by default, `pkg/sqlfp_core_bg.wasm` next to the glue.
+ *
+ * @returns {Promise<{ normalize(sql: string, options?: NormalizeOptions): NormalizeResult }>}
+ */
+export async function load(source) {
+  await init(source === undefined ? undefined : { module_or_path: source });
+  return {
+    /**
+     * Normalize and fingerprint the first statement of `sql`.
+     *
+     * @param {string} sql
+     * @param {NormalizeOptions} [options]
+     * @returns {NormalizeResult}
+     */
+    normalize(sql, options = {}) {
+      try {
+        return normalizeWasm(sql, snakeCase(options));
+      } catch (e) {
+        if (e !== null && typeof e === "object" && e.kind !== undefined) {
+          throw new SqlfpError(e.error, e.kind);
+        }
+        throw e;
+      }
+    },
+  };
+}
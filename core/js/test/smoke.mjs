@@ -0,0 +1,36 @@
+// Smoke test of the WebAssembly module through js/sqlfp.js, once built into
+// js/pkg (see the `wasm` feature of core/Cargo.toml): node core/js/test/smoke.mjs
+
+import assert from "node:assert/strict";
+import { readFile } from "node:fs/promises";
+
+import { load, SqlfpError } from "../sqlfp.js";
+
+const sqlfp = await load(await readFile(new URL("../pkg/sqlfp_core_bg.wasm", import.meta.url)));
+
+const result = sqlfp.normalize("SELECT * FROM users WHERE id = 42 AND name = 'x'");
+assert.equal(result.normalized, "SELECT * FROM users WHERE id = ? AND name = ?");
+assert.deepEqual(result.params, ["42", "'x'"]);
+assert.match(result.hash, /^[0-9a-f]{64}$/);
+assert.deepEqual(result.warnings, []);
+
+// camelCase options, as snake_case ones
+const options = { dialect: "postgres", placeholder: "${n}", placeholderStart: 0, collectParams: false };
+const numbered = sqlfp.normalize("SELECT * FROM t WHERE a = 1 AND b = NOW()", options);
+assert.equal(numbered.normalized, "SELECT * FROM t WHERE a = $0 AND b = NOW()");
+assert.deepEqual(numbered.params, []);
+const synonyms = { functionSynonyms: { nz: "coalesce" }, enablePasses: ["function-synonym-canonicalize"] };
+assert.equal(sqlfp.normalize("SELECT nz(a, 0) FROM t", synonyms).normalized, "SELECT COALESCE(a, ?) FROM t");
+assert.equal(sqlfp.normalize("SELECT 1", { placeholder_start: 3, placeholder: "${n}" }).normalized, "SELECT $3");
+
+const failure = (sql, options, kind) =>
+  assert.throws(
+    () => sqlfp.normalize(sql, options),
+    (e) => e instanceof SqlfpError && e.kind === kind && e.message.length > 0,
+  );
+failure("SELEC 1", {}, "parse");
+failure("SELECT 1", { dialect: "nosuch" }, "options");
+failure("SELECT 1", { noSuchOption: true }, "options");
+failure("SELECT 1", { placeholderStart: "one" }, "options");
+
+console.log("ok");
@@ -0,0 +1,113 @@
+//! Per-fingerprint query stats for `FingerprintAggregator`.
+//!
+//! Each fingerprint counts its observations and, over those given a
+//! duration, their total, least and greatest; it keeps when it was first and
+//! last seen, and one statement as observed for a sample: the slowest, or the
+//! first when none was timed. Unlike `FingerprintRegistry`'s counts, the stats
+//! are in-process only, and reset rather than checkpointed.
+
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stats {
+    pub normalized: String,
+    pub sample: String,
+    pub count: u64,
+    /// Observations given a duration, those that the durations are over
+    pub timed: u64,
+    /// Durations, in seconds
+    pub total_duration: f64,
+    pub min_duration: f64,
+    pub max_duration: f64,
+    /// Unix seconds
+    pub first_seen: f64,
+    pub last_seen: f64,
+}
+
+/// What `top()` ranks fingerprints by, most first
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rank {
+    Count,
+    TotalDuration,
+    MaxDuration,
+}
+
+impl Rank {
+    pub fn parse(name: &str) -> Result<Rank, String> {
+        match name {
+            "count" => Ok(Rank::Count),
+            "total_duration" => Ok(Rank::TotalDuration),
+            "max_duration" => Ok(Rank::MaxDuration),
+            _ => Err(format!("Unsupported rank: {}", name)),
+        }
+    }
+
+    fn key(self, stats: &Stats) -> f64 {
+        match self {
+            Rank::Count => stats.count as f64,
+            Rank::TotalDuration => stats.total_duration,
+            Rank::MaxDuration => stats.max_duration,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Aggregator {
+    /// Stats by hash
+    pub entries: HashMap<String, Stats>,
+    /// Observed statements that could not be parsed
+    pub unparsed: u64,
+}
+
+impl Aggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the statement `sql`, fingerprinted `hash` and `normalized`,
+    /// taking `duration` seconds if given, at `now`
+    pub fn observe(&mut self, hash: &str, normalized: &str, sql: &str, duration: Option<f64>, now: f64) {
+        let Some(stats) = self.entries.get_mut(hash) else {
+            let duration_or = |none: f64| duration.unwrap_or(none);
+            self.entries.insert(
+                hash.to_string(),
+                Stats {
+                    normalized: normalized.to_string(),
+                    sample: sql.to_string(),
+                    count: 1,
+                    timed: duration.is_some() as u64,
+                    total_duration: duration_or(0.0),
+                    min_duration: duration_or(f64::INFINITY),
+                    max_duration: duration_or(0.0),
+                    first_seen: now,
+                    last_seen: now,
+                },
+            );
+            return;
+        };
+        stats.count += 1;
+        stats.first_seen = stats.first_seen.min(now);
+        stats.last_seen = stats.last_seen.max(now);
+        if let Some(duration) = duration {
+            // The first timed observation replaces an untimed sample
+            if stats.timed == 0 || duration > stats.max_duration {
+                stats.sample = sql.to_string();
+            }
+            stats.timed += 1;
+            stats.total_duration += duration;
+            stats.min_duration = stats.min_duration.min(duration);
+            stats.max_duration = stats.max_duration.max(duration);
+        }
+    }
+
+    /// The `n` fingerprints ranking first by `rank` (all of them for
+    /// `None`), ties broken by count then hash
+    pub fn top(&self, n: Option<usize>, rank: Rank) -> Vec<(String, Stats)> {
+        let mut rows: Vec<_> = self.entries.iter().collect();
+        rows.sort_by(|(a_hash, a), (b_hash, b)| {
+            rank.key(b).total_cmp(&rank.key(a)).then(b.count.cmp(&a.count)).then(a_hash.cmp(b_hash))
+        });
+        rows.truncate(n.unwrap_or(usize::MAX));
+        rows.into_iter().map(|(hash, stats)| (hash.clone(), stats.clone())).collect()
+    }
+}
@@ -0,0 +1,219 @@
+//! Aliases as PostgreSQL's query jumbling sees them, for
+//! `compat="pg_stat_statements"`.
+//!
+//! The server identifies a query by its parse tree, in which column
+//! references point at relations by position and the select list carries no
+//! names, so `SELECT u.id AS user_id FROM users u` and `SELECT users.id FROM
+//! users` are one query to it. The alias of a table is replaced by the name of
+//! the table, together with the column qualifiers and qualified wildcards that
+//! refer to it, and the aliases of the outermost select list are dropped.
+//! Only what can be done without changing which relation or column a name
+//! refers to is: a table alias is kept where the table's name is bound
+//! elsewhere in the statement or the alias names its columns, and a select
+//! list alias is kept where the statement mentions it, as `ORDER BY x` does.
+//! Aliases of subqueries and functions, which name what has no other name,
+//! are kept too.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    Expr, FunctionArg, FunctionArgExpr, FunctionArguments, Ident, ObjectName, ObjectNamePart, Query, SelectItem,
+    SelectItemQualifiedWildcardKind, SetExpr, Statement, TableFactor, Value, Visit, VisitMut, Visitor, VisitorMut,
+};
+
+use crate::cte::key;
+
+/// The unqualified name of a table
+fn table_name(name: &ObjectName) -> Option<&Ident> {
+    match name.0.last() {
+        Some(ObjectNamePart::Identifier(ident)) => Some(ident),
+        _ => None,
+    }
+}
+
+/// The names relations are bound to and the identifiers used across a statement
+#[derive(Default)]
+struct Names {
+    /// How many relations each name is bound to: their alias, else the name of the table
+    bindings: HashMap<String, usize>,
+    /// How many aliased tables there are of each name
+    aliased: HashMap<String, usize>,
+    /// Unqualified identifiers, which may refer to select list aliases
+    identifiers: HashSet<String>,
+}
+
+impl Visitor for Names {
+    type Break = ();
+
+    fn pre_visit_table_factor(&mut self, factor: &TableFactor) -> ControlFlow<()> {
+        let bound = match factor {
+            TableFactor::Table { name, alias: Some(alias), .. } => {
+                if let Some(table) = table_name(name) {
+                    *self.aliased.entry(key(table)).or_default() += 1;
+                }
+                Some(&alias.name)
+            }
+            TableFactor::Table { name, alias: None, .. } => table_name(name),
+            TableFactor::Derived { alias: Some(alias), .. }
+            | TableFactor::TableFunction { alias: Some(alias), .. }
+            | TableFactor::Function { alias: Some(alias), .. }
+            | TableFactor::UNNEST { alias: Some(alias), .. } => Some(&alias.name),
+            _ => None,
+        };
+        if let Some(ident) = bound {
+            *self.bindings.entry(key(ident)).or_default() += 1;
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<()> {
+        if let Expr::Identifier(ident) = expr {
+            self.identifiers.insert(key(ident));
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Table aliases to replace, by key, with the name of their table
+struct Rename(HashMap<String, Ident>);
+
+impl Rename {
+    fn qualifier(&self, ident: &mut Ident) {
+        if let Some(name) = self.0.get(&key(ident)) {
+            *ident = name.clone();
+        }
+    }
+
+    fn wildcard(&self, name: &mut ObjectName) {
+        if let [ObjectNamePart::Identifier(ident)] = name.0.as_mut_slice() {
+            self.qualifier(ident);
+        }
+    }
+
+    fn projection(&self, body: &mut SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                for item in &mut select.projection {
+                    if let SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::ObjectName(name), _) = item {
+                        self.wildcard(name);
+                    }
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.projection(left);
+                self.projection(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl VisitorMut for Rename {
+    type Break = ();
+
+    fn post_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        self.projection(&mut query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, factor: &mut TableFactor) -> ControlFlow<()> {
+        if let TableFactor::Table { alias, .. } = factor {
+            if alias.as_ref().is_some_and(|alias| self.0.contains_key(&key(&alias.name))) {
+                *alias = None;
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<()> {
+        match expr {
+            Expr::CompoundIdentifier(idents) => {
+                if let [qualifier, _] = idents.as_mut_slice() {
+                    self.qualifier(qualifier);
+                }
+            }
+            Expr::QualifiedWildcard(name, _) => self.wildcard(name),
+            Expr::Function(function) => {
+                if let FunctionArguments::List(ref mut list) = function.args {
+                    for arg in &mut list.args {
+                        let (FunctionArg::Unnamed(arg) | FunctionArg::Named { arg, .. }) = arg else {
+                            continue;
+                        };
+                        if let FunctionArgExpr::QualifiedWildcard(name) = arg {
+                            self.wildcard(name);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Table aliases that can be replaced by the name of their table: the only
+/// binding of their name, of a table no other relation is bound to by name
+/// and that is not aliased elsewhere
+struct Renamable<'a> {
+    names: &'a Names,
+    out: HashMap<String, Ident>,
+}
+
+impl Visitor for Renamable<'_> {
+    type Break = ();
+
+    fn pre_visit_table_factor(&mut self, factor: &TableFactor) -> ControlFlow<()> {
+        if let TableFactor::Table { name, alias: Some(alias), .. } = factor {
+            let Some(table) = table_name(name) else {
+                return ControlFlow::Continue(());
+            };
+            let names = self.names;
+            if alias.columns.is_empty()
+                && names.bindings.get(&key(&alias.name)) == Some(&1)
+                && !names.bindings.contains_key(&key(table))
+                && names.aliased.get(&key(table)) == Some(&1)
+            {
+                self.out.insert(key(&alias.name), table.clone());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Drop the aliases of the select lists of `body` that `identifiers` does not mention
+fn drop_select_aliases(body: &mut SetExpr, identifiers: &HashSet<String>) {
+    match body {
+        SetExpr::Select(select) => {
+            for item in &mut select.projection {
+                if let SelectItem::ExprWithAlias { expr, alias } = item {
+                    if !identifiers.contains(&key(alias)) {
+                        let expr = std::mem::replace(expr, Expr::Value(Value::Null.into()));
+                        *item = SelectItem::UnnamedExpr(expr);
+                    }
+                }
+            }
+        }
+        SetExpr::SetOperation { left, right, .. } => {
+            drop_select_aliases(left, identifiers);
+            drop_select_aliases(right, identifiers);
+        }
+        SetExpr::Query(query) => drop_select_aliases(&mut query.body, identifiers),
+        _ => {}
+    }
+}
+
+/// Drop the aliases of `stmt` that PostgreSQL's query jumbling ignores, in place
+pub fn drop_aliases(stmt: &mut Statement) {
+    let mut names = Names::default();
+    let _ = Visit::visit(&*stmt, &mut names);
+    let mut renamable = Renamable { names: &names, out: HashMap::new() };
+    let _ = Visit::visit(&*stmt, &mut renamable);
+    let renames = renamable.out;
+    if !renames.is_empty() {
+        let _ = stmt.visit(&mut Rename(renames));
+    }
+    if let Statement::Query(query) = stmt {
+        drop_select_aliases(&mut query.body, &names.identifiers);
+    }
+}
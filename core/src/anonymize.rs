@@ -0,0 +1,225 @@
+//! Anonymized names, for `anonymize=True` and `metadata["anonymized"]`.
+//!
+//! Query shapes sent to a third party should not tell it the schema. Once the
+//! statement is normalized, tables (with their aliases and CTEs) are renamed
+//! to `t1`, `t2`, …, columns (with select list aliases) to `c1`, `c2`, … and
+//! the schemas and databases qualifying tables to `s1`, `s2`, …, numbered as
+//! they are met. A name is renamed alike wherever it stands in the same role,
+//! so the statement reads the same with the new names, and
+//! `metadata["anonymized"]` maps them back to the names as written. `hash` is
+//! that of the anonymized statement, shared by statements of the same shape
+//! over different tables. Literals are left to `literal-parameterize`;
+//! function names are kept.
+
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    AssignmentTarget, Expr, FunctionArg, FunctionArgExpr, FunctionArguments, Ident, JoinConstraint, ObjectName,
+    ObjectNamePart, Query, SelectItem, SelectItemQualifiedWildcardKind, SetExpr, Statement, TableAlias, TableFactor,
+    VisitMut, VisitorMut,
+};
+
+use crate::booleans::identifier_boolean;
+use crate::cte::key;
+use crate::metadata::MetaValue;
+use crate::using::constraint_mut;
+
+/// The names of one role, in order of first appearance
+struct Role {
+    prefix: &'static str,
+    /// `(key, name as written)`
+    names: Vec<(String, String)>,
+}
+
+impl Role {
+    fn new(prefix: &'static str) -> Role {
+        Role { prefix, names: Vec::new() }
+    }
+
+    fn rename(&mut self, ident: &mut Ident) {
+        let key = key(ident);
+        let n = match self.names.iter().position(|(seen, _)| *seen == key) {
+            Some(i) => i + 1,
+            None => {
+                self.names.push((key, ident.value.clone()));
+                self.names.len()
+            }
+        };
+        *ident = Ident::new(format!("{}{}", self.prefix, n));
+    }
+
+    fn metadata(self) -> MetaValue {
+        let prefix = self.prefix;
+        MetaValue::Map(
+            self.names
+                .into_iter()
+                .enumerate()
+                .map(|(i, (_, name))| (format!("{}{}", prefix, i + 1), MetaValue::Str(name)))
+                .collect(),
+        )
+    }
+}
+
+struct Anonymize {
+    tables: Role,
+    columns: Role,
+    schemas: Role,
+    /// TRUE and FALSE are identifiers of the dialect
+    identifier_booleans: bool,
+}
+
+impl Anonymize {
+    /// A table name: its qualifiers are schemas
+    fn table(&mut self, name: &mut ObjectName) {
+        let last = name.0.len().saturating_sub(1);
+        for (i, part) in name.0.iter_mut().enumerate() {
+            if let ObjectNamePart::Identifier(ident) = part {
+                if i == last {
+                    self.tables.rename(ident);
+                } else {
+                    self.schemas.rename(ident);
+                }
+            }
+        }
+    }
+
+    /// A column name, qualified by a table and its schemas
+    fn column<'a>(&mut self, idents: impl ExactSizeIterator<Item = &'a mut Ident>) {
+        let count = idents.len();
+        for (i, ident) in idents.enumerate() {
+            match count - i {
+                1 => self.columns.rename(ident),
+                2 => self.tables.rename(ident),
+                _ => self.schemas.rename(ident),
+            }
+        }
+    }
+
+    fn column_name(&mut self, name: &mut ObjectName) {
+        let idents: Vec<&mut Ident> = name
+            .0
+            .iter_mut()
+            .filter_map(|part| match part {
+                ObjectNamePart::Identifier(ident) => Some(ident),
+                _ => None,
+            })
+            .collect();
+        self.column(idents.into_iter());
+    }
+
+    fn alias(&mut self, alias: &mut TableAlias) {
+        self.tables.rename(&mut alias.name);
+        for column in &mut alias.columns {
+            self.columns.rename(&mut column.name);
+        }
+    }
+
+    fn projection(&mut self, body: &mut SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                for item in &mut select.projection {
+                    match item {
+                        SelectItem::ExprWithAlias { alias, .. } => self.columns.rename(alias),
+                        SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::ObjectName(name), _) => {
+                            self.table(name)
+                        }
+                        _ => {}
+                    }
+                }
+                for join in select.from.iter_mut().flat_map(|table| &mut table.joins) {
+                    if let Some(JoinConstraint::Using(columns)) = constraint_mut(&mut join.join_operator) {
+                        columns.iter_mut().for_each(|column| self.column_name(column));
+                    }
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.projection(left);
+                self.projection(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl VisitorMut for Anonymize {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        if let Some(with) = &mut query.with {
+            for cte in &mut with.cte_tables {
+                self.alias(&mut cte.alias);
+            }
+        }
+        self.projection(&mut query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &mut ObjectName) -> ControlFlow<()> {
+        self.table(relation);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, factor: &mut TableFactor) -> ControlFlow<()> {
+        if let TableFactor::Table { alias: Some(alias), .. }
+        | TableFactor::Derived { alias: Some(alias), .. }
+        | TableFactor::TableFunction { alias: Some(alias), .. }
+        | TableFactor::Function { alias: Some(alias), .. }
+        | TableFactor::UNNEST { alias: Some(alias), .. } = factor
+        {
+            self.alias(alias);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_statement(&mut self, stmt: &mut Statement) -> ControlFlow<()> {
+        match stmt {
+            Statement::Insert(insert) => insert.columns.iter_mut().for_each(|column| self.columns.rename(column)),
+            Statement::Update(update) => {
+                for assignment in &mut update.assignments {
+                    match &mut assignment.target {
+                        AssignmentTarget::ColumnName(name) => self.column_name(name),
+                        AssignmentTarget::Tuple(names) => names.iter_mut().for_each(|name| self.column_name(name)),
+                    }
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<()> {
+        match expr {
+            Expr::Identifier(ident) if !(self.identifier_booleans && identifier_boolean(ident).is_some()) => {
+                self.columns.rename(ident)
+            }
+            Expr::CompoundIdentifier(idents) => self.column(idents.iter_mut()),
+            Expr::QualifiedWildcard(name, _) => self.table(name),
+            Expr::Function(function) => {
+                if let FunctionArguments::List(ref mut list) = function.args {
+                    for arg in &mut list.args {
+                        if let FunctionArg::Unnamed(FunctionArgExpr::QualifiedWildcard(name))
+                        | FunctionArg::Named { arg: FunctionArgExpr::QualifiedWildcard(name), .. } = arg
+                        {
+                            self.table(name);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Anonymize the names of `stmt` in place; `metadata["anonymized"]`
+pub fn anonymize(stmt: &mut Statement, identifier_booleans: bool) -> (String, MetaValue) {
+    let mut anonymize =
+        Anonymize { tables: Role::new("t"), columns: Role::new("c"), schemas: Role::new("s"), identifier_booleans };
+    let _ = stmt.visit(&mut anonymize);
+    let mapping = vec![
+        ("tables".to_string(), anonymize.tables.metadata()),
+        ("columns".to_string(), anonymize.columns.metadata()),
+        ("schemas".to_string(), anonymize.schemas.metadata()),
+    ];
+    ("anonymized".to_string(), MetaValue::Map(mapping))
+}
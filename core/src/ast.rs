@@ -0,0 +1,135 @@
+//! Read-only tree view of a parsed statement, as exposed by `parse_ast()`.
+//!
+//! Only the node types sqlparser's visitor stops at are materialized
+//! (statements, queries, table factors, relations and expressions), which
+//! keeps the tree small while still covering everything the normalizer touches.
+
+use core::fmt::{self, Debug, Write};
+use core::ops::ControlFlow;
+use sqlparser::ast::{Expr, ObjectName, Query, Spanned, Statement, TableFactor, Visit, Visitor};
+use sqlparser::tokenizer::Span;
+
+/// (start line, start column, end line, end column), 1-based
+pub type LineSpan = (u64, u64, u64, u64);
+
+pub struct Node {
+    pub category: &'static str,
+    pub kind: String,
+    pub span: Option<LineSpan>,
+    pub sql: String,
+    pub children: Vec<Node>,
+}
+
+/// Name of the enum variant (or struct) `value` is, read off its Debug output.
+/// The writer bails out at the first non-identifier character, so this only
+/// formats the leading name rather than the whole subtree.
+pub fn variant_name<T: Debug>(value: &T) -> String {
+    struct Prefix(String);
+
+    impl Write for Prefix {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            for c in s.chars() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    return Err(fmt::Error);
+                }
+                self.0.push(c);
+            }
+            Ok(())
+        }
+    }
+
+    let mut prefix = Prefix(String::new());
+    let _ = write!(prefix, "{:?}", value);
+    prefix.0
+}
+
+fn line_span(span: Span) -> Option<LineSpan> {
+    if span.start.line == 0 {
+        return None;
+    }
+    Some((span.start.line, span.start.column, span.end.line, span.end.column))
+}
+
+fn node<T: Debug + Spanned + fmt::Display>(category: &'static str, value: &T) -> Node {
+    Node {
+        category,
+        kind: variant_name(value),
+        span: line_span(value.span()),
+        sql: value.to_string(),
+        children: Vec::new(),
+    }
+}
+
+#[derive(Default)]
+struct TreeBuilder {
+    stack: Vec<Node>,
+    root: Option<Node>,
+}
+
+impl TreeBuilder {
+    fn enter(&mut self, node: Node) -> ControlFlow<()> {
+        self.stack.push(node);
+        ControlFlow::Continue(())
+    }
+
+    fn leave(&mut self) -> ControlFlow<()> {
+        if let Some(node) = self.stack.pop() {
+            match self.stack.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => self.root = Some(node),
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+impl Visitor for TreeBuilder {
+    type Break = ();
+
+    fn pre_visit_statement(&mut self, stmt: &Statement) -> ControlFlow<()> {
+        self.enter(node("statement", stmt))
+    }
+
+    fn post_visit_statement(&mut self, _stmt: &Statement) -> ControlFlow<()> {
+        self.leave()
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        self.enter(node("query", query))
+    }
+
+    fn post_visit_query(&mut self, _query: &Query) -> ControlFlow<()> {
+        self.leave()
+    }
+
+    fn pre_visit_table_factor(&mut self, tf: &TableFactor) -> ControlFlow<()> {
+        self.enter(node("table_factor", tf))
+    }
+
+    fn post_visit_table_factor(&mut self, _tf: &TableFactor) -> ControlFlow<()> {
+        self.leave()
+    }
+
+    fn pre_visit_relation(&mut self, relation: &ObjectName) -> ControlFlow<()> {
+        self.enter(node("relation", relation))
+    }
+
+    fn post_visit_relation(&mut self, _relation: &ObjectName) -> ControlFlow<()> {
+        self.leave()
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<()> {
+        self.enter(node("expr", expr))
+    }
+
+    fn post_visit_expr(&mut self, _expr: &Expr) -> ControlFlow<()> {
+        self.leave()
+    }
+}
+
+/// Build the node tree of `stmt`, rooted at the statement itself
+pub fn build_tree(stmt: &Statement) -> Node {
+    let mut builder = TreeBuilder::default();
+    let _ = stmt.visit(&mut builder);
+    builder.root.unwrap_or_else(|| node("statement", stmt))
+}
@@ -0,0 +1,90 @@
+//! Backquoted names of BigQuery, for `Rules::plain_backticks`.
+//!
+//! BigQuery resolves a name alike whether it is backquoted or not; the quotes
+//! only let it hold other characters (`my-project`), dots (`` `ds.t` ``,
+//! which sqlparser splits into parts) or keywords. So that `` `ds`.`t` `` and
+//! `ds.t` share a fingerprint, the quotes of names that need none are
+//! dropped, in table references and their aliases, column references and
+//! qualified wildcards.
+
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    Expr, Ident, ObjectName, ObjectNamePart, Query, SelectItem, SelectItemQualifiedWildcardKind, SetExpr, Statement,
+    TableFactor, VisitMut, VisitorMut,
+};
+use sqlparser::keywords::ALL_KEYWORDS;
+
+/// Drop the backquotes of `ident` if it reads the same without them
+fn unquote_ident(ident: &mut Ident) {
+    let plain = ident.value.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && ident.value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && ALL_KEYWORDS.binary_search(&ident.value.to_uppercase().as_str()).is_err();
+    if ident.quote_style == Some('`') && plain {
+        ident.quote_style = None;
+    }
+}
+
+fn unquote_name(name: &mut ObjectName) {
+    for part in &mut name.0 {
+        if let ObjectNamePart::Identifier(ident) = part {
+            unquote_ident(ident);
+        }
+    }
+}
+
+/// The qualified wildcards of the select lists of `body`
+fn unquote_projection(body: &mut SetExpr) {
+    match body {
+        SetExpr::Select(select) => {
+            for item in &mut select.projection {
+                if let SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::ObjectName(name), _) = item {
+                    unquote_name(name);
+                }
+            }
+        }
+        SetExpr::SetOperation { left, right, .. } => {
+            unquote_projection(left);
+            unquote_projection(right);
+        }
+        _ => {}
+    }
+}
+
+struct Unquote;
+
+impl VisitorMut for Unquote {
+    type Break = ();
+
+    fn pre_visit_relation(&mut self, relation: &mut ObjectName) -> ControlFlow<()> {
+        unquote_name(relation);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        unquote_projection(&mut query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, factor: &mut TableFactor) -> ControlFlow<()> {
+        if let TableFactor::Table { alias: Some(alias), .. } | TableFactor::Derived { alias: Some(alias), .. } = factor {
+            unquote_ident(&mut alias.name);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<()> {
+        match expr {
+            Expr::Identifier(ident) => unquote_ident(ident),
+            Expr::CompoundIdentifier(idents) => idents.iter_mut().for_each(unquote_ident),
+            Expr::QualifiedWildcard(name, _) => unquote_name(name),
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Drop the backquotes of the names of `stmt` that need none, in place
+pub fn unquote(stmt: &mut Statement) {
+    let _ = stmt.visit(&mut Unquote);
+}
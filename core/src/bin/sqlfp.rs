@@ -0,0 +1,188 @@
+//! The `sqlfp` command, for the `cli` feature: fingerprints SQL from the
+//! command line, for shell users and CI checks without a Python environment.
+//!
+//! Statements come from the arguments, else from the files given with
+//! `--file` (`-` being stdin), else from stdin; files and stdin are scripts,
+//! split on `;` as `normalize_script()` splits them, so that a statement may
+//! span lines, or with `--lines` one statement per line. Each statement
+//! gives a JSON line with its `hash`, `normalized`, `params` and
+//! `statement_type`, or its `sql` and the `error` it failed with; with
+//! `--group`, each fingerprint gives one line with its `count` instead, most
+//! frequent first. The exit status is 1 if a statement failed, 2 if the
+//! command could not run.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::process::ExitCode;
+
+use serde_json::{json, Value};
+use sqlfp_core::{normalize, script, sql_dialect, Dialect, Options};
+
+const USAGE: &str = "\
+Usage: sqlfp [OPTIONS] [SQL]...
+
+Fingerprint each SQL statement given, or read from --file or stdin, as a JSON line.
+
+Options:
+  -d, --dialect <NAME>       Grammar to parse with [default: generic]
+  -p, --placeholder <TEXT>   Text the literals are replaced with, `{n}` numbering them [default: ?]
+  -f, --file <PATH>          Read statements from PATH, `-` for stdin (repeatable); they end at `;`
+  -l, --lines                Read one statement per line from the files and stdin, with or without `;`
+  -g, --group                One line per fingerprint, with the number of statements having it
+  -h, --help                 Print this help
+  -V, --version              Print the version";
+
+#[derive(Default)]
+struct Args {
+    dialect: Option<String>,
+    placeholder: Option<String>,
+    files: Vec<String>,
+    lines: bool,
+    group: bool,
+    statements: Vec<String>,
+}
+
+/// What the command line asks for
+enum Command {
+    Run(Args),
+    Help,
+    Version,
+}
+
+fn parse_args(argv: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = Args::default();
+    let mut argv = argv.into_iter();
+    while let Some(arg) = argv.next() {
+        // `--name=value` is `--name value`
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| inline.clone().or_else(|| argv.next()).ok_or_else(|| format!("{} needs a value", name));
+        match name.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-g" | "--group" => args.group = true,
+            "-l" | "--lines" => args.lines = true,
+            "-d" | "--dialect" => args.dialect = Some(value(&name)?),
+            "-p" | "--placeholder" => args.placeholder = Some(value(&name)?),
+            "-f" | "--file" => args.files.push(value(&name)?),
+            "--" => {
+                args.statements.extend(argv);
+                break;
+            }
+            _ if name.starts_with('-') => return Err(format!("unknown option {}", name)),
+            _ => args.statements.push(arg),
+        }
+    }
+    Ok(Command::Run(args))
+}
+
+fn read_input(path: &str) -> Result<String, String> {
+    if path == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text).map_err(|e| format!("stdin: {}", e))?;
+        Ok(text)
+    } else {
+        fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+/// The statements to fingerprint
+fn statements(args: &Args, dialect: Dialect) -> Result<Vec<String>, String> {
+    if !args.statements.is_empty() {
+        return Ok(args.statements.clone());
+    }
+    let files = if args.files.is_empty() { vec!["-".to_string()] } else { args.files.clone() };
+    let mut statements = Vec::new();
+    for path in &files {
+        let text = read_input(path)?;
+        if args.lines {
+            statements.extend(text.lines().filter(|line| !line.trim().is_empty()).map(str::to_string));
+            continue;
+        }
+        let pieces = script::split(&text, &*sql_dialect(dialect.name(), &text)?).map_err(|e| format!("{}: {}", path, e))?;
+        statements.extend(pieces.into_iter().map(|piece| piece.sql));
+    }
+    Ok(statements)
+}
+
+/// A fingerprint of `--group` and the statements having it
+struct Group {
+    hash: String,
+    normalized: String,
+    statement_type: String,
+    count: usize,
+}
+
+fn run(args: Args, out: &mut impl Write) -> Result<bool, String> {
+    let dialect = match args.dialect {
+        Some(ref name) => Dialect::new(name).map_err(|e| e.to_string())?,
+        None => Dialect::GENERIC,
+    };
+    let mut options = Options::default();
+    if let Some(ref placeholder) = args.placeholder {
+        options.placeholder = placeholder.clone();
+    }
+    let mut failed = false;
+    let mut groups: Vec<Group> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let write_line = |out: &mut dyn Write, line: Value| writeln!(out, "{}", line).map_err(|e| e.to_string());
+    for sql in statements(&args, dialect)? {
+        match normalize(&sql, dialect, &options) {
+            Ok(fp) if args.group => match index.get(&fp.hash) {
+                Some(&i) => groups[i].count += 1,
+                None => {
+                    index.insert(fp.hash.clone(), groups.len());
+                    groups.push(Group { hash: fp.hash, normalized: fp.normalized, statement_type: fp.statement_type, count: 1 });
+                }
+            },
+            Ok(fp) => write_line(
+                out,
+                json!({"hash": fp.hash, "normalized": fp.normalized, "params": fp.params, "statement_type": fp.statement_type}),
+            )?,
+            Err(e) => {
+                failed = true;
+                write_line(out, json!({"sql": sql, "error": e.to_string()}))?;
+            }
+        }
+    }
+    // Most frequent first, ties in the order first seen
+    groups.sort_by_key(|group| std::cmp::Reverse(group.count));
+    for group in groups {
+        write_line(
+            out,
+            json!({"hash": group.hash, "normalized": group.normalized, "statement_type": group.statement_type, "count": group.count}),
+        )?;
+    }
+    out.flush().map_err(|e| e.to_string())?;
+    Ok(!failed)
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Command::Run(args)) => args,
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Version) => {
+            println!("sqlfp {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("sqlfp: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let stdout = io::stdout();
+    match run(args, &mut BufWriter::new(stdout.lock())) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(message) => {
+            eprintln!("sqlfp: {}", message);
+            ExitCode::from(2)
+        }
+    }
+}
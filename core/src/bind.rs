@@ -0,0 +1,110 @@
+//! Parameters bound back into a normalized statement, for `bind()` and
+//! `NormalizeResult.bind()`.
+//!
+//! Captured production queries are replayed against another database from
+//! their normalized text and the values they ran with. Each placeholder of a
+//! literal is replaced with a parameter written as a literal of the dialect:
+//! the result's own `params` as they were written, or values the caller
+//! quoted and escaped for it. Placeholders are found on the token stream, so that
+//! one inside a string, a quoted name or a comment is left alone; a numbered
+//! placeholder takes the parameter of its number, and those numbered below
+//! the first literal's, the statement's own binds, are left as they are.
+
+use sqlparser::dialect::Dialect;
+use sqlparser::tokenizer::{Token, Tokenizer};
+
+use crate::paramstyle::token_offsets;
+
+/// Whether `token` is written in quotes, where no placeholder is read
+pub fn quoted(token: &Token) -> bool {
+    match token {
+        Token::Word(word) => word.quote_style.is_some(),
+        Token::Whitespace(_)
+        | Token::SingleQuotedString(_)
+        | Token::DoubleQuotedString(_)
+        | Token::TripleSingleQuotedString(_)
+        | Token::TripleDoubleQuotedString(_)
+        | Token::DollarQuotedString(_)
+        | Token::SingleQuotedByteStringLiteral(_)
+        | Token::DoubleQuotedByteStringLiteral(_)
+        | Token::TripleSingleQuotedByteStringLiteral(_)
+        | Token::TripleDoubleQuotedByteStringLiteral(_)
+        | Token::SingleQuotedRawStringLiteral(_)
+        | Token::DoubleQuotedRawStringLiteral(_)
+        | Token::TripleSingleQuotedRawStringLiteral(_)
+        | Token::TripleDoubleQuotedRawStringLiteral(_)
+        | Token::NationalStringLiteral(_)
+        | Token::QuoteDelimitedStringLiteral(_)
+        | Token::NationalQuoteDelimitedStringLiteral(_)
+        | Token::EscapedStringLiteral(_)
+        | Token::UnicodeStringLiteral(_)
+        | Token::HexStringLiteral(_) => true,
+        _ => false,
+    }
+}
+
+/// The length of the occurrence of `placeholder` at the start of `text`, and
+/// its number if it is numbered
+pub fn placeholder_at(placeholder: &str, text: &str) -> Option<(usize, Option<usize>)> {
+    let Some((prefix, suffix)) = placeholder.split_once("{n}") else {
+        return text.starts_with(placeholder).then_some((placeholder.len(), None));
+    };
+    let rest = text.strip_prefix(prefix)?;
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    if digits == 0 || !rest[digits..].starts_with(suffix) {
+        return None;
+    }
+    Some((prefix.len() + digits + suffix.len(), Some(rest[..digits].parse().ok()?)))
+}
+
+/// `sql` with its placeholders of `placeholder` replaced by `params`, in order
+/// or, for numbered ones, by number from `start`
+pub fn bind(sql: &str, dialect: &dyn Dialect, placeholder: &str, start: usize, params: &[String]) -> Result<String, String> {
+    if placeholder.is_empty() {
+        return Err("Cannot bind parameters to an empty placeholder".to_string());
+    }
+    let tokens = Tokenizer::new(dialect, sql)
+        .with_unescape(false)
+        .tokenize_with_location()
+        .map_err(|e| format!("Tokenize error: {}", e))?;
+    let offsets = token_offsets(sql, &tokens, dialect);
+    let end_of = |i: usize| offsets.get(i).copied().unwrap_or(sql.len());
+
+    let mut out = String::with_capacity(sql.len() + params.iter().map(String::len).sum::<usize>());
+    let mut used = vec![false; params.len()];
+    let mut next = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        let at = end_of(i);
+        let found = (!quoted(&tokens[i].token)).then(|| placeholder_at(placeholder, &sql[at..])).flatten();
+        // The placeholder must span whole tokens
+        let last = found.and_then(|(len, _)| (i..tokens.len()).find(|&j| end_of(j + 1) == at + len));
+        let (Some((_, number)), Some(last)) = (found, last) else {
+            out.push_str(&sql[at..end_of(i + 1)]);
+            i += 1;
+            continue;
+        };
+        let index = match number {
+            Some(n) => n.checked_sub(start),
+            None => {
+                next += 1;
+                Some(next - 1)
+            }
+        };
+        match (index, number) {
+            (Some(index), _) if index < params.len() => {
+                out.push_str(&params[index]);
+                used[index] = true;
+            }
+            // A bind of the statement itself
+            (_, Some(_)) => out.push_str(&sql[at..end_of(last + 1)]),
+            _ => return Err(format!("More placeholders than the {} parameter(s) given", params.len())),
+        }
+        i = last + 1;
+    }
+    let unused = used.iter().filter(|used| !**used).count();
+    if unused > 0 {
+        return Err(format!("{} of the {} parameter(s) given have no placeholder", unused, params.len()));
+    }
+    Ok(out)
+}
@@ -0,0 +1,84 @@
+//! The bind placeholders a statement already has, for `metadata["binds"]`.
+//!
+//! Oracle binds (`:1`, `:B1`, `:name`) are parsed as placeholders and written
+//! back as they are, except quoted ones: sqlparser keeps the name of
+//! `:"Name"` but drops its quotes, so `:"x y"` would be written `:x y`, which
+//! neither parses again nor binds the same variable, as unquoted names are
+//! case-insensitive. Quoted binds are found again in the tokens of the
+//! statement and their placeholders given back their quotes.
+
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{visit_expressions_mut, Expr, Ident, Statement, Value, ValueWithSpan, Visit, Visitor};
+use sqlparser::dialect::Dialect;
+use sqlparser::tokenizer::{Location, Token, Tokenizer};
+
+#[derive(Default)]
+struct Binds(Vec<String>);
+
+impl Visitor for Binds {
+    type Break = ();
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<()> {
+        if let Expr::Value(ValueWithSpan { value: Value::Placeholder(text), .. }) = expr {
+            self.0.push(text.clone());
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// `ESCAPE` characters are values rather than expressions, and come after
+    /// the pattern
+    fn post_visit_expr(&mut self, expr: &Expr) -> ControlFlow<()> {
+        if let Expr::Like { escape_char: Some(Value::Placeholder(text)), .. }
+        | Expr::ILike { escape_char: Some(Value::Placeholder(text)), .. }
+        | Expr::SimilarTo { escape_char: Some(Value::Placeholder(text)), .. } = expr
+        {
+            self.0.push(text.clone());
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// The bind placeholders already in `stmt` (`$1`, `?`, `:name`), as written
+/// and in order
+pub fn bind_placeholders(stmt: &Statement) -> Vec<String> {
+    let mut binds = Binds::default();
+    let _ = stmt.visit(&mut binds);
+    binds.0
+}
+
+/// The quoted binds of `sql` (`:"Name"`) as written, by where they start
+fn quoted_binds(sql: &str, dialect: &dyn Dialect) -> HashMap<Location, String> {
+    let mut binds = HashMap::new();
+    let Ok(tokens) = Tokenizer::new(dialect, sql).tokenize_with_location() else {
+        return binds;
+    };
+    for pair in tokens.windows(2) {
+        if let (Token::Colon, Token::Word(word)) = (&pair[0].token, &pair[1].token) {
+            if let Some(quote) = word.quote_style.filter(|_| pair[0].span.end == pair[1].span.start) {
+                binds.insert(pair[0].span.start, format!(":{}", Ident::with_quote(quote, word.value.as_str())));
+            }
+        }
+    }
+    binds
+}
+
+/// Give the quoted binds of `stmt`, parsed from `sql`, back their quotes
+pub fn restore_quotes(stmt: &mut Statement, sql: &str, dialect: &dyn Dialect) {
+    if !sql.contains(":\"") && !sql.contains(":`") && !sql.contains(":[") {
+        return;
+    }
+    let binds = quoted_binds(sql, dialect);
+    if binds.is_empty() {
+        return;
+    }
+    let _ = visit_expressions_mut(stmt, |expr| {
+        if let Expr::Value(ValueWithSpan { value: Value::Placeholder(text), span }) = expr {
+            if let Some(quoted) = binds.get(&span.start) {
+                *text = quoted.clone();
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+}
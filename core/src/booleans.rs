@@ -0,0 +1,42 @@
+//! Boolean literals that are not parsed as such, and columns that are.
+//!
+//! Dialects without boolean literals (MSSQL, Oracle) parse `TRUE` and `FALSE`
+//! as identifiers. The `boolean-uppercase` pass reads the unquoted ones as the
+//! booleans they usually stand for, to be uppercased and parameterized like
+//! `Value::Boolean` elsewhere; where a column is named `true`, disabling the
+//! pass keeps it a column. Quoted identifiers are always columns.
+//!
+//! The other way round, dialects with boolean literals parse the `true` of
+//! `t.true` as one, in a field access on `t`; it names a column all the same.
+
+use sqlparser::ast::{AccessExpr, Expr, Ident, Value, ValueWithSpan};
+
+/// The boolean an identifier stands for in dialects without boolean
+/// literals, uppercased: `TRUE` or `FALSE` written bare, in any case
+pub fn identifier_boolean(ident: &Ident) -> Option<&'static str> {
+    if ident.quote_style.is_some() {
+        return None;
+    }
+    if ident.value.eq_ignore_ascii_case("TRUE") {
+        Some("TRUE")
+    } else if ident.value.eq_ignore_ascii_case("FALSE") {
+        Some("FALSE")
+    } else {
+        None
+    }
+}
+
+/// Turn the booleans of `t.true` and `t.false` back into the column names
+/// they are, so that they are not parameterized
+pub fn qualified_columns(expr: &mut Expr) {
+    let Expr::CompoundFieldAccess { access_chain, .. } = expr else {
+        return;
+    };
+    for access in access_chain {
+        if let AccessExpr::Dot(ref mut field) = access {
+            if let Expr::Value(ValueWithSpan { value: Value::Boolean(b), span }) = field {
+                *field = Expr::Identifier(Ident::with_span(*span, b.to_string()));
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Builtin function names, for `function_case="builtins"`.
+//!
+//! A function counts as builtin when its name is a keyword of sqlparser's
+//! keyword set (`COUNT`, `LOWER`, `COALESCE`, …) or listed below, either for
+//! every dialect or for the dialect at hand. The lists are not exhaustive:
+//! they cover functions common enough to show up in fingerprints, and a
+//! builtin missing from them merely keeps the case it is written in.
+
+use sqlparser::keywords::ALL_KEYWORDS;
+
+use crate::dialects;
+
+/// Builtins of most dialects that are not sqlparser keywords
+const COMMON: &[&str] = &[
+    "ARRAY_AGG", "CONCAT", "CONCAT_WS", "GREATEST", "LENGTH", "LPAD", "LTRIM", "MD5", "NOW", "REVERSE",
+    "ROUND", "RPAD", "RTRIM", "SIGN", "SQRT", "STDDEV", "STRING_AGG", "SUBSTR", "TO_CHAR",
+];
+
+const POSTGRES: &[&str] = &[
+    "AGE", "ARRAY_LENGTH", "ARRAY_TO_STRING", "BOOL_AND", "BOOL_OR", "DATE_PART", "DATE_TRUNC",
+    "GENERATE_SERIES", "INITCAP", "JSON_AGG", "JSONB_AGG", "JSONB_BUILD_OBJECT", "JSON_BUILD_OBJECT",
+    "RANDOM", "REGEXP_REPLACE", "SPLIT_PART", "STRING_TO_ARRAY", "TO_DATE", "TO_NUMBER", "TO_TIMESTAMP",
+];
+
+const MYSQL: &[&str] = &[
+    "DATEDIFF", "DATE_ADD", "DATE_FORMAT", "DATE_SUB", "FIND_IN_SET", "FROM_UNIXTIME", "GROUP_CONCAT",
+    "IFNULL", "INSTR", "JSON_EXTRACT", "JSON_OBJECT", "JSON_UNQUOTE", "LAST_INSERT_ID", "LOCATE", "RAND",
+    "STR_TO_DATE", "UNIX_TIMESTAMP",
+];
+
+const SQLITE: &[&str] = &[
+    "CHANGES", "DATETIME", "GROUP_CONCAT", "IFNULL", "INSTR", "JSON_EXTRACT", "JULIANDAY", "LAST_INSERT_ROWID",
+    "PRINTF", "RANDOM", "STRFTIME", "TOTAL", "TYPEOF",
+];
+
+const MSSQL: &[&str] = &[
+    "CHARINDEX", "DATEADD", "DATEDIFF", "DATENAME", "DATEPART", "FORMAT", "GETDATE", "GETUTCDATE", "IIF",
+    "ISNULL", "LEN", "NEWID", "SCOPE_IDENTITY", "STUFF", "SYSDATETIME",
+];
+
+const ORACLE: &[&str] = &[
+    "ADD_MONTHS", "DECODE", "INSTR", "LISTAGG", "MONTHS_BETWEEN", "NVL", "NVL2", "SYS_GUID", "SYSDATE",
+    "TO_DATE", "TO_NUMBER", "TRUNC",
+];
+
+const BIGQUERY: &[&str] = &[
+    "ARRAY_LENGTH", "DATE_DIFF", "DATE_TRUNC", "FORMAT_DATE", "FORMAT_TIMESTAMP", "GENERATE_UUID", "IFNULL",
+    "PARSE_DATE", "PARSE_TIMESTAMP", "REGEXP_CONTAINS", "REGEXP_EXTRACT", "SAFE_DIVIDE", "TIMESTAMP_DIFF",
+    "TIMESTAMP_TRUNC",
+];
+
+const SNOWFLAKE: &[&str] = &[
+    "ARRAY_SIZE", "DATEADD", "DATEDIFF", "DATE_TRUNC", "IFF", "IFNULL", "LISTAGG", "NVL", "NVL2", "PARSE_JSON",
+    "TO_DATE", "TO_NUMBER", "TO_TIMESTAMP", "TO_VARCHAR", "ZEROIFNULL",
+];
+
+/// Uppercased: ClickHouse's own names are camel case (`toDate`, `uniqExact`)
+const CLICKHOUSE: &[&str] = &[
+    "ARRAYJOIN", "COUNTIF", "GROUPARRAY", "IFNULL", "TODATE", "TODATETIME", "TOSTARTOFDAY", "TOSTARTOFHOUR",
+    "TOSTRING", "TOUINT64", "UNIQ", "UNIQEXACT",
+];
+
+const DUCKDB: &[&str] = &[
+    "DATE_DIFF", "DATE_PART", "DATE_TRUNC", "EPOCH", "LIST_VALUE", "REGEXP_MATCHES", "STRFTIME", "STRING_SPLIT",
+    "STRPTIME",
+];
+
+/// Hive's, which Databricks shares
+const HIVE: &[&str] = &[
+    "COLLECT_LIST", "COLLECT_SET", "DATEDIFF", "DATE_ADD", "DATE_FORMAT", "FROM_UNIXTIME", "GET_JSON_OBJECT",
+    "NVL", "SIZE", "SPLIT", "UNIX_TIMESTAMP",
+];
+
+const REDSHIFT: &[&str] = &[
+    "DATEADD", "DATEDIFF", "DATE_TRUNC", "GETDATE", "LISTAGG", "NVL", "NVL2", "SPLIT_PART", "SYSDATE", "TO_DATE",
+];
+
+fn dialect_builtins(dialect: &str) -> &'static [&'static str] {
+    match dialect {
+        "postgresql" => POSTGRES,
+        "mysql" => MYSQL,
+        "sqlite" => SQLITE,
+        "mssql" => MSSQL,
+        "oracle" => ORACLE,
+        "bigquery" => BIGQUERY,
+        "snowflake" => SNOWFLAKE,
+        "clickhouse" => CLICKHOUSE,
+        "duckdb" => DUCKDB,
+        "hive" | "databricks" => HIVE,
+        "redshift" => REDSHIFT,
+        // Those of the base, for pseudo-dialects
+        name => dialects::spec(name).map_or(&[], |spec| dialect_builtins(spec.base)),
+    }
+}
+
+/// Whether `name` is a builtin function of `dialect` (a canonical name);
+/// builtins are matched case-insensitively
+pub fn is_builtin(dialect: &str, name: &str) -> bool {
+    let upper = name.to_uppercase();
+    let name = upper.as_str();
+    ALL_KEYWORDS.binary_search(&name).is_ok() || COMMON.contains(&name) || dialect_builtins(dialect).contains(&name)
+}
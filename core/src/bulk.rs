@@ -0,0 +1,240 @@
+//! MySQL bulk loads and exports, which sqlparser does not parse: `LOAD DATA
+//! [LOCAL] INFILE 'file' INTO TABLE t ...` and the `INTO OUTFILE 'file'` (or
+//! `INTO DUMPFILE`) clause of a `SELECT`.
+//!
+//! Their file paths are literals, often with per-run timestamps, and are
+//! parameterized like any other, as are the field and line separators and
+//! the number of lines to skip. Both are located on the token stream. The
+//! export clause is taken out of the `SELECT`, which is then normalized as
+//! usual, and written back at its end, so that the clause reads the same
+//! before `FROM` and after the query. A `LOAD DATA` statement is normalized
+//! token by token: the keywords of its syntax uppercased, whitespace and
+//! comments collapsed, literals replaced and the other tokens, names and the
+//! expressions of its `SET` clause included, kept as written.
+
+use sqlparser::dialect::Dialect;
+use sqlparser::tokenizer::{Token, TokenWithSpan, Tokenizer};
+
+use crate::paramstyle::token_offsets;
+use crate::{placeholder_text, Clause};
+
+/// Keywords of the `LOAD DATA` syntax, before its `SET` clause
+const LOAD_KEYWORDS: &[&str] = &[
+    "BY", "CHARACTER", "CHARSET", "COLUMNS", "CONCURRENT", "DATA", "ENCLOSED", "ESCAPED", "FIELDS", "IGNORE",
+    "INFILE", "INTO", "LINES", "LOAD", "LOCAL", "LOW_PRIORITY", "OPTIONALLY", "PARTITION", "REPLACE", "ROWS", "SET",
+    "STARTING", "TABLE", "TERMINATED",
+];
+
+/// Keywords of the field and line options of `INTO OUTFILE`
+const EXPORT_OPTIONS: &[&str] =
+    &["BY", "COLUMNS", "ENCLOSED", "ESCAPED", "FIELDS", "LINES", "OPTIONALLY", "STARTING", "TERMINATED"];
+
+/// A literal of a bulk statement
+pub struct Literal {
+    /// As written, without its prefix
+    pub value: String,
+    /// `"N"`, `"E"` or `"X"` for a prefixed string form
+    pub prefix: Option<&'static str>,
+    /// `"integer"`, `"decimal"` or `"float"` for a number
+    pub kind: Option<&'static str>,
+    /// The clause it is counted in: `Set` in the `SET` clause of a `LOAD
+    /// DATA`, `Other` in a statement that could not be parsed, else `Into`
+    pub clause: Clause,
+}
+
+pub enum Piece {
+    Keyword(String),
+    Text(String),
+    Literal(Literal),
+}
+
+/// A run of tokens, normalized one by one
+pub struct Tokens {
+    pieces: Vec<Piece>,
+    /// Whether a name is written against the parenthesis after it
+    /// everywhere, rather than in a `SET` clause only
+    calls: bool,
+}
+
+impl Tokens {
+    pub fn new(pieces: Vec<Piece>, calls: bool) -> Self {
+        Tokens { pieces, calls }
+    }
+
+    /// The literals, in order
+    pub fn literals(&self) -> impl Iterator<Item = &Literal> {
+        self.pieces.iter().filter_map(|piece| match piece {
+            Piece::Literal(literal) => Some(literal),
+            Piece::Keyword(_) | Piece::Text(_) => None,
+        })
+    }
+
+    /// The tokens separated by single spaces, but for none inside
+    /// parentheses, before commas, around dots and between a function name
+    /// and its arguments in a `SET` clause (or anywhere, with `calls`); the literals written
+    /// as the placeholders numbered from `start` if `parameterize`, else as
+    /// they are
+    pub fn write(&self, placeholder: &str, start: usize, parameterize: bool) -> String {
+        let mut out = String::new();
+        let mut n = start;
+        let (mut glued, mut name, mut in_set) = (true, false, false);
+        for (i, piece) in self.pieces.iter().enumerate() {
+            let text = match piece {
+                Piece::Keyword(text) | Piece::Text(text) => text.clone(),
+                Piece::Literal(_) if parameterize => {
+                    n += 1;
+                    placeholder_text(placeholder, n - 1)
+                }
+                Piece::Literal(literal) => format!("{}{}", literal.prefix.unwrap_or_default(), literal.value),
+            };
+            let call = name && text == "(";
+            if !glued && !call && !matches!(text.as_str(), ")" | "," | ".") {
+                out.push(' ');
+            }
+            glued = matches!(text.as_str(), "(" | ".");
+            name = (self.calls || in_set) && matches!(piece, Piece::Text(text) if text.starts_with(|c: char| c.is_alphabetic() || c == '_'));
+            in_set |= matches!(piece, Piece::Keyword(text) if text == "SET")
+                && !matches!(i.checked_sub(1).map(|j| &self.pieces[j]), Some(Piece::Keyword(text)) if text == "CHARACTER");
+            out.push_str(&text);
+        }
+        out
+    }
+}
+
+pub fn tokenize(sql: &str, dialect: &dyn Dialect) -> Option<Vec<TokenWithSpan>> {
+    Tokenizer::new(dialect, sql).with_unescape(false).tokenize_with_location().ok()
+}
+
+pub fn is_blank(token: &Token) -> bool {
+    matches!(token, Token::Whitespace(_))
+}
+
+/// The uppercased word `token` is, unquoted
+pub fn word(token: &Token) -> Option<String> {
+    match token {
+        Token::Word(w) if w.quote_style.is_none() => Some(w.value.to_ascii_uppercase()),
+        _ => None,
+    }
+}
+
+pub fn literal(token: &Token) -> Option<Literal> {
+    let (value, prefix) = match token {
+        Token::Number(n, _) => {
+            let kind = if n.contains(['e', 'E']) {
+                "float"
+            } else if n.contains('.') {
+                "decimal"
+            } else {
+                "integer"
+            };
+            return Some(Literal { value: n.clone(), prefix: None, kind: Some(kind), clause: Clause::Into });
+        }
+        Token::SingleQuotedString(_) | Token::DoubleQuotedString(_) => (token.to_string(), None),
+        Token::NationalStringLiteral(_) => (token.to_string(), Some("N")),
+        Token::EscapedStringLiteral(_) => (token.to_string(), Some("E")),
+        Token::HexStringLiteral(_) => (token.to_string(), Some("X")),
+        _ => return None,
+    };
+    let value = value[prefix.map_or(0, str::len)..].to_string();
+    Some(Literal { value, prefix, kind: None, clause: Clause::Into })
+}
+
+/// `tokens` as pieces, the words in `keywords` uppercased
+fn pieces<'a>(tokens: impl Iterator<Item = &'a Token>, keywords: &[&str]) -> Vec<Piece> {
+    tokens
+        .filter(|token| !is_blank(token))
+        .map(|token| match (literal(token), word(token)) {
+            (Some(literal), _) => Piece::Literal(literal),
+            (None, Some(word)) if keywords.contains(&word.as_str()) => Piece::Keyword(word),
+            _ => Piece::Text(token.to_string()),
+        })
+        .collect()
+}
+
+/// `sql` normalized token by token if it is a `LOAD DATA ... INFILE`
+pub fn load_data(sql: &str, dialect: &dyn Dialect) -> Option<Tokens> {
+    let tokens = tokenize(sql, dialect)?;
+    let mut tokens: Vec<&Token> = tokens.iter().map(|t| &t.token).filter(|t| !is_blank(t)).collect();
+    let words: Vec<Option<String>> = tokens.iter().take(6).map(|t| word(t)).collect();
+    let infile = words.iter().position(|w| w.as_deref() == Some("INFILE"))?;
+    let modifiers = words.get(2..infile)?;
+    let plain = modifiers.iter().all(|w| matches!(w.as_deref(), Some("LOW_PRIORITY" | "CONCURRENT" | "LOCAL")));
+    if words[0].as_deref() != Some("LOAD") || words[1].as_deref() != Some("DATA") || !plain {
+        return None;
+    }
+    // The first statement only
+    if let Some(end) = tokens.iter().position(|t| matches!(t, Token::SemiColon)) {
+        tokens.truncate(end);
+    }
+    // Keywords at the top level, before the `SET` clause: the column list
+    // and the `PARTITION` list are parenthesized
+    let mut out = Vec::with_capacity(tokens.len());
+    let (mut depth, mut in_set) = (0usize, false);
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::LParen => depth += 1,
+            Token::RParen => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        let keywords = if depth == 0 && !in_set { LOAD_KEYWORDS } else { &[] };
+        let mut piece = pieces([*token].into_iter(), keywords);
+        if let Some(Piece::Literal(ref mut literal)) = piece.first_mut() {
+            if in_set {
+                literal.clause = Clause::Set;
+            }
+        }
+        out.append(&mut piece);
+        // `CHARACTER SET` names the file's character set
+        let previous = i.checked_sub(1).and_then(|j| word(tokens[j]));
+        in_set |= depth == 0 && word(token).as_deref() == Some("SET") && previous.as_deref() != Some("CHARACTER");
+    }
+    Some(Tokens::new(out, false))
+}
+
+/// `sql` without the `INTO OUTFILE` or `INTO DUMPFILE` clause of its
+/// `SELECT`, and the clause, if it has one
+pub fn split_export(sql: &str, dialect: &dyn Dialect) -> Option<(String, Tokens)> {
+    let tokens = tokenize(sql, dialect)?;
+    let offsets = token_offsets(sql, &tokens, dialect);
+    let solid: Vec<usize> = (0..tokens.len()).filter(|&i| !is_blank(&tokens[i].token)).collect();
+    let token = |k: usize| solid.get(k).map(|&i| &tokens[i].token);
+    let mut depth = 0usize;
+    let mut start = None;
+    for k in 0..solid.len() {
+        match token(k) {
+            Some(Token::LParen) => depth += 1,
+            Some(Token::RParen) => depth = depth.saturating_sub(1),
+            Some(t) if depth == 0 && word(t).as_deref() == Some("INTO") => {
+                if matches!(token(k + 1).and_then(word).as_deref(), Some("OUTFILE" | "DUMPFILE")) {
+                    start = Some(k);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let start = start?;
+    let outfile = token(start + 1).and_then(word).as_deref() == Some("OUTFILE");
+    token(start + 2).and_then(literal)?;
+    let mut end = start + 3;
+    while let Some(option) = token(end).and_then(word).filter(|_| outfile) {
+        match option.as_str() {
+            "CHARACTER" if token(end + 1).and_then(word).as_deref() == Some("SET") => end += 3,
+            "CHARSET" => end += 2,
+            option if EXPORT_OPTIONS.contains(&option) => {
+                end += 1;
+                if option == "BY" && token(end).and_then(literal).is_some() {
+                    end += 1;
+                }
+            }
+            _ => break,
+        }
+    }
+    let end = end.min(solid.len());
+    let keywords = ["CHARACTER", "CHARSET", "DUMPFILE", "INTO", "OUTFILE", "SET"];
+    let clause = pieces((start..end).filter_map(token), &[&keywords[..], EXPORT_OPTIONS].concat());
+    let from = offsets[solid[start]];
+    let to = solid.get(end).map_or(sql.len(), |&i| offsets[i]);
+    let select = format!("{} {}", sql[..from].trim_end(), &sql[to..]);
+    Some((select, Tokens::new(clause, false)))
+}
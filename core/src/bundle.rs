@@ -0,0 +1,276 @@
+//! Anonymization and structural metrics for `export_bundle()`.
+//!
+//! Anonymization works on the token stream of the *normalized* statement:
+//! every identifier is replaced with `id1`, `id2`, … (the same name always
+//! getting the same replacement within a bundle) and any literal left after
+//! normalization becomes `?`. Identifiers are the words that are not keywords,
+//! plus every name the parsed statement uses for a table, column or alias, so
+//! that columns named like keywords (`name`, `id`) are caught as well.
+//! Keywords, operators, placeholders and function names are kept, so the shape
+//! of the statement survives but none of its names or data do.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+
+use serde_json::{json, Value};
+use sqlparser::ast::{
+    Expr, Ident, ObjectName, Query, SelectItem, SetExpr, Statement, TableFactor, Visit, Visitor,
+};
+use sqlparser::dialect::Dialect;
+use sqlparser::keywords::Keyword;
+use sqlparser::tokenizer::{Token, Tokenizer};
+
+use crate::coverage::table_alias;
+use crate::scope::Scopes;
+use crate::writer::FINGERPRINT_VERSION;
+
+/// Names are matched case-insensitively unless quoted
+fn key(value: &str, quoted: bool) -> String {
+    if quoted {
+        value.to_string()
+    } else {
+        value.to_lowercase()
+    }
+}
+
+/// Keys of the names a statement uses for tables, columns and aliases
+#[derive(Default)]
+struct Names(HashSet<String>);
+
+impl Names {
+    fn add(&mut self, ident: &Ident) {
+        self.0.insert(key(&ident.value, ident.quote_style.is_some()));
+    }
+
+    fn add_select_aliases(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                for item in &select.projection {
+                    if let SelectItem::ExprWithAlias { alias, .. } = item {
+                        self.add(alias);
+                    }
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.add_select_aliases(left);
+                self.add_select_aliases(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Visitor for Names {
+    type Break = ();
+
+    fn pre_visit_statement(&mut self, stmt: &Statement) -> ControlFlow<()> {
+        if let Statement::Insert(insert) = stmt {
+            insert.columns.iter().for_each(|c| self.add(c));
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        for cte in query.with.iter().flat_map(|with| &with.cte_tables) {
+            self.add(&cte.alias.name);
+        }
+        self.add_select_aliases(&query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &ObjectName) -> ControlFlow<()> {
+        relation.0.iter().filter_map(|part| part.as_ident()).for_each(|i| self.add(i));
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, tf: &TableFactor) -> ControlFlow<()> {
+        if let Some(alias) = table_alias(tf) {
+            self.add(&alias.name);
+            alias.columns.iter().for_each(|c| self.add(&c.name));
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<()> {
+        match expr {
+            Expr::Identifier(ident) => self.add(ident),
+            Expr::CompoundIdentifier(idents) => idents.iter().for_each(|i| self.add(i)),
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Replacement names for the identifiers seen so far in a bundle
+#[derive(Default)]
+pub struct Anonymizer {
+    names: HashMap<String, String>,
+}
+
+impl Anonymizer {
+    fn name(&mut self, key: String) -> &str {
+        let next = self.names.len() + 1;
+        self.names.entry(key).or_insert_with(|| format!("id{}", next))
+    }
+
+    /// `sql`, the normalized form of `stmt`, with its identifiers and
+    /// leftover literals replaced
+    pub fn anonymize(&mut self, sql: &str, stmt: &Statement, dialect: &dyn Dialect) -> Result<String, String> {
+        let mut names = Names::default();
+        let _ = stmt.visit(&mut names);
+        let tokens = Tokenizer::new(dialect, sql)
+            .with_unescape(false)
+            .tokenize()
+            .map_err(|e| format!("Tokenize error: {}", e))?;
+        let mut out = String::with_capacity(sql.len());
+        for (i, token) in tokens.iter().enumerate() {
+            let next = tokens[i + 1..].iter().find(|t| !matches!(t, Token::Whitespace(_)));
+            match token {
+                Token::Word(w) if w.quote_style.is_some() => {
+                    out.push_str(self.name(key(&w.value, true)));
+                }
+                Token::Word(w)
+                    if names.0.contains(&key(&w.value, false))
+                        || (w.keyword == Keyword::NoKeyword && next != Some(&Token::LParen)) =>
+                {
+                    out.push_str(self.name(key(&w.value, false)));
+                }
+                Token::Number(..)
+                | Token::SingleQuotedString(_)
+                | Token::DoubleQuotedString(_)
+                | Token::NationalStringLiteral(_)
+                | Token::EscapedStringLiteral(_)
+                | Token::HexStringLiteral(_)
+                | Token::DollarQuotedString(_) => out.push('?'),
+                _ => out.push_str(&token.to_string()),
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Size and shape of a statement, independent of its names and values
+#[derive(Default)]
+pub struct Metrics {
+    /// Table references, counting repeats
+    pub tables: u64,
+    /// References to CTEs, which `tables` leaves out
+    pub ctes: u64,
+    pub joins: u64,
+    /// Queries nested in another query (subqueries, CTEs, set operation sides)
+    pub subqueries: u64,
+    /// Deepest query nesting, 1 for a plain query and 0 for no query
+    pub depth: u64,
+    pub expressions: u64,
+}
+
+#[derive(Default)]
+struct Counter {
+    metrics: Metrics,
+    depth: u64,
+    scopes: Scopes,
+}
+
+impl Counter {
+    fn count_joins(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                self.metrics.joins += select.from.iter().map(|twj| twj.joins.len() as u64).sum::<u64>();
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.count_joins(left);
+                self.count_joins(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Visitor for Counter {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        self.depth += 1;
+        if self.depth > 1 {
+            self.metrics.subqueries += 1;
+        }
+        self.metrics.depth = self.metrics.depth.max(self.depth);
+        self.count_joins(&query.body);
+        self.scopes.enter(query);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        self.depth -= 1;
+        self.scopes.leave(query);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &ObjectName) -> ControlFlow<()> {
+        if self.scopes.is_cte(relation) {
+            self.metrics.ctes += 1;
+        } else {
+            self.metrics.tables += 1;
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, _expr: &Expr) -> ControlFlow<()> {
+        self.metrics.expressions += 1;
+        ControlFlow::Continue(())
+    }
+}
+
+pub fn metrics(stmt: &Statement) -> Metrics {
+    let mut counter = Counter::default();
+    let _ = stmt.visit(&mut counter);
+    counter.metrics
+}
+
+/// One distinct query shape of a bundle
+pub struct Entry {
+    pub hash: String,
+    /// The anonymized normalized statement
+    pub sql: String,
+    /// Number of input queries with this fingerprint
+    pub count: u64,
+    pub params: usize,
+    pub warnings: Vec<String>,
+    pub metrics: Metrics,
+}
+
+/// The bundle for `entries`, as pretty-printed JSON with sorted keys
+pub fn document(dialect: &str, compat: Option<&str>, entries: Vec<Entry>, unparsed: u64) -> String {
+    let queries: Vec<Value> = entries
+        .into_iter()
+        .map(|entry| {
+            let m = entry.metrics;
+            json!({
+                "hash": entry.hash,
+                "sql": entry.sql,
+                "count": entry.count,
+                "params": entry.params,
+                "warnings": entry.warnings,
+                "metrics": {
+                    "tables": m.tables,
+                    "ctes": m.ctes,
+                    "joins": m.joins,
+                    "subqueries": m.subqueries,
+                    "depth": m.depth,
+                    "expressions": m.expressions,
+                },
+            })
+        })
+        .collect();
+    let doc = json!({
+        "sqlfp_version": env!("CARGO_PKG_VERSION"),
+        "fingerprint_version": FINGERPRINT_VERSION,
+        "dialect": dialect,
+        "compat": compat,
+        "queries": queries,
+        "unparsed": unparsed,
+    });
+    let mut out = serde_json::to_string_pretty(&doc).expect("bundle is valid JSON");
+    out.push('\n');
+    out
+}
@@ -0,0 +1,174 @@
+//! Detection of possible cartesian products, for
+//! `metadata["possible_cartesian"]`.
+//!
+//! The relations of each `SELECT` start out apart; a join with a constraint
+//! (`ON`, `USING`, `NATURAL`) connects the joined relation to the first one of
+//! its comma-separated item, and each `WHERE` conjunct connects the relations
+//! its columns name through their qualifier. A `SELECT` whose relations are
+//! still apart afterwards is flagged: a comma join or `CROSS JOIN` with no
+//! predicate relating its sides.
+//!
+//! The check errs towards silence. A conjunct comparing unqualified columns
+//! is taken to connect every relation, as is any relation other than a plain
+//! table or derived table (`UNNEST`, table functions, `LATERAL`, nested joins),
+//! which usually reads from the others.
+
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    visit_expressions, BinaryOperator, Expr, JoinConstraint, Query, Select, SetExpr, Statement, TableFactor, Visit,
+    Visitor,
+};
+
+use crate::join_constraint;
+
+/// Union-find over the relations of one `SELECT`
+struct Relations {
+    /// Name each relation is referenced by, lowercased; `None` if it
+    /// connects to every other
+    names: Vec<Option<String>>,
+    parent: Vec<usize>,
+}
+
+impl Relations {
+    fn add(&mut self, factor: &TableFactor) -> usize {
+        let name = match factor {
+            TableFactor::Table { name, alias, .. } => match alias {
+                Some(alias) => Some(alias.name.value.to_lowercase()),
+                None => name.0.last().and_then(|part| part.as_ident()).map(|ident| ident.value.to_lowercase()),
+            },
+            TableFactor::Derived { lateral: false, alias, .. } => {
+                Some(alias.as_ref().map_or_else(String::new, |alias| alias.name.value.to_lowercase()))
+            }
+            _ => None,
+        };
+        self.names.push(name);
+        self.parent.push(self.parent.len());
+        self.parent.len() - 1
+    }
+
+    fn root(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.root(a), self.root(b));
+        self.parent[a] = b;
+    }
+
+    fn union_all(&mut self) {
+        for i in 1..self.parent.len() {
+            self.union(0, i);
+        }
+    }
+
+    /// Connect the relations `conjunct` refers to
+    fn correlate(&mut self, conjunct: &Expr) {
+        let (mut qualifiers, mut unqualified) = (Vec::new(), 0);
+        let _ = visit_expressions(conjunct, |expr| {
+            match expr {
+                Expr::Identifier(_) => unqualified += 1,
+                Expr::CompoundIdentifier(idents) if idents.len() >= 2 => {
+                    qualifiers.push(idents[idents.len() - 2].value.to_lowercase());
+                }
+                _ => {}
+            }
+            ControlFlow::<()>::Continue(())
+        });
+        if unqualified > 0 && unqualified + qualifiers.len() >= 2 {
+            self.union_all();
+            return;
+        }
+        let matching: Vec<usize> = (0..self.names.len())
+            .filter(|&i| self.names[i].as_ref().is_some_and(|name| qualifiers.contains(name)))
+            .collect();
+        for pair in matching.windows(2) {
+            self.union(pair[0], pair[1]);
+        }
+    }
+
+    fn connected(&mut self) -> bool {
+        let first = self.root(0);
+        (1..self.parent.len()).all(|i| self.root(i) == first)
+    }
+}
+
+/// Push the AND-ed conjuncts of `expr` onto `out`
+fn conjuncts<'a>(expr: &'a Expr, out: &mut Vec<&'a Expr>) {
+    match expr {
+        Expr::BinaryOp { left, op: BinaryOperator::And, right } => {
+            conjuncts(left, out);
+            conjuncts(right, out);
+        }
+        Expr::Nested(inner) => conjuncts(inner, out),
+        other => out.push(other),
+    }
+}
+
+fn is_cartesian(select: &Select) -> bool {
+    let mut relations = Relations { names: Vec::new(), parent: Vec::new() };
+    for item in &select.from {
+        let first = relations.add(&item.relation);
+        for join in &item.joins {
+            let joined = relations.add(&join.relation);
+            if !matches!(join_constraint(&join.join_operator), Some(JoinConstraint::None)) {
+                relations.union(first, joined);
+            }
+        }
+    }
+    if relations.names.len() < 2 || relations.names.iter().any(Option::is_none) {
+        return false;
+    }
+    let mut predicates = Vec::new();
+    if let Some(ref selection) = select.selection {
+        conjuncts(selection, &mut predicates);
+    }
+    for conjunct in predicates {
+        relations.correlate(conjunct);
+    }
+    !relations.connected()
+}
+
+#[derive(Default)]
+struct Cartesian {
+    found: bool,
+}
+
+impl Cartesian {
+    /// Check the `SELECT`s of `body`; parenthesized queries are checked when
+    /// visited
+    fn check(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Select(select) => self.found |= is_cartesian(select),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.check(left);
+                self.check(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Visitor for Cartesian {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        self.check(&query.body);
+        if self.found {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}
+
+/// Whether a `SELECT` of `stmt` joins relations no predicate relates
+pub fn possible_cartesian(stmt: &Statement) -> bool {
+    let mut pass = Cartesian::default();
+    let _ = stmt.visit(&mut pass);
+    pass.found
+}
@@ -0,0 +1,71 @@
+//! Canonical CASE expressions, for the opt-in `case-canonicalize` and
+//! `case-arm-collapse` passes.
+//!
+//! Query builders write the same logic as `CASE x WHEN 1 THEN a END` or
+//! `CASE WHEN x = 1 THEN a ELSE NULL END`. Simple CASE is rewritten in the
+//! searched form, comparing the operand to each `WHEN` value with `=` (which
+//! is how simple CASE matches, NULLs included), and an `ELSE NULL` is dropped
+//! as it is what CASE returns without one.
+//!
+//! Bulk updates generated as `SET x = CASE id WHEN 1 THEN ? WHEN 2 THEN ? ...
+//! END` have one arm per row of the batch. Like the IN lists of
+//! `shape_hash`, runs of adjacent arms of the same shape collapse to their
+//! first arm, so that the fingerprint does not depend on the batch size; the
+//! literals of the dropped arms are not collected as parameters.
+
+use core::ops::ControlFlow;
+use sqlparser::ast::{visit_expressions_mut, BinaryOperator, Expr, Value};
+
+use crate::canonicalize_parens;
+use crate::precedence::Precedence;
+
+/// `left = right`, parenthesizing the operands precedence requires
+fn equals(left: Expr, right: Expr, precedence: Precedence) -> Expr {
+    let mut expr = Expr::BinaryOp { left: Box::new(left), op: BinaryOperator::Eq, right: Box::new(right) };
+    canonicalize_parens(&mut expr, precedence);
+    expr
+}
+
+pub fn canonicalize(expr: &mut Expr, precedence: Precedence) {
+    let Expr::Case { operand, conditions, else_result, .. } = expr else {
+        return;
+    };
+    if let Some(operand) = operand.take() {
+        for when in conditions.iter_mut() {
+            let value = std::mem::replace(&mut when.condition, Expr::Value(Value::Null.into()));
+            when.condition = equals((*operand).clone(), value, precedence);
+        }
+    }
+    if else_result.as_deref().is_some_and(|e| matches!(e, Expr::Value(v) if v.value == Value::Null)) {
+        *else_result = None;
+    }
+}
+
+/// `expr` with its literals masked, to compare arms whatever their values
+pub fn shape(expr: &Expr) -> Expr {
+    let mut masked = expr.clone();
+    let _ = visit_expressions_mut(&mut masked, |e| {
+        if let Expr::Value(value) = e {
+            value.value = Value::Placeholder("?".to_string());
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    masked
+}
+
+/// Keep the first of each run of adjacent `WHEN` arms whose condition and
+/// result have the same shape; the number of arms dropped
+pub fn collapse_arms(expr: &mut Expr) -> usize {
+    let Expr::Case { conditions, .. } = expr else {
+        return 0;
+    };
+    let len = conditions.len();
+    let mut previous = None;
+    conditions.retain(|when| {
+        let arm = Some((shape(&when.condition), shape(&when.result)));
+        let keep = arm != previous;
+        previous = arm;
+        keep
+    });
+    len - conditions.len()
+}
@@ -0,0 +1,72 @@
+//! Canonical casts, for the `cast-canonicalize` pass.
+//!
+//! `x::int`, `CAST(x AS INT)` and `CAST(x AS integer)` are one conversion
+//! spelled three ways. PostgreSQL's `::` is written as `CAST(... AS ...)`, and
+//! type names that are synonyms in every dialect as one of them: `INT` as
+//! `INTEGER`, `DEC` as `DECIMAL`, `BOOL` as `BOOLEAN`, `CHARACTER VARYING` as
+//! `VARCHAR`, `CHARACTER` as `CHAR` and `TIMESTAMPTZ` as
+//! `TIMESTAMP WITH TIME ZONE`. PostgreSQL, Redshift and DuckDB also name
+//! their types `INT2`, `INT4`, `INT8`, `FLOAT4` and `FLOAT8`, which are
+//! written `SMALLINT`, `INTEGER`, `BIGINT`, `REAL` and `DOUBLE PRECISION`,
+//! `DECIMAL` as `NUMERIC` and `TIMESTAMP WITHOUT TIME ZONE` as `TIMESTAMP`;
+//! elsewhere those names may mean other types (ClickHouse's `Int8` is a byte).
+//! Other type names are uppercased unless quoted.
+//!
+//! `TRY_CAST` and `SAFE_CAST`, which return NULL where a cast fails, keep
+//! their keyword; their type names are canonicalized all the same.
+
+use sqlparser::ast::{ArrayElemTypeDef, CastKind, DataType, Expr, ObjectNamePart, TimezoneInfo};
+
+/// `data_type` with its name canonical, `pg_types` reading PostgreSQL's
+/// names of the integer and float types
+fn canonical_type(data_type: &mut DataType, pg_types: bool) {
+    let canonical = match data_type {
+        DataType::Int(p) => DataType::Integer(*p),
+        DataType::IntUnsigned(p) => DataType::IntegerUnsigned(*p),
+        DataType::Dec(info) if pg_types => DataType::Numeric(*info),
+        DataType::Decimal(info) if pg_types => DataType::Numeric(*info),
+        DataType::Dec(info) => DataType::Decimal(*info),
+        DataType::DecUnsigned(info) => DataType::DecimalUnsigned(*info),
+        DataType::Bool => DataType::Boolean,
+        DataType::CharacterVarying(len) | DataType::CharVarying(len) => DataType::Varchar(*len),
+        DataType::Character(len) => DataType::Char(*len),
+        DataType::Timestamp(p, TimezoneInfo::Tz) => DataType::Timestamp(*p, TimezoneInfo::WithTimeZone),
+        DataType::Time(p, TimezoneInfo::Tz) => DataType::Time(*p, TimezoneInfo::WithTimeZone),
+        DataType::Timestamp(p, TimezoneInfo::WithoutTimeZone) if pg_types => DataType::Timestamp(*p, TimezoneInfo::None),
+        DataType::Time(p, TimezoneInfo::WithoutTimeZone) if pg_types => DataType::Time(*p, TimezoneInfo::None),
+        DataType::Int2(p) if pg_types => DataType::SmallInt(*p),
+        DataType::Int4(p) if pg_types => DataType::Integer(*p),
+        DataType::Int8(p) if pg_types => DataType::BigInt(*p),
+        DataType::Float4 if pg_types => DataType::Real,
+        DataType::Float8 if pg_types => DataType::DoublePrecision,
+        DataType::Array(
+            ArrayElemTypeDef::AngleBracket(element)
+            | ArrayElemTypeDef::SquareBracket(element, _)
+            | ArrayElemTypeDef::Parenthesis(element),
+        ) => {
+            canonical_type(element, pg_types);
+            return;
+        }
+        DataType::Custom(name, _) => {
+            for part in &mut name.0 {
+                if let ObjectNamePart::Identifier(ident) = part {
+                    if ident.quote_style.is_none() {
+                        ident.value = ident.value.to_uppercase();
+                    }
+                }
+            }
+            return;
+        }
+        _ => return,
+    };
+    *data_type = canonical;
+}
+
+pub fn canonicalize(expr: &mut Expr, pg_types: bool) {
+    if let Expr::Cast { kind, data_type, .. } = expr {
+        if *kind == CastKind::DoubleColon {
+            *kind = CastKind::Cast;
+        }
+        canonical_type(data_type, pg_types);
+    }
+}
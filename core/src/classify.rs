@@ -0,0 +1,120 @@
+//! Coarse classes of statements, for `NormalizeResult.statement_class`,
+//! `is_read_only` and `is_ddl`.
+//!
+//! A query leading with a `WITH` is classed by its body, so that
+//! `WITH x AS (...) INSERT INTO ...` is an `INSERT` rather than a `SELECT`.
+//! A `SELECT` is read-only unless it writes through `INTO`, a data-modifying
+//! CTE (`WITH d AS (DELETE ... RETURNING *) SELECT ...`) or takes row locks
+//! (`FOR UPDATE`, `FOR SHARE`), which read-only transactions refuse as well.
+//! `SHOW`, `DESCRIBE` and `EXPLAIN` without `ANALYZE` of a read-only statement
+//! are read-only too, though of class `OTHER`.
+
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{Query, SetExpr, Statement, Visit, Visitor};
+
+use crate::ast::variant_name;
+
+/// Every class a statement can have
+pub const CLASSES: [&str; 6] = ["SELECT", "INSERT", "UPDATE", "DELETE", "DDL", "OTHER"];
+
+pub struct Class {
+    pub class: &'static str,
+    pub read_only: bool,
+}
+
+impl Class {
+    pub fn is_ddl(&self) -> bool {
+        self.class == "DDL"
+    }
+}
+
+/// The class of a statement other than a query, by its kind
+fn statement_class(stmt: &Statement) -> &'static str {
+    match stmt {
+        Statement::Insert(_) => "INSERT",
+        Statement::Update(_) => "UPDATE",
+        Statement::Delete(_) => "DELETE",
+        Statement::Truncate(_) | Statement::Comment { .. } | Statement::RenameTable(_) => "DDL",
+        _ => {
+            let kind = variant_name(stmt);
+            if kind.starts_with("Create") || kind.starts_with("Alter") || kind.starts_with("Drop") {
+                "DDL"
+            } else {
+                "OTHER"
+            }
+        }
+    }
+}
+
+fn set_expr_class(body: &SetExpr) -> Option<&'static str> {
+    match body {
+        SetExpr::Insert(_) => Some("INSERT"),
+        SetExpr::Update(_) => Some("UPDATE"),
+        SetExpr::Delete(_) => Some("DELETE"),
+        SetExpr::Merge(_) => Some("OTHER"),
+        SetExpr::Query(query) => set_expr_class(&query.body),
+        _ => None,
+    }
+}
+
+/// Whether a query writes or locks anywhere within it
+#[derive(Default)]
+struct Writes(bool);
+
+impl Visitor for Writes {
+    type Break = ();
+
+    fn pre_visit_statement(&mut self, stmt: &Statement) -> ControlFlow<()> {
+        if !matches!(stmt, Statement::Query(_)) {
+            self.0 = true;
+            return ControlFlow::Break(());
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        let into = matches!(*query.body, SetExpr::Select(ref select) if select.into.is_some());
+        if into || !query.locks.is_empty() || set_expr_class(&query.body).is_some() {
+            self.0 = true;
+            return ControlFlow::Break(());
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+fn read_only(stmt: &Statement) -> bool {
+    match stmt {
+        Statement::Query(_) => {
+            let mut writes = Writes::default();
+            let _ = stmt.visit(&mut writes);
+            !writes.0
+        }
+        Statement::Explain { analyze, statement, .. } => !analyze && read_only(statement),
+        Statement::ExplainTable { .. } => true,
+        _ => variant_name(stmt).starts_with("Show"),
+    }
+}
+
+pub fn classify(stmt: &Statement) -> Class {
+    let class = match stmt {
+        Statement::Query(query) => set_expr_class(&query.body).unwrap_or("SELECT"),
+        _ => statement_class(stmt),
+    };
+    Class { class, read_only: read_only(stmt) }
+}
+
+/// The class of a statement that could not be parsed, by its first word,
+/// uppercased. Nothing being known of what it reads or writes, it is not
+/// read-only, and one leading with a `WITH` is of class `OTHER`.
+pub fn classify_unparsed(first_word: Option<&str>) -> Class {
+    let class = match first_word {
+        Some("SELECT") => "SELECT",
+        Some("INSERT" | "REPLACE") => "INSERT",
+        Some("UPDATE") => "UPDATE",
+        Some("DELETE") => "DELETE",
+        Some("CREATE" | "ALTER" | "DROP" | "TRUNCATE" | "RENAME" | "COMMENT") => "DDL",
+        _ => "OTHER",
+    };
+    Class { class, read_only: false }
+}
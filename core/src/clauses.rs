@@ -0,0 +1,78 @@
+//! Hashes of the individual clauses of a normalized statement, for
+//! `metadata["clause_hashes"]`.
+//!
+//! Each clause is hashed as written after normalization, without its keyword,
+//! so that the WHERE of an `UPDATE` and of a `SELECT` hash alike when they test
+//! the same thing. Only the outermost query is looked at, and a set operation
+//! only contributes its `ORDER BY`.
+
+use sqlparser::ast::{FromTable, GroupByExpr, SetExpr, Statement, UpdateTableFromKind};
+
+use crate::{compute_hash, hashing};
+use crate::metadata::{MetaValue, Metadata};
+use crate::writer::{write, Node};
+
+fn joined<T: Node>(items: &[T]) -> String {
+    items.iter().map(write).collect::<Vec<_>>().join(", ")
+}
+
+/// `(name, text)` of the clauses of `stmt` that are present, in clause order
+pub fn clauses(stmt: &Statement) -> Vec<(&'static str, String)> {
+    let mut out = Vec::new();
+    match stmt {
+        Statement::Query(query) => {
+            if let SetExpr::Select(select) = query.body.as_ref() {
+                out.push(("projection", joined(&select.projection)));
+                if !select.from.is_empty() {
+                    out.push(("from", joined(&select.from)));
+                }
+                if let Some(ref selection) = select.selection {
+                    out.push(("where", write(selection)));
+                }
+                let grouped = match select.group_by {
+                    GroupByExpr::Expressions(ref exprs, ref modifiers) => !exprs.is_empty() || !modifiers.is_empty(),
+                    GroupByExpr::All(_) => true,
+                };
+                if grouped {
+                    let group_by = write(&select.group_by);
+                    out.push(("group_by", group_by.strip_prefix("GROUP BY ").unwrap_or(&group_by).to_string()));
+                }
+            }
+            if let Some(ref order_by) = query.order_by {
+                let order_by = write(order_by);
+                out.push(("order_by", order_by.strip_prefix("ORDER BY ").unwrap_or(&order_by).to_string()));
+            }
+        }
+        Statement::Update(update) => {
+            let mut from = write(&update.table);
+            if let Some(UpdateTableFromKind::BeforeSet(ref tables) | UpdateTableFromKind::AfterSet(ref tables)) =
+                update.from
+            {
+                from = format!("{}, {}", from, joined(tables));
+            }
+            out.push(("from", from));
+            if let Some(ref selection) = update.selection {
+                out.push(("where", write(selection)));
+            }
+        }
+        Statement::Delete(delete) => {
+            let (FromTable::WithFromKeyword(tables) | FromTable::WithoutKeyword(tables)) = &delete.from;
+            out.push(("from", joined(tables)));
+            if let Some(ref selection) = delete.selection {
+                out.push(("where", write(selection)));
+            }
+            if !delete.order_by.is_empty() {
+                out.push(("order_by", joined(&delete.order_by)));
+            }
+        }
+        _ => {}
+    }
+    out
+}
+
+/// `metadata["clause_hashes"]`, clause name → hash, for the clauses of `stmt`
+pub fn clause_hashes(stmt: &Statement) -> (String, MetaValue) {
+    let hashes: Metadata =
+        clauses(stmt).into_iter().map(|(name, text)| (name.to_string(), compute_hash(&hashing::Hasher::default(), &text).into())).collect();
+    ("clause_hashes".to_string(), MetaValue::Map(hashes))
+}
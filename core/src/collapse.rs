@@ -0,0 +1,215 @@
+//! Collapsing of value lists, for `NormalizeResult.shape_hash`.
+//!
+//! `IN (?, ?, ?)` becomes `IN (?)` and `VALUES (?, ?), (?, ?)` becomes
+//! `VALUES (?, ?)`, so that statements differing only in list lengths share a
+//! shape. Runs of adjacent items collapse when they have the same shape:
+//! both literals or placeholders (whatever their value), or equal otherwise.
+//! Numbered placeholders (`"${n}"`) are numbered again afterwards, so that the
+//! ones following a collapsed list do not keep the gap.
+//!
+//! Collapsing loses the parameters' place in their list, which
+//! `metadata["param_positions"]` keeps: `params` are laid out row-major, in
+//! order of appearance, and each one that is part of a VALUES row or an IN
+//! list is given the list's number, its row and its element in it, so that
+//! the batch the statement carried can be rebuilt from `params`.
+//!
+//! With `collapse_in_lists`, IN lists of constants only (the placeholders the
+//! literals were replaced with, or binds) are collapsed to their first item in
+//! the normalized statement itself, so that `IN (1, 2)` and `IN (1, 2, 3)`
+//! share a fingerprint; `params` still have all of the values. Likewise with
+//! `collapse_values`, the rows of a VALUES list collapse to the first when
+//! they differ from it only in their placeholders, so that bulk inserts
+//! fingerprint alike whatever their number of rows.
+
+use core::ops::ControlFlow;
+use sqlparser::ast::{Expr, Query, SetExpr, Statement, Value, VisitMut, VisitorMut};
+
+use crate::metadata::MetaValue;
+use crate::{placeholder_number, placeholder_text};
+
+/// Where a parameter stands in the VALUES rows or IN list it is part of
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    /// Number of the list among those of the statement, in order of appearance
+    pub list: usize,
+    /// Row of a VALUES list, `None` for an IN list
+    pub row: Option<usize>,
+    pub element: usize,
+}
+
+/// `metadata["param_positions"]`, the position of each of `params` (`null`
+/// for those outside lists), present only when some list was collapsed
+pub fn positions_metadata(collapsed: bool, positions: &[Option<Position>]) -> Option<(String, MetaValue)> {
+    if !collapsed || positions.iter().all(Option::is_none) {
+        return None;
+    }
+    let entry = |position: &Option<Position>| match position {
+        Some(Position { list, row, element }) => {
+            let mut entries = vec![("list".to_string(), MetaValue::Int(*list as i64))];
+            entries.extend(row.map(|row| ("row".to_string(), MetaValue::Int(row as i64))));
+            entries.push(("element".to_string(), MetaValue::Int(*element as i64)));
+            MetaValue::Map(entries)
+        }
+        None => MetaValue::Null,
+    };
+    Some(("param_positions".to_string(), MetaValue::List(positions.iter().map(entry).collect())))
+}
+
+fn same_shape(a: &Expr, b: &Expr) -> bool {
+    matches!((a, b), (Expr::Value(_), Expr::Value(_))) || a == b
+}
+
+fn same_row(a: &[Expr], b: &[Expr]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_shape(a, b))
+}
+
+struct Collapse {
+    changed: bool,
+}
+
+/// Collapses IN lists whose items are all placeholders to their first item
+struct CollapseConstants {
+    changed: bool,
+}
+
+impl VisitorMut for CollapseConstants {
+    type Break = ();
+
+    fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<()> {
+        if let Expr::InList { ref mut list, .. } = expr {
+            if list.len() > 1 && list.iter().all(is_placeholder) {
+                list.truncate(1);
+                self.changed = true;
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+impl Collapse {
+    fn dedup<T>(&mut self, items: &mut Vec<T>, same: impl Fn(&T, &T) -> bool) {
+        let len = items.len();
+        items.dedup_by(|b, a| same(a, b));
+        self.changed |= items.len() != len;
+    }
+}
+
+impl VisitorMut for Collapse {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        if let SetExpr::Values(ref mut values) = *query.body {
+            self.dedup(&mut values.rows, |a, b| same_row(a, b));
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<()> {
+        if let Expr::InList { ref mut list, .. } = expr {
+            self.dedup(list, same_shape);
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+fn is_placeholder(expr: &Expr) -> bool {
+    matches!(expr, Expr::Value(value) if matches!(value.value, Value::Placeholder(_)))
+}
+
+/// Whether rows differ only in their placeholders, so that collapsing them
+/// loses no literal
+fn same_parameters(a: &[Expr], b: &[Expr]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| (is_placeholder(a) && is_placeholder(b)) || a == b)
+}
+
+/// Collapses VALUES lists whose rows differ from the first only in their
+/// placeholders to it, keeping the number of rows of the first collapsed
+struct CollapseRows {
+    rows: Option<usize>,
+}
+
+impl VisitorMut for CollapseRows {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        if let SetExpr::Values(ref mut values) = *query.body {
+            if values.rows.len() > 1 && values.rows.iter().all(|row| same_parameters(&values.rows[0], row)) {
+                self.rows.get_or_insert(values.rows.len());
+                values.rows.truncate(1);
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// `metadata["values_rows"]`, the number of rows of the VALUES list
+/// `collapse_values` collapsed, present only when it did
+pub fn values_rows_metadata(rows: Option<usize>) -> Option<(String, MetaValue)> {
+    rows.map(|rows| ("values_rows".to_string(), MetaValue::Int(rows as i64)))
+}
+
+/// Renumbers the placeholders generated from `placeholder`, in the order the
+/// literal pass numbered them
+struct Renumber<'a> {
+    placeholder: &'a str,
+    start: usize,
+    next: usize,
+}
+
+impl Renumber<'_> {
+    /// Whether `text` is `placeholder` numbered from `start` on
+    fn generated(&self, text: &str) -> bool {
+        placeholder_number(self.placeholder, text).is_some_and(|n| n >= self.start)
+    }
+}
+
+impl VisitorMut for Renumber<'_> {
+    type Break = ();
+
+    fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<()> {
+        if let Expr::Value(ref mut value) = expr {
+            if let Value::Placeholder(ref mut text) = value.value {
+                if self.generated(text) {
+                    *text = placeholder_text(self.placeholder, self.next);
+                    self.next += 1;
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Collapse the value lists of `stmt` in place; false if there were none to
+/// collapse. `placeholder` and `start` are those the literals were replaced with.
+pub fn collapse_lists(stmt: &mut Statement, placeholder: &str, start: usize) -> bool {
+    let mut pass = Collapse { changed: false };
+    let _ = stmt.visit(&mut pass);
+    if pass.changed && placeholder.contains("{n}") {
+        let _ = stmt.visit(&mut Renumber { placeholder, start, next: start });
+    }
+    pass.changed
+}
+
+/// Collapse the IN lists of constants of `stmt` to a single item in place;
+/// false if there were none longer than that. `placeholder` and `start` are
+/// those the literals were replaced with.
+pub fn collapse_in_lists(stmt: &mut Statement, placeholder: &str, start: usize) -> bool {
+    let mut pass = CollapseConstants { changed: false };
+    let _ = stmt.visit(&mut pass);
+    if pass.changed && placeholder.contains("{n}") {
+        let _ = stmt.visit(&mut Renumber { placeholder, start, next: start });
+    }
+    pass.changed
+}
+
+/// Collapse the VALUES rows of `stmt` to the first in place, when they
+/// differ from it only in their placeholders; the number of rows of the first list collapsed, if any.
+/// `placeholder` and `start` are those the literals were replaced with.
+pub fn collapse_values(stmt: &mut Statement, placeholder: &str, start: usize) -> Option<usize> {
+    let mut pass = CollapseRows { rows: None };
+    let _ = stmt.visit(&mut pass);
+    if pass.rows.is_some() && placeholder.contains("{n}") {
+        let _ = stmt.visit(&mut Renumber { placeholder, start, next: start });
+    }
+    pass.rows
+}
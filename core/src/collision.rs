@@ -0,0 +1,140 @@
+//! Placeholders that the statement itself spells, for `placeholder_collision`.
+//!
+//! With `placeholder="?"`, `SELECT "?" FROM t WHERE data ? 'key'` normalizes to
+//! `SELECT "?" FROM t WHERE data ? ?`: the placeholder also stands for a column
+//! name and an operator, and rebinding `params` into the template goes wrong.
+//! Every placeholder node of the statement, bind or literal, is written as is,
+//! so the occurrences of the placeholder that no placeholder node accounts
+//! for are those of identifiers, literals left in place and operators.
+//! Numbered placeholders (`"${n}"`) occur wherever their prefix is followed by
+//! a number and their suffix.
+
+use core::ops::ControlFlow;
+
+use sqlparser::ast::{visit_expressions, visit_expressions_mut, Expr, Statement, Value, ValueWithSpan};
+
+use crate::writer;
+
+/// What to do when the statement collides with the placeholder
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Collision {
+    /// Count the collisions in `metadata["placeholder_collisions"]`
+    Report,
+    /// Raise `PlaceholderCollisionError`
+    Raise,
+    /// Use `placeholder + "{n}"` instead, decided before the hooks run
+    Numbered,
+    /// Double the first character of the statement's own occurrences
+    Escape,
+}
+
+/// The `placeholder_collision` option
+pub fn mode(name: &str) -> Result<Collision, String> {
+    match name {
+        "report" => Ok(Collision::Report),
+        "raise" => Ok(Collision::Raise),
+        "numbered" => Ok(Collision::Numbered),
+        "escape" => Ok(Collision::Escape),
+        _ => Err(format!("Unsupported placeholder_collision: {}", name)),
+    }
+}
+
+/// The numbered variant of `placeholder`, `None` if it is numbered already
+pub fn numbered(placeholder: &str) -> Option<String> {
+    (!placeholder.contains("{n}")).then(|| format!("{}{{n}}", placeholder))
+}
+
+/// Byte offsets of the non-overlapping occurrences of `placeholder` in `text`
+fn occurrences(placeholder: &str, text: &str) -> Vec<usize> {
+    let mut out = Vec::new();
+    each_occurrence(placeholder, text, |at| out.push(at));
+    out
+}
+
+/// The number of `occurrences()`, without collecting them
+fn count(placeholder: &str, text: &str) -> usize {
+    let mut count = 0;
+    each_occurrence(placeholder, text, |_| count += 1);
+    count
+}
+
+/// Call `found` with the byte offset of each non-overlapping occurrence of
+/// `placeholder` in `text`
+fn each_occurrence(placeholder: &str, text: &str, mut found: impl FnMut(usize)) {
+    let Some((prefix, suffix)) = placeholder.split_once("{n}") else {
+        if !placeholder.is_empty() {
+            text.match_indices(placeholder).for_each(|(at, _)| found(at));
+        }
+        return;
+    };
+    let mut at = 0;
+    while at < text.len() {
+        let rest = &text[at..];
+        // Without a prefix, a number inside a longer one does not count
+        let starts = rest.starts_with(prefix) && (!prefix.is_empty() || !text[..at].ends_with(|c: char| c.is_ascii_digit()));
+        if starts {
+            let digits = rest[prefix.len()..].find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len() - prefix.len());
+            let len = prefix.len() + digits + suffix.len();
+            if digits > 0 && rest[prefix.len() + digits..].starts_with(suffix) {
+                found(at);
+                at += len;
+                continue;
+            }
+        }
+        at += rest.chars().next().map_or(1, char::len_utf8);
+    }
+}
+
+/// The occurrences of `placeholder` in `normalized`, the writing of `stmt`,
+/// that are not placeholder nodes of `stmt`
+pub fn collisions(stmt: &Statement, normalized: &str, placeholder: &str) -> usize {
+    let mut own = 0;
+    let _ = visit_expressions(stmt, |expr| {
+        if let Expr::Value(ValueWithSpan { value: Value::Placeholder(text), .. }) = expr {
+            own += count(placeholder, text);
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    count(placeholder, normalized).saturating_sub(own)
+}
+
+/// The writing of `stmt` with the first character of each occurrence of
+/// `placeholder` outside its placeholder nodes doubled: `??` for `?`, `%%s`
+/// for `%s`
+pub fn escape(stmt: &Statement, placeholder: &str) -> String {
+    // Placeholder nodes are written as markers, their index spelled in
+    // letters so as not to look like a number, and put back once the rest is
+    // escaped
+    let mut marked = stmt.clone();
+    let mut texts = Vec::new();
+    let _ = visit_expressions_mut(&mut marked, |expr| {
+        if let Expr::Value(ValueWithSpan { value: Value::Placeholder(text), .. }) = expr {
+            let index: String = texts.len().to_string().bytes().map(|d| char::from(d - b'0' + b'a')).collect();
+            let marker = format!("\u{0}{}\u{0}", index);
+            texts.push(std::mem::replace(text, marker));
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    let written = writer::write(&marked);
+    let mut escaped = String::with_capacity(written.len() + 8);
+    let mut last = 0;
+    for at in occurrences(placeholder, &written) {
+        let first = written[at..].chars().next().expect("occurrences are not empty");
+        escaped.push_str(&written[last..at]);
+        escaped.push(first);
+        last = at;
+    }
+    escaped.push_str(&written[last..]);
+    let mut out = String::with_capacity(escaped.len());
+    let mut parts = escaped.split('\u{0}');
+    out.push_str(parts.next().unwrap_or_default());
+    while let (Some(index), Some(rest)) = (parts.next(), parts.next()) {
+        let digits: String = index.bytes().map(|l| char::from(l.wrapping_sub(b'a').wrapping_add(b'0'))).collect();
+        match digits.parse::<usize>().ok().and_then(|i| texts.get(i)) {
+            Some(text) => out.push_str(text),
+            None => out.push_str(index),
+        }
+        out.push_str(rest);
+    }
+    out
+}
@@ -0,0 +1,101 @@
+//! The columns a statement references, by clause, for `extract_columns()`.
+//!
+//! Clauses are told apart as for `param_clauses`: a column is in the clause of
+//! the innermost query or statement whose clause holds it, so the columns of a
+//! subquery in a `WHERE` are under its own `select`, `where` and so on. The
+//! columns of `JOIN ... USING (...)` are under `join`. Names are as written,
+//! qualified or not, each listed once per clause in order of appearance;
+//! aliases of the select list referenced in `ORDER BY` are not told apart from
+//! columns.
+
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{Expr, JoinConstraint, Query, SetExpr, Statement, TableFactor, Visit, Visitor};
+
+use crate::{booleans, join_constraint, Clause, ClauseRoots};
+
+#[derive(Default)]
+struct Collector {
+    roots: ClauseRoots,
+    clauses: Vec<Clause>,
+    columns: Vec<(Clause, Vec<String>)>,
+}
+
+impl Collector {
+    fn push(&mut self, clause: Clause, name: String) {
+        match self.columns.iter_mut().find(|(c, _)| *c == clause) {
+            Some((_, names)) => {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            None => self.columns.push((clause, vec![name])),
+        }
+    }
+}
+
+impl Visitor for Collector {
+    type Break = ();
+
+    fn pre_visit_statement(&mut self, stmt: &Statement) -> ControlFlow<()> {
+        self.roots.register_statement(stmt);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        self.roots.register_query(query);
+        if let SetExpr::Select(ref select) = *query.body {
+            for join in select.from.iter().flat_map(|twj| &twj.joins) {
+                if let Some(JoinConstraint::Using(names)) = join_constraint(&join.join_operator) {
+                    for name in names {
+                        self.push(Clause::Join, name.to_string());
+                    }
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, _tf: &TableFactor) -> ControlFlow<()> {
+        self.clauses.push(Clause::From);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_table_factor(&mut self, _tf: &TableFactor) -> ControlFlow<()> {
+        self.clauses.pop();
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<()> {
+        if let Some(clause) = self.roots.get(expr) {
+            self.clauses.push(clause);
+        }
+        let clause = self.clauses.last().copied().unwrap_or(Clause::Other);
+        match expr {
+            // TRUE/FALSE, identifiers in some dialects
+            Expr::Identifier(ident) if booleans::identifier_boolean(ident).is_some() => {}
+            Expr::Identifier(ident) => self.push(clause, ident.to_string()),
+            Expr::CompoundIdentifier(idents) => {
+                let name = idents.iter().map(ToString::to_string).collect::<Vec<_>>().join(".");
+                self.push(clause, name);
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_expr(&mut self, expr: &Expr) -> ControlFlow<()> {
+        if self.roots.get(expr).is_some() {
+            self.clauses.pop();
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Clause name → the columns `stmt` references there, clauses in order of
+/// their first column
+pub fn columns(stmt: &Statement) -> Vec<(&'static str, Vec<String>)> {
+    let mut collector = Collector::default();
+    let _ = stmt.visit(&mut collector);
+    collector.columns.into_iter().map(|(clause, names)| (clause.as_str(), names)).collect()
+}
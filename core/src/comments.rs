@@ -0,0 +1,186 @@
+//! Comment removal for `strip_comments()`, and the comments of a statement
+//! for the `comments` and `keep_hints` options.
+//!
+//! Comments are found on the token stream, so `--` or `/*` inside string
+//! literals and quoted identifiers are left alone, and what counts as a
+//! comment (`#` in MySQL, nesting of block comments) follows the dialect.
+//! MySQL `/*! */` hints are executed by the server, and the tokenizer hands
+//! them through as SQL rather than as comments, so they are kept. Everything
+//! else is copied through byte for byte.
+//!
+//! sqlparser drops comments, and with them the optimizer hints of the
+//! dialects it does not read hints for (`/*+ SeqScan(t) */ SELECT ...` of
+//! PostgreSQL's pg_hint_plan). `comments=True` reports them in
+//! `metadata["comments"]`, and the hints among them in `metadata["hints"]`.
+//! `keep_hints=True` writes the hints sqlparser dropped back into
+//! `normalized`, where they stood, so that hinted and unhinted variants get
+//! different fingerprints.
+
+use sqlparser::dialect::Dialect;
+use sqlparser::keywords::Keyword;
+use sqlparser::tokenizer::{Token, Tokenizer, Whitespace};
+
+use crate::metadata::MetaValue;
+use crate::paramstyle::token_offsets;
+
+/// `sql` without its comments. A line comment leaves its line break behind,
+/// and a block comment between two tokens a space, so that no tokens are
+/// joined.
+pub fn strip(sql: &str, dialect: &dyn Dialect) -> Result<String, String> {
+    let tokens = Tokenizer::new(dialect, sql)
+        .with_unescape(false)
+        .tokenize_with_location()
+        .map_err(|e| format!("Tokenize error: {}", e))?;
+    let offsets = token_offsets(sql, &tokens, dialect);
+    let end_of = |i: usize| offsets.get(i).copied().unwrap_or(sql.len());
+
+    let mut out = String::with_capacity(sql.len());
+    for (i, token) in tokens.iter().enumerate() {
+        match &token.token {
+            Token::Whitespace(Whitespace::SingleLineComment { comment, .. }) => {
+                if comment.ends_with("\r\n") {
+                    out.push_str("\r\n");
+                } else if comment.ends_with('\n') {
+                    out.push('\n');
+                }
+            }
+            Token::Whitespace(Whitespace::MultiLineComment(_)) => {
+                let next = sql[end_of(i + 1)..].chars().next();
+                let before = out.chars().next_back();
+                if before.is_some_and(|c| !c.is_whitespace()) && next.is_some_and(|c| !c.is_whitespace()) {
+                    out.push(' ');
+                }
+            }
+            _ => out.push_str(&sql[end_of(i)..end_of(i + 1)]),
+        }
+    }
+    Ok(out)
+}
+
+/// An optimizer hint, `/*+ ... */` or `--+ ...`
+pub struct Hint {
+    /// What follows the `+`, its whitespace collapsed
+    text: String,
+    /// The last keyword before the hint, and how many times it occurs
+    /// before that one; `None` for a hint leading the statement
+    anchor: Option<(Keyword, usize)>,
+}
+
+/// The text of `comment` if it is a hint
+fn hint_text(comment: &str) -> Option<String> {
+    comment.strip_prefix('+').map(|hint| hint.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// The comments of a statement
+#[derive(Default)]
+pub struct Comments {
+    /// Their text without delimiters and surrounding whitespace, hints included
+    comments: Vec<String>,
+    hints: Vec<Hint>,
+}
+
+/// The comments of `sql`; none if it does not tokenize
+pub fn extract(sql: &str, dialect: &dyn Dialect) -> Comments {
+    let Ok(tokens) = Tokenizer::new(dialect, sql).with_unescape(false).tokenize() else {
+        return Comments::default();
+    };
+    let mut out = Comments::default();
+    let mut seen: Vec<Keyword> = Vec::new();
+    for token in &tokens {
+        let comment = match token {
+            Token::Whitespace(Whitespace::SingleLineComment { comment, .. } | Whitespace::MultiLineComment(comment)) => {
+                comment
+            }
+            Token::Whitespace(_) => continue,
+            Token::Word(word) if word.quote_style.is_none() && word.keyword != Keyword::NoKeyword => {
+                seen.push(word.keyword);
+                continue;
+            }
+            _ => continue,
+        };
+        out.comments.push(comment.trim().to_string());
+        if let Some(text) = hint_text(comment) {
+            let anchor = seen.last().map(|keyword| (*keyword, seen.iter().filter(|k| *k == keyword).count() - 1));
+            out.hints.push(Hint { text, anchor });
+        }
+    }
+    out
+}
+
+impl Comments {
+    /// `metadata["comments"]` and `metadata["hints"]`, each left out when empty
+    pub fn metadata(&self) -> Vec<(String, MetaValue)> {
+        let mut metadata = Vec::new();
+        if !self.comments.is_empty() {
+            let comments = self.comments.iter().map(|comment| MetaValue::Str(comment.clone())).collect();
+            metadata.push(("comments".to_string(), MetaValue::List(comments)));
+        }
+        if !self.hints.is_empty() {
+            let hints = self.hints.iter().map(|hint| MetaValue::Str(hint.text.clone())).collect();
+            metadata.push(("hints".to_string(), MetaValue::List(hints)));
+        }
+        metadata
+    }
+
+    /// `sql` with the hints it lacks written back, each after the occurrence
+    /// of the last keyword before it; hints whose keyword is gone are dropped.
+    /// sqlparser keeps the hints of the dialects that support them after
+    /// `SELECT`, `INSERT`, `UPDATE`, `DELETE`, `REPLACE` and `MERGE`, and
+    /// those are not written twice.
+    pub fn attach(&self, sql: &str, dialect: &dyn Dialect) -> String {
+        if self.hints.is_empty() {
+            return sql.to_string();
+        }
+        let Ok(tokens) = Tokenizer::new(dialect, sql).with_unescape(false).tokenize_with_location() else {
+            return sql.to_string();
+        };
+        let offsets = token_offsets(sql, &tokens, dialect);
+        let end_of = |i: usize| offsets.get(i + 1).copied().unwrap_or(sql.len());
+        let mut kept = Vec::new();
+        let mut keywords: Vec<(usize, Keyword)> = Vec::new();
+        for (i, token) in tokens.iter().enumerate() {
+            match &token.token {
+                Token::Whitespace(Whitespace::SingleLineComment { comment, .. } | Whitespace::MultiLineComment(comment)) => {
+                    kept.extend(hint_text(comment))
+                }
+                Token::Word(word) if word.quote_style.is_none() && word.keyword != Keyword::NoKeyword => {
+                    keywords.push((end_of(i), word.keyword))
+                }
+                _ => {}
+            }
+        }
+        let mut inserts: Vec<(usize, &str)> = Vec::new();
+        for hint in &self.hints {
+            if let Some(i) = kept.iter().position(|text| *text == hint.text) {
+                kept.remove(i);
+                continue;
+            }
+            let at = match hint.anchor {
+                None => Some(0),
+                Some((keyword, before)) => keywords.iter().filter(|(_, k)| *k == keyword).nth(before).map(|(end, _)| *end),
+            };
+            if let Some(at) = at {
+                inserts.push((at, &hint.text));
+            }
+        }
+        // Stable, so that hints at the same place keep their order
+        inserts.sort_by_key(|(at, _)| *at);
+        let mut out = String::with_capacity(sql.len() + inserts.iter().map(|(_, text)| text.len() + 8).sum::<usize>());
+        let mut copied = 0;
+        for (at, text) in inserts {
+            out.push_str(&sql[copied..at]);
+            if at == 0 {
+                out.push_str("/*+ ");
+                out.push_str(text);
+                out.push_str(" */ ");
+            } else {
+                out.push_str(" /*+ ");
+                out.push_str(text);
+                out.push_str(" */");
+            }
+            copied = at;
+        }
+        out.push_str(&sql[copied..]);
+        out
+    }
+}
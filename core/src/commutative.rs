@@ -0,0 +1,124 @@
+//! Commutative operands in a canonical order, for the opt-in
+//! `commutative-canonicalize` and `predicate-sort` passes.
+//!
+//! `WHERE 5 = id` and `WHERE id = 5`, or `a = 1 AND b = 2` and `b = 2 AND a
+//! = 1`, are one predicate written two ways. The operands of `=`, `<>`, `+`,
+//! `*`, `AND` and `OR` are put in order: literals last, the others by their
+//! text with literals masked, so that the order does not depend on the
+//! values. A chain of one operator (`a AND b AND c`) is ordered as a whole.
+//! `<`, `<=`, `>` and `>=` have their operands ordered the same way, the
+//! operator flipped when they are swapped. `+` keeps its order where it
+//! concatenates strings (MSSQL).
+//!
+//! `predicate-sort` orders the `AND` and `OR` chains only, for ORMs that
+//! emit `WHERE a = ? AND b = ?` in a different order from one code path to
+//! the next, while leaving the operands of each comparison as written.
+
+use sqlparser::ast::{BinaryOperator, Expr, UnaryOperator, Value};
+
+use crate::case::shape;
+use crate::precedence::{self, Precedence};
+
+/// Whether `expr` is a literal or a bind, possibly signed
+fn is_literal(expr: &Expr) -> bool {
+    match expr {
+        Expr::Value(_) | Expr::TypedString(_) => true,
+        Expr::UnaryOp { op: UnaryOperator::Minus | UnaryOperator::Plus, expr } | Expr::Nested(expr) => is_literal(expr),
+        _ => false,
+    }
+}
+
+/// Where `expr` sorts among the operands
+fn key(expr: &Expr) -> (bool, String) {
+    (is_literal(expr), shape(expr).to_string())
+}
+
+/// The operator writing `a op b` as `b op a`, if there is one
+fn swapped(op: &BinaryOperator, plus_concat: bool) -> Option<BinaryOperator> {
+    Some(match op {
+        BinaryOperator::Eq | BinaryOperator::NotEq | BinaryOperator::Multiply | BinaryOperator::And | BinaryOperator::Or => op.clone(),
+        BinaryOperator::Plus if !plus_concat => BinaryOperator::Plus,
+        BinaryOperator::Lt => BinaryOperator::Gt,
+        BinaryOperator::Gt => BinaryOperator::Lt,
+        BinaryOperator::LtEq => BinaryOperator::GtEq,
+        BinaryOperator::GtEq => BinaryOperator::LtEq,
+        _ => return None,
+    })
+}
+
+/// `expr` without the parentheses around it
+fn unnest(mut expr: Expr) -> Expr {
+    while let Expr::Nested(inner) = expr {
+        expr = *inner;
+    }
+    expr
+}
+
+/// The operands of the chain of `op` that `expr` is, looking through
+/// parentheses when `parens` are the ones precedence requires only
+fn flatten(expr: Expr, op: &BinaryOperator, parens: bool, operands: &mut Vec<Expr>) {
+    let expr = if parens { unnest(expr) } else { expr };
+    match expr {
+        Expr::BinaryOp { left, op: ref chained, right } if chained == op => {
+            flatten(*left, op, parens, operands);
+            flatten(*right, op, parens, operands);
+        }
+        operand => operands.push(operand),
+    }
+}
+
+/// `left op right`, with the parentheses its operands need
+fn binary(left: Expr, op: BinaryOperator, right: Expr, precedence: Precedence) -> Expr {
+    let mut expr = Expr::BinaryOp { left: Box::new(left), op, right: Box::new(right) };
+    precedence::parenthesize(&mut expr, precedence);
+    expr
+}
+
+/// Order the operands of the chain of its operator that `expr` is. `parens`
+/// tells that the parentheses of the tree are those `precedence` requires,
+/// added by `paren-canonicalize`, to be dropped and added again as needed.
+fn sort_chain(expr: &mut Expr, parens: bool, precedence: Precedence) {
+    let Expr::BinaryOp { ref op, .. } = expr else {
+        return;
+    };
+    let op = op.clone();
+    let Expr::BinaryOp { left, right, .. } = std::mem::replace(expr, Expr::Value(Value::Null.into())) else {
+        unreachable!();
+    };
+    let mut operands = Vec::new();
+    flatten(*left, &op, parens, &mut operands);
+    flatten(*right, &op, parens, &mut operands);
+    operands.sort_by_cached_key(key);
+    let mut operands = operands.into_iter();
+    let first = operands.next().expect("a binary operator has operands");
+    *expr = operands.fold(first, |left, right| binary(left, op.clone(), right, precedence));
+}
+
+/// Order the conjuncts of `expr` if it is an `AND` chain, or its disjuncts
+/// if it is an `OR` chain
+pub fn sort_predicates(expr: &mut Expr, parens: bool, precedence: Precedence) {
+    if matches!(expr, Expr::BinaryOp { op: BinaryOperator::And | BinaryOperator::Or, .. }) {
+        sort_chain(expr, parens, precedence);
+    }
+}
+
+/// Order the operands of `expr`, if its operator is commutative; `parens`
+/// as for `sort_chain`
+pub fn canonicalize(expr: &mut Expr, plus_concat: bool, parens: bool, precedence: Precedence) {
+    let Expr::BinaryOp { ref op, .. } = expr else {
+        return;
+    };
+    let Some(flipped) = swapped(op, plus_concat) else {
+        return;
+    };
+    // These are associative as well, so a chain of them is one list
+    if matches!(op, BinaryOperator::And | BinaryOperator::Or | BinaryOperator::Plus | BinaryOperator::Multiply) {
+        return sort_chain(expr, parens, precedence);
+    }
+    let op = op.clone();
+    let Expr::BinaryOp { left, right, .. } = std::mem::replace(expr, Expr::Value(Value::Null.into())) else {
+        unreachable!();
+    };
+    let (left, right) = if parens { (unnest(*left), unnest(*right)) } else { (*left, *right) };
+    *expr = if key(&right) < key(&left) { binary(right, flipped, left, precedence) } else { binary(left, op, right, precedence) };
+}
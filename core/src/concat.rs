@@ -0,0 +1,91 @@
+//! Canonical string concatenation, for the opt-in `concat-canonicalize` pass.
+//!
+//! `a || b`, `CONCAT(a, b)` and MSSQL's `a + b` on strings are the same
+//! operation spelled per dialect. Calls to `CONCAT` with plain arguments are
+//! rewritten as `||` chains, and so is MSSQL's `+` when one of its operands is
+//! a string literal or already a concatenation, the only string context
+//! visible without types. Run post-order, so that `'a' + b + c` becomes
+//! `'a' || b || c` one operator at a time.
+
+use sqlparser::ast::{
+    BinaryOperator, Expr, FunctionArg, FunctionArgExpr, FunctionArguments, ObjectNamePart, Value,
+};
+
+use crate::canonicalize_parens;
+use crate::precedence::Precedence;
+
+/// The arguments of `expr` if it is a call to unqualified, unquoted `CONCAT` with at
+/// least two plain arguments and no other clause
+fn concat_args(expr: &mut Expr) -> Option<Vec<Expr>> {
+    let Expr::Function(func) = expr else {
+        return None;
+    };
+    let [ObjectNamePart::Identifier(name)] = func.name.0.as_slice() else {
+        return None;
+    };
+    if name.quote_style.is_some()
+        || !name.value.eq_ignore_ascii_case("CONCAT")
+        || func.uses_odbc_syntax
+        || !matches!(func.parameters, FunctionArguments::None)
+        || func.filter.is_some()
+        || func.null_treatment.is_some()
+        || func.over.is_some()
+        || !func.within_group.is_empty()
+    {
+        return None;
+    }
+    let FunctionArguments::List(list) = &mut func.args else {
+        return None;
+    };
+    if list.args.len() < 2 || list.duplicate_treatment.is_some() || !list.clauses.is_empty() {
+        return None;
+    }
+    let plain = |arg: &FunctionArg| matches!(arg, FunctionArg::Unnamed(FunctionArgExpr::Expr(_)));
+    if !list.args.iter().all(plain) {
+        return None;
+    }
+    Some(
+        list.args
+            .drain(..)
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(e)) => e,
+                _ => unreachable!("checked above"),
+            })
+            .collect(),
+    )
+}
+
+/// `left || right`, parenthesizing the operands precedence requires
+fn concat(left: Expr, right: Expr, precedence: Precedence) -> Expr {
+    let mut expr = Expr::BinaryOp { left: Box::new(left), op: BinaryOperator::StringConcat, right: Box::new(right) };
+    canonicalize_parens(&mut expr, precedence);
+    expr
+}
+
+/// Whether `expr` is a string for MSSQL's `+`: a string literal or a
+/// concatenation
+fn is_string(expr: &Expr) -> bool {
+    match expr {
+        Expr::Nested(inner) => is_string(inner),
+        Expr::Value(value) => matches!(
+            value.value,
+            Value::SingleQuotedString(_) | Value::NationalStringLiteral(_) | Value::DoubleQuotedString(_)
+        ),
+        Expr::BinaryOp { op: BinaryOperator::StringConcat, .. } => true,
+        _ => false,
+    }
+}
+
+/// Rewrite `expr` as a `||` chain if it is a concatenation; `plus_concat`
+/// when `+` concatenates strings (MSSQL)
+pub fn canonicalize(expr: &mut Expr, plus_concat: bool, precedence: Precedence) {
+    if let Some(args) = concat_args(expr) {
+        *expr = args.into_iter().reduce(|left, right| concat(left, right, precedence)).expect("at least two arguments");
+        return;
+    }
+    if let Expr::BinaryOp { left, op: op @ BinaryOperator::Plus, right } = expr {
+        if plus_concat && (is_string(left) || is_string(right)) {
+            *op = BinaryOperator::StringConcat;
+        }
+    }
+}
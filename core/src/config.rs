@@ -0,0 +1,286 @@
+//! Normalization settings loaded from TOML or JSON config files, and the
+//! process-wide defaults set through `configure()`.
+//!
+//! A config file holds the same keys as the `normalize()` arguments, either at
+//! the top level or, for `pyproject.toml`, under `[tool.sqlfp]`:
+//!
+//! ```toml
+//! dialect = "postgres"
+//! placeholder = "$"
+//! compat = "0.1"
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+use serde::Deserialize;
+
+use crate::pool::EngineKey;
+use crate::{builtins_only, canonical_dialect, collision, get_rules, hashing, identifier_case, pipeline, qualifiers, style_placeholder, synonyms, trailing, PassOrder};
+
+/// Settings left unset fall back to the argument defaults of `normalize()`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub dialect: Option<String>,
+    pub placeholder: Option<String>,
+    /// Number of the first placeholder, when `placeholder` contains `{n}`
+    pub placeholder_start: Option<usize>,
+    /// `"question"`, `"dollar"`, `"colon"` or `"pyformat"`, instead of `placeholder`
+    pub style: Option<String>,
+    pub compat: Option<String>,
+    pub templates: Option<bool>,
+    pub strict: Option<bool>,
+    pub encoding: Option<String>,
+    pub errors: Option<String>,
+    /// Pass names in the order to run them
+    pub passes: Option<Vec<String>>,
+    pub disable_passes: Option<Vec<String>>,
+    /// Opt-in passes to add to the default pipeline
+    pub enable_passes: Option<Vec<String>>,
+    /// `"upper"` or `"builtins"`
+    pub function_case: Option<String>,
+    /// `"preserve"`, `"lower"` or `"upper"`
+    pub identifier_case: Option<String>,
+    /// Add `metadata["clause_hashes"]` to results
+    pub clause_hashes: Option<bool>,
+    /// Depth beyond which subqueries are replaced by `SELECT ...`
+    pub max_subquery_depth: Option<usize>,
+    /// Columns whose literals go in `metadata["routing_keys"]`
+    pub routing_columns: Option<Vec<String>>,
+    /// `"report"`, `"raise"`, `"numbered"` or `"escape"`
+    pub placeholder_collision: Option<String>,
+    /// `"order_by"`, `"limit"` and `"offset"`, left out of the hashes
+    pub ignore_clauses: Option<Vec<String>>,
+    /// Columns of each table, to rewrite natural joins with
+    pub table_columns: Option<BTreeMap<String, Vec<String>>>,
+    /// Function names to rewrite, each to the one it is an alias of
+    pub function_synonyms: Option<BTreeMap<String, String>>,
+    /// Keep the literals in `params`
+    pub collect_params: Option<bool>,
+    /// Literals kept in `params` at most
+    pub max_params: Option<usize>,
+    /// Fingerprint the longest prefix that parses of statements that do not
+    pub truncated_input: Option<bool>,
+    /// Normalize statements that do not parse token by token
+    pub lenient: Option<bool>,
+    /// Collapse IN lists of constants to a single placeholder
+    pub collapse_in_lists: Option<bool>,
+    /// Collapse VALUES rows of the same shape to the first
+    pub collapse_values: Option<bool>,
+    /// Leave the literals of `LIMIT` as written
+    pub keep_limit: Option<bool>,
+    /// Leave the literals of `OFFSET` as written
+    pub keep_offset: Option<bool>,
+    /// Leave `NULL` as written
+    pub keep_null: Option<bool>,
+    /// `true` to strip every table qualifier, or the schemas to strip
+    pub strip_qualifiers: Option<qualifiers::Strip>,
+    /// Rename aliases to positional names
+    pub ignore_aliases: Option<bool>,
+    /// Rename tables, columns and schemas to `t1`, `c1`, `s1`, …
+    pub anonymize: Option<bool>,
+    /// Add `metadata["comments"]` and `metadata["hints"]`
+    pub comments: Option<bool>,
+    /// Write optimizer hints back into `normalized`
+    pub keep_hints: Option<bool>,
+    /// Fingerprint the statement an `EXPLAIN` wraps instead
+    pub unwrap_explain: Option<bool>,
+    /// Write the literals' placeholders in the order of `params`
+    pub prepared: Option<bool>,
+    /// `"sha256"`, `"xxhash64"` or `"blake3"`
+    pub hash_algorithm: Option<String>,
+    /// `"hex"`, `"base64"` or `"int"`
+    pub hash_encoding: Option<String>,
+}
+
+impl Config {
+    /// Check the values that can be checked up front, so that a bad config
+    /// fails when loaded rather than on the first `normalize()` call
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(ref dialect) = self.dialect {
+            canonical_dialect(dialect)?;
+        }
+        if let Some(ref function_case) = self.function_case {
+            builtins_only(function_case)?;
+        }
+        if let Some(ref identifier_case) = self.identifier_case {
+            identifier_case::mode(identifier_case)?;
+        }
+        style_placeholder(self.placeholder.as_deref(), self.style.as_deref())?;
+        if let Some(ref placeholder_collision) = self.placeholder_collision {
+            collision::mode(placeholder_collision)?;
+        }
+        hashing::Hasher::new(self.hash_algorithm.as_deref(), self.hash_encoding.as_deref())?;
+        if let Some(ref ignore_clauses) = self.ignore_clauses {
+            trailing::parse(ignore_clauses)?;
+        }
+        if let Some(ref function_synonyms) = self.function_synonyms {
+            synonyms::validate(function_synonyms)?;
+        }
+        let rules = get_rules(self.compat.as_deref())?;
+        let order: Option<PassOrder> = self.passes.as_ref().map(|names| names.iter().cloned().map(Some).collect());
+        pipeline(
+            &rules,
+            order.as_ref(),
+            self.disable_passes.as_deref().unwrap_or_default(),
+            self.enable_passes.as_deref().unwrap_or_default(),
+        )?;
+        Ok(())
+    }
+
+    /// `other`'s settings, falling back to ours where it leaves them unset
+    pub fn merged(&self, other: &Config) -> Config {
+        Config {
+            dialect: other.dialect.clone().or_else(|| self.dialect.clone()),
+            // Either sets the placeholder, so `other` setting one unsets the other
+            placeholder: if other.style.is_some() { None } else { other.placeholder.clone().or_else(|| self.placeholder.clone()) },
+            style: if other.placeholder.is_some() { None } else { other.style.clone().or_else(|| self.style.clone()) },
+            placeholder_start: other.placeholder_start.or(self.placeholder_start),
+            compat: other.compat.clone().or_else(|| self.compat.clone()),
+            templates: other.templates.or(self.templates),
+            strict: other.strict.or(self.strict),
+            encoding: other.encoding.clone().or_else(|| self.encoding.clone()),
+            errors: other.errors.clone().or_else(|| self.errors.clone()),
+            passes: other.passes.clone().or_else(|| self.passes.clone()),
+            disable_passes: other.disable_passes.clone().or_else(|| self.disable_passes.clone()),
+            enable_passes: other.enable_passes.clone().or_else(|| self.enable_passes.clone()),
+            function_case: other.function_case.clone().or_else(|| self.function_case.clone()),
+            identifier_case: other.identifier_case.clone().or_else(|| self.identifier_case.clone()),
+            clause_hashes: other.clause_hashes.or(self.clause_hashes),
+            max_subquery_depth: other.max_subquery_depth.or(self.max_subquery_depth),
+            routing_columns: other.routing_columns.clone().or_else(|| self.routing_columns.clone()),
+            placeholder_collision: other.placeholder_collision.clone().or_else(|| self.placeholder_collision.clone()),
+            ignore_clauses: other.ignore_clauses.clone().or_else(|| self.ignore_clauses.clone()),
+            table_columns: other.table_columns.clone().or_else(|| self.table_columns.clone()),
+            function_synonyms: other.function_synonyms.clone().or_else(|| self.function_synonyms.clone()),
+            collect_params: other.collect_params.or(self.collect_params),
+            max_params: other.max_params.or(self.max_params),
+            truncated_input: other.truncated_input.or(self.truncated_input),
+            lenient: other.lenient.or(self.lenient),
+            collapse_in_lists: other.collapse_in_lists.or(self.collapse_in_lists),
+            collapse_values: other.collapse_values.or(self.collapse_values),
+            keep_limit: other.keep_limit.or(self.keep_limit),
+            keep_offset: other.keep_offset.or(self.keep_offset),
+            keep_null: other.keep_null.or(self.keep_null),
+            strip_qualifiers: other.strip_qualifiers.clone().or_else(|| self.strip_qualifiers.clone()),
+            ignore_aliases: other.ignore_aliases.or(self.ignore_aliases),
+            anonymize: other.anonymize.or(self.anonymize),
+            comments: other.comments.or(self.comments),
+            keep_hints: other.keep_hints.or(self.keep_hints),
+            unwrap_explain: other.unwrap_explain.or(self.unwrap_explain),
+            prepared: other.prepared.or(self.prepared),
+            hash_algorithm: other.hash_algorithm.clone().or_else(|| self.hash_algorithm.clone()),
+            hash_encoding: other.hash_encoding.clone().or_else(|| self.hash_encoding.clone()),
+        }
+    }
+
+    /// The options of `normalize()` these settings give, falling back to
+    /// `defaults` where they are unset, then to the built-in defaults
+    pub fn options(&self, defaults: &Config) -> Result<EngineKey, String> {
+        let passes = self.passes.as_ref().or(defaults.passes.as_ref()).map(|names| names.iter().cloned().map(Some).collect());
+        let mut disabled_passes = self.disable_passes.clone().or_else(|| defaults.disable_passes.clone()).unwrap_or_default();
+        disabled_passes.sort();
+        disabled_passes.dedup();
+        let mut enabled_passes = self.enable_passes.clone().or_else(|| defaults.enable_passes.clone()).unwrap_or_default();
+        enabled_passes.sort();
+        enabled_passes.dedup();
+        let mut ignore_clauses = self.ignore_clauses.clone().or_else(|| defaults.ignore_clauses.clone()).unwrap_or_default();
+        ignore_clauses.sort();
+        ignore_clauses.dedup();
+        // Table names are matched up to case
+        let table_columns = self
+            .table_columns
+            .clone()
+            .or_else(|| defaults.table_columns.clone())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(table, columns)| (table.to_lowercase(), columns))
+            .collect();
+        // As are function names
+        let function_synonyms = self
+            .function_synonyms
+            .clone()
+            .or_else(|| defaults.function_synonyms.clone())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(alias, canonical)| (alias.to_lowercase(), canonical))
+            .collect();
+        let placeholder = style_placeholder(self.placeholder.as_deref(), self.style.as_deref())?
+            .or(style_placeholder(defaults.placeholder.as_deref(), defaults.style.as_deref())?);
+        Ok(EngineKey {
+            dialect: self.dialect.as_deref().or(defaults.dialect.as_deref()).unwrap_or("generic").to_string(),
+            placeholder: placeholder.unwrap_or("?").to_string(),
+            placeholder_start: self.placeholder_start.or(defaults.placeholder_start).unwrap_or(1),
+            compat: self.compat.as_deref().or(defaults.compat.as_deref()).map(str::to_string),
+            templates: self.templates.or(defaults.templates).unwrap_or(false),
+            strict: self.strict.or(defaults.strict).unwrap_or(false),
+            passes,
+            disabled_passes,
+            enabled_passes,
+            function_case: self.function_case.as_deref().or(defaults.function_case.as_deref()).unwrap_or("upper").to_string(),
+            identifier_case: self.identifier_case.as_deref().or(defaults.identifier_case.as_deref()).unwrap_or("preserve").to_string(),
+            clause_hashes: self.clause_hashes.or(defaults.clause_hashes).unwrap_or(false),
+            max_subquery_depth: self.max_subquery_depth.or(defaults.max_subquery_depth),
+            routing_columns: self.routing_columns.clone().or_else(|| defaults.routing_columns.clone()).unwrap_or_default(),
+            placeholder_collision: self.placeholder_collision.as_deref().or(defaults.placeholder_collision.as_deref()).unwrap_or("report").to_string(),
+            ignore_clauses,
+            table_columns,
+            function_synonyms,
+            collect_params: self.collect_params.or(defaults.collect_params).unwrap_or(true),
+            max_params: self.max_params.or(defaults.max_params),
+            truncated_input: self.truncated_input.or(defaults.truncated_input).unwrap_or(false),
+            lenient: self.lenient.or(defaults.lenient).unwrap_or(false),
+            collapse_in_lists: self.collapse_in_lists.or(defaults.collapse_in_lists).unwrap_or(false),
+            collapse_values: self.collapse_values.or(defaults.collapse_values).unwrap_or(false),
+            keep_limit: self.keep_limit.or(defaults.keep_limit).unwrap_or(false),
+            keep_offset: self.keep_offset.or(defaults.keep_offset).unwrap_or(false),
+            keep_null: self.keep_null.or(defaults.keep_null).unwrap_or(true),
+            strip_qualifiers: self.strip_qualifiers.clone().or_else(|| defaults.strip_qualifiers.clone()).unwrap_or_default().canonical(),
+            ignore_aliases: self.ignore_aliases.or(defaults.ignore_aliases).unwrap_or(false),
+            anonymize: self.anonymize.or(defaults.anonymize).unwrap_or(false),
+            comments: self.comments.or(defaults.comments).unwrap_or(false),
+            keep_hints: self.keep_hints.or(defaults.keep_hints).unwrap_or(false),
+            unwrap_explain: self.unwrap_explain.or(defaults.unwrap_explain).unwrap_or(false),
+            prepared: self.prepared.or(defaults.prepared).unwrap_or(false),
+            hash_algorithm: self.hash_algorithm.as_deref().or(defaults.hash_algorithm.as_deref()).unwrap_or("sha256").to_string(),
+            hash_encoding: self.hash_encoding.as_deref().or(defaults.hash_encoding.as_deref()).unwrap_or("hex").to_string(),
+        })
+    }
+}
+
+/// Parse and validate the contents of the config file at `path`; the format
+/// is picked by its extension
+pub fn parse(path: &Path, text: &str) -> Result<Config, String> {
+    let invalid = |e: &dyn std::fmt::Display| format!("Invalid config file {}: {}", path.display(), e);
+    let config: Config = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => {
+            let doc: toml::Table = toml::from_str(text).map_err(|e| invalid(&e))?;
+            let tool = doc.get("tool").and_then(|t| t.get("sqlfp")).and_then(|s| s.as_table());
+            tool.cloned().unwrap_or(doc).try_into().map_err(|e| invalid(&e))?
+        }
+        Some("json") => {
+            let doc: serde_json::Value = serde_json::from_str(text).map_err(|e| invalid(&e))?;
+            let tool = doc.get("tool").and_then(|t| t.get("sqlfp")).cloned();
+            serde_json::from_value(tool.unwrap_or(doc)).map_err(|e| invalid(&e))?
+        }
+        _ => return Err(format!("Unsupported config file format: {}", path.display())),
+    };
+    config.validate().map_err(|e| invalid(&e))?;
+    Ok(config)
+}
+
+fn defaults_lock() -> &'static RwLock<Arc<Config>> {
+    static DEFAULTS: OnceLock<RwLock<Arc<Config>>> = OnceLock::new();
+    DEFAULTS.get_or_init(Default::default)
+}
+
+/// The process-wide defaults
+pub fn defaults() -> Arc<Config> {
+    Arc::clone(&defaults_lock().read().unwrap_or_else(PoisonError::into_inner))
+}
+
+pub fn set_defaults(config: Config) {
+    *defaults_lock().write().unwrap_or_else(PoisonError::into_inner) = Arc::new(config);
+}
@@ -0,0 +1,88 @@
+//! Sample collection and manifest output for `snapshot_corpus()`.
+//!
+//! A corpus is either a directory, where every `*.sql` file below it is one
+//! sample, or a single file holding one sample per non-blank line. Samples are
+//! listed in a fixed order (paths sorted, `/`-separated, then line numbers) so
+//! that the manifest is byte-for-byte reproducible for the same input.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::writer::FINGERPRINT_VERSION;
+
+/// One SQL sample: where it came from and its raw bytes
+pub struct Sample {
+    pub id: String,
+    pub bytes: Vec<u8>,
+}
+
+/// The samples of the corpus at `path`, in manifest order
+pub fn samples(path: &Path) -> io::Result<Vec<Sample>> {
+    if !path.is_dir() {
+        let name = path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+        return Ok(lines(&name, &fs::read(path)?));
+    }
+    let mut files = Vec::new();
+    sql_files(path, "", &mut files)?;
+    files.sort();
+    files
+        .into_iter()
+        .map(|id| Ok(Sample { bytes: fs::read(path.join(&id))?, id }))
+        .collect()
+}
+
+/// Relative paths of the `*.sql` files below `dir`, prefixed with `prefix`
+fn sql_files(dir: &Path, prefix: &str, out: &mut Vec<String>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let id = format!("{}{}", prefix, name);
+        if entry.file_type()?.is_dir() {
+            sql_files(&entry.path(), &format!("{}/", id), out)?;
+        } else if name.ends_with(".sql") {
+            out.push(id);
+        }
+    }
+    Ok(())
+}
+
+/// One sample per non-blank line of `bytes`, identified as `name:line`
+fn lines(name: &str, bytes: &[u8]) -> Vec<Sample> {
+    bytes
+        .split(|&b| b == b'\n')
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.strip_suffix(b"\r").unwrap_or(line)))
+        .filter(|(_, line)| !line.iter().all(u8::is_ascii_whitespace))
+        .map(|(n, line)| Sample { id: format!("{}:{}", name, n), bytes: line.to_vec() })
+        .collect()
+}
+
+/// Outcome of normalizing one sample
+pub enum Entry {
+    Normalized { normalized: String, hash: String },
+    Failed(String),
+}
+
+/// The manifest for `entries`, as pretty-printed JSON with sorted keys
+pub fn manifest(dialect: &str, compat: Option<&str>, entries: Vec<(String, Entry)>) -> String {
+    let samples: Vec<Value> = entries
+        .into_iter()
+        .map(|(sample, entry)| match entry {
+            Entry::Normalized { normalized, hash } => json!({ "sample": sample, "normalized": normalized, "hash": hash }),
+            Entry::Failed(error) => json!({ "sample": sample, "error": error }),
+        })
+        .collect();
+    let doc = json!({
+        "sqlfp_version": env!("CARGO_PKG_VERSION"),
+        "fingerprint_version": FINGERPRINT_VERSION,
+        "dialect": dialect,
+        "compat": compat,
+        "samples": samples,
+    });
+    let mut out = serde_json::to_string_pretty(&doc).expect("manifest is valid JSON");
+    out.push('\n');
+    out
+}
@@ -0,0 +1,166 @@
+//! Detection of constructs the normalizer passed through untouched.
+//!
+//! Runs on the statement *after* normalization and looks for leftovers of the
+//! enabled rules (explicit aliases, long join keywords, explicit ASC, default
+//! NULLS ordering, `SELECT ALL`). Anything found sits in a part of the tree
+//! the structural pass does not reach, and is a likely cause of fingerprint
+//! splits. Statement kinds the structural pass does not handle at all are
+//! reported as well.
+
+use core::ops::ControlFlow;
+use sqlparser::ast::{
+    Distinct, Expr, FunctionArgumentClause, FunctionArguments, JoinOperator, NamedWindowExpr, OrderByExpr,
+    OrderByKind, Query, SetExpr, Statement, TableAlias, TableFactor, Visit, Visitor,
+};
+
+use crate::ast::variant_name;
+use crate::{default_nulls, Rules};
+
+pub fn table_alias(tf: &TableFactor) -> Option<&TableAlias> {
+    match tf {
+        TableFactor::Table { alias, .. }
+        | TableFactor::Derived { alias, .. }
+        | TableFactor::TableFunction { alias, .. }
+        | TableFactor::Function { alias, .. }
+        | TableFactor::UNNEST { alias, .. }
+        | TableFactor::JsonTable { alias, .. }
+        | TableFactor::OpenJsonTable { alias, .. }
+        | TableFactor::NestedJoin { alias, .. }
+        | TableFactor::Pivot { alias, .. }
+        | TableFactor::Unpivot { alias, .. }
+        | TableFactor::MatchRecognize { alias, .. }
+        | TableFactor::XmlTable { alias, .. }
+        | TableFactor::SemanticView { alias, .. } => alias.as_ref(),
+    }
+}
+
+fn long_join_keyword(op: &JoinOperator) -> Option<&'static str> {
+    match op {
+        JoinOperator::Inner(_) => Some("INNER JOIN"),
+        JoinOperator::LeftOuter(_) => Some("LEFT OUTER JOIN"),
+        JoinOperator::RightOuter(_) => Some("RIGHT OUTER JOIN"),
+        _ => None,
+    }
+}
+
+struct Leftovers<'a> {
+    rules: &'a Rules,
+    /// Kinds of the enclosing statements and table factors, innermost last
+    context: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl Leftovers<'_> {
+    /// Record `what` as left in `place`, or else in the innermost enclosing context
+    fn warn(&mut self, what: &str, place: Option<&str>) {
+        let place = place.or(self.context.last().map(String::as_str)).unwrap_or("statement");
+        let warning = format!("{} left in {}", what, place);
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    fn check_order_by(&mut self, exprs: &[OrderByExpr], place: Option<&str>) {
+        if self.rules.strip_default_asc && exprs.iter().any(|e| e.options.asc == Some(true)) {
+            self.warn("explicit ASC", place);
+        }
+        if let (true, Some(high)) = (self.rules.strip_default_nulls, self.rules.nulls_high) {
+            if exprs.iter().any(|e| default_nulls(&e.options, high)) {
+                self.warn("default NULLS ordering", place);
+            }
+        }
+    }
+
+    fn check_set_expr(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                if self.rules.strip_select_all && select.distinct == Some(Distinct::All) {
+                    self.warn("SELECT ALL", None);
+                }
+                if self.rules.join_keywords {
+                    for join in select.from.iter().flat_map(|twj| &twj.joins) {
+                        if let Some(keyword) = long_join_keyword(&join.join_operator) {
+                            self.warn(keyword, None);
+                        }
+                    }
+                }
+                for window in &select.named_window {
+                    if let NamedWindowExpr::WindowSpec(ref spec) = window.1 {
+                        self.check_order_by(&spec.order_by, Some("WINDOW clause"));
+                    }
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.check_set_expr(left);
+                self.check_set_expr(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Visitor for Leftovers<'_> {
+    type Break = ();
+
+    fn pre_visit_statement(&mut self, stmt: &Statement) -> ControlFlow<()> {
+        if !matches!(
+            stmt,
+            Statement::Query(_) | Statement::Insert(_) | Statement::Update(_) | Statement::Delete(_)
+        ) {
+            let warning = format!("statement {} is not structurally normalized", variant_name(stmt));
+            self.warnings.push(warning);
+        }
+        self.context.push(variant_name(stmt));
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_statement(&mut self, _stmt: &Statement) -> ControlFlow<()> {
+        self.context.pop();
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        self.check_set_expr(&query.body);
+        if let Some(ref order_by) = query.order_by {
+            if let OrderByKind::Expressions(ref exprs) = order_by.kind {
+                self.check_order_by(exprs, None);
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, tf: &TableFactor) -> ControlFlow<()> {
+        if self.rules.implicit_aliases && table_alias(tf).is_some_and(|a| a.explicit) {
+            self.warn("explicit table alias", None);
+        }
+        self.context.push(variant_name(tf));
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_table_factor(&mut self, _tf: &TableFactor) -> ControlFlow<()> {
+        self.context.pop();
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<()> {
+        if let Expr::Function(func) = expr {
+            let place = format!("{}()", func.name);
+            if let FunctionArguments::List(ref list) = func.args {
+                for clause in &list.clauses {
+                    if let FunctionArgumentClause::OrderBy(ref exprs) = clause {
+                        self.check_order_by(exprs, Some(&place));
+                    }
+                }
+            }
+            self.check_order_by(&func.within_group, Some(&place));
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Warnings for the parts of the normalized `stmt` that `rules` did not reach
+pub fn leftovers(stmt: &Statement, rules: &Rules) -> Vec<String> {
+    let mut pass = Leftovers { rules, context: Vec::new(), warnings: Vec::new() };
+    let _ = stmt.visit(&mut pass);
+    pass.warnings
+}
@@ -0,0 +1,116 @@
+//! Canonical CTE names, for the opt-in `cte-rename` pass.
+//!
+//! ORMs name their CTEs `cte0`, `anon_1`, … depending on their version, which
+//! would split otherwise identical `WITH` queries into several fingerprints.
+//! Every CTE is renamed to `cte_1`, `cte_2`, … in the order the `WITH` clauses
+//! are entered (outer ones before the ones nested in them), together with the
+//! references in its scope: table references, column qualifiers and qualified
+//! wildcards. Names are resolved as `scope` does, so shadowing CTEs keep
+//! referring to the right one and a table of the same name as a CTE, read in
+//! its own body, keeps its name.
+
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    Expr, Ident, ObjectName, ObjectNamePart, Query, SelectItem, SelectItemQualifiedWildcardKind, SetExpr,
+    Statement, VisitMut, VisitorMut,
+};
+
+use crate::scope::Scopes;
+
+/// Names are matched case-insensitively unless quoted
+pub fn key(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
+
+#[derive(Default)]
+struct Rename {
+    scopes: Scopes,
+    /// The new names of the CTEs of each `WITH` being visited
+    names: Vec<Vec<String>>,
+    /// Number of CTEs renamed so far
+    count: usize,
+}
+
+impl Rename {
+    fn rename(&self, ident: &mut Ident) {
+        if let Some((depth, i)) = self.scopes.resolve(ident) {
+            *ident = Ident::new(self.names[depth][i].clone());
+        }
+    }
+
+    /// Rename `name` if it is a single part naming a CTE
+    fn rename_single(&self, name: &mut ObjectName) {
+        if let [ObjectNamePart::Identifier(ident)] = name.0.as_mut_slice() {
+            self.rename(ident);
+        }
+    }
+
+    fn rename_wildcards(&self, body: &mut SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                for item in &mut select.projection {
+                    if let SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::ObjectName(name), _) = item {
+                        self.rename_single(name);
+                    }
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.rename_wildcards(left);
+                self.rename_wildcards(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl VisitorMut for Rename {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        self.scopes.enter(query);
+        if let Some(ref mut with) = query.with {
+            let mut names = Vec::with_capacity(with.cte_tables.len());
+            for cte in &mut with.cte_tables {
+                self.count += 1;
+                let name = format!("cte_{}", self.count);
+                cte.alias.name = Ident::new(name.clone());
+                names.push(name);
+            }
+            self.names.push(names);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        // All the CTEs of the query are visible in its body by now
+        self.rename_wildcards(&mut query.body);
+        if query.with.is_some() {
+            self.names.pop();
+        }
+        self.scopes.leave(query);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &mut ObjectName) -> ControlFlow<()> {
+        self.rename_single(relation);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<()> {
+        if let Expr::CompoundIdentifier(idents) = expr {
+            if let [qualifier, _] = idents.as_mut_slice() {
+                self.rename(qualifier);
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Rename the CTEs of `stmt` and their references in place
+pub fn rename_ctes(stmt: &mut Statement) {
+    let _ = stmt.visit(&mut Rename::default());
+}
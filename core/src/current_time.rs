@@ -0,0 +1,103 @@
+//! Canonical current-time functions, for the opt-in
+//! `current-time-canonicalize` pass.
+//!
+//! Schedulers and ORMs ask for the current time as `NOW()`, `SYSDATE()`,
+//! `GETDATE()` or `CURRENT_TIMESTAMP` depending on the dialect and the
+//! author, and for the current date as `CURDATE()` or `CURRENT_DATE`. Each
+//! spelling is rewritten as the standard `CURRENT_TIMESTAMP`, `CURRENT_DATE`
+//! or `CURRENT_TIME`, keeping a precision argument, so that the variants of
+//! one recurring job share a fingerprint; their offsets (`- INTERVAL 1 DAY`)
+//! are literals, parameterized as usual. Oracle's `SYSDATE` and `SYSTIMESTAMP`
+//! are taken as the functions when written as bare words.
+
+use sqlparser::ast::{
+    Expr, Function, FunctionArg, FunctionArgExpr, FunctionArguments, Ident, ObjectName, ObjectNamePart,
+};
+
+/// Current-time functions and the standard function they are rewritten as
+const SYNONYMS: &[(&str, &str)] = &[
+    ("CURRENT_DATETIME", "CURRENT_TIMESTAMP"),
+    ("CURRENT_TIMESTAMP", "CURRENT_TIMESTAMP"),
+    ("GETDATE", "CURRENT_TIMESTAMP"),
+    ("LOCALTIMESTAMP", "CURRENT_TIMESTAMP"),
+    ("NOW", "CURRENT_TIMESTAMP"),
+    ("SYSDATE", "CURRENT_TIMESTAMP"),
+    ("SYSDATETIME", "CURRENT_TIMESTAMP"),
+    ("SYSTIMESTAMP", "CURRENT_TIMESTAMP"),
+    ("TRANSACTION_TIMESTAMP", "CURRENT_TIMESTAMP"),
+    ("CURDATE", "CURRENT_DATE"),
+    ("CURRENT_DATE", "CURRENT_DATE"),
+    ("CURTIME", "CURRENT_TIME"),
+    ("CURRENT_TIME", "CURRENT_TIME"),
+];
+
+/// Current-time keywords Oracle accepts without parentheses
+const BARE_WORDS: &[&str] = &["SYSDATE", "SYSTIMESTAMP"];
+
+fn canonical_name(ident: &Ident) -> Option<&'static str> {
+    if ident.quote_style.is_some() {
+        return None;
+    }
+    SYNONYMS.iter().find(|(name, _)| ident.value.eq_ignore_ascii_case(name)).map(|&(_, canonical)| canonical)
+}
+
+/// A call of `name` with `args`, printed without parentheses when `args` is
+/// `FunctionArguments::None`
+fn call(name: &str, args: FunctionArguments) -> Function {
+    Function {
+        name: ObjectName::from(vec![Ident::new(name)]),
+        uses_odbc_syntax: false,
+        parameters: FunctionArguments::None,
+        args,
+        filter: None,
+        null_treatment: None,
+        over: None,
+        within_group: Vec::new(),
+    }
+}
+
+/// The arguments of a current-time call: none, or a single precision
+fn precision(args: &FunctionArguments) -> Option<FunctionArguments> {
+    match args {
+        FunctionArguments::None => Some(FunctionArguments::None),
+        FunctionArguments::List(list) if list.duplicate_treatment.is_none() && list.clauses.is_empty() => {
+            match list.args.as_slice() {
+                [] => Some(FunctionArguments::None),
+                [FunctionArg::Unnamed(FunctionArgExpr::Expr(_))] => Some(args.clone()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+pub fn canonicalize(expr: &mut Expr) {
+    match expr {
+        Expr::Function(func) => {
+            let [ObjectNamePart::Identifier(ident)] = func.name.0.as_slice() else {
+                return;
+            };
+            let Some(canonical) = canonical_name(ident) else {
+                return;
+            };
+            if func.uses_odbc_syntax
+                || !matches!(func.parameters, FunctionArguments::None)
+                || func.filter.is_some()
+                || func.null_treatment.is_some()
+                || func.over.is_some()
+                || !func.within_group.is_empty()
+            {
+                return;
+            }
+            if let Some(args) = precision(&func.args) {
+                *func = call(canonical, args);
+            }
+        }
+        Expr::Identifier(ident) if BARE_WORDS.iter().any(|word| ident.value.eq_ignore_ascii_case(word)) => {
+            if let Some(canonical) = canonical_name(ident) {
+                *expr = Expr::Function(call(canonical, FunctionArguments::None));
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,67 @@
+//! Subquery depth limit, for the `max_subquery_depth` option.
+//!
+//! Queries nested deeper than the limit are replaced by the opaque `SELECT
+//! ...` right after parsing, before any pass runs, so that adversarially
+//! nested statements normalize in time bounded by the limit rather than by
+//! their nesting. A subquery, CTE or `INSERT` source of the statement is at
+//! depth 1, a query inside one of those at depth 2, and so on.
+
+use std::ops::ControlFlow;
+use std::sync::OnceLock;
+
+use sqlparser::ast::{Expr, Query, SelectItem, SetExpr, Statement, Value, VisitMut, VisitorMut};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+
+/// `SELECT ...`, standing in for a query beyond the limit
+fn opaque() -> Query {
+    static OPAQUE: OnceLock<Query> = OnceLock::new();
+    OPAQUE
+        .get_or_init(|| {
+            let Ok(Some(Statement::Query(mut query))) =
+                Parser::parse_sql(&GenericDialect {}, "SELECT 1").map(|stmts| stmts.into_iter().next())
+            else {
+                unreachable!("SELECT 1 parses as a query");
+            };
+            if let SetExpr::Select(ref mut select) = *query.body {
+                select.projection =
+                    vec![SelectItem::UnnamedExpr(Expr::Value(Value::Placeholder("...".to_string()).into()))];
+            }
+            *query
+        })
+        .clone()
+}
+
+struct Limit {
+    max: usize,
+    /// Depth of the query about to be visited
+    depth: usize,
+    truncated: usize,
+}
+
+impl VisitorMut for Limit {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        if self.depth > self.max {
+            *query = opaque();
+            self.truncated += 1;
+        }
+        self.depth += 1;
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, _query: &mut Query) -> ControlFlow<()> {
+        self.depth -= 1;
+        ControlFlow::Continue(())
+    }
+}
+
+/// Replace the queries of `stmt` nested deeper than `max`; the number replaced
+pub fn limit(stmt: &mut Statement, max: usize) -> usize {
+    // The body of a query statement is depth 0, not a subquery
+    let depth = if matches!(stmt, Statement::Query(_)) { 0 } else { 1 };
+    let mut pass = Limit { max, depth, truncated: 0 };
+    let _ = stmt.visit(&mut pass);
+    pass.truncated
+}
@@ -0,0 +1,65 @@
+//! `dialect="auto"`, for traffic mixing engines with no way to tell where
+//! each statement comes from.
+//!
+//! Each statement is read with the first dialect of a fixed list that parses
+//! it, PostgreSQL first. Syntax only one engine has moves that engine to the
+//! front of the list: backquoted names are MySQL's, `::` casts and `$1`
+//! parameters PostgreSQL's, `SELECT TOP n` and `[name]` MSSQL's. A statement
+//! no dialect parses is read with the first one, so that it fails (or takes
+//! the `truncated_input` and `lenient` paths) as it would with that dialect.
+
+use sqlparser::dialect::GenericDialect;
+use sqlparser::keywords::Keyword;
+use sqlparser::tokenizer::{Token, Tokenizer};
+
+use crate::{get_dialect, parse_leading_statement};
+
+/// Dialects tried in turn, after the one the statement's syntax points to
+const CANDIDATES: [&str; 5] = ["postgresql", "mysql", "mssql", "sqlite", "generic"];
+
+/// The dialect only whose syntax `sql` uses, if any
+fn sniff(sql: &str) -> Option<&'static str> {
+    let tokens = Tokenizer::new(&GenericDialect {}, sql).tokenize().ok()?;
+    let solid: Vec<&Token> = tokens.iter().filter(|t| !matches!(t, Token::Whitespace(_))).collect();
+    for (i, token) in solid.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|j| solid[j]);
+        match token {
+            Token::Word(w) if w.quote_style == Some('`') => return Some("mysql"),
+            Token::DoubleColon => return Some("postgresql"),
+            Token::Placeholder(p) if p.strip_prefix('$').is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())) => {
+                return Some("postgresql")
+            }
+            Token::Word(w) if w.keyword == Keyword::TOP => {
+                if matches!(previous, Some(Token::Word(w)) if matches!(w.keyword, Keyword::SELECT | Keyword::DISTINCT)) {
+                    return Some("mssql");
+                }
+            }
+            // `[name]`, unless it subscripts the value before it
+            Token::LBracket => {
+                let name = matches!(solid.get(i + 1..i + 3), Some([Token::Word(w), Token::RBracket]) if w.quote_style.is_none());
+                let subscript = match previous {
+                    Some(Token::Word(w)) => w.keyword == Keyword::NoKeyword || w.quote_style.is_some(),
+                    Some(Token::RParen | Token::RBracket) => true,
+                    _ => false,
+                };
+                if name && !subscript {
+                    return Some("mssql");
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The canonical name of the dialect `sql` is read with under `dialect="auto"`
+pub fn detect(sql: &str) -> &'static str {
+    let sniffed = sniff(sql);
+    let candidates: Vec<&'static str> =
+        sniffed.into_iter().chain(CANDIDATES.into_iter().filter(|name| Some(*name) != sniffed)).collect();
+    candidates
+        .iter()
+        .copied()
+        .find(|name| get_dialect(name).is_ok_and(|dialect| parse_leading_statement(sql, &*dialect).is_ok()))
+        .unwrap_or(candidates[0])
+}
@@ -0,0 +1,307 @@
+//! Best-effort pseudo-dialects for engines sqlparser has no dialect for.
+//!
+//! Each one is the sqlparser dialect its engine is closest to, with the
+//! identifier quotes and alias keywords of the engine overriding the base's,
+//! so that the bulk of a Teradata, Vertica or Informix workload parses rather
+//! than taking the fallback path. Syntax the base does not know (Teradata's
+//! `SEL`, Informix's `SKIP n FIRST m`) still fails to parse.
+//!
+//! The base's methods are forwarded and its `TypeId` reported, so the parser
+//! treats the pseudo-dialect as its base wherever it checks the dialect. Only
+//! bases that keep sqlparser's default alias rules (generic, postgresql) are
+//! used, as the alias overrides go through those defaults.
+
+use std::any::TypeId;
+
+use sqlparser::ast::{ColumnOption, Expr, Statement};
+use sqlparser::dialect::{Dialect, Precedence};
+use sqlparser::keywords::Keyword;
+use sqlparser::parser::{Parser, ParserError};
+
+use crate::get_dialect;
+
+pub struct Spec {
+    /// Canonical name
+    pub name: &'static str,
+    /// Canonical name of the sqlparser dialect it builds on
+    pub base: &'static str,
+    /// Characters starting a delimited identifier, replacing the base's;
+    /// `None` keeps them. A `"` that does not start one starts a string.
+    quotes: Option<&'static [char]>,
+    /// Keywords the base reserves that the engine takes as aliases
+    unreserved: &'static [Keyword],
+    /// Keywords the engine reserves that the base takes as aliases
+    reserved: &'static [Keyword],
+}
+
+/// Keywords sqlparser reserves for the clauses of other engines (ClickHouse,
+/// Snowflake, Hive, ...), which are plain words to the pseudo-dialects
+const FOREIGN_CLAUSES: &[Keyword] = &[
+    Keyword::ANTI,
+    Keyword::ASOF,
+    Keyword::CLUSTER,
+    Keyword::CONNECT,
+    Keyword::DISTRIBUTE,
+    Keyword::FORMAT,
+    Keyword::GLOBAL,
+    Keyword::MATCH_CONDITION,
+    Keyword::OPEN,
+    Keyword::PREWHERE,
+    Keyword::SEMI,
+    Keyword::SETTINGS,
+    Keyword::SORT,
+    Keyword::START,
+];
+
+pub const PSEUDO_DIALECTS: [Spec; 3] = [
+    Spec {
+        name: "teradata",
+        base: "generic",
+        quotes: Some(&['"']),
+        unreserved: FOREIGN_CLAUSES,
+        reserved: &[Keyword::QUALIFY, Keyword::SAMPLE],
+    },
+    Spec { name: "vertica", base: "postgresql", quotes: None, unreserved: FOREIGN_CLAUSES, reserved: &[] },
+    // Double quotes delimit strings unless DELIMIDENT is set
+    Spec { name: "informix", base: "generic", quotes: Some(&[]), unreserved: FOREIGN_CLAUSES, reserved: &[] },
+];
+
+/// The pseudo-dialect named `name` (canonical)
+pub fn spec(name: &str) -> Option<&'static Spec> {
+    PSEUDO_DIALECTS.iter().find(|spec| spec.name == name)
+}
+
+#[derive(Debug)]
+pub struct PseudoDialect {
+    base: Box<dyn Dialect + Send + Sync>,
+    quotes: Option<&'static [char]>,
+    unreserved: &'static [Keyword],
+    reserved: &'static [Keyword],
+}
+
+impl PseudoDialect {
+    pub fn new(spec: &Spec) -> Result<PseudoDialect, String> {
+        Ok(PseudoDialect {
+            base: get_dialect(spec.base)?,
+            quotes: spec.quotes,
+            unreserved: spec.unreserved,
+            reserved: spec.reserved,
+        })
+    }
+
+    /// Whether `kw` may be an alias, by the overrides or else by `base`
+    fn alias(&self, kw: &Keyword, base: bool) -> bool {
+        if self.unreserved.contains(kw) {
+            true
+        } else if self.reserved.contains(kw) {
+            false
+        } else {
+            base
+        }
+    }
+}
+
+/// Forward methods taking only `&self` and returning `bool` to the base
+macro_rules! forward {
+    ($($name:ident),* $(,)?) => {
+        $(fn $name(&self) -> bool {
+            self.base.$name()
+        })*
+    };
+}
+
+impl Dialect for PseudoDialect {
+    fn dialect(&self) -> TypeId {
+        self.base.dialect()
+    }
+
+    fn is_delimited_identifier_start(&self, ch: char) -> bool {
+        match self.quotes {
+            Some(quotes) => quotes.contains(&ch),
+            None => self.base.is_delimited_identifier_start(ch),
+        }
+    }
+
+    fn identifier_quote_style(&self, identifier: &str) -> Option<char> {
+        match self.quotes {
+            Some(quotes) => quotes.first().copied(),
+            None => self.base.identifier_quote_style(identifier),
+        }
+    }
+
+    fn is_identifier_start(&self, ch: char) -> bool {
+        self.base.is_identifier_start(ch)
+    }
+
+    fn is_identifier_part(&self, ch: char) -> bool {
+        self.base.is_identifier_part(ch)
+    }
+
+    fn is_custom_operator_part(&self, ch: char) -> bool {
+        self.base.is_custom_operator_part(ch)
+    }
+
+    fn parse_prefix(&self, parser: &mut Parser) -> Option<Result<Expr, ParserError>> {
+        self.base.parse_prefix(parser)
+    }
+
+    fn parse_infix(&self, parser: &mut Parser, expr: &Expr, precedence: u8) -> Option<Result<Expr, ParserError>> {
+        self.base.parse_infix(parser, expr, precedence)
+    }
+
+    fn get_next_precedence(&self, parser: &Parser) -> Option<Result<u8, ParserError>> {
+        self.base.get_next_precedence(parser)
+    }
+
+    fn parse_statement(&self, parser: &mut Parser) -> Option<Result<Statement, ParserError>> {
+        self.base.parse_statement(parser)
+    }
+
+    fn parse_column_option(
+        &self,
+        parser: &mut Parser,
+    ) -> Result<Option<Result<Option<ColumnOption>, ParserError>>, ParserError> {
+        self.base.parse_column_option(parser)
+    }
+
+    fn prec_value(&self, prec: Precedence) -> u8 {
+        self.base.prec_value(prec)
+    }
+
+    fn prec_unknown(&self) -> u8 {
+        self.base.prec_unknown()
+    }
+
+    fn is_column_alias(&self, kw: &Keyword, parser: &mut Parser) -> bool {
+        let base = self.base.is_column_alias(kw, parser);
+        self.alias(kw, base)
+    }
+
+    fn is_table_alias(&self, kw: &Keyword, parser: &mut Parser) -> bool {
+        let base = self.base.is_table_alias(kw, parser);
+        self.alias(kw, base)
+    }
+
+    forward!(
+        supports_string_literal_backslash_escape,
+        ignores_wildcard_escapes,
+        supports_unicode_string_literal,
+        supports_filter_during_aggregation,
+        supports_window_clause_named_window_reference,
+        supports_within_after_array_aggregation,
+        supports_group_by_expr,
+        supports_group_by_with_modifier,
+        supports_left_associative_joins_without_parens,
+        supports_outer_join_operator,
+        supports_cross_join_constraint,
+        supports_connect_by,
+        supports_execute_immediate,
+        supports_match_recognize,
+        supports_in_empty_list,
+        supports_start_transaction_modifier,
+        supports_end_transaction_modifier,
+        supports_named_fn_args_with_eq_operator,
+        supports_named_fn_args_with_colon_operator,
+        supports_named_fn_args_with_assignment_operator,
+        supports_named_fn_args_with_rarrow_operator,
+        supports_named_fn_args_with_expr_name,
+        supports_numeric_prefix,
+        supports_numeric_literal_underscores,
+        supports_window_function_null_treatment_arg,
+        supports_dictionary_syntax,
+        support_map_literal_syntax,
+        supports_lambda_functions,
+        supports_parenthesized_set_variables,
+        supports_comma_separated_set_assignments,
+        supports_select_wildcard_except,
+        convert_type_before_value,
+        supports_triple_quoted_string,
+        supports_trailing_commas,
+        supports_limit_comma,
+        supports_string_literal_concatenation,
+        supports_string_literal_concatenation_with_newline,
+        supports_projection_trailing_commas,
+        supports_from_trailing_commas,
+        supports_column_definition_trailing_commas,
+        supports_object_name_double_dot_notation,
+        supports_struct_literal,
+        supports_empty_projections,
+        supports_select_expr_star,
+        supports_from_first_select,
+        supports_pipe_operator,
+        supports_user_host_grantee,
+        supports_match_against,
+        supports_select_wildcard_exclude,
+        supports_select_exclude,
+        supports_create_table_multi_schema_info_sources,
+        supports_select_modifiers,
+        describe_requires_table_keyword,
+        allow_extract_custom,
+        allow_extract_single_quotes,
+        supports_extract_comma_syntax,
+        supports_subquery_as_function_arg,
+        supports_create_view_comment_syntax,
+        supports_array_typedef_without_element_type,
+        supports_parens_around_table_factor,
+        supports_values_as_table_factor,
+        supports_dollar_placeholder,
+        supports_create_index_with_clause,
+        require_interval_qualifier,
+        supports_explain_with_utility_options,
+        supports_asc_desc_in_column_definition,
+        supports_factorial_operator,
+        supports_bitwise_shift_operators,
+        supports_nested_comments,
+        supports_multiline_comment_hints,
+        supports_eq_alias_assignment,
+        supports_try_convert,
+        supports_bang_not_operator,
+        supports_listen_notify,
+        supports_load_data,
+        supports_load_extension,
+        supports_top_before_distinct,
+        supports_boolean_literals,
+        supports_show_like_before_in,
+        supports_comment_on,
+        supports_create_table_select,
+        supports_partiql,
+        supports_constraint_keyword_without_name,
+        supports_table_sample_before_alias,
+        supports_insert_set,
+        supports_insert_table_function,
+        supports_insert_format,
+        supports_set_stmt_without_operator,
+        supports_table_versioning,
+        supports_string_escape_constant,
+        supports_table_hints,
+        requires_single_line_comment_whitespace,
+        supports_array_typedef_with_brackets,
+        supports_geometric_types,
+        supports_order_by_all,
+        supports_set_names,
+        supports_space_separated_column_options,
+        supports_alter_column_type_using,
+        supports_comma_separated_drop_column_list,
+        supports_notnull_operator,
+        supports_data_type_signed_suffix,
+        supports_interval_options,
+        supports_create_table_like_parenthesized,
+        supports_semantic_view_table_factor,
+        supports_quote_delimited_string,
+        supports_comment_optimizer_hint,
+        supports_double_ampersand_operator,
+        supports_binary_kw_as_cast,
+        supports_select_wildcard_replace,
+        supports_select_wildcard_ilike,
+        supports_select_wildcard_rename,
+        supports_optimize_table,
+        supports_install,
+        supports_detach,
+        supports_prewhere,
+        supports_with_fill,
+        supports_limit_by,
+        supports_interpolate,
+        supports_settings,
+        supports_select_format,
+    );
+}
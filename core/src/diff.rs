@@ -0,0 +1,56 @@
+//! Comparison of the fingerprint counts of two corpora, for `diff_corpora()`.
+//!
+//! Both corpora are counted into a `Registry` with the same engine, so their
+//! hashes are comparable. A fingerprint seen only in the second corpus is new,
+//! one seen only in the first has disappeared, and one seen in both a
+//! different number of times has changed in frequency.
+
+use crate::registry::Registry;
+
+pub struct Change<'a> {
+    pub hash: &'a str,
+    pub normalized: &'a str,
+    pub before: u64,
+    pub after: u64,
+}
+
+impl Change<'_> {
+    fn delta(&self) -> u64 {
+        self.before.abs_diff(self.after)
+    }
+}
+
+#[derive(Default)]
+pub struct Diff<'a> {
+    pub new: Vec<Change<'a>>,
+    pub disappeared: Vec<Change<'a>>,
+    pub changed: Vec<Change<'a>>,
+    /// Fingerprints seen as many times in both corpora
+    pub unchanged: usize,
+}
+
+/// Compare `before` and `after`; each list is sorted by the size of the
+/// change, largest first, then by hash
+pub fn diff<'a>(before: &'a Registry, after: &'a Registry) -> Diff<'a> {
+    let mut diff = Diff::default();
+    for (hash, entry) in &before.entries {
+        let count = after.entries.get(hash).map_or(0, |other| other.count);
+        let change = Change { hash, normalized: &entry.normalized, before: entry.count, after: count };
+        if count == 0 {
+            diff.disappeared.push(change);
+        } else if count != entry.count {
+            diff.changed.push(change);
+        } else {
+            diff.unchanged += 1;
+        }
+    }
+    for (hash, entry) in &after.entries {
+        if !before.entries.contains_key(hash) {
+            diff.new.push(Change { hash, normalized: &entry.normalized, before: 0, after: entry.count });
+        }
+    }
+    for changes in [&mut diff.new, &mut diff.disappeared, &mut diff.changed] {
+        changes.sort_by(|a, b| b.delta().cmp(&a.delta()).then(a.hash.cmp(b.hash)));
+    }
+    diff
+}
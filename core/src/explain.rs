@@ -0,0 +1,22 @@
+//! `EXPLAIN` wrappers, for `unwrap_explain`.
+//!
+//! Plans are looked at for the queries the application runs, and
+//! `EXPLAIN ANALYZE SELECT ...` fingerprints apart from the `SELECT` it
+//! explains. With `unwrap_explain=True` the wrapped statement is normalized
+//! in its place, whatever the `EXPLAIN` options, so that both share a
+//! fingerprint and `NormalizeResult.explained` tells them apart.
+//! `DESCRIBE t` and other forms explaining a table wrap no statement and are
+//! left as written.
+
+use sqlparser::ast::Statement;
+
+/// Replace an `EXPLAIN` with the statement it wraps, in place; whether there
+/// was one. Nested `EXPLAIN`s are unwrapped too.
+pub fn unwrap(stmt: &mut Statement) -> bool {
+    let mut explained = false;
+    while let Statement::Explain { statement, .. } = stmt {
+        *stmt = (**statement).clone();
+        explained = true;
+    }
+    explained
+}
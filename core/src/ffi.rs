@@ -0,0 +1,161 @@
+//! C API, for the `ffi` feature.
+//!
+//! Go and C++ sidecars fingerprint statements through the cdylib built with
+//! `cargo rustc -p sqlfp-core --profile release-ffi --features ffi --crate-type cdylib`,
+//! as declared in `include/sqlfp.h`. The `release-ffi` profile unwinds on
+//! panic, unlike `release`, so that a panic is returned as
+//! `SQLFP_INTERNAL_ERROR` rather than aborting the host process. `sqlfp_normalize()` normalizes with
+//! the default options and fills a `SqlfpResult` whatever its status: the
+//! normalized text, hash and params on `SQLFP_OK`, the error message alone
+//! otherwise. Its strings are NUL-terminated UTF-8 allocated here, owned by
+//! the caller until it hands the result back to `sqlfp_free_result()`, and
+//! never freed otherwise.
+
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::{normalize, Dialect, Error, Options};
+
+/// The statement was normalized
+pub const SQLFP_OK: c_int = 0;
+/// The statement does not parse, or there is none
+pub const SQLFP_PARSE_ERROR: c_int = 1;
+/// An argument is NULL or not UTF-8, or the dialect is not supported
+pub const SQLFP_INVALID_ARGUMENT: c_int = 2;
+/// The statement cannot be normalized with these options
+pub const SQLFP_NORMALIZE_ERROR: c_int = 3;
+/// The normalizer panicked, which is a bug; only returned by a library built
+/// to unwind on panic, as with the `release-ffi` profile, which others abort on
+pub const SQLFP_INTERNAL_ERROR: c_int = 4;
+/// The normalized statement or a param holds a NUL byte, as a string literal
+/// can, which a C string cannot
+pub const SQLFP_NUL_IN_OUTPUT: c_int = 5;
+
+/// The fingerprint of a statement, or why there is none
+#[repr(C)]
+pub struct SqlfpResult {
+    /// The statement with its literals replaced by `?`
+    pub normalized: *mut c_char,
+    /// SHA-256 of `normalized`, in hex
+    pub hash: *mut c_char,
+    /// The literals replaced, without the prefix of a prefixed string
+    /// (`'abc'` for `N'abc'`), `params_len` of them (NULL for none)
+    pub params: *mut *mut c_char,
+    pub params_len: usize,
+    /// Why the statement was not normalized, NULL on `SQLFP_OK`
+    pub error: *mut c_char,
+}
+
+impl SqlfpResult {
+    const EMPTY: SqlfpResult = SqlfpResult {
+        normalized: ptr::null_mut(),
+        hash: ptr::null_mut(),
+        params: ptr::null_mut(),
+        params_len: 0,
+        error: ptr::null_mut(),
+    };
+}
+
+/// A failure, its status and message
+type Failure = (c_int, String);
+
+/// `text` as a C string, or the failure of holding a NUL byte
+fn c_string(text: String) -> Result<CString, Failure> {
+    CString::new(text).map_err(|_| (SQLFP_NUL_IN_OUTPUT, "the output holds a NUL byte".to_string()))
+}
+
+/// The message of a failure as a C string, its NUL bytes written `\0`
+fn c_message(message: String) -> *mut c_char {
+    CString::new(message.replace('\0', "\\0")).unwrap_or_default().into_raw()
+}
+
+/// The text of the C string `ptr`, the argument `name`
+unsafe fn text<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, Failure> {
+    if ptr.is_null() {
+        return Err((SQLFP_INVALID_ARGUMENT, format!("{} is NULL", name)));
+    }
+    CStr::from_ptr(ptr).to_str().map_err(|_| (SQLFP_INVALID_ARGUMENT, format!("{} is not UTF-8", name)))
+}
+
+unsafe fn fingerprint(sql: *const c_char, dialect: *const c_char) -> Result<SqlfpResult, Failure> {
+    let sql = text(sql, "sql")?;
+    let dialect = if dialect.is_null() {
+        Dialect::GENERIC
+    } else {
+        Dialect::new(text(dialect, "dialect")?).map_err(|e| (SQLFP_INVALID_ARGUMENT, e.to_string()))?
+    };
+    let fingerprint = normalize(sql, dialect, &Options::default()).map_err(|e| {
+        let status = match e {
+            Error::Parse(_) => SQLFP_PARSE_ERROR,
+            Error::Options(_) => SQLFP_INVALID_ARGUMENT,
+            Error::Unnormalizable { .. } | Error::PlaceholderCollision(_) => SQLFP_NORMALIZE_ERROR,
+            Error::Callback(never) => match never {},
+        };
+        (status, e.to_string())
+    })?;
+    // Every string is converted before any is handed out, so that none
+    // leaks when one fails
+    let normalized = c_string(fingerprint.normalized)?;
+    let hash = c_string(fingerprint.hash)?;
+    let params = fingerprint.params.into_iter().map(c_string).collect::<Result<Vec<_>, _>>()?;
+    let params: Box<[*mut c_char]> = params.into_iter().map(CString::into_raw).collect();
+    Ok(SqlfpResult {
+        normalized: normalized.into_raw(),
+        hash: hash.into_raw(),
+        params_len: params.len(),
+        // An empty array is not allocated
+        params: if params.is_empty() { ptr::null_mut() } else { Box::into_raw(params).cast() },
+        error: ptr::null_mut(),
+    })
+}
+
+/// Normalize the first statement of `sql` with the grammar of `dialect` (a
+/// name `normalize()` takes, NULL for `"generic"`) into `*out_result`; the
+/// status, `SQLFP_OK` or the kind of error.
+///
+/// # Safety
+///
+/// `sql` and `dialect` are NULL or NUL-terminated strings, and `out_result`
+/// is NULL or points to a `SqlfpResult` to overwrite, without freeing what it
+/// held. Unless `out_result` is NULL, which is `SQLFP_INVALID_ARGUMENT`, the
+/// result must be passed to `sqlfp_free_result()` whatever the status.
+#[no_mangle]
+pub unsafe extern "C" fn sqlfp_normalize(sql: *const c_char, dialect: *const c_char, out_result: *mut SqlfpResult) -> c_int {
+    if out_result.is_null() {
+        return SQLFP_INVALID_ARGUMENT;
+    }
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| fingerprint(sql, dialect)))
+        .unwrap_or_else(|_| Err((SQLFP_INTERNAL_ERROR, "sqlfp panicked".to_string())));
+    let (status, result) = match outcome {
+        Ok(result) => (SQLFP_OK, result),
+        Err((status, message)) => (status, SqlfpResult { error: c_message(message), ..SqlfpResult::EMPTY }),
+    };
+    out_result.write(result);
+    status
+}
+
+/// Free the strings of `*result` and set its fields to NULL, so that freeing
+/// it again does nothing; NULL is ignored.
+///
+/// # Safety
+///
+/// `result` is NULL or points to a `SqlfpResult` filled by
+/// `sqlfp_normalize()`, or already freed, whose fields were not changed.
+#[no_mangle]
+pub unsafe extern "C" fn sqlfp_free_result(result: *mut SqlfpResult) {
+    let Some(result) = result.as_mut() else {
+        return;
+    };
+    let SqlfpResult { normalized, hash, params, params_len, error } = std::mem::replace(result, SqlfpResult::EMPTY);
+    for text in [normalized, hash, error] {
+        if !text.is_null() {
+            drop(CString::from_raw(text));
+        }
+    }
+    if !params.is_null() {
+        for param in Box::from_raw(ptr::slice_from_raw_parts_mut(params, params_len)) {
+            drop(CString::from_raw(param));
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Grouping sets, for the opt-in `grouping-canonicalize` pass.
+//!
+//! BI tools spell the same grouping several ways: `ROLLUP (a, b)`,
+//! `GROUPING SETS ((a, b), (a), ())` and MySQL's `GROUP BY a, b WITH ROLLUP`
+//! all group by `(a, b)`, then `(a)`, then nothing. A `GROUP BY` list holding a
+//! `ROLLUP`, `CUBE`, `GROUPING SETS` or the empty grouping set `()` is
+//! rewritten as the single `GROUPING SETS` it stands for: the cross product of
+//! the sets of its elements, a plain expression being a set of itself, and a
+//! plain list when that is a single set. `ROLLUP` gives the prefixes of its
+//! elements, longest first, and `CUBE` all their subsets, in the order
+//! PostgreSQL documents. Lists standing for more than `MAX_SETS` sets are left
+//! as written.
+
+use sqlparser::ast::{Expr, GroupByExpr, GroupByWithModifier};
+
+/// The most grouping sets a `GROUP BY` list is expanded to
+const MAX_SETS: usize = 256;
+
+/// The sets of `ROLLUP (elements)`
+fn rollup(elements: &[Vec<Expr>]) -> Vec<Vec<Expr>> {
+    (0..=elements.len()).rev().map(|n| elements[..n].concat()).collect()
+}
+
+/// The sets of `CUBE (elements)`: with the first element as the highest bit,
+/// the subsets counting down from all of them
+fn cube(elements: &[Vec<Expr>]) -> Option<Vec<Vec<Expr>>> {
+    let count = 1usize.checked_shl(elements.len() as u32).filter(|&count| count <= MAX_SETS)?;
+    let sets = (0..count).rev().map(|mask| {
+        let picked = elements.iter().enumerate().filter(|(i, _)| mask & (1 << (elements.len() - 1 - i)) != 0);
+        picked.flat_map(|(_, element)| element.iter().cloned()).collect()
+    });
+    Some(sets.collect())
+}
+
+/// The grouping sets of an element of a `GROUP BY` list, `None` when it is a
+/// plain expression or too large to expand. The flag tells grouping
+/// constructs from plain expressions.
+fn sets_of(expr: &Expr) -> Option<(Vec<Vec<Expr>>, bool)> {
+    let sets = match expr {
+        Expr::GroupingSets(sets) => sets.clone(),
+        Expr::Rollup(elements) => rollup(elements),
+        Expr::Cube(elements) => cube(elements)?,
+        Expr::Tuple(exprs) if exprs.is_empty() => vec![Vec::new()],
+        _ => return Some((vec![vec![expr.clone()]], false)),
+    };
+    Some((sets, true))
+}
+
+/// Rewrite `group_by` as one `GROUPING SETS`, if it holds grouping constructs
+/// or a `WITH ROLLUP` or `WITH CUBE` modifier
+pub fn canonicalize(group_by: &mut GroupByExpr) {
+    let GroupByExpr::Expressions(exprs, modifiers) = group_by else {
+        return;
+    };
+    let elements = match modifiers.as_slice() {
+        [] => {
+            let Some(elements) = exprs.iter().map(sets_of).collect::<Option<Vec<_>>>() else {
+                return;
+            };
+            if !elements.iter().any(|(_, construct)| *construct) {
+                return;
+            }
+            elements.into_iter().map(|(sets, _)| sets).collect()
+        }
+        [modifier @ (GroupByWithModifier::Rollup | GroupByWithModifier::Cube)] => {
+            if exprs.iter().any(|expr| !matches!(sets_of(expr), Some((_, false)))) {
+                return;
+            }
+            let singletons: Vec<Vec<Expr>> = exprs.iter().map(|expr| vec![expr.clone()]).collect();
+            let sets = match modifier {
+                GroupByWithModifier::Rollup => rollup(&singletons),
+                _ => match cube(&singletons) {
+                    Some(sets) => sets,
+                    None => return,
+                },
+            };
+            vec![sets]
+        }
+        _ => return,
+    };
+    let mut product: Vec<Vec<Expr>> = vec![Vec::new()];
+    for sets in elements {
+        if product.len().saturating_mul(sets.len()) > MAX_SETS {
+            return;
+        }
+        product = product
+            .iter()
+            .flat_map(|prefix| sets.iter().map(move |set| prefix.iter().chain(set).cloned().collect()))
+            .collect();
+    }
+    let exprs = match product.as_mut_slice() {
+        // A single set, unless it is empty, groups as a plain list
+        [set] if !set.is_empty() => std::mem::take(set),
+        _ => vec![Expr::GroupingSets(product)],
+    };
+    *group_by = GroupByExpr::Expressions(exprs, Vec::new());
+}
@@ -0,0 +1,145 @@
+//! Hash algorithms and encodings of `hash` and `shape_hash`, picked with
+//! `hash_algorithm` and `hash_encoding`.
+//!
+//! SHA-256 in hex is the default, and what fingerprints are compared against
+//! across releases. `xxhash64` (XXH64, seed 0) is much cheaper, for counting
+//! fingerprints in memory, and `blake3` a faster cryptographic hash. Digests
+//! are encoded as lowercase hex, standard padded base64 or, for the 64 bits
+//! of `xxhash64` only, a Python `int`. An `int` hash is kept in hex here, so
+//! that it keys maps and fits `repr()` like the others, and only converted
+//! when handed to Python.
+//!
+//! `metadata["query_id"]` is an XXH64 as well, whatever `hash_algorithm`.
+
+use sha2::{Digest, Sha256};
+use xxhash_rust::xxh64::xxh64;
+
+pub const ALGORITHMS: [&str; 3] = ["sha256", "xxhash64", "blake3"];
+pub const ENCODINGS: [&str; 3] = ["hex", "base64", "int"];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    #[default]
+    Sha256,
+    XxHash64,
+    Blake3,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    #[default]
+    Hex,
+    Base64,
+    Int,
+}
+
+pub fn algorithm(name: &str) -> Result<Algorithm, String> {
+    match name {
+        "sha256" => Ok(Algorithm::Sha256),
+        "xxhash64" => Ok(Algorithm::XxHash64),
+        "blake3" => Ok(Algorithm::Blake3),
+        _ => Err(format!("Unknown hash_algorithm '{}', expected one of: {}", name, ALGORITHMS.join(", "))),
+    }
+}
+
+pub fn encoding(name: &str) -> Result<Encoding, String> {
+    match name {
+        "hex" => Ok(Encoding::Hex),
+        "base64" => Ok(Encoding::Base64),
+        "int" => Ok(Encoding::Int),
+        _ => Err(format!("Unknown hash_encoding '{}', expected one of: {}", name, ENCODINGS.join(", "))),
+    }
+}
+
+/// How `hash` and `shape_hash` are computed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Hasher {
+    pub algorithm: Algorithm,
+    pub encoding: Encoding,
+}
+
+impl Hasher {
+    /// The hasher for the algorithm and encoding names given, defaulting to
+    /// `sha256` and `hex`
+    pub fn new(algorithm_name: Option<&str>, encoding_name: Option<&str>) -> Result<Hasher, String> {
+        let hasher = Hasher {
+            algorithm: algorithm_name.map(algorithm).transpose()?.unwrap_or_default(),
+            encoding: encoding_name.map(encoding).transpose()?.unwrap_or_default(),
+        };
+        if hasher.encoding == Encoding::Int && hasher.algorithm != Algorithm::XxHash64 {
+            return Err(format!("hash_encoding 'int' needs hash_algorithm 'xxhash64', not '{}'", hasher.name()));
+        }
+        Ok(hasher)
+    }
+
+    /// The name of the algorithm, as passed to `hash_algorithm`
+    pub fn name(&self) -> &'static str {
+        match self.algorithm {
+            Algorithm::Sha256 => "sha256",
+            Algorithm::XxHash64 => "xxhash64",
+            Algorithm::Blake3 => "blake3",
+        }
+    }
+
+    /// The name of the encoding, as passed to `hash_encoding`
+    pub fn encoding_name(&self) -> &'static str {
+        match self.encoding {
+            Encoding::Hex => "hex",
+            Encoding::Base64 => "base64",
+            Encoding::Int => "int",
+        }
+    }
+
+    /// The encoded digest of `text`
+    pub fn hash(&self, text: &str) -> String {
+        let digest = match self.algorithm {
+            Algorithm::Sha256 => Sha256::digest(text.as_bytes()).to_vec(),
+            Algorithm::XxHash64 => xxh64(text.as_bytes(), 0).to_be_bytes().to_vec(),
+            Algorithm::Blake3 => blake3::hash(text.as_bytes()).as_bytes().to_vec(),
+        };
+        match self.encoding {
+            Encoding::Hex | Encoding::Int => hex::encode(digest),
+            Encoding::Base64 => base64(&digest),
+        }
+    }
+    /// `hash`, one of ours or empty (for a result yet to be unpickled),
+    /// shortened for `repr()`
+    pub fn short(&self, hash: &str) -> String {
+        match self.int(hash) {
+            Some(n) => n.to_string(),
+            None => format!("'{}'", hash.get(..8).unwrap_or(hash)),
+        }
+    }
+
+    /// The value of `hash`, one of ours, with `hash_encoding="int"`
+    pub fn int(&self, hash: &str) -> Option<u64> {
+        if self.encoding != Encoding::Int {
+            return None;
+        }
+        u64::from_str_radix(hash, 16).ok()
+    }
+}
+
+/// The `query_id` of `compat="pg_stat_statements"`: the XXH64 of the
+/// normalized statement as the signed 64-bit integer Postgres shows its
+/// `queryid` as
+pub fn query_id(normalized: &str) -> i64 {
+    xxh64(normalized.as_bytes(), 0) as i64
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,158 @@
+//! Case folding of unquoted names, for `identifier_case`.
+//!
+//! Most engines resolve unquoted names without regard to case (PostgreSQL
+//! folds them to lowercase, Oracle and Snowflake to uppercase), so `SELECT Id
+//! FROM Users` and `select id from users` run the same query. With
+//! `identifier_case="lower"` or `"upper"`, the unquoted names of tables, their
+//! aliases, columns, select-item aliases and CTEs are folded so that such
+//! statements share a fingerprint. Quoted names keep their case, which is
+//! what quoting them asks for; function names follow `function_case`.
+
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    AssignmentTarget, Expr, Ident, JoinConstraint, JoinOperator, ObjectName, ObjectNamePart, Query, SelectItem,
+    SelectItemQualifiedWildcardKind, SetExpr, Statement, TableAlias, TableFactor, VisitMut, VisitorMut,
+};
+
+/// The case unquoted names are folded to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Fold {
+    Lower,
+    Upper,
+}
+
+/// The `identifier_case` option; `None` for `"preserve"`
+pub fn mode(name: &str) -> Result<Option<Fold>, String> {
+    match name {
+        "preserve" => Ok(None),
+        "lower" => Ok(Some(Fold::Lower)),
+        "upper" => Ok(Some(Fold::Upper)),
+        _ => Err(format!("Unsupported identifier_case: {}", name)),
+    }
+}
+
+struct Folder(Fold);
+
+impl Folder {
+    fn ident(&self, ident: &mut Ident) {
+        if ident.quote_style.is_none() {
+            ident.value = match self.0 {
+                Fold::Lower => ident.value.to_lowercase(),
+                Fold::Upper => ident.value.to_uppercase(),
+            };
+        }
+    }
+
+    fn name(&self, name: &mut ObjectName) {
+        for part in &mut name.0 {
+            if let ObjectNamePart::Identifier(ident) = part {
+                self.ident(ident);
+            }
+        }
+    }
+
+    fn alias(&self, alias: &mut TableAlias) {
+        self.ident(&mut alias.name);
+        for column in &mut alias.columns {
+            self.ident(&mut column.name);
+        }
+    }
+
+    /// The select-item aliases, qualified wildcards and `USING` columns of
+    /// the selects of `body`, which no expression holds
+    fn body(&self, body: &mut SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                for item in &mut select.projection {
+                    match item {
+                        SelectItem::ExprWithAlias { alias, .. } => self.ident(alias),
+                        SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::ObjectName(name), _) => {
+                            self.name(name)
+                        }
+                        _ => {}
+                    }
+                }
+                for join in select.from.iter_mut().flat_map(|table| &mut table.joins) {
+                    if let JoinOperator::Join(JoinConstraint::Using(columns))
+                    | JoinOperator::Inner(JoinConstraint::Using(columns))
+                    | JoinOperator::Left(JoinConstraint::Using(columns))
+                    | JoinOperator::LeftOuter(JoinConstraint::Using(columns))
+                    | JoinOperator::Right(JoinConstraint::Using(columns))
+                    | JoinOperator::RightOuter(JoinConstraint::Using(columns))
+                    | JoinOperator::FullOuter(JoinConstraint::Using(columns)) = &mut join.join_operator
+                    {
+                        columns.iter_mut().for_each(|column| self.name(column));
+                    }
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.body(left);
+                self.body(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl VisitorMut for Folder {
+    type Break = ();
+
+    fn pre_visit_relation(&mut self, relation: &mut ObjectName) -> ControlFlow<()> {
+        self.name(relation);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        if let Some(with) = &mut query.with {
+            for cte in &mut with.cte_tables {
+                self.alias(&mut cte.alias);
+            }
+        }
+        self.body(&mut query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, factor: &mut TableFactor) -> ControlFlow<()> {
+        if let TableFactor::Table { alias: Some(alias), .. }
+        | TableFactor::Derived { alias: Some(alias), .. }
+        | TableFactor::Function { alias: Some(alias), .. }
+        | TableFactor::TableFunction { alias: Some(alias), .. }
+        | TableFactor::UNNEST { alias: Some(alias), .. } = factor
+        {
+            self.alias(alias);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_statement(&mut self, stmt: &mut Statement) -> ControlFlow<()> {
+        match stmt {
+            Statement::Insert(insert) => insert.columns.iter_mut().for_each(|column| self.ident(column)),
+            Statement::Update(update) => {
+                for assignment in &mut update.assignments {
+                    match &mut assignment.target {
+                        AssignmentTarget::ColumnName(name) => self.name(name),
+                        AssignmentTarget::Tuple(names) => names.iter_mut().for_each(|name| self.name(name)),
+                    }
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<()> {
+        match expr {
+            Expr::Identifier(ident) => self.ident(ident),
+            Expr::CompoundIdentifier(idents) => idents.iter_mut().for_each(|ident| self.ident(ident)),
+            Expr::QualifiedWildcard(name, _) => self.name(name),
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Fold the unquoted names of `stmt` to `fold`, in place
+pub fn fold(stmt: &mut Statement, fold: Fold) {
+    let _ = stmt.visit(&mut Folder(fold));
+}
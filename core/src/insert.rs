@@ -0,0 +1,58 @@
+//! Canonical INSERT column order, for the opt-in `insert-column-sort` pass.
+//!
+//! ORMs list the columns of an `INSERT` in model-definition order, hand-written
+//! code often alphabetically; both insert the same row. The column list of an
+//! `INSERT ... VALUES` is sorted by name (case-insensitively unless quoted) and
+//! every row is reordered to match. Inserts from a query, and rows whose length
+//! does not match the column list, are left as written.
+//!
+//! The number of VALUES items is also how many literals the literal pass
+//! allocates for up front, as very wide inserts hold thousands of them.
+
+use sqlparser::ast::{Ident, SetExpr, Statement};
+
+/// Names are compared case-insensitively unless quoted
+fn key(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
+
+/// Reorder `items` so that the item at `order[i]` comes `i`th
+fn permute<T>(items: &mut Vec<T>, order: &[usize]) {
+    let mut taken: Vec<Option<T>> = items.drain(..).map(Some).collect();
+    items.extend(order.iter().map(|&i| taken[i].take().expect("each index once")));
+}
+
+/// Number of items in the VALUES rows of an INSERT, which wide generated
+/// inserts make most of its literals
+pub fn value_count(stmt: &Statement) -> usize {
+    let Statement::Insert(insert) = stmt else {
+        return 0;
+    };
+    match insert.source.as_ref().map(|source| source.body.as_ref()) {
+        Some(SetExpr::Values(values)) => values.rows.iter().map(Vec::len).sum(),
+        _ => 0,
+    }
+}
+
+/// Sort the INSERT column list of `stmt` and its VALUES rows in place
+pub fn sort_columns(stmt: &mut Statement) {
+    let Statement::Insert(insert) = stmt else {
+        return;
+    };
+    let Some(SetExpr::Values(values)) = insert.source.as_mut().map(|source| source.body.as_mut()) else {
+        return;
+    };
+    let len = insert.columns.len();
+    if len < 2 || values.rows.iter().any(|row| row.len() != len) {
+        return;
+    }
+    let mut order: Vec<usize> = (0..len).collect();
+    order.sort_by_cached_key(|&i| key(&insert.columns[i]));
+    permute(&mut insert.columns, &order);
+    for row in &mut values.rows {
+        permute(row, &order);
+    }
+}
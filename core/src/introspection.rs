@@ -0,0 +1,167 @@
+//! Detection of introspection statements, for `metadata["statement_class"]`
+//! and `metadata["introspected_objects"]`.
+//!
+//! Connection pools and ORMs warm up by asking the server about its schema:
+//! `SHOW TABLES`, `SHOW COLUMNS FROM t`, `DESCRIBE t`, SQLite's `PRAGMA
+//! table_info(t)`, or queries reading only catalog relations
+//! (`information_schema`, `pg_catalog` and the `pg_*` tables psql's `\d`
+//! reads, SQL Server's `sys`, MySQL's `performance_schema`, `sqlite_master`).
+//! Such statements are classed as `"introspection"`, and the objects they ask
+//! about are listed: the table or database a `SHOW` is scoped to, or the
+//! strings a catalog query compares its name columns to (`table_name =
+//! 'users'`, `'users'::regclass`). They are taken before literals are
+//! parameterized, as the statement was written.
+
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    visit_expressions, BinaryOperator, Expr, ObjectName, ShowStatementOptions, Statement, Value,
+};
+
+use crate::metadata::MetaValue;
+use crate::scope;
+
+/// Schemas holding the system catalog
+const CATALOG_SCHEMAS: &[&str] = &["information_schema", "pg_catalog", "performance_schema", "sys"];
+
+/// Unqualified catalog relations
+const CATALOG_TABLES: &[&str] = &["sqlite_master", "sqlite_schema", "sqlite_temp_master"];
+
+/// Catalog columns naming the object a row describes
+const NAME_COLUMNS: &[&str] = &[
+    "name",
+    "nspname",
+    "relname",
+    "schema_name",
+    "schemaname",
+    "table_catalog",
+    "table_name",
+    "table_schema",
+    "tablename",
+    "tbl_name",
+    "viewname",
+];
+
+fn is_catalog(name: &ObjectName) -> bool {
+    let parts: Vec<String> =
+        name.0.iter().map(|part| part.as_ident().map_or_else(|| part.to_string(), |ident| ident.value.clone())).collect();
+    match parts.as_slice() {
+        [] => false,
+        [table] => {
+            table.to_lowercase().starts_with("pg_") || CATALOG_TABLES.iter().any(|t| table.eq_ignore_ascii_case(t))
+        }
+        [.., schema, _] => CATALOG_SCHEMAS.iter().any(|s| schema.eq_ignore_ascii_case(s)),
+    }
+}
+
+fn is_name_column(expr: &Expr) -> bool {
+    let ident = match expr {
+        Expr::Identifier(ident) => ident,
+        Expr::CompoundIdentifier(idents) => match idents.last() {
+            Some(ident) => ident,
+            None => return false,
+        },
+        _ => return false,
+    };
+    NAME_COLUMNS.iter().any(|column| ident.value.eq_ignore_ascii_case(column))
+}
+
+fn unquoted(value: &Value) -> Option<&str> {
+    match value {
+        Value::SingleQuotedString(s) | Value::DoubleQuotedString(s) | Value::NationalStringLiteral(s) => Some(s.as_str()),
+        _ => None,
+    }
+}
+
+fn string(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::Value(value) => unquoted(&value.value),
+        _ => None,
+    }
+}
+
+/// The strings a catalog query matches its name columns against
+fn compared_names(stmt: &Statement) -> Vec<String> {
+    let mut out = Vec::new();
+    let _ = visit_expressions(stmt, |expr| {
+        match expr {
+            Expr::BinaryOp { left, op: BinaryOperator::Eq, right } => {
+                let value = if is_name_column(left) {
+                    string(right)
+                } else if is_name_column(right) {
+                    string(left)
+                } else {
+                    None
+                };
+                out.extend(value.map(str::to_string));
+            }
+            Expr::InList { expr, list, negated: false } if is_name_column(expr) => {
+                out.extend(list.iter().filter_map(string).map(str::to_string));
+            }
+            Expr::Cast { expr, data_type, .. } if data_type.to_string().eq_ignore_ascii_case("regclass") => {
+                out.extend(string(expr).map(str::to_string));
+            }
+            _ => {}
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    out
+}
+
+fn show_scope(options: &ShowStatementOptions) -> Vec<String> {
+    options.show_in.iter().filter_map(|show_in| show_in.parent_name.as_ref()).map(ToString::to_string).collect()
+}
+
+/// The objects `stmt` asks about, or `None` if it is not an introspection
+/// statement
+fn introspected_objects(stmt: &Statement) -> Option<Vec<String>> {
+    let objects = match stmt {
+        Statement::ShowTables { show_options, .. }
+        | Statement::ShowColumns { show_options, .. }
+        | Statement::ShowDatabases { show_options, .. }
+        | Statement::ShowSchemas { show_options, .. }
+        | Statement::ShowViews { show_options, .. } => show_scope(show_options),
+        Statement::ShowObjects(show) => show_scope(&show.show_options),
+        Statement::ShowCreate { obj_name, .. } => vec![obj_name.to_string()],
+        Statement::ExplainTable { table_name, .. } => vec![table_name.to_string()],
+        Statement::ShowVariable { .. }
+        | Statement::ShowVariables { .. }
+        | Statement::ShowStatus { .. }
+        | Statement::ShowFunctions { .. }
+        | Statement::ShowCollation { .. }
+        | Statement::ShowCharset(_) => Vec::new(),
+        Statement::Pragma { value, .. } => value.iter().filter_map(unquoted).map(str::to_string).collect(),
+        Statement::Query(_) => {
+            let mut relations = 0;
+            // A CTE over the catalog is as much introspection as the catalog
+            let catalog = scope::visit_tables(stmt, |name| {
+                relations += 1;
+                if is_catalog(name) {
+                    ControlFlow::Continue(())
+                } else {
+                    ControlFlow::Break(())
+                }
+            });
+            if catalog.is_break() || relations == 0 {
+                return None;
+            }
+            compared_names(stmt)
+        }
+        _ => return None,
+    };
+    Some(objects)
+}
+
+/// `metadata["statement_class"]` and `metadata["introspected_objects"]`,
+/// set only for introspection statements, the objects only when there are
+/// some
+pub fn introspection_metadata(stmt: &Statement) -> Vec<(String, MetaValue)> {
+    let Some(objects) = introspected_objects(stmt) else {
+        return Vec::new();
+    };
+    let mut out = vec![("statement_class".to_string(), "introspection".into())];
+    if !objects.is_empty() {
+        out.push(("introspected_objects".to_string(), MetaValue::List(objects.into_iter().map(Into::into).collect())));
+    }
+    out
+}
@@ -0,0 +1,36 @@
+//! Literals `literal-parameterize` leaves as written, for the `keep_limit`,
+//! `keep_offset` and `keep_null` options.
+//!
+//! `LIMIT 10` and `LIMIT 10000` are different workloads to some, a page and
+//! a dump, and the same query to others. Kept literals stay in `normalized`
+//! and out of `params`, so that statements differing in them get different
+//! fingerprints. `NULL` is kept by default, as it is rarely a value bound by
+//! the application; `keep_null=False` replaces it like any other literal.
+
+use crate::Clause;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Keep {
+    /// The literals of `LIMIT`, `FETCH` and `LIMIT ... BY`
+    pub limit: bool,
+    /// The literals of `OFFSET`, also when written `LIMIT offset, count`
+    pub offset: bool,
+    pub null: bool,
+}
+
+impl Default for Keep {
+    fn default() -> Self {
+        Keep { limit: false, offset: false, null: true }
+    }
+}
+
+impl Keep {
+    /// Whether the literals of `clause` are kept
+    pub fn clause(&self, clause: Clause) -> bool {
+        match clause {
+            Clause::Limit => self.limit,
+            Clause::Offset => self.offset,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! Statements sqlparser cannot parse, for `lenient`.
+//!
+//! Vendor syntax it does not know, and SQL that is simply broken, would
+//! otherwise get no fingerprint at all. Such a statement is normalized token
+//! by token, as a `LOAD DATA` is: the words sqlparser knows as keywords
+//! uppercased, whitespace and comments collapsed, literals replaced and the
+//! other tokens kept as written. A name is written against the parenthesis
+//! after it, as calls are, and a keyword is not. Without the tree, none of
+//! the other passes apply, so spellings of a query that the AST path brings
+//! together may stay apart here.
+
+use sqlparser::dialect::Dialect;
+use sqlparser::keywords::Keyword;
+use sqlparser::tokenizer::Token;
+
+use crate::bulk::{is_blank, literal, tokenize, word, Piece, Tokens};
+use crate::classify::{classify_unparsed, Class};
+use crate::Clause;
+
+/// The first statement of `sql` normalized token by token, with its class,
+/// unless it has no tokens or does not tokenize
+pub fn tokens(sql: &str, dialect: &dyn Dialect) -> Option<(Tokens, Class)> {
+    let tokens = tokenize(sql, dialect)?;
+    let mut tokens: Vec<&Token> = tokens.iter().map(|t| &t.token).filter(|t| !is_blank(t)).collect();
+    if let Some(end) = tokens.iter().position(|t| matches!(t, Token::SemiColon)) {
+        tokens.truncate(end);
+    }
+    let class = classify_unparsed(word(tokens.first()?).as_deref());
+    let pieces = tokens
+        .into_iter()
+        .map(|token| match (literal(token), token) {
+            (Some(mut literal), _) => {
+                literal.clause = Clause::Other;
+                Piece::Literal(literal)
+            }
+            (None, Token::Word(w)) if w.quote_style.is_none() && w.keyword != Keyword::NoKeyword => {
+                Piece::Keyword(w.value.to_ascii_uppercase())
+            }
+            _ => Piece::Text(token.to_string()),
+        })
+        .collect();
+    Some((Tokens::new(pieces, true), class))
+}
@@ -0,0 +1,2832 @@
+//! SQL normalization and fingerprinting.
+//!
+//! [`normalize()`] parses a statement with the grammar of a [`Dialect`],
+//! replaces its literals with placeholders, runs the normalization passes
+//! that [`Options`] selects and returns its [`Fingerprint`]: the normalized
+//! text, its hash and the literals taken out of it. It is the code the
+//! `sqlfp` Python module runs, without Python, for log processors and proxies
+//! written in Rust.
+//!
+//! ```
+//! use sqlfp_core::{normalize, Dialect, Options};
+//!
+//! let fingerprint = normalize("SELECT * FROM users WHERE id = 42", Dialect::POSTGRESQL, &Options::default())?;
+//! assert_eq!(fingerprint.normalized, "SELECT * FROM users WHERE id = ?");
+//! assert_eq!(fingerprint.params, ["42"]);
+//! # Ok::<(), sqlfp_core::Error>(())
+//! ```
+//!
+//! The modules are the building blocks of the Python module's other entry
+//! points, and have no stability guarantees of their own.
+
+pub mod aggregate;
+pub mod aliases;
+pub mod anonymize;
+pub mod ast;
+pub mod backticks;
+pub mod bind;
+pub mod binds;
+pub mod booleans;
+pub mod builtins;
+pub mod bulk;
+pub mod bundle;
+pub mod cartesian;
+pub mod case;
+pub mod cast;
+pub mod classify;
+pub mod clauses;
+pub mod collapse;
+pub mod collision;
+pub mod columns;
+pub mod comments;
+pub mod commutative;
+pub mod concat;
+pub mod config;
+pub mod corpus;
+pub mod coverage;
+pub mod cte;
+pub mod current_time;
+pub mod depth;
+pub mod detect;
+pub mod diff;
+pub mod dialects;
+pub mod explain;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod grouping;
+pub mod hashing;
+pub mod identifier_case;
+pub mod insert;
+pub mod introspection;
+pub mod keep;
+pub mod lenient;
+pub mod logs;
+pub mod mask;
+pub mod metadata;
+pub mod metrics;
+pub mod natural;
+pub mod offsets;
+pub mod msgpack;
+pub mod paramstyle;
+pub mod pool;
+pub mod percona;
+pub mod positional;
+pub mod precedence;
+pub mod prepared;
+pub mod qualifiers;
+pub mod registry;
+pub mod rewrite;
+pub mod routing;
+pub mod scope;
+pub mod script;
+pub mod session;
+pub mod setops;
+pub mod similarity;
+pub mod stats;
+pub mod stream;
+pub mod synonyms;
+pub mod tables;
+pub mod template;
+pub mod trailing;
+pub mod truncated;
+pub mod using;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod wildcard;
+pub mod wire;
+pub mod writer;
+
+use sqlparser::ast::{
+    Assignment, BinaryOperator, Distinct, DuplicateTreatment, Expr, Fetch, FromTable, Function, FunctionArgumentClause, FunctionArguments,
+    GroupByExpr, JoinConstraint, JoinOperator, LimitClause, ObjectName, ObjectNamePart, OnConflict, OnConflictAction,
+    OnInsert, OrderByExpr, OrderByKind, OrderByOptions, Query, SelectItem, SetExpr, Statement, TableFactor,
+    UnaryOperator, TypedString, UpdateTableFromKind, Value, ValueWithSpan, VisitMut, VisitorMut, WindowType,
+    visit_expressions_mut,
+};
+use sqlparser::dialect::{
+    AnsiDialect, BigQueryDialect, ClickHouseDialect, DatabricksDialect, DuckDbDialect, GenericDialect, HiveDialect,
+    MsSqlDialect, MySqlDialect, OracleDialect, PostgreSqlDialect, RedshiftSqlDialect, SQLiteDialect, SnowflakeDialect,
+};
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::Span;
+use core::ops::ControlFlow;
+use std::any::TypeId;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasherDefault, Hasher};
+use metadata::{MetaValue, Metadata};
+use precedence::Precedence;
+
+/// `Fingerprint.parse_path` of statements normalized from their AST
+pub const PARSE_PATH_AST: &str = "ast";
+
+/// `Fingerprint.parse_path` of statements normalized from their tokens
+pub const PARSE_PATH_TOKENS: &str = "tokens";
+
+/// Start of the error message of a dialect name that is not supported
+pub const UNSUPPORTED_DIALECT: &str = "Unsupported dialect: ";
+
+/// Error message of an input without a statement
+pub const NO_STATEMENT: &str = "No SQL statement found";
+
+pub fn canonical_dialect(dialect: &str) -> Result<&'static str, String> {
+    match dialect.to_lowercase().as_str() {
+        "auto" => Ok("auto"),
+        "mysql" | "mariadb" => Ok("mysql"),
+        "postgresql" | "postgres" => Ok("postgresql"),
+        "sqlite" => Ok("sqlite"),
+        "generic" => Ok("generic"),
+        "ansi" => Ok("ansi"),
+        "mssql" => Ok("mssql"),
+        "oracle" => Ok("oracle"),
+        "bigquery" => Ok("bigquery"),
+        "snowflake" => Ok("snowflake"),
+        "clickhouse" => Ok("clickhouse"),
+        "duckdb" => Ok("duckdb"),
+        "hive" => Ok("hive"),
+        "redshift" => Ok("redshift"),
+        "databricks" | "spark" => Ok("databricks"),
+        name => match dialects::spec(name) {
+            Some(spec) => Ok(spec.name),
+            None => Err(format!("{}{}", UNSUPPORTED_DIALECT, dialect)),
+        },
+    }
+}
+
+pub fn get_dialect(dialect: &str) -> Result<Box<dyn sqlparser::dialect::Dialect + Send + Sync>, String> {
+    Ok(match canonical_dialect(dialect)? {
+        "mysql" => Box::new(MySqlDialect {}),
+        "postgresql" => Box::new(PostgreSqlDialect {}),
+        "sqlite" => Box::new(SQLiteDialect {}),
+        "ansi" => Box::new(AnsiDialect {}),
+        "mssql" => Box::new(MsSqlDialect {}),
+        "oracle" => Box::new(OracleDialect {}),
+        "bigquery" => Box::new(BigQueryDialect {}),
+        "snowflake" => Box::new(SnowflakeDialect {}),
+        "clickhouse" => Box::new(ClickHouseDialect {}),
+        "duckdb" => Box::new(DuckDbDialect {}),
+        "hive" => Box::new(HiveDialect {}),
+        "redshift" => Box::new(RedshiftSqlDialect {}),
+        "databricks" => Box::new(DatabricksDialect {}),
+        name => match dialects::spec(name) {
+            Some(spec) => Box::new(dialects::PseudoDialect::new(spec)?),
+            // `"auto"` too, where there is no statement to detect it from
+            None => Box::new(GenericDialect {}),
+        },
+    })
+}
+
+/// The dialect to read `sql` with: `dialect`, or the one detected for `sql`
+/// if it is `"auto"`
+pub fn sql_dialect(dialect: &str, sql: &str) -> Result<Box<dyn sqlparser::dialect::Dialect + Send + Sync>, String> {
+    match canonical_dialect(dialect)? {
+        "auto" => get_dialect(detect::detect(sql)),
+        _ => get_dialect(dialect),
+    }
+}
+
+/// Whether `dialect` reads `"..."` as a string, where sqlparser parses it as
+/// a quoted name (MySQL, BigQuery, Hive)
+pub fn string_quotes(dialect: &dyn sqlparser::dialect::Dialect) -> bool {
+    let id = dialect.dialect();
+    id == TypeId::of::<MySqlDialect>() || id == TypeId::of::<BigQueryDialect>() || id == TypeId::of::<HiveDialect>()
+}
+
+// ---- Normalization rules ----
+
+/// The set of normalization rules applied to a statement.
+///
+/// Every rule that changes fingerprints gets a flag here, so that the exact
+/// behavior of a past release can be reproduced through `compat`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rules {
+    /// INNER JOIN → JOIN, LEFT OUTER JOIN → LEFT JOIN, RIGHT OUTER JOIN → RIGHT JOIN
+    pub join_keywords: bool,
+    /// `FROM users AS u` → `FROM users u`
+    pub implicit_aliases: bool,
+    /// Drop explicit ASC in ORDER BY, window ORDER BY and, with
+    /// `aggregate_clauses`, aggregate orderings
+    pub strip_default_asc: bool,
+    /// Drop `NULLS FIRST`/`NULLS LAST` where they state the dialect's default
+    pub strip_default_nulls: bool,
+    /// `SELECT ALL` → `SELECT` and, with `aggregate_clauses`, `SUM(ALL a)` →
+    /// `SUM(a)`
+    pub strip_select_all: bool,
+    /// Whether the dialect sorts NULLs as the largest values (`Some(true)`)
+    /// or the smallest; `None` if it is not known. Set from the dialect, not
+    /// by a pass.
+    pub nulls_high: Option<bool>,
+    /// Strip all parentheses, then re-add only those required by precedence
+    pub canonical_parens: bool,
+    /// The precedence tables parentheses are re-added by. Set from the
+    /// dialect where `dialect_precedence`, not by a pass.
+    pub precedence: Precedence,
+    /// Uppercase unquoted function names
+    pub uppercase_functions: bool,
+    /// Read the TRUE/FALSE that `identifier_booleans` dialects parse as
+    /// identifiers as booleans: uppercase them, and parameterize them with
+    /// the literals
+    pub uppercase_booleans: bool,
+    /// Whether the dialect has no boolean literals and parses TRUE/FALSE as
+    /// identifiers (MSSQL, Oracle). Set from the dialect, not by a pass.
+    pub identifier_booleans: bool,
+    /// Whether the dialect reads `"..."` as a string, which sqlparser parses
+    /// as a quoted name (MySQL, BigQuery, Hive). Set from the dialect, not by
+    /// a pass.
+    pub string_quotes: bool,
+    /// `x::int` → `CAST(x AS INTEGER)`: casts in the standard syntax, with
+    /// canonical type names
+    pub canonical_casts: bool,
+    /// The dialect names its types `INT4`, `INT8`, `FLOAT8`, ... as PostgreSQL
+    /// does (PostgreSQL, Redshift, DuckDB). Set from the dialect, not by a pass.
+    pub pg_type_names: bool,
+    /// Rename CTEs to `cte_1`, `cte_2`, … (opt-in)
+    pub canonical_cte_names: bool,
+    /// Sort INSERT column lists and their VALUES rows by column name (opt-in)
+    pub sorted_insert_columns: bool,
+    /// Strip system schemas from function names and lowercase the other
+    /// schema qualifiers, leaving case rules to the name itself (opt-in)
+    pub function_schemas: bool,
+    /// Fold unary minus over a numeric literal into a negative literal (opt-in)
+    pub signed_literals: bool,
+    /// Rewrite `CONCAT()` calls, and string `+` where `plus_concat`, as `||`
+    /// chains (opt-in)
+    pub concat_operators: bool,
+    /// `+` concatenates strings (MSSQL). Set from the dialect, not by a pass.
+    pub plus_concat: bool,
+    /// Backquotes only let names hold other characters and keywords, not
+    /// change how they resolve (BigQuery), so they are dropped where they
+    /// are not needed. Set from the dialect, not by a pass.
+    pub plain_backticks: bool,
+    /// Rewrite simple CASE in the searched form and drop `ELSE NULL` (opt-in)
+    pub searched_case: bool,
+    /// Write `a <=> b` as `a IS NOT DISTINCT FROM b` (opt-in)
+    pub distinct_from: bool,
+    /// Collapse runs of CASE arms of the same shape to one arm (opt-in)
+    pub collapsed_case_arms: bool,
+    /// `SELECT a.*, b.* FROM a JOIN b` → `SELECT * FROM a JOIN b` (opt-in)
+    pub canonical_wildcards: bool,
+    /// Write `ROLLUP`, `CUBE`, `WITH ROLLUP` and the empty grouping set as the
+    /// `GROUPING SETS` they stand for (opt-in)
+    pub canonical_grouping_sets: bool,
+    /// `JOIN b ON a.id = b.id` → `JOIN b USING (id)` (opt-in)
+    pub using_joins: bool,
+    /// `5 = id` → `id = 5`: the operands of commutative operators in a
+    /// canonical order (opt-in)
+    pub commutative_operands: bool,
+    /// `b = ? AND a = ?` → `a = ? AND b = ?`: the operands of AND and OR
+    /// chains in a canonical order (opt-in)
+    pub sorted_predicates: bool,
+    /// Write `NOW()`, `GETDATE()`, `CURDATE()`, ... as `CURRENT_TIMESTAMP`,
+    /// `CURRENT_DATE` and `CURRENT_TIME` (opt-in)
+    pub current_time: bool,
+    /// Write the builtin functions that are synonyms in every dialect with
+    /// one name (`IFNULL` → `COALESCE`, `NOW()` → `CURRENT_TIMESTAMP`) (opt-in)
+    pub builtin_synonyms: bool,
+    /// With `function_case="builtins"`, the dialect whose builtin functions
+    /// are the only ones uppercased. Set from the option, not by a pass.
+    pub builtins_only: Option<&'static str>,
+    /// With `identifier_case="lower"` or `"upper"`, the case unquoted names
+    /// are folded to. Set from the option, not by a pass.
+    pub identifier_case: Option<identifier_case::Fold>,
+    /// With `ignore_aliases`, rename aliases to `t1`, `t2`, … and `c1`, `c2`,
+    /// …. Set from the option, not by a pass.
+    pub positional_aliases: bool,
+    /// Apply the structural rules to the source query of `INSERT ... SELECT`.
+    /// No pass applies this flag; it widens the reach of the others.
+    pub insert_sources: bool,
+    /// Apply the structural rules to the statement an `EXPLAIN` wraps. No
+    /// pass applies this flag; it widens the reach of the others.
+    pub explain_statements: bool,
+    /// Drop the `DISTINCT` of `UNION DISTINCT`, `INTERSECT DISTINCT` and
+    /// `EXCEPT DISTINCT` with `strip_select_all`, and with `canonical_parens`
+    /// strip the parentheses around set operation branches, keeping those
+    /// the operator precedence needs. No pass applies this flag; it widens the
+    /// reach of the others.
+    pub set_operations: bool,
+    /// Reach into the clauses of aggregate calls: drop the `ALL` of their
+    /// arguments with `strip_select_all`, apply the ORDER BY rules to
+    /// the ordering of their arguments (`ARRAY_AGG(a ORDER BY b)`) and to
+    /// `WITHIN GROUP`, parameterize `GROUP_CONCAT(... SEPARATOR ',')`, and take
+    /// the literals of `WITHIN GROUP`, `FILTER` and `OVER` in the order they are
+    /// written. No pass applies this flag; it widens the reach of the others.
+    pub aggregate_clauses: bool,
+    /// Parameterize the literals of typed strings (`DATE '...'`) and of
+    /// `MATCH ... AGAINST`. No pass applies this flag; it widens the reach of
+    /// the literal pass.
+    pub special_form_literals: bool,
+    /// Keep the `true` of `t.true`, which sqlparser parses as a boolean in a
+    /// field access, as the column it names. No pass applies this flag; it
+    /// narrows the reach of the literal pass.
+    pub boolean_columns: bool,
+    /// Number the placeholders of literals after the highest bind of the same
+    /// style already in the statement, rather than from `placeholder_start`
+    /// regardless. No pass applies this flag.
+    pub bind_numbering: bool,
+    /// Re-add parentheses by the precedence of the dialect, and around the
+    /// operands of `IS`, `LIKE`, `BETWEEN` and `IN` too, rather than by one
+    /// table for operators under operators. No pass applies this flag.
+    pub dialect_precedence: bool,
+    /// Write quoted binds (`:"Name"`) with their quotes, which sqlparser
+    /// drops. No pass applies this flag.
+    pub quoted_binds: bool,
+    /// Keep double-quoted names, parameterizing them with the literals only
+    /// where `string_quotes` rather than in every dialect. No pass applies
+    /// this flag; it narrows the reach of the literal pass.
+    pub quoted_names: bool,
+    /// Follow PostgreSQL's query jumbling: with `implicit_aliases`, drop the
+    /// aliases the server ignores; run the opt-in passes whose rewrites it
+    /// makes as well by default; add `metadata["query_id"]`. No pass applies
+    /// this flag.
+    pub jumbling: bool,
+    /// Write `normalized` as pt-fingerprint does, from the text of the
+    /// statement, and add `metadata["checksum"]`. No pass applies this flag.
+    pub percona: bool,
+}
+
+impl Rules {
+    /// Rules of the 0.1.x releases
+    const V0_1: Rules = Rules {
+        join_keywords: true,
+        implicit_aliases: true,
+        strip_default_asc: true,
+        strip_default_nulls: false,
+        strip_select_all: false,
+        nulls_high: None,
+        canonical_parens: true,
+        precedence: Precedence::Legacy,
+        uppercase_functions: true,
+        uppercase_booleans: true,
+        identifier_booleans: false,
+        string_quotes: false,
+        canonical_casts: false,
+        pg_type_names: false,
+        canonical_cte_names: false,
+        sorted_insert_columns: false,
+        function_schemas: false,
+        signed_literals: false,
+        concat_operators: false,
+        plus_concat: false,
+        plain_backticks: false,
+        searched_case: false,
+        distinct_from: false,
+        collapsed_case_arms: false,
+        current_time: false,
+        builtin_synonyms: false,
+        canonical_wildcards: false,
+        canonical_grouping_sets: false,
+        using_joins: false,
+        commutative_operands: false,
+        sorted_predicates: false,
+        builtins_only: None,
+        identifier_case: None,
+        positional_aliases: false,
+        insert_sources: false,
+        explain_statements: false,
+        set_operations: false,
+        aggregate_clauses: false,
+        special_form_literals: false,
+        boolean_columns: false,
+        bind_numbering: false,
+        dialect_precedence: false,
+        quoted_binds: false,
+        quoted_names: false,
+        jumbling: false,
+        percona: false,
+    };
+
+    /// Rules applied when no `compat` version is requested
+    pub const LATEST: Rules = Rules {
+        strip_default_nulls: true,
+        strip_select_all: true,
+        canonical_casts: true,
+        canonical_cte_names: true,
+        sorted_insert_columns: true,
+        function_schemas: true,
+        signed_literals: true,
+        concat_operators: true,
+        searched_case: true,
+        distinct_from: true,
+        collapsed_case_arms: true,
+        current_time: true,
+        builtin_synonyms: true,
+        canonical_wildcards: true,
+        canonical_grouping_sets: true,
+        using_joins: true,
+        commutative_operands: true,
+        sorted_predicates: true,
+        insert_sources: true,
+        explain_statements: true,
+        set_operations: true,
+        aggregate_clauses: true,
+        special_form_literals: true,
+        boolean_columns: true,
+        bind_numbering: true,
+        dialect_precedence: true,
+        quoted_binds: true,
+        quoted_names: true,
+        ..Rules::V0_1
+    };
+
+    /// Rules of `compat="pg_stat_statements"`: those of the installed
+    /// release that PostgreSQL's query jumbling agrees with. The server keeps
+    /// CTE names, column order, function and operator choices, grouping and
+    /// join spellings apart, so the passes rewriting those are not provided.
+    const PG_STAT_STATEMENTS: Rules = Rules {
+        canonical_cte_names: false,
+        sorted_insert_columns: false,
+        concat_operators: false,
+        searched_case: false,
+        distinct_from: false,
+        collapsed_case_arms: false,
+        current_time: false,
+        builtin_synonyms: false,
+        canonical_grouping_sets: false,
+        using_joins: false,
+        commutative_operands: false,
+        sorted_predicates: false,
+        jumbling: true,
+        ..Rules::LATEST
+    };
+
+    /// Rules of `compat="percona"`: the installed release's, for the
+    /// parameters and metadata, with pt-fingerprint writing `normalized`
+    const PERCONA: Rules = Rules { percona: true, ..Rules::LATEST };
+
+    const NONE: Rules = Rules {
+        join_keywords: false,
+        implicit_aliases: false,
+        strip_default_asc: false,
+        strip_default_nulls: false,
+        strip_select_all: false,
+        nulls_high: None,
+        canonical_parens: false,
+        precedence: Precedence::Legacy,
+        uppercase_functions: false,
+        uppercase_booleans: false,
+        identifier_booleans: false,
+        string_quotes: false,
+        canonical_casts: false,
+        pg_type_names: false,
+        canonical_cte_names: false,
+        sorted_insert_columns: false,
+        function_schemas: false,
+        signed_literals: false,
+        concat_operators: false,
+        plus_concat: false,
+        plain_backticks: false,
+        searched_case: false,
+        distinct_from: false,
+        collapsed_case_arms: false,
+        current_time: false,
+        builtin_synonyms: false,
+        canonical_wildcards: false,
+        canonical_grouping_sets: false,
+        using_joins: false,
+        commutative_operands: false,
+        sorted_predicates: false,
+        builtins_only: None,
+        identifier_case: None,
+        positional_aliases: false,
+        insert_sources: false,
+        explain_statements: false,
+        set_operations: false,
+        aggregate_clauses: false,
+        special_form_literals: false,
+        boolean_columns: false,
+        bind_numbering: false,
+        dialect_precedence: false,
+        quoted_binds: false,
+        quoted_names: false,
+        jumbling: false,
+        percona: false,
+    };
+
+    /// `NONE`, with the flags of `self` that no pass applies
+    fn scope(&self) -> Rules {
+        Rules {
+            plus_concat: self.plus_concat,
+            plain_backticks: self.plain_backticks,
+            nulls_high: self.nulls_high,
+            precedence: self.precedence,
+            identifier_booleans: self.identifier_booleans,
+            string_quotes: self.string_quotes,
+            pg_type_names: self.pg_type_names,
+            builtins_only: self.builtins_only,
+            identifier_case: self.identifier_case,
+            positional_aliases: self.positional_aliases,
+            insert_sources: self.insert_sources,
+            explain_statements: self.explain_statements,
+            set_operations: self.set_operations,
+            aggregate_clauses: self.aggregate_clauses,
+            special_form_literals: self.special_form_literals,
+            boolean_columns: self.boolean_columns,
+            bind_numbering: self.bind_numbering,
+            quoted_binds: self.quoted_binds,
+            quoted_names: self.quoted_names,
+            jumbling: self.jumbling,
+            percona: self.percona,
+            ..Rules::NONE
+        }
+    }
+}
+
+// ---- Normalization passes ----
+
+/// A named normalization pass. Each one applies a single flag of `Rules`,
+/// except `LiteralParameterize`, which replaces literals with placeholders.
+/// Opt-in passes only run when enabled or listed explicitly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Pass {
+    AliasNormalize,
+    JoinNormalize,
+    AscStrip,
+    NullsStrip,
+    SelectAllStrip,
+    ParenCanonicalize,
+    FunctionUppercase,
+    BooleanUppercase,
+    CastCanonicalize,
+    CteRename,
+    InsertColumnSort,
+    FunctionSchemaNormalize,
+    NegativeLiteralFold,
+    ConcatCanonicalize,
+    CaseCanonicalize,
+    DistinctFromCanonicalize,
+    CaseArmCollapse,
+    CurrentTimeCanonicalize,
+    FunctionSynonymCanonicalize,
+    WildcardCanonicalize,
+    GroupingCanonicalize,
+    JoinUsingCanonicalize,
+    CommutativeCanonicalize,
+    PredicateSort,
+    LiteralParameterize,
+}
+
+impl Pass {
+    /// All passes, in the order they run by default
+    pub const ALL: [Pass; 25] = [
+        Pass::AliasNormalize,
+        Pass::JoinNormalize,
+        Pass::AscStrip,
+        Pass::NullsStrip,
+        Pass::SelectAllStrip,
+        Pass::ParenCanonicalize,
+        Pass::FunctionUppercase,
+        Pass::BooleanUppercase,
+        Pass::CastCanonicalize,
+        Pass::CteRename,
+        Pass::InsertColumnSort,
+        Pass::FunctionSchemaNormalize,
+        Pass::NegativeLiteralFold,
+        Pass::ConcatCanonicalize,
+        Pass::CaseCanonicalize,
+        Pass::DistinctFromCanonicalize,
+        Pass::CaseArmCollapse,
+        Pass::CurrentTimeCanonicalize,
+        Pass::FunctionSynonymCanonicalize,
+        Pass::WildcardCanonicalize,
+        Pass::GroupingCanonicalize,
+        Pass::JoinUsingCanonicalize,
+        Pass::CommutativeCanonicalize,
+        Pass::PredicateSort,
+        Pass::LiteralParameterize,
+    ];
+
+    /// Whether the pass is left out of the default pipeline
+    pub fn opt_in(self) -> bool {
+        matches!(
+            self,
+            Pass::CteRename
+                | Pass::InsertColumnSort
+                | Pass::FunctionSchemaNormalize
+                | Pass::NegativeLiteralFold
+                | Pass::ConcatCanonicalize
+                | Pass::CaseCanonicalize
+                | Pass::DistinctFromCanonicalize
+                | Pass::CaseArmCollapse
+                | Pass::CurrentTimeCanonicalize
+                | Pass::FunctionSynonymCanonicalize
+                | Pass::WildcardCanonicalize
+                | Pass::GroupingCanonicalize
+                | Pass::JoinUsingCanonicalize
+                | Pass::CommutativeCanonicalize
+                | Pass::PredicateSort
+        )
+    }
+
+    /// Whether PostgreSQL's query jumbling makes the rewrite of this opt-in
+    /// pass as well, so that it runs by default under `jumbling` rules
+    fn jumbled(self) -> bool {
+        matches!(self, Pass::FunctionSchemaNormalize | Pass::NegativeLiteralFold | Pass::WildcardCanonicalize)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Pass::AliasNormalize => "alias-normalize",
+            Pass::JoinNormalize => "join-normalize",
+            Pass::AscStrip => "asc-strip",
+            Pass::NullsStrip => "nulls-strip",
+            Pass::SelectAllStrip => "select-all-strip",
+            Pass::ParenCanonicalize => "paren-canonicalize",
+            Pass::FunctionUppercase => "function-uppercase",
+            Pass::BooleanUppercase => "boolean-uppercase",
+            Pass::CastCanonicalize => "cast-canonicalize",
+            Pass::CteRename => "cte-rename",
+            Pass::InsertColumnSort => "insert-column-sort",
+            Pass::FunctionSchemaNormalize => "function-schema-normalize",
+            Pass::NegativeLiteralFold => "negative-literal-fold",
+            Pass::ConcatCanonicalize => "concat-canonicalize",
+            Pass::CaseCanonicalize => "case-canonicalize",
+            Pass::DistinctFromCanonicalize => "distinct-from-canonicalize",
+            Pass::CaseArmCollapse => "case-arm-collapse",
+            Pass::CurrentTimeCanonicalize => "current-time-canonicalize",
+            Pass::FunctionSynonymCanonicalize => "function-synonym-canonicalize",
+            Pass::WildcardCanonicalize => "wildcard-canonicalize",
+            Pass::GroupingCanonicalize => "grouping-canonicalize",
+            Pass::JoinUsingCanonicalize => "join-using-canonicalize",
+            Pass::CommutativeCanonicalize => "commutative-canonicalize",
+            Pass::PredicateSort => "predicate-sort",
+            Pass::LiteralParameterize => "literal-parameterize",
+        }
+    }
+
+    fn from_name(name: &str) -> Result<Pass, String> {
+        Pass::ALL
+            .into_iter()
+            .find(|pass| pass.name() == name)
+            .ok_or_else(|| format!("Unknown normalization pass: {}", name))
+    }
+
+    /// The flag of `rules` this pass applies, if it applies one
+    fn rule(self, rules: &mut Rules) -> Option<&mut bool> {
+        match self {
+            Pass::AliasNormalize => Some(&mut rules.implicit_aliases),
+            Pass::JoinNormalize => Some(&mut rules.join_keywords),
+            Pass::AscStrip => Some(&mut rules.strip_default_asc),
+            Pass::NullsStrip => Some(&mut rules.strip_default_nulls),
+            Pass::SelectAllStrip => Some(&mut rules.strip_select_all),
+            Pass::ParenCanonicalize => Some(&mut rules.canonical_parens),
+            Pass::FunctionUppercase => Some(&mut rules.uppercase_functions),
+            Pass::BooleanUppercase => Some(&mut rules.uppercase_booleans),
+            Pass::CastCanonicalize => Some(&mut rules.canonical_casts),
+            Pass::CteRename => Some(&mut rules.canonical_cte_names),
+            Pass::InsertColumnSort => Some(&mut rules.sorted_insert_columns),
+            Pass::FunctionSchemaNormalize => Some(&mut rules.function_schemas),
+            Pass::NegativeLiteralFold => Some(&mut rules.signed_literals),
+            Pass::ConcatCanonicalize => Some(&mut rules.concat_operators),
+            Pass::CaseCanonicalize => Some(&mut rules.searched_case),
+            Pass::DistinctFromCanonicalize => Some(&mut rules.distinct_from),
+            Pass::CaseArmCollapse => Some(&mut rules.collapsed_case_arms),
+            Pass::CurrentTimeCanonicalize => Some(&mut rules.current_time),
+            Pass::FunctionSynonymCanonicalize => Some(&mut rules.builtin_synonyms),
+            Pass::WildcardCanonicalize => Some(&mut rules.canonical_wildcards),
+            Pass::GroupingCanonicalize => Some(&mut rules.canonical_grouping_sets),
+            Pass::JoinUsingCanonicalize => Some(&mut rules.using_joins),
+            Pass::CommutativeCanonicalize => Some(&mut rules.commutative_operands),
+            Pass::PredicateSort => Some(&mut rules.sorted_predicates),
+            Pass::LiteralParameterize => None,
+        }
+    }
+
+    /// Whether `rules` has the flag this pass applies; always true for
+    /// `LiteralParameterize`
+    fn provided_by(self, rules: &Rules) -> bool {
+        self.rule(&mut rules.clone()).is_none_or(|flag| *flag)
+    }
+
+}
+
+/// One step of the normalization pipeline
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Step {
+    Pass(Pass),
+    /// The next caller hook given with the pipeline
+    Hook,
+}
+
+/// Pass names in the order to run them, `None` marking a caller hook
+pub type PassOrder = Vec<Option<String>>;
+
+/// The steps to run: `order` or else the default passes of `rules` and those named in `enabled`, in default order, minus
+/// the passes named in `disabled` and those `rules` does not provide
+fn pipeline(rules: &Rules, order: Option<&PassOrder>, disabled: &[String], enabled: &[String]) -> Result<Vec<Step>, String> {
+    let parse = |names: &[String]| names.iter().map(|name| Pass::from_name(name)).collect::<Result<Vec<_>, _>>();
+    let (disabled, enabled) = (parse(disabled)?, parse(enabled)?);
+    let steps = match order {
+        None => Pass::ALL
+            .into_iter()
+            .filter(|pass| !pass.opt_in() || enabled.contains(pass) || (rules.jumbling && pass.jumbled()))
+            .map(Step::Pass)
+            .collect(),
+        Some(order) => {
+            let mut steps = Vec::with_capacity(order.len());
+            for name in order {
+                let step = match name {
+                    Some(name) => Step::Pass(Pass::from_name(name)?),
+                    None => Step::Hook,
+                };
+                if step != Step::Hook && steps.contains(&step) {
+                    return Err(format!("Normalization pass listed twice: {}", name.as_deref().unwrap_or_default()));
+                }
+                steps.push(step);
+            }
+            steps
+        }
+    };
+    Ok(steps
+        .into_iter()
+        .filter(|step| match step {
+            Step::Pass(pass) => !disabled.contains(pass) && pass.provided_by(rules),
+            Step::Hook => true,
+        })
+        .collect())
+}
+
+/// Frozen rule sets, keyed by the `major.minor` release that introduced them.
+/// Fingerprints are stable within such a release line. `pg_stat_statements`
+/// names the rules that follow the server's notion of query identity instead,
+/// and `percona` those reproducing pt-fingerprint.
+const COMPAT_VERSIONS: &[(&str, Rules)] = &[
+    ("0.1", Rules::V0_1),
+    ("pg_stat_statements", Rules::PG_STAT_STATEMENTS),
+    ("percona", Rules::PERCONA),
+];
+
+fn get_rules(compat: Option<&str>) -> Result<Rules, String> {
+    let Some(version) = compat else {
+        return Ok(Rules::LATEST);
+    };
+    // "0.1", "0.1.4" and "v0.1.4" all select the 0.1 rules
+    let trimmed = version.trim().trim_start_matches('v');
+    let line = match trimmed.match_indices('.').nth(1) {
+        Some((idx, _)) => &trimmed[..idx],
+        None => trimmed,
+    };
+    COMPAT_VERSIONS
+        .iter()
+        .find(|(v, _)| *v == line)
+        .map(|(_, rules)| *rules)
+        .ok_or_else(|| format!("Unsupported compat version: {}", version))
+}
+
+// ---- Structural normalization (aliases, joins, ORDER BY) ----
+
+/// Normalize equivalent join types to their shorter canonical forms:
+/// INNER JOIN → JOIN, LEFT OUTER JOIN → LEFT JOIN, RIGHT OUTER JOIN → RIGHT JOIN
+fn normalize_join_operator(op: &mut JoinOperator) {
+    let taken = std::mem::replace(op, JoinOperator::CrossJoin(JoinConstraint::None));
+    *op = match taken {
+        JoinOperator::Inner(c) => JoinOperator::Join(c),
+        JoinOperator::LeftOuter(c) => JoinOperator::Left(c),
+        JoinOperator::RightOuter(c) => JoinOperator::Right(c),
+        other => other,
+    };
+}
+
+/// Set alias to implicit form (no AS keyword) for all TableFactor variants
+fn normalize_table_factor_alias(tf: &mut TableFactor) {
+    let alias = match tf {
+        TableFactor::Table { ref mut alias, .. }
+        | TableFactor::Derived { ref mut alias, .. }
+        | TableFactor::TableFunction { ref mut alias, .. }
+        | TableFactor::Function { ref mut alias, .. }
+        | TableFactor::UNNEST { ref mut alias, .. }
+        | TableFactor::JsonTable { ref mut alias, .. }
+        | TableFactor::OpenJsonTable { ref mut alias, .. }
+        | TableFactor::NestedJoin { ref mut alias, .. }
+        | TableFactor::Pivot { ref mut alias, .. }
+        | TableFactor::Unpivot { ref mut alias, .. }
+        | TableFactor::MatchRecognize { ref mut alias, .. }
+        | TableFactor::XmlTable { ref mut alias, .. }
+        | TableFactor::SemanticView { ref mut alias, .. } => alias,
+    };
+    if let Some(ref mut a) = alias {
+        a.explicit = false;
+    }
+}
+
+/// Normalize a TableFactor: recurse into subqueries/nested joins and normalize alias
+fn normalize_table_factor(tf: &mut TableFactor, rules: &Rules) {
+    match tf {
+        TableFactor::Derived { ref mut subquery, .. } => {
+            normalize_query_structure(subquery, rules);
+        }
+        TableFactor::NestedJoin { ref mut table_with_joins, .. } => {
+            normalize_table_with_joins(table_with_joins, rules);
+        }
+        _ => {}
+    }
+    if rules.implicit_aliases {
+        normalize_table_factor_alias(tf);
+    }
+}
+
+/// Normalize a TableWithJoins: the relation, all joins, and their table factors
+fn normalize_table_with_joins(twj: &mut sqlparser::ast::TableWithJoins, rules: &Rules) {
+    if rules.using_joins {
+        using::canonicalize(twj);
+    }
+    normalize_table_factor(&mut twj.relation, rules);
+    for join in &mut twj.joins {
+        if rules.join_keywords {
+            normalize_join_operator(&mut join.join_operator);
+        }
+        normalize_table_factor(&mut join.relation, rules);
+    }
+}
+
+/// Normalize a SetExpr (query body): SELECT, UNION, etc.
+fn normalize_set_expr(body: &mut SetExpr, rules: &Rules) {
+    match body {
+        SetExpr::Select(ref mut select) => {
+            if rules.strip_select_all && select.distinct == Some(Distinct::All) {
+                select.distinct = None;
+            }
+            if rules.canonical_wildcards {
+                wildcard::canonicalize(select);
+            }
+            if rules.canonical_grouping_sets {
+                grouping::canonicalize(&mut select.group_by);
+            }
+            for twj in &mut select.from {
+                normalize_table_with_joins(twj, rules);
+            }
+        }
+        SetExpr::SetOperation { ref mut left, ref mut right, ref op, ref mut set_quantifier } => {
+            if rules.set_operations && rules.strip_select_all {
+                setops::strip_distinct(set_quantifier);
+            }
+            normalize_set_expr(left, rules);
+            normalize_set_expr(right, rules);
+            if rules.set_operations && rules.canonical_parens {
+                setops::strip_parens(left, op, false);
+                setops::strip_parens(right, op, true);
+            }
+        }
+        SetExpr::Query(ref mut query) => normalize_query_structure(query, rules),
+        _ => {}
+    }
+}
+
+/// Normalize a Query: CTEs, body, and ORDER BY
+fn normalize_query_structure(query: &mut Query, rules: &Rules) {
+    if let Some(ref mut with) = query.with {
+        for cte in &mut with.cte_tables {
+            normalize_query_structure(&mut cte.query, rules);
+        }
+    }
+    normalize_set_expr(&mut query.body, rules);
+    if let Some(ref mut order_by) = query.order_by {
+        if let OrderByKind::Expressions(ref mut exprs) = order_by.kind {
+            strip_order_defaults(exprs, rules);
+        }
+    }
+}
+
+/// Whether `options` spell out the NULLS ordering the dialect applies anyway:
+/// NULLs sort as the largest values where `nulls_high`, so last ascending and
+/// first descending, and the other way round elsewhere
+pub fn default_nulls(options: &OrderByOptions, nulls_high: bool) -> bool {
+    let descending = options.asc == Some(false);
+    options.nulls_first.is_some_and(|first| first == (descending == nulls_high))
+}
+
+/// Drop the explicit ASC and default NULLS ordering of `exprs`, as `rules` ask
+fn strip_order_defaults(exprs: &mut [OrderByExpr], rules: &Rules) {
+    for expr in exprs {
+        if rules.strip_default_nulls && rules.nulls_high.is_some_and(|high| default_nulls(&expr.options, high)) {
+            expr.options.nulls_first = None;
+        }
+        if rules.strip_default_asc && expr.options.asc == Some(true) {
+            expr.options.asc = None;
+        }
+    }
+}
+
+/// Entry point: normalize structural elements of a Statement
+fn normalize_structure(stmt: &mut Statement, rules: &Rules) {
+    match stmt {
+        Statement::Query(ref mut query) => normalize_query_structure(query, rules),
+        Statement::Explain { ref mut statement, .. } if rules.explain_statements => normalize_structure(statement, rules),
+        Statement::Update(ref mut update) => {
+            normalize_table_with_joins(&mut update.table, rules);
+            if let Some(ref mut from) = update.from {
+                let tables = match from {
+                    UpdateTableFromKind::BeforeSet(t) | UpdateTableFromKind::AfterSet(t) => t,
+                };
+                for twj in tables {
+                    normalize_table_with_joins(twj, rules);
+                }
+            }
+        }
+        Statement::Insert(ref mut insert) if rules.insert_sources => {
+            if let Some(ref mut source) = insert.source {
+                normalize_query_structure(source, rules);
+            }
+        }
+        Statement::Delete(ref mut delete) => {
+            let tables = match &mut delete.from {
+                FromTable::WithFromKeyword(t) | FromTable::WithoutKeyword(t) => t,
+            };
+            for twj in tables {
+                normalize_table_with_joins(twj, rules);
+            }
+        }
+        _ => {}
+    }
+}
+
+// ---- Expression normalization ----
+
+/// Strip the parentheses around `expr`, then re-add those its children need.
+/// Run post-order, this yields the minimal parenthesization for the tree.
+fn canonicalize_parens(expr: &mut Expr, precedence: Precedence) {
+    // Step 1: Strip ALL Nested (parentheses)
+    if let Expr::Nested(inner) = expr {
+        let inner = std::mem::replace(inner.as_mut(), Expr::Value(Value::Null.into()));
+        *expr = inner;
+    }
+
+    // Step 2: Re-add Nested where removing parens would change semantics
+    precedence::parenthesize(expr, precedence);
+}
+
+/// `-5` → the literal `-5`, so that it is one signed parameter however the
+/// parser represented it. An already negative literal is left alone: `- -5`
+/// keeps its outer minus.
+fn fold_negative_literal(expr: &mut Expr) {
+    let Expr::UnaryOp { op: UnaryOperator::Minus, expr: inner } = expr else {
+        return;
+    };
+    let Expr::Value(ValueWithSpan { value: Value::Number(n, long), span }) = inner.as_mut() else {
+        return;
+    };
+    if n.starts_with('-') {
+        return;
+    }
+    let value = Value::Number(format!("-{}", n), *long);
+    *expr = Expr::Value(ValueWithSpan { value, span: *span });
+}
+
+/// `a <=> b` → `a IS NOT DISTINCT FROM b`, MySQL's null-safe equality in
+/// the standard spelling, and `NOT a IS NOT DISTINCT FROM b` →
+/// `a IS DISTINCT FROM b`
+fn canonicalize_distinct_from(expr: &mut Expr) {
+    let take = |e: &mut Box<Expr>| std::mem::replace(e, Box::new(Expr::Value(Value::Null.into())));
+    match expr {
+        Expr::BinaryOp { left, op: BinaryOperator::Spaceship, right } => {
+            *expr = Expr::IsNotDistinctFrom(take(left), take(right));
+        }
+        Expr::UnaryOp { op: UnaryOperator::Not, expr: inner } => {
+            let mut negated = inner.as_mut();
+            while let Expr::Nested(nested) = negated {
+                negated = nested.as_mut();
+            }
+            if let Expr::IsNotDistinctFrom(left, right) = negated {
+                *expr = Expr::IsDistinctFrom(take(left), take(right));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Schemas holding the builtin functions, which calls may name or not
+const SYSTEM_SCHEMAS: &[&str] = &["pg_catalog", "sys"];
+
+/// `pg_catalog.lower` → `lower`, `MySchema.f` → `myschema.f`: drop a system
+/// schema qualifier and lowercase the other unquoted schema parts
+fn normalize_function_schema(name: &mut ObjectName) {
+    let Some((_, qualifier)) = name.0.split_last_mut() else {
+        return;
+    };
+    for part in qualifier.iter_mut() {
+        if let ObjectNamePart::Identifier(ref mut ident) = part {
+            if ident.quote_style.is_none() {
+                ident.value = ident.value.to_lowercase();
+            }
+        }
+    }
+    if let [ObjectNamePart::Identifier(schema), _] = name.0.as_slice() {
+        if SYSTEM_SCHEMAS.contains(&schema.value.as_str()) {
+            name.0.remove(0);
+        }
+    }
+}
+
+/// Whether `name` calls a builtin of `dialect`: unqualified or in a system schema
+fn is_builtin_call(dialect: &str, name: &ObjectName) -> bool {
+    let Some((ObjectNamePart::Identifier(ident), qualifier)) = name.0.split_last() else {
+        return false;
+    };
+    let system = match qualifier {
+        [] => true,
+        [ObjectNamePart::Identifier(schema)] => SYSTEM_SCHEMAS.contains(&schema.value.to_lowercase().as_str()),
+        _ => false,
+    };
+    system && builtins::is_builtin(dialect, &ident.value)
+}
+
+/// Whether `function_case` asks for uppercasing builtins only
+fn builtins_only(function_case: &str) -> Result<bool, String> {
+    match function_case {
+        "upper" => Ok(false),
+        "builtins" => Ok(true),
+        _ => Err(format!("Unsupported function_case: {}", function_case)),
+    }
+}
+
+/// Apply `rules` to `stmt`; the number of CASE arms collapsed
+fn normalize_ast(stmt: &mut Statement, rules: &Rules) -> usize {
+    if rules.plain_backticks {
+        backticks::unquote(stmt);
+    }
+    if let Some(fold) = rules.identifier_case {
+        identifier_case::fold(stmt, fold);
+    }
+    if rules.implicit_aliases && rules.jumbling {
+        aliases::drop_aliases(stmt);
+    }
+    if rules.canonical_cte_names {
+        cte::rename_ctes(stmt);
+    }
+    if rules.positional_aliases {
+        positional::rename_aliases(stmt);
+    }
+    if rules.sorted_insert_columns {
+        insert::sort_columns(stmt);
+    }
+
+    // Phase 1: Structural normalization (aliases, joins, ORDER BY)
+    normalize_structure(stmt, rules);
+
+    // Phase 2: Expression normalization
+    // The visitor is post-order: children are processed before parents.
+    // This lets us (1) strip ALL Nested, then (2) re-add only where
+    // needed for operator precedence — producing a canonical form.
+    let mut collapsed_case_arms = 0;
+    let _ = visit_expressions_mut(stmt, |expr| {
+        if rules.canonical_parens {
+            canonicalize_parens(expr, rules.precedence);
+        }
+        if rules.signed_literals {
+            fold_negative_literal(expr);
+        }
+        if rules.concat_operators {
+            concat::canonicalize(expr, rules.plus_concat, rules.precedence);
+        }
+        if rules.searched_case {
+            case::canonicalize(expr, rules.precedence);
+        }
+        if rules.distinct_from {
+            canonicalize_distinct_from(expr);
+        }
+        if rules.collapsed_case_arms {
+            collapsed_case_arms += case::collapse_arms(expr);
+        }
+
+        // Normalize function names to uppercase (COUNT = count = CoUnT)
+        // and strip ASC in window, argument and WITHIN GROUP ORDER BY clauses
+        if let Expr::Function(ref mut func) = expr {
+            if rules.function_schemas {
+                normalize_function_schema(&mut func.name);
+            }
+            if rules.builtin_synonyms {
+                synonyms::canonicalize(func);
+            }
+            if rules.uppercase_functions && rules.builtins_only.is_none_or(|dialect| is_builtin_call(dialect, &func.name)) {
+                // With per-part rules, only the name itself is uppercased
+                let skip = if rules.function_schemas { func.name.0.len().saturating_sub(1) } else { 0 };
+                for part in func.name.0.iter_mut().skip(skip) {
+                    if let ObjectNamePart::Identifier(ref mut ident) = part {
+                        if ident.quote_style.is_none() {
+                            ident.value = ident.value.to_uppercase();
+                        }
+                    }
+                }
+            }
+            if let Some(WindowType::WindowSpec(ref mut spec)) = func.over {
+                strip_order_defaults(&mut spec.order_by, rules);
+            }
+            if rules.aggregate_clauses {
+                if let FunctionArguments::List(ref mut list) = func.args {
+                    // `ALL` keeps duplicates, as aggregates do anyway
+                    if rules.strip_select_all && list.duplicate_treatment == Some(DuplicateTreatment::All) {
+                        list.duplicate_treatment = None;
+                    }
+                    for clause in &mut list.clauses {
+                        if let FunctionArgumentClause::OrderBy(ref mut exprs) = clause {
+                            strip_order_defaults(exprs, rules);
+                        }
+                    }
+                }
+                strip_order_defaults(&mut func.within_group, rules);
+            }
+        }
+
+        if rules.canonical_casts {
+            cast::canonicalize(expr, rules.pg_type_names);
+        }
+
+        // After schema normalization, so that `pg_catalog.now()` is matched
+        if rules.current_time {
+            current_time::canonicalize(expr);
+        }
+
+        // Normalize boolean identifiers (TRUE/FALSE/True/true → uppercase)
+        if let Expr::Identifier(ref mut ident) = expr {
+            if rules.uppercase_booleans && rules.identifier_booleans {
+                if let Some(upper) = booleans::identifier_boolean(ident) {
+                    ident.value = upper.to_string();
+                }
+            }
+        }
+
+        // Last, so that the operands are compared in their final spelling
+        if rules.commutative_operands {
+            commutative::canonicalize(expr, rules.plus_concat, rules.canonical_parens, rules.precedence);
+        } else if rules.sorted_predicates {
+            commutative::sort_predicates(expr, rules.canonical_parens, rules.precedence);
+        }
+
+        // Normalize structure inside subqueries embedded in expressions
+        match expr {
+            Expr::Subquery(ref mut query) => normalize_query_structure(query, rules),
+            Expr::Exists { ref mut subquery, .. } => normalize_query_structure(subquery, rules),
+            Expr::InSubquery { ref mut subquery, .. } => normalize_query_structure(subquery, rules),
+            _ => {}
+        }
+
+        ControlFlow::<()>::Continue(())
+    });
+    collapsed_case_arms
+}
+
+// ---- Literal extraction ----
+
+/// Clause of the statement a literal was found in, as reported to `redactor` callbacks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Clause {
+    Select,
+    From,
+    Join,
+    Where,
+    GroupBy,
+    Having,
+    Qualify,
+    OrderBy,
+    Limit,
+    Offset,
+    Values,
+    Set,
+    Returning,
+    /// `INTO OUTFILE` and the options of `LOAD DATA`
+    Into,
+    Other,
+}
+
+impl Clause {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Clause::Select => "select",
+            Clause::From => "from",
+            Clause::Join => "join",
+            Clause::Where => "where",
+            Clause::GroupBy => "group_by",
+            Clause::Having => "having",
+            Clause::Qualify => "qualify",
+            Clause::OrderBy => "order_by",
+            Clause::Limit => "limit",
+            Clause::Offset => "offset",
+            Clause::Values => "values",
+            Clause::Set => "set",
+            Clause::Returning => "returning",
+            Clause::Into => "into",
+            Clause::Other => "other",
+        }
+    }
+}
+
+/// The `WITHIN GROUP`, `FILTER` and `OVER` of a call, taken out of it while
+/// its arguments are visited so as to be visited after them
+type AggregateClauses = (Vec<OrderByExpr>, Option<Box<Expr>>, Option<WindowType>);
+
+/// Called for each extracted literal with (index, clause, value).
+/// Returning `Ok(None)` drops the value from `params`; the literal is still replaced.
+pub type ParamHook<'a, E> = dyn FnMut(usize, Clause, String) -> Result<Option<String>, E> + 'a;
+
+/// The placeholder for literal number `n`: `placeholder` with `{n}` replaced,
+/// so that `"${n}"` numbers them and `"?"` does not
+pub fn placeholder_text(placeholder: &str, n: usize) -> String {
+    if !placeholder.contains("{n}") {
+        return placeholder.to_string();
+    }
+    placeholder.replace("{n}", &n.to_string())
+}
+
+/// The placeholder of each `style`, numbered where the style is
+const STYLES: [(&str, &str); 4] = [("question", "?"), ("dollar", "${n}"), ("colon", ":p{n}"), ("pyformat", "%s")];
+
+/// The placeholder to replace literals with, given as such or by `style`
+/// (`"question"`, `"dollar"`, `"colon"` or `"pyformat"`), which are exclusive
+pub fn style_placeholder<'a>(placeholder: Option<&'a str>, style: Option<&str>) -> Result<Option<&'a str>, String> {
+    let Some(style) = style else {
+        return Ok(placeholder);
+    };
+    if placeholder.is_some() {
+        return Err("placeholder and style cannot both be given".to_string());
+    }
+    match STYLES.iter().find(|(name, _)| *name == style) {
+        Some((_, placeholder)) => Ok(Some(placeholder)),
+        None => Err(format!(
+            "Unknown placeholder style: {:?} (expected one of {})",
+            style,
+            STYLES.map(|(name, _)| format!("{:?}", name)).join(", ")
+        )),
+    }
+}
+
+/// The number of `text` if it is `placeholder` with some number for `{n}`
+pub fn placeholder_number(placeholder: &str, text: &str) -> Option<usize> {
+    let (prefix, _) = placeholder.split_once("{n}")?;
+    let rest = text.strip_prefix(prefix)?;
+    let digits = &rest[..rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len())];
+    digits.parse().ok().filter(|&n| placeholder_text(placeholder, n) == text)
+}
+
+/// Hasher of the expression addresses keying `ClauseRoots` and
+/// `LiteralPass::items`: a multiply-shift, as SipHash took most of the time
+/// of the literal pass over a statement of many literals. Addresses are not
+/// chosen by the input, so they need no protection against collisions.
+#[derive(Default)]
+struct AddressHasher(u64);
+
+impl Hasher for AddressHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_u64(u64::from(byte));
+        }
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        // The low bits of an address are those of its alignment, the same for
+        // every expression, so the high half is folded into them
+        let mixed = (self.0 ^ n).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        self.0 = mixed ^ (mixed >> 32);
+    }
+}
+
+/// A map keyed by expression address
+type AddressMap<V> = HashMap<*const Expr, V, BuildHasherDefault<AddressHasher>>;
+
+/// The root expression of each clause of the queries and statements
+/// registered, by address, for telling which clause an expression is in
+#[derive(Default)]
+struct ClauseRoots {
+    roots: AddressMap<Clause>,
+}
+
+impl ClauseRoots {
+    fn get(&self, expr: &Expr) -> Option<Clause> {
+        self.roots.get(&(expr as *const Expr)).copied()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.roots.reserve(additional);
+    }
+
+    fn register(&mut self, expr: &Expr, clause: Clause) {
+        self.roots.insert(expr as *const Expr, clause);
+    }
+
+    fn register_select_items(&mut self, items: &[SelectItem], clause: Clause) {
+        for item in items {
+            match item {
+                SelectItem::UnnamedExpr(e) | SelectItem::ExprWithAlias { expr: e, .. } => {
+                    self.register(e, clause);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn register_assignments(&mut self, assignments: &[Assignment]) {
+        for a in assignments {
+            self.register(&a.value, Clause::Set);
+        }
+    }
+
+    fn register_set_expr(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                self.register_select_items(&select.projection, Clause::Select);
+                for twj in &select.from {
+                    for join in &twj.joins {
+                        if let Some(JoinConstraint::On(e)) = join_constraint(&join.join_operator) {
+                            self.register(e, Clause::Join);
+                        }
+                    }
+                }
+                if let Some(ref e) = select.prewhere {
+                    self.register(e, Clause::Where);
+                }
+                if let Some(ref e) = select.selection {
+                    self.register(e, Clause::Where);
+                }
+                if let GroupByExpr::Expressions(ref exprs, _) = select.group_by {
+                    for e in exprs {
+                        self.register(e, Clause::GroupBy);
+                    }
+                }
+                if let Some(ref e) = select.having {
+                    self.register(e, Clause::Having);
+                }
+                if let Some(ref e) = select.qualify {
+                    self.register(e, Clause::Qualify);
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.register_set_expr(left);
+                self.register_set_expr(right);
+            }
+            SetExpr::Values(values) => {
+                for e in values.rows.iter().flatten() {
+                    self.register(e, Clause::Values);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn register_query(&mut self, query: &Query) {
+        self.register_set_expr(&query.body);
+        if let Some(ref order_by) = query.order_by {
+            if let OrderByKind::Expressions(ref exprs) = order_by.kind {
+                for e in exprs {
+                    self.register(&e.expr, Clause::OrderBy);
+                }
+            }
+        }
+        match query.limit_clause {
+            Some(LimitClause::LimitOffset { ref limit, ref offset, ref limit_by }) => {
+                if let Some(e) = limit {
+                    self.register(e, Clause::Limit);
+                }
+                if let Some(o) = offset {
+                    self.register(&o.value, Clause::Offset);
+                }
+                for e in limit_by {
+                    self.register(e, Clause::Limit);
+                }
+            }
+            Some(LimitClause::OffsetCommaLimit { ref offset, ref limit }) => {
+                self.register(offset, Clause::Offset);
+                self.register(limit, Clause::Limit);
+            }
+            None => {}
+        }
+        if let Some(Fetch { quantity: Some(ref e), .. }) = query.fetch {
+            self.register(e, Clause::Limit);
+        }
+    }
+
+    fn register_statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Update(update) => {
+                self.register_assignments(&update.assignments);
+                if let Some(ref e) = update.selection {
+                    self.register(e, Clause::Where);
+                }
+                if let Some(ref e) = update.limit {
+                    self.register(e, Clause::Limit);
+                }
+                if let Some(ref items) = update.returning {
+                    self.register_select_items(items, Clause::Returning);
+                }
+            }
+            Statement::Delete(delete) => {
+                if let Some(ref e) = delete.selection {
+                    self.register(e, Clause::Where);
+                }
+                for e in &delete.order_by {
+                    self.register(&e.expr, Clause::OrderBy);
+                }
+                if let Some(ref e) = delete.limit {
+                    self.register(e, Clause::Limit);
+                }
+                if let Some(ref items) = delete.returning {
+                    self.register_select_items(items, Clause::Returning);
+                }
+            }
+            Statement::Insert(insert) => {
+                self.register_assignments(&insert.assignments);
+                match insert.on {
+                    Some(OnInsert::DuplicateKeyUpdate(ref assignments)) => {
+                        self.register_assignments(assignments);
+                    }
+                    Some(OnInsert::OnConflict(OnConflict {
+                        action: OnConflictAction::DoUpdate(ref update),
+                        ..
+                    })) => {
+                        self.register_assignments(&update.assignments);
+                        if let Some(ref e) = update.selection {
+                            self.register(e, Clause::Where);
+                        }
+                    }
+                    _ => {}
+                }
+                if let Some(ref items) = insert.returning {
+                    self.register_select_items(items, Clause::Returning);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Replaces literals with placeholders and collects them into `params`.
+///
+/// Clause tracking works on expression identity: when a query or statement is
+/// entered, the root expression of each of its clauses is registered, and the
+/// clause is pushed/popped as the visitor enters/leaves that root. Literals that
+/// are not under any registered root are reported as `Clause::Other`.
+struct LiteralPass<'a, 'h, E> {
+    placeholder: &'a str,
+    /// Number of the first literal's placeholder, for numbered placeholders
+    start: usize,
+    params: Vec<String>,
+    param_types: Vec<Option<String>>,
+    param_kinds: Vec<Option<&'static str>>,
+    /// Of each of `params`, see `collapse::Position`
+    param_positions: Vec<Option<collapse::Position>>,
+    /// Of each of `params`, where its literal was in the parsed text; empty
+    /// for literals with no place there
+    param_spans: Vec<Span>,
+    literal_count: usize,
+    /// Whether to keep literals in `params`; without, they are replaced but
+    /// neither written out nor passed to `hook`
+    collect_params: bool,
+    /// Literals kept in `params` at most; those beyond are replaced but not
+    /// passed to `hook`
+    max_params: Option<usize>,
+    /// Literals left out of `params` by `max_params`
+    truncated: usize,
+    /// Literals left as written
+    keep: keep::Keep,
+    /// Literals replaced per clause, including those `hook` dropped
+    clause_counts: Vec<(Clause, usize)>,
+    /// See `Rules::special_form_literals`
+    special_form_literals: bool,
+    /// See `Rules::aggregate_clauses`
+    aggregate_clauses: bool,
+    /// Whether TRUE/FALSE identifiers are booleans, see `Rules::uppercase_booleans`
+    identifier_booleans: bool,
+    /// See `Rules::boolean_columns`
+    boolean_columns: bool,
+    /// Whether double-quoted names are strings, see `Rules::string_quotes`
+    quoted_strings: bool,
+    /// Of the aggregate calls being visited, see `AggregateClauses`
+    deferred: Vec<AggregateClauses>,
+    roots: ClauseRoots,
+    clauses: Vec<Clause>,
+    /// The items of VALUES rows and IN lists, registered like `roots`
+    items: AddressMap<collapse::Position>,
+    lists: usize,
+    positions: Vec<collapse::Position>,
+    hook: &'h mut ParamHook<'h, E>,
+}
+
+impl<'a, 'h, E> LiteralPass<'a, 'h, E> {
+    /// `expected` literals are allocated for up front
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        placeholder: &'a str,
+        start: usize,
+        rules: &Rules,
+        collect_params: bool,
+        max_params: Option<usize>,
+        keep: keep::Keep,
+        expected: usize,
+        hook: &'h mut ParamHook<'h, E>,
+    ) -> Self {
+        let capacity = if collect_params { max_params.map_or(expected, |max| expected.min(max)) } else { 0 };
+        LiteralPass {
+            placeholder,
+            start,
+            params: Vec::with_capacity(capacity),
+            param_types: Vec::with_capacity(capacity),
+            param_kinds: Vec::with_capacity(capacity),
+            param_positions: Vec::with_capacity(capacity),
+            param_spans: Vec::with_capacity(capacity),
+            literal_count: 0,
+            collect_params,
+            max_params,
+            truncated: 0,
+            keep,
+            clause_counts: Vec::new(),
+            special_form_literals: rules.special_form_literals,
+            aggregate_clauses: rules.aggregate_clauses,
+            identifier_booleans: rules.uppercase_booleans && rules.identifier_booleans,
+            boolean_columns: rules.boolean_columns,
+            quoted_strings: rules.string_quotes || !rules.quoted_names,
+            deferred: Vec::new(),
+            roots: ClauseRoots::default(),
+            clauses: Vec::new(),
+            items: AddressMap::default(),
+            lists: 0,
+            positions: Vec::new(),
+            hook,
+        }
+    }
+
+    /// Register the items of one list, `rows` of them for VALUES
+    fn register_list<'e>(&mut self, rows: impl IntoIterator<Item = (Option<usize>, &'e [Expr])>) {
+        let list = self.lists;
+        self.lists += 1;
+        for (row, exprs) in rows {
+            for (element, e) in exprs.iter().enumerate() {
+                self.items.insert(e as *const Expr, collapse::Position { list, row, element });
+            }
+        }
+    }
+
+    fn clause(&self) -> Clause {
+        self.clauses.last().copied().unwrap_or(Clause::Other)
+    }
+
+    /// Record one literal, its text and prefix given by `param` (see
+    /// `literal_param`) when it is kept, and return the placeholder that
+    /// replaces it
+    fn extract(
+        &mut self,
+        param: impl FnOnce() -> (String, Option<&'static str>),
+        kind: Option<&'static str>,
+        span: Span,
+    ) -> ControlFlow<E, Value> {
+        let index = self.literal_count;
+        self.literal_count += 1;
+        let clause = self.clause();
+        match self.clause_counts.iter_mut().find(|(c, _)| *c == clause) {
+            Some((_, count)) => *count += 1,
+            None => self.clause_counts.push((clause, 1)),
+        }
+        let placeholder = Value::Placeholder(placeholder_text(self.placeholder, self.start + index));
+        if !self.collect_params {
+            return ControlFlow::Continue(placeholder);
+        }
+        if self.max_params.is_some_and(|max| self.params.len() >= max) {
+            self.truncated += 1;
+            return ControlFlow::Continue(placeholder);
+        }
+        let (value, prefix) = param();
+        match (self.hook)(index, clause, value) {
+            Ok(Some(v)) => {
+                self.params.push(v);
+                self.param_types.push(prefix.map(str::to_string));
+                self.param_kinds.push(kind);
+                self.param_positions.push(self.positions.last().copied());
+                self.param_spans.push(span);
+            }
+            Ok(None) => {}
+            Err(e) => return ControlFlow::Break(e),
+        }
+        ControlFlow::Continue(placeholder)
+    }
+
+    /// Replace the literal operand of a typed string or `MATCH ... AGAINST`
+    /// with a placeholder, keeping the construct around it
+    fn extract_operand(&mut self, expr: &mut Expr) -> ControlFlow<E> {
+        let (value, span) = match expr {
+            Expr::TypedString(TypedString { value, .. }) => (&mut value.value, value.span),
+            Expr::MatchAgainst { match_value, .. } => (match_value, Span::empty()),
+            _ => return ControlFlow::Continue(()),
+        };
+        if matches!(value, Value::Null | Value::Placeholder(_)) {
+            return ControlFlow::Continue(());
+        }
+        *value = self.extract(|| literal_param(value), None, span)?;
+        ControlFlow::Continue(())
+    }
+
+    /// Replace the `SEPARATOR` of `func` with a placeholder, then visit and
+    /// put back the clauses `pre_visit_expr` deferred
+    fn extract_aggregate_clauses(&mut self, func: &mut Function) -> ControlFlow<E> {
+        if let FunctionArguments::List(ref mut list) = func.args {
+            for clause in &mut list.clauses {
+                if let FunctionArgumentClause::Separator(ref mut value) = clause {
+                    if !matches!(value, Value::Null | Value::Placeholder(_)) {
+                        *value = self.extract(|| literal_param(value), None, Span::empty())?;
+                    }
+                }
+            }
+        }
+        let (mut within_group, mut filter, mut over) = self.deferred.pop().unwrap_or_default();
+        within_group.visit(self)?;
+        filter.visit(self)?;
+        over.visit(self)?;
+        (func.within_group, func.filter, func.over) = (within_group, filter, over);
+        ControlFlow::Continue(())
+    }
+}
+
+/// The text of a literal for `params`, and its prefix if it is written in a
+/// prefixed string form. The prefix is left out of the text, so that `N'x'`
+/// and `'x'` give the same parameter, told apart by `param_types` only.
+fn literal_param(value: &Value) -> (String, Option<&'static str>) {
+    let prefix = match value {
+        Value::NationalStringLiteral(_) | Value::NationalQuoteDelimitedStringLiteral(_) => "N",
+        Value::EscapedStringLiteral(_) => "E",
+        Value::UnicodeStringLiteral(_) => "U&",
+        Value::HexStringLiteral(_) => "X",
+        Value::SingleQuotedByteStringLiteral(_)
+        | Value::DoubleQuotedByteStringLiteral(_)
+        | Value::TripleSingleQuotedByteStringLiteral(_)
+        | Value::TripleDoubleQuotedByteStringLiteral(_) => "B",
+        Value::SingleQuotedRawStringLiteral(_)
+        | Value::DoubleQuotedRawStringLiteral(_)
+        | Value::TripleSingleQuotedRawStringLiteral(_)
+        | Value::TripleDoubleQuotedRawStringLiteral(_) => "R",
+        // The most common literals, written without going through `Display`
+        Value::Number(n, false) => return (n.clone(), None),
+        Value::SingleQuotedString(s) if !s.contains('\'') => return (format!("'{}'", s), None),
+        _ => return (value.to_string(), None),
+    };
+    (value.to_string()[prefix.len()..].to_string(), Some(prefix))
+}
+
+/// The text for `params` of `expr`, a literal as `post_visit_expr` takes it:
+/// a value, a TRUE/FALSE identifier read as a boolean, or a double-quoted
+/// string parsed as an identifier
+fn expr_param(expr: &Expr) -> (String, Option<&'static str>) {
+    match expr {
+        Expr::Value(val) => literal_param(&val.value),
+        Expr::Identifier(ident) if ident.quote_style == Some('"') => (format!("\"{}\"", ident.value), None),
+        Expr::Identifier(ident) => (ident.value.to_uppercase(), None),
+        _ => (expr.to_string(), None),
+    }
+}
+
+/// The kinds `numeric_kind` tells apart
+pub const NUMERIC_KINDS: [&str; 3] = ["integer", "decimal", "float"];
+
+/// The kind of a numeric literal, from how it is written: `integer` (`42`),
+/// `decimal` (`4.2`) or `float` (`4.2e1`, with an exponent)
+fn numeric_kind(value: &Value) -> Option<&'static str> {
+    let Value::Number(n, _) = value else {
+        return None;
+    };
+    Some(if n.contains(['e', 'E']) {
+        "float"
+    } else if n.contains('.') {
+        "decimal"
+    } else {
+        "integer"
+    })
+}
+
+/// The constraint of a join operator, for the variants that carry one
+fn join_constraint(op: &JoinOperator) -> Option<&JoinConstraint> {
+    match op {
+        JoinOperator::Join(c)
+        | JoinOperator::Inner(c)
+        | JoinOperator::Left(c)
+        | JoinOperator::LeftOuter(c)
+        | JoinOperator::Right(c)
+        | JoinOperator::RightOuter(c)
+        | JoinOperator::FullOuter(c)
+        | JoinOperator::CrossJoin(c)
+        | JoinOperator::Semi(c)
+        | JoinOperator::LeftSemi(c)
+        | JoinOperator::RightSemi(c)
+        | JoinOperator::Anti(c)
+        | JoinOperator::LeftAnti(c)
+        | JoinOperator::RightAnti(c)
+        | JoinOperator::StraightJoin(c) => Some(c),
+        JoinOperator::AsOf { constraint, .. } => Some(constraint),
+        _ => None,
+    }
+}
+
+impl<E> VisitorMut for LiteralPass<'_, '_, E> {
+    type Break = E;
+
+    fn pre_visit_statement(&mut self, stmt: &mut Statement) -> ControlFlow<E> {
+        self.roots.register_statement(stmt);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<E> {
+        if let SetExpr::Values(ref values) = *query.body {
+            // Each item is both a root and a list item
+            let items = values.rows.iter().map(Vec::len).sum();
+            self.roots.reserve(items);
+            self.items.reserve(items);
+        }
+        self.roots.register_query(query);
+        if let SetExpr::Values(ref values) = *query.body {
+            self.register_list(values.rows.iter().enumerate().map(|(row, exprs)| (Some(row), exprs.as_slice())));
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, _tf: &mut TableFactor) -> ControlFlow<E> {
+        self.clauses.push(Clause::From);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_table_factor(&mut self, _tf: &mut TableFactor) -> ControlFlow<E> {
+        self.clauses.pop();
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<E> {
+        if let Some(clause) = self.roots.get(expr) {
+            self.clauses.push(clause);
+        }
+        if let Some(position) = self.items.get(&(expr as *const Expr)) {
+            self.positions.push(*position);
+        }
+        if let Expr::InList { ref list, .. } = expr {
+            self.register_list([(None, list.as_slice())]);
+        }
+        if self.boolean_columns {
+            booleans::qualified_columns(expr);
+        }
+        if let Expr::Function(ref mut func) = expr {
+            if self.aggregate_clauses {
+                // `AGG(...) WITHIN GROUP (...) FILTER (...) OVER (...)`, visited
+                // by `post_visit_expr` once the arguments are done
+                let within_group = std::mem::take(&mut func.within_group);
+                self.deferred.push((within_group, func.filter.take(), func.over.take()));
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<E> {
+        let kept = self.keep.clause(self.clause());
+        let literal = match expr {
+            _ if kept => None,
+            Expr::Value(ref val) => match val.value {
+                Value::Placeholder(_) => None,
+                Value::Null if self.keep.null => None,
+                _ => Some(val.span),
+            },
+            // In MSSQL/Oracle, TRUE/FALSE are identifiers, not boolean values.
+            // Parameterize them just like Value::Boolean in other dialects.
+            Expr::Identifier(ref ident) if self.identifier_booleans && booleans::identifier_boolean(ident).is_some() => {
+                Some(ident.span)
+            }
+            // Double-quoted strings are parsed as identifiers, but they are
+            // string values in MySQL, BigQuery and Hive
+            Expr::Identifier(ref ident) if self.quoted_strings && ident.quote_style == Some('"') => Some(ident.span),
+            _ => None,
+        };
+        if let Some(span) = literal {
+            let kind = match expr {
+                Expr::Value(ref val) => numeric_kind(&val.value),
+                _ => None,
+            };
+            *expr = Expr::Value(self.extract(|| expr_param(expr), kind, span)?.into());
+        } else if self.special_form_literals && !kept {
+            self.extract_operand(expr)?;
+        }
+        if let Expr::Function(ref mut func) = expr {
+            if self.aggregate_clauses {
+                self.extract_aggregate_clauses(func)?;
+            }
+        }
+        if self.roots.get(expr).is_some() {
+            self.clauses.pop();
+        }
+        if self.items.contains_key(&(expr as *const Expr)) {
+            self.positions.pop();
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Output of `normalize_statement`
+pub struct Normalized {
+    pub sql: String,
+    pub params: Vec<String>,
+    pub param_types: Vec<Option<String>>,
+    pub param_kinds: Vec<Option<&'static str>>,
+    /// See `LiteralPass::param_positions`
+    pub param_positions: Vec<Option<collapse::Position>>,
+    /// See `LiteralPass::param_spans`
+    pub param_spans: Vec<Span>,
+    /// Constructs the passes did not reach, see `coverage::leftovers`
+    pub warnings: Vec<String>,
+    /// See `setops::duplicate_branches`
+    pub duplicate_branches: usize,
+    /// CASE arms dropped by `case-arm-collapse`
+    pub collapsed_case_arms: usize,
+    /// Bind placeholders the statement had before literals were replaced
+    pub binds: Vec<String>,
+    /// Number of the first literal's placeholder
+    pub start: usize,
+    /// Literals replaced per clause, in order of first appearance
+    pub literal_counts: Vec<(Clause, usize)>,
+    /// Literals left out of `params` by `max_params`
+    pub truncated_params: usize,
+}
+
+/// Called for each `Step::Hook` with its index among the hooks, to rewrite
+/// the statement in place; whether it did
+type StepHook<'a, E> = dyn FnMut(usize, &mut Statement) -> Result<bool, E> + 'a;
+
+/// Run `steps` over `stmt`, in order, with the flags of `rules` that no pass
+/// applies. Consecutive rule passes do not interact, so each run of them
+/// shares a single traversal.
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "sqlfp.passes", level = "debug", skip_all, fields(steps = steps.len())))]
+#[allow(clippy::too_many_arguments)]
+pub fn normalize_statement<E>(
+    stmt: &mut Statement,
+    placeholder: &str,
+    placeholder_start: usize,
+    steps: &[Step],
+    rules: &Rules,
+    collect_params: bool,
+    max_params: Option<usize>,
+    keep: keep::Keep,
+    hook: &mut ParamHook<'_, E>,
+    step_hook: &mut StepHook<'_, E>,
+) -> Result<Normalized, E> {
+    let scope = rules.scope();
+    let mut applied = scope;
+    let mut pending = scope;
+    let (mut params, mut param_types, mut param_kinds, mut param_positions) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    // Counted before literals are replaced, as branches differing only in
+    // their values are not duplicates
+    let mut duplicate_branches = None;
+    let mut collapsed_case_arms = 0;
+    let (mut binds, mut start) = (Vec::new(), placeholder_start);
+    let mut literal_counts = Vec::new();
+    let mut truncated_params = 0;
+    let mut param_spans = Vec::new();
+    let mut hooks = 0;
+    // Once a hook rewrote the statement, its spans are those of the rewrite
+    let mut reparsed = false;
+    for step in steps.iter().map(Some).chain([None]) {
+        if let Some(Step::Pass(pass)) = step {
+            if let (Some(flag), Some(done)) = (pass.rule(&mut pending), pass.rule(&mut applied)) {
+                (*flag, *done) = (true, true);
+                continue;
+            }
+        }
+        if pending != scope {
+            collapsed_case_arms += normalize_ast(stmt, &pending);
+            pending = scope;
+        }
+        match step {
+            Some(Step::Pass(_)) => {
+                duplicate_branches = Some(setops::duplicate_branches(stmt));
+                binds = binds::bind_placeholders(stmt);
+                if rules.bind_numbering {
+                    let highest = binds.iter().filter_map(|bind| placeholder_number(placeholder, bind)).max();
+                    start = highest.map_or(placeholder_start, |n| placeholder_start.max(n + 1));
+                }
+                // TRUE/FALSE identifiers are booleans once `boolean-uppercase` ran
+                let expected = insert::value_count(stmt);
+                let mut literals =
+                    LiteralPass::new(placeholder, start, &applied, collect_params, max_params, keep, expected, &mut *hook);
+                if let ControlFlow::Break(e) = stmt.visit(&mut literals) {
+                    return Err(e);
+                }
+                (params, param_types, param_kinds) = (literals.params, literals.param_types, literals.param_kinds);
+                param_positions = literals.param_positions;
+                param_spans = literals.param_spans;
+                if reparsed {
+                    param_spans.fill(Span::empty());
+                }
+                literal_counts = literals.clause_counts;
+                truncated_params = literals.truncated;
+            }
+            Some(Step::Hook) => {
+                reparsed |= step_hook(hooks, stmt)?;
+                hooks += 1;
+            }
+            None => {}
+        }
+    }
+    let warnings = coverage::leftovers(stmt, &applied);
+    let duplicate_branches = duplicate_branches.unwrap_or_else(|| setops::duplicate_branches(stmt));
+
+    Ok(Normalized {
+        sql: writer::write(stmt),
+        params,
+        param_types,
+        param_kinds,
+        param_positions,
+        param_spans,
+        warnings,
+        duplicate_branches,
+        collapsed_case_arms,
+        binds,
+        start,
+        literal_counts,
+        truncated_params,
+    })
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "sqlfp.hash", level = "debug", skip_all, fields(size = normalized.len())))]
+pub fn compute_hash(hasher: &hashing::Hasher, normalized: &str) -> String {
+    hasher.hash(normalized)
+}
+
+/// `normalized`, a statement as `engine` writes it, without the clauses of
+/// its `ignore_clauses`: the text its hash is computed from
+pub fn hashed_text<'a>(engine: &pool::Engine, normalized: &'a str) -> Result<Cow<'a, str>, String> {
+    let clauses = &engine.options.ignore_clauses;
+    if clauses.is_empty() {
+        return Ok(Cow::Borrowed(normalized));
+    }
+    let stmt = parse_first_statement(normalized, &*engine.dialect)?;
+    Ok(trailing::stripped(&stmt, clauses).map_or(Cow::Borrowed(normalized), |stmt| Cow::Owned(writer::write(&stmt))))
+}
+
+/// Parse `sql` and return its first statement
+pub fn parse_first_statement(sql: &str, dialect: &dyn sqlparser::dialect::Dialect) -> Result<Statement, String> {
+    parse_leading_statement(sql, dialect).map(|(stmt, _)| stmt)
+}
+
+/// Parse `sql` and return its first statement, and how many statements
+/// follow it
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "sqlfp.parse", level = "debug", skip_all, fields(size = sql.len())))]
+pub fn parse_leading_statement(sql: &str, dialect: &dyn sqlparser::dialect::Dialect) -> Result<(Statement, usize), String> {
+    let statements = Parser::parse_sql(dialect, sql).map_err(|e| format!("Parse error: {}", e))?;
+    let rest = statements.len().saturating_sub(1);
+
+    statements
+        .into_iter()
+        .next()
+        .map(|stmt| (stmt, rest))
+        .ok_or_else(|| NO_STATEMENT.to_string())
+}
+
+/// Where a parse or tokenize error message says the statement went wrong
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ErrorPosition {
+    /// 1-based line and column, absent at the end of the input
+    pub line: Option<u64>,
+    pub column: Option<u64>,
+    /// The token found where another was expected (`EOF` at the end of the
+    /// input), absent for tokenize errors
+    pub token: Option<String>,
+}
+
+/// The position in `message`, as sqlparser writes it: `Expected: ...,
+/// found: TOKEN at Line: L, Column: C`
+pub fn error_position(message: &str) -> ErrorPosition {
+    let (text, location) = match message.rsplit_once(" at Line: ") {
+        Some((text, location)) => (text, Some(location)),
+        None => (message, None),
+    };
+    let (line, column) = location
+        .and_then(|location| location.split_once(", Column: "))
+        .map_or((None, None), |(line, column)| (line.trim().parse().ok(), column.trim().parse().ok()));
+    let token = text.rsplit_once("found: ").map(|(_, token)| token.to_string());
+    ErrorPosition { line, column, token }
+}
+
+/// Metadata entry describing the templating constructs replaced before parsing
+fn template_metadata(markers: &[template::TemplateMarker]) -> (String, MetaValue) {
+    let entries = markers
+        .iter()
+        .map(|m| {
+            MetaValue::Map(vec![
+                ("kind".to_string(), m.kind.as_str().into()),
+                ("source".to_string(), m.source.clone().into()),
+                ("marker".to_string(), m.marker.clone().into()),
+            ])
+        })
+        .collect();
+    ("templates".to_string(), MetaValue::List(entries))
+}
+
+/// The items of the RETURNING clause of `stmt`, if it has one
+pub fn returning_items(stmt: &Statement) -> Option<&[SelectItem]> {
+    match stmt {
+        Statement::Insert(insert) => insert.returning.as_deref(),
+        Statement::Update(update) => update.returning.as_deref(),
+        Statement::Delete(delete) => delete.returning.as_deref(),
+        _ => None,
+    }
+}
+
+/// Name of the column a projection item returns: its alias, the last part of
+/// a column reference, or else the item as SQL
+fn output_name(item: &SelectItem) -> String {
+    match item {
+        SelectItem::ExprWithAlias { alias, .. } => alias.value.clone(),
+        SelectItem::UnnamedExpr(Expr::Identifier(ident)) => ident.value.clone(),
+        SelectItem::UnnamedExpr(Expr::CompoundIdentifier(idents)) if !idents.is_empty() => {
+            idents[idents.len() - 1].value.clone()
+        }
+        other => other.to_string(),
+    }
+}
+
+/// `metadata["returning"]`, the columns of the RETURNING clause of `stmt`
+pub fn returning_metadata(stmt: &Statement) -> Option<(String, MetaValue)> {
+    let items = returning_items(stmt)?;
+    let names = items.iter().map(|item| output_name(item).into()).collect();
+    Some(("returning".to_string(), MetaValue::List(names)))
+}
+
+/// `metadata["uses_distinct"]` and `metadata["distinct_on_columns"]`, set
+/// when the outermost `SELECT` of `stmt` is `DISTINCT` (`ON`)
+fn distinct_metadata(stmt: &Statement) -> Vec<(String, MetaValue)> {
+    let Statement::Query(query) = stmt else {
+        return Vec::new();
+    };
+    let SetExpr::Select(ref select) = *query.body else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    match select.distinct {
+        Some(Distinct::Distinct) => out.push(("uses_distinct".to_string(), MetaValue::Bool(true))),
+        Some(Distinct::On(ref exprs)) => {
+            out.push(("uses_distinct".to_string(), MetaValue::Bool(true)));
+            let columns = exprs.iter().map(|expr| expr.to_string().into()).collect();
+            out.push(("distinct_on_columns".to_string(), MetaValue::List(columns)));
+        }
+        Some(Distinct::All) | None => {}
+    }
+    out
+}
+
+/// `metadata["duplicate_branches"]`, present only when there are some
+pub fn duplicate_branches_metadata(count: usize) -> Option<(String, MetaValue)> {
+    (count > 0).then(|| ("duplicate_branches".to_string(), MetaValue::Int(count as i64)))
+}
+
+/// `metadata["checksum"]`, pt-query-digest's checksum of the fingerprint
+/// under `compat="percona"`
+fn checksum_metadata(fingerprint: &str) -> (String, MetaValue) {
+    ("checksum".to_string(), MetaValue::Str(percona::checksum(fingerprint)))
+}
+
+/// `metadata["query_id"]`, the signed 64-bit identity of the statement under
+/// `compat="pg_stat_statements"`
+fn query_id_metadata(hashed: &str) -> (String, MetaValue) {
+    ("query_id".to_string(), MetaValue::Int(hashing::query_id(hashed)))
+}
+
+/// `metadata["collapsed_case_arms"]`, present only when arms were collapsed
+fn collapsed_case_arms_metadata(count: usize) -> Option<(String, MetaValue)> {
+    (count > 0).then(|| ("collapsed_case_arms".to_string(), MetaValue::Int(count as i64)))
+}
+
+/// `metadata["placeholder_collisions"]`, present only when the statement
+/// itself spells the placeholder
+fn placeholder_collisions_metadata(count: usize) -> Option<(String, MetaValue)> {
+    (count > 0).then(|| ("placeholder_collisions".to_string(), MetaValue::Int(count as i64)))
+}
+
+/// `metadata["truncated_subqueries"]`, present only when `max_subquery_depth`
+/// cut subqueries off
+fn truncated_subqueries_metadata(count: usize) -> Option<(String, MetaValue)> {
+    (count > 0).then(|| ("truncated_subqueries".to_string(), MetaValue::Int(count as i64)))
+}
+
+/// `metadata["ignored_statements"]`, the number of statements after the
+/// first that were left out, present only when there were some
+fn ignored_statements_metadata(count: usize) -> Option<(String, MetaValue)> {
+    (count > 0).then(|| ("ignored_statements".to_string(), MetaValue::Int(count as i64)))
+}
+
+/// `metadata["truncated_input"]`, set only when `truncated_input` had to
+/// fingerprint a prefix of the statement
+fn truncated_input_metadata() -> (String, MetaValue) {
+    ("truncated_input".to_string(), MetaValue::Bool(true))
+}
+
+/// `metadata["parse_error"]`, why a statement `lenient` normalized token
+/// by token could not be parsed
+fn parse_error_metadata(error: &str) -> (String, MetaValue) {
+    ("parse_error".to_string(), error.into())
+}
+
+/// `metadata["truncated_params"]`, present only when `max_params` left
+/// literals out of `params`
+fn truncated_params_metadata(count: usize) -> Option<(String, MetaValue)> {
+    (count > 0).then(|| ("truncated_params".to_string(), MetaValue::Int(count as i64)))
+}
+
+/// `metadata["binds"]`, the bind placeholders of the input, present only
+/// when it has some
+fn binds_metadata(binds: Vec<String>) -> Option<(String, MetaValue)> {
+    (!binds.is_empty()).then(|| ("binds".to_string(), MetaValue::List(binds.into_iter().map(Into::into).collect())))
+}
+
+/// `metadata["literal_counts"]`, the number of literals replaced in each
+/// clause, present only when there were some
+fn literal_counts_metadata(counts: Vec<(Clause, usize)>) -> Option<(String, MetaValue)> {
+    (!counts.is_empty()).then(|| {
+        let counts = counts.into_iter().map(|(clause, count)| (clause.as_str().to_string(), MetaValue::Int(count as i64)));
+        ("literal_counts".to_string(), MetaValue::Map(counts.collect()))
+    })
+}
+
+/// `metadata["possible_cartesian"]`, set only when `stmt` may be one
+pub fn cartesian_metadata(stmt: &Statement) -> Option<(String, MetaValue)> {
+    cartesian::possible_cartesian(stmt).then(|| ("possible_cartesian".to_string(), MetaValue::Bool(true)))
+}
+
+/// Settings shared by the normalizing entry points
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Settings {
+    pub placeholder: String,
+    /// Number of the first placeholder, when `placeholder` contains `{n}`
+    pub placeholder_start: usize,
+    pub rules: Rules,
+    /// The pipeline to run; only passes that `rules` provides
+    pub steps: Vec<Step>,
+    pub templates: bool,
+    pub strict: bool,
+    /// Add `metadata["clause_hashes"]`
+    pub clause_hashes: bool,
+    /// Depth beyond which subqueries are replaced by `SELECT ...`
+    pub max_subquery_depth: Option<usize>,
+    /// Columns whose literals go in `metadata["routing_keys"]`
+    pub routing_columns: Vec<String>,
+    pub placeholder_collision: collision::Collision,
+    /// Trailing clauses of the outermost statement left out of the hashes
+    pub ignore_clauses: Vec<trailing::Trailing>,
+    /// Columns of each table, by lowercased name, to rewrite natural joins with
+    pub table_columns: BTreeMap<String, Vec<String>>,
+    /// Function names to rewrite, by lowercased alias, each to the one it is
+    /// an alias of
+    pub function_synonyms: BTreeMap<String, String>,
+    /// Whether to keep literals in `params`, see `LiteralPass::collect_params`
+    pub collect_params: bool,
+    /// Literals kept in `params` at most
+    pub max_params: Option<usize>,
+    /// Fingerprint the longest prefix that parses of statements that do not
+    pub truncated_input: bool,
+    /// Normalize statements that do not parse token by token
+    pub lenient: bool,
+    /// Collapse IN lists of constants to a single placeholder
+    pub collapse_in_lists: bool,
+    /// Collapse VALUES rows of the same shape to the first
+    pub collapse_values: bool,
+    /// Literals left as written
+    pub keep: keep::Keep,
+    /// Table qualifiers dropped
+    pub strip_qualifiers: qualifiers::Strip,
+    /// Rename tables, columns and schemas once normalized, for
+    /// `metadata["anonymized"]`
+    pub anonymize: bool,
+    /// Add `metadata["comments"]` and `metadata["hints"]`
+    pub comments: bool,
+    /// Write optimizer hints back into `normalized`
+    pub keep_hints: bool,
+    /// Fingerprint the statement an `EXPLAIN` wraps instead
+    pub unwrap_explain: bool,
+    /// Write the literals' placeholders in the order of `params`, for
+    /// executing `normalized`
+    pub prepared: bool,
+    /// How `hash` and `shape_hash` are computed
+    pub hasher: hashing::Hasher,
+}
+
+/// A dialect to parse statements with, by canonical name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Dialect(&'static str);
+
+impl Dialect {
+    pub const GENERIC: Dialect = Dialect("generic");
+    pub const ANSI: Dialect = Dialect("ansi");
+    pub const MYSQL: Dialect = Dialect("mysql");
+    pub const POSTGRESQL: Dialect = Dialect("postgresql");
+    pub const SQLITE: Dialect = Dialect("sqlite");
+    pub const MSSQL: Dialect = Dialect("mssql");
+    pub const ORACLE: Dialect = Dialect("oracle");
+    pub const BIGQUERY: Dialect = Dialect("bigquery");
+    pub const SNOWFLAKE: Dialect = Dialect("snowflake");
+    pub const CLICKHOUSE: Dialect = Dialect("clickhouse");
+    pub const DUCKDB: Dialect = Dialect("duckdb");
+    pub const HIVE: Dialect = Dialect("hive");
+    pub const REDSHIFT: Dialect = Dialect("redshift");
+    pub const DATABRICKS: Dialect = Dialect("databricks");
+    /// The dialect detected from each statement itself
+    pub const AUTO: Dialect = Dialect("auto");
+
+    /// The dialect `name`, one of the names `normalize()` takes in Python
+    /// (`"postgres"` and the other aliases included)
+    pub fn new(name: &str) -> Result<Dialect, Error> {
+        canonical_dialect(name).map(Dialect).map_err(Error::Options)
+    }
+
+    /// Its canonical name, as in `Fingerprint::dialect_used`
+    pub fn name(self) -> &'static str {
+        self.0
+    }
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Dialect::GENERIC
+    }
+}
+
+impl std::str::FromStr for Dialect {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Dialect, Error> {
+        Dialect::new(name)
+    }
+}
+
+/// The options of `normalize()`, with the defaults of the Python module's.
+/// Its `dialect` is that passed to `normalize()`.
+pub use pool::EngineKey as Options;
+
+/// Why a statement could not be normalized; `E` is the error of the
+/// caller's redactor and hooks, if there are any
+#[derive(Debug)]
+pub enum Error<E = std::convert::Infallible> {
+    /// The statement does not parse, or there is none
+    Parse(String),
+    /// An option has a value that is not supported
+    Options(String),
+    /// With `strict`, parts of the statement could not be normalized; with
+    /// `prepared`, its binds would take the parameters of the literals
+    Unnormalizable { message: String, warnings: Vec<String> },
+    /// The placeholder also occurs in the statement, per `placeholder_collision`
+    PlaceholderCollision(String),
+    /// The redactor or a hook failed
+    Callback(E),
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Parse(message)
+            | Error::Options(message)
+            | Error::Unnormalizable { message, .. }
+            | Error::PlaceholderCollision(message) => f.write_str(message),
+            Error::Callback(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for Error<E> {}
+
+/// A caller's rewrite of the statement at each `Step::Hook`, by index
+pub type SqlHook<'a, E> = dyn FnMut(usize, &str) -> Result<Option<String>, E> + 'a;
+
+/// The fingerprint of a statement
+#[derive(Clone, Debug)]
+pub struct Fingerprint {
+    /// The statement with its literals replaced by placeholders and the
+    /// passes applied
+    pub normalized: String,
+    /// Hash of `normalized`, the fingerprint
+    pub hash: String,
+    /// Hash of `normalized` with its value lists collapsed
+    pub shape_hash: String,
+    /// How `hash` and `shape_hash` were computed
+    pub hasher: hashing::Hasher,
+    /// `normalized` with its value lists collapsed, if that changed it
+    pub shape: Option<String>,
+    /// The statement as given
+    pub original: String,
+    /// The literals replaced by placeholders, as written but without the
+    /// prefix of a prefixed string: `'abc'` for `N'abc'`, `'00'` for `X'00'`
+    pub params: Vec<String>,
+    /// Prefix of each of `params` written in a prefixed string form
+    /// (`N'...'`, `E'...'`, ...), `None` for the others; with its param, the
+    /// literal as written
+    pub param_types: Vec<Option<String>>,
+    /// Kind of each of `params` written as a number (`integer`, `decimal` or
+    /// `float`), `None` for the others
+    pub param_kinds: Vec<Option<&'static str>>,
+    /// Byte offsets `(start, end)` of each of `params` in `original`, `None`
+    /// where the literal has no place there
+    pub param_spans: Vec<Option<(usize, usize)>>,
+    /// The placeholder the literals were written as, and the number of the
+    /// first where it is numbered
+    pub placeholder: String,
+    pub placeholder_start: usize,
+    /// Constructs that could not be normalized
+    pub warnings: Vec<String>,
+    /// Names of the passes (and `hook:<name>` for caller hooks) that
+    /// produced `normalized`, in the order they ran
+    pub passes: Vec<String>,
+    /// Whether the statement has a RETURNING clause, its columns being in
+    /// `metadata["returning"]`
+    pub has_returning: bool,
+    /// `"SELECT"`, `"INSERT"`, `"UPDATE"`, `"DELETE"`, `"DDL"` or `"OTHER"`,
+    /// see `classify`
+    pub statement_class: &'static str,
+    pub is_read_only: bool,
+    pub is_ddl: bool,
+    /// Whether the statement was the one an `EXPLAIN` wraps, see
+    /// `unwrap_explain`
+    pub explained: bool,
+    /// Complexity of the normalized statement, `None` where it was not parsed
+    pub metrics: Option<metrics::Metrics>,
+    /// Canonical name of the dialect the statement was parsed with
+    pub dialect_used: &'static str,
+    /// How the fingerprint was computed: `"ast"` from the parsed statement,
+    /// `"tokens"` from its token stream when it could not be parsed
+    pub parse_path: &'static str,
+    pub metadata: Metadata,
+    /// Kind of the statement, the name of its `Statement` variant
+    pub statement_type: String,
+    /// The settings the statement was normalized with
+    pub options: std::sync::Arc<pool::EngineKey>,
+}
+
+/// Normalize and fingerprint the first statement of `sql`, parsed with
+/// `dialect`
+pub fn normalize(sql: &str, dialect: Dialect, options: &Options) -> Result<Fingerprint, Error> {
+    let key = pool::EngineKey { dialect: dialect.name().to_string(), ..options.clone() };
+    let engine = pool::engine(key).map_err(Error::Options)?;
+    // Hooks are Python callables
+    if engine.options.steps.contains(&Step::Hook) {
+        return Err(Error::Options("passes can only name passes".to_string()));
+    }
+    normalize_text(sql.to_string(), &engine, &mut |_, _, value| Ok(Some(value)), &mut |_, _| Ok(None), &[])
+}
+
+/// `values` in `order`, if it has each of them once
+fn reordered<T: Clone>(values: &mut Vec<T>, order: &[usize]) {
+    let mut sorted = order.to_vec();
+    sorted.sort_unstable();
+    if sorted.iter().copied().eq(0..values.len()) {
+        *values = order.iter().map(|&i| values[i].clone()).collect();
+    }
+}
+
+/// `normalize_text()` with `prepared=True`: the statement is normalized with
+/// numbered placeholders, whose order in the text gives that of `params`
+fn prepared_text<E>(
+    sql: String,
+    engine: &pool::Engine,
+    redactor: &mut ParamHook<'_, E>,
+    hooks: &mut SqlHook<'_, E>,
+    hook_names: &[String],
+) -> Result<Fingerprint, Error<E>> {
+    let key = &engine.key;
+    let numbered = collision::numbered(&key.placeholder).unwrap_or_else(|| key.placeholder.clone());
+    let numbering = pool::engine(pool::EngineKey { placeholder: numbered, prepared: false, ..(**key).clone() })
+        .map_err(Error::Options)?;
+    let mut result = normalize_text(sql, &numbering, redactor, hooks, hook_names)?;
+    // Binds have no value in `params`: only those numbered in the style of
+    // the placeholder, which the literals are numbered after, leave the
+    // literals' placeholders a place the driver can bind `params` to
+    if let Some((_, MetaValue::List(binds))) = result.metadata.iter().find(|(name, _)| name == "binds") {
+        let others: Vec<&str> = binds
+            .iter()
+            .filter_map(|bind| match bind {
+                MetaValue::Str(bind) if !engine.options.rules.bind_numbering => Some(bind.as_str()),
+                MetaValue::Str(bind) => placeholder_number(&key.placeholder, bind).is_none().then_some(bind.as_str()),
+                _ => None,
+            })
+            .collect();
+        if !others.is_empty() {
+            return Err(Error::Unnormalizable {
+                message: format!("prepared cannot be combined with binds not numbered like the placeholder: {}", others.join(", ")),
+                warnings: Vec::new(),
+            });
+        }
+    }
+    let dialect = &*engine.dialect;
+    let (numbered, start, count) = (result.placeholder.clone(), result.placeholder_start, result.params.len());
+    let prepare = |sql: &str, percent: bool| {
+        prepared::prepare(sql, dialect, &numbered, start, &key.placeholder, count, percent).map_err(Error::Parse)
+    };
+    let (plain, order) = prepare(&result.normalized, false)?;
+    reordered(&mut result.params, &order);
+    reordered(&mut result.param_types, &order);
+    reordered(&mut result.param_kinds, &order);
+    reordered(&mut result.param_spans, &order);
+    for (name, value) in &mut result.metadata {
+        if let (true, MetaValue::List(positions)) = (name == "param_positions", value) {
+            reordered(positions, &order);
+        }
+    }
+    let hashed = |sql: &str| -> Result<String, Error<E>> {
+        Ok(compute_hash(&result.hasher, &hashed_text(engine, sql).map_err(Error::Parse)?))
+    };
+    let shape = result.shape.as_deref().map(|shape| prepare(shape, false)).transpose()?.map(|(shape, _)| shape);
+    result.hash = hashed(&plain)?;
+    result.shape_hash = match shape {
+        Some(ref shape) => hashed(shape)?,
+        None => result.hash.clone(),
+    };
+    result.shape = shape;
+    result.normalized = if key.placeholder.starts_with('%') { prepare(&result.normalized, true)?.0 } else { plain };
+    result.placeholder = key.placeholder.clone();
+    result.options = std::sync::Arc::clone(key);
+    Ok(result)
+}
+
+/// Shared implementation of `normalize()` and the Python module's entry
+/// points
+///
+/// `redactor` gets each literal's index, clause and value, and returns the
+/// parameter to keep for it, or `None` to leave it out of `params`. `hooks`
+/// rewrites the statement at the `Step::Hook`s of the engine's pipeline,
+/// named `hook_names` in `passes`: it gets the hook's index and the statement
+/// as it stands, and returns the rewritten SQL, or `None` to leave it
+/// unchanged.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "sqlfp.normalize", level = "debug", skip_all, fields(dialect = engine.dialect_name, size = sql.len()))
+)]
+pub fn normalize_text<E>(
+    sql: String,
+    engine: &pool::Engine,
+    redactor: &mut ParamHook<'_, E>,
+    hooks: &mut SqlHook<'_, E>,
+    hook_names: &[String],
+) -> Result<Fingerprint, Error<E>> {
+    let options = &engine.options;
+    let (placeholder, start, steps) = (options.placeholder.as_str(), options.placeholder_start, &options.steps);
+    let mut metadata = Metadata::new();
+    let mut text = if options.templates {
+        let (plain, markers) = template::strip_templates(&sql);
+        metadata.push(template_metadata(&markers));
+        Cow::Owned(plain)
+    } else {
+        Cow::Borrowed(sql.as_str())
+    };
+    if engine.dialect_name == "auto" {
+        let key = pool::EngineKey { dialect: detect::detect(&text).to_string(), ..(*engine.key).clone() };
+        let engine = pool::engine(key).map_err(Error::Options)?;
+        return normalize_text(sql, &engine, redactor, hooks, hook_names);
+    }
+    if options.prepared {
+        return prepared_text(sql, engine, redactor, hooks, hook_names);
+    }
+    let comments = (options.comments || options.keep_hints).then(|| comments::extract(&text, &*engine.dialect));
+    if options.comments {
+        metadata.extend(comments.iter().flat_map(comments::Comments::metadata));
+    }
+    let mut ignored_statements = 0;
+    let mut parsed = parse_leading_statement(&text, &*engine.dialect).map(|(stmt, rest)| {
+        ignored_statements = rest;
+        stmt
+    });
+    // MySQL's bulk loads and exports, which sqlparser does not parse
+    let mut export = None;
+    if parsed.is_err() {
+        if let Some(tokens) = bulk::load_data(&text, &*engine.dialect) {
+            metadata.push(bulk_load_metadata());
+            let class = classify::Class { class: "INSERT", read_only: false };
+            return tokens_result(sql, engine, &tokens, metadata, redactor, class, "LoadData");
+        }
+        if let Some((select, clause)) = bulk::split_export(&text, &*engine.dialect) {
+            if let Ok(stmt) = parse_first_statement(&select, &*engine.dialect) {
+                (parsed, text, export) = (Ok(stmt), Cow::Owned(select), Some(clause));
+            }
+        }
+    }
+    if parsed.is_err() && options.truncated_input {
+        if let Some((prefix, stmt)) = truncated::prefix(&text, &*engine.dialect) {
+            (parsed, text) = (Ok(stmt), Cow::Owned(prefix));
+            metadata.push(truncated_input_metadata());
+        }
+    }
+    if let (Err(e), true) = (&parsed, options.lenient) {
+        if let Some((tokens, class)) = lenient::tokens(&text, &*engine.dialect) {
+            stats::bump(&stats::PARSE_FAILURES);
+            metadata.push(parse_error_metadata(e));
+            return tokens_result(sql, engine, &tokens, metadata, redactor, class, "Unparsed");
+        }
+    }
+    let stmt = &mut parsed
+        .inspect_err(|_e| {
+            stats::bump(&stats::PARSE_FAILURES);
+            #[cfg(feature = "tracing")]
+            tracing::debug!(error = %_e, "parse failed");
+        })
+        .map_err(Error::Parse)?;
+    let explained = options.unwrap_explain && explain::unwrap(stmt);
+    if options.rules.quoted_binds {
+        binds::restore_quotes(stmt, &text, &*engine.dialect);
+    }
+    if let Some(max) = options.max_subquery_depth {
+        metadata.extend(truncated_subqueries_metadata(depth::limit(stmt, max)));
+    }
+    metadata.extend(ignored_statements_metadata(ignored_statements));
+    metadata.extend(introspection::introspection_metadata(stmt));
+    if !options.routing_columns.is_empty() {
+        metadata.extend(routing::routing_keys(stmt, &options.routing_columns));
+    }
+    metadata.extend(natural::uses_natural_join(stmt));
+    qualifiers::strip(stmt, &options.strip_qualifiers);
+    natural::expand(stmt, &options.table_columns);
+    synonyms::rename(stmt, &options.function_synonyms);
+    let step_hook = &mut |index: usize, stmt: &mut Statement| {
+        let rewritten = hooks(index, &stmt.to_string()).map_err(Error::Callback)?;
+        if let Some(ref sql) = rewritten {
+            *stmt = parse_first_statement(sql, &*engine.dialect).map_err(Error::Parse)?;
+        }
+        Ok(rewritten.is_some())
+    };
+    let mut normalize = |stmt: &mut Statement, placeholder: &str| {
+        normalize_statement(
+            stmt,
+            placeholder,
+            start,
+            steps,
+            &options.rules,
+            options.collect_params,
+            options.max_params,
+            options.keep,
+            &mut |index, clause, value| redactor(index, clause, value).map_err(Error::Callback),
+            &mut *step_hook,
+        )
+    };
+    // The placeholder is numbered before the redactor and the hooks are
+    // called, so that they are called once: the passes alone run on a copy of
+    // the statement to find its collisions
+    let mut placeholder = Cow::Borrowed(placeholder);
+    if options.placeholder_collision == collision::Collision::Numbered {
+        let spelled = writer::write(stmt).contains(&*placeholder);
+        if let Some(numbered) = collision::numbered(&placeholder).filter(|_| spelled) {
+            let mut probe = stmt.clone();
+            let probed = normalize_statement(
+                &mut probe,
+                &placeholder,
+                start,
+                steps,
+                &options.rules,
+                false,
+                options.max_params,
+                options.keep,
+                &mut |_, _, value| Ok::<_, Error<E>>(Some(value)),
+                &mut |_, _| Ok(false),
+            )?;
+            if collision::collisions(&probe, &probed.sql, &placeholder) > 0 {
+                placeholder = Cow::Owned(numbered);
+            }
+        }
+    }
+    let result = normalize(stmt, &placeholder)?;
+    let collisions = collision::collisions(stmt, &result.sql, &placeholder);
+    let Normalized {
+        sql: mut normalized,
+        mut params,
+        mut param_types,
+        mut param_kinds,
+        mut param_positions,
+        mut param_spans,
+        warnings,
+        duplicate_branches,
+        collapsed_case_arms,
+        binds,
+        start,
+        mut literal_counts,
+        mut truncated_params,
+    } = result;
+    if options.strict && !warnings.is_empty() {
+        return Err(Error::Unnormalizable {
+            message: format!("Statement cannot be fully normalized: {}", warnings.join("; ")),
+            warnings,
+        });
+    }
+    // Numbered placeholders that collide as well, or collisions a hook
+    // brought in, leave no way out
+    if collisions > 0 && matches!(options.placeholder_collision, collision::Collision::Raise | collision::Collision::Numbered) {
+        return Err(Error::PlaceholderCollision(format!(
+            "Placeholder {:?} also occurs {} time(s) in the statement itself",
+            placeholder, collisions
+        )));
+    }
+    let escape = collisions > 0 && options.placeholder_collision == collision::Collision::Escape;
+    if escape {
+        normalized = collision::escape(stmt, &placeholder);
+    }
+    let metrics = metrics::metrics(stmt);
+    let collapsed_in_lists = options.collapse_in_lists && collapse::collapse_in_lists(stmt, &placeholder, start);
+    let values_rows = options.collapse_values.then(|| collapse::collapse_values(stmt, &placeholder, start)).flatten();
+    let anonymized = options.anonymize.then(|| anonymize::anonymize(stmt, options.rules.identifier_booleans));
+    if collapsed_in_lists || values_rows.is_some() || anonymized.is_some() {
+        normalized = if escape { collision::escape(stmt, &placeholder) } else { writer::write(stmt) };
+    }
+    metadata.extend(anonymized);
+    let hints = comments.as_ref().filter(|_| options.keep_hints);
+    if let Some(hints) = hints {
+        normalized = hints.attach(&normalized, &*engine.dialect);
+    }
+    // The export clause goes after the query, its literals after the query's
+    let mut suffix = String::new();
+    if let Some(ref clause) = export {
+        let index = literal_counts.iter().map(|(_, count)| count).sum();
+        let parameterize = steps.contains(&Step::Pass(Pass::LiteralParameterize));
+        if parameterize {
+            for (param, param_type, kind) in bulk_params(clause, index, redactor, &mut literal_counts).map_err(Error::Callback)? {
+                if options.max_params.is_some_and(|max| params.len() >= max) {
+                    truncated_params += 1;
+                    continue;
+                }
+                params.push(param);
+                param_types.push(param_type);
+                param_kinds.push(kind);
+                param_positions.push(None);
+                param_spans.push(Span::empty());
+            }
+        }
+        suffix = format!(" {}", clause.write(&placeholder, start + index, parameterize));
+        normalized.push_str(&suffix);
+        // An export of the catalog is still an export
+        metadata.retain(|(key, _)| key != "statement_class");
+        metadata.push(bulk_load_metadata());
+    }
+    metadata.extend(placeholder_collisions_metadata(collisions));
+    metadata.extend(duplicate_branches_metadata(duplicate_branches));
+    metadata.extend(collapsed_case_arms_metadata(collapsed_case_arms));
+    metadata.extend(binds_metadata(binds));
+    metadata.extend(literal_counts_metadata(literal_counts));
+    metadata.extend(truncated_params_metadata(truncated_params));
+    metadata.extend(collapse::values_rows_metadata(values_rows));
+    metadata.extend(returning_metadata(stmt));
+    metadata.extend(distinct_metadata(stmt));
+    metadata.extend(wildcard::wildcard_tables(stmt));
+    metadata.extend(cartesian_metadata(stmt));
+    if options.clause_hashes {
+        metadata.push(clauses::clause_hashes(stmt));
+    }
+    let written = |stmt: &Statement| {
+        let mut sql = if escape { collision::escape(stmt, &placeholder) } else { writer::write(stmt) };
+        if let Some(hints) = hints {
+            sql = hints.attach(&sql, &*engine.dialect);
+        }
+        sql.push_str(&suffix);
+        sql
+    };
+    let stripped = trailing::stripped(stmt, &options.ignore_clauses).map(|stripped| written(&stripped));
+    let hashed = stripped.as_deref().unwrap_or(&normalized);
+    let hash = compute_hash(&options.hasher, hashed);
+    if options.rules.jumbling {
+        metadata.push(query_id_metadata(hashed));
+    }
+    let shape = collapse::collapse_lists(stmt, &placeholder, start).then(|| written(stmt));
+    metadata.extend(collapse::positions_metadata(shape.is_some() || collapsed_in_lists || values_rows.is_some(), &param_positions));
+    let shape_hash = match (&shape, trailing::stripped(stmt, &options.ignore_clauses)) {
+        (Some(_), Some(stripped)) => compute_hash(&options.hasher, &written(&stripped)),
+        (shape, _) => shape.as_deref().map_or_else(|| hash.clone(), |shape| compute_hash(&options.hasher, shape)),
+    };
+    // pt-fingerprint's fingerprint has its lists collapsed already
+    let (normalized, hash, shape_hash, shape) = if options.rules.percona {
+        let fingerprint = percona::fingerprint(&sql);
+        metadata.push(checksum_metadata(&fingerprint));
+        let hash = compute_hash(&options.hasher, &fingerprint);
+        (fingerprint, hash.clone(), hash, None)
+    } else {
+        (normalized, hash, shape_hash, shape)
+    };
+    let param_spans = offsets::byte_spans(&sql, &text, &param_spans);
+    let class = classify::classify(stmt);
+    stats::record_statement(engine.dialect_name);
+
+    Ok(Fingerprint {
+        normalized,
+        hash,
+        shape_hash,
+        hasher: options.hasher,
+        shape,
+        original: sql,
+        params,
+        param_types,
+        param_kinds,
+        param_spans,
+        placeholder: placeholder.into_owned(),
+        placeholder_start: start,
+        warnings,
+        passes: step_names(steps, hook_names),
+        has_returning: returning_items(stmt).is_some(),
+        statement_class: class.class,
+        is_read_only: class.read_only,
+        is_ddl: class.is_ddl(),
+        explained,
+        metrics: Some(metrics),
+        dialect_used: engine.dialect_name,
+        parse_path: PARSE_PATH_AST,
+        metadata,
+        statement_type: ast::variant_name(stmt),
+        options: std::sync::Arc::clone(&engine.key),
+    })
+}
+
+/// A parameter with its type and kind, as in `Fingerprint`
+type Param = (String, Option<String>, Option<&'static str>);
+
+/// The literals of the bulk statement `tokens`, the first numbered `index`, as
+/// `params` with their types and kinds, through `redactor`; counted per
+/// clause in `counts` like those the passes replace
+fn bulk_params<E>(
+    tokens: &bulk::Tokens,
+    index: usize,
+    redactor: &mut ParamHook<'_, E>,
+    counts: &mut Vec<(Clause, usize)>,
+) -> Result<Vec<Param>, E> {
+    let mut params = Vec::new();
+    for (i, literal) in tokens.literals().enumerate() {
+        let clause = literal.clause;
+        match counts.iter_mut().find(|(c, _)| *c == clause) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((clause, 1)),
+        }
+        if let Some(value) = redactor(index + i, clause, literal.value.clone())? {
+            params.push((value, literal.prefix.map(str::to_string), literal.kind));
+        }
+    }
+    Ok(params)
+}
+
+/// `metadata["statement_class"]` of `LOAD DATA` and `SELECT ... INTO OUTFILE`
+fn bulk_load_metadata() -> (String, MetaValue) {
+    ("statement_class".to_string(), "bulk_load".into())
+}
+
+/// `normalize_text` for a statement normalized token by token, a `LOAD
+/// DATA` or, with `lenient`, one that could not be parsed: only
+/// `literal-parameterize` applies, and there are no lists to collapse
+fn tokens_result<E>(
+    sql: String,
+    engine: &pool::Engine,
+    tokens: &bulk::Tokens,
+    mut metadata: Metadata,
+    redactor: &mut ParamHook<'_, E>,
+    class: classify::Class,
+    statement_type: &str,
+) -> Result<Fingerprint, Error<E>> {
+    let options = &engine.options;
+    let parameterize = options.steps.contains(&Step::Pass(Pass::LiteralParameterize));
+    let (mut params, mut param_types, mut param_kinds, mut literal_counts) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    if parameterize {
+        for (param, param_type, kind) in bulk_params(tokens, 0, redactor, &mut literal_counts).map_err(Error::Callback)? {
+            params.push(param);
+            param_types.push(param_type);
+            param_kinds.push(kind);
+        }
+    }
+    let normalized = if options.rules.percona {
+        let fingerprint = percona::fingerprint(&sql);
+        metadata.push(checksum_metadata(&fingerprint));
+        fingerprint
+    } else {
+        tokens.write(&options.placeholder, options.placeholder_start, parameterize)
+    };
+    metadata.extend(literal_counts_metadata(literal_counts));
+    let hash = compute_hash(&options.hasher, &normalized);
+    stats::record_statement(engine.dialect_name);
+
+    Ok(Fingerprint {
+        normalized,
+        shape_hash: hash.clone(),
+        hasher: options.hasher,
+        hash,
+        shape: None,
+        original: sql,
+        param_spans: vec![None; params.len()],
+        placeholder: options.placeholder.clone(),
+        placeholder_start: options.placeholder_start,
+        params,
+        param_types,
+        param_kinds,
+        warnings: Vec::new(),
+        passes: if parameterize { vec![Pass::LiteralParameterize.name().to_string()] } else { Vec::new() },
+        has_returning: false,
+        statement_class: class.class,
+        is_read_only: class.read_only,
+        is_ddl: class.is_ddl(),
+        explained: false,
+        metrics: None,
+        dialect_used: engine.dialect_name,
+        parse_path: PARSE_PATH_TOKENS,
+        metadata,
+        statement_type: statement_type.to_string(),
+        options: std::sync::Arc::clone(&engine.key),
+    })
+}
+
+/// `Fingerprint.passes` for `steps`, with `hook_names` for their hooks
+pub fn step_names(steps: &[Step], hook_names: &[String]) -> Vec<String> {
+    let mut hook_names = hook_names.iter();
+    steps
+        .iter()
+        .map(|step| match step {
+            Step::Pass(pass) => pass.name().to_string(),
+            Step::Hook => format!("hook:{}", hook_names.next().expect("one name per Step::Hook")),
+        })
+        .collect()
+}
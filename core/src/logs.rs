@@ -0,0 +1,313 @@
+//! Server log parsing, for `fingerprint_mysql_slowlog()` and
+//! `fingerprint_postgres_csvlog()`.
+//!
+//! Logs are read a line at a time, so that memory stays bounded by the
+//! longest entry whatever the size of the file; each parser takes the lines in
+//! turn and returns the entries they complete, with the SQL they logged and
+//! the metadata of its run.
+//!
+//! A MySQL slow log entry is a block of `# ` header lines (`# Time:`,
+//! `# User@Host:`, `# Query_time:` and, from Percona Server, `# Schema:`)
+//! followed by the query, behind the `use db;` and `SET timestamp=...;`
+//! lines the server writes ahead of it. Servers before 5.7 write `# Time:`
+//! only when the second changes, so an entry without one keeps the last.
+//! The banner written at each server start is skipped, as are the
+//! `# administrator command:` entries, which hold no SQL.
+//!
+//! A PostgreSQL csvlog record is a CSV row, its quoted fields spanning lines
+//! where the text they hold does. Only the `statement:` and `execute ...:`
+//! messages of `log_statement` and `log_min_duration_statement` log SQL (the
+//! `parse` and `bind` of the extended protocol would count the same execution
+//! three times); the others are skipped.
+
+use crate::metadata::{MetaValue, Metadata};
+
+/// A statement of a log and the metadata it was logged with
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub sql: String,
+    /// 1-based line the SQL starts on
+    pub line: usize,
+    pub metadata: Metadata,
+}
+
+/// A log format, read a line at a time
+pub trait LogParser {
+    /// Take the `line_no`th line, without its terminator; the entry it
+    /// completes, if any
+    fn feed(&mut self, line_no: usize, line: &str) -> Result<Option<Entry>, String>;
+
+    /// The entry left at the end of the log, if any
+    fn finish(&mut self) -> Result<Option<Entry>, String>;
+}
+
+/// The microseconds in `text`, a decimal number of seconds (`per_second`
+/// 1) or milliseconds (1000), exactly; `None` if it is not a number
+fn micros(text: &str, per_second: u32) -> Option<i64> {
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    if whole.is_empty() || !whole.bytes().all(|b| b.is_ascii_digit()) || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let digits = (6 - per_second.ilog10()) as usize;
+    let fraction = format!("{:0<width$}", &fraction[..fraction.len().min(digits)], width = digits);
+    let scale = 10i64.pow(digits as u32);
+    Some(whole.parse::<i64>().ok()?.checked_mul(scale)? + fraction.parse::<i64>().unwrap_or(0))
+}
+
+/// `value` as metadata, `None` for an absent or empty one
+fn text_value(value: Option<&str>) -> MetaValue {
+    value.filter(|v| !v.is_empty()).map_or(MetaValue::Null, MetaValue::from)
+}
+
+fn int_value(value: Option<i64>) -> MetaValue {
+    value.map_or(MetaValue::Null, MetaValue::Int)
+}
+
+/// The MySQL slow query log
+#[derive(Default)]
+pub struct MysqlSlowlog {
+    /// Line the SQL of the entry being read starts on
+    start: Option<usize>,
+    /// `# Time:`, kept for the entries that have none
+    time: Option<String>,
+    unix_timestamp: Option<i64>,
+    user: Option<String>,
+    host: Option<String>,
+    thread_id: Option<i64>,
+    database: Option<String>,
+    query_time: Option<i64>,
+    lock_time: Option<i64>,
+    rows_sent: Option<i64>,
+    rows_examined: Option<i64>,
+    sql: Vec<String>,
+}
+
+impl MysqlSlowlog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The entry read so far, if it has SQL, and start the next
+    fn flush(&mut self) -> Option<Entry> {
+        let start = self.start.take();
+        let sql = std::mem::take(&mut self.sql);
+        let entry = Entry {
+            sql: sql.join("\n"),
+            line: start.unwrap_or(1),
+            metadata: vec![
+                ("timestamp".to_string(), text_value(self.time.as_deref())),
+                ("unix_timestamp".to_string(), int_value(self.unix_timestamp.take())),
+                ("user".to_string(), text_value(self.user.take().as_deref())),
+                ("host".to_string(), text_value(self.host.take().as_deref())),
+                ("thread_id".to_string(), int_value(self.thread_id.take())),
+                ("database".to_string(), text_value(self.database.take().as_deref())),
+                ("duration_us".to_string(), int_value(self.query_time.take())),
+                ("lock_time_us".to_string(), int_value(self.lock_time.take())),
+                ("rows_sent".to_string(), int_value(self.rows_sent.take())),
+                ("rows_examined".to_string(), int_value(self.rows_examined.take())),
+            ],
+        };
+        (!sql.is_empty()).then_some(entry)
+    }
+
+    /// `# User@Host: priv_user[user] @ host [ip]  Id: 8`
+    fn user_host(&mut self, rest: &str) {
+        let (account, rest) = rest.split_once(" @ ").unwrap_or((rest, ""));
+        // The account the privileges were checked against, in brackets
+        let user = account.split_once('[').map_or(account, |(_, user)| user.trim_end_matches(']'));
+        self.user = Some(user.trim().to_string());
+        let (address, id) = rest.split_once("Id:").unwrap_or((rest, ""));
+        let (host, ip) = address.split_once('[').unwrap_or((address, ""));
+        let ip = ip.split(']').next().unwrap_or_default();
+        self.host = Some(if host.trim().is_empty() { ip.trim() } else { host.trim() }.to_string());
+        self.thread_id = id.trim().parse().ok();
+    }
+
+    /// `# Query_time: 0.000123  Lock_time: 0.000045 Rows_sent: 1  Rows_examined: 1`
+    fn timings(&mut self, line: &str) {
+        let mut words = line.split_whitespace();
+        while let Some(word) = words.next() {
+            let Some(name) = word.strip_suffix(':') else {
+                continue;
+            };
+            let value = words.next().unwrap_or_default();
+            match name {
+                "Query_time" => self.query_time = micros(value, 1),
+                "Lock_time" => self.lock_time = micros(value, 1),
+                "Rows_sent" => self.rows_sent = value.parse().ok(),
+                "Rows_examined" => self.rows_examined = value.parse().ok(),
+                "Schema" => self.database = Some(value.to_string()),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Whether `line` is part of the banner a server writes to its slow log
+/// when it starts
+fn banner(line: &str) -> bool {
+    line.ends_with("started with:")
+        || line.starts_with("Tcp port:")
+        || (line.starts_with("Time ") && line.ends_with("Id Command    Argument"))
+}
+
+impl LogParser for MysqlSlowlog {
+    fn feed(&mut self, line_no: usize, line: &str) -> Result<Option<Entry>, String> {
+        let reading_sql = !self.sql.is_empty();
+        if let Some(time) = line.strip_prefix("# Time:") {
+            let entry = self.flush();
+            self.time = Some(time.trim().to_string());
+            return Ok(entry);
+        }
+        if let Some(rest) = line.strip_prefix("# User@Host:") {
+            let entry = if reading_sql || self.user.is_some() { self.flush() } else { None };
+            self.user_host(rest);
+            return Ok(entry);
+        }
+        if banner(line) {
+            return Ok(self.flush());
+        }
+        if reading_sql {
+            self.sql.push(line.to_string());
+            return Ok(None);
+        }
+        let statement = line.trim().trim_end_matches(';');
+        if line.starts_with("# ") {
+            // `# administrator command: Quit;` leaves the entry without SQL
+            self.timings(line);
+        } else if let Some(database) = statement.strip_prefix("use ").or_else(|| statement.strip_prefix("USE ")) {
+            self.database = Some(database.trim().trim_matches('`').to_string());
+        } else if let Some(timestamp) = statement.strip_prefix("SET timestamp=") {
+            self.unix_timestamp = timestamp.parse().ok();
+        } else if !line.trim().is_empty() {
+            self.start = Some(line_no);
+            self.sql.push(line.to_string());
+        }
+        Ok(None)
+    }
+
+    fn finish(&mut self) -> Result<Option<Entry>, String> {
+        Ok(self.flush())
+    }
+}
+
+/// Fields of a csvlog row, by position
+const LOG_TIME: usize = 0;
+const USER_NAME: usize = 1;
+const DATABASE_NAME: usize = 2;
+const PROCESS_ID: usize = 3;
+const SESSION_ID: usize = 5;
+const MESSAGE: usize = 13;
+const DETAIL: usize = 14;
+const APPLICATION_NAME: usize = 22;
+
+/// The PostgreSQL CSV log (`log_destination = 'csvlog'`)
+#[derive(Default)]
+pub struct PostgresCsvlog {
+    /// Line the row being read starts on, and its text so far
+    start: usize,
+    row: String,
+    /// Whether the text so far ends inside a quoted field
+    quoted: bool,
+}
+
+impl PostgresCsvlog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// The fields of a CSV row
+fn csv_fields(row: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().expect("at least one field");
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            _ => field.push(c),
+        }
+    }
+    fields
+}
+
+/// The duration and SQL of a csvlog message, if it logs a statement
+fn logged_statement(message: &str) -> Option<(Option<i64>, &str)> {
+    let (duration, message) = match message.strip_prefix("duration: ") {
+        Some(rest) => {
+            let (ms, rest) = rest.split_once(" ms")?;
+            (micros(ms, 1000), rest.trim_start())
+        }
+        None => (None, message),
+    };
+    let sql = match message.strip_prefix("statement: ") {
+        Some(sql) => sql,
+        // `execute <unnamed>: ...`, `execute S_1/C_2: ...`
+        None => message.strip_prefix("execute ")?.split_once(": ")?.1,
+    };
+    Some((duration, sql))
+}
+
+impl PostgresCsvlog {
+    fn entry(&self, row: &str) -> Result<Option<Entry>, String> {
+        let fields = csv_fields(row);
+        if fields.len() <= DETAIL {
+            return Err(format!("Not a csvlog row: {} fields", fields.len()));
+        }
+        let field = |i: usize| fields.get(i).map(String::as_str);
+        let Some((duration, sql)) = logged_statement(&fields[MESSAGE]) else {
+            return Ok(None);
+        };
+        let parameters = fields[DETAIL].strip_prefix("parameters: ");
+        Ok(Some(Entry {
+            sql: sql.to_string(),
+            line: self.start,
+            metadata: vec![
+                ("timestamp".to_string(), text_value(field(LOG_TIME))),
+                ("user".to_string(), text_value(field(USER_NAME))),
+                ("database".to_string(), text_value(field(DATABASE_NAME))),
+                ("pid".to_string(), int_value(field(PROCESS_ID).and_then(|pid| pid.parse().ok()))),
+                ("session_id".to_string(), text_value(field(SESSION_ID))),
+                ("application_name".to_string(), text_value(field(APPLICATION_NAME))),
+                ("duration_us".to_string(), int_value(duration)),
+                ("parameters".to_string(), text_value(parameters)),
+            ],
+        }))
+    }
+}
+
+impl LogParser for PostgresCsvlog {
+    fn feed(&mut self, line_no: usize, line: &str) -> Result<Option<Entry>, String> {
+        if self.row.is_empty() && !self.quoted {
+            if line.is_empty() {
+                return Ok(None);
+            }
+            self.start = line_no;
+        } else {
+            // The line break was in a quoted field
+            self.row.push('\n');
+        }
+        self.row.push_str(line);
+        self.quoted ^= line.bytes().filter(|&b| b == b'"').count() % 2 == 1;
+        if self.quoted {
+            return Ok(None);
+        }
+        let row = std::mem::take(&mut self.row);
+        self.entry(&row)
+    }
+
+    fn finish(&mut self) -> Result<Option<Entry>, String> {
+        if self.row.is_empty() {
+            return Ok(None);
+        }
+        self.row.clear();
+        self.quoted = false;
+        Err("Unterminated quoted field".to_string())
+    }
+}
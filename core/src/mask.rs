@@ -0,0 +1,74 @@
+//! Literal masking for `redact()`.
+//!
+//! Unlike `normalize()`, which writes the statement back from its AST, the
+//! literals are found on the token stream and replaced where they stand: the
+//! rest of the statement, whitespace, case and comments included, is copied
+//! through byte for byte. Statements need not parse, only tokenize, so that
+//! any log line can be scrubbed. Numbers, strings in all their prefixed and
+//! quoted forms and `TRUE`/`FALSE` are masked; `NULL` and the placeholders
+//! already in the statement are not values to hide, and are kept.
+
+use sqlparser::dialect::Dialect;
+use sqlparser::keywords::Keyword;
+use sqlparser::tokenizer::{Token, Tokenizer};
+
+use crate::paramstyle::token_offsets;
+use crate::{placeholder_number, placeholder_text};
+
+fn is_literal(token: &Token) -> bool {
+    match token {
+        Token::Number(..)
+        | Token::SingleQuotedString(_)
+        | Token::DoubleQuotedString(_)
+        | Token::TripleSingleQuotedString(_)
+        | Token::TripleDoubleQuotedString(_)
+        | Token::DollarQuotedString(_)
+        | Token::SingleQuotedByteStringLiteral(_)
+        | Token::DoubleQuotedByteStringLiteral(_)
+        | Token::TripleSingleQuotedByteStringLiteral(_)
+        | Token::TripleDoubleQuotedByteStringLiteral(_)
+        | Token::SingleQuotedRawStringLiteral(_)
+        | Token::DoubleQuotedRawStringLiteral(_)
+        | Token::TripleSingleQuotedRawStringLiteral(_)
+        | Token::TripleDoubleQuotedRawStringLiteral(_)
+        | Token::NationalStringLiteral(_)
+        | Token::QuoteDelimitedStringLiteral(_)
+        | Token::NationalQuoteDelimitedStringLiteral(_)
+        | Token::EscapedStringLiteral(_)
+        | Token::UnicodeStringLiteral(_)
+        | Token::HexStringLiteral(_) => true,
+        Token::Word(word) => word.quote_style.is_none() && matches!(word.keyword, Keyword::TRUE | Keyword::FALSE),
+        _ => false,
+    }
+}
+
+/// `sql` with each of its literals replaced by `mask`, numbered where it has
+/// a `{n}` (from 1, or after the highest placeholder of its style already in
+/// `sql`), and everything else as written
+pub fn redact(sql: &str, dialect: &dyn Dialect, mask: &str) -> Result<String, String> {
+    let tokens = Tokenizer::new(dialect, sql)
+        .with_unescape(false)
+        .tokenize_with_location()
+        .map_err(|e| format!("Tokenize error: {}", e))?;
+    let offsets = token_offsets(sql, &tokens, dialect);
+    let end_of = |i: usize| offsets.get(i).copied().unwrap_or(sql.len());
+
+    let mut out = String::with_capacity(sql.len());
+    let mut n = tokens
+        .iter()
+        .filter_map(|token| match token.token {
+            Token::Placeholder(ref text) => placeholder_number(mask, text),
+            _ => None,
+        })
+        .max()
+        .map_or(1, |highest| highest + 1);
+    for (i, token) in tokens.iter().enumerate() {
+        if is_literal(&token.token) {
+            out.push_str(&placeholder_text(mask, n));
+            n += 1;
+        } else {
+            out.push_str(&sql[end_of(i)..end_of(i + 1)]);
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,35 @@
+//! JSON-like values attached to a `NormalizeResult` as its `metadata` dict.
+//!
+//! Kept as plain Rust data so that results can be built, cloned and compared
+//! without holding the GIL; conversion to Python happens in the getter.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MetaValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    List(Vec<MetaValue>),
+    Map(Metadata),
+}
+
+/// Ordered key/value pairs; keys are unique
+pub type Metadata = Vec<(String, MetaValue)>;
+
+impl From<&str> for MetaValue {
+    fn from(s: &str) -> Self {
+        MetaValue::Str(s.to_string())
+    }
+}
+
+impl From<String> for MetaValue {
+    fn from(s: String) -> Self {
+        MetaValue::Str(s)
+    }
+}
+
+impl<T: Into<MetaValue>> From<Option<T>> for MetaValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(MetaValue::Null, Into::into)
+    }
+}
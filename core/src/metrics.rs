@@ -0,0 +1,234 @@
+//! Complexity metrics of a statement, for `NormalizeResult.metrics`.
+//!
+//! Alerting on complexity creep per fingerprint needs a few counts that
+//! otherwise take parsing the statement again: joins, how deeply queries
+//! nest, predicates, aggregate and window calls, set operations and whether a
+//! select list has a wildcard. They are counted on the normalized statement,
+//! so that they are the same for every statement of a fingerprint.
+
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    BinaryOperator, Expr, FromTable, ObjectNamePart, Query, SelectItem, SetExpr, Statement, TableFactor, TableWithJoins,
+    UpdateTableFromKind, Visit, Visitor,
+};
+
+/// Aggregate functions across the dialects, by uppercased name. A call with
+/// `WITHIN GROUP` or `FILTER` is an aggregate whatever its name.
+const AGGREGATES: &[&str] = &[
+    "ANY_VALUE",
+    "APPROX_COUNT_DISTINCT",
+    "ARRAY_AGG",
+    "AVG",
+    "BIT_AND",
+    "BIT_OR",
+    "BIT_XOR",
+    "BOOL_AND",
+    "BOOL_OR",
+    "CORR",
+    "COUNT",
+    "COUNT_BIG",
+    "COVAR_POP",
+    "COVAR_SAMP",
+    "EVERY",
+    "GROUP_CONCAT",
+    "JSONB_AGG",
+    "JSONB_OBJECT_AGG",
+    "JSON_AGG",
+    "JSON_ARRAYAGG",
+    "JSON_OBJECTAGG",
+    "JSON_OBJECT_AGG",
+    "LISTAGG",
+    "MAX",
+    "MEDIAN",
+    "MIN",
+    "MODE",
+    "PERCENTILE_CONT",
+    "PERCENTILE_DISC",
+    "STDDEV",
+    "STDDEV_POP",
+    "STDDEV_SAMP",
+    "STRING_AGG",
+    "SUM",
+    "VARIANCE",
+    "VAR_POP",
+    "VAR_SAMP",
+    "XMLAGG",
+];
+
+/// The metrics of a statement
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// `JOIN` clauses, nested joins included; tables listed with commas are
+    /// not counted
+    pub joins: usize,
+    /// Deepest nesting of queries in the statement's own query: 0 without
+    /// subqueries, 1 for a subquery, derived table or CTE, and so on
+    pub subquery_depth: usize,
+    /// Comparisons and the other conditions (`IS NULL`, `IN`, `BETWEEN`,
+    /// `LIKE`, `EXISTS`, …), wherever they stand
+    pub predicates: usize,
+    /// Whether a select list has `*` or `t.*`
+    pub select_star: bool,
+    /// Aggregate calls that are not window calls
+    pub aggregates: usize,
+    /// Calls with an `OVER` clause
+    pub window_functions: usize,
+    /// `UNION`, `EXCEPT` and `INTERSECT` operators
+    pub set_operations: usize,
+}
+
+/// Whether `op` compares its operands
+fn comparison(op: &BinaryOperator) -> bool {
+    matches!(
+        op,
+        BinaryOperator::Eq
+            | BinaryOperator::NotEq
+            | BinaryOperator::Lt
+            | BinaryOperator::LtEq
+            | BinaryOperator::Gt
+            | BinaryOperator::GtEq
+            | BinaryOperator::Spaceship
+            | BinaryOperator::PGRegexMatch
+            | BinaryOperator::PGRegexIMatch
+            | BinaryOperator::PGRegexNotMatch
+            | BinaryOperator::PGRegexNotIMatch
+            | BinaryOperator::PGLikeMatch
+            | BinaryOperator::PGILikeMatch
+            | BinaryOperator::PGNotLikeMatch
+            | BinaryOperator::PGNotILikeMatch
+    )
+}
+
+#[derive(Default)]
+struct Counter {
+    metrics: Metrics,
+    /// Queries open around the one visited, the statement's own left out
+    depth: usize,
+    /// Whether each open query counts towards `depth`
+    nested: Vec<bool>,
+    /// The next query visited is the statement's own
+    outermost: bool,
+}
+
+impl Counter {
+    fn joins(&mut self, tables: &[TableWithJoins]) {
+        self.metrics.joins += tables.iter().map(|table| table.joins.len()).sum::<usize>();
+    }
+
+    /// The joins, wildcards and set operations of `body`, but not of the
+    /// queries it holds, which are visited on their own
+    fn body(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                self.joins(&select.from);
+                self.metrics.select_star |= select
+                    .projection
+                    .iter()
+                    .any(|item| matches!(item, SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..)));
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.metrics.set_operations += 1;
+                self.body(left);
+                self.body(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Visitor for Counter {
+    type Break = ();
+
+    fn pre_visit_statement(&mut self, stmt: &Statement) -> ControlFlow<()> {
+        match stmt {
+            Statement::Query(_) => self.outermost = true,
+            Statement::Insert(insert) => self.outermost = insert.source.is_some(),
+            Statement::CreateTable(create) => self.outermost = create.query.is_some(),
+            Statement::CreateView { .. } => self.outermost = true,
+            Statement::Update(update) => {
+                self.joins(std::slice::from_ref(&update.table));
+                if let Some(UpdateTableFromKind::BeforeSet(from) | UpdateTableFromKind::AfterSet(from)) = &update.from {
+                    self.joins(from);
+                }
+            }
+            Statement::Delete(delete) => {
+                let (FromTable::WithFromKeyword(from) | FromTable::WithoutKeyword(from)) = &delete.from;
+                self.joins(from);
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        let nested = !std::mem::take(&mut self.outermost);
+        if nested {
+            self.depth += 1;
+            self.metrics.subquery_depth = self.metrics.subquery_depth.max(self.depth);
+        }
+        self.nested.push(nested);
+        self.body(&query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, _query: &Query) -> ControlFlow<()> {
+        if self.nested.pop() == Some(true) {
+            self.depth -= 1;
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, factor: &TableFactor) -> ControlFlow<()> {
+        if let TableFactor::NestedJoin { table_with_joins, .. } = factor {
+            self.joins(std::slice::from_ref(table_with_joins));
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<()> {
+        match expr {
+            Expr::BinaryOp { op, .. } if comparison(op) => self.metrics.predicates += 1,
+            Expr::IsNull(_)
+            | Expr::IsNotNull(_)
+            | Expr::IsTrue(_)
+            | Expr::IsNotTrue(_)
+            | Expr::IsFalse(_)
+            | Expr::IsNotFalse(_)
+            | Expr::IsUnknown(_)
+            | Expr::IsNotUnknown(_)
+            | Expr::IsDistinctFrom(..)
+            | Expr::IsNotDistinctFrom(..)
+            | Expr::InList { .. }
+            | Expr::InSubquery { .. }
+            | Expr::InUnnest { .. }
+            | Expr::Between { .. }
+            | Expr::Like { .. }
+            | Expr::ILike { .. }
+            | Expr::SimilarTo { .. }
+            | Expr::RLike { .. }
+            | Expr::AnyOp { .. }
+            | Expr::AllOp { .. }
+            | Expr::Exists { .. } => self.metrics.predicates += 1,
+            Expr::Function(function) if function.over.is_some() => self.metrics.window_functions += 1,
+            Expr::Function(function) => {
+                let aggregate = !function.within_group.is_empty()
+                    || function.filter.is_some()
+                    || matches!(function.name.0.last(), Some(ObjectNamePart::Identifier(name))
+                        if AGGREGATES.binary_search(&name.value.to_uppercase().as_str()).is_ok());
+                if aggregate {
+                    self.metrics.aggregates += 1;
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// The metrics of `stmt`
+pub fn metrics(stmt: &Statement) -> Metrics {
+    let mut counter = Counter::default();
+    let _ = stmt.visit(&mut counter);
+    counter.metrics
+}
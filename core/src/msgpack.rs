@@ -0,0 +1,131 @@
+//! Minimal MessagePack encoder, for `NormalizeResult.to_msgpack()` and
+//! `results_to_msgpack()`.
+//!
+//! Only the types results are made of are written: nil, booleans, integers,
+//! UTF-8 strings, arrays and maps, each in its most compact form, so that the
+//! output decodes with any MessagePack library.
+
+use crate::metadata::{MetaValue, Metadata};
+
+#[derive(Default)]
+pub struct Encoder {
+    pub out: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Encoder {
+        Encoder { out: Vec::new() }
+    }
+
+    pub fn nil(&mut self) {
+        self.out.push(0xc0);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.out.push(if value { 0xc3 } else { 0xc2 });
+    }
+
+    pub fn int(&mut self, value: i64) {
+        match value {
+            0..=0x7f => self.out.push(value as u8),
+            -32..=-1 => self.out.push(value as i8 as u8),
+            0x80..=0xff => self.out.extend([0xcc, value as u8]),
+            0x100..=0xffff => {
+                self.out.push(0xcd);
+                self.out.extend((value as u16).to_be_bytes());
+            }
+            0x1_0000..=0xffff_ffff => {
+                self.out.push(0xce);
+                self.out.extend((value as u32).to_be_bytes());
+            }
+            -0x80..=-33 => self.out.extend([0xd0, value as i8 as u8]),
+            -0x8000..=-0x81 => {
+                self.out.push(0xd1);
+                self.out.extend((value as i16).to_be_bytes());
+            }
+            -0x8000_0000..=-0x8001 => {
+                self.out.push(0xd2);
+                self.out.extend((value as i32).to_be_bytes());
+            }
+            _ if value > 0 => {
+                self.out.push(0xcf);
+                self.out.extend((value as u64).to_be_bytes());
+            }
+            _ => {
+                self.out.push(0xd3);
+                self.out.extend(value.to_be_bytes());
+            }
+        }
+    }
+
+    pub fn uint(&mut self, value: u64) {
+        match i64::try_from(value) {
+            Ok(value) => self.int(value),
+            Err(_) => {
+                self.out.push(0xcf);
+                self.out.extend(value.to_be_bytes());
+            }
+        }
+    }
+
+    /// Header of a value of `len` items: `fix` is the marker of the fixed
+    /// form (up to `fix_max` items), `small` of the 8-bit form if the type has
+    /// one, `large` of the 16-bit form, the 32-bit form following it
+    fn header(&mut self, len: usize, fix: u8, fix_max: usize, small: Option<u8>, large: u8) {
+        if len <= fix_max {
+            self.out.push(fix | len as u8);
+        } else if let (true, Some(small)) = (len <= 0xff, small) {
+            self.out.extend([small, len as u8]);
+        } else if len <= 0xffff {
+            self.out.push(large);
+            self.out.extend((len as u16).to_be_bytes());
+        } else {
+            self.out.push(large + 1);
+            self.out.extend((len as u32).to_be_bytes());
+        }
+    }
+
+    pub fn str(&mut self, value: &str) {
+        self.header(value.len(), 0xa0, 31, Some(0xd9), 0xda);
+        self.out.extend(value.as_bytes());
+    }
+
+    pub fn array(&mut self, len: usize) {
+        self.header(len, 0x90, 15, None, 0xdc);
+    }
+
+    pub fn map(&mut self, len: usize) {
+        self.header(len, 0x80, 15, None, 0xde);
+    }
+
+    pub fn strs<S: AsRef<str>>(&mut self, items: &[S]) {
+        self.array(items.len());
+        for item in items {
+            self.str(item.as_ref());
+        }
+    }
+
+    pub fn meta(&mut self, value: &MetaValue) {
+        match value {
+            MetaValue::Null => self.nil(),
+            MetaValue::Bool(b) => self.bool(*b),
+            MetaValue::Int(n) => self.int(*n),
+            MetaValue::Str(s) => self.str(s),
+            MetaValue::List(items) => {
+                self.array(items.len());
+                for item in items {
+                    self.meta(item);
+                }
+            }
+            MetaValue::Map(entries) => self.metadata(entries),
+        }
+    }
+
+    pub fn metadata(&mut self, entries: &Metadata) {
+        self.map(entries.len());
+        for (key, value) in entries {
+            self.str(key);
+            self.meta(value);
+        }
+    }
+}
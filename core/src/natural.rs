@@ -0,0 +1,214 @@
+//! `NATURAL` joins, for `metadata["uses_natural_join"]` and the
+//! `table_columns` option.
+//!
+//! A natural join joins on whatever columns its sides happen to share, so its
+//! meaning changes with the schema and many shops ban it; the metadata flags
+//! statements using one anywhere. Given the columns of the tables involved, a
+//! natural join is rewritten as the `USING` join it currently stands for: the
+//! columns the joined table shares with the relations before it in the same
+//! `FROM` item, in the order of those relations, as SQL defines. Joins next
+//! to a relation whose columns are not given (derived tables, unknown
+//! tables, nested joins), and those sharing no column, are left as written.
+
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    FromTable, Ident, JoinConstraint, JoinOperator, ObjectName, Query, SetExpr, Statement, TableFactor, TableWithJoins,
+    UpdateTableFromKind, Visit, VisitMut, Visitor, VisitorMut,
+};
+
+use crate::join_constraint;
+use crate::metadata::MetaValue;
+
+/// The constraint of a join that can be `NATURAL`
+fn constraint_mut(op: &mut JoinOperator) -> Option<&mut JoinConstraint> {
+    match op {
+        JoinOperator::Join(c)
+        | JoinOperator::Inner(c)
+        | JoinOperator::Left(c)
+        | JoinOperator::LeftOuter(c)
+        | JoinOperator::Right(c)
+        | JoinOperator::RightOuter(c)
+        | JoinOperator::FullOuter(c) => Some(c),
+        _ => None,
+    }
+}
+
+fn is_natural(twj: &TableWithJoins) -> bool {
+    twj.joins.iter().any(|join| matches!(join_constraint(&join.join_operator), Some(JoinConstraint::Natural)))
+}
+
+/// Apply `f` to the `FROM` items of the `SELECT`s of `body`; parenthesized
+/// queries are reached when visited
+fn for_each_item(body: &mut SetExpr, f: &mut impl FnMut(&mut TableWithJoins)) {
+    match body {
+        SetExpr::Select(select) => select.from.iter_mut().for_each(f),
+        SetExpr::SetOperation { left, right, .. } => {
+            for_each_item(left, f);
+            for_each_item(right, f);
+        }
+        _ => {}
+    }
+}
+
+#[derive(Default)]
+struct Finder {
+    found: bool,
+}
+
+impl Finder {
+    fn check(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Select(select) => self.found |= select.from.iter().any(is_natural),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.check(left);
+                self.check(right);
+            }
+            _ => {}
+        }
+    }
+
+    fn done(&self) -> ControlFlow<()> {
+        if self.found {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}
+
+impl Visitor for Finder {
+    type Break = ();
+
+    fn pre_visit_statement(&mut self, stmt: &Statement) -> ControlFlow<()> {
+        self.found |= statement_items(stmt).into_iter().any(is_natural);
+        self.done()
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        self.check(&query.body);
+        self.done()
+    }
+
+    fn pre_visit_table_factor(&mut self, factor: &TableFactor) -> ControlFlow<()> {
+        if let TableFactor::NestedJoin { table_with_joins, .. } = factor {
+            self.found |= is_natural(table_with_joins);
+        }
+        self.done()
+    }
+}
+
+/// The `FROM` items of an `UPDATE` or `DELETE`
+fn statement_items(stmt: &Statement) -> Vec<&TableWithJoins> {
+    match stmt {
+        Statement::Update(update) => {
+            let mut items = vec![&update.table];
+            if let Some(UpdateTableFromKind::BeforeSet(from) | UpdateTableFromKind::AfterSet(from)) = &update.from {
+                items.extend(from);
+            }
+            items
+        }
+        Statement::Delete(delete) => match &delete.from {
+            FromTable::WithFromKeyword(from) | FromTable::WithoutKeyword(from) => from.iter().collect(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// `metadata["uses_natural_join"]`, set only when `stmt` has a `NATURAL`
+/// join
+pub fn uses_natural_join(stmt: &Statement) -> Option<(String, MetaValue)> {
+    let mut finder = Finder::default();
+    let _ = stmt.visit(&mut finder);
+    finder.found.then(|| ("uses_natural_join".to_string(), MetaValue::Bool(true)))
+}
+
+/// The given columns of `factor`'s table, by its whole name or its name
+/// without schema, lowercased
+fn columns<'a>(factor: &TableFactor, tables: &'a BTreeMap<String, Vec<String>>) -> Option<&'a [String]> {
+    let TableFactor::Table { name, args: None, .. } = factor else {
+        return None;
+    };
+    let whole = name.to_string().to_lowercase();
+    let bare = name.0.last()?.as_ident()?.value.to_lowercase();
+    tables.get(&whole).or_else(|| tables.get(&bare)).map(Vec::as_slice)
+}
+
+/// Rewrite the natural joins of `twj` whose sides have known columns
+fn expand_item(twj: &mut TableWithJoins, tables: &BTreeMap<String, Vec<String>>) {
+    // The columns of the relations joined so far, `None` once one is unknown
+    let mut left: Option<Vec<&str>> = columns(&twj.relation, tables).map(|cols| cols.iter().map(String::as_str).collect());
+    for join in &mut twj.joins {
+        let right = columns(&join.relation, tables);
+        if let (Some(known), Some(right), Some(constraint)) = (&left, right, constraint_mut(&mut join.join_operator)) {
+            if *constraint == JoinConstraint::Natural {
+                let shared: Vec<ObjectName> = known
+                    .iter()
+                    .filter(|column| right.iter().any(|other| other.eq_ignore_ascii_case(column)))
+                    .map(|column| ObjectName::from(vec![Ident::new(*column)]))
+                    .collect();
+                if !shared.is_empty() {
+                    *constraint = JoinConstraint::Using(shared);
+                }
+            }
+        }
+        left = match (left, right) {
+            (Some(mut known), Some(right)) => {
+                for column in right {
+                    if !known.iter().any(|other| other.eq_ignore_ascii_case(column)) {
+                        known.push(column);
+                    }
+                }
+                Some(known)
+            }
+            _ => None,
+        };
+    }
+}
+
+struct Expand<'a> {
+    tables: &'a BTreeMap<String, Vec<String>>,
+}
+
+impl VisitorMut for Expand<'_> {
+    type Break = ();
+
+    fn pre_visit_statement(&mut self, stmt: &mut Statement) -> ControlFlow<()> {
+        match stmt {
+            Statement::Update(update) => {
+                expand_item(&mut update.table, self.tables);
+                if let Some(UpdateTableFromKind::BeforeSet(from) | UpdateTableFromKind::AfterSet(from)) = &mut update.from {
+                    from.iter_mut().for_each(|twj| expand_item(twj, self.tables));
+                }
+            }
+            Statement::Delete(delete) => match &mut delete.from {
+                FromTable::WithFromKeyword(from) | FromTable::WithoutKeyword(from) => {
+                    from.iter_mut().for_each(|twj| expand_item(twj, self.tables));
+                }
+            },
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        for_each_item(&mut query.body, &mut |twj| expand_item(twj, self.tables));
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, factor: &mut TableFactor) -> ControlFlow<()> {
+        if let TableFactor::NestedJoin { table_with_joins, .. } = factor {
+            expand_item(table_with_joins, self.tables);
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Rewrite the natural joins of `stmt` as `USING` joins where `tables`, the
+/// columns of each table by lowercased name, tells which columns they share
+pub fn expand(stmt: &mut Statement, tables: &BTreeMap<String, Vec<String>>) {
+    if !tables.is_empty() {
+        let _ = VisitMut::visit(stmt, &mut Expand { tables });
+    }
+}
@@ -0,0 +1,33 @@
+//! Byte offsets of the literals replaced, for `NormalizeResult.param_spans`.
+//!
+//! The parser gives each literal a line and column span in the text it
+//! parsed, which is the original SQL but for templates stripped, a `SELECT`
+//! split from its `INTO OUTFILE` clause or a truncated statement closed. A
+//! span is only given back for literals where that text still agrees with
+//! the original, up to the literal's end, so that each offset pair slices the
+//! literal out of the original as written.
+
+use sqlparser::tokenizer::Span;
+
+use crate::paramstyle::Cursor;
+
+/// The `(start, end)` byte offsets in `sql` of each of `spans`, taken in
+/// `text`, the text parsed from `sql`; `None` for empty spans and those
+/// where `text` no longer agrees with `sql`
+pub fn byte_spans(sql: &str, text: &str, spans: &[Span]) -> Vec<Option<(usize, usize)>> {
+    // The cursor only goes forward, so spans are taken in the order of the text
+    let mut order: Vec<usize> = (0..spans.len()).filter(|&i| spans[i] != Span::empty()).collect();
+    order.sort_by_key(|&i| (spans[i].start.line, spans[i].start.column));
+    // Where `text` and `sql` part
+    let agreed = sql.bytes().zip(text.bytes()).take_while(|(a, b)| a == b).count();
+    let mut offsets = vec![None; spans.len()];
+    let mut cursor = Cursor::new(text);
+    for i in order {
+        let start = cursor.seek(spans[i].start);
+        let end = cursor.clone().seek(spans[i].end);
+        if start < end && end <= agreed {
+            offsets[i] = Some((start, end));
+        }
+    }
+    offsets
+}
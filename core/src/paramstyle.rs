@@ -0,0 +1,358 @@
+//! Conversion between DB-API (PEP 249) placeholder styles, as used by
+//! `convert_paramstyle()`.
+//!
+//! Placeholders are found on the token stream, so string literals, quoted
+//! identifiers and comments are never touched; the rest of the statement is
+//! copied through byte for byte.
+
+use sqlparser::dialect::Dialect;
+use sqlparser::tokenizer::{Location, Token, TokenWithSpan, Tokenizer, Whitespace};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// `?`
+    Qmark,
+    /// `:1`
+    Numeric,
+    /// `:name`
+    Named,
+    /// `%s`
+    Format,
+    /// `%(name)s`
+    Pyformat,
+}
+
+impl Style {
+    pub fn parse(style: &str) -> Result<Style, String> {
+        match style {
+            "qmark" => Ok(Style::Qmark),
+            "numeric" => Ok(Style::Numeric),
+            "named" => Ok(Style::Named),
+            "format" => Ok(Style::Format),
+            "pyformat" => Ok(Style::Pyformat),
+            _ => Err(format!("Unsupported paramstyle: {}", style)),
+        }
+    }
+
+    /// Styles whose placeholders start with `%`, where a literal `%` is written `%%`
+    pub fn is_percent(self) -> bool {
+        matches!(self, Style::Format | Style::Pyformat)
+    }
+}
+
+/// Which source parameter a placeholder refers to: a 1-based position for
+/// qmark, format and numeric placeholders, a name for named and pyformat ones
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParamKey {
+    Position(usize),
+    Name(String),
+}
+
+/// Walks a statement by the line and column numbers of the tokenizer
+#[derive(Clone)]
+pub struct Cursor<'s> {
+    sql: &'s str,
+    at: usize,
+    here: Location,
+}
+
+impl Cursor<'_> {
+    pub fn new(sql: &str) -> Cursor<'_> {
+        Cursor { sql, at: 0, here: Location::new(1, 1) }
+    }
+
+    /// Advance to `target`, or to the end; the byte offset reached
+    pub fn seek(&mut self, target: Location) -> usize {
+        while (self.here.line, self.here.column) < (target.line, target.column) {
+            let Some(c) = self.sql[self.at..].chars().next() else {
+                break;
+            };
+            self.at += c.len_utf8();
+            match c {
+                '\n' => self.here = Location::new(self.here.line + 1, 1),
+                _ => self.here.column += 1,
+            }
+        }
+        self.at
+    }
+}
+
+/// Byte offset of the `*/` closing the block comment opened at `start`
+fn comment_end(sql: &str, start: usize, nested: bool) -> usize {
+    let bytes = sql.as_bytes();
+    let (mut at, mut depth) = (start + 2, 1);
+    while at + 1 < bytes.len() {
+        match &bytes[at..at + 2] {
+            b"*/" => {
+                depth -= 1;
+                if depth == 0 {
+                    return at;
+                }
+                at += 2;
+            }
+            b"/*" if nested => {
+                depth += 1;
+                at += 2;
+            }
+            _ => at += 1,
+        }
+    }
+    sql.len()
+}
+
+/// The bytes of a MySQL `/*! ... */` hint, from its `/*!` to past its `*/`
+pub type Hint = (usize, usize);
+
+/// Byte offset of each token's start in `sql`, and the hint it is in if any.
+///
+/// The tokenizer hands the SQL of hints through as tokens, located as if the
+/// hint's text started where the comment does; they are located in the
+/// comment's text instead. The `/*!` opening goes with the token before the
+/// hint and the `*/` with the token after it, so that tokens inside are
+/// sliced as written.
+pub fn token_spans(sql: &str, tokens: &[TokenWithSpan], dialect: &dyn Dialect) -> (Vec<usize>, Vec<Option<Hint>>) {
+    let mut offsets = Vec::with_capacity(tokens.len());
+    let mut hints = Vec::with_capacity(tokens.len());
+    let mut cursor = Cursor::new(sql);
+    // The hint being read: a cursor over its text in the tokenizer's
+    // locations, and the offset of its `*/`
+    let mut hint: Option<(Cursor, usize, Hint)> = None;
+    for token in tokens {
+        let target = token.span.start;
+        let mut closed = None;
+        if let Some((ref mut inner, end, range)) = hint {
+            let at = inner.seek(target);
+            if at < end {
+                offsets.push(at);
+                hints.push(Some(range));
+                continue;
+            }
+            closed = Some(end);
+            hint = None;
+        }
+        let at = cursor.seek(target);
+        let is_comment = matches!(token.token, Token::Whitespace(Whitespace::MultiLineComment(_)));
+        if !is_comment && sql[at..].starts_with("/*!") {
+            let digits = sql[at + 3..].bytes().take_while(u8::is_ascii_digit).count();
+            let end = comment_end(sql, at, dialect.supports_nested_comments());
+            let range = (at, (end + 2).min(sql.len()));
+            let text = at + 3 + digits;
+            hint = Some((Cursor { sql, at: text, here: target }, end, range));
+            hints.push(Some(range));
+            offsets.push(if offsets.is_empty() { at } else { closed.unwrap_or(text) });
+            continue;
+        }
+        hints.push(None);
+        offsets.push(closed.unwrap_or(at));
+    }
+    (offsets, hints)
+}
+
+/// Byte offset of each token's start in `sql`, as located by `token_spans()`
+pub fn token_offsets(sql: &str, tokens: &[TokenWithSpan], dialect: &dyn Dialect) -> Vec<usize> {
+    token_spans(sql, tokens, dialect).0
+}
+
+/// A placeholder of the source style starting at token `i`:
+/// the key it refers to and the number of tokens it spans
+fn match_placeholder(
+    tokens: &[TokenWithSpan],
+    i: usize,
+    style: Style,
+    positional: &mut usize,
+    bracket_depth: usize,
+) -> Option<(ParamKey, usize)> {
+    let token = |n: usize| tokens.get(i + n).map(|t| &t.token);
+    let mut next_position = || {
+        *positional += 1;
+        ParamKey::Position(*positional)
+    };
+    match (style, token(0)?) {
+        (Style::Qmark, Token::Placeholder(p)) if p == "?" => Some((next_position(), 1)),
+        // Dialects with `?` JSON operators tokenize it as an operator
+        (Style::Qmark, Token::Question) => Some((next_position(), 1)),
+        // `a[1:2]` is an array slice, not a placeholder
+        (Style::Numeric | Style::Named, Token::Colon) if bracket_depth == 0 => {
+            match (style, token(1)?) {
+                (Style::Numeric, Token::Number(n, _)) => {
+                    Some((ParamKey::Position(n.parse().ok()?), 2))
+                }
+                (Style::Named, Token::Word(w)) if w.quote_style.is_none() => {
+                    Some((ParamKey::Name(w.value.clone()), 2))
+                }
+                _ => None,
+            }
+        }
+        (Style::Format, Token::Mod) => match token(1)? {
+            Token::Word(w) if w.quote_style.is_none() && w.value == "s" => {
+                Some((next_position(), 2))
+            }
+            _ => None,
+        },
+        (Style::Pyformat, Token::Mod) => match (token(1)?, token(2)?, token(3)?, token(4)?) {
+            (Token::LParen, Token::Word(name), Token::RParen, Token::Word(s))
+                if name.quote_style.is_none() && s.quote_style.is_none() && s.value == "s" =>
+            {
+                Some((ParamKey::Name(name.value.clone()), 5))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Rewrite the placeholders of `sql` from one style to another.
+///
+/// Also returns, for each parameter of the converted statement, the source
+/// parameter it binds: one entry per placeholder for the positional target
+/// styles (qmark, format), one per distinct parameter in order of first use
+/// for the others. Positional parameters become `p1`, `p2`, … in named styles.
+pub fn convert(
+    sql: &str,
+    dialect: &dyn Dialect,
+    from: Style,
+    to: Style,
+) -> Result<(String, Vec<ParamKey>), String> {
+    let tokens = Tokenizer::new(dialect, sql)
+        .with_unescape(false)
+        .tokenize_with_location()
+        .map_err(|e| format!("Tokenize error: {}", e))?;
+    let offsets = token_offsets(sql, &tokens, dialect);
+    let end_of = |i: usize| offsets.get(i).copied().unwrap_or(sql.len());
+
+    let mut out = String::with_capacity(sql.len());
+    let mut order: Vec<ParamKey> = Vec::new();
+    let mut positional = 0;
+    let mut bracket_depth: usize = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        if let Some((key, len)) = match_placeholder(&tokens, i, from, &mut positional, bracket_depth) {
+            let number = match order.iter().position(|k| *k == key) {
+                Some(idx) if !matches!(to, Style::Qmark | Style::Format) => idx + 1,
+                _ => {
+                    order.push(key.clone());
+                    order.len()
+                }
+            };
+            let name = match key {
+                ParamKey::Name(name) => name,
+                ParamKey::Position(n) => format!("p{}", n),
+            };
+            match to {
+                Style::Qmark => out.push('?'),
+                Style::Numeric => out.push_str(&format!(":{}", number)),
+                Style::Named => out.push_str(&format!(":{}", name)),
+                Style::Format => out.push_str("%s"),
+                Style::Pyformat => out.push_str(&format!("%({})s", name)),
+            }
+            i += len;
+            continue;
+        }
+
+        let text = &sql[end_of(i)..end_of(i + 1)];
+        match tokens[i].token {
+            Token::LBracket => bracket_depth += 1,
+            Token::RBracket => bracket_depth = bracket_depth.saturating_sub(1),
+            _ => {}
+        }
+        match tokens[i].token {
+            // `%%` is an escaped `%` in the percent styles
+            Token::Mod if from.is_percent() && !to.is_percent() => {
+                out.push('%');
+                if matches!(tokens.get(i + 1).map(|t| &t.token), Some(Token::Mod)) {
+                    i += 1;
+                }
+            }
+            Token::Mod if !from.is_percent() && to.is_percent() => out.push_str("%%"),
+            _ => out.push_str(text),
+        }
+        i += 1;
+    }
+    Ok((out, order))
+}
+
+/// Rewrite the `from`-style placeholders of `sql` to `?` and list every `?` of
+/// the result in order: `None` for those `sql` already had, the source
+/// parameter for the rewritten ones.
+pub fn bind_order(
+    sql: &str,
+    dialect: &dyn Dialect,
+    from: Style,
+) -> Result<(String, Vec<Option<ParamKey>>), String> {
+    let tokens = Tokenizer::new(dialect, sql)
+        .with_unescape(false)
+        .tokenize_with_location()
+        .map_err(|e| format!("Tokenize error: {}", e))?;
+    let offsets = token_offsets(sql, &tokens, dialect);
+    let end_of = |i: usize| offsets.get(i).copied().unwrap_or(sql.len());
+
+    let mut out = String::with_capacity(sql.len());
+    let mut order = Vec::new();
+    let mut positional = 0;
+    let mut bracket_depth: usize = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        if let Some((key, len)) = match_placeholder(&tokens, i, from, &mut positional, bracket_depth) {
+            out.push('?');
+            order.push(Some(key));
+            i += len;
+            continue;
+        }
+        match tokens[i].token {
+            Token::LBracket => bracket_depth += 1,
+            Token::RBracket => bracket_depth = bracket_depth.saturating_sub(1),
+            Token::Placeholder(ref p) if p == "?" => order.push(None),
+            Token::Question => order.push(None),
+            _ => {}
+        }
+        out.push_str(&sql[end_of(i)..end_of(i + 1)]);
+        i += 1;
+    }
+    Ok((out, order))
+}
+
+/// Copy `sql` through token by token, replacing the ones `replace` returns
+/// text for
+fn map_tokens(
+    sql: &str,
+    dialect: &dyn Dialect,
+    mut replace: impl FnMut(&Token) -> Option<String>,
+) -> Result<String, String> {
+    let tokens = Tokenizer::new(dialect, sql)
+        .with_unescape(false)
+        .tokenize_with_location()
+        .map_err(|e| format!("Tokenize error: {}", e))?;
+    let offsets = token_offsets(sql, &tokens, dialect);
+    let end_of = |i: usize| offsets.get(i).copied().unwrap_or(sql.len());
+    let mut out = String::with_capacity(sql.len());
+    for (i, token) in tokens.iter().enumerate() {
+        match replace(&token.token) {
+            Some(text) => out.push_str(&text),
+            None => out.push_str(&sql[end_of(i)..end_of(i + 1)]),
+        }
+    }
+    Ok(out)
+}
+
+/// Rewrite the `?` that `dialect` tokenizes as an operator (PostgreSQL's JSON
+/// operators) as `$_1`, `$_2`, …, so that a normalized statement parses again;
+/// `restore_qmarks` undoes it
+pub fn dollar_qmarks(sql: &str, dialect: &dyn Dialect) -> Result<String, String> {
+    let mut count = 0;
+    map_tokens(sql, dialect, |token| match token {
+        Token::Question => {
+            count += 1;
+            Some(format!("$_{}", count))
+        }
+        _ => None,
+    })
+}
+
+pub fn restore_qmarks(sql: &str, dialect: &dyn Dialect) -> Result<String, String> {
+    map_tokens(sql, dialect, |token| match token {
+        Token::Placeholder(p) if p.strip_prefix("$_").is_some_and(|n| n.parse::<usize>().is_ok()) => {
+            Some("?".to_string())
+        }
+        _ => None,
+    })
+}
@@ -0,0 +1,435 @@
+//! pt-fingerprint's fingerprints, for `compat="percona"`.
+//!
+//! pt-query-digest and pt-fingerprint fingerprint the text of a query with a
+//! series of regular expressions, not a parse tree, and stored digests depend
+//! on each of their quirks: numbers embedded in names (`tbl1` → `tbl?`),
+//! every `+` and `-`, `NULL` and booleans become `?`, the whitespace runs
+//! collapse to one space, the text is lowercased, `IN` and `VALUES` lists of
+//! placeholders collapse to `(?+)`, a multi-row `INSERT` is cut to its first
+//! row, repeated `UNION` branches to a `/*repeat union*/` comment, and `ASC`
+//! is dropped after `ORDER BY`. The steps are reproduced here one by one, in
+//! their order, with the defaults of the tools (no `--match-md5-checksums`, no
+//! `--match-embedded-numbers`). Each step only removes or replaces ASCII, so
+//! the text stays UTF-8 throughout; as in Perl without `use utf8`, only ASCII
+//! letters are lowercased and are word characters.
+
+use md5::{Digest, Md5};
+
+/// Perl's `\s`
+fn is_space(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c)
+}
+
+/// Perl's `\w`
+fn is_word(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+/// Perl's `\b` before the word character at `i`
+fn word_start(s: &[u8], i: usize) -> bool {
+    i == 0 || !is_word(s[i - 1])
+}
+
+fn starts_with_ci(s: &[u8], i: usize, word: &[u8]) -> bool {
+    s.len() >= i + word.len() && s[i..i + word.len()].eq_ignore_ascii_case(word)
+}
+
+/// The end of the run of `\s` starting at `i`
+fn skip_spaces(s: &[u8], mut i: usize) -> usize {
+    while i < s.len() && is_space(s[i]) {
+        i += 1;
+    }
+    i
+}
+
+/// Replace the matches of `find`, tried at each position in turn, with what
+/// it returns. `find(s, i)` gives the end of the match at `i` and its
+/// replacement, as a global `s///g` does.
+fn substitute(s: &[u8], mut find: impl FnMut(&[u8], usize) -> Option<(usize, Vec<u8>)>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(s.len());
+    let mut i = 0;
+    while i < s.len() {
+        match find(s, i) {
+            Some((end, replacement)) => {
+                out.extend(replacement);
+                i = end;
+            }
+            None => {
+                out.push(s[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// `m#/\*\w+\.\w+:[0-9]/[0-9]\*/#`, the comment Percona tools tag their own
+/// queries with
+fn toolkit_comment(s: &[u8]) -> bool {
+    let words = |mut i: usize| {
+        let start = i;
+        while i < s.len() && is_word(s[i]) {
+            i += 1;
+        }
+        (i > start).then_some(i)
+    };
+    (0..s.len()).any(|i| {
+        let Some(i) = s[i..].starts_with(b"/*").then(|| words(i + 2)).flatten() else {
+            return false;
+        };
+        let Some(i) = (s.get(i) == Some(&b'.')).then(|| words(i + 1)).flatten() else {
+            return false;
+        };
+        s.len() >= i + 6
+            && s[i] == b':'
+            && s[i + 1].is_ascii_digit()
+            && s[i + 2] == b'/'
+            && s[i + 3].is_ascii_digit()
+            && &s[i + 4..i + 6] == b"*/"
+    })
+}
+
+/// `m/\A\s*(call\s+\S+)\(/i`: `call` and the procedure name, up to the last
+/// `(` of the name as Perl backtracks to it
+fn call(s: &[u8]) -> Option<String> {
+    let start = skip_spaces(s, 0);
+    if !starts_with_ci(s, start, b"call") {
+        return None;
+    }
+    let name = skip_spaces(s, start + 4);
+    if name == start + 4 {
+        return None;
+    }
+    let mut end = name;
+    while end < s.len() && !is_space(s[end]) {
+        end += 1;
+    }
+    let paren = (name + 1..end).rev().find(|&i| s[i] == b'(')?;
+    Some(String::from_utf8_lossy(&s[start..paren]).to_ascii_lowercase())
+}
+
+/// `m/\A((?:INSERT|REPLACE)(?: IGNORE)?\s+INTO.+?VALUES\s*\(.*?\))\s*,\s*\(/is`:
+/// a multi-row `INSERT` up to the end of its first row
+fn first_row(s: &[u8]) -> Option<usize> {
+    let mut i = if starts_with_ci(s, 0, b"INSERT") {
+        6
+    } else if starts_with_ci(s, 0, b"REPLACE") {
+        7
+    } else {
+        return None;
+    };
+    if starts_with_ci(s, i, b" IGNORE") {
+        i += 7;
+    }
+    let into = skip_spaces(s, i);
+    if into == i || !starts_with_ci(s, into, b"INTO") {
+        return None;
+    }
+    for values in into + 5..s.len() {
+        if !starts_with_ci(s, values, b"VALUES") {
+            continue;
+        }
+        let open = skip_spaces(s, values + 6);
+        if s.get(open) != Some(&b'(') {
+            continue;
+        }
+        for close in open + 1..s.len() {
+            if s[close] != b')' {
+                continue;
+            }
+            let comma = skip_spaces(s, close + 1);
+            if s.get(comma) == Some(&b',') && s.get(skip_spaces(s, comma + 1)) == Some(&b'(') {
+                return Some(close + 1);
+            }
+        }
+    }
+    None
+}
+
+/// `s#/\*[^!].*?\*/##sg` and `s/(?:--|#)[^'"\r\n]*(?=[\r\n]|\Z)//g`
+fn strip_comments(s: &[u8]) -> Vec<u8> {
+    let s = substitute(s, |s, i| {
+        if !s[i..].starts_with(b"/*") || s.get(i + 2).is_none_or(|&b| b == b'!') {
+            return None;
+        }
+        let end = (i + 3..s.len().saturating_sub(1)).find(|&j| &s[j..j + 2] == b"*/")?;
+        Some((end + 2, Vec::new()))
+    });
+    substitute(&s, |s, i| {
+        let mut j = if s[i..].starts_with(b"--") {
+            i + 2
+        } else if s[i] == b'#' {
+            i + 1
+        } else {
+            return None;
+        };
+        while j < s.len() && !matches!(s[j], b'\'' | b'"' | b'\r' | b'\n') {
+            j += 1;
+        }
+        let at_end = j == s.len() || (j + 1 == s.len() && s[j] == b'\n');
+        (at_end || matches!(s[j], b'\r' | b'\n')).then(|| (j, Vec::new()))
+    })
+}
+
+/// `s/([^\\])(\\Q)/$1/sg`: an escaped quote after anything but a backslash
+fn drop_escaped_quotes(s: &[u8], quote: u8) -> Vec<u8> {
+    substitute(s, |s, i| {
+        (s[i] != b'\\' && s.get(i + 1) == Some(&b'\\') && s.get(i + 2) == Some(&quote)).then(|| (i + 3, vec![s[i]]))
+    })
+}
+
+/// `s/$seq//sg`
+fn drop_all(s: &[u8], seq: &[u8]) -> Vec<u8> {
+    substitute(s, |s, i| s[i..].starts_with(seq).then(|| (i + seq.len(), Vec::new())))
+}
+
+/// `s/([^\\])(Q.*?[^\\]?Q)/$1?/sg`: quoted strings after anything but a
+/// backslash, the optional `[^\\]` taking a quote where it can
+fn replace_strings(s: &[u8], quote: u8) -> Vec<u8> {
+    substitute(s, |s, i| {
+        if s[i] == b'\\' || s.get(i + 1) != Some(&quote) {
+            return None;
+        }
+        for body in i + 2..s.len() {
+            if s[body] != b'\\' && s.get(body + 1) == Some(&quote) {
+                return Some((body + 2, vec![s[i], b'?']));
+            }
+            if s[body] == quote {
+                return Some((body + 1, vec![s[i], b'?']));
+            }
+        }
+        None
+    })
+}
+
+/// `s/\bfalse\b|\btrue\b/?/isg`
+fn replace_booleans(s: &[u8]) -> Vec<u8> {
+    substitute(s, |s, i| {
+        if !word_start(s, i) {
+            return None;
+        }
+        let len = [b"false".as_slice(), b"true"].into_iter().find(|word| starts_with_ci(s, i, word))?.len();
+        s.get(i + len).is_none_or(|&b| !is_word(b)).then(|| (i + len, vec![b'?']))
+    })
+}
+
+/// `s/[0-9+-][0-9a-f.xb+-]*/?/g`, then `s/[xb.+-]\?/?/g`
+fn replace_numbers(s: &[u8]) -> Vec<u8> {
+    let s = substitute(s, |s, i| {
+        if !(s[i].is_ascii_digit() || matches!(s[i], b'+' | b'-')) {
+            return None;
+        }
+        let mut end = i + 1;
+        while end < s.len() && (s[end].is_ascii_digit() || matches!(s[end], b'a'..=b'f' | b'.' | b'x' | b'+' | b'-')) {
+            end += 1;
+        }
+        Some((end, vec![b'?']))
+    });
+    substitute(&s, |s, i| (matches!(s[i], b'x' | b'b' | b'.' | b'+' | b'-') && s.get(i + 1) == Some(&b'?')).then(|| (i + 2, vec![b'?'])))
+}
+
+/// `s/\A\s+//`, `chomp` and `tr[ \n\t\r\f][ ]s`
+fn collapse_whitespace(s: &[u8]) -> Vec<u8> {
+    let mut s = &s[skip_spaces(s, 0)..];
+    if let Some(rest) = s.strip_suffix(b"\n") {
+        s = rest;
+    }
+    let mut out = Vec::with_capacity(s.len());
+    for &b in s {
+        if matches!(b, b' ' | b'\n' | b'\t' | b'\r' | 0x0c) {
+            if out.last() != Some(&b' ') {
+                out.push(b' ');
+            }
+        } else {
+            out.push(b);
+        }
+    }
+    out
+}
+
+/// `s/\bnull\b/?/g`
+fn replace_nulls(s: &[u8]) -> Vec<u8> {
+    substitute(s, |s, i| {
+        (word_start(s, i) && s[i..].starts_with(b"null") && s.get(i + 4).is_none_or(|&b| !is_word(b))).then(|| (i + 4, vec![b'?']))
+    })
+}
+
+/// `s/\b(in|values?)(?:[\s,]*\([\s?,]*\))+/$1(?+)/gx`
+fn collapse_lists(s: &[u8]) -> Vec<u8> {
+    substitute(s, |s, i| {
+        if !word_start(s, i) {
+            return None;
+        }
+        let keyword = if s[i..].starts_with(b"in") {
+            2
+        } else if s[i..].starts_with(b"values") {
+            6
+        } else if s[i..].starts_with(b"value") {
+            5
+        } else {
+            return None;
+        };
+        let mut end = None;
+        let mut j = i + keyword;
+        loop {
+            while j < s.len() && (is_space(s[j]) || s[j] == b',') {
+                j += 1;
+            }
+            if s.get(j) != Some(&b'(') {
+                break;
+            }
+            j += 1;
+            while j < s.len() && (is_space(s[j]) || matches!(s[j], b'?' | b',')) {
+                j += 1;
+            }
+            if s.get(j) != Some(&b')') {
+                break;
+            }
+            j += 1;
+            end = Some(j);
+        }
+        let mut replacement = s[i..i + keyword].to_vec();
+        replacement.extend(b"(?+)");
+        end.map(|end| (end, replacement))
+    })
+}
+
+/// `s/\b(select\s.*?)(?:(\sunion(?:\sall)?)\s\1)+/$1 \/*repeat$2*\//xg`: a
+/// `SELECT` repeated as the branches of a `UNION`
+fn collapse_unions(s: &[u8]) -> Vec<u8> {
+    substitute(s, |s, i| {
+        if !word_start(s, i) || !s[i..].starts_with(b"select") || s.get(i + 6).is_none_or(|&b| !is_space(b)) {
+            return None;
+        }
+        // `.` stops at a line break
+        let line_end = (i..s.len()).find(|&j| s[j] == b'\n').unwrap_or(s.len());
+        // `\sunion(?:\sall)?\s` at `j`, then `branch` again: the end of the
+        // union keywords and of the repeated branch
+        let repeat = |branch: &[u8], j: usize| -> Option<(usize, usize)> {
+            if s.get(j).is_none_or(|&b| !is_space(b)) || !s[j + 1..].starts_with(b"union") {
+                return None;
+            }
+            let union = j + 6;
+            let all = (s.get(union).is_some_and(|&b| is_space(b)) && s[union + 1..].starts_with(b"all")).then_some(union + 4);
+            all.into_iter().chain([union]).find_map(|keywords| {
+                (s.get(keywords).is_some_and(|&b| is_space(b)) && s[keywords + 1..].starts_with(branch))
+                    .then_some((keywords, keywords + 1 + branch.len()))
+            })
+        };
+        for branch_end in i + 7..=line_end {
+            let branch = &s[i..branch_end];
+            let Some((mut union_end, mut end)) = repeat(branch, branch_end) else {
+                continue;
+            };
+            let mut union_start = branch_end;
+            while let Some((next_union_end, next_end)) = repeat(branch, end) {
+                (union_start, union_end, end) = (end, next_union_end, next_end);
+            }
+            let mut replacement = branch.to_vec();
+            replacement.extend(b" /*repeat");
+            replacement.extend(&s[union_start..union_end]);
+            replacement.extend(b"*/");
+            return Some((end, replacement));
+        }
+        None
+    })
+}
+
+/// `s/\blimit \?(?:, ?\?| offset \?)?/limit ?/`, on the first `LIMIT` only
+fn collapse_limit(s: &[u8]) -> Vec<u8> {
+    let mut done = false;
+    substitute(s, |s, i| {
+        if done || !word_start(s, i) || !s[i..].starts_with(b"limit ?") {
+            return None;
+        }
+        let mut end = i + 7;
+        if s[end..].starts_with(b", ?") {
+            end += 3;
+        } else if s[end..].starts_with(b",?") {
+            end += 2;
+        } else if s[end..].starts_with(b" offset ?") {
+            end += 9;
+        }
+        done = true;
+        Some((end, b"limit ?".to_vec()))
+    })
+}
+
+/// `if (m/\bORDER BY /gi) { 1 while s/\G(.+?)\s+ASC/$1/gi && pos }`: drop
+/// every `\s+asc` after the first `ORDER BY`, as long as they follow each
+/// other, even where it starts a longer word
+fn strip_asc(s: &[u8]) -> Vec<u8> {
+    let Some(mut pos) = (0..s.len()).find(|&i| word_start(s, i) && starts_with_ci(s, i, b"order by ")).map(|i| i + 9) else {
+        return s.to_vec();
+    };
+    let mut out = s[..pos].to_vec();
+    loop {
+        // `.` stops at a line break
+        let found = (pos + 1..s.len()).take_while(|&k| s[k - 1] != b'\n').find_map(|k| {
+            if !is_space(s[k]) {
+                return None;
+            }
+            let asc = skip_spaces(s, k);
+            starts_with_ci(s, asc, b"asc").then_some((k, asc + 3))
+        });
+        let Some((kept, end)) = found else {
+            break;
+        };
+        out.extend(&s[pos..kept]);
+        pos = end;
+    }
+    out.extend(&s[pos..]);
+    out
+}
+
+/// The fingerprint pt-fingerprint gives `query`
+pub fn fingerprint(query: &str) -> String {
+    let s = query.as_bytes();
+    if s.starts_with(b"SELECT /*!40001 SQL_NO_CACHE */ * FROM `") {
+        return "mysqldump".to_string();
+    }
+    if toolkit_comment(s) {
+        return "percona-toolkit".to_string();
+    }
+    if s.starts_with(b"administrator command: ") {
+        return query.to_string();
+    }
+    if let Some(call) = call(s) {
+        return call;
+    }
+    let s = match first_row(s) {
+        Some(end) => &s[..end],
+        None => s,
+    };
+    let s = strip_comments(s);
+    // `s/\Ause \S+\Z/use ?/i` ends it
+    let line = s.strip_suffix(b"\n").unwrap_or(&s);
+    if starts_with_ci(line, 0, b"use ") && line.len() > 4 && !line[4..].iter().any(|&b| is_space(b)) {
+        let mut out = b"use ?".to_vec();
+        out.extend(&s[line.len()..]);
+        return String::from_utf8_lossy(&out).into_owned();
+    }
+    let s = drop_escaped_quotes(&s, b'\'');
+    let s = drop_escaped_quotes(&s, b'"');
+    let s = drop_all(&s, b"\\\\");
+    let s = drop_all(&s, b"\\'");
+    let s = drop_all(&s, b"\\\"");
+    let s = replace_strings(&s, b'"');
+    let s = replace_strings(&s, b'\'');
+    let s = replace_booleans(&s);
+    let s = replace_numbers(&s);
+    let mut s = collapse_whitespace(&s);
+    s.make_ascii_lowercase();
+    let s = replace_nulls(&s);
+    let s = collapse_lists(&s);
+    let s = collapse_unions(&s);
+    let s = collapse_limit(&s);
+    let s = strip_asc(&s);
+    String::from_utf8_lossy(&s).into_owned()
+}
+
+/// The checksum pt-query-digest keys fingerprints by: the last 16 hex digits
+/// of their MD5, uppercased
+pub fn checksum(fingerprint: &str) -> String {
+    Md5::digest(fingerprint.as_bytes())[8..].iter().map(|b| format!("{:02X}", b)).collect()
+}
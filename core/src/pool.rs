@@ -0,0 +1,230 @@
+//! Process-wide pool of configured engines, one per distinct combination of
+//! dialect and options, so that repeated calls skip resolving the dialect,
+//! the compat rules and the option strings.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+use serde::{Deserialize, Serialize};
+use sqlparser::dialect::Dialect;
+
+use crate::precedence::Precedence;
+use crate::{builtins_only, canonical_dialect, collision, get_dialect, get_rules, hashing, identifier_case, keep, pipeline, qualifiers, stats, string_quotes, synonyms, trailing, PassOrder, Settings};
+
+/// Beyond this many engines (e.g. with ever-changing placeholders), new
+/// combinations are built per call instead of being pooled
+pub const MAX_ENGINES: usize = 64;
+
+/// The arguments an engine is configured from, as passed by the caller
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EngineKey {
+    pub dialect: String,
+    pub placeholder: String,
+    pub placeholder_start: usize,
+    pub compat: Option<String>,
+    pub templates: bool,
+    pub strict: bool,
+    pub passes: Option<PassOrder>,
+    /// Sorted and deduplicated
+    pub disabled_passes: Vec<String>,
+    /// Sorted and deduplicated
+    pub enabled_passes: Vec<String>,
+    pub function_case: String,
+    pub identifier_case: String,
+    pub clause_hashes: bool,
+    pub max_subquery_depth: Option<usize>,
+    pub routing_columns: Vec<String>,
+    pub placeholder_collision: String,
+    /// Sorted and deduplicated
+    pub ignore_clauses: Vec<String>,
+    /// Keys lowercased
+    pub table_columns: BTreeMap<String, Vec<String>>,
+    /// Keys lowercased
+    pub function_synonyms: BTreeMap<String, String>,
+    pub collect_params: bool,
+    pub max_params: Option<usize>,
+    pub truncated_input: bool,
+    pub lenient: bool,
+    pub collapse_in_lists: bool,
+    pub collapse_values: bool,
+    pub keep_limit: bool,
+    pub keep_offset: bool,
+    pub keep_null: bool,
+    pub strip_qualifiers: qualifiers::Strip,
+    pub ignore_aliases: bool,
+    pub anonymize: bool,
+    pub comments: bool,
+    pub keep_hints: bool,
+    pub unwrap_explain: bool,
+    pub prepared: bool,
+    pub hash_algorithm: String,
+    pub hash_encoding: String,
+}
+
+impl Default for EngineKey {
+    /// The defaults of `normalize()`
+    fn default() -> Self {
+        EngineKey {
+            dialect: "generic".to_string(),
+            placeholder: "?".to_string(),
+            placeholder_start: 1,
+            compat: None,
+            templates: false,
+            strict: false,
+            passes: None,
+            disabled_passes: Vec::new(),
+            enabled_passes: Vec::new(),
+            function_case: "upper".to_string(),
+            identifier_case: "preserve".to_string(),
+            clause_hashes: false,
+            max_subquery_depth: None,
+            routing_columns: Vec::new(),
+            placeholder_collision: "report".to_string(),
+            ignore_clauses: Vec::new(),
+            table_columns: BTreeMap::new(),
+            function_synonyms: BTreeMap::new(),
+            collect_params: true,
+            max_params: None,
+            truncated_input: false,
+            lenient: false,
+            collapse_in_lists: false,
+            collapse_values: false,
+            keep_limit: false,
+            keep_offset: false,
+            keep_null: true,
+            strip_qualifiers: qualifiers::Strip::default(),
+            ignore_aliases: false,
+            anonymize: false,
+            comments: false,
+            keep_hints: false,
+            unwrap_explain: false,
+            prepared: false,
+            hash_algorithm: "sha256".to_string(),
+            hash_encoding: "hex".to_string(),
+        }
+    }
+}
+
+pub struct Engine {
+    /// Canonical name, one of `stats::DIALECTS`, or `"auto"` for an engine
+    /// that hands each statement to the engine of the dialect detected for it
+    pub dialect_name: &'static str,
+    pub dialect: Box<dyn Dialect + Send + Sync>,
+    pub options: Settings,
+    /// What the engine was configured from
+    pub key: Arc<EngineKey>,
+    /// Number of times this engine was reused from the pool
+    hits: AtomicU64,
+}
+
+impl Engine {
+    fn new(key: &EngineKey) -> Result<Engine, String> {
+        let dialect_name = canonical_dialect(&key.dialect)?;
+        let mut rules = get_rules(key.compat.as_deref())?;
+        if builtins_only(&key.function_case)? {
+            rules.builtins_only = Some(dialect_name);
+        }
+        rules.identifier_case = identifier_case::mode(&key.identifier_case)?;
+        rules.positional_aliases = key.ignore_aliases;
+        // A collapsed list has one placeholder for several parameters
+        if key.prepared && (key.collapse_in_lists || key.collapse_values) {
+            return Err("prepared cannot be combined with collapse_in_lists or collapse_values".to_string());
+        }
+        synonyms::validate(&key.function_synonyms)?;
+        let dialect = get_dialect(dialect_name)?;
+        rules.plus_concat = dialect_name == "mssql";
+        rules.plain_backticks = dialect_name == "bigquery";
+        rules.pg_type_names = matches!(dialect_name, "postgresql" | "redshift" | "duckdb");
+        rules.identifier_booleans = !dialect.supports_boolean_literals();
+        rules.string_quotes = string_quotes(&*dialect);
+        if rules.dialect_precedence {
+            rules.precedence = Precedence::of(dialect_name);
+        }
+        // Where the dialect's documentation says NULLs sort
+        rules.nulls_high = match dialect_name {
+            "postgresql" | "oracle" | "snowflake" | "redshift" => Some(true),
+            "mysql" | "sqlite" | "mssql" | "teradata" | "informix" | "bigquery" | "hive" | "databricks" => Some(false),
+            // ClickHouse and DuckDB put NULLs last in both directions
+            _ => None,
+        };
+        Ok(Engine {
+            dialect_name,
+            dialect,
+            options: Settings {
+                placeholder: key.placeholder.clone(),
+                placeholder_start: key.placeholder_start,
+                steps: pipeline(&rules, key.passes.as_ref(), &key.disabled_passes, &key.enabled_passes)?,
+                rules,
+                templates: key.templates,
+                strict: key.strict,
+                clause_hashes: key.clause_hashes,
+                max_subquery_depth: key.max_subquery_depth,
+                routing_columns: key.routing_columns.clone(),
+                placeholder_collision: collision::mode(&key.placeholder_collision)?,
+                ignore_clauses: trailing::parse(&key.ignore_clauses)?,
+                table_columns: key.table_columns.clone(),
+                function_synonyms: key.function_synonyms.clone(),
+                collect_params: key.collect_params,
+                max_params: key.max_params,
+                truncated_input: key.truncated_input,
+                lenient: key.lenient,
+                collapse_in_lists: key.collapse_in_lists,
+                collapse_values: key.collapse_values,
+                keep: keep::Keep { limit: key.keep_limit, offset: key.keep_offset, null: key.keep_null },
+                strip_qualifiers: key.strip_qualifiers.clone(),
+                anonymize: key.anonymize,
+                comments: key.comments,
+                keep_hints: key.keep_hints,
+                unwrap_explain: key.unwrap_explain,
+                prepared: key.prepared,
+                hasher: hashing::Hasher::new(Some(&key.hash_algorithm), Some(&key.hash_encoding))?,
+            },
+            key: Arc::new(key.clone()),
+            hits: AtomicU64::new(0),
+        })
+    }
+}
+
+type Pool = RwLock<HashMap<EngineKey, Arc<Engine>>>;
+
+fn pool() -> &'static Pool {
+    static POOL: OnceLock<Pool> = OnceLock::new();
+    POOL.get_or_init(Default::default)
+}
+
+/// The pooled engine for `key`, building (and pooling) it on first use
+pub fn engine(mut key: EngineKey) -> Result<Arc<Engine>, String> {
+    // Aliases share the engine of their canonical name
+    let dialect = canonical_dialect(&key.dialect)?;
+    if key.dialect != dialect {
+        key.dialect = dialect.to_string();
+    }
+    if let Some(engine) = pool().read().unwrap_or_else(PoisonError::into_inner).get(&key) {
+        engine.hits.fetch_add(1, Ordering::Relaxed);
+        stats::bump(&stats::CACHE_HITS);
+        return Ok(Arc::clone(engine));
+    }
+    // Invalid keys fail here and are never pooled
+    let engine = Arc::new(Engine::new(&key)?);
+    let mut pool = pool().write().unwrap_or_else(PoisonError::into_inner);
+    if pool.len() < MAX_ENGINES {
+        // Another thread may have pooled the same key meanwhile; keep theirs
+        return Ok(Arc::clone(pool.entry(key).or_insert(engine)));
+    }
+    Ok(engine)
+}
+
+/// Pooled engines and their hit counts, in no particular order
+pub fn snapshot() -> Vec<(EngineKey, &'static str, u64)> {
+    pool()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .map(|(key, engine)| (key.clone(), engine.dialect_name, engine.hits.load(Ordering::Relaxed)))
+        .collect()
+}
+
+pub fn clear() {
+    pool().write().unwrap_or_else(PoisonError::into_inner).clear();
+}
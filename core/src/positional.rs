@@ -0,0 +1,303 @@
+//! Positional alias names, for `ignore_aliases`.
+//!
+//! Query builders name their aliases `t0`, `sq_47`, `anon_1`, … depending on
+//! how the query was assembled, which splits otherwise identical queries into
+//! several fingerprints. Table aliases are renamed to `t1`, `t2`, … and select
+//! list aliases to `c1`, `c2`, … in the order they first appear, together with
+//! the references to them: column qualifiers, qualified wildcards, the
+//! unqualified names of `GROUP BY`, `HAVING` and `ORDER BY`, and the columns
+//! of derived tables read through their alias. Each name is renamed alike
+//! wherever it appears, so references keep resolving to what they did. Only
+//! what can be renamed so is: a table alias that is also the name of an
+//! unaliased table, and a select list alias that is also read as a column
+//! (in a select list, `WHERE`, or qualified by a table), are left as written,
+//! and new names skip those the statement already uses.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    visit_expressions, Expr, FunctionArg, FunctionArgExpr, FunctionArguments, Ident, JoinConstraint, ObjectName, ObjectNamePart,
+    Query, SelectItem, SelectItemQualifiedWildcardKind, SetExpr, Statement, TableFactor, Visit, VisitMut, Visitor,
+    VisitorMut,
+};
+
+use crate::cte::key;
+use crate::join_constraint;
+
+/// The aliases of a statement and the names it uses otherwise
+#[derive(Default)]
+struct Names {
+    /// Table aliases, in order of first appearance
+    tables: Vec<String>,
+    /// Select list aliases, in order of first appearance
+    columns: Vec<String>,
+    /// Aliases of derived tables, whose columns are the select list aliases
+    /// of their query
+    derived: HashSet<String>,
+    /// Unaliased tables, by their unqualified name
+    unaliased: HashSet<String>,
+    /// Names read as columns, which select list aliases must not be renamed
+    /// away from
+    read: HashSet<String>,
+    /// `(qualifier, column)` of two-part column references
+    qualified: Vec<(String, String)>,
+    /// Every identifier of the statement
+    used: HashSet<String>,
+}
+
+impl Names {
+    fn add(list: &mut Vec<String>, name: String) {
+        if !list.contains(&name) {
+            list.push(name);
+        }
+    }
+
+    /// The unqualified identifiers of the select lists, `WHERE` clauses and
+    /// join conditions of `body`
+    fn read_columns(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                let mut read = |expr: &Expr| {
+                    if let Expr::Identifier(ident) = expr {
+                        self.read.insert(key(ident));
+                    }
+                    ControlFlow::<()>::Continue(())
+                };
+                for item in &select.projection {
+                    if let SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } = item {
+                        let _ = visit_expressions(expr, &mut read);
+                    }
+                }
+                if let Some(ref selection) = select.selection {
+                    let _ = visit_expressions(selection, &mut read);
+                }
+                for join in select.from.iter().flat_map(|table| &table.joins) {
+                    if let Some(JoinConstraint::On(on)) = join_constraint(&join.join_operator) {
+                        let _ = visit_expressions(on, &mut read);
+                    }
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.read_columns(left);
+                self.read_columns(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The select list aliases of `body`
+fn select_aliases(body: &SetExpr, out: &mut Vec<String>) {
+    match body {
+        SetExpr::Select(select) => {
+            for item in &select.projection {
+                if let SelectItem::ExprWithAlias { alias, .. } = item {
+                    Names::add(out, key(alias));
+                }
+            }
+        }
+        SetExpr::SetOperation { left, right, .. } => {
+            select_aliases(left, out);
+            select_aliases(right, out);
+        }
+        _ => {}
+    }
+}
+
+/// The alias of `factor`, if it has one that can be renamed
+fn alias(factor: &TableFactor) -> Option<&Ident> {
+    match factor {
+        TableFactor::Table { alias: Some(alias), .. }
+        | TableFactor::Derived { alias: Some(alias), .. }
+        | TableFactor::TableFunction { alias: Some(alias), .. }
+        | TableFactor::Function { alias: Some(alias), .. }
+        | TableFactor::UNNEST { alias: Some(alias), .. } => Some(&alias.name),
+        _ => None,
+    }
+}
+
+fn alias_mut(factor: &mut TableFactor) -> Option<&mut Ident> {
+    match factor {
+        TableFactor::Table { alias: Some(alias), .. }
+        | TableFactor::Derived { alias: Some(alias), .. }
+        | TableFactor::TableFunction { alias: Some(alias), .. }
+        | TableFactor::Function { alias: Some(alias), .. }
+        | TableFactor::UNNEST { alias: Some(alias), .. } => Some(&mut alias.name),
+        _ => None,
+    }
+}
+
+impl Visitor for Names {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        select_aliases(&query.body, &mut self.columns);
+        self.read_columns(&query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, factor: &TableFactor) -> ControlFlow<()> {
+        match alias(factor) {
+            Some(alias) => {
+                let alias = key(alias);
+                if matches!(factor, TableFactor::Derived { .. }) {
+                    self.derived.insert(alias.clone());
+                }
+                Names::add(&mut self.tables, alias);
+            }
+            None => {
+                if let TableFactor::Table { name, .. } = factor {
+                    if let Some(ObjectNamePart::Identifier(table)) = name.0.last() {
+                        self.unaliased.insert(key(table));
+                    }
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &ObjectName) -> ControlFlow<()> {
+        self.used.extend(relation.0.iter().filter_map(|part| part.as_ident()).map(key));
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<()> {
+        match expr {
+            Expr::Identifier(ident) => {
+                self.used.insert(key(ident));
+            }
+            Expr::CompoundIdentifier(idents) => {
+                self.used.extend(idents.iter().map(key));
+                match idents.as_slice() {
+                    [qualifier, column] => self.qualified.push((key(qualifier), key(column))),
+                    [.., column] => {
+                        self.read.insert(key(column));
+                    }
+                    [] => {}
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// The new names of the aliases, by key
+struct Rename {
+    tables: HashMap<String, String>,
+    columns: HashMap<String, String>,
+    derived: HashSet<String>,
+}
+
+impl Rename {
+    fn table(&self, ident: &mut Ident) {
+        if let Some(name) = self.tables.get(&key(ident)) {
+            *ident = Ident::new(name);
+        }
+    }
+
+    fn column(&self, ident: &mut Ident) {
+        if let Some(name) = self.columns.get(&key(ident)) {
+            *ident = Ident::new(name);
+        }
+    }
+
+    fn wildcard(&self, name: &mut ObjectName) {
+        if let [ObjectNamePart::Identifier(ident)] = name.0.as_mut_slice() {
+            self.table(ident);
+        }
+    }
+
+    fn projection(&self, body: &mut SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                for item in &mut select.projection {
+                    match item {
+                        SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::ObjectName(name), _) => {
+                            self.wildcard(name)
+                        }
+                        SelectItem::ExprWithAlias { alias, .. } => self.column(alias),
+                        _ => {}
+                    }
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.projection(left);
+                self.projection(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl VisitorMut for Rename {
+    type Break = ();
+
+    fn post_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        self.projection(&mut query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, factor: &mut TableFactor) -> ControlFlow<()> {
+        if let Some(alias) = alias_mut(factor) {
+            self.table(alias);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<()> {
+        match expr {
+            Expr::Identifier(ident) => self.column(ident),
+            Expr::CompoundIdentifier(idents) => {
+                if let [qualifier, column] = idents.as_mut_slice() {
+                    if self.derived.contains(&key(qualifier)) {
+                        self.column(column);
+                    }
+                    self.table(qualifier);
+                }
+            }
+            Expr::QualifiedWildcard(name, _) => self.wildcard(name),
+            Expr::Function(function) => {
+                if let FunctionArguments::List(ref mut list) = function.args {
+                    for arg in &mut list.args {
+                        if let FunctionArg::Unnamed(FunctionArgExpr::QualifiedWildcard(name))
+                        | FunctionArg::Named { arg: FunctionArgExpr::QualifiedWildcard(name), .. } = arg
+                        {
+                            self.wildcard(name);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// `{prefix}1`, `{prefix}2`, … for `aliases`, skipping the names in `taken`
+fn number(aliases: &[String], prefix: &str, taken: &HashSet<String>) -> HashMap<String, String> {
+    let mut names = (1..).map(|n| format!("{}{}", prefix, n)).filter(|name| !taken.contains(name));
+    aliases.iter().map(|alias| (alias.clone(), names.next().unwrap_or_default())).collect()
+}
+
+/// Rename the aliases of `stmt` to positional names, in place
+pub fn rename_aliases(stmt: &mut Statement) {
+    let mut names = Names::default();
+    let _ = Visit::visit(&*stmt, &mut names);
+    for (qualifier, column) in &names.qualified {
+        if !names.derived.contains(qualifier) {
+            names.read.insert(column.clone());
+        }
+    }
+    let tables: Vec<String> = names.tables.iter().filter(|alias| !names.unaliased.contains(*alias)).cloned().collect();
+    let columns: Vec<String> = names.columns.iter().filter(|alias| !names.read.contains(*alias)).cloned().collect();
+    if tables.is_empty() && columns.is_empty() {
+        return;
+    }
+    // Names that stay in the statement, which the new ones must not take
+    let taken: HashSet<String> =
+        names.used.iter().chain(&names.unaliased).filter(|name| !tables.contains(name) && !columns.contains(name)).cloned().collect();
+    let mut rename = Rename { tables: number(&tables, "t", &taken), columns: number(&columns, "c", &taken), derived: names.derived };
+    let _ = stmt.visit(&mut rename);
+}
@@ -0,0 +1,412 @@
+//! Operator precedence, for `paren-canonicalize`.
+//!
+//! Parentheses are stripped and re-added where precedence needs them, so the
+//! precedence table decides the normalized text. Two tables matter for a
+//! dialect: the one sqlparser reads the text back by, and the one the
+//! database follows, which differ in places. MySQL binds `^` tighter than `*`
+//! and `!` tighter than `BETWEEN`, PostgreSQL puts `||`, `&`, `|`, `#` and
+//! the shifts on one level below `+`, SQL Server puts the bitwise operators
+//! on the level of `+`, and SQLite binds `||` tighter than `*`. A child keeps
+//! its parentheses when either table needs them, so that the text means the
+//! same to both. The 0.1.x releases used one table for every dialect, and
+//! only for operators under operators; it is kept as `Precedence::Legacy`.
+//!
+//! Parentheses cannot undo a misreading of the input. sqlparser binds `XOR`
+//! tighter than `=` in every dialect, where MySQL binds it looser than `AND`:
+//! `id = 1 XOR id = 2` is read as `id = ((1 XOR id) = 2)`, and written
+//! `id = (? XOR id) = ?` so that MySQL reads it that way too. Only the
+//! parenthesized `(id = 1) XOR (id = 2)` means the same to both.
+
+use sqlparser::ast::{BinaryOperator, Expr, UnaryOperator, Value};
+
+use crate::dialects;
+
+/// The precedence tables parentheses are re-added by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Precedence {
+    /// One table for every dialect, as in the 0.1.x releases
+    Legacy,
+    /// The tables of the dialect: sqlparser's for `parsed`, the canonical
+    /// name of the sqlparser dialect it parses with, and the database's for
+    /// `engine`, its own canonical name
+    Dialect { parsed: &'static str, engine: &'static str },
+}
+
+impl Precedence {
+    /// The tables of the dialect with canonical name `name`
+    pub fn of(name: &'static str) -> Precedence {
+        let parsed = dialects::spec(name).map_or(name, |spec| spec.base);
+        Precedence::Dialect { parsed, engine: name }
+    }
+}
+
+/// Operators that bind alike in every table below
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Level {
+    Or,
+    Xor,
+    And,
+    /// `NOT`
+    Not,
+    /// MySQL's `!`
+    Bang,
+    /// `IS NULL`, `IS TRUE`, `IS DISTINCT FROM`, ...
+    Is,
+    /// `LIKE`, `ILIKE`, `SIMILAR TO`, `REGEXP`, ...
+    Like,
+    /// `BETWEEN` and `IN`
+    Between,
+    /// `=`, `<>`, `<=>`
+    Eq,
+    /// `<`, `<=`, `>`, `>=`
+    Compare,
+    /// PostgreSQL's `~`, `~*`, `~~`, ...
+    Match,
+    /// `|`
+    Pipe,
+    /// `^` as exclusive or
+    Caret,
+    /// PostgreSQL's `#`
+    Sharp,
+    /// `<<` and `>>`
+    Shift,
+    /// `&`
+    Ampersand,
+    /// PostgreSQL's JSON, containment and other operators
+    Json,
+    /// Binary `+` and `-`
+    PlusMinus,
+    /// `||`
+    Concat,
+    /// `*`, `/`, `%`, `DIV`, `//`
+    MulDivMod,
+    /// PostgreSQL's `^`
+    Exp,
+    /// Unary `+` and `-`
+    Minus,
+    /// Unary `~`
+    Tilde,
+    /// Any other operator, parenthesized whatever it is next to
+    Other,
+}
+
+fn binary_level(op: &BinaryOperator) -> Level {
+    match op {
+        BinaryOperator::Or => Level::Or,
+        BinaryOperator::Xor => Level::Xor,
+        BinaryOperator::And => Level::And,
+        BinaryOperator::Eq | BinaryOperator::NotEq | BinaryOperator::Spaceship => Level::Eq,
+        BinaryOperator::Lt | BinaryOperator::LtEq | BinaryOperator::Gt | BinaryOperator::GtEq => Level::Compare,
+        BinaryOperator::Match | BinaryOperator::Regexp => Level::Like,
+        BinaryOperator::PGRegexMatch
+        | BinaryOperator::PGRegexIMatch
+        | BinaryOperator::PGRegexNotMatch
+        | BinaryOperator::PGRegexNotIMatch
+        | BinaryOperator::PGLikeMatch
+        | BinaryOperator::PGILikeMatch
+        | BinaryOperator::PGNotLikeMatch
+        | BinaryOperator::PGNotILikeMatch => Level::Match,
+        BinaryOperator::BitwiseOr => Level::Pipe,
+        BinaryOperator::BitwiseXor => Level::Caret,
+        BinaryOperator::PGBitwiseXor => Level::Sharp,
+        BinaryOperator::PGBitwiseShiftLeft | BinaryOperator::PGBitwiseShiftRight => Level::Shift,
+        BinaryOperator::BitwiseAnd => Level::Ampersand,
+        BinaryOperator::Arrow
+        | BinaryOperator::LongArrow
+        | BinaryOperator::HashArrow
+        | BinaryOperator::HashLongArrow
+        | BinaryOperator::AtArrow
+        | BinaryOperator::ArrowAt
+        | BinaryOperator::HashMinus
+        | BinaryOperator::AtQuestion
+        | BinaryOperator::AtAt
+        | BinaryOperator::Question
+        | BinaryOperator::QuestionAnd
+        | BinaryOperator::QuestionPipe
+        | BinaryOperator::PGOverlap
+        | BinaryOperator::PGCustomBinaryOperator(_) => Level::Json,
+        BinaryOperator::Plus | BinaryOperator::Minus => Level::PlusMinus,
+        BinaryOperator::StringConcat => Level::Concat,
+        BinaryOperator::Multiply
+        | BinaryOperator::Divide
+        | BinaryOperator::Modulo
+        | BinaryOperator::MyIntegerDivide
+        | BinaryOperator::DuckIntegerDivide => Level::MulDivMod,
+        BinaryOperator::PGExp => Level::Exp,
+        _ => Level::Other,
+    }
+}
+
+fn unary_level(op: &UnaryOperator) -> Level {
+    match op {
+        UnaryOperator::Not => Level::Not,
+        UnaryOperator::BangNot => Level::Bang,
+        UnaryOperator::Plus | UnaryOperator::Minus => Level::Minus,
+        UnaryOperator::BitwiseNot => Level::Tilde,
+        _ => Level::Other,
+    }
+}
+
+/// How tightly sqlparser binds `level` when parsing with the dialect named
+/// `parsed`; for a prefix operator, the precedence its operand is parsed at.
+/// `None` for the operators it has no single rank for.
+fn parsed_rank(parsed: &str, level: Level) -> Option<u8> {
+    if parsed == "postgresql" {
+        return Some(match level {
+            Level::Or => 10,
+            Level::And => 20,
+            Level::Not | Level::Bang => 30,
+            Level::Is => 40,
+            Level::Eq | Level::Compare | Level::Match => 50,
+            Level::Like | Level::Between => 60,
+            Level::Pipe | Level::Sharp | Level::Shift | Level::Ampersand | Level::Json | Level::Concat => 70,
+            Level::Xor => 75,
+            Level::PlusMinus | Level::Tilde => 80,
+            Level::MulDivMod | Level::Minus => 90,
+            Level::Caret | Level::Exp => 100,
+            Level::Other => return None,
+        });
+    }
+    Some(match level {
+        Level::Or => 5,
+        Level::And => 10,
+        Level::Not | Level::Bang => 15,
+        Level::Is => 17,
+        Level::Like => 19,
+        Level::Between | Level::Eq | Level::Compare | Level::Match => 20,
+        Level::Pipe | Level::Json => 21,
+        Level::Caret | Level::Sharp | Level::Shift | Level::Exp => 22,
+        Level::Ampersand => 23,
+        Level::Xor => 24,
+        Level::PlusMinus | Level::Tilde => 30,
+        // Oracle concatenates on the level of `+`
+        Level::Concat if parsed == "oracle" => 30,
+        Level::Concat | Level::MulDivMod | Level::Minus => 40,
+        Level::Other => return None,
+    })
+}
+
+/// How tightly the database named `engine` binds `level`, by its
+/// documentation; `None` where it does not say, or where it agrees with
+/// sqlparser's order anyway
+fn engine_rank(engine: &str, level: Level) -> Option<u8> {
+    match engine {
+        "mysql" => match level {
+            Level::Or => Some(1),
+            Level::Xor => Some(2),
+            Level::And => Some(3),
+            Level::Not => Some(4),
+            Level::Between => Some(5),
+            Level::Eq | Level::Compare | Level::Is | Level::Like => Some(6),
+            Level::Pipe => Some(7),
+            Level::Ampersand => Some(8),
+            Level::Shift => Some(9),
+            Level::PlusMinus => Some(10),
+            Level::MulDivMod => Some(11),
+            Level::Caret => Some(12),
+            Level::Minus | Level::Tilde => Some(13),
+            Level::Bang => Some(14),
+            _ => None,
+        },
+        "postgresql" => match level {
+            Level::Or => Some(1),
+            Level::And => Some(2),
+            Level::Not => Some(3),
+            Level::Is => Some(4),
+            Level::Eq | Level::Compare => Some(5),
+            Level::Like | Level::Between => Some(6),
+            Level::Match
+            | Level::Pipe
+            | Level::Ampersand
+            | Level::Sharp
+            | Level::Shift
+            | Level::Concat
+            | Level::Json => Some(7),
+            Level::PlusMinus => Some(8),
+            Level::MulDivMod => Some(9),
+            Level::Caret | Level::Exp => Some(10),
+            Level::Minus => Some(11),
+            _ => None,
+        },
+        "mssql" => match level {
+            Level::Or => Some(1),
+            Level::And => Some(2),
+            Level::Not => Some(3),
+            Level::Eq | Level::Compare => Some(4),
+            Level::PlusMinus | Level::Concat | Level::Pipe | Level::Ampersand | Level::Caret => Some(5),
+            Level::MulDivMod => Some(6),
+            Level::Tilde => Some(7),
+            _ => None,
+        },
+        "sqlite" => match level {
+            Level::Or => Some(1),
+            Level::And => Some(2),
+            Level::Not => Some(3),
+            Level::Eq | Level::Is | Level::Like | Level::Between | Level::Match => Some(4),
+            Level::Compare => Some(5),
+            Level::Pipe | Level::Ampersand | Level::Shift => Some(6),
+            Level::PlusMinus => Some(7),
+            Level::MulDivMod => Some(8),
+            Level::Concat | Level::Json => Some(9),
+            Level::Minus | Level::Tilde => Some(10),
+            _ => None,
+        },
+        "oracle" => match level {
+            Level::Or => Some(1),
+            Level::And => Some(2),
+            Level::Not => Some(3),
+            Level::Is | Level::Like | Level::Between => Some(4),
+            Level::Eq | Level::Compare => Some(5),
+            Level::PlusMinus | Level::Concat => Some(6),
+            Level::MulDivMod => Some(7),
+            Level::Minus => Some(8),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The level of the operator at the top of `expr`, if any
+fn level(expr: &Expr) -> Option<Level> {
+    Some(match expr {
+        Expr::BinaryOp { op, .. } => binary_level(op),
+        Expr::UnaryOp { op, .. } => unary_level(op),
+        Expr::IsNull(_)
+        | Expr::IsNotNull(_)
+        | Expr::IsTrue(_)
+        | Expr::IsNotTrue(_)
+        | Expr::IsFalse(_)
+        | Expr::IsNotFalse(_)
+        | Expr::IsUnknown(_)
+        | Expr::IsNotUnknown(_)
+        | Expr::IsDistinctFrom(..)
+        | Expr::IsNotDistinctFrom(..) => Level::Is,
+        Expr::Like { .. } | Expr::ILike { .. } | Expr::SimilarTo { .. } | Expr::RLike { .. } => Level::Like,
+        Expr::Between { .. } | Expr::InList { .. } | Expr::InSubquery { .. } => Level::Between,
+        _ => return None,
+    })
+}
+
+/// Whether a child at `child` needs parentheses under an operator at
+/// `parent`, on its right (or as the operand of a prefix operator) or its
+/// left
+fn needs_parens(precedence: Precedence, parent: Level, child: Level, right: bool) -> bool {
+    let Precedence::Dialect { parsed, engine } = precedence else {
+        unreachable!("the legacy table is applied apart");
+    };
+    let binds_looser = |parent: u8, child: u8| child < parent || (right && child == parent);
+    match (parsed_rank(parsed, parent), parsed_rank(parsed, child)) {
+        (Some(p), Some(c)) if !binds_looser(p, c) => {}
+        _ => return true,
+    }
+    matches!((engine_rank(engine, parent), engine_rank(engine, child)), (Some(p), Some(c)) if binds_looser(p, c))
+}
+
+/// Wrap the content of a Box<Expr> in Expr::Nested (i.e. add parentheses)
+pub fn wrap_in_nested(e: &mut Box<Expr>) {
+    let inner = std::mem::replace(e.as_mut(), Expr::Value(Value::Null.into()));
+    **e = Expr::Nested(Box::new(inner));
+}
+
+/// Parenthesize `child` of an operator at `parent` if precedence needs it.
+/// A prefix operator on the right starts a new operand and never does.
+fn parenthesize_child(precedence: Precedence, parent: Level, child: &mut Box<Expr>, right: bool) {
+    let Some(level) = level(child) else {
+        return;
+    };
+    let prefix = matches!(level, Level::Not | Level::Bang | Level::Minus | Level::Tilde);
+    if !(right && prefix) && needs_parens(precedence, parent, level, right) {
+        wrap_in_nested(child);
+    }
+}
+
+/// SQL operator precedence of the 0.1.x releases (higher = binds tighter)
+fn legacy_precedence(op: &BinaryOperator) -> u8 {
+    match op {
+        BinaryOperator::Or => 10,
+        BinaryOperator::Xor => 20,
+        BinaryOperator::And => 30,
+        // NOT (unary) sits at ~35
+        BinaryOperator::Eq | BinaryOperator::NotEq
+        | BinaryOperator::Lt | BinaryOperator::LtEq
+        | BinaryOperator::Gt | BinaryOperator::GtEq
+        | BinaryOperator::Spaceship => 40,
+        BinaryOperator::BitwiseOr => 50,
+        BinaryOperator::BitwiseXor | BinaryOperator::PGBitwiseXor => 55,
+        BinaryOperator::BitwiseAnd => 60,
+        BinaryOperator::PGBitwiseShiftLeft | BinaryOperator::PGBitwiseShiftRight => 65,
+        BinaryOperator::Plus | BinaryOperator::Minus | BinaryOperator::StringConcat => 70,
+        BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo
+        | BinaryOperator::MyIntegerDivide | BinaryOperator::DuckIntegerDivide => 80,
+        _ => 90,
+    }
+}
+
+fn legacy_unary_precedence(op: &UnaryOperator) -> u8 {
+    match op {
+        UnaryOperator::Not => 35,
+        _ => 85, // unary +/- and others bind tighter than multiplication
+    }
+}
+
+/// Add the parentheses the operands of `expr` need, which have none
+pub fn parenthesize(expr: &mut Expr, precedence: Precedence) {
+    if precedence == Precedence::Legacy {
+        match expr {
+            Expr::BinaryOp { ref mut left, ref op, ref mut right } => {
+                let prec = legacy_precedence(op);
+                // Left child: wrap if strictly lower precedence
+                if let Expr::BinaryOp { op: ref child_op, .. } = left.as_ref() {
+                    if legacy_precedence(child_op) < prec {
+                        wrap_in_nested(left);
+                    }
+                }
+                // Right child: wrap if lower-or-equal (SQL is left-associative)
+                if let Expr::BinaryOp { op: ref child_op, .. } = right.as_ref() {
+                    if legacy_precedence(child_op) <= prec {
+                        wrap_in_nested(right);
+                    }
+                }
+            }
+            Expr::UnaryOp { op, expr: ref mut inner } => {
+                let prec = legacy_unary_precedence(op);
+                if let Expr::BinaryOp { op: ref child_op, .. } = inner.as_ref() {
+                    if legacy_precedence(child_op) < prec {
+                        wrap_in_nested(inner);
+                    }
+                }
+            }
+            _ => {}
+        }
+        return;
+    }
+    let Some(parent) = level(expr) else {
+        return;
+    };
+    match expr {
+        Expr::BinaryOp { left, right, .. }
+        | Expr::IsDistinctFrom(left, right)
+        | Expr::IsNotDistinctFrom(left, right)
+        | Expr::Like { expr: left, pattern: right, .. }
+        | Expr::ILike { expr: left, pattern: right, .. }
+        | Expr::SimilarTo { expr: left, pattern: right, .. }
+        | Expr::RLike { expr: left, pattern: right, .. } => {
+            parenthesize_child(precedence, parent, left, false);
+            parenthesize_child(precedence, parent, right, true);
+        }
+        // The operand of a prefix operator is parsed like a right operand
+        Expr::UnaryOp { expr: inner, .. } => parenthesize_child(precedence, parent, inner, true),
+        Expr::IsNull(inner)
+        | Expr::IsNotNull(inner)
+        | Expr::IsTrue(inner)
+        | Expr::IsNotTrue(inner)
+        | Expr::IsFalse(inner)
+        | Expr::IsNotFalse(inner)
+        | Expr::IsUnknown(inner)
+        | Expr::IsNotUnknown(inner)
+        | Expr::Between { expr: inner, .. }
+        | Expr::InList { expr: inner, .. }
+        | Expr::InSubquery { expr: inner, .. } => parenthesize_child(precedence, parent, inner, false),
+        _ => {}
+    }
+}
@@ -0,0 +1,73 @@
+//! Prepared statements, for `prepared=True`.
+//!
+//! `cursor.execute(result.normalized, result.typed_params)` takes the
+//! placeholders of the literals in the order of `params`. Literals are
+//! numbered in the order they are visited, which is not always the order
+//! they are written in (passes run after `literal-parameterize`, clauses the
+//! writer puts elsewhere), so they are normalized with numbered placeholders
+//! first (`"?{n}"` for `"?"`), then written with the placeholder asked for in
+//! the order they stand in the text, `params` following that order. Binds
+//! already in the statement are left as written and out of `params`, listed
+//! in `metadata["binds"]`. With a placeholder of the `%` styles, every other
+//! `%` is doubled, as drivers read `%%` as `%` wherever it stands.
+
+use sqlparser::dialect::Dialect;
+use sqlparser::tokenizer::Tokenizer;
+
+use crate::bind::{placeholder_at, quoted};
+use crate::paramstyle::token_offsets;
+use crate::placeholder_text;
+
+/// `sql`, normalized with the numbered placeholder `numbered` from `start`,
+/// with its literals' placeholders written as `placeholder` in text order,
+/// numbered from `start` again if it is numbered, and every other `%`
+/// doubled with `percent`; and the index in `params` of each, for the first
+/// `count` of them
+pub fn prepare(
+    sql: &str,
+    dialect: &dyn Dialect,
+    numbered: &str,
+    start: usize,
+    placeholder: &str,
+    count: usize,
+    percent: bool,
+) -> Result<(String, Vec<usize>), String> {
+    let tokens = Tokenizer::new(dialect, sql)
+        .with_unescape(false)
+        .tokenize_with_location()
+        .map_err(|e| format!("Tokenize error: {}", e))?;
+    let offsets = token_offsets(sql, &tokens, dialect);
+    let end_of = |i: usize| offsets.get(i).copied().unwrap_or(sql.len());
+
+    let mut out = String::with_capacity(sql.len());
+    let mut order = Vec::with_capacity(count);
+    let mut written = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        let at = end_of(i);
+        let found = (!quoted(&tokens[i].token)).then(|| placeholder_at(numbered, &sql[at..])).flatten();
+        let literal = found.and_then(|(len, number)| {
+            let index = number?.checked_sub(start)?;
+            let last = (i..tokens.len()).find(|&j| end_of(j + 1) == at + len)?;
+            Some((index, last))
+        });
+        let Some((index, last)) = literal else {
+            let text = &sql[at..end_of(i + 1)];
+            if percent {
+                out.push_str(&text.replace('%', "%%"));
+            } else {
+                out.push_str(text);
+            }
+            i += 1;
+            continue;
+        };
+        out.push_str(&placeholder_text(placeholder, start + written));
+        written += 1;
+        // Those of literals `max_params` left out have no parameter
+        if index < count {
+            order.push(index);
+        }
+        i = last + 1;
+    }
+    Ok((out, order))
+}
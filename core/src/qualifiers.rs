@@ -0,0 +1,160 @@
+//! Schema and database qualifiers of table names, for `strip_qualifiers`.
+//!
+//! Multi-tenant applications run the same query against `tenant_123.orders`
+//! and `tenant_456.orders`, and MSSQL code spells `dbo.Orders` and `Orders`
+//! alike. With `strip_qualifiers=True`, table references keep their last
+//! name part only, so that such statements share a fingerprint; with a list
+//! of schemas, only names qualified by one of them (matched without regard
+//! to case) are stripped. Column references and qualified wildcards spelling
+//! a stripped table in full (`dbo.Orders.id`) are shortened to match.
+
+use std::ops::ControlFlow;
+
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{
+    Expr, Ident, ObjectName, ObjectNamePart, Query, SelectItem, SelectItemQualifiedWildcardKind, SetExpr, Statement,
+    VisitMut, VisitorMut,
+};
+
+/// The `strip_qualifiers` option: `True` or `False`, or the schemas to strip
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Strip {
+    All(bool),
+    Schemas(Vec<String>),
+}
+
+impl Default for Strip {
+    fn default() -> Self {
+        Strip::All(false)
+    }
+}
+
+impl Strip {
+    /// With the schemas lowercased, sorted and deduplicated
+    pub fn canonical(self) -> Strip {
+        match self {
+            Strip::Schemas(schemas) => {
+                let mut schemas: Vec<String> = schemas.iter().map(|schema| schema.to_lowercase()).collect();
+                schemas.sort();
+                schemas.dedup();
+                Strip::Schemas(schemas)
+            }
+            all => all,
+        }
+    }
+
+    /// Whether a table name qualified by `qualifier` is stripped
+    fn strips(&self, qualifier: &[ObjectNamePart]) -> bool {
+        match (self, qualifier.last()) {
+            (_, None) => false,
+            (Strip::All(all), _) => *all,
+            (Strip::Schemas(schemas), Some(ObjectNamePart::Identifier(schema))) => {
+                schemas.binary_search(&schema.value.to_lowercase()).is_ok()
+            }
+            (Strip::Schemas(_), Some(_)) => false,
+        }
+    }
+}
+
+/// The lowercased parts of a name, for comparing a column's qualifier with it
+fn parts(idents: &[Ident]) -> Vec<String> {
+    idents.iter().map(|ident| ident.value.to_lowercase()).collect()
+}
+
+fn name_parts(name: &ObjectName) -> Option<Vec<String>> {
+    name.0.iter().map(|part| part.as_ident().map(|ident| ident.value.to_lowercase())).collect()
+}
+
+struct Stripper<'a> {
+    strip: &'a Strip,
+    /// The full names of the tables stripped
+    stripped: Vec<Vec<String>>,
+}
+
+impl VisitorMut for Stripper<'_> {
+    type Break = ();
+
+    fn pre_visit_relation(&mut self, relation: &mut ObjectName) -> ControlFlow<()> {
+        let qualifiers = relation.0.len().saturating_sub(1);
+        if self.strip.strips(&relation.0[..qualifiers]) {
+            if let Some(parts) = name_parts(relation) {
+                self.stripped.push(parts);
+            }
+            relation.0.drain(..qualifiers);
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Shortens the references to the tables stripped
+struct Shorten<'a> {
+    stripped: &'a [Vec<String>],
+}
+
+impl Shorten<'_> {
+    /// The number of leading parts of `qualifier` that a stripped table drops
+    fn excess(&self, qualifier: Option<Vec<String>>) -> usize {
+        match qualifier {
+            Some(qualifier) if qualifier.len() > 1 && self.stripped.contains(&qualifier) => qualifier.len() - 1,
+            _ => 0,
+        }
+    }
+
+    fn name(&self, name: &mut ObjectName) {
+        let excess = self.excess(name_parts(name));
+        name.0.drain(..excess);
+    }
+
+    /// The qualified wildcards of the select lists of `body`
+    fn projection(&self, body: &mut SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                for item in &mut select.projection {
+                    if let SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::ObjectName(name), _) = item {
+                        self.name(name);
+                    }
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.projection(left);
+                self.projection(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl VisitorMut for Shorten<'_> {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        self.projection(&mut query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<()> {
+        match expr {
+            Expr::CompoundIdentifier(idents) if idents.len() > 2 => {
+                let excess = self.excess(Some(parts(&idents[..idents.len() - 1])));
+                idents.drain(..excess);
+            }
+            Expr::QualifiedWildcard(name, _) => self.name(name),
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Strip the qualifiers of the table names of `stmt` that `strip` asks for,
+/// in place
+pub fn strip(stmt: &mut Statement, strip: &Strip) {
+    if *strip == Strip::All(false) {
+        return;
+    }
+    let mut stripper = Stripper { strip, stripped: Vec::new() };
+    let _ = stmt.visit(&mut stripper);
+    if !stripper.stripped.is_empty() {
+        let _ = stmt.visit(&mut Shorten { stripped: &stripper.stripped });
+    }
+}
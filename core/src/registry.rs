@@ -0,0 +1,167 @@
+//! Per-fingerprint counts for `FingerprintRegistry`, and their checkpoints.
+//!
+//! Observations given a timestamp are also counted per time bucket (a minute
+//! by default), giving each fingerprint a time series of its rate.
+//!
+//! A checkpoint is JSONL: a header line with the sqlfp and fingerprint
+//! versions, dialect, compat, bucket size and unparsed count, then one line per
+//! fingerprint sorted by hash, so that a registry always saves to the same
+//! bytes. Registries only merge when their dialect and compat match, as their
+//! hashes are not comparable otherwise, and when their buckets have the same
+//! size; checkpoints of another fingerprint version do not load.
+
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value};
+
+use crate::writer::FINGERPRINT_VERSION;
+
+/// Checkpoint format version, bumped on incompatible changes
+const FORMAT: u64 = 1;
+
+pub const DEFAULT_BUCKET_SECONDS: u64 = 60;
+
+pub struct Entry {
+    pub normalized: String,
+    pub count: u64,
+    /// Counts of the timestamped observations, by bucket start (Unix seconds)
+    pub series: BTreeMap<i64, u64>,
+}
+
+pub struct Registry {
+    /// Canonical name, one of `stats::DIALECTS`
+    pub dialect: String,
+    pub compat: Option<String>,
+    /// Size of the time buckets, in seconds
+    pub bucket_seconds: u64,
+    /// Entries by hash
+    pub entries: BTreeMap<String, Entry>,
+    /// Observed statements that could not be parsed
+    pub unparsed: u64,
+}
+
+impl Registry {
+    pub fn new(dialect: &str, compat: Option<&str>, bucket_seconds: u64) -> Result<Registry, String> {
+        if bucket_seconds == 0 {
+            return Err("bucket_seconds must be positive".to_string());
+        }
+        Ok(Registry {
+            dialect: dialect.to_string(),
+            compat: compat.map(str::to_string),
+            bucket_seconds,
+            entries: BTreeMap::new(),
+            unparsed: 0,
+        })
+    }
+
+    fn entry(&mut self, hash: &str, normalized: &str) -> &mut Entry {
+        self.entries
+            .entry(hash.to_string())
+            .or_insert_with(|| Entry { normalized: normalized.to_string(), count: 0, series: BTreeMap::new() })
+    }
+
+    /// Count `count` observations, in the bucket of `timestamp` (Unix
+    /// seconds) if given
+    pub fn observe(&mut self, hash: &str, normalized: &str, count: u64, timestamp: Option<f64>) {
+        let bucket_seconds = self.bucket_seconds as f64;
+        let entry = self.entry(hash, normalized);
+        entry.count += count;
+        if let Some(timestamp) = timestamp {
+            let bucket = ((timestamp / bucket_seconds).floor() * bucket_seconds) as i64;
+            *entry.series.entry(bucket).or_insert(0) += count;
+        }
+    }
+
+    pub fn merge(&mut self, other: &Registry) -> Result<(), String> {
+        if self.bucket_seconds != other.bucket_seconds {
+            return Err(format!(
+                "Cannot merge a registry with {}s buckets into one with {}s buckets",
+                other.bucket_seconds, self.bucket_seconds
+            ));
+        }
+        if (&self.dialect, &self.compat) != (&other.dialect, &other.compat) {
+            return Err(format!(
+                "Cannot merge a {} registry (compat {}) into a {} registry (compat {})",
+                other.dialect,
+                other.compat.as_deref().unwrap_or("none"),
+                self.dialect,
+                self.compat.as_deref().unwrap_or("none"),
+            ));
+        }
+        for (hash, other) in &other.entries {
+            let entry = self.entry(hash, &other.normalized);
+            entry.count += other.count;
+            for (&bucket, &count) in &other.series {
+                *entry.series.entry(bucket).or_insert(0) += count;
+            }
+        }
+        self.unparsed += other.unparsed;
+        Ok(())
+    }
+
+    pub fn to_jsonl(&self) -> String {
+        let header = json!({
+            "sqlfp_registry": FORMAT,
+            "sqlfp_version": env!("CARGO_PKG_VERSION"),
+            "fingerprint_version": FINGERPRINT_VERSION,
+            "dialect": self.dialect,
+            "compat": self.compat,
+            "bucket_seconds": self.bucket_seconds,
+            "unparsed": self.unparsed,
+        });
+        let mut out = header.to_string();
+        out.push('\n');
+        for (hash, entry) in &self.entries {
+            let series: Vec<Value> = entry.series.iter().map(|(bucket, count)| json!([bucket, count])).collect();
+            let line = json!({ "hash": hash, "normalized": entry.normalized, "count": entry.count, "series": series });
+            out.push_str(&line.to_string());
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn from_jsonl(text: &str) -> Result<Registry, String> {
+        let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        let header = match lines.next() {
+            Some((_, line)) => serde_json::from_str::<Value>(line).map_err(|e| format!("line 1: Invalid JSON: {}", e))?,
+            None => return Err("Empty registry checkpoint".to_string()),
+        };
+        match header.get("sqlfp_registry").and_then(Value::as_u64) {
+            Some(FORMAT) => {}
+            Some(version) => return Err(format!("Unsupported registry checkpoint format: {}", version)),
+            None => return Err("Not a registry checkpoint".to_string()),
+        }
+        // Checkpoints predating the field were written by version 1
+        let fingerprint_version = header.get("fingerprint_version").and_then(Value::as_u64).unwrap_or(1);
+        if fingerprint_version != FINGERPRINT_VERSION {
+            return Err(format!(
+                "Registry checkpoint has fingerprint version {}, not {}: its hashes are not comparable",
+                fingerprint_version, FINGERPRINT_VERSION
+            ));
+        }
+        let dialect = header.get("dialect").and_then(Value::as_str).ok_or("line 1: Missing field 'dialect'")?;
+        let compat = header.get("compat").and_then(Value::as_str);
+        let bucket_seconds = header.get("bucket_seconds").and_then(Value::as_u64).unwrap_or(DEFAULT_BUCKET_SECONDS);
+        let mut registry = Registry::new(dialect, compat, bucket_seconds).map_err(|e| format!("line 1: {}", e))?;
+        registry.unparsed = header.get("unparsed").and_then(Value::as_u64).unwrap_or(0);
+        for (i, line) in lines {
+            let value: Value = serde_json::from_str(line).map_err(|e| format!("line {}: Invalid JSON: {}", i + 1, e))?;
+            let field = |key: &str| value.get(key).ok_or_else(|| format!("line {}: Missing field '{}'", i + 1, key));
+            let (Some(hash), Some(normalized), Some(count)) =
+                (field("hash")?.as_str(), field("normalized")?.as_str(), field("count")?.as_u64())
+            else {
+                return Err(format!("line {}: Invalid entry", i + 1));
+            };
+            let entry = registry.entry(hash, normalized);
+            entry.count += count;
+            for point in value.get("series").and_then(Value::as_array).into_iter().flatten() {
+                let (Some(bucket), Some(count)) = (point.get(0).and_then(Value::as_i64), point.get(1).and_then(Value::as_u64))
+                else {
+                    return Err(format!("line {}: Invalid series point", i + 1));
+                };
+                *entry.series.entry(bucket).or_insert(0) += count;
+            }
+        }
+        Ok(registry)
+    }
+}
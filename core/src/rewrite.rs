@@ -0,0 +1,310 @@
+//! Declarative rewrite rules applied to a parsed statement before it is
+//! re-serialized, as used by `rewrite()`, and the table and schema renaming
+//! of `retarget()`.
+
+use core::ops::ControlFlow;
+use sqlparser::ast::{
+    BinaryOperator, Expr, Ident, ObjectName, ObjectNamePart, Query, SetExpr, Statement,
+    TableFactor, Visit, VisitMut, Visitor, VisitorMut,
+};
+
+use crate::scope::Scopes;
+
+/// A dotted name split into its parts, e.g. `"sales.orders"` → `["sales", "orders"]`
+pub type QualifiedName = Vec<String>;
+
+pub enum Rule {
+    /// Rename a table wherever it is referenced; `from` matches name suffixes,
+    /// so `orders` also matches `sales.orders`
+    RenameTable { from: QualifiedName, to: QualifiedName },
+    /// Replace the schema part of qualified table and column references
+    ReplaceSchema { from: String, to: String },
+    /// Remove every top-level AND-ed WHERE condition that references the column
+    DropPredicate { column: QualifiedName },
+}
+
+pub fn parse_name(name: &str) -> QualifiedName {
+    name.split('.').map(|part| part.trim().to_string()).collect()
+}
+
+/// Unquoted identifiers compare case-insensitively, quoted ones exactly
+fn ident_matches(ident: &Ident, name: &str) -> bool {
+    if ident.quote_style.is_some() {
+        ident.value == name
+    } else {
+        ident.value.eq_ignore_ascii_case(name)
+    }
+}
+
+fn ends_with(parts: &[Ident], suffix: &[String]) -> bool {
+    parts.len() >= suffix.len()
+        && parts[parts.len() - suffix.len()..]
+            .iter()
+            .zip(suffix)
+            .all(|(ident, name)| ident_matches(ident, name))
+}
+
+/// Replace the last `suffix_len` parts of `parts` with `replacement`
+fn replace_suffix(parts: &mut Vec<Ident>, suffix_len: usize, replacement: &[String]) {
+    parts.truncate(parts.len() - suffix_len);
+    parts.extend(replacement.iter().map(|name| Ident::new(name.clone())));
+}
+
+fn object_name_idents(name: &ObjectName) -> Option<Vec<Ident>> {
+    name.0
+        .iter()
+        .map(|part| match part {
+            ObjectNamePart::Identifier(ident) => Some(ident.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Aliases declared anywhere in the statement; column qualifiers naming one of
+/// these refer to the alias, not to a table, and are left alone.
+fn table_aliases(stmt: &Statement) -> Vec<Ident> {
+    struct Aliases(Vec<Ident>);
+
+    impl Visitor for Aliases {
+        type Break = ();
+
+        fn pre_visit_table_factor(&mut self, tf: &TableFactor) -> ControlFlow<()> {
+            if let TableFactor::Table { alias: Some(alias), .. }
+            | TableFactor::Derived { alias: Some(alias), .. } = tf
+            {
+                self.0.push(alias.name.clone());
+            }
+            ControlFlow::Continue(())
+        }
+    }
+
+    let mut aliases = Aliases(Vec::new());
+    let _ = stmt.visit(&mut aliases);
+    aliases.0
+}
+
+struct Renamer<'a> {
+    rule: &'a Rule,
+    aliases: Vec<Ident>,
+}
+
+impl Renamer<'_> {
+    fn rename(&self, parts: &mut Vec<Ident>, is_column: bool) {
+        // Column references end with the column name; only their qualifier is a table name
+        let qualifier_len = if is_column { parts.len() - 1 } else { parts.len() };
+        let column = if is_column { parts.pop() } else { None };
+        match self.rule {
+            Rule::RenameTable { from, to } => {
+                let aliased = is_column
+                    && qualifier_len == 1
+                    && self.aliases.iter().any(|a| ident_matches(a, &parts[0].value));
+                if !aliased && ends_with(parts, from) {
+                    replace_suffix(parts, from.len(), to);
+                }
+            }
+            Rule::ReplaceSchema { from, to } => {
+                if qualifier_len >= 2 && ident_matches(&parts[qualifier_len - 2], from) {
+                    parts[qualifier_len - 2] = Ident::new(to.clone());
+                }
+            }
+            Rule::DropPredicate { .. } => {}
+        }
+        parts.extend(column);
+    }
+}
+
+impl VisitorMut for Renamer<'_> {
+    type Break = ();
+
+    fn post_visit_relation(&mut self, relation: &mut ObjectName) -> ControlFlow<()> {
+        if let Some(mut parts) = object_name_idents(relation) {
+            self.rename(&mut parts, false);
+            *relation = ObjectName::from(parts);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<()> {
+        if let Expr::CompoundIdentifier(ref mut parts) = expr {
+            self.rename(parts, true);
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+struct Retargeter<'a> {
+    mapping: &'a [(QualifiedName, QualifiedName)],
+    scopes: Scopes,
+    aliases: Vec<Ident>,
+}
+
+impl Retargeter<'_> {
+    /// The entry renaming `parts`: the one with the longest `from` that ends
+    /// them
+    fn entry(&self, parts: &[Ident]) -> Option<&(QualifiedName, QualifiedName)> {
+        self.mapping.iter().filter(|(from, _)| ends_with(parts, from)).max_by_key(|(from, _)| from.len())
+    }
+
+    /// Rename the table name `parts`, or else its schema qualifier. A
+    /// qualified replacement also replaces the qualifiers it spells out, so
+    /// that `orders` → `shadow.orders` turns `sales.orders` into
+    /// `shadow.orders`
+    fn retarget(&self, parts: &mut Vec<Ident>) {
+        if let Some((from, to)) = self.entry(parts) {
+            replace_suffix(parts, from.len().max(to.len()).min(parts.len()), to);
+        } else if let Some(table) = parts.pop() {
+            if let Some((from, to)) = self.entry(parts) {
+                replace_suffix(parts, from.len().max(to.len()).min(parts.len()), to);
+            }
+            parts.push(table);
+        }
+    }
+
+    fn is_local(&self, parts: &[Ident], names: &[Ident]) -> bool {
+        matches!(parts, [name] if names.iter().any(|n| ident_matches(n, &name.value)))
+    }
+}
+
+impl VisitorMut for Retargeter<'_> {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        self.scopes.enter(query);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        self.scopes.leave(query);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_relation(&mut self, relation: &mut ObjectName) -> ControlFlow<()> {
+        if let Some(mut parts) = object_name_idents(relation) {
+            if !self.scopes.qualifies_cte(&parts) {
+                self.retarget(&mut parts);
+                *relation = ObjectName::from(parts);
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<()> {
+        if let Expr::CompoundIdentifier(ref mut parts) = expr {
+            let Some(column) = parts.pop() else {
+                return ControlFlow::Continue(());
+            };
+            if !self.is_local(parts, &self.aliases) && !self.scopes.qualifies_cte(parts) {
+                self.retarget(parts);
+            }
+            parts.push(column);
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Rename the tables and schemas of `stmt` per `mapping`, all at once. An
+/// entry renames the tables whose name it ends, or failing that the schema
+/// qualifier it ends; the longest match wins.
+/// References to the CTEs in scope and column qualifiers naming an alias are
+/// left alone.
+pub fn retarget(stmt: &mut Statement, mapping: &[(QualifiedName, QualifiedName)]) {
+    let aliases = table_aliases(stmt);
+    let _ = stmt.visit(&mut Retargeter { mapping, scopes: Scopes::default(), aliases });
+}
+
+fn references_column(expr: &Expr, column: &[String]) -> bool {
+    let mut found = false;
+    let _ = sqlparser::ast::visit_expressions(expr, |e| {
+        found |= match e {
+            Expr::Identifier(ident) => column.len() == 1 && ident_matches(ident, &column[0]),
+            Expr::CompoundIdentifier(parts) => ends_with(parts, column),
+            _ => false,
+        };
+        if found {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    });
+    found
+}
+
+/// Split an AND chain into its conjuncts, looking through parentheses
+fn conjuncts(expr: Expr, out: &mut Vec<Expr>) {
+    match expr {
+        Expr::BinaryOp { left, op: BinaryOperator::And, right } => {
+            conjuncts(*left, out);
+            conjuncts(*right, out);
+        }
+        Expr::Nested(inner) if matches!(*inner, Expr::BinaryOp { op: BinaryOperator::And, .. }) => {
+            conjuncts(*inner, out);
+        }
+        other => out.push(other),
+    }
+}
+
+fn drop_predicates(selection: &mut Option<Expr>, column: &[String]) {
+    let Some(expr) = selection.take() else {
+        return;
+    };
+    let mut parts = Vec::new();
+    conjuncts(expr, &mut parts);
+    *selection = parts
+        .into_iter()
+        .filter(|e| !references_column(e, column))
+        .reduce(|left, right| Expr::BinaryOp {
+            left: Box::new(left),
+            op: BinaryOperator::And,
+            right: Box::new(right),
+        });
+}
+
+struct PredicateDropper<'a> {
+    column: &'a [String],
+}
+
+impl PredicateDropper<'_> {
+    fn set_expr(&self, body: &mut SetExpr) {
+        match body {
+            SetExpr::Select(select) => drop_predicates(&mut select.selection, self.column),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.set_expr(left);
+                self.set_expr(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl VisitorMut for PredicateDropper<'_> {
+    type Break = ();
+
+    fn pre_visit_statement(&mut self, stmt: &mut Statement) -> ControlFlow<()> {
+        match stmt {
+            Statement::Update(update) => drop_predicates(&mut update.selection, self.column),
+            Statement::Delete(delete) => drop_predicates(&mut delete.selection, self.column),
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        self.set_expr(&mut query.body);
+        ControlFlow::Continue(())
+    }
+}
+
+/// Apply `rules` to `stmt`, in order
+pub fn apply(stmt: &mut Statement, rules: &[Rule]) {
+    for rule in rules {
+        match rule {
+            Rule::RenameTable { .. } | Rule::ReplaceSchema { .. } => {
+                let aliases = table_aliases(stmt);
+                let _ = stmt.visit(&mut Renamer { rule, aliases });
+            }
+            Rule::DropPredicate { column } => {
+                let _ = stmt.visit(&mut PredicateDropper { column });
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Extraction of routing keys, for the `routing_columns` option and
+//! `metadata["routing_keys"]`.
+//!
+//! Sharded and multi-tenant deployments route a statement by the value it
+//! binds to a tenant or partition column, which parameterization removes from
+//! the fingerprint. Before literals are replaced, the first literal compared
+//! with `=` to each of the given columns (`tenant_id = 42`, `42 = t.tenant_id`)
+//! or inserted into it (first row of `INSERT ... VALUES`, MySQL's `INSERT ...
+//! SET`) is taken. Columns match by name, whatever their qualifier, ignoring
+//! case; bind placeholders are not literals and are skipped.
+
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    visit_expressions, AssignmentTarget, BinaryOperator, Expr, Ident, SetExpr, Statement, UnaryOperator, Value,
+};
+
+use crate::metadata::{MetaValue, Metadata};
+
+/// The routing column `expr` names, as given in `columns`
+fn routing_column<'a>(expr: &Expr, columns: &'a [String]) -> Option<&'a str> {
+    let ident = match expr {
+        Expr::Identifier(ident) => ident,
+        Expr::CompoundIdentifier(idents) => idents.last()?,
+        _ => return None,
+    };
+    named(ident, columns)
+}
+
+fn named<'a>(ident: &Ident, columns: &'a [String]) -> Option<&'a str> {
+    columns.iter().find(|column| ident.value.eq_ignore_ascii_case(column)).map(String::as_str)
+}
+
+fn number(n: &str) -> MetaValue {
+    n.parse().map_or_else(|_| n.into(), MetaValue::Int)
+}
+
+/// Strings as their value, integers as numbers, other numbers as written
+fn literal(expr: &Expr) -> Option<MetaValue> {
+    match expr {
+        Expr::Value(value) => match &value.value {
+            Value::SingleQuotedString(s) | Value::DoubleQuotedString(s) | Value::NationalStringLiteral(s) => {
+                Some(s.clone().into())
+            }
+            Value::Number(n, _) => Some(number(n)),
+            _ => None,
+        },
+        Expr::UnaryOp { op: UnaryOperator::Minus, expr } => match &**expr {
+            Expr::Value(value) => match &value.value {
+                Value::Number(n, _) => Some(number(&format!("-{n}"))),
+                _ => None,
+            },
+            _ => None,
+        },
+        Expr::Nested(expr) => literal(expr),
+        _ => None,
+    }
+}
+
+/// Add `column: value` unless `column` already has a value
+fn bind(keys: &mut Metadata, column: Option<&str>, value: Option<MetaValue>) {
+    if let (Some(column), Some(value)) = (column, value) {
+        if !keys.iter().any(|(key, _)| key == column) {
+            keys.push((column.to_string(), value));
+        }
+    }
+}
+
+/// `metadata["routing_keys"]`, present only when `stmt` binds a literal to
+/// one of `columns`
+pub fn routing_keys(stmt: &Statement, columns: &[String]) -> Option<(String, MetaValue)> {
+    let mut keys = Metadata::new();
+    if let Statement::Insert(insert) = stmt {
+        if let Some(SetExpr::Values(values)) = insert.source.as_ref().map(|source| &*source.body) {
+            if let Some(row) = values.rows.first() {
+                for (column, value) in insert.columns.iter().zip(row) {
+                    bind(&mut keys, named(column, columns), literal(value));
+                }
+            }
+        }
+        for assignment in &insert.assignments {
+            if let AssignmentTarget::ColumnName(name) = &assignment.target {
+                let column = name.0.last().and_then(|part| part.as_ident()).and_then(|ident| named(ident, columns));
+                bind(&mut keys, column, literal(&assignment.value));
+            }
+        }
+    }
+    let _ = visit_expressions(stmt, |expr| {
+        if let Expr::BinaryOp { left, op: BinaryOperator::Eq, right } = expr {
+            match routing_column(left, columns) {
+                Some(column) => bind(&mut keys, Some(column), literal(right)),
+                None => bind(&mut keys, routing_column(right, columns), literal(left)),
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    (!keys.is_empty()).then(|| ("routing_keys".to_string(), MetaValue::Map(keys)))
+}
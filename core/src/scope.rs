@@ -0,0 +1,127 @@
+//! Scope-aware resolution of CTE names, telling references to a CTE apart
+//! from those to a table of the same name.
+//!
+//! A CTE is visible in the body of the query defining it and in the CTEs
+//! defined after it in the same `WITH`; with `WITH RECURSIVE`, in all the CTEs
+//! of that `WITH`, its own included. In
+//! `WITH orders AS (SELECT * FROM orders WHERE paid) SELECT * FROM orders`,
+//! the inner `orders` is the table and the outer one the CTE. A nested `WITH`
+//! shadows the CTEs of the enclosing ones, and a name defined twice in one
+//! `WITH` refers to its latest definition visible. Only unqualified names
+//! refer to CTEs, matched case-insensitively unless quoted.
+//!
+//! Visitors call [`Scopes::enter`] and [`Scopes::leave`] from their
+//! `pre_visit_query` and `post_visit_query`, and resolve names in between.
+
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{Ident, ObjectName, ObjectNamePart, Query, Statement, Visit, Visitor};
+
+use crate::cte::key;
+
+/// The CTEs of one `WITH`
+struct Scope {
+    query: *const Query,
+    /// Name keys, in definition order
+    names: Vec<String>,
+    bodies: Vec<*const Query>,
+    recursive: bool,
+    /// Number of leading `names` visible where the visit stands
+    visible: usize,
+}
+
+/// The `WITH` clauses enclosing the node being visited, innermost last
+#[derive(Default)]
+pub struct Scopes {
+    stack: Vec<Scope>,
+}
+
+impl Scopes {
+    /// Enter `query`, from `pre_visit_query`
+    pub fn enter(&mut self, query: &Query) {
+        let ptr: *const Query = query;
+        if let Some(scope) = self.stack.last_mut() {
+            if let Some(i) = scope.bodies.iter().position(|&body| body == ptr) {
+                if !scope.recursive {
+                    scope.visible = i;
+                }
+            }
+        }
+        if let Some(ref with) = query.with {
+            let names: Vec<String> = with.cte_tables.iter().map(|cte| key(&cte.alias.name)).collect();
+            self.stack.push(Scope {
+                query: ptr,
+                visible: if with.recursive { names.len() } else { 0 },
+                names,
+                bodies: with.cte_tables.iter().map(|cte| &*cte.query as *const Query).collect(),
+                recursive: with.recursive,
+            });
+        }
+    }
+
+    /// Leave `query`, from `post_visit_query`
+    pub fn leave(&mut self, query: &Query) {
+        let ptr: *const Query = query;
+        if self.stack.last().is_some_and(|scope| scope.query == ptr) {
+            self.stack.pop();
+        }
+        if let Some(scope) = self.stack.last_mut() {
+            if let Some(i) = scope.bodies.iter().position(|&body| body == ptr) {
+                if !scope.recursive {
+                    scope.visible = i + 1;
+                }
+            }
+        }
+    }
+
+    /// The CTE `ident` refers to, as the depth of its `WITH` among those
+    /// entered and its position in it
+    pub fn resolve(&self, ident: &Ident) -> Option<(usize, usize)> {
+        let key = key(ident);
+        self.stack.iter().enumerate().rev().find_map(|(depth, scope)| {
+            scope.names[..scope.visible].iter().rposition(|name| *name == key).map(|i| (depth, i))
+        })
+    }
+
+    /// Whether the relation `name` refers to a CTE rather than a table
+    pub fn is_cte(&self, name: &ObjectName) -> bool {
+        matches!(name.0.as_slice(), [ObjectNamePart::Identifier(ident)] if self.resolve(ident).is_some())
+    }
+
+    /// Whether the qualifier `parts` (of a column or wildcard) names a CTE
+    pub fn qualifies_cte(&self, parts: &[Ident]) -> bool {
+        matches!(parts, [ident] if self.resolve(ident).is_some())
+    }
+}
+
+/// Like sqlparser's `visit_relations`, but only over the references to
+/// tables, skipping those to CTEs
+pub fn visit_tables<B>(stmt: &Statement, f: impl FnMut(&ObjectName) -> ControlFlow<B>) -> ControlFlow<B> {
+    struct Tables<F> {
+        scopes: Scopes,
+        f: F,
+    }
+
+    impl<B, F: FnMut(&ObjectName) -> ControlFlow<B>> Visitor for Tables<F> {
+        type Break = B;
+
+        fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<B> {
+            self.scopes.enter(query);
+            ControlFlow::Continue(())
+        }
+
+        fn post_visit_query(&mut self, query: &Query) -> ControlFlow<B> {
+            self.scopes.leave(query);
+            ControlFlow::Continue(())
+        }
+
+        fn pre_visit_relation(&mut self, relation: &ObjectName) -> ControlFlow<B> {
+            if self.scopes.is_cte(relation) {
+                return ControlFlow::Continue(());
+            }
+            (self.f)(relation)
+        }
+    }
+
+    stmt.visit(&mut Tables { scopes: Scopes::default(), f })
+}
@@ -0,0 +1,187 @@
+//! Script splitting and session context, for `normalize_script()`.
+//!
+//! Scripts and session logs hold many statements, separated by `;` and, in
+//! SQL Server tooling, by `GO` batch separators: a line holding only `GO`,
+//! optionally followed by a repeat count, which is not SQL and is dropped.
+//! Separators are found on the token stream, so a `;` or `GO` inside a string
+//! literal, quoted identifier or comment does not split.
+//!
+//! Along the way, `USE db` (`USE db.schema` also setting the schema) and
+//! `SET search_path TO a, b` change the database and the schemas that bare
+//! table names resolve against. Each statement gets the context the preceding
+//! ones set up, so that consumers can qualify the tables it names; switching
+//! databases forgets the schemas.
+
+use sqlparser::ast::{Expr, ObjectName, Set, Statement, Use};
+use sqlparser::dialect::Dialect;
+use sqlparser::keywords::Keyword;
+use sqlparser::tokenizer::{Token, TokenWithSpan, Tokenizer, Whitespace};
+
+use crate::metadata::MetaValue;
+use crate::paramstyle::token_spans;
+
+/// A statement of a script, as written
+pub struct Piece {
+    pub sql: String,
+    /// 1-based line the statement starts on
+    pub line: usize,
+    /// 1-based index of the `GO` batch the statement is in
+    pub batch: usize,
+    /// Whether it may change the context: it starts with `USE` or `SET`
+    pub sets_context: bool,
+}
+
+fn is_space(token: &Token) -> bool {
+    matches!(token, Token::Whitespace(Whitespace::Space | Whitespace::Tab))
+}
+
+fn ends_line(token: &Token) -> bool {
+    match token {
+        Token::Whitespace(Whitespace::Newline) => true,
+        Token::Whitespace(Whitespace::SingleLineComment { comment, .. }) => comment.ends_with('\n'),
+        _ => false,
+    }
+}
+
+/// The number of tokens of the `GO` separator line starting at token `i`, if
+/// there is one: `GO` alone on its line, save for a repeat count and a comment
+fn batch_separator(tokens: &[TokenWithSpan], i: usize) -> Option<usize> {
+    let Token::Word(word) = &tokens[i].token else {
+        return None;
+    };
+    if word.quote_style.is_some() || !word.value.eq_ignore_ascii_case("GO") {
+        return None;
+    }
+    let line_start = tokens[..i].iter().rev().find(|t| !is_space(&t.token)).is_none_or(|t| ends_line(&t.token));
+    if !line_start {
+        return None;
+    }
+    let mut end = i + 1;
+    let mut count = false;
+    while let Some(token) = tokens.get(end).map(|t| &t.token) {
+        end += 1;
+        match token {
+            _ if is_space(token) => {}
+            Token::Number(..) if !count => count = true,
+            _ if ends_line(token) => return Some(end - i),
+            Token::Whitespace(Whitespace::MultiLineComment(_)) => {}
+            _ => return None,
+        }
+    }
+    Some(end - i)
+}
+
+/// The statements of `script`, without their terminating `;`, skipping empty
+/// ones and those holding only comments
+pub fn split(script: &str, dialect: &dyn Dialect) -> Result<Vec<Piece>, String> {
+    let tokens = Tokenizer::new(dialect, script)
+        .with_unescape(false)
+        .tokenize_with_location()
+        .map_err(|e| format!("Tokenize error: {}", e))?;
+    let (offsets, hints) = token_spans(script, &tokens, dialect);
+    let end_of = |i: usize| offsets.get(i).copied().unwrap_or(script.len());
+    // A statement starting or ending in a MySQL `/*! ... */` hint takes the
+    // whole comment
+    let start_of = |first: usize| hints[first].map_or(end_of(first), |(from, _)| from);
+    let stop_of = |last: usize| hints[last].map_or(end_of(last + 1), |(_, to)| to);
+
+    let mut pieces = Vec::new();
+    let mut batch = 1;
+    // First and last significant token of the statement being read
+    let mut current: Option<(usize, usize)> = None;
+    let mut flush = |current: &mut Option<(usize, usize)>, batch: usize| {
+        if let Some((first, last)) = current.take() {
+            let sets_context = matches!(
+                &tokens[first].token,
+                Token::Word(word) if word.quote_style.is_none() && matches!(word.keyword, Keyword::USE | Keyword::SET)
+            );
+            pieces.push(Piece {
+                sql: script[start_of(first)..stop_of(last)].to_string(),
+                line: tokens[first].span.start.line as usize,
+                batch,
+                sets_context,
+            });
+        }
+    };
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i].token {
+            Token::SemiColon => flush(&mut current, batch),
+            Token::Whitespace(_) => {}
+            _ => match batch_separator(&tokens, i) {
+                Some(len) => {
+                    flush(&mut current, batch);
+                    batch += 1;
+                    i += len;
+                    continue;
+                }
+                None => current = Some((current.map_or(i, |(first, _)| first), i)),
+            },
+        }
+        i += 1;
+    }
+    flush(&mut current, batch);
+    Ok(pieces)
+}
+
+/// The parts of `name`, unquoted
+fn parts(name: &ObjectName) -> Vec<String> {
+    name.0.iter().map(|part| part.as_ident().map_or_else(|| part.to_string(), |ident| ident.value.clone())).collect()
+}
+
+/// A `search_path` entry: a bare or quoted name, or a string
+fn schema_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Identifier(ident) => Some(ident.value.clone()),
+        Expr::Value(value) => value.value.clone().into_string(),
+        _ => None,
+    }
+}
+
+/// The database and search path in effect at a point of a script
+#[derive(Default)]
+pub struct Context {
+    database: Option<String>,
+    search_path: Option<Vec<String>>,
+}
+
+impl Context {
+    /// Apply the context change `stmt` makes, if any
+    pub fn observe(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Use(Use::Object(name) | Use::Database(name)) => match parts(name).as_slice() {
+                [database, schema] => {
+                    self.database = Some(database.clone());
+                    self.search_path = Some(vec![schema.clone()]);
+                }
+                parts => {
+                    // The schemas of another database are not those of this one
+                    self.database = Some(parts.join("."));
+                    self.search_path = None;
+                }
+            },
+            Statement::Set(Set::SingleAssignment { variable, values, .. })
+                if parts(variable).last().is_some_and(|name| name.eq_ignore_ascii_case("search_path")) =>
+            {
+                self.search_path = match values.as_slice() {
+                    [Expr::Identifier(ident)] if ident.quote_style.is_none() && ident.value.eq_ignore_ascii_case("DEFAULT") => None,
+                    values => Some(values.iter().filter_map(schema_name).collect()),
+                };
+            }
+            _ => {}
+        }
+    }
+
+    /// `metadata["database"]` and `metadata["search_path"]`, each present only
+    /// once the script has set it
+    pub fn metadata(&self) -> Vec<(String, MetaValue)> {
+        let mut out = Vec::new();
+        if let Some(ref database) = self.database {
+            out.push(("database".to_string(), database.clone().into()));
+        }
+        if let Some(ref search_path) = self.search_path {
+            out.push(("search_path".to_string(), MetaValue::List(search_path.iter().cloned().map(Into::into).collect())));
+        }
+        out
+    }
+}
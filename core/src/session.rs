@@ -0,0 +1,232 @@
+//! Per-connection grouping for `SessionTracker`.
+//!
+//! Query logs interleave the statements of many connections. Grouping them
+//! by connection tells which application session produced which statement
+//! shapes, and under which context: the database selected with `USE` and the
+//! variables assigned with `SET`, which change what a statement means without
+//! appearing in it. Context statements update their session's context rather
+//! than being counted as fingerprints, and each fingerprint is counted per
+//! database it ran against.
+
+use std::collections::{BTreeMap, HashMap};
+
+use sqlparser::ast::{ContextModifier, Expr, ObjectName, Set, Statement, Use};
+
+/// A connection id as given, a string or an integer
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ConnectionId {
+    Int(i64),
+    Str(String),
+}
+
+/// What `USE` and `SET` have set on a session
+#[derive(Default)]
+pub struct Context {
+    pub database: Option<String>,
+    /// Values by variable name, lowercased, as normalized SQL
+    pub settings: BTreeMap<String, String>,
+}
+
+/// A change of context
+enum Change {
+    Database(String),
+    Setting(String, String),
+}
+
+/// The changes `stmt` makes to the context of its session; `None` if it is
+/// not a context statement. `SET GLOBAL` leaves the session as it is.
+fn changes(stmt: &Statement) -> Option<Vec<Change>> {
+    let setting = |name: &str, value: String| Change::Setting(name.to_string(), value);
+    let assignment = |scope: Option<ContextModifier>, name: &ObjectName, values: &[&Expr]| {
+        let name = name.to_string().to_lowercase();
+        if scope == Some(ContextModifier::Global) || name.starts_with("@@global.") {
+            return None;
+        }
+        let name = name.strip_prefix("@@session.").or_else(|| name.strip_prefix("@@local.")).unwrap_or(&name);
+        let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        Some(setting(name.strip_prefix("@@").unwrap_or(name), values.join(", ")))
+    };
+    Some(match stmt {
+        Statement::Use(Use::Database(name) | Use::Schema(name) | Use::Object(name)) => {
+            vec![Change::Database(name.to_string())]
+        }
+        // Hive's database named `default`
+        Statement::Use(Use::Default) => vec![Change::Database("default".to_string())],
+        Statement::Use(Use::Catalog(name)) => vec![setting("catalog", name.to_string())],
+        Statement::Use(Use::Warehouse(name)) => vec![setting("warehouse", name.to_string())],
+        Statement::Use(Use::Role(name)) => vec![setting("role", name.to_string())],
+        Statement::Use(other @ Use::SecondaryRoles(_)) => {
+            vec![setting("secondary_roles", other.to_string().trim_start_matches("USE SECONDARY ROLES ").to_string())]
+        }
+        Statement::Set(Set::SingleAssignment { scope, variable, values, .. }) => {
+            assignment(*scope, variable, &values.iter().collect::<Vec<_>>()).into_iter().collect()
+        }
+        Statement::Set(Set::ParenthesizedAssignments { variables, values }) => variables
+            .iter()
+            .zip(values)
+            .filter_map(|(variable, value)| assignment(None, variable, &[value]))
+            .collect(),
+        Statement::Set(Set::MultipleAssignments { assignments }) => assignments
+            .iter()
+            .filter_map(|a| assignment(a.scope, &a.name, &[&a.value]))
+            .collect(),
+        Statement::Set(Set::SetNames { charset_name, .. }) => vec![setting("names", charset_name.to_string())],
+        Statement::Set(Set::SetNamesDefault {}) => vec![setting("names", "DEFAULT".to_string())],
+        Statement::Set(Set::SetTimeZone { value, .. }) => vec![setting("time_zone", value.to_string())],
+        Statement::Set(Set::SetRole { role_name, .. }) => {
+            vec![setting("role", role_name.as_ref().map_or_else(|| "NONE".to_string(), ToString::to_string))]
+        }
+        Statement::Set(Set::SetTransaction { modes, .. }) => {
+            let modes: Vec<String> = modes.iter().map(ToString::to_string).collect();
+            vec![setting("transaction", modes.join(", "))]
+        }
+        Statement::Set(Set::SetSessionAuthorization(param)) => vec![setting("session_authorization", param.to_string())],
+        // SQL Server's `SET NOCOUNT ON` and the like
+        Statement::Set(Set::SetSessionParam(param)) => {
+            let text = param.to_string();
+            let (name, value) = text.rsplit_once(' ').unwrap_or((&text, ""));
+            vec![setting(&name.to_lowercase(), value.to_string())]
+        }
+        _ => return None,
+    })
+}
+
+pub struct Fingerprint {
+    pub hash: String,
+    pub normalized: String,
+    /// The database of the session when the statement ran
+    pub database: Option<String>,
+    pub count: u64,
+    pub first_seen: Option<f64>,
+    pub last_seen: Option<f64>,
+}
+
+pub struct Session {
+    /// Rank of the session among those observed, to list them in order
+    order: u64,
+    pub context: Context,
+    pub first_seen: Option<f64>,
+    pub last_seen: Option<f64>,
+    /// Statements observed, context and unparsed ones included
+    pub statements: u64,
+    pub unparsed: u64,
+    /// In order of first observation
+    pub fingerprints: Vec<Fingerprint>,
+    /// Index in `fingerprints` by hash and database
+    index: HashMap<(String, Option<String>), usize>,
+}
+
+impl Session {
+    fn new(order: u64) -> Session {
+        Session {
+            order,
+            context: Context::default(),
+            first_seen: None,
+            last_seen: None,
+            statements: 0,
+            unparsed: 0,
+            fingerprints: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    fn seen(&mut self, timestamp: Option<f64>) {
+        self.statements += 1;
+        widen(&mut self.first_seen, &mut self.last_seen, timestamp);
+    }
+}
+
+/// Widen the span from `first` to `last` to cover `timestamp`, if given
+fn widen(first: &mut Option<f64>, last: &mut Option<f64>, timestamp: Option<f64>) {
+    if let Some(timestamp) = timestamp {
+        *first = Some(first.map_or(timestamp, |first| first.min(timestamp)));
+        *last = Some(last.map_or(timestamp, |last| last.max(timestamp)));
+    }
+}
+
+/// The sessions of the connections observed
+#[derive(Default)]
+pub struct Sessions {
+    sessions: HashMap<ConnectionId, Session>,
+    /// Sessions started so far, closed ones included
+    started: u64,
+}
+
+impl Sessions {
+    fn session(&mut self, connection: &ConnectionId) -> &mut Session {
+        let started = &mut self.started;
+        self.sessions.entry(connection.clone()).or_insert_with(|| {
+            *started += 1;
+            Session::new(*started)
+        })
+    }
+
+    /// The open sessions, in the order they started
+    pub fn list(&self) -> Vec<(&ConnectionId, &Session)> {
+        let mut sessions: Vec<_> = self.sessions.iter().collect();
+        sessions.sort_by_key(|(_, session)| session.order);
+        sessions
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// Apply `stmt` to the context of `connection` if it is a context
+    /// statement; whether it was
+    pub fn apply_context(&mut self, connection: &ConnectionId, timestamp: Option<f64>, stmt: &Statement) -> bool {
+        let Some(changes) = changes(stmt) else {
+            return false;
+        };
+        let session = self.session(connection);
+        session.seen(timestamp);
+        for change in changes {
+            match change {
+                Change::Database(database) => session.context.database = Some(database),
+                Change::Setting(name, value) => {
+                    session.context.settings.insert(name, value);
+                }
+            }
+        }
+        true
+    }
+
+    /// Count the statement fingerprinted `hash` on `connection`, under the
+    /// database of its session
+    pub fn observe(&mut self, connection: &ConnectionId, timestamp: Option<f64>, hash: &str, normalized: &str) {
+        let session = self.session(connection);
+        session.seen(timestamp);
+        let key = (hash.to_string(), session.context.database.clone());
+        let i = *session.index.entry(key).or_insert_with(|| {
+            session.fingerprints.push(Fingerprint {
+                hash: hash.to_string(),
+                normalized: normalized.to_string(),
+                database: session.context.database.clone(),
+                count: 0,
+                first_seen: None,
+                last_seen: None,
+            });
+            session.fingerprints.len() - 1
+        });
+        let fingerprint = &mut session.fingerprints[i];
+        fingerprint.count += 1;
+        widen(&mut fingerprint.first_seen, &mut fingerprint.last_seen, timestamp);
+    }
+
+    /// Count a statement of `connection` that could not be parsed
+    pub fn unparsed(&mut self, connection: &ConnectionId, timestamp: Option<f64>) {
+        let session = self.session(connection);
+        session.seen(timestamp);
+        session.unparsed += 1;
+    }
+
+    /// End the session of `connection`, so that the next statement on it
+    /// starts a new one
+    pub fn close(&mut self, connection: &ConnectionId) -> Option<Session> {
+        self.sessions.remove(connection)
+    }
+}
@@ -0,0 +1,132 @@
+//! Set operations (`UNION`, `INTERSECT`, `EXCEPT`): their canonical form, and
+//! the detection of duplicate branches for `metadata["duplicate_branches"]`.
+//!
+//! `DISTINCT` is the default quantifier of every set operator, so
+//! `UNION DISTINCT` is written `UNION`, while `ALL` is kept. Parentheses around
+//! a branch are stripped unless the branch is a set operation the precedence
+//! would otherwise regroup: `INTERSECT` binds tighter than the others, and
+//! all of them group to the left, so `(a UNION b) INTERSECT c` and
+//! `a UNION (b UNION ALL c)` keep theirs. A parenthesized query with clauses
+//! of its own (`ORDER BY`, `LIMIT`, ...) keeps them too.
+//!
+//! Generated SQL sometimes repeats a branch of a `UNION` (or `INTERSECT`,
+//! `EXCEPT`) verbatim, which costs a full extra scan for nothing. Chains of the
+//! same operator and quantifier (`a UNION b UNION c`) are compared as one list
+//! of branches, a parenthesized branch comparing as its body. Branches are
+//! compared once the structural passes ran but before literals are replaced,
+//! so `SELECT 1 UNION SELECT 2` is not reported.
+
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{Query, SetExpr, SetOperator, SetQuantifier, Statement, Values, Visit, Visitor};
+
+/// Whether `query` is only its body, so that its parentheses only group
+fn bare(query: &Query) -> bool {
+    query.with.is_none()
+        && query.order_by.is_none()
+        && query.limit_clause.is_none()
+        && query.fetch.is_none()
+        && query.locks.is_empty()
+        && query.for_clause.is_none()
+        && query.settings.is_none()
+        && query.format_clause.is_none()
+        && query.pipe_operators.is_empty()
+}
+
+/// The branch `body` stands for: the body of a parenthesized query without
+/// clauses of its own, `body` itself otherwise
+fn branch(body: &SetExpr) -> &SetExpr {
+    match body {
+        SetExpr::Query(query) if bare(query) => branch(&query.body),
+        _ => body,
+    }
+}
+
+/// How tightly `op` binds, as sqlparser parses it
+fn precedence(op: &SetOperator) -> u8 {
+    match op {
+        SetOperator::Intersect => 20,
+        _ => 10,
+    }
+}
+
+/// `UNION DISTINCT` → `UNION`, `UNION DISTINCT BY NAME` → `UNION BY NAME`
+pub fn strip_distinct(quantifier: &mut SetQuantifier) {
+    match quantifier {
+        SetQuantifier::Distinct => *quantifier = SetQuantifier::None,
+        SetQuantifier::DistinctByName => *quantifier = SetQuantifier::ByName,
+        _ => {}
+    }
+}
+
+/// Strip the parentheses around `body`, the left or `right` branch of an `op`
+/// set operation, but for the pair a set operation needs to keep its place
+pub fn strip_parens(body: &mut SetExpr, op: &SetOperator, right: bool) {
+    loop {
+        let SetExpr::Query(query) = body else {
+            return;
+        };
+        if !bare(query) {
+            return;
+        }
+        if let SetExpr::SetOperation { op: inner, .. } = &*query.body {
+            if precedence(inner) < precedence(op) || (right && precedence(inner) == precedence(op)) {
+                return;
+            }
+        }
+        let empty = SetExpr::Values(Values { explicit_row: false, value_keyword: false, rows: Vec::new() });
+        *body = std::mem::replace(&mut *query.body, empty);
+    }
+}
+
+/// Push the branches of the `op`/`quantifier` chain `body` onto `out`, in
+/// order. Parenthesized chains are branches of their own, checked when their
+/// query is visited.
+fn flatten<'a>(body: &'a SetExpr, op: &SetOperator, quantifier: &SetQuantifier, out: &mut Vec<&'a SetExpr>) {
+    match body {
+        SetExpr::SetOperation { op: o, set_quantifier: q, left, right } if o == op && q == quantifier => {
+            flatten(left, op, quantifier, out);
+            flatten(right, op, quantifier, out);
+        }
+        other => out.push(other),
+    }
+}
+
+#[derive(Default)]
+struct Duplicates {
+    count: usize,
+}
+
+impl Duplicates {
+    fn check(&mut self, body: &SetExpr) {
+        let SetExpr::SetOperation { op, set_quantifier, .. } = body else {
+            return;
+        };
+        let mut branches = Vec::new();
+        flatten(body, op, set_quantifier, &mut branches);
+        for (i, b) in branches.iter().enumerate() {
+            if branches[..i].iter().any(|earlier| branch(earlier) == branch(b)) {
+                self.count += 1;
+            }
+            // Chains of another operator nested in this one
+            self.check(b);
+        }
+    }
+}
+
+impl Visitor for Duplicates {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        self.check(&query.body);
+        ControlFlow::Continue(())
+    }
+}
+
+/// Number of set operation branches in `stmt` that repeat an earlier branch
+/// of the same chain
+pub fn duplicate_branches(stmt: &Statement) -> usize {
+    let mut pass = Duplicates::default();
+    let _ = stmt.visit(&mut pass);
+    pass.count
+}
@@ -0,0 +1,145 @@
+//! Structural similarity of two normalized statements, for `similarity()`.
+//!
+//! Queries whose fingerprints differ by one extra predicate or column are
+//! near-duplicates that clustering by hash keeps apart. Both statements are
+//! normalized, turned into the node trees of `parse_ast()`, and compared by
+//! their tree edit distance (Zhang and Shasha): inserting or deleting a node
+//! costs 1, relabelling it 1 as well, or half of that between nodes of the
+//! same kind (`a = ?` and `a > ?`, or a select list with one column more).
+//! The score is 1 less the distance over the size of the larger tree, so 1
+//! for the same tree and about 0 for trees with nothing in common.
+
+use std::collections::HashSet;
+
+use sqlparser::ast::Statement;
+
+use crate::ast::{build_tree, variant_name, Node};
+use crate::clauses::clauses;
+
+/// A node's kind, and its text with that of its children left out, which
+/// holds its operator, keywords and names
+struct Label {
+    kind: String,
+    skeleton: String,
+}
+
+impl Label {
+    fn new(node: &Node) -> Label {
+        let mut skeleton = String::with_capacity(node.sql.len());
+        let mut rest = node.sql.as_str();
+        for child in &node.children {
+            if let Some(at) = rest.find(child.sql.as_str()) {
+                skeleton.push_str(&rest[..at]);
+                skeleton.push('_');
+                rest = &rest[at + child.sql.len()..];
+            }
+        }
+        skeleton.push_str(rest);
+        Label { kind: format!("{}:{}", node.category, node.kind), skeleton }
+    }
+
+    /// The cost of relabelling `self` as `other`, in half units
+    fn cost(&self, other: &Label) -> usize {
+        match (self.kind == other.kind, self.skeleton == other.skeleton) {
+            (true, true) => 0,
+            (true, false) => 1,
+            (false, _) => 2,
+        }
+    }
+}
+
+/// A tree in postorder, with the leftmost leaf under each node and the
+/// nodes that start a new leftmost path
+struct Flat {
+    labels: Vec<Label>,
+    leftmost: Vec<usize>,
+    keyroots: Vec<usize>,
+}
+
+impl Flat {
+    fn new(root: &Node) -> Flat {
+        let mut flat = Flat { labels: Vec::new(), leftmost: Vec::new(), keyroots: Vec::new() };
+        flat.add(root);
+        let mut seen = HashSet::new();
+        for i in (0..flat.labels.len()).rev() {
+            if seen.insert(flat.leftmost[i]) {
+                flat.keyroots.push(i);
+            }
+        }
+        flat.keyroots.reverse();
+        flat
+    }
+
+    /// Add `node` after its children; its index
+    fn add(&mut self, node: &Node) -> usize {
+        let children: Vec<usize> = node.children.iter().map(|child| self.add(child)).collect();
+        let leftmost = children.first().map(|&first| self.leftmost[first]);
+        self.labels.push(Label::new(node));
+        let index = self.labels.len() - 1;
+        self.leftmost.push(leftmost.unwrap_or(index));
+        index
+    }
+}
+
+/// The edit distance between `a` and `b`, in half units
+fn distance(a: &Flat, b: &Flat) -> usize {
+    const EDIT: usize = 2;
+    let mut tree = vec![vec![0; b.labels.len()]; a.labels.len()];
+    for &i in &a.keyroots {
+        for &j in &b.keyroots {
+            let (li, lj) = (a.leftmost[i], b.leftmost[j]);
+            let (m, n) = (i - li + 2, j - lj + 2);
+            let mut forest = vec![vec![0; n]; m];
+            for x in 1..m {
+                forest[x][0] = forest[x - 1][0] + EDIT;
+            }
+            for y in 1..n {
+                forest[0][y] = forest[0][y - 1] + EDIT;
+            }
+            for x in 1..m {
+                for y in 1..n {
+                    let (i1, j1) = (li + x - 1, lj + y - 1);
+                    let edit = (forest[x - 1][y] + EDIT).min(forest[x][y - 1] + EDIT);
+                    if a.leftmost[i1] == li && b.leftmost[j1] == lj {
+                        forest[x][y] = edit.min(forest[x - 1][y - 1] + a.labels[i1].cost(&b.labels[j1]));
+                        tree[i1][j1] = forest[x][y];
+                    } else {
+                        let (p, q) = (a.leftmost[i1] - li, b.leftmost[j1] - lj);
+                        forest[x][y] = edit.min(forest[p][q] + tree[i1][j1]);
+                    }
+                }
+            }
+        }
+    }
+    tree[a.labels.len() - 1][b.labels.len() - 1]
+}
+
+/// The similarity of `a` and `b`, from 0 to 1
+pub fn score(a: &Statement, b: &Statement) -> f64 {
+    let (a, b) = (Flat::new(&build_tree(a)), Flat::new(&build_tree(b)));
+    let larger = a.labels.len().max(b.labels.len());
+    1.0 - distance(&a, &b) as f64 / (2 * larger) as f64
+}
+
+/// The clauses of the outermost query that differ between `a` and `b`, in
+/// clause order, or `["statement"]` for statements of different kinds
+pub fn differing_clauses(a: &Statement, b: &Statement) -> Vec<&'static str> {
+    if variant_name(a) != variant_name(b) {
+        return vec!["statement"];
+    }
+    let (a, b) = (clauses(a), clauses(b));
+    let mut out: Vec<&'static str> = Vec::new();
+    for (name, text) in &a {
+        if !b.iter().any(|(other, other_text)| other == name && other_text == text) {
+            out.push(name);
+        }
+    }
+    for (name, _) in &b {
+        if !a.iter().any(|(other, _)| other == name) {
+            out.push(name);
+        }
+    }
+    let order = ["projection", "from", "where", "group_by", "order_by"];
+    out.sort_by_key(|name| order.iter().position(|clause| clause == name));
+    out
+}
@@ -0,0 +1,70 @@
+//! Process-wide counters exposed by `stats()`.
+//!
+//! Plain relaxed atomics: the numbers are for health metrics, not for
+//! synchronization, so a snapshot may be a few increments behind.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub static STATEMENTS_NORMALIZED: AtomicU64 = AtomicU64::new(0);
+pub static PARSE_FAILURES: AtomicU64 = AtomicU64::new(0);
+/// Engines reused from the pool rather than built for the call
+pub static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+
+/// Canonical dialect names, in the order of `DIALECT_COUNTS`
+pub const DIALECTS: [&str; 17] = [
+    "generic",
+    "ansi",
+    "mysql",
+    "postgresql",
+    "sqlite",
+    "mssql",
+    "oracle",
+    "teradata",
+    "vertica",
+    "informix",
+    "bigquery",
+    "snowflake",
+    "clickhouse",
+    "duckdb",
+    "hive",
+    "redshift",
+    "databricks",
+];
+
+static DIALECT_COUNTS: [AtomicU64; DIALECTS.len()] = [const { AtomicU64::new(0) }; DIALECTS.len()];
+
+pub fn bump(counter: &AtomicU64) {
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count one statement normalized with `dialect` (a name from `DIALECTS`)
+pub fn record_statement(dialect: &str) {
+    bump(&STATEMENTS_NORMALIZED);
+    if let Some(i) = DIALECTS.iter().position(|d| *d == dialect) {
+        bump(&DIALECT_COUNTS[i]);
+    }
+}
+
+pub struct Snapshot {
+    pub statements_normalized: u64,
+    pub parse_failures: u64,
+    pub cache_hits: u64,
+    pub dialects: Vec<(&'static str, u64)>,
+}
+
+/// Read all counters, zeroing them if `reset` is set
+pub fn snapshot(reset: bool) -> Snapshot {
+    let read = |counter: &AtomicU64| {
+        if reset {
+            counter.swap(0, Ordering::Relaxed)
+        } else {
+            counter.load(Ordering::Relaxed)
+        }
+    };
+    Snapshot {
+        statements_normalized: read(&STATEMENTS_NORMALIZED),
+        parse_failures: read(&PARSE_FAILURES),
+        cache_hits: read(&CACHE_HITS),
+        dialects: DIALECTS.iter().zip(&DIALECT_COUNTS).map(|(d, c)| (*d, read(c))).collect(),
+    }
+}
@@ -0,0 +1,537 @@
+//! Fingerprint-only streaming, for `fingerprint_stream()`.
+//!
+//! Statements too large to parse (e.g. generated multi-megabyte `INSERT`s) are
+//! tokenized incrementally from a reader, and each masked token is fed to the
+//! hasher as soon as it is complete. Neither the token vector nor an AST is
+//! ever built, so memory stays bounded by the read buffer and the longest
+//! single word or quoted identifier, whatever the size of the statement.
+//!
+//! Masking follows `literal-parameterize`: string, numeric and boolean
+//! literals become `?`, keywords are uppercased, and identifiers, operators and
+//! existing placeholders are kept. Whitespace, comments and a trailing `;` are
+//! dropped, so the fingerprint does not depend on formatting. What counts as a
+//! comment follows the tokenizer: `#` in MySQL, `--` only before whitespace in
+//! MySQL, block comments nested where the dialect nests them, and the SQL of
+//! MySQL `/*! ... */` hints read as SQL. Without an AST, none of the other
+//! normalization passes apply, so the result is not comparable to
+//! `NormalizeResult.hash`.
+//!
+//! The masker does no IO itself: it is fed chunks split anywhere, and reads
+//! the tokens they complete. A token cut off by the end of a chunk is read
+//! again from its start once the next one arrives, but for comments and
+//! string literals, whose content is skipped, which resume where they
+//! stopped, so that a chunked statement is read once whatever their length.
+//! `fingerprint` feeds it from a reader; `Fingerprinter` leaves feeding to
+//! its caller, splitting the fingerprints at each `;`.
+
+use std::any::TypeId;
+use std::io::{self, Read};
+
+use sha2::{Digest, Sha256};
+use sqlparser::dialect::{Dialect, MySqlDialect};
+use sqlparser::keywords::ALL_KEYWORDS;
+
+/// Longest `$tag$` looked ahead for when telling dollar quotes from `$`
+const MAX_DOLLAR_TAG: usize = 64;
+
+/// The bytes fed so far from `start` on. Looking past them fails with
+/// `io::ErrorKind::WouldBlock` until the input is closed, and reads `None`
+/// after.
+struct Input {
+    buf: Vec<u8>,
+    start: usize,
+    closed: bool,
+}
+
+impl Input {
+    /// Append `chunk`, dropping the bytes consumed so far
+    fn feed(&mut self, chunk: &[u8]) {
+        self.buf.drain(..self.start);
+        self.start = 0;
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// The byte `k` positions ahead, or `None` at the end of input
+    fn peek_at(&self, k: usize) -> io::Result<Option<u8>> {
+        match self.buf.get(self.start + k) {
+            Some(&b) => Ok(Some(b)),
+            None if self.closed => Ok(None),
+            None => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+
+    fn peek(&self) -> io::Result<Option<u8>> {
+        self.peek_at(0)
+    }
+
+    fn bump(&mut self, n: usize) {
+        self.start += n;
+    }
+
+    /// Consume and return the next byte
+    fn next(&mut self) -> io::Result<Option<u8>> {
+        let byte = self.peek()?;
+        self.bump(byte.is_some() as usize);
+        Ok(byte)
+    }
+}
+
+/// A construct whose content is skipped, left where the input ran out and
+/// resumed from there once more is fed. Other tokens are read again from
+/// their start.
+enum Skip {
+    LineComment,
+    /// Depth of the nested comments still open
+    BlockComment(usize),
+    /// Its quote
+    Literal(u8),
+    /// Its `$tag$` delimiter
+    Dollar(Vec<u8>),
+}
+
+fn unterminated(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Unterminated {}", what))
+}
+
+struct Masker {
+    input: Input,
+    dialect: Box<dyn Dialect + Send + Sync>,
+    hasher: Sha256,
+    /// Whether a token was hashed yet, to separate the next one
+    started: bool,
+    /// A `;` seen but not hashed yet, as it is dropped at the end
+    semicolon: bool,
+    /// Inside a MySQL `/*! ... */` hint, whose SQL is hashed
+    hint: bool,
+    /// The construct being skipped when the input ran out
+    skip: Option<Skip>,
+    /// Whether `;` ends a statement, rather than being hashed
+    split: bool,
+    /// Fingerprints of the statements ended since the last `feed`
+    statements: Vec<String>,
+    word: Vec<u8>,
+}
+
+impl Masker {
+    fn new(dialect: Box<dyn Dialect + Send + Sync>, split: bool) -> Self {
+        Masker {
+            input: Input { buf: Vec::new(), start: 0, closed: false },
+            dialect,
+            hasher: Sha256::new(),
+            started: false,
+            semicolon: false,
+            hint: false,
+            skip: None,
+            split,
+            statements: Vec::new(),
+            word: Vec::new(),
+        }
+    }
+
+    /// Start over, for a new stream
+    fn reset(&mut self) {
+        self.input = Input { buf: Vec::new(), start: 0, closed: false };
+        self.hasher.reset();
+        (self.started, self.semicolon, self.hint, self.skip) = (false, false, false, None);
+        self.statements.clear();
+    }
+
+    fn emit(&mut self, token: &[u8]) {
+        if self.semicolon {
+            self.semicolon = false;
+            self.emit(b";");
+        }
+        if self.started {
+            self.hasher.update(b" ");
+        }
+        self.started = true;
+        self.hasher.update(token);
+    }
+
+    fn is_word_start(&self, b: u8) -> bool {
+        b >= 0x80 || self.dialect.is_identifier_start(b as char)
+    }
+
+    fn is_word_part(&self, b: u8) -> bool {
+        b >= 0x80 || self.dialect.is_identifier_part(b as char)
+    }
+
+    /// Hash the tokens of what was fed, up to where the input runs out
+    fn run(&mut self) -> io::Result<()> {
+        let mut read = match self.skip.take() {
+            Some(skip) => self.skip(skip),
+            None => Ok(()),
+        };
+        while read.is_ok() {
+            let start = self.input.start;
+            read = match self.input.peek() {
+                Ok(Some(b)) => self.token(b),
+                Ok(None) => break,
+                Err(e) => Err(e),
+            };
+            if read.as_ref().is_err_and(|e| e.kind() == io::ErrorKind::WouldBlock) && self.skip.is_none() {
+                // Read the token again once it is complete
+                self.input.start = start;
+            }
+        }
+        match read {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            read => read,
+        }
+    }
+
+    /// Hash the token starting with `b`
+    fn token(&mut self, b: u8) -> io::Result<()> {
+        match b {
+            b if b.is_ascii_whitespace() => self.input.bump(1),
+            b'-' if self.input.peek_at(1)? == Some(b'-') && self.starts_line_comment()? => self.skip(Skip::LineComment)?,
+            b'#' if self.dialect.dialect() == TypeId::of::<MySqlDialect>() => self.skip(Skip::LineComment)?,
+            b'/' if self.input.peek_at(1)? == Some(b'*') => self.block_comment()?,
+            b'*' if self.hint && self.input.peek_at(1)? == Some(b'/') => {
+                self.input.bump(2);
+                self.hint = false;
+            }
+            b'\'' => self.literal(b'\'')?,
+            b'"' | b'`' | b'[' if self.dialect.is_delimited_identifier_start(b as char) => self.quoted_identifier(b)?,
+            b'"' => self.literal(b'"')?,
+            b'0'..=b'9' => self.number()?,
+            b'.' if self.input.peek_at(1)?.is_some_and(|n| n.is_ascii_digit()) => self.number()?,
+            b'$' => self.dollar()?,
+            b';' => {
+                self.input.bump(1);
+                if self.split {
+                    self.end_statement();
+                    return Ok(());
+                }
+                if self.semicolon {
+                    self.semicolon = false;
+                    self.emit(b";");
+                }
+                self.semicolon = true;
+            }
+            b if self.is_word_start(b) => self.word()?,
+            b => {
+                self.input.bump(1);
+                self.emit(&[b]);
+            }
+        }
+        Ok(())
+    }
+
+    /// Record the fingerprint of the statement read so far, unless empty
+    fn end_statement(&mut self) {
+        if self.started {
+            self.statements.push(hex::encode(self.hasher.finalize_reset()));
+        }
+        (self.started, self.semicolon) = (false, false);
+    }
+
+    /// Close the input and hash what is left of it, unterminated constructs
+    /// failing
+    fn close(&mut self) -> io::Result<()> {
+        self.input.closed = true;
+        self.run()?;
+        if self.hint {
+            return Err(unterminated("block comment"));
+        }
+        Ok(())
+    }
+
+    /// Skip the content of `skip`, hashing literals as `?`. When the input
+    /// runs out, `skip` is kept to be resumed where it stopped.
+    fn skip(&mut self, mut skip: Skip) -> io::Result<()> {
+        let read = match skip {
+            Skip::LineComment => self.line_comment(),
+            Skip::BlockComment(ref mut depth) => self.block_comment_content(depth),
+            Skip::Literal(quote) => self.literal_content(quote),
+            Skip::Dollar(ref delimiter) => self.dollar_content(delimiter),
+        };
+        match read {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.skip = Some(skip);
+                Err(e)
+            }
+            Err(e) => Err(e),
+            Ok(()) => {
+                if matches!(skip, Skip::Literal(_) | Skip::Dollar(_)) {
+                    self.emit(b"?");
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether the `--` ahead starts a comment: MySQL wants whitespace after
+    /// it, reading `1--1` as `1 - -1`
+    fn starts_line_comment(&mut self) -> io::Result<bool> {
+        if !self.dialect.requires_single_line_comment_whitespace() {
+            return Ok(true);
+        }
+        Ok(self.input.peek_at(2)?.is_some_and(|b| b.is_ascii_whitespace()))
+    }
+
+    /// Skip a `--` or `#` comment, up to the end of its line
+    fn line_comment(&mut self) -> io::Result<()> {
+        while let Some(b) = self.input.next()? {
+            if b == b'\n' {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Skip a `/* */` comment, nested if the dialect nests them. The
+    /// `/*!` and version of a hint are skipped instead, and its SQL read on.
+    fn block_comment(&mut self) -> io::Result<()> {
+        if !self.hint && self.dialect.supports_multiline_comment_hints() && self.input.peek_at(2)? == Some(b'!') {
+            let mut len = 3;
+            while self.input.peek_at(len)?.is_some_and(|b| b.is_ascii_digit()) {
+                len += 1;
+            }
+            self.input.bump(len);
+            self.hint = true;
+            return Ok(());
+        }
+        self.input.bump(2);
+        self.skip(Skip::BlockComment(1))
+    }
+
+    /// Skip the rest of a block comment, `depth` of them being open
+    fn block_comment_content(&mut self, depth: &mut usize) -> io::Result<()> {
+        let nested = self.dialect.supports_nested_comments();
+        while *depth > 0 {
+            match (self.input.peek()?, self.input.peek_at(1)?) {
+                (None, _) => return Err(unterminated("block comment")),
+                (Some(b'*'), Some(b'/')) => {
+                    self.input.bump(2);
+                    *depth -= 1;
+                }
+                (Some(b'/'), Some(b'*')) if nested => {
+                    self.input.bump(2);
+                    *depth += 1;
+                }
+                _ => self.input.bump(1),
+            }
+        }
+        Ok(())
+    }
+
+    /// Skip a string literal quoted with `quote`, and hash it as `?`
+    fn literal(&mut self, quote: u8) -> io::Result<()> {
+        self.input.bump(1);
+        self.skip(Skip::Literal(quote))
+    }
+
+    /// Skip the rest of a string literal, up to its closing `quote`
+    fn literal_content(&mut self, quote: u8) -> io::Result<()> {
+        let backslash = self.dialect.supports_string_literal_backslash_escape();
+        loop {
+            match (self.input.peek()?, self.input.peek_at(1)?) {
+                (None, _) => return Err(unterminated("string literal")),
+                (Some(b'\\'), None) if backslash => return Err(unterminated("string literal")),
+                (Some(b'\\'), Some(_)) if backslash => self.input.bump(2),
+                (Some(b), next) if b == quote => {
+                    if next != Some(quote) {
+                        self.input.bump(1);
+                        return Ok(());
+                    }
+                    self.input.bump(2);
+                }
+                _ => self.input.bump(1),
+            }
+        }
+    }
+
+    /// Hash a delimited identifier as written, quotes included
+    fn quoted_identifier(&mut self, open: u8) -> io::Result<()> {
+        let close = if open == b'[' { b']' } else { open };
+        self.input.bump(1);
+        let mut word = std::mem::take(&mut self.word);
+        word.clear();
+        word.push(open);
+        let read = loop {
+            match self.input.next() {
+                Ok(None) => break Err(unterminated("quoted identifier")),
+                Ok(Some(b)) if b == close => {
+                    word.push(b);
+                    match self.input.peek() {
+                        Ok(Some(b)) if b == close => {}
+                        Ok(_) => break Ok(()),
+                        Err(e) => break Err(e),
+                    }
+                    self.input.bump(1);
+                    word.push(b);
+                }
+                Ok(Some(b)) => word.push(b),
+                Err(e) => break Err(e),
+            }
+        };
+        if read.is_ok() {
+            self.emit(&word);
+        }
+        self.word = word;
+        read
+    }
+
+    /// Skip a numeric literal (decimal, exponent or hex), and hash it as `?`
+    fn number(&mut self) -> io::Result<()> {
+        let mut prev = 0;
+        while let Some(b) = self.input.peek()? {
+            let exponent_sign = matches!(b, b'+' | b'-') && matches!(prev, b'e' | b'E');
+            if !(b.is_ascii_alphanumeric() || b == b'.' || b == b'_' || exponent_sign) {
+                break;
+            }
+            self.input.bump(1);
+            prev = b;
+        }
+        self.emit(b"?");
+        Ok(())
+    }
+
+    /// A `$1` placeholder, a `$tag$ ... $tag$` string, or a lone `$`
+    fn dollar(&mut self) -> io::Result<()> {
+        if self.input.peek_at(1)?.is_some_and(|b| b.is_ascii_digit()) {
+            let mut len = 1;
+            while self.input.peek_at(len)?.is_some_and(|b| b.is_ascii_digit()) {
+                len += 1;
+            }
+            let start = self.input.start;
+            let placeholder = self.input.buf[start..start + len].to_vec();
+            self.input.bump(len);
+            self.emit(&placeholder);
+            return Ok(());
+        }
+        let mut tag = Some(1);
+        while let Some(len) = tag.filter(|&len| len <= MAX_DOLLAR_TAG) {
+            match self.input.peek_at(len)? {
+                Some(b'$') => break,
+                Some(b) if b.is_ascii_alphanumeric() || b == b'_' => tag = Some(len + 1),
+                _ => tag = None,
+            }
+        }
+        let tag = match tag {
+            Some(len) if len <= MAX_DOLLAR_TAG && !self.dialect.supports_dollar_placeholder() => len,
+            _ => {
+                self.input.bump(1);
+                self.emit(b"$");
+                return Ok(());
+            }
+        };
+        // The delimiter, `$tag$`, both opens and closes the string
+        let delimiter = self.input.buf[self.input.start..=self.input.start + tag].to_vec();
+        self.input.bump(delimiter.len());
+        self.skip(Skip::Dollar(delimiter))
+    }
+
+    /// Skip the rest of a dollar-quoted string, up to its `delimiter`
+    fn dollar_content(&mut self, delimiter: &[u8]) -> io::Result<()> {
+        loop {
+            match self.input.peek()? {
+                None => return Err(unterminated("dollar-quoted string")),
+                Some(b'$') => {
+                    let mut matched = true;
+                    for (k, &d) in delimiter.iter().enumerate().skip(1) {
+                        if self.input.peek_at(k)? != Some(d) {
+                            matched = false;
+                            break;
+                        }
+                    }
+                    if matched {
+                        self.input.bump(delimiter.len());
+                        return Ok(());
+                    }
+                    self.input.bump(1);
+                }
+                Some(_) => self.input.bump(1),
+            }
+        }
+    }
+
+    /// A keyword (uppercased), an identifier (as written), a boolean or a
+    /// prefixed string literal such as `N'...'` (both hashed as `?`)
+    fn word(&mut self) -> io::Result<()> {
+        let mut len = 0;
+        while self.input.peek_at(len)?.is_some_and(|b| self.is_word_part(b)) {
+            len += 1;
+        }
+        let start = self.input.start;
+        let upper = self.input.buf[start..start + len].to_ascii_uppercase();
+        let prefix = matches!(upper.as_slice(), b"N" | b"E" | b"X" | b"B" | b"R");
+        if prefix && self.input.peek_at(len)? == Some(b'\'') {
+            self.input.bump(len);
+            return self.literal(b'\'');
+        }
+        let mut word = std::mem::take(&mut self.word);
+        word.clear();
+        word.extend_from_slice(&self.input.buf[start..start + len]);
+        self.input.bump(len);
+        if matches!(upper.as_slice(), b"TRUE" | b"FALSE") {
+            self.emit(b"?");
+        } else if std::str::from_utf8(&upper).is_ok_and(|w| ALL_KEYWORDS.binary_search(&w).is_ok()) {
+            self.emit(&upper);
+        } else {
+            self.emit(&word);
+        }
+        self.word = word;
+        Ok(())
+    }
+}
+
+/// SHA-256 hex digest of the masked tokens read from `reader`, reading at
+/// most `chunk_size` bytes at a time. Unterminated literals, identifiers and
+/// comments fail with `io::ErrorKind::InvalidData`.
+pub fn fingerprint<R: Read>(
+    mut reader: R,
+    dialect: Box<dyn Dialect + Send + Sync>,
+    chunk_size: usize,
+) -> io::Result<String> {
+    let mut masker = Masker::new(dialect, false);
+    let mut chunk = vec![0; chunk_size.max(1)];
+    loop {
+        match reader.read(&mut chunk) {
+            Ok(0) => {
+                masker.close()?;
+                return Ok(hex::encode(masker.hasher.finalize()));
+            }
+            Ok(n) => {
+                masker.input.feed(&chunk[..n]);
+                masker.run()?;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Sans-IO fingerprinting of a stream of statements, as a proxy sees them on
+/// the wire: chunks are fed as they arrive, split anywhere, and each
+/// statement's fingerprint is produced as soon as its `;` is read, without
+/// the statement being kept or read again.
+pub struct Fingerprinter {
+    masker: Masker,
+}
+
+impl Fingerprinter {
+    pub fn new(dialect: Box<dyn Dialect + Send + Sync>) -> Self {
+        Fingerprinter { masker: Masker::new(dialect, true) }
+    }
+
+    /// Feed the next chunk; the fingerprints of the statements it ends
+    pub fn feed(&mut self, chunk: &[u8]) -> io::Result<Vec<String>> {
+        self.masker.input.feed(chunk);
+        self.masker.run()?;
+        Ok(std::mem::take(&mut self.masker.statements))
+    }
+
+    /// End the stream: the fingerprint of its last statement if it was not
+    /// ended by a `;`. The fingerprinter starts over, even when unterminated
+    /// constructs fail.
+    pub fn finish(&mut self) -> io::Result<Option<String>> {
+        let last = self.masker.close().map(|()| {
+            self.masker.end_statement();
+            self.masker.statements.pop()
+        });
+        self.masker.reset();
+        last
+    }
+}
@@ -0,0 +1,106 @@
+//! Function synonyms: the builtin ones the opt-in
+//! `function-synonym-canonicalize` pass writes as one name, and those of the
+//! `function_synonyms` option.
+//!
+//! `IFNULL(a, b)` and `COALESCE(a, b)`, or `NOW()` and `CURRENT_TIMESTAMP`,
+//! are the same call in every dialect having both, so each pair is written
+//! with the standard name. The keyword functions of the current time take
+//! empty parentheses in MySQL (`CURRENT_TIMESTAMP()`), which are dropped. The
+//! synonyms whose meaning depends on the dialect (`SYSDATE`, `GETDATE()`,
+//! MSSQL's two-argument `ISNULL()`) are left to the opt-in
+//! `current-time-canonicalize` pass or to the option.
+//!
+//! The option maps a shop's own aliases, such as UDFs wrapping a builtin, to
+//! the function they stand for: `{"my_coalesce": "coalesce"}`. Names are
+//! matched up to case, schema-qualified ones (`"util.nvl"`) part by part, and
+//! calls are renamed before the passes run, so that the canonical name gets
+//! the case rules, and with the pass the builtin synonyms, in turn. Quoted
+//! names are left alone.
+
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    visit_expressions_mut, Expr, Function, FunctionArguments, Ident, ObjectName, ObjectNamePart, Statement,
+};
+
+/// Builtin functions and the standard name of the same call
+const BUILTINS: &[(&str, &str)] = &[
+    ("CHARACTER_LENGTH", "CHAR_LENGTH"),
+    ("IFNULL", "COALESCE"),
+    ("LCASE", "LOWER"),
+    ("NOW", "CURRENT_TIMESTAMP"),
+    ("NVL", "COALESCE"),
+    ("UCASE", "UPPER"),
+];
+
+/// Keyword functions written without parentheses when they take no argument
+const NILADIC: &[&str] = &["CURRENT_DATE", "CURRENT_TIME", "CURRENT_TIMESTAMP", "LOCALTIME", "LOCALTIMESTAMP"];
+
+/// The name of `func` when it is one unquoted part
+fn plain_name(func: &Function) -> Option<&Ident> {
+    match func.name.0.as_slice() {
+        [ObjectNamePart::Identifier(ident)] if ident.quote_style.is_none() => Some(ident),
+        _ => None,
+    }
+}
+
+/// Write `func` with the standard name of its builtin synonym, if it has one
+pub fn canonicalize(func: &mut Function) {
+    let Some(ident) = plain_name(func) else {
+        return;
+    };
+    if let Some(&(_, canonical)) = BUILTINS.iter().find(|(name, _)| ident.value.eq_ignore_ascii_case(name)) {
+        func.name = ObjectName::from(vec![Ident::new(canonical)]);
+    }
+    let Some(ident) = plain_name(func) else {
+        return;
+    };
+    if NILADIC.iter().any(|name| ident.value.eq_ignore_ascii_case(name)) {
+        if let FunctionArguments::List(ref list) = func.args {
+            if list.args.is_empty() && list.clauses.is_empty() && list.duplicate_treatment.is_none() {
+                func.args = FunctionArguments::None;
+            }
+        }
+    }
+}
+
+/// Check the names of `function_synonyms`
+pub fn validate(synonyms: &BTreeMap<String, String>) -> Result<(), String> {
+    for name in synonyms.iter().flat_map(|(alias, canonical)| [alias, canonical]) {
+        if name.split('.').any(|part| part.trim().is_empty()) {
+            return Err(format!("Invalid function name in function_synonyms: {:?}", name));
+        }
+    }
+    Ok(())
+}
+
+/// The lowercased dotted name of `name`, if no part of it is quoted
+fn lookup_key(name: &ObjectName) -> Option<String> {
+    let parts: Option<Vec<String>> = name
+        .0
+        .iter()
+        .map(|part| match part {
+            ObjectNamePart::Identifier(ident) if ident.quote_style.is_none() => Some(ident.value.to_lowercase()),
+            _ => None,
+        })
+        .collect();
+    Some(parts?.join("."))
+}
+
+/// Rename the calls in `stmt` of the functions `synonyms` maps, its keys
+/// lowercased
+pub fn rename(stmt: &mut Statement, synonyms: &BTreeMap<String, String>) {
+    if synonyms.is_empty() {
+        return;
+    }
+    let _ = visit_expressions_mut(stmt, |expr| {
+        if let Expr::Function(func) = expr {
+            if let Some(canonical) = lookup_key(&func.name).and_then(|key| synonyms.get(&key)) {
+                let parts: Vec<Ident> = canonical.split('.').map(|part| Ident::new(part.trim())).collect();
+                func.name = ObjectName::from(parts);
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+}
@@ -0,0 +1,101 @@
+//! The tables a statement reads and writes, for `extract_tables()`.
+//!
+//! The target of an `INSERT`, `UPDATE`, `DELETE` or `MERGE` is written, at
+//! any depth (`WITH ... INSERT` included), as is the table or view a
+//! `CREATE TABLE` or `CREATE VIEW` defines; every other table referenced, in
+//! joins, subqueries and CTE bodies alike, is read. References to CTEs are
+//! not tables and are left out. A table both written and read elsewhere in
+//! the statement, as in `UPDATE t SET a = (SELECT max(a) FROM t)`, is listed
+//! in both. Names are as written, schema and all, each listed once in order
+//! of appearance.
+
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{FromTable, ObjectName, Query, Statement, TableFactor, TableObject, Visit, Visitor};
+
+use crate::scope::Scopes;
+
+#[derive(Default)]
+pub struct Tables {
+    pub read: Vec<String>,
+    pub written: Vec<String>,
+}
+
+fn push(names: &mut Vec<String>, name: &ObjectName) {
+    let name = name.to_string();
+    if !names.contains(&name) {
+        names.push(name);
+    }
+}
+
+fn table_name(factor: &TableFactor) -> Option<&ObjectName> {
+    match factor {
+        TableFactor::Table { name, .. } => Some(name),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct Collector {
+    scopes: Scopes,
+    /// The names of the targets of the statements entered, by address
+    targets: Vec<*const ObjectName>,
+    tables: Tables,
+}
+
+impl Visitor for Collector {
+    type Break = ();
+
+    fn pre_visit_statement(&mut self, stmt: &Statement) -> ControlFlow<()> {
+        match stmt {
+            Statement::Insert(insert) => {
+                if let TableObject::TableName(ref name) = insert.table {
+                    self.targets.push(name);
+                }
+            }
+            Statement::Update(update) => self.targets.extend(table_name(&update.table.relation).map(|name| name as *const _)),
+            Statement::Delete(delete) => {
+                // `DELETE t1 FROM t1 JOIN t2` lists its targets apart, and they
+                // are not visited as relations
+                for name in &delete.tables {
+                    push(&mut self.tables.written, name);
+                }
+                if delete.tables.is_empty() {
+                    let (FromTable::WithFromKeyword(from) | FromTable::WithoutKeyword(from)) = &delete.from;
+                    self.targets.extend(from.iter().filter_map(|twj| table_name(&twj.relation)).map(|name| name as *const _));
+                }
+            }
+            Statement::Merge(merge) => self.targets.extend(table_name(&merge.table).map(|name| name as *const _)),
+            Statement::CreateTable(create) => self.targets.push(&create.name),
+            // Not visited as a relation
+            Statement::CreateView(create) => push(&mut self.tables.written, &create.name),
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        self.scopes.enter(query);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        self.scopes.leave(query);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &ObjectName) -> ControlFlow<()> {
+        if self.targets.contains(&(relation as *const _)) {
+            push(&mut self.tables.written, relation);
+        } else if !self.scopes.is_cte(relation) {
+            push(&mut self.tables.read, relation);
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+pub fn tables(stmt: &Statement) -> Tables {
+    let mut collector = Collector::default();
+    let _ = stmt.visit(&mut collector);
+    collector.tables
+}
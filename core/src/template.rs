@@ -0,0 +1,82 @@
+//! Pre-pass replacing Jinja/dbt templating constructs with plain SQL, so that
+//! templated models can be parsed and fingerprinted.
+//!
+//! - `{{ expr }}` becomes a marker identifier (`sqlfp_tpl_0`, `sqlfp_tpl_1`, …),
+//!   valid both as a table name and as a column reference
+//! - `{% tag %}` and `{# comment #}` are removed, keeping the SQL between tags
+//!   (so both branches of an `{% if %}` end up in the statement)
+//!
+//! Expressions before any SQL (typically `{{ config(...) }}`) are removed too,
+//! since a bare identifier cannot start a statement.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TemplateKind {
+    Expression,
+    Statement,
+    Comment,
+}
+
+impl TemplateKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TemplateKind::Expression => "expression",
+            TemplateKind::Statement => "statement",
+            TemplateKind::Comment => "comment",
+        }
+    }
+}
+
+/// One templating construct found in the input
+#[derive(Clone, Debug)]
+pub struct TemplateMarker {
+    pub kind: TemplateKind,
+    /// The construct as written, delimiters included
+    pub source: String,
+    /// Identifier substituted for it, if it was not simply removed
+    pub marker: Option<String>,
+}
+
+const DELIMITERS: [(&str, &str, TemplateKind); 3] = [
+    ("{{", "}}", TemplateKind::Expression),
+    ("{%", "%}", TemplateKind::Statement),
+    ("{#", "#}", TemplateKind::Comment),
+];
+
+/// Replace templating constructs in `sql`; returns the plain SQL and what was replaced.
+/// An unterminated construct is left as-is.
+pub fn strip_templates(sql: &str) -> (String, Vec<TemplateMarker>) {
+    let mut out = String::with_capacity(sql.len());
+    let mut markers = Vec::new();
+    let mut expressions = 0;
+    let mut rest = sql;
+
+    while let Some(start) = rest.find('{') {
+        let (before, tail) = rest.split_at(start);
+        out.push_str(before);
+        let Some(&(_, close, kind)) = DELIMITERS.iter().find(|(open, _, _)| tail.starts_with(open))
+        else {
+            out.push('{');
+            rest = &tail[1..];
+            continue;
+        };
+        let Some(end) = tail[2..].find(close).map(|i| i + 2 + close.len()) else {
+            out.push_str(tail);
+            rest = "";
+            break;
+        };
+        let source = &tail[..end];
+        let marker = if kind == TemplateKind::Expression && !out.trim().is_empty() {
+            let name = format!("sqlfp_tpl_{}", expressions);
+            expressions += 1;
+            out.push_str(&name);
+            Some(name)
+        } else {
+            out.push(' ');
+            None
+        };
+        markers.push(TemplateMarker { kind, source: source.to_string(), marker });
+        rest = &tail[end..];
+    }
+    out.push_str(rest);
+    (out, markers)
+}
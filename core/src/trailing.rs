@@ -0,0 +1,90 @@
+//! Trailing clauses left out of the hashes, for the `ignore_clauses` option.
+//!
+//! Grouping policies that treat ordering and pagination as presentation want
+//! `SELECT * FROM t ORDER BY a LIMIT 10` and `SELECT * FROM t` to share a
+//! fingerprint. `hash` and `shape_hash` are then taken over a copy of the
+//! normalized statement without those clauses, while `normalized` keeps them.
+//! Only the clauses of the outermost statement are left out: those of
+//! subqueries, CTEs and set operation branches change which rows are
+//! returned, not just how.
+
+use sqlparser::ast::{LimitClause, Offset, OffsetRows, Statement};
+
+/// A clause that `ignore_clauses` can name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Trailing {
+    /// `ORDER BY`
+    OrderBy,
+    /// `LIMIT`, `FETCH` and ClickHouse's `LIMIT ... BY`
+    Limit,
+    /// `OFFSET`, also when written `LIMIT offset, count`
+    Offset,
+}
+
+/// The `ignore_clauses` option, sorted and deduplicated
+pub fn parse(names: &[String]) -> Result<Vec<Trailing>, String> {
+    let mut clauses = names
+        .iter()
+        .map(|name| match name.as_str() {
+            "order_by" => Ok(Trailing::OrderBy),
+            "limit" => Ok(Trailing::Limit),
+            "offset" => Ok(Trailing::Offset),
+            _ => Err(format!("Unsupported ignore_clauses entry: {}", name)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    clauses.sort();
+    clauses.dedup();
+    Ok(clauses)
+}
+
+/// `limit` without the parts in `clauses`, `None` once nothing is left
+fn strip_limit(limit: LimitClause, clauses: &[Trailing]) -> Option<LimitClause> {
+    let (limit, offset, limit_by) = match limit {
+        LimitClause::LimitOffset { limit, offset, limit_by } => (limit, offset, limit_by),
+        // The part left is written the standard way
+        LimitClause::OffsetCommaLimit { offset, limit } => {
+            (Some(limit), Some(Offset { value: offset, rows: OffsetRows::None }), Vec::new())
+        }
+    };
+    let (limit, limit_by) =
+        if clauses.contains(&Trailing::Limit) { (None, Vec::new()) } else { (limit, limit_by) };
+    let offset = offset.filter(|_| !clauses.contains(&Trailing::Offset));
+    match (limit, offset) {
+        (None, None) if limit_by.is_empty() => None,
+        (limit, offset) => Some(LimitClause::LimitOffset { limit, offset, limit_by }),
+    }
+}
+
+/// A copy of `stmt` without the trailing clauses in `clauses`, `None` when it
+/// has none of them
+pub fn stripped(stmt: &Statement, clauses: &[Trailing]) -> Option<Statement> {
+    if clauses.is_empty() {
+        return None;
+    }
+    let mut stmt = stmt.clone();
+    let order_by = clauses.contains(&Trailing::OrderBy);
+    let limit = clauses.contains(&Trailing::Limit);
+    let offset = clauses.contains(&Trailing::Offset);
+    let changed = match stmt {
+        Statement::Query(ref mut query) => {
+            let before = (query.order_by.is_some(), query.limit_clause.clone(), query.fetch.is_some());
+            if order_by {
+                query.order_by = None;
+            }
+            if limit {
+                query.fetch = None;
+            }
+            if limit || offset {
+                query.limit_clause = query.limit_clause.take().and_then(|l| strip_limit(l, clauses));
+            }
+            before != (query.order_by.is_some(), query.limit_clause.clone(), query.fetch.is_some())
+        }
+        Statement::Update(ref mut update) => limit && update.limit.take().is_some(),
+        Statement::Delete(ref mut delete) => {
+            let ordered = order_by && !std::mem::take(&mut delete.order_by).is_empty();
+            ordered | (limit && delete.limit.take().is_some())
+        }
+        _ => false,
+    };
+    changed.then_some(stmt)
+}
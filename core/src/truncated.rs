@@ -0,0 +1,62 @@
+//! Recovery of statements cut short by their source, for `truncated_input`.
+//!
+//! Slow query logs and APM agents cap the length of the statements they
+//! record, often in the middle of a token. When such a statement does not
+//! parse, the longest prefix of it that does is fingerprinted instead: the
+//! tokens of the statement are read up to where it was cut (a string cut
+//! short is closed, an unterminated quoted name or comment ends them), a word
+//! or number running into the very end is dropped as possibly partial, and then tokens are dropped
+//! from the end, the parentheses left open being closed, until the rest
+//! parses.
+
+use std::borrow::Cow;
+
+use sqlparser::ast::Statement;
+use sqlparser::dialect::Dialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, TokenWithSpan, Tokenizer};
+
+use crate::paramstyle::Cursor;
+
+/// Tokens dropped from the end at most before giving up
+const MAX_DROPPED: usize = 32;
+
+/// The longest prefix of `sql` that parses once its open parentheses are
+/// closed, as written and parsed
+pub fn prefix(sql: &str, dialect: &dyn Dialect) -> Option<(String, Statement)> {
+    let mut tokens: Vec<TokenWithSpan> = Vec::new();
+    // On error, the tokens read before the cut are kept
+    let mut sql = Cow::Borrowed(sql);
+    if Tokenizer::new(dialect, &sql).tokenize_with_location_into_buf(&mut tokens).is_err() {
+        // A string cut short is closed, so that its literal is kept
+        let closed = format!("{}'", sql);
+        let mut all = Vec::new();
+        if Tokenizer::new(dialect, &closed).tokenize_with_location_into_buf(&mut all).is_ok() {
+            (sql, tokens) = (Cow::Owned(closed), all);
+        }
+    }
+    tokens.retain(|t| !matches!(t.token, Token::Whitespace(_) | Token::EOF));
+    let cursor = Cursor::new(&sql);
+    if let Some(last) = tokens.last() {
+        if matches!(last.token, Token::Word(_) | Token::Number(..)) && cursor.clone().seek(last.span.end) == sql.len() {
+            tokens.pop();
+        }
+    }
+    for keep in (1..=tokens.len()).rev().take(MAX_DROPPED + 1) {
+        let kept = &tokens[..keep];
+        let depth = kept.iter().fold(0isize, |depth, t| match t.token {
+            Token::LParen => depth + 1,
+            Token::RParen => depth - 1,
+            _ => depth,
+        });
+        if depth < 0 {
+            continue;
+        }
+        let mut text = sql[..cursor.clone().seek(kept[keep - 1].span.end)].to_string();
+        text.push_str(&")".repeat(depth as usize));
+        if let Some(stmt) = Parser::parse_sql(dialect, &text).ok().and_then(|stmts| stmts.into_iter().next()) {
+            return Some((text, stmt));
+        }
+    }
+    None
+}
@@ -0,0 +1,105 @@
+//! `USING` joins, for the opt-in `join-using-canonicalize` pass.
+//!
+//! `JOIN b USING (id)` and `JOIN b ON a.id = b.id` join the same rows, and
+//! both spellings are common enough, often for the same query across code
+//! paths, to split fingerprints. An `ON` constraint made only of equalities
+//! between same-named columns, one side qualified by the joined relation and
+//! the other by a relation before it in the same `FROM` item, is written as
+//! the `USING` list of those columns, in the order of the conjuncts. `USING`
+//! is the target because it is the spelling that does not depend on which of
+//! several earlier relations a column is read from. Other constraints, and
+//! relations without a name to qualify them by, are left alone.
+
+use sqlparser::ast::{BinaryOperator, Expr, Ident, JoinConstraint, JoinOperator, ObjectName, TableWithJoins};
+
+use crate::wildcard::{qualifies, relation_name};
+
+/// The constraint of a join that `USING` can express
+pub fn constraint_mut(op: &mut JoinOperator) -> Option<&mut JoinConstraint> {
+    match op {
+        JoinOperator::Join(c)
+        | JoinOperator::Inner(c)
+        | JoinOperator::Left(c)
+        | JoinOperator::LeftOuter(c)
+        | JoinOperator::Right(c)
+        | JoinOperator::RightOuter(c)
+        | JoinOperator::FullOuter(c) => Some(c),
+        _ => None,
+    }
+}
+
+/// The conjuncts of `expr`, through parentheses
+fn conjuncts<'a>(expr: &'a Expr, out: &mut Vec<&'a Expr>) {
+    match expr {
+        Expr::Nested(inner) => conjuncts(inner, out),
+        Expr::BinaryOp { left, op: BinaryOperator::And, right } => {
+            conjuncts(left, out);
+            conjuncts(right, out);
+        }
+        other => out.push(other),
+    }
+}
+
+/// `qualifier.column` split into the qualifier and the column
+fn qualified(expr: &Expr) -> Option<(ObjectName, &Ident)> {
+    let Expr::CompoundIdentifier(parts) = expr else {
+        return None;
+    };
+    let (column, qualifier) = parts.split_last()?;
+    (!qualifier.is_empty()).then(|| (ObjectName::from(qualifier.to_vec()), column))
+}
+
+/// Whether `a` and `b` name the same column: alike if quoted, and up to case
+/// otherwise
+fn same_column(a: &Ident, b: &Ident) -> bool {
+    match (a.quote_style, b.quote_style) {
+        (None, None) => a.value.eq_ignore_ascii_case(&b.value),
+        (Some(_), Some(_)) => a.value == b.value,
+        _ => false,
+    }
+}
+
+/// The `USING` columns `on` stands for, joining the relation named `right`
+/// to those named `left`
+fn using_columns(on: &Expr, left: &[String], right: &str) -> Option<Vec<ObjectName>> {
+    let mut parts = Vec::new();
+    conjuncts(on, &mut parts);
+    let mut columns: Vec<Ident> = Vec::with_capacity(parts.len());
+    for part in parts {
+        let Expr::BinaryOp { left: a, op: BinaryOperator::Eq, right: b } = part else {
+            return None;
+        };
+        let ((qa, ca), (qb, cb)) = (qualified(a)?, qualified(b)?);
+        let joined = if qualifies(&qb, right) && left.iter().any(|name| qualifies(&qa, name)) {
+            cb
+        } else if qualifies(&qa, right) && left.iter().any(|name| qualifies(&qb, name)) {
+            ca
+        } else {
+            return None;
+        };
+        if !same_column(ca, cb) || columns.iter().any(|column| same_column(column, joined)) {
+            return None;
+        }
+        columns.push(joined.clone());
+    }
+    Some(columns.into_iter().map(|column| ObjectName::from(vec![column])).collect())
+}
+
+/// `a JOIN b ON a.id = b.id` → `a JOIN b USING (id)` throughout `twj`
+pub fn canonicalize(twj: &mut TableWithJoins) {
+    let mut names: Vec<String> = relation_name(&twj.relation).into_iter().collect();
+    for join in &mut twj.joins {
+        let name = relation_name(&join.relation);
+        if let (Some(right), Some(constraint)) = (&name, constraint_mut(&mut join.join_operator)) {
+            // An earlier relation named like the joined one cannot be told
+            // apart from it
+            let left: Vec<String> = names.iter().filter(|left| !left.eq_ignore_ascii_case(right)).cloned().collect();
+            if let JoinConstraint::On(on) = constraint {
+                if let Some(columns) = using_columns(on, &left, right) {
+                    *constraint = JoinConstraint::Using(columns);
+                }
+            }
+        }
+        names.extend(name);
+    }
+}
@@ -0,0 +1,74 @@
+//! WebAssembly exports, for the `wasm` feature.
+//!
+//! Browser and Node tooling fingerprint statements through the module built
+//! with `cargo rustc -p sqlfp-core --release --target wasm32-unknown-unknown
+//! --features wasm --crate-type cdylib`, then `wasm-bindgen --target web`,
+//! wrapped by `js/sqlfp.js`.
+//!
+//! The options are the object of the `normalize()` keyword arguments a
+//! config file takes, so `{dialect: "postgres", placeholder_start: 0}`; the
+//! answer is `{normalized, hash, params, warnings}`. Errors are thrown as
+//! `{error, kind}`, with `kind` one of `"parse"`, `"options"`,
+//! `"unnormalizable"` or `"placeholder_collision"`.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::config::Config;
+use crate::{normalize, Dialect, Error};
+
+/// What `normalize()` answers
+#[derive(Serialize)]
+struct Answer {
+    normalized: String,
+    hash: String,
+    params: Vec<String>,
+    warnings: Vec<String>,
+}
+
+/// What `normalize()` throws
+#[derive(Serialize)]
+struct Failure {
+    error: String,
+    kind: &'static str,
+}
+
+fn failure(error: String, kind: &'static str) -> JsValue {
+    serde_wasm_bindgen::to_value(&Failure { error, kind }).unwrap_or_else(|e| e.into())
+}
+
+/// Normalize the first statement of `sql` with `options` (undefined or null
+/// for the defaults)
+#[wasm_bindgen(js_name = normalize)]
+pub fn normalize_js(sql: &str, options: JsValue) -> Result<JsValue, JsValue> {
+    let invalid = |message: String| failure(message, "options");
+    let config: Config = if options.is_undefined() || options.is_null() {
+        Config::default()
+    } else {
+        // Through JSON, as serde-wasm-bindgen only looks up the fields it
+        // knows and would let a misspelt option pass
+        serde_wasm_bindgen::from_value::<serde_json::Value>(options)
+            .map_err(|e| e.to_string())
+            .and_then(|options| serde_json::from_value(options).map_err(|e| e.to_string()))
+            .map_err(|e| invalid(format!("Invalid options: {}", e)))?
+    };
+    let options = config.options(&Config::default()).map_err(invalid)?;
+    let dialect = Dialect::new(&options.dialect).map_err(|e| invalid(e.to_string()))?;
+    let fingerprint = normalize(sql, dialect, &options).map_err(|e| {
+        let kind = match e {
+            Error::Parse(_) => "parse",
+            Error::Options(_) => "options",
+            Error::Unnormalizable { .. } => "unnormalizable",
+            Error::PlaceholderCollision(_) => "placeholder_collision",
+            Error::Callback(never) => match never {},
+        };
+        failure(e.to_string(), kind)
+    })?;
+    let answer = Answer {
+        normalized: fingerprint.normalized,
+        hash: fingerprint.hash,
+        params: fingerprint.params,
+        warnings: fingerprint.warnings,
+    };
+    serde_wasm_bindgen::to_value(&answer).map_err(|e| e.into())
+}
@@ -0,0 +1,119 @@
+//! Qualified wildcards, for the opt-in `wildcard-canonicalize` pass and
+//! `metadata["wildcard_tables"]`.
+//!
+//! ORMs switch between `SELECT * FROM a JOIN b` and `SELECT a.*, b.* FROM a
+//! JOIN b` as eager loading is toggled. A projection made only of qualified
+//! wildcards, one per relation of the `FROM` list and in its order, selects
+//! the same columns as `*` and is rewritten as such. Joins that merge or drop
+//! columns (`USING`, `NATURAL`, semi and anti joins) are left alone, as are
+//! relations without a name to qualify them by.
+
+use sqlparser::ast::{
+    JoinConstraint, JoinOperator, ObjectName, Select, SelectItem, SelectItemQualifiedWildcardKind, SetExpr, Statement,
+    TableFactor, WildcardAdditionalOptions,
+};
+
+use crate::metadata::MetaValue;
+
+/// The name `factor` is qualified by: its alias, else the table name
+pub fn relation_name(factor: &TableFactor) -> Option<String> {
+    match factor {
+        TableFactor::Table { name, alias, .. } => match alias {
+            Some(alias) => Some(alias.name.to_string()),
+            None => Some(name.to_string()),
+        },
+        TableFactor::Derived { alias: Some(alias), .. } => Some(alias.name.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether a join keeps the columns of both sides, as `*` lists them
+fn keeps_columns(op: &JoinOperator) -> bool {
+    match op {
+        JoinOperator::Join(c)
+        | JoinOperator::Inner(c)
+        | JoinOperator::Left(c)
+        | JoinOperator::LeftOuter(c)
+        | JoinOperator::Right(c)
+        | JoinOperator::RightOuter(c)
+        | JoinOperator::FullOuter(c)
+        | JoinOperator::CrossJoin(c) => matches!(c, JoinConstraint::On(_) | JoinConstraint::None),
+        _ => false,
+    }
+}
+
+/// The relations of `select` in `FROM` order, the unnamed ones as `None`
+fn relations(select: &Select) -> Vec<Option<String>> {
+    let mut out = Vec::new();
+    for twj in &select.from {
+        out.push(relation_name(&twj.relation));
+        for join in &twj.joins {
+            out.push(relation_name(&join.relation));
+        }
+    }
+    out
+}
+
+fn plain(options: &WildcardAdditionalOptions) -> bool {
+    options.opt_ilike.is_none()
+        && options.opt_exclude.is_none()
+        && options.opt_except.is_none()
+        && options.opt_replace.is_none()
+        && options.opt_rename.is_none()
+}
+
+/// Whether `qualifier` refers to the relation named `name`: by its whole
+/// name, or by the table name without its schema
+pub fn qualifies(qualifier: &ObjectName, name: &str) -> bool {
+    let qualifier = qualifier.to_string();
+    qualifier.eq_ignore_ascii_case(name)
+        || name.rsplit_once('.').is_some_and(|(_, table)| qualifier.eq_ignore_ascii_case(table))
+}
+
+/// `SELECT a.*, b.* FROM a JOIN b` → `SELECT * FROM a JOIN b`
+pub fn canonicalize(select: &mut Select) {
+    if select.projection.iter().any(|item| !matches!(item, SelectItem::QualifiedWildcard(..))) {
+        return;
+    }
+    if !select.from.iter().flat_map(|twj| &twj.joins).all(|join| keeps_columns(&join.join_operator)) {
+        return;
+    }
+    let relations = relations(select);
+    if relations.len() != select.projection.len() {
+        return;
+    }
+    let covered = select.projection.iter().zip(&relations).all(|(item, name)| match (item, name) {
+        (SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::ObjectName(qualifier), options), Some(name)) => {
+            plain(options) && qualifies(qualifier, name)
+        }
+        _ => false,
+    });
+    if covered {
+        select.projection = vec![SelectItem::Wildcard(WildcardAdditionalOptions::default())];
+    }
+}
+
+/// `metadata["wildcard_tables"]`, the relations all of whose columns the
+/// outermost `SELECT` of `stmt` selects, present only when there are some
+pub fn wildcard_tables(stmt: &Statement) -> Option<(String, MetaValue)> {
+    let Statement::Query(query) = stmt else {
+        return None;
+    };
+    let SetExpr::Select(ref select) = *query.body else {
+        return None;
+    };
+    let mut tables = Vec::new();
+    for item in &select.projection {
+        match item {
+            SelectItem::Wildcard(_) => tables.extend(relations(select).into_iter().flatten()),
+            SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::ObjectName(qualifier), _) => {
+                tables.push(qualifier.to_string());
+            }
+            _ => {}
+        }
+    }
+    if tables.is_empty() {
+        return None;
+    }
+    Some(("wildcard_tables".to_string(), MetaValue::List(tables.into_iter().map(Into::into).collect())))
+}
@@ -0,0 +1,227 @@
+//! Query text of captured protocol messages, for `fingerprint_mysql_query()`
+//! and `fingerprint_postgres_message()`.
+//!
+//! A MySQL `COM_QUERY` payload is the `0x03` command byte followed by the
+//! query, preceded since 8.0.23 by the query attributes when the client
+//! negotiated `CLIENT_QUERY_ATTRIBUTES`; a packet capture only tells by the
+//! handshake, so the caller does. A PostgreSQL simple `Query` message body is
+//! the NUL-terminated query, and that of a `Parse` message the NUL-terminated
+//! statement name and query, followed by parameter types. Either may hold
+//! several statements, split like scripts.
+//!
+//! The text is in the connection's character set, named the protocol's way
+//! (`utf8mb4`, `latin1`; `UTF8`, `WIN1252`), which is mapped onto the Python
+//! codec decoding it. Other names are taken as codec names.
+
+/// The `COM_QUERY` command byte
+const COM_QUERY: u8 = 0x03;
+
+/// Reads the fields of a MySQL payload in turn
+struct Fields<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = self.at.checked_add(n).filter(|&end| end <= self.data.len());
+        let end = end.ok_or_else(|| "Truncated COM_QUERY payload".to_string())?;
+        let bytes = &self.data[self.at..end];
+        self.at = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// A length-encoded integer
+    fn lenenc(&mut self) -> Result<u64, String> {
+        let size = match self.byte()? {
+            n @ 0..=0xfa => return Ok(n as u64),
+            0xfc => 2,
+            0xfd => 3,
+            0xfe => 8,
+            n => return Err(format!("Invalid length-encoded integer in COM_QUERY payload: 0x{:02x}", n)),
+        };
+        Ok(self.take(size)?.iter().rev().fold(0, |n, &b| n << 8 | b as u64))
+    }
+
+    /// Skip a length-encoded string
+    fn skip_lenenc(&mut self) -> Result<(), String> {
+        let len = self.lenenc()?;
+        self.take(usize::try_from(len).map_err(|_| "Truncated COM_QUERY payload".to_string())?)?;
+        Ok(())
+    }
+
+    /// Skip a query attribute value of MySQL column type `kind`, in the
+    /// binary protocol
+    fn skip_value(&mut self, kind: u8) -> Result<(), String> {
+        match kind {
+            // TINY
+            0x01 => self.take(1).map(drop),
+            // SHORT, YEAR
+            0x02 | 0x0d => self.take(2).map(drop),
+            // LONG, FLOAT, INT24
+            0x03 | 0x04 | 0x09 => self.take(4).map(drop),
+            // DOUBLE, LONGLONG
+            0x05 | 0x08 => self.take(8).map(drop),
+            // NULL
+            0x06 => Ok(()),
+            // TIMESTAMP, DATE, TIME, DATETIME: a length byte and that many
+            0x07 | 0x0a | 0x0b | 0x0c => {
+                let len = self.byte()?;
+                self.take(len as usize).map(drop)
+            }
+            // DECIMAL, VARCHAR, BIT, JSON, NEWDECIMAL and the string and
+            // blob types, length-encoded
+            0x00 | 0x0f | 0x10 | 0xf5..=0xff => self.skip_lenenc(),
+            _ => Err(format!("Unsupported query attribute type in COM_QUERY payload: 0x{:02x}", kind)),
+        }
+    }
+}
+
+/// The query of a MySQL `COM_QUERY` payload, its query attributes skipped
+/// with `query_attributes`
+pub fn mysql_query(payload: &[u8], query_attributes: bool) -> Result<&[u8], String> {
+    let mut fields = Fields { data: payload, at: 0 };
+    match fields.byte() {
+        Ok(COM_QUERY) => {}
+        _ => return Err("Not a COM_QUERY payload: it does not start with 0x03".to_string()),
+    }
+    if query_attributes {
+        let count = fields.lenenc()?;
+        // The parameter set count, always 1
+        fields.lenenc()?;
+        if count > 0 {
+            let count = usize::try_from(count).map_err(|_| "Truncated COM_QUERY payload".to_string())?;
+            let nulls = fields.take(count.div_ceil(8))?;
+            if fields.byte()? != 1 {
+                return Err("COM_QUERY query attributes without their types".to_string());
+            }
+            let mut kinds = Vec::with_capacity(count.min(payload.len()));
+            for _ in 0..count {
+                // The type, then a flags byte
+                kinds.push(fields.take(2)?[0]);
+                fields.skip_lenenc()?;
+            }
+            for (i, kind) in kinds.into_iter().enumerate() {
+                if nulls[i / 8] & (1 << (i % 8)) == 0 {
+                    fields.skip_value(kind)?;
+                }
+            }
+        }
+    }
+    Ok(&payload[fields.at..])
+}
+
+/// The NUL-terminated string at the start of `body`, and what follows it
+fn c_string<'a>(body: &'a [u8], message: &str) -> Result<(&'a [u8], &'a [u8]), String> {
+    match body.iter().position(|&b| b == 0) {
+        Some(end) => Ok((&body[..end], &body[end + 1..])),
+        None => Err(format!("Truncated {} message: missing NUL terminator", message)),
+    }
+}
+
+/// The query of the body of a PostgreSQL frontend message of type
+/// `message_type`: `"Q"` (simple `Query`) or `"P"` (`Parse`)
+pub fn postgres_query<'a>(body: &'a [u8], message_type: &str) -> Result<&'a [u8], String> {
+    match message_type {
+        "Q" => Ok(c_string(body, "Query")?.0),
+        "P" => {
+            let (_name, rest) = c_string(body, "Parse")?;
+            Ok(c_string(rest, "Parse")?.0)
+        }
+        _ => Err(format!("Unsupported message_type: {}", message_type)),
+    }
+}
+
+/// The Python codec for the MySQL character set `charset`
+pub fn mysql_encoding(charset: &str) -> &str {
+    match charset.to_ascii_lowercase().as_str() {
+        "utf8mb4" | "utf8mb3" | "utf8" => "utf-8",
+        // MySQL's latin1 is Windows-1252
+        "latin1" => "cp1252",
+        "ascii" => "ascii",
+        "binary" => "latin-1",
+        "latin2" => "iso8859-2",
+        "latin5" => "iso8859-9",
+        "latin7" => "iso8859-13",
+        "greek" => "iso8859-7",
+        "hebrew" => "iso8859-8",
+        "cp1250" => "cp1250",
+        "cp1251" => "cp1251",
+        "cp1256" => "cp1256",
+        "cp1257" => "cp1257",
+        "cp850" => "cp850",
+        "cp852" => "cp852",
+        "cp866" => "cp866",
+        "koi8r" => "koi8-r",
+        "koi8u" => "koi8-u",
+        "macroman" => "mac-roman",
+        "macce" => "mac-latin2",
+        "sjis" => "shift_jis",
+        "cp932" => "cp932",
+        "ujis" | "eucjpms" => "euc_jp",
+        "euckr" => "euc_kr",
+        "gb2312" => "gb2312",
+        "gbk" => "gbk",
+        "gb18030" => "gb18030",
+        "big5" => "big5",
+        "tis620" => "tis-620",
+        "ucs2" | "utf16" => "utf-16-be",
+        "utf16le" => "utf-16-le",
+        "utf32" => "utf-32-be",
+        _ => charset,
+    }
+}
+
+/// The Python codec for the PostgreSQL client encoding `name`, matched as
+/// the server does: in any case, ignoring other characters than letters and
+/// digits
+pub fn postgres_encoding(name: &str) -> &str {
+    let key: String = name.chars().filter(char::is_ascii_alphanumeric).map(|c| c.to_ascii_uppercase()).collect();
+    match key.as_str() {
+        "UTF8" | "UNICODE" => "utf-8",
+        "SQLASCII" => "ascii",
+        "LATIN1" => "iso8859-1",
+        "LATIN2" => "iso8859-2",
+        "LATIN3" => "iso8859-3",
+        "LATIN4" => "iso8859-4",
+        "LATIN5" => "iso8859-9",
+        "LATIN6" => "iso8859-10",
+        "LATIN7" => "iso8859-13",
+        "LATIN8" => "iso8859-14",
+        "LATIN9" => "iso8859-15",
+        "LATIN10" => "iso8859-16",
+        "ISO88595" => "iso8859-5",
+        "ISO88596" => "iso8859-6",
+        "ISO88597" => "iso8859-7",
+        "ISO88598" => "iso8859-8",
+        "WIN866" => "cp866",
+        "WIN874" => "cp874",
+        "WIN1250" => "cp1250",
+        "WIN1251" => "cp1251",
+        "WIN1252" => "cp1252",
+        "WIN1253" => "cp1253",
+        "WIN1254" => "cp1254",
+        "WIN1255" => "cp1255",
+        "WIN1256" => "cp1256",
+        "WIN1257" => "cp1257",
+        "WIN1258" => "cp1258",
+        "KOI8R" => "koi8-r",
+        "KOI8U" => "koi8-u",
+        "EUCJP" => "euc_jp",
+        "EUCJIS2004" => "euc_jis_2004",
+        "EUCKR" => "euc_kr",
+        "EUCCN" => "gb2312",
+        "SJIS" => "shift_jis",
+        "SHIFTJIS2004" => "shift_jis_2004",
+        "BIG5" => "big5",
+        "GBK" => "gbk",
+        "GB18030" => "gb18030",
+        "UHC" => "cp949",
+        "JOHAB" => "johab",
+        _ => name,
+    }
+}
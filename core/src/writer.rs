@@ -0,0 +1,1050 @@
+//! The serializer producing the text that fingerprints are computed from.
+//!
+//! A fingerprint is the hash of the normalized statement as written out, so
+//! any change to how it is written shifts every stored hash. All fingerprinted
+//! text (the normalized SQL, its shape and clause hashes) goes through
+//! `write()`, which lays statements out itself rather than through sqlparser's
+//! `Display`:
+//!
+//! - keywords are upper case, identifiers and function names are written as
+//!   normalization left them;
+//! - tokens are separated by a single space, list items by `", "`, and there
+//!   is no space inside parentheses or before a function's argument list;
+//! - clauses come in a fixed order: `WITH`, `SELECT [DISTINCT]` and its
+//!   projection, `FROM` and its joins, `WHERE`, `GROUP BY`, `HAVING`, then
+//!   `ORDER BY`, `LIMIT`, `OFFSET` for the query; `INSERT ... (columns)
+//!   source`, `UPDATE ... SET ... FROM ... WHERE` and `DELETE FROM ... USING
+//!   ... WHERE`, each followed by `RETURNING`.
+//!
+//! Tokens (identifiers, literals, operators, data types) and the
+//! dialect-specific constructs not laid out here (DDL, window specifications,
+//! table hints, ClickHouse and Hive clauses, ...) are written as sqlparser
+//! 0.61.0, pinned exactly in `Cargo.toml`, displays them. Version 1 writes
+//! everything as that release does, so that hashes stored before this
+//! serializer existed still match.
+//!
+//! Upgrading sqlparser means checking its output against
+//! `test_sqlfp_fingerprint_version` and `tests/hashes_refs.txt`. Constructs it
+//! displays differently are written here the old way; if that is not possible,
+//! `FINGERPRINT_VERSION` is bumped, so that checkpoints and manifests written
+//! under the previous version are recognized as such.
+
+use std::fmt::{Display, Write as _};
+
+use sqlparser::ast::{
+    Assignment, CastKind, Cte, Delete, Expr, FromTable, Function, FunctionArg, FunctionArgExpr, FunctionArguments,
+    GroupByExpr, Ident, Insert, Join, JoinConstraint, JoinOperator, LimitClause, ObjectName, OrderBy, OrderByExpr,
+    OrderByKind, OrderByOptions, Query, Select, SelectFlavor, SelectItem, SelectItemQualifiedWildcardKind, SetExpr,
+    SetQuantifier, Statement, TableAlias, TableFactor, TableWithJoins, UnaryOperator, Update, UpdateTableFromKind,
+    Values,
+};
+
+/// Version of the fingerprinted text, bumped whenever it changes for
+/// statements whose normalization did not
+pub const FINGERPRINT_VERSION: u64 = 1;
+
+/// A node of the AST that can be written as fingerprinted text
+pub trait Node {
+    fn write_to(&self, out: &mut String);
+}
+
+/// `node` as fingerprinted text
+pub fn write(node: &impl Node) -> String {
+    let mut out = String::new();
+    node.write_to(&mut out);
+    out
+}
+
+/// A token, or a construct not laid out here, as sqlparser displays it
+fn token(out: &mut String, node: &impl Display) {
+    let _ = write!(out, "{node}");
+}
+
+fn separated<T: Node>(out: &mut String, items: &[T], separator: &str) {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push_str(separator);
+        }
+        item.write_to(out);
+    }
+}
+
+fn comma_separated<T: Node>(out: &mut String, items: &[T]) {
+    separated(out, items, ", ");
+}
+
+/// `keyword` followed by a space, or nothing, depending on `present`
+fn keyword_if(out: &mut String, present: bool, keyword: &str) {
+    if present {
+        out.push_str(keyword);
+        out.push(' ');
+    }
+}
+
+impl<T: Node> Node for Box<T> {
+    fn write_to(&self, out: &mut String) {
+        (**self).write_to(out);
+    }
+}
+
+impl Node for Ident {
+    fn write_to(&self, out: &mut String) {
+        token(out, self);
+    }
+}
+
+impl Node for ObjectName {
+    fn write_to(&self, out: &mut String) {
+        token(out, self);
+    }
+}
+
+impl Node for Statement {
+    fn write_to(&self, out: &mut String) {
+        match self {
+            Statement::Query(query) => query.write_to(out),
+            Statement::Insert(insert) => insert.write_to(out),
+            Statement::Update(update) => update.write_to(out),
+            Statement::Delete(delete) => delete.write_to(out),
+            _ => token(out, self),
+        }
+    }
+}
+
+impl Node for Query {
+    fn write_to(&self, out: &mut String) {
+        if let Some(ref with) = self.with {
+            out.push_str("WITH ");
+            keyword_if(out, with.recursive, "RECURSIVE");
+            comma_separated(out, &with.cte_tables);
+            out.push(' ');
+        }
+        self.body.write_to(out);
+        if let Some(ref order_by) = self.order_by {
+            out.push(' ');
+            order_by.write_to(out);
+        }
+        if let Some(ref limit_clause) = self.limit_clause {
+            limit(out, limit_clause);
+        }
+        if let Some(ref settings) = self.settings {
+            out.push_str(" SETTINGS ");
+            for (i, setting) in settings.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                token(out, setting);
+            }
+        }
+        if let Some(ref fetch) = self.fetch {
+            out.push(' ');
+            token(out, fetch);
+        }
+        for lock in &self.locks {
+            out.push(' ');
+            token(out, lock);
+        }
+        if let Some(ref for_clause) = self.for_clause {
+            out.push(' ');
+            token(out, for_clause);
+        }
+        if let Some(ref format) = self.format_clause {
+            out.push(' ');
+            token(out, format);
+        }
+        for pipe_operator in &self.pipe_operators {
+            out.push_str(" |> ");
+            token(out, pipe_operator);
+        }
+    }
+}
+
+impl Node for Cte {
+    fn write_to(&self, out: &mut String) {
+        alias(out, &self.alias);
+        out.push_str(" AS ");
+        if let Some(ref materialized) = self.materialized {
+            token(out, materialized);
+            out.push(' ');
+        }
+        out.push('(');
+        self.query.write_to(out);
+        out.push(')');
+        if let Some(ref from) = self.from {
+            out.push_str(" FROM ");
+            from.write_to(out);
+        }
+    }
+}
+
+fn limit(out: &mut String, clause: &LimitClause) {
+    match clause {
+        LimitClause::LimitOffset { limit, offset, limit_by } => {
+            if let Some(limit) = limit {
+                out.push_str(" LIMIT ");
+                limit.write_to(out);
+            }
+            if let Some(offset) = offset {
+                out.push_str(" OFFSET ");
+                offset.value.write_to(out);
+                token(out, &offset.rows);
+            }
+            if !limit_by.is_empty() {
+                out.push_str(" BY ");
+                comma_separated(out, limit_by);
+            }
+        }
+        LimitClause::OffsetCommaLimit { offset, limit } => {
+            out.push_str(" LIMIT ");
+            offset.write_to(out);
+            out.push_str(", ");
+            limit.write_to(out);
+        }
+    }
+}
+
+impl Node for SetExpr {
+    fn write_to(&self, out: &mut String) {
+        match self {
+            SetExpr::Select(select) => select.write_to(out),
+            SetExpr::Query(query) => {
+                out.push('(');
+                query.write_to(out);
+                out.push(')');
+            }
+            SetExpr::Values(values) => values.write_to(out),
+            SetExpr::SetOperation { left, op, set_quantifier, right } => {
+                left.write_to(out);
+                out.push(' ');
+                token(out, op);
+                if !matches!(set_quantifier, SetQuantifier::None) {
+                    out.push(' ');
+                    token(out, set_quantifier);
+                }
+                out.push(' ');
+                right.write_to(out);
+            }
+            _ => token(out, self),
+        }
+    }
+}
+
+impl Node for Values {
+    fn write_to(&self, out: &mut String) {
+        out.push_str(if self.value_keyword { "VALUE" } else { "VALUES" });
+        for (i, row) in self.rows.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push(' ');
+            if self.explicit_row {
+                out.push_str("ROW");
+            }
+            out.push('(');
+            comma_separated(out, row);
+            out.push(')');
+        }
+    }
+}
+
+impl Node for Select {
+    fn write_to(&self, out: &mut String) {
+        match self.flavor {
+            SelectFlavor::Standard => out.push_str("SELECT"),
+            SelectFlavor::FromFirst => {
+                out.push_str("FROM ");
+                comma_separated(out, &self.from);
+                out.push_str(" SELECT");
+            }
+            SelectFlavor::FromFirstNoSelect => {
+                out.push_str("FROM ");
+                comma_separated(out, &self.from);
+            }
+        }
+        if let Some(ref hint) = self.optimizer_hint {
+            out.push(' ');
+            token(out, hint);
+        }
+        if let Some(ref value_table_mode) = self.value_table_mode {
+            out.push(' ');
+            token(out, value_table_mode);
+        }
+        let top = |out: &mut String| {
+            if let Some(ref top) = self.top {
+                out.push(' ');
+                token(out, top);
+            }
+        };
+        if self.top_before_distinct {
+            top(out);
+        }
+        if let Some(ref distinct) = self.distinct {
+            out.push(' ');
+            token(out, distinct);
+        }
+        if !self.top_before_distinct {
+            top(out);
+        }
+        if let Some(ref select_modifiers) = self.select_modifiers {
+            token(out, select_modifiers);
+        }
+        if !self.projection.is_empty() {
+            out.push(' ');
+            comma_separated(out, &self.projection);
+        }
+        if let Some(ref exclude) = self.exclude {
+            out.push(' ');
+            token(out, exclude);
+        }
+        if let Some(ref into) = self.into {
+            out.push(' ');
+            token(out, into);
+        }
+        if self.flavor == SelectFlavor::Standard && !self.from.is_empty() {
+            out.push_str(" FROM ");
+            comma_separated(out, &self.from);
+        }
+        for lateral_view in &self.lateral_views {
+            token(out, lateral_view);
+        }
+        if let Some(ref prewhere) = self.prewhere {
+            out.push_str(" PREWHERE ");
+            prewhere.write_to(out);
+        }
+        if let Some(ref selection) = self.selection {
+            out.push_str(" WHERE ");
+            selection.write_to(out);
+        }
+        for connect_by in &self.connect_by {
+            out.push(' ');
+            token(out, connect_by);
+        }
+        if !matches!(self.group_by, GroupByExpr::Expressions(ref exprs, _) if exprs.is_empty()) {
+            out.push(' ');
+            self.group_by.write_to(out);
+        }
+        for (keyword, exprs) in [(" CLUSTER BY ", &self.cluster_by), (" DISTRIBUTE BY ", &self.distribute_by)] {
+            if !exprs.is_empty() {
+                out.push_str(keyword);
+                comma_separated(out, exprs);
+            }
+        }
+        if !self.sort_by.is_empty() {
+            out.push_str(" SORT BY ");
+            comma_separated(out, &self.sort_by);
+        }
+        if let Some(ref having) = self.having {
+            out.push_str(" HAVING ");
+            having.write_to(out);
+        }
+        let window = |out: &mut String| {
+            if !self.named_window.is_empty() {
+                out.push_str(" WINDOW ");
+                for (i, window) in self.named_window.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    token(out, window);
+                }
+            }
+        };
+        if self.window_before_qualify {
+            window(out);
+        }
+        if let Some(ref qualify) = self.qualify {
+            out.push_str(" QUALIFY ");
+            qualify.write_to(out);
+        }
+        if !self.window_before_qualify {
+            window(out);
+        }
+    }
+}
+
+impl Node for SelectItem {
+    fn write_to(&self, out: &mut String) {
+        match self {
+            SelectItem::UnnamedExpr(expr) => expr.write_to(out),
+            SelectItem::ExprWithAlias { expr, alias } => {
+                expr.write_to(out);
+                out.push_str(" AS ");
+                alias.write_to(out);
+            }
+            SelectItem::QualifiedWildcard(kind, options) => {
+                match kind {
+                    SelectItemQualifiedWildcardKind::ObjectName(name) => name.write_to(out),
+                    SelectItemQualifiedWildcardKind::Expr(expr) => expr.write_to(out),
+                }
+                out.push_str(".*");
+                token(out, options);
+            }
+            SelectItem::Wildcard(options) => {
+                out.push('*');
+                token(out, options);
+            }
+        }
+    }
+}
+
+impl Node for GroupByExpr {
+    fn write_to(&self, out: &mut String) {
+        let modifiers = match self {
+            GroupByExpr::All(modifiers) => {
+                out.push_str("GROUP BY ALL");
+                modifiers
+            }
+            GroupByExpr::Expressions(exprs, modifiers) => {
+                out.push_str("GROUP BY ");
+                comma_separated(out, exprs);
+                modifiers
+            }
+        };
+        for modifier in modifiers {
+            out.push(' ');
+            token(out, modifier);
+        }
+    }
+}
+
+impl Node for OrderBy {
+    fn write_to(&self, out: &mut String) {
+        out.push_str("ORDER BY");
+        match self.kind {
+            OrderByKind::Expressions(ref exprs) => {
+                out.push(' ');
+                comma_separated(out, exprs);
+            }
+            OrderByKind::All(ref options) => {
+                out.push_str(" ALL");
+                order_options(out, options);
+            }
+        }
+        if let Some(ref interpolate) = self.interpolate {
+            out.push_str(" INTERPOLATE");
+            if let Some(ref exprs) = interpolate.exprs {
+                out.push_str(" (");
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    token(out, expr);
+                }
+                out.push(')');
+            }
+        }
+    }
+}
+
+impl Node for OrderByExpr {
+    fn write_to(&self, out: &mut String) {
+        self.expr.write_to(out);
+        order_options(out, &self.options);
+        if let Some(ref with_fill) = self.with_fill {
+            out.push(' ');
+            token(out, with_fill);
+        }
+    }
+}
+
+fn order_options(out: &mut String, options: &OrderByOptions) {
+    match options.asc {
+        Some(true) => out.push_str(" ASC"),
+        Some(false) => out.push_str(" DESC"),
+        None => {}
+    }
+    match options.nulls_first {
+        Some(true) => out.push_str(" NULLS FIRST"),
+        Some(false) => out.push_str(" NULLS LAST"),
+        None => {}
+    }
+}
+
+impl Node for TableWithJoins {
+    fn write_to(&self, out: &mut String) {
+        self.relation.write_to(out);
+        for join in &self.joins {
+            out.push(' ');
+            join.write_to(out);
+        }
+    }
+}
+
+/// The keywords of a join and its constraint, for the joins laid out here
+fn join_keywords(op: &JoinOperator) -> Option<(&'static str, &JoinConstraint)> {
+    Some(match op {
+        JoinOperator::Join(c) => ("JOIN", c),
+        JoinOperator::Inner(c) => ("INNER JOIN", c),
+        JoinOperator::Left(c) => ("LEFT JOIN", c),
+        JoinOperator::LeftOuter(c) => ("LEFT OUTER JOIN", c),
+        JoinOperator::Right(c) => ("RIGHT JOIN", c),
+        JoinOperator::RightOuter(c) => ("RIGHT OUTER JOIN", c),
+        JoinOperator::FullOuter(c) => ("FULL JOIN", c),
+        JoinOperator::CrossJoin(c) => ("CROSS JOIN", c),
+        _ => return None,
+    })
+}
+
+impl Node for Join {
+    fn write_to(&self, out: &mut String) {
+        let Some((keywords, constraint)) = join_keywords(&self.join_operator) else {
+            return token(out, self);
+        };
+        keyword_if(out, self.global, "GLOBAL");
+        let cross = matches!(self.join_operator, JoinOperator::CrossJoin(_));
+        keyword_if(out, !cross && matches!(constraint, JoinConstraint::Natural), "NATURAL");
+        out.push_str(keywords);
+        out.push(' ');
+        self.relation.write_to(out);
+        match constraint {
+            JoinConstraint::On(expr) => {
+                out.push_str(" ON ");
+                expr.write_to(out);
+            }
+            JoinConstraint::Using(columns) => {
+                out.push_str(" USING(");
+                comma_separated(out, columns);
+                out.push(')');
+            }
+            JoinConstraint::Natural | JoinConstraint::None => {}
+        }
+    }
+}
+
+fn alias(out: &mut String, alias: &TableAlias) {
+    keyword_if(out, alias.explicit, "AS");
+    alias.name.write_to(out);
+    if !alias.columns.is_empty() {
+        out.push_str(" (");
+        for (i, column) in alias.columns.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            token(out, column);
+        }
+        out.push(')');
+    }
+}
+
+impl Node for TableFactor {
+    fn write_to(&self, out: &mut String) {
+        match self {
+            TableFactor::Table {
+                name,
+                alias: table_alias,
+                args: None,
+                with_hints,
+                version: None,
+                partitions,
+                with_ordinality: false,
+                json_path: None,
+                sample: None,
+                index_hints,
+            } if with_hints.is_empty() && partitions.is_empty() && index_hints.is_empty() => {
+                name.write_to(out);
+                if let Some(table_alias) = table_alias {
+                    out.push(' ');
+                    alias(out, table_alias);
+                }
+            }
+            TableFactor::Derived { lateral, subquery, alias: derived_alias, sample: None } => {
+                keyword_if(out, *lateral, "LATERAL");
+                out.push('(');
+                subquery.write_to(out);
+                out.push(')');
+                if let Some(derived_alias) = derived_alias {
+                    out.push(' ');
+                    alias(out, derived_alias);
+                }
+            }
+            _ => token(out, self),
+        }
+    }
+}
+
+impl Node for Assignment {
+    fn write_to(&self, out: &mut String) {
+        token(out, &self.target);
+        out.push_str(" = ");
+        self.value.write_to(out);
+    }
+}
+
+fn returning(out: &mut String, items: &Option<Vec<SelectItem>>) {
+    if let Some(items) = items {
+        out.push_str(" RETURNING ");
+        comma_separated(out, items);
+    }
+}
+
+impl Node for Insert {
+    fn write_to(&self, out: &mut String) {
+        let hint = |out: &mut String| {
+            if let Some(ref hint) = self.optimizer_hint {
+                out.push(' ');
+                token(out, hint);
+            }
+        };
+        if let Some(ref or) = self.or {
+            out.push_str("INSERT");
+            hint(out);
+            out.push(' ');
+            token(out, or);
+            out.push_str(" INTO");
+        } else {
+            out.push_str(if self.replace_into { "REPLACE" } else { "INSERT" });
+            hint(out);
+            if let Some(ref priority) = self.priority {
+                out.push(' ');
+                token(out, priority);
+            }
+            for (present, keyword) in [
+                (self.ignore, " IGNORE"),
+                (self.overwrite, " OVERWRITE"),
+                (self.into, " INTO"),
+                (self.has_table_keyword, " TABLE"),
+            ] {
+                if present {
+                    out.push_str(keyword);
+                }
+            }
+        }
+        out.push(' ');
+        token(out, &self.table);
+        if let Some(ref table_alias) = self.table_alias {
+            out.push_str(" AS ");
+            table_alias.write_to(out);
+        }
+        out.push(' ');
+        if !self.columns.is_empty() {
+            out.push('(');
+            comma_separated(out, &self.columns);
+            out.push_str(") ");
+        }
+        if let Some(ref parts) = self.partitioned {
+            if !parts.is_empty() {
+                out.push_str("PARTITION (");
+                comma_separated(out, parts);
+                out.push_str(") ");
+            }
+        }
+        if !self.after_columns.is_empty() {
+            out.push('(');
+            comma_separated(out, &self.after_columns);
+            out.push_str(") ");
+        }
+        if let Some(ref settings) = self.settings {
+            out.push_str("SETTINGS ");
+            for (i, setting) in settings.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                token(out, setting);
+            }
+            out.push(' ');
+        }
+        if let Some(ref source) = self.source {
+            source.write_to(out);
+        } else if !self.assignments.is_empty() {
+            out.push_str("SET ");
+            comma_separated(out, &self.assignments);
+        } else if let Some(ref format_clause) = self.format_clause {
+            token(out, format_clause);
+        } else if self.columns.is_empty() {
+            out.push_str("DEFAULT VALUES");
+        }
+        if let Some(ref insert_alias) = self.insert_alias {
+            out.push_str(" AS ");
+            insert_alias.row_alias.write_to(out);
+            if let Some(ref columns) = insert_alias.col_aliases {
+                if !columns.is_empty() {
+                    out.push_str(" (");
+                    comma_separated(out, columns);
+                    out.push(')');
+                }
+            }
+        }
+        if let Some(ref on) = self.on {
+            token(out, on);
+        }
+        returning(out, &self.returning);
+    }
+}
+
+impl Node for Update {
+    fn write_to(&self, out: &mut String) {
+        out.push_str("UPDATE ");
+        if let Some(ref hint) = self.optimizer_hint {
+            token(out, hint);
+            out.push(' ');
+        }
+        if let Some(ref or) = self.or {
+            token(out, or);
+            out.push(' ');
+        }
+        self.table.write_to(out);
+        if let Some(UpdateTableFromKind::BeforeSet(ref from)) = self.from {
+            out.push_str(" FROM ");
+            comma_separated(out, from);
+        }
+        if !self.assignments.is_empty() {
+            out.push_str(" SET ");
+            comma_separated(out, &self.assignments);
+        }
+        if let Some(UpdateTableFromKind::AfterSet(ref from)) = self.from {
+            out.push_str(" FROM ");
+            comma_separated(out, from);
+        }
+        if let Some(ref selection) = self.selection {
+            out.push_str(" WHERE ");
+            selection.write_to(out);
+        }
+        returning(out, &self.returning);
+        if let Some(ref limit) = self.limit {
+            out.push_str(" LIMIT ");
+            limit.write_to(out);
+        }
+    }
+}
+
+impl Node for Delete {
+    fn write_to(&self, out: &mut String) {
+        out.push_str("DELETE");
+        if let Some(ref hint) = self.optimizer_hint {
+            out.push(' ');
+            token(out, hint);
+        }
+        if !self.tables.is_empty() {
+            out.push(' ');
+            comma_separated(out, &self.tables);
+        }
+        let (FromTable::WithFromKeyword(from) | FromTable::WithoutKeyword(from)) = &self.from;
+        out.push_str(if matches!(self.from, FromTable::WithFromKeyword(_)) { " FROM " } else { " " });
+        comma_separated(out, from);
+        if let Some(ref using) = self.using {
+            out.push_str(" USING ");
+            comma_separated(out, using);
+        }
+        if let Some(ref selection) = self.selection {
+            out.push_str(" WHERE ");
+            selection.write_to(out);
+        }
+        returning(out, &self.returning);
+        if !self.order_by.is_empty() {
+            out.push_str(" ORDER BY ");
+            comma_separated(out, &self.order_by);
+        }
+        if let Some(ref limit) = self.limit {
+            out.push_str(" LIMIT ");
+            limit.write_to(out);
+        }
+    }
+}
+
+impl Node for Function {
+    fn write_to(&self, out: &mut String) {
+        if self.uses_odbc_syntax {
+            return token(out, self);
+        }
+        self.name.write_to(out);
+        arguments(out, &self.parameters);
+        arguments(out, &self.args);
+        if !self.within_group.is_empty() {
+            out.push_str(" WITHIN GROUP (ORDER BY ");
+            comma_separated(out, &self.within_group);
+            out.push(')');
+        }
+        if let Some(ref filter) = self.filter {
+            out.push_str(" FILTER (WHERE ");
+            filter.write_to(out);
+            out.push(')');
+        }
+        if let Some(ref null_treatment) = self.null_treatment {
+            out.push(' ');
+            token(out, null_treatment);
+        }
+        if let Some(ref over) = self.over {
+            out.push_str(" OVER ");
+            token(out, over);
+        }
+    }
+}
+
+fn arguments(out: &mut String, arguments: &FunctionArguments) {
+    match arguments {
+        FunctionArguments::None => {}
+        FunctionArguments::Subquery(query) => {
+            out.push('(');
+            query.write_to(out);
+            out.push(')');
+        }
+        FunctionArguments::List(list) => {
+            out.push('(');
+            if let Some(ref duplicate_treatment) = list.duplicate_treatment {
+                token(out, duplicate_treatment);
+                out.push(' ');
+            }
+            comma_separated(out, &list.args);
+            if !list.clauses.is_empty() {
+                if !list.args.is_empty() {
+                    out.push(' ');
+                }
+                for (i, clause) in list.clauses.iter().enumerate() {
+                    if i > 0 {
+                        out.push(' ');
+                    }
+                    token(out, clause);
+                }
+            }
+            out.push(')');
+        }
+    }
+}
+
+impl Node for FunctionArg {
+    fn write_to(&self, out: &mut String) {
+        match self {
+            FunctionArg::Named { name, arg, operator } => {
+                name.write_to(out);
+                out.push(' ');
+                token(out, operator);
+                out.push(' ');
+                arg.write_to(out);
+            }
+            FunctionArg::ExprNamed { name, arg, operator } => {
+                name.write_to(out);
+                out.push(' ');
+                token(out, operator);
+                out.push(' ');
+                arg.write_to(out);
+            }
+            FunctionArg::Unnamed(arg) => arg.write_to(out),
+        }
+    }
+}
+
+impl Node for FunctionArgExpr {
+    fn write_to(&self, out: &mut String) {
+        match self {
+            FunctionArgExpr::Expr(expr) => expr.write_to(out),
+            FunctionArgExpr::QualifiedWildcard(prefix) => {
+                prefix.write_to(out);
+                out.push_str(".*");
+            }
+            FunctionArgExpr::Wildcard => out.push('*'),
+        }
+    }
+}
+
+/// `(query)`
+fn subquery(out: &mut String, query: &Query) {
+    out.push('(');
+    query.write_to(out);
+    out.push(')');
+}
+
+/// `expr [NOT ]keyword `, the start of a negatable predicate
+fn predicate(out: &mut String, expr: &Expr, negated: bool, keyword: &str) {
+    expr.write_to(out);
+    out.push(' ');
+    keyword_if(out, negated, "NOT");
+    out.push_str(keyword);
+    out.push(' ');
+}
+
+impl Node for Expr {
+    fn write_to(&self, out: &mut String) {
+        match self {
+            Expr::Identifier(ident) => ident.write_to(out),
+            Expr::CompoundIdentifier(idents) => separated(out, idents, "."),
+            Expr::Value(value) => token(out, value),
+            Expr::Wildcard(_) => out.push('*'),
+            Expr::QualifiedWildcard(prefix, _) => {
+                prefix.write_to(out);
+                out.push_str(".*");
+            }
+            Expr::IsNull(expr) => postfix(out, expr, " IS NULL"),
+            Expr::IsNotNull(expr) => postfix(out, expr, " IS NOT NULL"),
+            Expr::IsTrue(expr) => postfix(out, expr, " IS TRUE"),
+            Expr::IsNotTrue(expr) => postfix(out, expr, " IS NOT TRUE"),
+            Expr::IsFalse(expr) => postfix(out, expr, " IS FALSE"),
+            Expr::IsNotFalse(expr) => postfix(out, expr, " IS NOT FALSE"),
+            Expr::IsUnknown(expr) => postfix(out, expr, " IS UNKNOWN"),
+            Expr::IsNotUnknown(expr) => postfix(out, expr, " IS NOT UNKNOWN"),
+            Expr::IsDistinctFrom(left, right) => {
+                left.write_to(out);
+                out.push_str(" IS DISTINCT FROM ");
+                right.write_to(out);
+            }
+            Expr::IsNotDistinctFrom(left, right) => {
+                left.write_to(out);
+                out.push_str(" IS NOT DISTINCT FROM ");
+                right.write_to(out);
+            }
+            Expr::InList { expr, list, negated } => {
+                predicate(out, expr, *negated, "IN");
+                out.push('(');
+                comma_separated(out, list);
+                out.push(')');
+            }
+            Expr::InSubquery { expr, subquery: query, negated } => {
+                predicate(out, expr, *negated, "IN");
+                subquery(out, query);
+            }
+            Expr::Between { expr, negated, low, high } => {
+                predicate(out, expr, *negated, "BETWEEN");
+                low.write_to(out);
+                out.push_str(" AND ");
+                high.write_to(out);
+            }
+            Expr::BinaryOp { left, op, right } => {
+                left.write_to(out);
+                out.push(' ');
+                token(out, op);
+                out.push(' ');
+                right.write_to(out);
+            }
+            // sqlparser writes `ANY` without its trailing space here
+            Expr::ILike { any: true, escape_char: Some(_), .. } => token(out, self),
+            Expr::Like { negated, expr, pattern, escape_char, any }
+            | Expr::ILike { negated, expr, pattern, escape_char, any } => {
+                let keyword = if matches!(self, Expr::Like { .. }) { "LIKE" } else { "ILIKE" };
+                predicate(out, expr, *negated, keyword);
+                keyword_if(out, *any, "ANY");
+                pattern.write_to(out);
+                if let Some(escape_char) = escape_char {
+                    out.push_str(" ESCAPE ");
+                    token(out, escape_char);
+                }
+            }
+            Expr::AnyOp { left, compare_op, right, is_some } => {
+                quantified(out, left, compare_op, if *is_some { "SOME" } else { "ANY" }, right);
+            }
+            Expr::AllOp { left, compare_op, right } => quantified(out, left, compare_op, "ALL", right),
+            Expr::UnaryOp { op, expr } => match op {
+                UnaryOperator::PGPostfixFactorial => {
+                    expr.write_to(out);
+                    token(out, op);
+                }
+                UnaryOperator::Not
+                | UnaryOperator::Hash
+                | UnaryOperator::AtDashAt
+                | UnaryOperator::DoubleAt
+                | UnaryOperator::QuestionDash
+                | UnaryOperator::QuestionPipe => {
+                    token(out, op);
+                    out.push(' ');
+                    expr.write_to(out);
+                }
+                _ => {
+                    token(out, op);
+                    expr.write_to(out);
+                }
+            },
+            Expr::Cast { kind: CastKind::DoubleColon, expr, data_type, .. } => {
+                expr.write_to(out);
+                out.push_str("::");
+                token(out, data_type);
+            }
+            Expr::Cast { kind, expr, data_type, array, format } => {
+                out.push_str(match kind {
+                    CastKind::TryCast => "TRY_CAST(",
+                    CastKind::SafeCast => "SAFE_CAST(",
+                    _ => "CAST(",
+                });
+                expr.write_to(out);
+                out.push_str(" AS ");
+                token(out, data_type);
+                if *array && matches!(kind, CastKind::Cast) {
+                    out.push_str(" ARRAY");
+                }
+                if let Some(format) = format {
+                    out.push_str(" FORMAT ");
+                    token(out, format);
+                }
+                out.push(')');
+            }
+            Expr::Nested(expr) => {
+                out.push('(');
+                expr.write_to(out);
+                out.push(')');
+            }
+            Expr::Tuple(exprs) => {
+                out.push('(');
+                comma_separated(out, exprs);
+                out.push(')');
+            }
+            Expr::GroupingSets(sets) => grouping_sets(out, "GROUPING SETS (", sets, false),
+            Expr::Cube(sets) => grouping_sets(out, "CUBE (", sets, true),
+            Expr::Rollup(sets) => grouping_sets(out, "ROLLUP (", sets, true),
+            Expr::Function(function) => function.write_to(out),
+            Expr::Case { operand, conditions, else_result, .. } => {
+                out.push_str("CASE");
+                if let Some(operand) = operand {
+                    out.push(' ');
+                    operand.write_to(out);
+                }
+                for when in conditions {
+                    out.push_str(" WHEN ");
+                    when.condition.write_to(out);
+                    out.push_str(" THEN ");
+                    when.result.write_to(out);
+                }
+                if let Some(else_result) = else_result {
+                    out.push_str(" ELSE ");
+                    else_result.write_to(out);
+                }
+                out.push_str(" END");
+            }
+            Expr::Exists { subquery: query, negated } => {
+                keyword_if(out, *negated, "NOT");
+                out.push_str("EXISTS ");
+                subquery(out, query);
+            }
+            Expr::Subquery(query) => subquery(out, query),
+            _ => token(out, self),
+        }
+    }
+}
+
+/// The sets of `GROUPING SETS`, `CUBE` or `ROLLUP`, each in parentheses
+/// except, for the latter two, those of a single expression
+fn grouping_sets(out: &mut String, keywords: &str, sets: &[Vec<Expr>], bare_singletons: bool) {
+    out.push_str(keywords);
+    for (i, set) in sets.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        match set.as_slice() {
+            [expr] if bare_singletons => expr.write_to(out),
+            _ => {
+                out.push('(');
+                comma_separated(out, set);
+                out.push(')');
+            }
+        }
+    }
+    out.push(')');
+}
+
+fn postfix(out: &mut String, expr: &Expr, keywords: &str) {
+    expr.write_to(out);
+    out.push_str(keywords);
+}
+
+/// `left op ANY(right)`, without the parentheses when `right` is a subquery,
+/// which brings its own
+fn quantified(out: &mut String, left: &Expr, op: &impl Display, quantifier: &str, right: &Expr) {
+    left.write_to(out);
+    out.push(' ');
+    token(out, op);
+    out.push(' ');
+    out.push_str(quantifier);
+    let parenthesized = !matches!(right, Expr::Subquery(_));
+    if parenthesized {
+        out.push('(');
+    }
+    right.write_to(out);
+    if parenthesized {
+        out.push(')');
+    }
+}
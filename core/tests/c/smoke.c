@@ -0,0 +1,51 @@
+// Smoke test of the C API, linked against the library of the `ffi` feature:
+//
+// cargo rustc -p sqlfp-core --profile release-ffi --features ffi --crate-type cdylib
+// cc -I core/include core/tests/c/smoke.c -L target/release-ffi -lsqlfp_core -o target/smoke
+// LD_LIBRARY_PATH=target/release-ffi target/smoke
+
+#include <stdio.h>
+#include <string.h>
+
+#include "sqlfp.h"
+
+static int failures = 0;
+
+static void check(int ok, const char *what) {
+    if (!ok) {
+        fprintf(stderr, "FAIL: %s\n", what);
+        failures++;
+    }
+}
+
+int main(void) {
+    SqlfpResult result;
+
+    int status = sqlfp_normalize("SELECT * FROM users WHERE id = 42 AND name = 'x'", "postgresql", &result);
+    check(status == SQLFP_OK, "status of a statement");
+    check(status == SQLFP_OK && strcmp(result.normalized, "SELECT * FROM users WHERE id = ? AND name = ?") == 0,
+          "normalized");
+    check(status == SQLFP_OK && strlen(result.hash) == 64, "hash");
+    check(result.params_len == 2 && strcmp(result.params[0], "42") == 0 && strcmp(result.params[1], "'x'") == 0,
+          "params");
+    check(result.error == NULL, "no error");
+    sqlfp_free_result(&result);
+    check(result.normalized == NULL && result.params == NULL, "freed");
+    sqlfp_free_result(&result);
+
+    status = sqlfp_normalize("SELEC 1", NULL, &result);
+    check(status == SQLFP_PARSE_ERROR, "status of a parse error");
+    check(result.normalized == NULL && result.error != NULL, "parse error");
+    sqlfp_free_result(&result);
+
+    status = sqlfp_normalize("SELECT 1", "nosuch", &result);
+    check(status == SQLFP_INVALID_ARGUMENT && result.error != NULL, "unsupported dialect");
+    sqlfp_free_result(&result);
+
+    check(sqlfp_normalize("SELECT 1", NULL, NULL) == SQLFP_INVALID_ARGUMENT, "NULL result");
+
+    if (failures == 0) {
+        printf("ok\n");
+    }
+    return failures == 0 ? 0 : 1;
+}
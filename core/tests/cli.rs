@@ -0,0 +1,103 @@
+//! The `sqlfp` command of the `cli` feature, run as a shell would.
+
+#![cfg(feature = "cli")]
+
+use std::io::Write;
+
+use assert_cmd::Command;
+use serde_json::{json, Value};
+
+fn sqlfp() -> Command {
+    Command::cargo_bin("sqlfp").expect("the sqlfp binary is built with the cli feature")
+}
+
+/// The JSON lines of `stdout`
+fn lines(stdout: &[u8]) -> Vec<Value> {
+    String::from_utf8_lossy(stdout).lines().map(|line| serde_json::from_str(line).expect("a JSON line")).collect()
+}
+
+#[test]
+fn arguments() {
+    let output = sqlfp().args(["SELECT * FROM users WHERE id = 42", "select 1"]).assert().success();
+    let lines = lines(&output.get_output().stdout);
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["normalized"], "SELECT * FROM users WHERE id = ?");
+    assert_eq!(lines[0]["params"], json!(["42"]));
+    assert_eq!(lines[0]["statement_type"], "Query");
+    assert_eq!(lines[0]["hash"].as_str().map(str::len), Some(64));
+    assert_eq!(lines[1]["normalized"], "SELECT ?");
+}
+
+#[test]
+fn dialect_and_placeholder() {
+    let output = sqlfp()
+        .args(["--dialect", "postgres", "--placeholder=${n}", "SELECT a::int FROM t WHERE b = 'x' AND c = 2"])
+        .assert()
+        .success();
+    let normalized = "SELECT CAST(a AS INTEGER) FROM t WHERE b = $1 AND c = $2";
+    assert_eq!(lines(&output.get_output().stdout)[0]["normalized"], normalized);
+}
+
+#[test]
+fn stdin_script() {
+    // Split on `;`, a statement spanning lines
+    let output = sqlfp().write_stdin("SELECT 1;\nSELECT *\n  FROM t\n  WHERE a = 2;\n").assert().success();
+    let normalized: Vec<_> = lines(&output.get_output().stdout).iter().map(|line| line["normalized"].clone()).collect();
+    assert_eq!(normalized, [json!("SELECT ?"), json!("SELECT * FROM t WHERE a = ?")]);
+}
+
+#[test]
+fn stdin_lines() {
+    let input = "SELECT 1\nSELECT * FROM t WHERE a = 2;\n\nUPDATE t SET a = 3\n";
+    // Without `;`, lines are one statement
+    sqlfp().write_stdin(input).assert().failure().code(1);
+    let output = sqlfp().arg("--lines").write_stdin(input).assert().success();
+    let normalized: Vec<_> = lines(&output.get_output().stdout).iter().map(|line| line["normalized"].clone()).collect();
+    assert_eq!(normalized, [json!("SELECT ?"), json!("SELECT * FROM t WHERE a = ?"), json!("UPDATE t SET a = ?")]);
+}
+
+#[test]
+fn files_and_group() {
+    let mut file = tempfile();
+    writeln!(file.1, "SELECT * FROM t WHERE a = 1;\nSELECT * FROM t WHERE a = 2;\nSELECT b FROM u;").unwrap();
+    let output = sqlfp().args(["--group", "--file"]).arg(&file.0).assert().success();
+    let lines = lines(&output.get_output().stdout);
+    assert_eq!(lines.len(), 2);
+    assert_eq!((&lines[0]["normalized"], &lines[0]["count"]), (&json!("SELECT * FROM t WHERE a = ?"), &json!(2)));
+    assert_eq!((&lines[1]["normalized"], &lines[1]["count"]), (&json!("SELECT b FROM u"), &json!(1)));
+    assert!(lines[0].get("params").is_none());
+    std::fs::remove_file(&file.0).unwrap();
+}
+
+#[test]
+fn failed_statement() {
+    let output = sqlfp().args(["SELECT 1", "SELEC 1"]).assert().failure().code(1);
+    let lines = lines(&output.get_output().stdout);
+    assert_eq!(lines[0]["normalized"], "SELECT ?");
+    assert_eq!(lines[1]["sql"], "SELEC 1");
+    assert!(lines[1]["error"].is_string());
+}
+
+#[test]
+fn usage_errors() {
+    sqlfp().arg("--nosuch").assert().failure().code(2);
+    sqlfp().arg("--dialect").assert().failure().code(2);
+    sqlfp().args(["--dialect", "nosuch", "SELECT 1"]).assert().failure().code(2);
+    sqlfp().args(["--file", "/nonexistent/queries.sql"]).assert().failure().code(2);
+}
+
+#[test]
+fn help_and_version() {
+    let help = sqlfp().arg("--help").assert().success();
+    assert!(String::from_utf8_lossy(&help.get_output().stdout).starts_with("Usage: sqlfp"));
+    let version = sqlfp().arg("-V").assert().success();
+    let version = String::from_utf8_lossy(&version.get_output().stdout).trim().to_string();
+    assert_eq!(version, format!("sqlfp {}", env!("CARGO_PKG_VERSION")));
+}
+
+/// A new file in the temporary directory, and its handle
+fn tempfile() -> (std::path::PathBuf, std::fs::File) {
+    let path = std::env::temp_dir().join(format!("sqlfp-cli-{}.sql", std::process::id()));
+    let file = std::fs::File::create(&path).unwrap();
+    (path, file)
+}
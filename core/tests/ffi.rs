@@ -0,0 +1,98 @@
+//! The C API of the `ffi` feature, called as a C caller would.
+
+#![cfg(feature = "ffi")]
+
+use std::ffi::{c_char, CStr};
+use std::ptr;
+
+use sqlfp_core::ffi::{
+    sqlfp_free_result, sqlfp_normalize, SqlfpResult, SQLFP_INVALID_ARGUMENT, SQLFP_NUL_IN_OUTPUT, SQLFP_OK,
+    SQLFP_PARSE_ERROR,
+};
+
+fn empty() -> SqlfpResult {
+    SqlfpResult {
+        normalized: ptr::null_mut(),
+        hash: ptr::null_mut(),
+        params: ptr::null_mut(),
+        params_len: 0,
+        error: ptr::null_mut(),
+    }
+}
+
+unsafe fn text(ptr: *const c_char) -> Option<String> {
+    (!ptr.is_null()).then(|| CStr::from_ptr(ptr).to_str().unwrap().to_string())
+}
+
+#[test]
+fn normalize_ok() {
+    let mut result = empty();
+    unsafe {
+        let status = sqlfp_normalize(c"SELECT * FROM t WHERE a = 1 AND b = 'x'".as_ptr(), c"postgres".as_ptr(), &mut result);
+        assert_eq!(status, SQLFP_OK);
+        assert_eq!(text(result.normalized).as_deref(), Some("SELECT * FROM t WHERE a = ? AND b = ?"));
+        assert_eq!(text(result.hash).map(|hash| hash.len()), Some(64));
+        assert_eq!(result.error, ptr::null_mut());
+        let params: Vec<_> = (0..result.params_len).map(|i| text(*result.params.add(i)).unwrap()).collect();
+        assert_eq!(params, ["1", "'x'"]);
+        sqlfp_free_result(&mut result);
+        assert_eq!(result.normalized, ptr::null_mut());
+        assert_eq!(result.params, ptr::null_mut());
+        // Freeing again does nothing
+        sqlfp_free_result(&mut result);
+        sqlfp_free_result(ptr::null_mut());
+    }
+}
+
+#[test]
+fn normalize_no_params() {
+    let mut result = empty();
+    unsafe {
+        // NULL is the generic dialect
+        assert_eq!(sqlfp_normalize(c"SELECT a FROM t".as_ptr(), ptr::null(), &mut result), SQLFP_OK);
+        assert_eq!((result.params, result.params_len), (ptr::null_mut(), 0));
+        sqlfp_free_result(&mut result);
+    }
+}
+
+#[test]
+fn normalize_errors() {
+    let mut result = empty();
+    unsafe {
+        assert_eq!(sqlfp_normalize(c"SELEC 1".as_ptr(), ptr::null(), &mut result), SQLFP_PARSE_ERROR);
+        assert_eq!(result.normalized, ptr::null_mut());
+        assert!(text(result.error).is_some());
+        sqlfp_free_result(&mut result);
+
+        assert_eq!(sqlfp_normalize(c"SELECT 1".as_ptr(), c"nosuch".as_ptr(), &mut result), SQLFP_INVALID_ARGUMENT);
+        assert!(text(result.error).unwrap().contains("nosuch"));
+        sqlfp_free_result(&mut result);
+
+        assert_eq!(sqlfp_normalize(ptr::null(), ptr::null(), &mut result), SQLFP_INVALID_ARGUMENT);
+        assert_eq!(text(result.error).as_deref(), Some("sql is NULL"));
+        sqlfp_free_result(&mut result);
+
+        let invalid = [0xffu8, 0];
+        assert_eq!(sqlfp_normalize(invalid.as_ptr().cast(), ptr::null(), &mut result), SQLFP_INVALID_ARGUMENT);
+        assert_eq!(text(result.error).as_deref(), Some("sql is not UTF-8"));
+        sqlfp_free_result(&mut result);
+
+        assert_eq!(sqlfp_normalize(c"SELECT 1".as_ptr(), ptr::null(), ptr::null_mut()), SQLFP_INVALID_ARGUMENT);
+    }
+}
+
+#[test]
+fn normalize_nul_in_output() {
+    let mut result = empty();
+    unsafe {
+        // The MySQL escape `\0` is a NUL byte in the param
+        assert_eq!(sqlfp_normalize(c"SELECT 'a\\0b'".as_ptr(), c"mysql".as_ptr(), &mut result), SQLFP_NUL_IN_OUTPUT);
+        assert_eq!((result.normalized, result.params), (ptr::null_mut(), ptr::null_mut()));
+        assert!(text(result.error).unwrap().contains("NUL"));
+        sqlfp_free_result(&mut result);
+
+        // Without the NUL, the same statement is normalized
+        assert_eq!(sqlfp_normalize(c"SELECT 'a\\nb'".as_ptr(), c"mysql".as_ptr(), &mut result), SQLFP_OK);
+        sqlfp_free_result(&mut result);
+    }
+}
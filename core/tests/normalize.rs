@@ -0,0 +1,202 @@
+//! `normalize()` as a Rust caller uses it: dialects, options, the fields of
+//! the fingerprint and the errors.
+
+use sqlfp_core::{normalize, Dialect, Error, Fingerprint, Options};
+
+fn generic(sql: &str) -> Fingerprint {
+    normalize(sql, Dialect::GENERIC, &Options::default()).expect("normalizes")
+}
+
+fn sha256(text: &str) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(text.as_bytes()).iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[test]
+fn fingerprint_fields() {
+    let sql = "SELECT * FROM users WHERE id = 42 AND name = 'x' AND score > 1.5";
+    let fingerprint = generic(sql);
+    assert_eq!(fingerprint.normalized, "SELECT * FROM users WHERE id = ? AND name = ? AND score > ?");
+    assert_eq!(fingerprint.hash, sha256(&fingerprint.normalized));
+    assert_eq!(fingerprint.shape_hash, fingerprint.hash);
+    assert_eq!(fingerprint.shape, None);
+    assert_eq!(fingerprint.original, sql);
+    assert_eq!(fingerprint.params, ["42", "'x'", "1.5"]);
+    assert_eq!(fingerprint.param_types, [None, None, None]);
+    assert_eq!(fingerprint.param_kinds, [Some("integer"), None, Some("decimal")]);
+    let spans: Vec<_> = fingerprint.param_spans.iter().map(|span| span.map(|(start, end)| &sql[start..end])).collect();
+    assert_eq!(spans, [Some("42"), Some("'x'"), Some("1.5")]);
+    assert_eq!((fingerprint.placeholder.as_str(), fingerprint.placeholder_start), ("?", 1));
+    assert!(fingerprint.warnings.is_empty());
+    assert!(!fingerprint.passes.is_empty());
+    assert_eq!(fingerprint.statement_type, "Query");
+    assert_eq!(fingerprint.statement_class, "SELECT");
+    assert!(fingerprint.is_read_only && !fingerprint.is_ddl && !fingerprint.explained && !fingerprint.has_returning);
+    assert!(fingerprint.metrics.is_some());
+    assert_eq!((fingerprint.dialect_used, fingerprint.parse_path), ("generic", "ast"));
+    assert_eq!(fingerprint.options.dialect, "generic");
+}
+
+#[test]
+fn same_fingerprint_for_other_literals() {
+    let a = generic("select * from users where id = 1");
+    let b = generic("SELECT *\n  FROM users\n  WHERE id = 99");
+    assert_eq!((a.normalized, a.hash), (b.normalized, b.hash));
+    assert_ne!(generic("SELECT * FROM users WHERE id = 1").hash, generic("SELECT * FROM orders WHERE id = 1").hash);
+}
+
+#[test]
+fn statement_kinds() {
+    let insert = generic("INSERT INTO t (a, b) VALUES (1, 'x') RETURNING id");
+    assert_eq!(insert.normalized, "INSERT INTO t (a, b) VALUES (?, ?) RETURNING id");
+    assert_eq!((insert.statement_class, insert.is_read_only, insert.has_returning), ("INSERT", false, true));
+    let update = generic("UPDATE t SET a = 1 WHERE b = 2");
+    assert_eq!((update.statement_type.as_str(), update.statement_class), ("Update", "UPDATE"));
+    assert_eq!(generic("DELETE FROM t WHERE a = 1").statement_class, "DELETE");
+    let ddl = generic("CREATE TABLE t (a INT)");
+    assert_eq!((ddl.statement_class, ddl.is_ddl), ("DDL", true));
+}
+
+#[test]
+fn dialects() {
+    let cases = [
+        (Dialect::POSTGRESQL, "SELECT a::int FROM t WHERE b = E'x'", "SELECT CAST(a AS INTEGER) FROM t WHERE b = ?"),
+        (Dialect::MYSQL, "SELECT `a` FROM t WHERE b = \"x\"", "SELECT `a` FROM t WHERE b = ?"),
+        (Dialect::MSSQL, "SELECT TOP 10 [a] FROM t WHERE b = N'x'", "SELECT TOP 10 [a] FROM t WHERE b = ?"),
+        (Dialect::BIGQUERY, "SELECT a FROM `p.d.t` WHERE b = \"x\"", "SELECT a FROM p.d.t WHERE b = ?"),
+        (Dialect::SQLITE, "SELECT a FROM t WHERE b = 1", "SELECT a FROM t WHERE b = ?"),
+    ];
+    for (dialect, sql, normalized) in cases {
+        let fingerprint = normalize(sql, dialect, &Options::default()).unwrap();
+        assert_eq!(fingerprint.normalized, normalized, "{}", dialect.name());
+        assert_eq!(fingerprint.dialect_used, dialect.name());
+    }
+    // The prefix of a prefixed string is kept apart from its value
+    let mssql = normalize("SELECT * FROM t WHERE b = N'x'", Dialect::MSSQL, &Options::default()).unwrap();
+    assert_eq!(mssql.param_types, [Some("N".to_string())]);
+}
+
+#[test]
+fn dialect_names() {
+    assert_eq!(Dialect::new("postgres").unwrap(), Dialect::POSTGRESQL);
+    assert_eq!(Dialect::new("mysql").unwrap().name(), "mysql");
+    assert!(matches!(Dialect::new("nosuch"), Err(Error::Options(message)) if message.contains("nosuch")));
+}
+
+#[test]
+fn auto_dialect() {
+    let fingerprint = normalize("SELECT `a` FROM t WHERE b = 1", Dialect::AUTO, &Options::default()).unwrap();
+    assert_eq!(fingerprint.dialect_used, "mysql");
+    assert_eq!(fingerprint.normalized, "SELECT `a` FROM t WHERE b = ?");
+}
+
+#[test]
+fn placeholder_options() {
+    let options = Options { placeholder: "${n}".to_string(), ..Options::default() };
+    let fingerprint = normalize("SELECT * FROM t WHERE a = 1 AND b = 2", Dialect::POSTGRESQL, &options).unwrap();
+    assert_eq!(fingerprint.normalized, "SELECT * FROM t WHERE a = $1 AND b = $2");
+    let options = Options { placeholder: ":{n}".to_string(), placeholder_start: 0, ..Options::default() };
+    let fingerprint = normalize("SELECT * FROM t WHERE a = 1 AND b = 2", Dialect::ORACLE, &options).unwrap();
+    assert_eq!(fingerprint.normalized, "SELECT * FROM t WHERE a = :0 AND b = :1");
+    assert_eq!(fingerprint.placeholder_start, 0);
+}
+
+#[test]
+fn collapse_options() {
+    let sql = "SELECT * FROM t WHERE a IN (1, 2, 3)";
+    assert_eq!(generic(sql).normalized, "SELECT * FROM t WHERE a IN (?, ?, ?)");
+    let fingerprint = generic(sql);
+    assert_eq!(fingerprint.shape.as_deref(), Some("SELECT * FROM t WHERE a IN (?)"));
+    assert_eq!(fingerprint.shape_hash, generic("SELECT * FROM t WHERE a IN (1)").shape_hash);
+
+    let options = Options { collapse_in_lists: true, ..Options::default() };
+    let collapsed = normalize(sql, Dialect::GENERIC, &options).unwrap();
+    assert_eq!(collapsed.normalized, "SELECT * FROM t WHERE a IN (?)");
+    assert_eq!(collapsed.params, ["1", "2", "3"]);
+
+    let options = Options { collapse_values: true, ..Options::default() };
+    let values = normalize("INSERT INTO t VALUES (1, 2), (3, 4)", Dialect::GENERIC, &options).unwrap();
+    assert_eq!(values.normalized, "INSERT INTO t VALUES (?, ?)");
+    assert_eq!(values.params, ["1", "2", "3", "4"]);
+}
+
+#[test]
+fn collect_params_option() {
+    let options = Options { collect_params: false, ..Options::default() };
+    let fingerprint = normalize("SELECT * FROM t WHERE a = 1", Dialect::GENERIC, &options).unwrap();
+    assert_eq!(fingerprint.normalized, "SELECT * FROM t WHERE a = ?");
+    assert!(fingerprint.params.is_empty() && fingerprint.param_spans.is_empty());
+    assert_eq!(fingerprint.hash, generic("SELECT * FROM t WHERE a = 1").hash);
+}
+
+#[test]
+fn keep_options() {
+    let sql = "SELECT * FROM t WHERE a IS NULL LIMIT 10 OFFSET 20";
+    assert_eq!(generic(sql).normalized, "SELECT * FROM t WHERE a IS NULL LIMIT ? OFFSET ?");
+    let options = Options { keep_limit: true, keep_offset: true, ..Options::default() };
+    let fingerprint = normalize(sql, Dialect::GENERIC, &options).unwrap();
+    assert_eq!(fingerprint.normalized, "SELECT * FROM t WHERE a IS NULL LIMIT 10 OFFSET 20");
+    assert!(fingerprint.params.is_empty());
+}
+
+#[test]
+fn compat_option() {
+    // The 0.1 rules leave the source query of INSERT ... SELECT untouched
+    let sql = "INSERT INTO t SELECT * FROM a AS x";
+    let options = Options { compat: Some("0.1".to_string()), ..Options::default() };
+    let fingerprint = normalize(sql, Dialect::GENERIC, &options).unwrap();
+    assert_eq!(fingerprint.warnings, ["explicit table alias left in Insert"]);
+    assert!(generic(sql).warnings.is_empty());
+}
+
+#[test]
+fn parse_errors() {
+    assert!(matches!(normalize("SELEC 1", Dialect::GENERIC, &Options::default()), Err(Error::Parse(_))));
+    assert!(matches!(normalize("", Dialect::GENERIC, &Options::default()), Err(Error::Parse(_))));
+}
+
+#[test]
+fn option_errors() {
+    let options = [
+        Options { compat: Some("9.9".to_string()), ..Options::default() },
+        Options { placeholder_collision: "nope".to_string(), ..Options::default() },
+        Options { hash_algorithm: "crc32".to_string(), ..Options::default() },
+        Options { disabled_passes: vec!["nosuch-pass".to_string()], ..Options::default() },
+    ];
+    for options in options {
+        let result = normalize("SELECT 1", Dialect::GENERIC, &options);
+        assert!(matches!(result, Err(Error::Options(_))), "{:?}", options);
+    }
+}
+
+#[test]
+fn strict_errors() {
+    let sql = "INSERT INTO t SELECT * FROM a AS x";
+    let options = Options { compat: Some("0.1".to_string()), strict: true, ..Options::default() };
+    match normalize(sql, Dialect::GENERIC, &options) {
+        Err(Error::Unnormalizable { message, warnings }) => {
+            assert!(message.contains("explicit table alias left in Insert"));
+            assert_eq!(warnings, ["explicit table alias left in Insert"]);
+        }
+        other => panic!("expected Unnormalizable, got {:?}", other.map(|fingerprint| fingerprint.normalized)),
+    }
+    let options = Options { strict: true, ..Options::default() };
+    assert_eq!(normalize("SELECT * FROM a AS x", Dialect::GENERIC, &options).unwrap().normalized, "SELECT * FROM a x");
+}
+
+#[test]
+fn placeholder_collisions() {
+    let sql = "SELECT [x?y] FROM t WHERE a = 1";
+    let fingerprint = normalize(sql, Dialect::MSSQL, &Options::default()).unwrap();
+    assert_eq!(fingerprint.normalized, "SELECT [x?y] FROM t WHERE a = ?");
+    let options = Options { placeholder_collision: "raise".to_string(), ..Options::default() };
+    match normalize(sql, Dialect::MSSQL, &options) {
+        Err(Error::PlaceholderCollision(message)) => {
+            assert!(message.starts_with("Placeholder \"?\" also occurs 1 time"))
+        }
+        other => panic!("expected PlaceholderCollision, got {:?}", other.map(|fingerprint| fingerprint.normalized)),
+    }
+    let options = Options { placeholder_collision: "escape".to_string(), ..Options::default() };
+    let escaped = normalize("SELECT `?` FROM t WHERE a = 1", Dialect::MYSQL, &options).unwrap();
+    assert_eq!(escaped.normalized, "SELECT `??` FROM t WHERE a = ?");
+}
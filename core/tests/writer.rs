@@ -0,0 +1,158 @@
+//! The fingerprinted text `writer::write()` lays statements out as.
+//!
+//! Version 1 of the text is what sqlparser 0.61.0 displays, so each case is
+//! also checked against `Display`: a difference means the fingerprints of
+//! the construct changed.
+
+use sqlparser::dialect::{BigQueryDialect, Dialect, GenericDialect, MsSqlDialect, MySqlDialect, PostgreSqlDialect};
+
+use sqlfp_core::parse_first_statement;
+use sqlfp_core::writer::{write, FINGERPRINT_VERSION};
+
+/// `sql` parsed with `dialect` and written, checked against `Display`
+fn written_with(sql: &str, dialect: &dyn Dialect) -> String {
+    let stmt = parse_first_statement(sql, dialect).expect("parses");
+    let text = write(&stmt);
+    assert_eq!(text, stmt.to_string(), "written differently from Display");
+    text
+}
+
+fn written(sql: &str) -> String {
+    written_with(sql, &GenericDialect {})
+}
+
+/// Each statement of `cases` is written as itself
+fn assert_round_trips(cases: &[&str]) {
+    for sql in cases {
+        assert_eq!(written(sql), *sql);
+    }
+}
+
+#[test]
+fn version() {
+    assert_eq!(FINGERPRINT_VERSION, 1);
+}
+
+#[test]
+fn layout() {
+    // Keywords upper case, single spaces, ", " between list items
+    assert_eq!(written("select  a,b\n from\tt  where a=1"), "SELECT a, b FROM t WHERE a = 1");
+    assert_eq!(written("SELECT count( * ), f( a , b ) FROM t"), "SELECT count(*), f(a, b) FROM t");
+    assert_eq!(written("SELECT DISTINCT a FROM t"), "SELECT DISTINCT a FROM t");
+}
+
+#[test]
+fn clause_order() {
+    assert_round_trips(&[
+        "SELECT a, count(*) FROM t WHERE b = 1 GROUP BY a HAVING count(*) > 1 ORDER BY a DESC LIMIT 10 OFFSET 5",
+        "SELECT a FROM t ORDER BY a ASC NULLS LAST, b",
+        "SELECT t.* FROM t",
+    ]);
+    // Select item aliases are written with AS, table aliases as given
+    assert_eq!(written("SELECT a x, b AS y FROM t u"), "SELECT a AS x, b AS y FROM t u");
+    assert_eq!(written("SELECT a FROM t AS u"), "SELECT a FROM t AS u");
+}
+
+#[test]
+fn joins() {
+    assert_round_trips(&[
+        "SELECT * FROM a JOIN b ON a.id = b.id LEFT JOIN c USING(id)",
+        "SELECT * FROM a LEFT OUTER JOIN b ON a.id = b.id",
+        "SELECT * FROM a CROSS JOIN b",
+        "SELECT * FROM a, b WHERE a.id = b.id",
+        "SELECT * FROM (SELECT a FROM t) AS s NATURAL JOIN u",
+    ]);
+}
+
+#[test]
+fn set_operations() {
+    assert_round_trips(&[
+        "SELECT a FROM t UNION SELECT a FROM u",
+        "SELECT a FROM t UNION ALL SELECT a FROM u",
+        "SELECT a FROM t EXCEPT SELECT a FROM u",
+        "SELECT a FROM t INTERSECT SELECT a FROM u",
+        "(SELECT a FROM t) UNION (SELECT a FROM u) ORDER BY a",
+        "SELECT a FROM t UNION SELECT a FROM u UNION ALL SELECT a FROM v",
+    ]);
+}
+
+#[test]
+fn ctes() {
+    assert_round_trips(&[
+        "WITH x AS (SELECT a FROM t) SELECT * FROM x",
+        "WITH x AS (SELECT a FROM t), y (b) AS (SELECT a FROM x) SELECT * FROM y",
+        "WITH RECURSIVE r AS (SELECT 1 UNION ALL SELECT n + 1 FROM r WHERE n < 10) SELECT * FROM r",
+    ]);
+}
+
+#[test]
+fn windows() {
+    assert_round_trips(&[
+        "SELECT row_number() OVER (PARTITION BY a ORDER BY b) FROM t",
+        "SELECT sum(a) OVER (ORDER BY b ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW) FROM t",
+        "SELECT sum(a) OVER w FROM t WINDOW w AS (PARTITION BY b)",
+        "SELECT count(*) FILTER (WHERE a > 1) FROM t",
+    ]);
+}
+
+#[test]
+fn case_expressions() {
+    assert_round_trips(&[
+        "SELECT CASE WHEN a = 1 THEN 'x' WHEN a = 2 THEN 'y' ELSE 'z' END FROM t",
+        "SELECT CASE a WHEN 1 THEN 'x' END FROM t",
+        "SELECT CASE WHEN a IS NULL THEN 0 ELSE CASE WHEN b THEN 1 END END FROM t",
+    ]);
+}
+
+#[test]
+fn casts() {
+    assert_round_trips(&[
+        "SELECT CAST(a AS INT) FROM t",
+        "SELECT TRY_CAST(a AS VARCHAR(10)) FROM t",
+        "SELECT CAST(a AS DECIMAL(10,2)) FROM t",
+    ]);
+    assert_eq!(written_with("SELECT a::int FROM t", &PostgreSqlDialect {}), "SELECT a::INT FROM t");
+    let safe_cast = "SELECT SAFE_CAST(a AS INT64) FROM t";
+    assert_eq!(written_with(safe_cast, &BigQueryDialect {}), safe_cast);
+}
+
+#[test]
+fn quoting() {
+    assert_eq!(written("SELECT \"a b\", \"A\" FROM \"t\""), "SELECT \"a b\", \"A\" FROM \"t\"");
+    assert_eq!(written_with("SELECT `a` FROM `db`.`t`", &MySqlDialect {}), "SELECT `a` FROM `db`.`t`");
+    assert_eq!(written_with("SELECT [a] FROM [dbo].[t]", &MsSqlDialect {}), "SELECT [a] FROM [dbo].[t]");
+    // Strings keep their quotes and escapes
+    assert_eq!(written("SELECT 'it''s', E'x' FROM t WHERE a = 'b'"), "SELECT 'it''s', E'x' FROM t WHERE a = 'b'");
+}
+
+#[test]
+fn dml() {
+    assert_round_trips(&[
+        "INSERT INTO t (a, b) VALUES (1, 'x'), (2, 'y')",
+        "INSERT INTO t SELECT * FROM u",
+        "UPDATE t SET a = 1, b = b + 1 WHERE c = 2",
+        "DELETE FROM t WHERE a IN (SELECT a FROM u)",
+    ]);
+    assert_eq!(
+        written_with("INSERT INTO t (a) VALUES (1) RETURNING id", &PostgreSqlDialect {}),
+        "INSERT INTO t (a) VALUES (1) RETURNING id"
+    );
+    assert_eq!(
+        written_with("UPDATE t SET a = u.a FROM u WHERE t.id = u.id RETURNING t.id", &PostgreSqlDialect {}),
+        "UPDATE t SET a = u.a FROM u WHERE t.id = u.id RETURNING t.id"
+    );
+    assert_eq!(
+        written_with("DELETE FROM t USING u WHERE t.id = u.id", &PostgreSqlDialect {}),
+        "DELETE FROM t USING u WHERE t.id = u.id"
+    );
+}
+
+#[test]
+fn expressions() {
+    assert_round_trips(&[
+        "SELECT * FROM t WHERE a BETWEEN 1 AND 2 AND b NOT IN (1, 2) AND c LIKE 'x%' AND d IS NOT NULL",
+        "SELECT * FROM t WHERE EXISTS (SELECT 1 FROM u WHERE u.id = t.id)",
+        "SELECT -a, NOT b, (a + b) * c FROM t",
+        "SELECT * FROM t WHERE a = ANY(SELECT a FROM u)",
+    ]);
+}
@@ -1,6 +1,15 @@
-from typing import Literal, final
+import datetime
+import os
+from typing import IO, Any, Callable, Iterable, Iterator, Literal, Mapping, Optional, Sequence, Tuple, TypedDict, Union, final, overload
+
+from typing_extensions import Unpack
 
 __version__: str
+FINGERPRINT_VERSION: int
+"""Version of the text fingerprints are computed from. It is bumped,
+independently of ``__version__``, whenever a release writes a statement's
+normalized SQL differently without changing its normalization, e.g. after a
+parser upgrade; hashes of different versions are not comparable."""
 
 Dialect = Literal[
     "generic",
@@ -12,8 +21,286 @@ Dialect = Literal[
     "sqlite",
     "mssql",
     "oracle",
+    "teradata",
+    "vertica",
+    "informix",
+    "bigquery",
+    "snowflake",
+    "clickhouse",
+    "duckdb",
+    "hive",
+    "redshift",
+    "databricks",
+    "spark",
+    "auto",
+]
+
+Clause = Literal[
+    "select",
+    "from",
+    "join",
+    "where",
+    "group_by",
+    "having",
+    "qualify",
+    "order_by",
+    "limit",
+    "offset",
+    "values",
+    "set",
+    "returning",
+    "into",
+    "other",
+]
+
+Redactor = Callable[[int, Clause, str], Optional[str]]
+
+RewriteRule = Mapping[str, str]
+
+PassHook = Callable[[str], Optional[str]]
+
+Pass = Literal[
+    "alias-normalize",
+    "join-normalize",
+    "asc-strip",
+    "nulls-strip",
+    "select-all-strip",
+    "paren-canonicalize",
+    "function-uppercase",
+    "boolean-uppercase",
+    "cast-canonicalize",
+    "cte-rename",
+    "insert-column-sort",
+    "function-schema-normalize",
+    "negative-literal-fold",
+    "concat-canonicalize",
+    "case-canonicalize",
+    "distinct-from-canonicalize",
+    "case-arm-collapse",
+    "current-time-canonicalize",
+    "function-synonym-canonicalize",
+    "wildcard-canonicalize",
+    "grouping-canonicalize",
+    "join-using-canonicalize",
+    "commutative-canonicalize",
+    "predicate-sort",
+    "literal-parameterize",
 ]
 
+PlaceholderCollision = Literal["report", "raise", "numbered", "escape"]
+
+PlaceholderStyle = Literal["question", "dollar", "colon", "pyformat"]
+
+HashAlgorithm = Literal["sha256", "xxhash64", "blake3"]
+
+HashEncoding = Literal["hex", "base64", "int"]
+
+IgnoredClause = Literal["order_by", "limit", "offset"]
+
+class PooledEngine(TypedDict):
+    dialect: str
+    placeholder: str
+    placeholder_start: int
+    compat: Optional[str]
+    templates: bool
+    strict: bool
+    passes: Optional[list[Optional[Pass]]]
+    disabled_passes: list[Pass]
+    enabled_passes: list[Pass]
+    function_case: Literal["upper", "builtins"]
+    identifier_case: Literal["preserve", "lower", "upper"]
+    clause_hashes: bool
+    max_subquery_depth: Optional[int]
+    routing_columns: list[str]
+    placeholder_collision: PlaceholderCollision
+    ignore_clauses: list[IgnoredClause]
+    table_columns: dict[str, list[str]]
+    function_synonyms: dict[str, str]
+    collect_params: bool
+    max_params: Optional[int]
+    truncated_input: bool
+    lenient: bool
+    collapse_in_lists: bool
+    collapse_values: bool
+    keep_limit: bool
+    keep_offset: bool
+    keep_null: bool
+    strip_qualifiers: Union[bool, list[str]]
+    ignore_aliases: bool
+    anonymize: bool
+    comments: bool
+    keep_hints: bool
+    unwrap_explain: bool
+    prepared: bool
+    hash_algorithm: HashAlgorithm
+    hash_encoding: HashEncoding
+    hits: int
+
+class Config(TypedDict, total=False):
+    dialect: Dialect
+    placeholder: str
+    placeholder_start: int
+    style: PlaceholderStyle
+    compat: str
+    templates: bool
+    strict: bool
+    encoding: str
+    errors: str
+    passes: list[Pass]
+    disable_passes: list[Pass]
+    enable_passes: list[Pass]
+    function_case: Literal["upper", "builtins"]
+    identifier_case: Literal["preserve", "lower", "upper"]
+    clause_hashes: bool
+    max_subquery_depth: int
+    routing_columns: list[str]
+    placeholder_collision: PlaceholderCollision
+    ignore_clauses: list[IgnoredClause]
+    table_columns: dict[str, list[str]]
+    function_synonyms: dict[str, str]
+    collect_params: bool
+    max_params: int
+    truncated_input: bool
+    lenient: bool
+    collapse_in_lists: bool
+    collapse_values: bool
+    keep_limit: bool
+    keep_offset: bool
+    keep_null: bool
+    strip_qualifiers: Union[bool, list[str]]
+    ignore_aliases: bool
+    anonymize: bool
+    comments: bool
+    keep_hints: bool
+    unwrap_explain: bool
+    prepared: bool
+    hash_algorithm: HashAlgorithm
+    hash_encoding: HashEncoding
+
+class EngineOptions(TypedDict, total=False):
+    """The keyword arguments of :func:`normalize` that set up its engine,
+    taken by every function normalizing statements. ``None`` leaves an
+    option to the :func:`configure` default."""
+
+    compat: Optional[str]
+    templates: Optional[bool]
+    strict: Optional[bool]
+    disable_passes: Optional[Iterable[Pass]]
+    placeholder_start: Optional[int]
+    style: Optional[PlaceholderStyle]
+    enable_passes: Optional[Iterable[Pass]]
+    function_case: Optional[Literal["upper", "builtins"]]
+    identifier_case: Optional[Literal["preserve", "lower", "upper"]]
+    clause_hashes: Optional[bool]
+    max_subquery_depth: Optional[int]
+    routing_columns: Optional[Iterable[str]]
+    placeholder_collision: Optional[PlaceholderCollision]
+    ignore_clauses: Optional[Iterable[IgnoredClause]]
+    table_columns: Optional[Mapping[str, Sequence[str]]]
+    function_synonyms: Optional[Mapping[str, str]]
+    collect_params: Optional[bool]
+    max_params: Optional[int]
+    truncated_input: Optional[bool]
+    lenient: Optional[bool]
+    collapse_in_lists: Optional[bool]
+    collapse_values: Optional[bool]
+    keep_limit: Optional[bool]
+    keep_offset: Optional[bool]
+    keep_null: Optional[bool]
+    strip_qualifiers: Union[bool, Iterable[str], None]
+    ignore_aliases: Optional[bool]
+    anonymize: Optional[bool]
+    comments: Optional[bool]
+    keep_hints: Optional[bool]
+    unwrap_explain: Optional[bool]
+    prepared: Optional[bool]
+    hash_algorithm: Optional[HashAlgorithm]
+    hash_encoding: Optional[HashEncoding]
+
+class CallOptions(EngineOptions, total=False):
+    """The options of :func:`normalize` past ``dialect`` and ``placeholder``."""
+
+    encoding: Optional[str]
+    errors: Optional[str]
+    passes: Optional[Iterable[Pass | PassHook]]
+
+class BoundOptions(CallOptions, total=False):
+    """The options of :class:`Normalizer` and :func:`normalize_file`, which
+    take ``dialect`` and ``placeholder`` by keyword."""
+
+    dialect: Optional[Dialect]
+    placeholder: Optional[str]
+
+class RecordedOptions(EngineOptions, total=False):
+    """The arguments of :class:`NormalizeOptions`: those of
+    :func:`normalize` that make up a fingerprint, with pass names only."""
+
+    dialect: Optional[Dialect]
+    placeholder: Optional[str]
+    passes: Optional[Iterable[Pass]]
+
+class DefaultOptions(RecordedOptions, total=False):
+    """The options :func:`configure` sets defaults for."""
+
+    encoding: Optional[str]
+    errors: Optional[str]
+
+class Stats(TypedDict):
+    statements_normalized: int
+    parse_failures: int
+    cache_hits: int
+    dialects: dict[str, int]
+
+class VerifyReport(TypedDict):
+    stable: bool
+    normalized: str
+    hash: str
+    renormalized: Optional[str]
+    renormalized_hash: Optional[str]
+    error: Optional[str]
+
+ParamStyle = Literal["qmark", "numeric", "named", "format", "pyformat"]
+
+class MergedParam(TypedDict):
+    value: Any
+    """The driver-bound value, or the SQL text of an extracted literal."""
+    key: int | str | None
+    """Index or name of the driver parameter; ``None`` for literals."""
+    bound: bool
+    """Whether the value was bound by the driver, i.e. already parameterized."""
+
+class Error(ValueError):
+    """Base class of the errors raised for the statements and options given
+    to sqlfp; a ``ValueError``, as they all were before."""
+
+class ParseError(Error):
+    """Raised when a statement does not parse."""
+
+    line: Optional[int]
+    """1-based line of the error, ``None`` at the end of the input."""
+    column: Optional[int]
+    """1-based column of the error, ``None`` at the end of the input."""
+    token: Optional[str]
+    """The token found where another was expected (``"EOF"`` at the end of
+    the input), ``None`` if the statement could not be tokenized."""
+
+class UnsupportedDialectError(Error):
+    """Raised for a dialect name that is not supported."""
+
+class EmptyStatementError(Error):
+    """Raised when the input holds no statement, only whitespace or comments."""
+
+class PlaceholderCollisionError(Error):
+    """Raised when the placeholder also occurs in the statement itself, with
+    ``placeholder_collision="raise"`` or ``"numbered"``."""
+
+class UnnormalizableError(Error):
+    """Raised with ``strict=True`` when part of a statement cannot be normalized, and with
+    ``prepared=True`` when its binds would take the parameters of its literals."""
+
+    warnings: list[str]
+    """The constructs that were left unnormalized, as in :attr:`NormalizeResult.warnings`."""
+
 @final
 class NormalizeResult:
     """Result of a SQL normalization and fingerprinting operation."""
@@ -24,8 +311,23 @@ class NormalizeResult:
         ...
 
     @property
-    def hash(self) -> str:
-        """SHA-256 hex digest of the normalized SQL."""
+    def hash(self) -> Union[str, int]:
+        """Digest of the normalized SQL, SHA-256 in hex unless
+        ``hash_algorithm`` or ``hash_encoding`` say otherwise; an ``int``
+        with ``hash_encoding="int"``."""
+        ...
+
+    @property
+    def shape_hash(self) -> Union[str, int]:
+        """Like ``hash``, but with ``IN`` lists and ``VALUES`` rows collapsed
+        to a single item, so it does not depend on their lengths. Equal to
+        ``hash`` for statements without such lists."""
+        ...
+
+    @property
+    def hash_algorithm(self) -> HashAlgorithm:
+        """The algorithm ``hash`` and ``shape_hash`` were computed with, so
+        that fingerprints of different settings are not compared."""
         ...
 
     @property
@@ -35,40 +337,2056 @@ class NormalizeResult:
 
     @property
     def params(self) -> list[str]:
-        """Extracted literal values in order of appearance."""
+        """Extracted literal values in order of appearance. Prefixed string
+        forms are stored without their prefix (``'text'`` for ``N'text'``)."""
+        ...
+
+    @property
+    def param_types(self) -> list[Optional[str]]:
+        """For each of ``params``, the prefix of the string form it was
+        written in (``"N"``, ``"E"``, ``"U&"``, ``"X"``, ``"B"`` or ``"R"``),
+        or ``None`` for other literals."""
+        ...
+
+    @property
+    def param_kinds(self) -> list[Optional[Literal["integer", "decimal", "float"]]]:
+        """For each of ``params``, the kind of number it was written as:
+        ``"integer"`` (``42``), ``"decimal"`` (``4.20``) or ``"float"``
+        (``4.2e1``, with an exponent), or ``None`` for other literals. The
+        parameter itself keeps the literal as written, e.g. ``"4.20"``."""
+        ...
+
+    @property
+    def param_spans(self) -> list[Optional[tuple[int, int]]]:
+        """For each of ``params``, the ``(start, end)`` byte offsets of its
+        literal in ``original`` encoded as UTF-8, prefix and quotes included,
+        so that it can be scrubbed from the raw text without reformatting it.
+        ``None`` for literals with no place there: those after a stripped
+        template or an ``INTO OUTFILE`` clause, in a statement a pass
+        callable rewrote, or in ``LOAD DATA``."""
+        ...
+
+    @property
+    def typed_params(self) -> list[Union[int, float, bool, bytes, str]]:
+        """``params`` as Python values, to bind them back: numbers as ``int``
+        or ``float``, ``TRUE``/``FALSE`` as ``bool``, hex strings as
+        ``bytes`` and other strings as ``str`` without their quotes and
+        escapes. Parameters that are none of these, such as those a
+        ``redactor`` returned, are kept as ``str``."""
+        ...
+
+    @property
+    def warnings(self) -> list[str]:
+        """Constructs the normalizer passed through untouched.
+
+        Each entry names what was left as written and where, e.g.
+        ``"explicit table alias left in Insert"`` or
+        ``"statement CreateView is not structurally normalized"``. Equivalent
+        statements hitting such gaps may get different fingerprints.
+        """
+        ...
+
+    @property
+    def passes(self) -> list[Pass]:
+        """The normalization passes that produced ``normalized``, in the order
+        they ran, with caller hooks as ``"hook:<name>"``. Passes disabled with
+        ``disable_passes`` or not part of the ``compat`` rules are left out."""
+        ...
+
+    @property
+    def has_returning(self) -> bool:
+        """Whether the statement is an ``INSERT``, ``UPDATE`` or ``DELETE``
+        with a ``RETURNING`` clause, whose columns are listed in
+        ``metadata["returning"]``."""
+        ...
+
+    @property
+    def statement_class(self) -> Literal["SELECT", "INSERT", "UPDATE", "DELETE", "DDL", "OTHER"]:
+        """Coarse class of the statement, for metrics. A query leading with a
+        ``WITH`` is classed by its body, so ``WITH x AS (...) INSERT ...`` is
+        an ``"INSERT"``; ``MERGE`` is ``"OTHER"``. Unlike
+        ``LeanResult.statement_type``, which names the parsed statement kind,
+        this never grows new values."""
+        ...
+
+    @property
+    def is_read_only(self) -> bool:
+        """Whether the statement neither writes nor takes locks: a ``SELECT``
+        without ``INTO``, ``FOR UPDATE``/``FOR SHARE`` or data-modifying CTEs,
+        a ``SHOW`` or ``DESCRIBE``, or an ``EXPLAIN`` without ``ANALYZE`` of
+        a read-only statement."""
+        ...
+
+    @property
+    def is_ddl(self) -> bool:
+        """Whether ``statement_class`` is ``"DDL"``: ``CREATE``, ``ALTER``,
+        ``DROP``, ``TRUNCATE``, ``COMMENT`` and ``RENAME TABLE``."""
+        ...
+
+    @property
+    def explained(self) -> bool:
+        """Whether, with ``unwrap_explain=True``, the statement was wrapped in
+        an ``EXPLAIN`` that was left out: ``normalized``, ``hash`` and the
+        other attributes are those of the statement it wraps."""
+        ...
+
+    @property
+    def metrics(self) -> Optional[QueryMetrics]:
+        """Complexity of the normalized statement, the same for every
+        statement of a fingerprint; ``None`` when ``parse_path`` is
+        ``"tokens"``."""
+        ...
+
+    @property
+    def dialect_used(self) -> str:
+        """Canonical name of the dialect the statement was parsed with, e.g.
+        ``"postgresql"`` for ``dialect="postgres"``, or the one detected
+        for ``dialect="auto"``."""
+        ...
+
+    @property
+    def parse_path(self) -> Literal["ast", "tokens"]:
+        """How the fingerprint was computed: ``"ast"`` from the parsed
+        statement, with every normalization pass, or ``"tokens"`` from the
+        token stream of a statement that could not be parsed, which is less
+        reliable. MySQL's ``LOAD DATA``, which sqlparser does not parse, is
+        normalized from its tokens, keeping its names and ``SET`` expressions
+        as written, and so are other statements that fail to parse with
+        ``lenient``; without it they raise."""
+        ...
+
+    @property
+    def parsed(self) -> bool:
+        """Whether sqlparser parsed the statement, ``parse_path`` being
+        ``"ast"``."""
+        ...
+
+    @property
+    def options(self) -> Optional[NormalizeOptions]:
+        """The settings the statement was normalized with, for normalizing
+        it again to the same fingerprint: ``normalize(result.original,
+        options=result.options)``. ``dialect`` is the one detected for
+        ``dialect="auto"``. ``None`` for the results of :func:`rewrite`."""
+        ...
+
+    @property
+    def metadata(self) -> dict[str, Any]:
+        """Extra information about how the statement was processed.
+
+        With ``templates=True``, ``metadata["templates"]`` lists the templating
+        constructs that were replaced, each as a dict with ``kind``
+        (``"expression"``, ``"statement"`` or ``"comment"``), ``source`` (the
+        construct as written) and ``marker`` (the identifier substituted for
+        it, or ``None`` if it was removed).
+
+        ``metadata["duplicate_branches"]``, when present, counts the branches
+        of a ``UNION``, ``INTERSECT`` or ``EXCEPT`` that repeat an earlier
+        branch after normalization, literal values included.
+
+        ``metadata["returning"]`` lists the columns of a ``RETURNING`` clause:
+        the alias of each item, the name of a plain column, or else the item
+        as normalized SQL (e.g. ``"t.*"``).
+
+        ``metadata["wildcard_tables"]`` lists the relations all of whose
+        columns the outermost ``SELECT`` selects, through ``*`` (every named
+        relation of the ``FROM`` list) or ``t.*``, by alias or table name.
+
+        ``metadata["statement_class"]`` is ``"introspection"`` for statements
+        asking about the schema: ``SHOW``, ``DESCRIBE``, ``PRAGMA`` and queries
+        reading only catalog relations (``information_schema``,
+        ``pg_catalog`` and ``pg_*``, ``sys``, ``sqlite_master``), directly or
+        through CTEs; a CTE named like a catalog table is not one. It is left
+        out for other statements. ``metadata["introspected_objects"]`` lists
+        the objects they ask about, as written: the table or database a
+        ``SHOW`` or ``DESCRIBE`` names, or the strings a catalog query compares
+        its name columns to (``table_name = 'users'``, ``'users'::regclass``).
+
+        ``metadata["statement_class"]`` is ``"bulk_load"`` for MySQL's
+        ``LOAD DATA INFILE`` and ``SELECT ... INTO OUTFILE`` (or ``INTO
+        DUMPFILE``). Their file paths, separators and skipped line counts are
+        parameterized, under the ``"into"`` clause (``"set"`` for the ``SET``
+        clause of ``LOAD DATA``), and the export clause is written after the
+        query wherever it stood, so that both placements share a fingerprint.
+
+        With ``routing_columns``, ``metadata["routing_keys"]`` maps the
+        routing columns the statement binds a literal to onto that literal,
+        strings unquoted and integers as ``int``, e.g. ``{"tenant_id": 42}``;
+        it is left out when there are none.
+
+        ``metadata["placeholder_collisions"]`` counts the occurrences of the
+        placeholder that the statement itself spells, in an identifier, a
+        literal left in place or an operator such as PostgreSQL's ``?``;
+        ``placeholder_collision`` picks what is done about them. It is left out
+        when there are none.
+
+        ``metadata["literal_counts"]`` maps each ``Clause`` literals were
+        replaced in onto how many, in order of first appearance, e.g.
+        ``{"where": 3, "limit": 1}``, counting those a ``redactor`` dropped
+        from ``params``; it is left out when there are none.
+
+        ``metadata["binds"]`` lists the bind placeholders the statement
+        already had (``$1``, ``?``, ``:name``, Oracle's ``:1``, ``:B1`` and
+        ``:"Name"``), in order of appearance; they are kept as written and are
+        not in ``params``.
+
+        ``metadata["param_positions"]``, when ``shape_hash``,
+        ``collapse_in_lists`` or ``collapse_values`` collapsed a list, gives for each of ``params``
+        where it stands in the ``VALUES`` rows or ``IN`` list it is part of: ``{"list": 0, "row": 1, "element": 0}``,
+        without ``"row"`` for ``IN``, or ``None`` outside lists. Lists are
+        numbered in order of appearance, and ``params`` are laid out
+        row-major, so that the batch can be rebuilt from them.
+
+        ``metadata["uses_distinct"]`` is ``True`` when the outermost ``SELECT``
+        of a query is ``DISTINCT`` or ``DISTINCT ON``, and
+        ``metadata["distinct_on_columns"]`` lists the expressions of the latter
+        as normalized SQL.
+
+        ``metadata["possible_cartesian"]`` is ``True`` when a ``SELECT`` has
+        a comma join or ``CROSS JOIN`` whose sides no ``WHERE`` predicate
+        relates, judging columns by their table qualifier. Predicates on
+        unqualified columns and relations such as ``UNNEST`` or ``LATERAL``
+        subqueries are assumed to relate, so the flag errs towards silence.
+
+        ``metadata["uses_natural_join"]`` is ``True`` when the statement has a
+        ``NATURAL`` join anywhere, also when ``table_columns`` rewrote it.
+
+        With ``clause_hashes=True``, ``metadata["clause_hashes"]`` maps
+        ``"projection"``, ``"from"``, ``"where"``, ``"group_by"`` and
+        ``"order_by"`` to the hash of that clause as normalized, without its
+        keyword, for the clauses the statement has. Of a set operation, only
+        the ``ORDER BY`` is hashed.
+
+        ``metadata["truncated_subqueries"]`` counts the subqueries that
+        ``max_subquery_depth`` replaced with ``SELECT ...``; it is left out
+        when there are none.
+
+        ``metadata["truncated_params"]`` counts the literals that
+        ``max_params`` left out of ``params``; it is left out when there are
+        none.
+
+        ``metadata["truncated_input"]`` is ``True`` when ``truncated_input``
+        fingerprinted a prefix of a statement that did not parse as a whole.
+
+        ``metadata["parse_error"]`` is the parser's error for a statement
+        ``lenient`` normalized from its tokens.
+
+        ``metadata["values_rows"]`` is the number of rows of the ``VALUES``
+        list ``collapse_values`` collapsed to its first, when it did.
+
+        ``metadata["anonymized"]``, with ``anonymize=True``, maps the names
+        given to tables, columns and schemas back to the names as written:
+        ``{"tables": {"t1": "users"}, "columns": {"c1": "id"}, "schemas": {}}``.
+
+        ``metadata["comments"]``, with ``comments=True``, is the text of the
+        comments of the input, and ``metadata["hints"]`` that of its optimizer
+        hints without the ``+``, their whitespace collapsed. Each is left out
+        when there are none.
+
+        ``metadata["ignored_statements"]`` counts the statements after the
+        first that the input held; only the first is normalized, see
+        :func:`normalize_script` for the others. It is left out when there
+        are none.
+        """
+        ...
+
+    def to_msgpack(self) -> bytes:
+        """The result encoded as a MessagePack map keyed by the attribute
+        names above, ``metadata`` included. See :func:`results_to_msgpack` for
+        batches."""
+        ...
+
+    def as_dict(self) -> dict[str, Any]:
+        """The attributes :meth:`to_msgpack` writes, as a dict: ``metrics``
+        as its own ``to_dict()`` and ``metadata`` included (a fresh dict on
+        each call)."""
+        ...
+
+    def to_json(self, indent: Optional[int] = None) -> str:
+        """:meth:`as_dict` as a JSON document, indented by ``indent`` spaces
+        if given, with non-ASCII text as written."""
+        ...
+
+    def __eq__(self, other: object) -> bool:
+        """Results are equal when their fingerprints are: the same ``hash``,
+        with the same ``hash_algorithm`` and ``hash_encoding``, whatever
+        their literals."""
+        ...
+
+    def __hash__(self) -> int: ...
+    def __reduce__(self) -> tuple[Callable[[dict[str, Any]], NormalizeResult], tuple[dict[str, Any]]]:
+        """Everything the result holds, ``options`` included, so that
+        results pickle and copy whole. Results are only made by
+        :func:`normalize` and the other functions, not constructed."""
+        ...
+
+    def bind(self, params: Optional[Sequence[Any]] = None) -> str:
+        """``normalized`` with parameters bound back into its placeholders,
+        as executable SQL of ``dialect_used``, e.g. to replay a captured
+        query against a staging database.
+
+        Without ``params``, the result's own ``params`` are bound as they were
+        written, prefix included. Python values are written as literals of
+        the dialect: ``None`` as ``NULL``, ``bool`` as ``TRUE``/``FALSE``
+        (``1``/``0`` for MSSQL and Oracle), numbers and ``Decimal`` as
+        Python writes them, ``bytes`` as a binary literal, and ``str``,
+        dates and times quoted, with quotes doubled and backslashes too
+        where the dialect reads them as escapes. See :func:`bind`.
+
+        Raises:
+            ValueError: If the placeholders and ``params`` do not match up,
+                or a float or ``Decimal`` is not finite.
+            TypeError: If a value has no literal form.
+        """
         ...
 
     def __repr__(self) -> str: ...
 
-def normalize(
-    sql: str,
-    dialect: Dialect = "generic",
-    placeholder: str = "?",
-) -> NormalizeResult:
-    """Normalize a SQL statement and return its fingerprint.
+@final
+class QueryMetrics:
+    """Complexity counts of a statement, see :attr:`NormalizeResult.metrics`.
+    Immutable."""
 
-    Args:
-        sql: The SQL statement to normalize.
-        dialect: The SQL dialect to use for parsing. Defaults to ``"generic"``.
-        placeholder: The string to replace literal values with. Defaults to ``"?"``.
+    @property
+    def joins(self) -> int:
+        """``JOIN`` clauses, nested joins included; tables listed with commas
+        are not counted."""
+        ...
 
-    Returns:
-        A :class:`NormalizeResult` containing the normalized SQL, its SHA-256
-        hash, the original SQL, and the extracted parameter values.
+    @property
+    def subquery_depth(self) -> int:
+        """Deepest nesting of queries: 0 without subqueries, 1 for a
+        subquery, derived table or CTE, 2 for a subquery in one of those, and
+        so on."""
+        ...
 
-    Raises:
-        ValueError: If the dialect is not supported or the SQL cannot be parsed.
+    @property
+    def predicates(self) -> int:
+        """Comparisons and other conditions (``IS NULL``, ``IN``,
+        ``BETWEEN``, ``LIKE``, ``EXISTS``, ...), wherever they stand."""
+        ...
 
-    Example::
+    @property
+    def select_star(self) -> bool:
+        """Whether a select list has ``*`` or ``t.*``."""
+        ...
 
-        import sqlfp
+    @property
+    def aggregates(self) -> int:
+        """Aggregate calls (``COUNT``, ``SUM``, ``ARRAY_AGG``, ..., and any
+        call with ``WITHIN GROUP`` or ``FILTER``) without ``OVER``."""
+        ...
 
-        result = sqlfp.normalize(
-            "SELECT * FROM users WHERE id = 42",
-            dialect="postgres",
-        )
-        print(result.hash)        # SHA-256 of the normalized form
-        print(result.normalized)  # SELECT * FROM users WHERE id = ?
-        print(result.params)      # ['42']
+    @property
+    def window_functions(self) -> int:
+        """Calls with an ``OVER`` clause."""
+        ...
+
+    @property
+    def set_operations(self) -> int:
+        """``UNION``, ``EXCEPT`` and ``INTERSECT`` operators."""
+        ...
+
+    def to_dict(self) -> dict[str, int | bool]:
+        """The metrics keyed by the attribute names above."""
+        ...
+
+    def __repr__(self) -> str: ...
+
+@final
+class FileResults(Iterator[NormalizeResult]):
+    """Iterator over the results of :func:`normalize_file`."""
+
+    def __iter__(self) -> "FileResults": ...
+    def __next__(self) -> NormalizeResult: ...
+
+@final
+class LogResults(Iterator[NormalizeResult]):
+    """Iterator over the results of :func:`fingerprint_mysql_slowlog` and
+    :func:`fingerprint_postgres_csvlog`."""
+
+    def __iter__(self) -> "LogResults": ...
+    def __next__(self) -> NormalizeResult: ...
+
+@final
+class LeanResult:
+    """What ``lean=True`` keeps of a :class:`NormalizeResult`, for callers
+    that only count fingerprints. Immutable."""
+
+    @property
+    def hash(self) -> Union[str, int]:
+        """Digest of the normalized SQL, as ``NormalizeResult.hash``."""
+        ...
+
+    @property
+    def statement_type(self) -> str:
+        """Kind of the statement, named like ``AstNode.kind``: ``"Query"``,
+        ``"Insert"``, ``"Update"``, ``"CreateTable"``, ..."""
+        ...
+
+    def __repr__(self) -> str: ...
+
+class RegistryEntry(TypedDict):
+    hash: str
+    normalized: str
+    count: int
+
+@final
+class FingerprintRegistry:
+    """Per-fingerprint statement counts that can be checkpointed and merged.
+
+    Statements are normalized as by :func:`normalize`, with the ``configure()``
+    defaults in effect when the registry is created or loaded.
+    """
+
+    def __init__(
+        self, *, dialect: Optional[Dialect] = None, compat: Optional[str] = None, bucket_seconds: int = 60
+    ) -> None: ...
+    def observe(
+        self, sql: str | bytes, count: int = 1, timestamp: Optional[float | datetime.datetime] = None
+    ) -> Optional[str]:
+        """Count ``sql`` ``count`` times.
+
+        With a ``timestamp`` (Unix seconds or a ``datetime``), the
+        observations are also counted in the ``bucket_seconds`` bucket it
+        falls in, see :meth:`series`.
+
+        Returns:
+            The hash of ``sql``, or ``None`` if it could not be parsed (it is
+            then counted in ``unparsed``).
+        """
+        ...
+
+    def merge(self, other: "FingerprintRegistry") -> None:
+        """Add the counts of ``other``.
+
+        Raises:
+            ValueError: If ``other`` has a different dialect or compat, as its
+                hashes are not comparable, or a different ``bucket_seconds``.
+        """
+        ...
+
+    def save(self, path: str | os.PathLike) -> None:
+        """Write the registry to ``path`` as JSONL: a header line, then one line
+        per fingerprint sorted by hash."""
+        ...
+
+    @staticmethod
+    def load(path: str | os.PathLike) -> "FingerprintRegistry":
+        """Read a registry written by :meth:`save`.
+
+        Raises:
+            ValueError: If ``path`` is not a registry checkpoint, or was saved
+                under another :data:`FINGERPRINT_VERSION`.
+        """
+        ...
+
+    def entries(self) -> list[RegistryEntry]:
+        """One entry per fingerprint, most observed first."""
+        ...
+
+    def series(self, hash: str) -> list[Tuple[int, int]]:
+        """``(bucket start, count)`` pairs of the timestamped observations of
+        the fingerprint ``hash``, oldest first; buckets without observations
+        are left out. Bucket starts are Unix seconds."""
+        ...
+
+    @property
+    def bucket_seconds(self) -> int: ...
+
+    @property
+    def dialect(self) -> str:
+        """Canonical name of the dialect, e.g. ``"postgresql"``."""
+        ...
+
+    @property
+    def compat(self) -> Optional[str]: ...
+    @property
+    def unparsed(self) -> int:
+        """Observed statements that could not be parsed."""
+        ...
+
+    def __len__(self) -> int: ...
+    def __repr__(self) -> str: ...
+
+class AggregateRow(TypedDict):
+    hash: str
+    normalized: str
+    sample: str
+    count: int
+    timed: int
+    total_duration: Optional[float]
+    min_duration: Optional[float]
+    max_duration: Optional[float]
+    first_seen: float
+    last_seen: float
+
+@final
+class FingerprintAggregator:
+    """Per-fingerprint query stats collected in-process, for application
+    middleware.
+
+    :meth:`observe` is safe to call from any number of threads: statements are
+    normalized with the GIL released, as by :func:`normalize` with the
+    ``configure()`` defaults in effect when the aggregator is created, and the
+    stats are kept in Rust under a lock of their own.
+    """
+
+    def __init__(self, *, dialect: Optional[Dialect] = None, compat: Optional[str] = None) -> None: ...
+    def observe(self, sql: str | bytes, duration: Optional[float] = None) -> Optional[str]:
+        """Count ``sql``, which took ``duration`` seconds if given.
+
+        Returns:
+            The hash of ``sql``, or ``None`` if it could not be parsed (it is
+            then counted in ``unparsed``).
+
+        Raises:
+            ValueError: If ``duration`` is negative or not finite.
+        """
+        ...
+
+    def snapshot(self, reset: bool = False) -> list[AggregateRow]:
+        """One row per fingerprint, most observed first.
+
+        Each row has the fingerprint's ``count``, the ``timed`` observations
+        among them that were given a duration and the ``total_duration``,
+        ``min_duration`` and ``max_duration`` over those (``None`` if there
+        are none), when it was ``first_seen`` and ``last_seen`` (Unix
+        seconds), and a ``sample`` statement as observed: the slowest, or the
+        first when none was timed.
+
+        Args:
+            reset: Clear the stats once read, for periodic reporting.
+        """
+        ...
+
+    def top(self, n: int = 10, by: Literal["count", "total_duration", "max_duration"] = "count") -> list[AggregateRow]:
+        """The ``n`` rows of :meth:`snapshot` ranking first ``by`` count, total
+        or greatest duration, ties broken by count then hash.
+
+        Raises:
+            ValueError: If ``by`` is not supported.
+        """
+        ...
+
+    @property
+    def dialect(self) -> str:
+        """Canonical name of the dialect, e.g. ``"postgresql"``."""
+        ...
+
+    @property
+    def unparsed(self) -> int:
+        """Observed statements that could not be parsed."""
+        ...
+
+    def __len__(self) -> int: ...
+    def __repr__(self) -> str: ...
+
+class SessionFingerprint(TypedDict):
+    hash: str
+    normalized: str
+    database: Optional[str]
+    count: int
+    first_seen: Optional[float]
+    last_seen: Optional[float]
+
+class Session(TypedDict):
+    connection_id: str | int
+    database: Optional[str]
+    settings: dict[str, str]
+    first_seen: Optional[float]
+    last_seen: Optional[float]
+    statements: int
+    unparsed: int
+    fingerprints: list[SessionFingerprint]
+
+@final
+class NormalizeOptions:
+    """The settings of :func:`normalize` that make up a fingerprint,
+    resolved and validated once.
+
+    The arguments are those of :func:`normalize` that configure its engine,
+    with pass names only in ``passes``; those left as ``None`` take the
+    :func:`configure` defaults in effect at construction, then the built-in
+    ones. The attributes hold the resolved values: the canonical dialect
+    name, the placeholder ``style`` stands for, sorted pass and clause lists
+    and lowercased table names. Options compare equal, and hash alike, when
+    they normalize statements alike.
+    """
+
+    def __init__(self, **kwargs: Unpack[RecordedOptions]) -> None: ...
+    @property
+    def dialect(self) -> str: ...
+    @property
+    def placeholder(self) -> str: ...
+    @property
+    def compat(self) -> Optional[str]: ...
+    @property
+    def templates(self) -> bool: ...
+    @property
+    def strict(self) -> bool: ...
+    @property
+    def passes(self) -> Optional[list[Optional[Pass]]]:
+        """The pass order, ``None`` standing for each caller hook of the
+        call the options were recorded from. Such options need the hooks
+        given again in ``passes``."""
+        ...
+    @property
+    def disable_passes(self) -> list[Pass]: ...
+    @property
+    def placeholder_start(self) -> int: ...
+    @property
+    def enable_passes(self) -> list[Pass]: ...
+    @property
+    def function_case(self) -> Literal["upper", "builtins"]: ...
+    @property
+    def identifier_case(self) -> Literal["preserve", "lower", "upper"]: ...
+    @property
+    def clause_hashes(self) -> bool: ...
+    @property
+    def max_subquery_depth(self) -> Optional[int]: ...
+    @property
+    def routing_columns(self) -> list[str]: ...
+    @property
+    def placeholder_collision(self) -> PlaceholderCollision: ...
+    @property
+    def ignore_clauses(self) -> list[IgnoredClause]: ...
+    @property
+    def table_columns(self) -> dict[str, list[str]]: ...
+    @property
+    def function_synonyms(self) -> dict[str, str]: ...
+    @property
+    def collect_params(self) -> bool: ...
+    @property
+    def max_params(self) -> Optional[int]: ...
+    @property
+    def truncated_input(self) -> bool: ...
+    @property
+    def lenient(self) -> bool: ...
+    @property
+    def collapse_in_lists(self) -> bool: ...
+    @property
+    def collapse_values(self) -> bool: ...
+    @property
+    def keep_limit(self) -> bool: ...
+    @property
+    def keep_offset(self) -> bool: ...
+    @property
+    def keep_null(self) -> bool: ...
+    @property
+    def strip_qualifiers(self) -> Union[bool, list[str]]:
+        """``True`` or ``False``, or the schemas stripped, lowercased, sorted
+        and deduplicated."""
+        ...
+    @property
+    def ignore_aliases(self) -> bool: ...
+    @property
+    def anonymize(self) -> bool: ...
+    @property
+    def comments(self) -> bool: ...
+    @property
+    def keep_hints(self) -> bool: ...
+    @property
+    def unwrap_explain(self) -> bool: ...
+    @property
+    def prepared(self) -> bool: ...
+    @property
+    def hash_algorithm(self) -> HashAlgorithm: ...
+    @property
+    def hash_encoding(self) -> HashEncoding: ...
+    def to_dict(self) -> dict[str, Any]:
+        """The options keyed by their argument names, for recording them
+        next to fingerprints; ``NormalizeOptions(**options.to_dict())``
+        equals ``options``."""
+        ...
+    def __eq__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+@final
+class Normalizer:
+    """:func:`normalize` with its options bound once.
+
+    The ``configure()`` defaults in effect at construction, the dialect, the
+    compat rules and the pass hooks are resolved then and kept for every
+    call, which only reads and normalizes its statements. The arguments are
+    those of :func:`normalize`.
+    """
+
+    def __init__(
+        self,
+        *,
+        redactor: Optional[Redactor] = None,
+        lean: bool = False,
+        options: Optional[NormalizeOptions] = None,
+        **kwargs: Unpack[BoundOptions],
+    ) -> None: ...
+    @property
+    def options(self) -> NormalizeOptions:
+        """The settings of the normalizer."""
+        ...
+
+    def normalize(self, sql: str | bytes) -> NormalizeResult | LeanResult:
+        """:func:`normalize` ``sql``; a :class:`LeanResult` if the
+        normalizer was built with ``lean=True``."""
+        ...
+
+    def normalize_many(
+        self,
+        queries: Iterable[str | bytes | Tuple[str | bytes, Optional[Dialect]]],
+        *,
+        on_error: Literal["raise", "none"] = "raise",
+        progress: Optional[Callable[[int, int], object]] = None,
+        progress_every: int = 1000,
+    ) -> list[Optional[NormalizeResult | LeanResult]]:
+        """:func:`normalize_many` ``queries``. The dialect of a
+        ``(sql, dialect)`` item replaces the normalizer's, with its other
+        options unchanged."""
+        ...
+
+@final
+class SessionTracker:
+    """Fingerprints of a query log grouped per connection.
+
+    ``USE`` and ``SET`` statements update the context of their session
+    (``database`` and ``settings``) instead of being counted, and each
+    fingerprint is counted per database it ran against. Statements are
+    normalized as by :func:`normalize`, with the ``configure()`` defaults in
+    effect when the tracker is created.
+    """
+
+    def __init__(self, *, dialect: Optional[Dialect] = None, compat: Optional[str] = None) -> None: ...
+    def observe(
+        self, connection_id: str | int, timestamp: Optional[float | datetime.datetime], sql: str | bytes
+    ) -> Optional[str]:
+        """Count ``sql``, run on ``connection_id`` at ``timestamp`` (Unix
+        seconds or a ``datetime``).
+
+        Returns:
+            The hash of ``sql``, or ``None`` for ``USE`` and ``SET`` statements
+            and statements that could not be parsed (counted in ``unparsed``).
+        """
+        ...
+
+    def ingest(
+        self, records: Iterable[Tuple[str | int, Optional[float | datetime.datetime], str | bytes]]
+    ) -> int:
+        """:meth:`observe` each ``(connection_id, timestamp, sql)`` record.
+
+        Returns:
+            The number of records observed.
+        """
+        ...
+
+    def sessions(self) -> list[Session]:
+        """The open sessions, in the order they started; ``settings`` holds
+        the values as normalized SQL, by lowercased variable name."""
+        ...
+
+    def close(self, connection_id: str | int) -> Optional[Session]:
+        """End the session of ``connection_id``, so that its next statement
+        starts a new one, e.g. on a reconnection.
+
+        Returns:
+            The closed session, or ``None`` if none was open.
+        """
+        ...
+
+    @property
+    def dialect(self) -> str:
+        """Canonical name of the dialect, e.g. ``"postgresql"``."""
+        ...
+
+    def __len__(self) -> int: ...
+    def __repr__(self) -> str: ...
+
+@final
+class AstNode:
+    """Read-only node of a parsed statement tree, as returned by :func:`parse_ast`."""
+
+    @property
+    def category(self) -> Literal["statement", "query", "table_factor", "relation", "expr"]:
+        """Which family of AST node this is."""
+        ...
+
+    @property
+    def kind(self) -> str:
+        """The sqlparser variant name, e.g. ``"BinaryOp"`` or ``"Table"``."""
+        ...
+
+    @property
+    def span(self) -> Optional[Tuple[int, int, int, int]]:
+        """``(start_line, start_column, end_line, end_column)`` in the source, 1-based."""
+        ...
+
+    @property
+    def sql(self) -> str:
+        """The node rendered back to SQL."""
+        ...
+
+    @property
+    def children(self) -> Tuple["AstNode", ...]:
+        """Direct child nodes, in source order."""
+        ...
+
+    def walk(self) -> list["AstNode"]:
+        """All nodes of this subtree, depth-first, parents before children."""
+        ...
+
+    def __repr__(self) -> str: ...
+
+@overload
+def normalize(
+    sql: str | bytes,
+    dialect: Optional[Dialect] = None,
+    placeholder: Optional[str] = None,
+    *,
+    redactor: Optional[Redactor] = None,
+    lean: Literal[False] = False,
+    options: Optional[NormalizeOptions] = None,
+    **kwargs: Unpack[CallOptions],
+) -> NormalizeResult:
+    """Normalize a SQL statement and return its fingerprint.
+
+    Arguments left as ``None`` take the value set with :func:`configure`, or
+    else the default given below. The arguments past ``placeholder`` are
+    keyword-only, and an unknown keyword raises ``TypeError``.
+
+    Args:
+        sql: The SQL statement to normalize, as ``str`` or as ``bytes``
+            decoded with ``encoding``.
+        dialect: The SQL dialect to use for parsing. Defaults to ``"generic"``.
+            ``"auto"`` parses each statement with the first of PostgreSQL,
+            MySQL, MSSQL, SQLite and the generic dialect that parses it,
+            starting with the one its syntax points to (backquoted names,
+            ``::`` casts and ``$1``, ``SELECT TOP`` and ``[name]``);
+            ``dialect_used`` tells which.
+        placeholder: The string to replace literal values with. Defaults to ``"?"``.
+            A ``{n}`` in it is replaced with the number of the literal, so
+            ``"${n}"`` gives ``$1``, ``$2``, ... and ``":b{n}"`` gives ``:b1``,
+            ``:b2``, ... Literals dropped by ``redactor`` keep their number.
+        redactor: Optional callable invoked as ``redactor(index, clause, value)``
+            for each extracted literal, in order of appearance. It returns the
+            string to store in ``params`` instead of ``value``, or ``None`` to
+            leave the literal out of ``params``. The normalized SQL and hash
+            are not affected. Exceptions raised by the callable propagate.
+        compat: Release whose normalization rules should be reproduced, e.g.
+            ``"0.1"`` or ``"0.1.4"``. Fingerprints computed with a pinned
+            version stay identical across upgrades. Defaults to the rules of
+            the installed release. ``"pg_stat_statements"`` follows what
+            PostgreSQL's query jumbling tells apart instead, dropping the
+            aliases it ignores, and adds ``metadata["query_id"]``, a signed
+            64-bit identity of the statement (sqlfp's own, not the server's
+            ``queryid``). ``"percona"`` writes ``normalized`` as
+            pt-fingerprint does and adds pt-query-digest's checksum of it as
+            ``metadata["checksum"]``.
+        templates: Accept Jinja/dbt templated SQL. ``{{ ... }}`` expressions
+            are replaced with marker identifiers (``sqlfp_tpl_0``, ...), and
+            ``{% ... %}`` tags and ``{# ... #}`` comments are removed, keeping
+            the SQL between them. Expressions before the statement itself,
+            such as ``{{ config(...) }}``, are removed. What was replaced is
+            recorded in ``metadata["templates"]``.
+        encoding: Encoding of ``bytes`` input. Defaults to ``"utf-8"``; UTF-8
+            and latin-1 are decoded natively, other codecs through Python.
+        errors: Error handler for decoding ``bytes`` input, as for
+            :meth:`bytes.decode` (``"strict"``, ``"replace"``, ``"ignore"``, ...).
+        strict: Raise :class:`UnnormalizableError` instead of returning a
+            result with non-empty ``warnings``, for pipelines where a partially
+            normalized fingerprint is worse than none.
+        passes: The normalization pipeline: pass names in the order to run
+            them, e.g. ``["literal-parameterize", "alias-normalize"]``. Passes
+            left out do not run. Callables can be inserted between passes;
+            each is called with the statement as it stands at that point and
+            returns the rewritten SQL, or ``None`` to leave it unchanged.
+            Defaults to all passes except the opt-in ones, in the order
+            listed by ``Pass``.
+        disable_passes: Names of normalization passes to skip, e.g.
+            ``["alias-normalize"]``. The passes that ran are listed in
+            ``passes`` of the result. Defaults to running all passes.
+        placeholder_start: Number of the first literal with a numbered
+            ``placeholder``, e.g. ``5`` when ``$1`` to ``$4`` are taken.
+            Binds of the same style already in the statement are taken too:
+            literals are numbered after the highest of them. Defaults to
+            ``1``.
+        style: The placeholder of a driver's paramstyle, instead of
+            ``placeholder``, so that ``normalized`` can be prepared as is:
+            ``"question"`` (``?``), ``"dollar"`` (``$1``, ``$2``, ...),
+            ``"colon"`` (``:p1``, ``:p2``, ...) or ``"pyformat"`` (``%s``).
+            Giving both raises ``ValueError``.
+        enable_passes: Opt-in passes to add to the default pipeline:
+            ``"cte-rename"`` renames CTEs to ``cte_1``, ``cte_2``, ... so that
+            generated CTE names do not split fingerprints, leaving the
+            tables they shadow outside their scope as named;
+            ``"insert-column-sort"`` sorts the column list of
+            ``INSERT ... VALUES`` (and every row with it) by name;
+            ``"function-schema-normalize"`` drops the ``pg_catalog`` and
+            ``sys`` qualifiers of function calls and lowercases other schema
+            qualifiers, uppercasing only the function name;
+            ``"negative-literal-fold"`` folds a minus sign into the numeric
+            literal it precedes, so ``-5`` is one ``"-5"`` parameter;
+            ``"concat-canonicalize"`` rewrites ``CONCAT(a, b)`` calls, and in
+            MSSQL ``+`` with a string operand, as ``a || b``;
+            ``"case-canonicalize"`` rewrites ``CASE x WHEN 1 ...`` as
+            ``CASE WHEN x = 1 ...`` and drops ``ELSE NULL``;
+            ``"distinct-from-canonicalize"`` writes MySQL's ``a <=> b`` as
+            ``a IS NOT DISTINCT FROM b`` (and its negation as
+            ``IS DISTINCT FROM``); ``"case-arm-collapse"`` keeps one of each
+            run of adjacent CASE arms of the same shape, counting the others
+            in ``metadata["collapsed_case_arms"]``;
+            ``"current-time-canonicalize"`` writes ``NOW()``, ``SYSDATE()``,
+            ``GETDATE()``, ``CURDATE()`` and the like as ``CURRENT_TIMESTAMP``,
+            ``CURRENT_DATE`` or ``CURRENT_TIME``;
+            ``"function-synonym-canonicalize"`` writes the builtin functions
+            that are synonyms in every dialect as one name (``IFNULL`` and
+            ``NVL`` as ``COALESCE``, ``NOW()`` as ``CURRENT_TIMESTAMP``);
+            ``"wildcard-canonicalize"``
+            writes ``SELECT a.*, b.* FROM a JOIN b`` (one qualified wildcard
+            per relation, in ``FROM`` order) as ``SELECT *``;
+            ``"grouping-canonicalize"`` writes ``ROLLUP``, ``CUBE``,
+            ``WITH ROLLUP`` and the empty grouping set ``()`` as the
+            ``GROUPING SETS`` they stand for;
+            ``"join-using-canonicalize"`` writes ``JOIN b ON a.id = b.id``
+            (equalities of same-named columns only) as ``JOIN b USING (id)``;
+            ``"commutative-canonicalize"`` orders the operands of ``=``,
+            ``<>``, ``+``, ``*``, ``AND`` and ``OR``, literals last, so that
+            ``5 = id`` reads ``id = 5``, flipping ``<`` and ``>`` to match;
+            ``"predicate-sort"`` orders the ``AND`` and ``OR`` chains only.
+            Ignored
+            when ``passes`` is given, as that lists the passes to run itself.
+        function_case: Which function names ``function-uppercase``
+            uppercases: ``"upper"`` for all unquoted ones, ``"builtins"`` for
+            the builtins of the dialect only, keeping the case of
+            user-defined functions. Disable the pass to keep every name as
+            written. Defaults to ``"upper"``.
+        identifier_case: ``"lower"`` or ``"upper"`` to fold the unquoted
+            names of tables, their aliases, columns, select-item aliases and
+            CTEs to that case before hashing, so that ``SELECT Id FROM Users``
+            and ``select id from users`` share a fingerprint. Quoted names
+            keep their case. Defaults to ``"preserve"``.
+        clause_hashes: Whether to add ``metadata["clause_hashes"]``, the
+            hashes of the projection, ``FROM`` list (with its joins),
+            ``WHERE``, ``GROUP BY`` and ``ORDER BY`` clauses present in the
+            statement, to find fingerprints sharing one of them. Defaults to
+            ``False``.
+        max_subquery_depth: Replace the subqueries nested deeper than this
+            with the opaque ``SELECT ...`` before normalizing, bounding the
+            work spent on pathologically nested statements. Subqueries, CTEs
+            and ``INSERT`` sources of the statement are at depth 1, those
+            inside them at depth 2, and so on; ``0`` keeps the outermost query
+            only. Defaults to ``None``, no limit.
+        routing_columns: Tenant or partition columns whose values to report
+            in ``metadata["routing_keys"]``: the first literal compared with
+            ``=`` to each column, or inserted into it, taken before
+            parameterization. Columns match by name, ignoring their qualifier
+            and case. Defaults to none.
+        placeholder_collision: What to do when the placeholder also occurs
+            in the statement itself, which makes the template ambiguous to
+            rebind: ``"report"`` counts the occurrences in
+            ``metadata["placeholder_collisions"]``, ``"escape"`` also doubles
+            their first character (``??``, ``%%s``), ``"numbered"`` uses
+            ``placeholder + "{n}"`` instead, decided before ``redactor`` and
+            the hooks are called, and ``"raise"`` raises
+            :class:`PlaceholderCollisionError`, as ``"numbered"`` does when
+            the numbered placeholders collide too or a hook brings in a
+            collision. Bind placeholders already in the statement do not
+            collide. Defaults to ``"report"``.
+        ignore_clauses: Trailing clauses of the outermost statement to leave
+            out of ``hash`` and ``shape_hash``, for grouping that treats
+            ordering and pagination as presentation: ``"order_by"``,
+            ``"limit"`` (also ``FETCH`` and ``LIMIT ... BY``) and
+            ``"offset"``. ``normalized`` and ``params`` still include them.
+            Those of subqueries, CTEs and set operation branches are kept.
+            Defaults to none.
+        table_columns: The columns of each table, by table name, matched up
+            to case; a name without schema matches the table in any schema,
+            e.g.
+            ``{"orders": ["id", "user_id"], "users": ["user_id", "name"]}``.
+            ``NATURAL`` joins between tables given here are rewritten as the
+            ``JOIN ... USING`` of the columns they share, in the order of the
+            left side, so that they fingerprint like the explicit join; others
+            are left as written. Defaults to none.
+        function_synonyms: Function names to rewrite, each to the one it is
+            an alias of, matched up to case; a dotted name matches the
+            schema-qualified call, e.g. ``{"util.nz": "coalesce"}``. Quoted
+            names are left alone. The canonical name is then subject to
+            ``function-synonym-canonicalize``, when enabled, and to
+            ``function-uppercase``. Defaults to none.
+        collect_params: Whether to keep the literals in ``params``. With
+            ``False``, they are still replaced by placeholders but not written
+            out, so ``params``, ``param_types`` and ``param_kinds`` are empty
+            and ``redactor`` is not called; for callers that only need the
+            fingerprint of large statements. Defaults to ``True``.
+        max_params: Keep at most this many literals in ``params``, for
+            generated statements such as an ``INSERT`` of thousands of
+            columns. Later literals are still replaced by placeholders, so
+            ``normalized`` and the hashes do not change, but are not passed to
+            ``redactor``; ``metadata["truncated_params"]`` counts them.
+            Defaults to no limit.
+        truncated_input: Whether statements may have been cut short by their
+            source, as by slow query logs and APM agents capping their
+            length. A statement that does not parse is then fingerprinted by
+            the longest prefix of it that does, once the string and the
+            parentheses left open are closed and a word cut at the very end
+            is dropped; ``metadata["truncated_input"]`` marks such results,
+            and their hashes are those of the prefix. Statements that parse
+            are left as they are. Defaults to ``False``.
+        lenient: Whether a statement that does not parse, even with
+            ``truncated_input``, is normalized token by token instead of
+            raising: the words sqlparser knows as keywords uppercased,
+            whitespace and comments collapsed and string and number literals
+            replaced by placeholders, the other tokens kept as written. No
+            other pass applies. ``parsed`` is ``False`` on such results and
+            ``metadata["parse_error"]`` has the parser's error. Defaults to
+            ``False``.
+        collapse_in_lists: Whether ``IN`` lists of constants collapse to a
+            single placeholder in ``normalized`` and the hashes, so that
+            ``IN (1, 2)`` and ``IN (1, 2, 3)`` share a fingerprint. ``params``
+            keep every value, and ``metadata["param_positions"]`` where each
+            stood. Lists with other items are left as they are. Defaults to
+            ``False``.
+        collapse_values: Whether the rows of a ``VALUES`` list collapse to
+            the first when they differ from it only in their literals, so
+            that bulk inserts fingerprint alike whatever their number of rows.
+            ``params`` keep every value, and ``metadata["values_rows"]`` has
+            the number of rows the list had. Defaults to ``False``.
+        keep_limit: Whether the literals of ``LIMIT`` and ``FETCH`` are left
+            as written, so that ``LIMIT 10`` and ``LIMIT 10000`` get
+            different fingerprints. Defaults to ``False``.
+        keep_offset: The same for those of ``OFFSET``. Defaults to ``False``.
+        keep_null: Whether ``NULL`` is left as written. With ``False`` it is
+            replaced like any other literal, ``typed_params`` holding
+            ``None`` for it. Defaults to ``True``.
+        strip_qualifiers: ``True`` to drop the schema and database
+            qualifiers of table references, so that ``tenant_123.orders``
+            and ``orders`` share a fingerprint, or the schemas to drop them
+            for only (``["public", "dbo"]``, matched without regard to case).
+            Column references spelling a stripped table in full are
+            shortened to match. Defaults to ``False``.
+        ignore_aliases: Whether to rename table aliases to ``t1``, ``t2``,
+            … and select list aliases to ``c1``, ``c2``, … in the order they
+            appear, with the references to them, so that the aliases query
+            builders make up (``t0``, ``sq_47``) do not split fingerprints.
+            Aliases that are also the names of unaliased tables or of columns
+            read elsewhere are kept. Defaults to ``False``.
+        anonymize: Whether to rename the tables (with their aliases and
+            CTEs), columns and schemas of the normalized statement to ``t1``,
+            ``c1``, ``s1``, … for sharing its shape without the schema.
+            ``metadata["anonymized"]`` maps the new names back, and ``hash``
+            is that of the anonymized statement. Defaults to ``False``.
+        comments: Whether to report the comments of the statement in
+            ``metadata["comments"]`` and its optimizer hints (``/*+ ... */``)
+            in ``metadata["hints"]``. Defaults to ``False``.
+        keep_hints: Whether to write back the optimizer hints the parser
+            drops (all but those of MySQL, Oracle and the generic dialect
+            after ``SELECT``, ``INSERT``, ``UPDATE``, ``DELETE`` and
+            ``MERGE``) where they stood, so that hinted and unhinted variants
+            of a statement get different fingerprints. Defaults to ``False``.
+        unwrap_explain: Whether to normalize the statement an ``EXPLAIN``
+            (``EXPLAIN ANALYZE``, ``EXPLAIN (FORMAT JSON)``, …) wraps in its
+            place, so that it shares the fingerprint of that statement;
+            ``explained`` is then ``True``. Defaults to ``False``.
+        prepared: Whether to make ``normalized`` a prepared statement for
+            the placeholder: the literals' placeholders are written in the
+            order of ``params``, numbered in text order where the placeholder
+            is numbered, so that ``cursor.execute(result.normalized,
+            result.typed_params)`` runs it, and other ``%`` are doubled for
+            the ``%`` placeholders. Binds already in the statement are left
+            as written and listed in ``metadata["binds"]``; they must be
+            numbered like the placeholder (``$1`` with ``"${n}"``), the
+            literals being numbered after them, so that their values come
+            before ``typed_params``, or :class:`UnnormalizableError` is
+            raised. Cannot be combined with ``collapse_in_lists`` or
+            ``collapse_values``. Defaults to ``False``.
+        hash_algorithm: How ``hash`` and ``shape_hash`` are computed:
+            ``"sha256"``, ``"xxhash64"`` (XXH64, much cheaper, for counting
+            fingerprints in memory) or ``"blake3"``. Defaults to
+            ``"sha256"``.
+        hash_encoding: How they are written: lowercase ``"hex"``, padded
+            ``"base64"`` or, with ``"xxhash64"`` only, an ``"int"``.
+            Registries, trackers and corpora keep hashes as strings whatever
+            this is. Defaults to ``"hex"``.
+        lean: Return a :class:`LeanResult`, holding only the hash and the
+            statement type, instead of a :class:`NormalizeResult` and its
+            strings. Defaults to ``False``.
+        options: Settings to use in place of the :func:`configure` defaults,
+            as a :class:`NormalizeOptions` or the ``options`` of an earlier
+            result; the other arguments given replace theirs.
+
+    Returns:
+        A :class:`NormalizeResult` containing the normalized SQL, its
+        hash, the original SQL, and the extracted parameter values.
+
+    Raises:
+        ParseError: If the SQL cannot be parsed, with the ``line``,
+            ``column`` and ``token`` of the error.
+        EmptyStatementError: If the SQL holds no statement.
+        UnsupportedDialectError: If the dialect is not supported.
+        Error: If the compat version or a pass name is not supported, or
+            another option cannot be used. All of these are ``ValueError``.
+        UnicodeDecodeError: If ``bytes`` input cannot be decoded and
+            ``errors`` is ``"strict"``.
+        UnnormalizableError: With ``strict=True``, if part of the statement
+            cannot be normalized.
+        PlaceholderCollisionError: With ``placeholder_collision="raise"`` or
+            ``"numbered"``, if the placeholder occurs in the statement itself.
+
+    Example::
+
+        import sqlfp
+
+        result = sqlfp.normalize(
+            "SELECT * FROM users WHERE id = 42",
+            dialect="postgres",
+        )
+        print(result.hash)        # SHA-256 of the normalized form
+        print(result.normalized)  # SELECT * FROM users WHERE id = ?
+        print(result.params)      # ['42']
+    """
+    ...
+
+@overload
+def normalize(
+    sql: str | bytes,
+    dialect: Optional[Dialect] = None,
+    placeholder: Optional[str] = None,
+    *,
+    redactor: Optional[Redactor] = None,
+    lean: Literal[True],
+    options: Optional[NormalizeOptions] = None,
+    **kwargs: Unpack[CallOptions],
+) -> LeanResult: ...
+
+@overload
+def normalize_many(
+    queries: Iterable[str | bytes | Tuple[str | bytes, Optional[Dialect]]],
+    dialect: Optional[Dialect] = None,
+    placeholder: Optional[str] = None,
+    *,
+    redactor: Optional[Redactor] = None,
+    on_error: Literal["raise", "none"] = "raise",
+    progress: Optional[Callable[[int, int], object]] = None,
+    progress_every: int = 1000,
+    lean: Literal[False] = False,
+    **kwargs: Unpack[CallOptions],
+) -> list[Optional[NormalizeResult]]:
+    """Normalize a batch of statements, one result per statement in order.
+
+    ``dialect``, ``placeholder``, ``redactor``, ``lean`` and the keyword
+    options are as for :func:`normalize` and apply to the whole batch. An
+    item can also be a ``(sql, dialect)`` pair, to mix dialects in one
+    batch; its ``dialect`` overrides the batch's unless it is ``None``.
+
+    Statements are read from ``queries`` in chunks; unless a ``redactor`` or
+    a hook in ``passes`` is given, each chunk is normalized in parallel with
+    the GIL released. Results are in input order either way.
+
+    Args:
+        on_error: ``"raise"`` re-raises the error of the first statement
+            that fails; ``"none"`` puts ``None`` in its place.
+        progress: Called as ``progress(processed, errors)`` every
+            ``progress_every`` statements and once more at the end, from the
+            thread running the batch. Exceptions raised by it abort the batch.
+        progress_every: Number of statements between ``progress`` calls.
+
+    Returns:
+        The results, or on ``KeyboardInterrupt`` (Ctrl-C, checked between
+        chunks of statements and raised from the input iterable or
+        callbacks) the results for the statements processed so far.
+
+    Raises:
+        ValueError: If an argument is not supported, or a statement fails and
+            ``on_error`` is ``"raise"``.
+        TypeError: If an item is a tuple other than a pair and ``on_error``
+            is ``"raise"``.
+    """
+    ...
+
+@overload
+def normalize_many(
+    queries: Iterable[str | bytes | Tuple[str | bytes, Optional[Dialect]]],
+    dialect: Optional[Dialect] = None,
+    placeholder: Optional[str] = None,
+    *,
+    redactor: Optional[Redactor] = None,
+    on_error: Literal["raise", "none"] = "raise",
+    progress: Optional[Callable[[int, int], object]] = None,
+    progress_every: int = 1000,
+    lean: Literal[True],
+    **kwargs: Unpack[CallOptions],
+) -> list[Optional[LeanResult]]: ...
+
+@overload
+def normalize_file(
+    path: str | os.PathLike[str],
+    format: Literal["lines", "jsonl"] = "lines",
+    field: str = "query",
+    *,
+    on_error: Literal["raise", "skip"] = "raise",
+    output: None = None,
+    **kwargs: Unpack[BoundOptions],
+) -> FileResults:
+    """Normalize every statement of a file, lazily.
+
+    The file is memory-mapped and read line by line in Rust; blank lines are
+    skipped. With ``format="lines"`` each line is one statement; with
+    ``format="jsonl"`` each line is a JSON object holding the statement in
+    ``field``. Each result carries its 1-based line number in
+    ``metadata["line"]``. The remaining arguments are as for :func:`normalize`,
+    including the fallback to :func:`configure` defaults.
+
+    Args:
+        on_error: ``"raise"`` re-raises the error of the first line that
+            fails, its message prefixed with ``path:line:``; ``"skip"`` leaves
+            such lines out.
+        output: Path of a JSONL file to write the results to instead of
+            returning them: one object per result with ``line``, ``hash``,
+            ``shape_hash``, ``normalized``, ``params``, ``warnings``,
+            ``dialect_used`` and ``parse_path``. No
+            Python objects are built for the results, which pays off for large
+            files. The whole file is processed before returning.
+
+    Returns:
+        An iterator over the results, or with ``output`` the number of
+        results written.
+
+    Raises:
+        OSError: If the file cannot be opened.
+        ValueError: If an argument is not supported, or (while iterating) a
+            line fails and ``on_error`` is ``"raise"``.
+
+    Example::
+
+        for result in sqlfp.normalize_file("slow.jsonl", format="jsonl", field="sql"):
+            print(result.metadata["line"], result.hash)
+    """
+    ...
+
+@overload
+def normalize_file(
+    path: str | os.PathLike[str],
+    format: Literal["lines", "jsonl"] = "lines",
+    field: str = "query",
+    *,
+    on_error: Literal["raise", "skip"] = "raise",
+    output: str | os.PathLike[str],
+    **kwargs: Unpack[BoundOptions],
+) -> int: ...
+
+def normalize_script(
+    script: str | bytes,
+    dialect: Optional[Dialect] = None,
+    placeholder: Optional[str] = None,
+    *,
+    redactor: Optional[Redactor] = None,
+    on_error: Literal["raise", "skip"] = "raise",
+    **kwargs: Unpack[CallOptions],
+) -> list[NormalizeResult]:
+    """Normalize every statement of a script or session log.
+
+    The script is split at ``;`` and at ``GO`` batch separators (``GO`` alone
+    on a line, optionally with a repeat count), outside string literals,
+    quoted identifiers and comments; empty statements are skipped. What
+    counts as a comment follows the dialect: ``#`` in MySQL, where ``--``
+    needs whitespace after it, and block comments nested in PostgreSQL and
+    SQL Server. MySQL ``/*! ... */`` hints are SQL the server runs and stay
+    whole in the statement they are part of. Each result carries the 1-based
+    line its statement starts on in ``metadata["line"]`` and its 1-based
+    ``GO`` batch in ``metadata["batch"]``.
+
+    ``USE db`` sets the database and ``SET search_path TO a, b`` the schemas
+    bare table names resolve against; ``USE db.schema`` sets both, and
+    switching databases forgets the schemas. The statements after them carry
+    the current ``metadata["database"]`` (a string) and
+    ``metadata["search_path"]`` (a list), each once the script has set it.
+    The remaining arguments are as for :func:`normalize`, including the
+    fallback to :func:`configure` defaults.
+
+    Args:
+        on_error: ``"raise"`` re-raises the error of the first statement
+            that fails, its message prefixed with ``line N:``; ``"skip"``
+            leaves such statements out.
+
+    Raises:
+        ValueError: If an argument is not supported, the script cannot be
+            tokenized, or a statement fails and ``on_error`` is ``"raise"``.
+
+    Example::
+
+        for result in sqlfp.normalize_script(open("migrate.sql").read(), dialect="mssql"):
+            print(result.metadata.get("database"), result.normalized)
+    """
+    ...
+
+def script_hash(results: Sequence[NormalizeResult]) -> str:
+    """Fingerprint a whole script from the results of its statements.
+
+    The hash is the SHA-256 of the ``hash`` of each result followed by a
+    newline, in order, so that scripts running the same statements in the
+    same order share it whatever their literals.
+
+    Example::
+
+        sqlfp.script_hash(sqlfp.normalize_script(migration, dialect="postgres"))
+    """
+    ...
+
+def results_to_msgpack(results: Sequence[NormalizeResult]) -> bytes:
+    """Encode a batch of results as one MessagePack array of
+    :meth:`NormalizeResult.to_msgpack` maps, in a single pass in Rust.
+
+    Example::
+
+        producer.send("fingerprints", sqlfp.results_to_msgpack(sqlfp.normalize_many(batch)))
+    """
+    ...
+
+def snapshot_corpus(
+    path: str | os.PathLike[str],
+    output: Optional[str | os.PathLike[str]] = None,
+    *,
+    dialect: Optional[Dialect] = None,
+    placeholder: Optional[str] = None,
+    compat: Optional[str] = None,
+    templates: Optional[bool] = None,
+    encoding: Optional[str] = None,
+    errors: Optional[str] = None,
+) -> str:
+    """Fingerprint a corpus of SQL samples into a deterministic JSON manifest.
+
+    If ``path`` is a directory, each ``*.sql`` file below it is one sample,
+    identified by its ``/``-separated relative path. Otherwise ``path`` is a
+    file with one sample per non-blank line, identified as ``name:line``.
+    Samples are listed in sorted order with their ``normalized`` text and
+    ``hash``, or an ``error`` message if they fail; the manifest also records
+    ``sqlfp_version``, ``fingerprint_version``, ``dialect`` and ``compat``. The same corpus and options
+    always give the same bytes, so manifests from two releases can be diffed
+    to find fingerprint drift before upgrading.
+
+    The remaining arguments are as for :func:`normalize`, including the
+    fallback to :func:`configure` defaults.
+
+    Args:
+        output: Also write the manifest to this file.
+
+    Returns:
+        The manifest, as pretty-printed JSON.
+
+    Raises:
+        KeyboardInterrupt: On Ctrl-C; no manifest is written.
+        OSError: If the corpus cannot be read or the manifest cannot be written.
+        ValueError: If an argument is not supported.
+    """
+    ...
+
+def export_bundle(
+    queries: Iterable[str | bytes],
+    output: Optional[str | os.PathLike[str]] = None,
+    *,
+    dialect: Optional[Dialect] = None,
+    compat: Optional[str] = None,
+) -> str:
+    """Export the shapes of ``queries`` as an anonymized JSON bundle.
+
+    The bundle is meant to be shared, e.g. with a database vendor for a
+    support case, and holds no raw data: each distinct fingerprint is listed
+    once with its ``hash``, its normalized ``sql`` with every table, column
+    and alias name replaced by ``id1``, ``id2``, ... (consistently across the
+    bundle), the number of input queries (``count``) and extracted parameters
+    (``params``) it covers, its ``warnings``, and structural ``metrics``
+    (``tables`` and ``ctes``, the references to tables and to CTEs,
+    ``joins``, ``subqueries``, nesting ``depth`` and ``expressions``). Queries that fail to parse are only counted, in
+    ``unparsed``. Function names and keywords are kept.
+
+    Args:
+        output: Also write the bundle to this file.
+
+    Returns:
+        The bundle, as pretty-printed JSON.
+    """
+    ...
+
+class CorpusChange(TypedDict):
+    hash: str
+    normalized: str
+    before: int
+    """Occurrences in the ``before`` corpus."""
+    after: int
+    """Occurrences in the ``after`` corpus."""
+
+class CorpusDiff(TypedDict):
+    dialect: str
+    compat: Optional[str]
+    new: list[CorpusChange]
+    disappeared: list[CorpusChange]
+    changed: list[CorpusChange]
+    unchanged: int
+    """Fingerprints seen as many times in both corpora."""
+    unparsed: tuple[int, int]
+    """Queries of ``before`` and of ``after`` that could not be parsed."""
+
+def diff_corpora(
+    before: Iterable[str | bytes] | os.PathLike[str],
+    after: Iterable[str | bytes] | os.PathLike[str],
+    *,
+    dialect: Optional[Dialect] = None,
+    placeholder: Optional[str] = None,
+    compat: Optional[str] = None,
+    templates: Optional[bool] = None,
+    encoding: Optional[str] = None,
+    errors: Optional[str] = None,
+) -> CorpusDiff:
+    """Compare the fingerprints of two corpora of queries.
+
+    Each corpus is an iterable of queries, or the path of a corpus as read
+    by :func:`snapshot_corpus` (a directory of ``*.sql`` files, or a file of
+    one query per line, such as a query log). Both are fingerprinted with the
+    same options and their fingerprints counted; the report lists those only
+    in ``after`` (``new``), those only in ``before`` (``disappeared``) and
+    those in both with a different count (``changed``), each largest change
+    first. Queries that cannot be fully normalized are still counted.
+
+    The remaining arguments are as for :func:`normalize`, including the
+    fallback to :func:`configure` defaults.
+
+    Example::
+
+        report = sqlfp.diff_corpora(Path("last-week.log"), Path("this-week.log"))
+        for entry in report["new"]:
+            print(entry["after"], entry["normalized"])
+
+    Raises:
+        KeyboardInterrupt: On Ctrl-C.
+        OSError: If a corpus path cannot be read.
+        TypeError: If a corpus is a single ``str`` or ``bytes`` query.
+        ValueError: If an argument is not supported.
+    """
+    ...
+
+class Similarity(TypedDict):
+    score: float
+    """From 0 for statements with nothing in common to 1 for the same
+    normalized statement."""
+    differing_clauses: list[Literal["statement", "projection", "from", "where", "group_by", "order_by"]]
+    """The clauses of the outermost query that differ, in clause order, or
+    ``["statement"]`` for statements of different kinds."""
+
+def similarity(
+    sql_a: str,
+    sql_b: str,
+    *,
+    dialect: Optional[Dialect] = None,
+    placeholder: Optional[str] = None,
+    compat: Optional[str] = None,
+) -> Similarity:
+    """Score how alike two statements are once normalized.
+
+    Both statements are normalized and their trees, as :func:`parse_ast`
+    builds them, compared by tree edit distance: adding, removing or changing
+    a node counts 1, and changing it into a node of the same kind (``a = ?``
+    into ``a > ?``) counts half. The score is 1 less that distance over the
+    number of nodes of the larger tree, so that near-duplicates whose
+    fingerprints differ by one predicate or column score close to 1.
+
+    The remaining arguments are as for :func:`normalize`, including the
+    fallback to :func:`configure` defaults.
+
+    Example::
+
+        sqlfp.similarity("SELECT a FROM t WHERE x = 1", "SELECT a, b FROM t WHERE x = 2")
+        # {'score': 0.833..., 'differing_clauses': ['projection']}
+
+    Raises:
+        ValueError: If a statement cannot be parsed, or an argument is not
+            supported.
+    """
+    ...
+
+def bind(
+    normalized: str,
+    params: Sequence[Any],
+    dialect: Optional[Dialect] = None,
+    *,
+    placeholder: Optional[str] = None,
+    placeholder_start: Optional[int] = None,
+) -> str:
+    """Bind Python values into the placeholders of a normalized statement.
+
+    The values are written as literals of ``dialect``, as
+    :meth:`NormalizeResult.bind` writes them. Placeholders are found on the
+    token stream, so that one in a string, a quoted name or a comment is left
+    alone. Unnumbered placeholders take ``params`` in order; numbered ones
+    (``"${n}"``) take the parameter of their number counted from
+    ``placeholder_start``, those numbered below it being left as they are.
+    ``dialect``, ``placeholder`` and ``placeholder_start`` fall back to the
+    :func:`configure` defaults.
+
+    Example::
+
+        sqlfp.bind("SELECT * FROM t WHERE a = ? AND b = ?", ["it's", 42])
+        # "SELECT * FROM t WHERE a = 'it''s' AND b = 42"
+
+    Raises:
+        ValueError: If the placeholders and ``params`` do not match up, the
+            dialect is not supported, or a float or ``Decimal`` is not finite.
+        TypeError: If a value has no literal form.
+    """
+    ...
+
+def fingerprint_execute(
+    statement: str,
+    parameters: Optional[Any] = None,
+    paramstyle: ParamStyle = "format",
+    dialect: Optional[Dialect] = None,
+    compat: Optional[str] = None,
+) -> Tuple[NormalizeResult, list[MergedParam]]:
+    """Fingerprint a statement as passed to a DB-API ``execute()``.
+
+    Meant for instrumentation hooks such as SQLAlchemy's
+    ``before_cursor_execute``: ``statement`` uses the driver's
+    ``paramstyle`` placeholders and ``parameters`` is the sequence or mapping
+    bound to them (one set of parameters, for ``executemany()`` call it per
+    set). Bound placeholders are normalized like literals, so the fingerprint
+    is the same whether values are bound or inlined; ``normalized`` uses
+    ``?`` for both.
+
+    Returns:
+        The :class:`NormalizeResult`, whose ``original`` is ``statement``,
+        and one entry per placeholder of ``normalized``, in order, holding
+        either a driver-bound value or an extracted literal.
+
+    Raises:
+        ValueError: If the paramstyle, dialect or compat version is not
+            supported, or the statement cannot be parsed.
+        KeyError, IndexError: If a placeholder has no bound parameter.
+
+    Example::
+
+        @event.listens_for(engine, "before_cursor_execute")
+        def record(conn, cursor, statement, parameters, context, executemany):
+            result, params = sqlfp.fingerprint_execute(
+                statement, parameters, paramstyle=context.dialect.paramstyle
+            )
+    """
+    ...
+
+def fingerprint_stream(
+    source: str | bytes | os.PathLike[str] | IO[bytes] | IO[str],
+    dialect: Optional[Dialect] = None,
+    *,
+    chunk_size: int = 65536,
+) -> str:
+    """Fingerprint a statement too large to parse, in bounded memory.
+
+    ``source`` is the SQL text, a path, or a file object opened in binary or
+    text mode, read ``chunk_size`` bytes at a time. The statement is tokenized
+    incrementally and each token is hashed as soon as it is complete, without
+    building an AST or keeping the tokens, so e.g. a 100 MB generated
+    ``INSERT`` is fingerprinted in a few megabytes of memory.
+
+    Literals are masked as ``?`` and keywords uppercased; whitespace,
+    comments and a trailing ``;`` are ignored. The other normalization passes
+    need an AST and do not apply, so the result is not comparable to
+    ``NormalizeResult.hash``. ``dialect`` falls back to the :func:`configure`
+    default.
+
+    Returns:
+        The SHA-256 hex digest of the masked token stream.
+
+    Raises:
+        ValueError: If the dialect is not supported, or a string literal,
+            quoted identifier or comment is unterminated.
+        OSError: If the file cannot be read. Exceptions raised by the file
+            object's ``read()`` propagate.
+    """
+    ...
+
+@final
+class StreamFingerprinter:
+    """:func:`fingerprint_stream` for statements arriving in chunks, as a
+    proxy sees them on the wire.
+
+    Chunks are fed as they arrive and may be split anywhere, even inside a
+    token or a UTF-8 character. Each statement is fingerprinted as its ``;``
+    is read, without being kept or read again; statements holding nothing but
+    comments are skipped. Fingerprints equal ``fingerprint_stream()`` of each
+    statement on its own.
+
+    Example::
+
+        fp = sqlfp.StreamFingerprinter(dialect="postgres")
+        fp.feed("SELECT * FROM t WHERE a")   # []
+        fp.feed(" = 1; SELECT 2")            # [fingerprint of the first]
+        fp.finish()                          # fingerprint of SELECT 2
+    """
+
+    def __init__(self, dialect: Optional[Dialect] = None) -> None:
+        """``dialect`` falls back to the :func:`configure` default.
+
+        Raises:
+            ValueError: If the dialect is not supported.
+        """
+        ...
+
+    def feed(self, chunk: str | bytes) -> list[str]:
+        """Read the next chunk.
+
+        Returns:
+            The SHA-256 hex digests of the statements the chunk ends, in order.
+        """
+        ...
+
+    def finish(self) -> Optional[str]:
+        """End the stream, and start over for a new one.
+
+        Returns:
+            The digest of the last statement if no ``;`` ended it, else ``None``.
+
+        Raises:
+            ValueError: If a string literal, quoted identifier or comment is
+                unterminated.
+        """
+        ...
+
+def fingerprint_mysql_query(
+    payload: bytes,
+    *,
+    encoding: str = "utf8mb4",
+    errors: Optional[str] = None,
+    query_attributes: bool = False,
+    dialect: Dialect = "mysql",
+    compat: Optional[str] = None,
+    on_error: Literal["raise", "skip"] = "raise",
+) -> list[NormalizeResult]:
+    """Normalize the statements of a captured MySQL ``COM_QUERY`` packet.
+
+    ``payload`` is the packet payload, without its 4-byte header: the
+    ``0x03`` command byte, then the query. Clients that negotiated
+    ``CLIENT_QUERY_ATTRIBUTES`` (MySQL 8.0.23 and later) send query attributes
+    ahead of the query, which ``query_attributes=True`` skips. The query is
+    split and normalized like :func:`normalize_script`, multi-statement
+    payloads giving one result per statement.
+
+    Args:
+        encoding: The connection's character set, as MySQL names it
+            (``utf8mb4``, ``latin1``, ``sjis``, ...), or a Python codec.
+        errors: How decoding errors are handled, as for :meth:`bytes.decode`;
+            falls back to the :func:`configure` default.
+        on_error: As for :func:`normalize_script`.
+
+    Raises:
+        ValueError: If the payload is not a ``COM_QUERY`` payload or is
+            truncated, or a statement fails and ``on_error`` is ``"raise"``.
+        UnicodeDecodeError: If the query cannot be decoded and ``errors`` is
+            ``"strict"``.
+    """
+    ...
+
+def fingerprint_postgres_message(
+    body: bytes,
+    message_type: Literal["Q", "P"] = "Q",
+    *,
+    encoding: str = "UTF8",
+    errors: Optional[str] = None,
+    dialect: Dialect = "postgresql",
+    compat: Optional[str] = None,
+    on_error: Literal["raise", "skip"] = "raise",
+) -> list[NormalizeResult]:
+    """Normalize the statements of a captured PostgreSQL frontend message.
+
+    ``body`` is the message without its type byte and length: the
+    NUL-terminated query of a simple ``Query`` message (``"Q"``), or the
+    NUL-terminated statement name and query of a ``Parse`` message (``"P"``),
+    whose parameter types are ignored. The query is split and normalized
+    like :func:`normalize_script`, one result per statement.
+
+    Args:
+        encoding: The connection's ``client_encoding``, as PostgreSQL names
+            it (``UTF8``, ``LATIN1``, ``WIN1252``, ...), or a Python codec.
+        errors: How decoding errors are handled, as for :meth:`bytes.decode`;
+            falls back to the :func:`configure` default.
+        on_error: As for :func:`normalize_script`.
+
+    Raises:
+        ValueError: If ``message_type`` is not supported, the body is missing
+            a NUL terminator, or a statement fails and ``on_error`` is
+            ``"raise"``.
+        UnicodeDecodeError: If the query cannot be decoded and ``errors`` is
+            ``"strict"``.
+    """
+    ...
+
+def fingerprint_mysql_slowlog(
+    path: str | os.PathLike[str],
+    *,
+    encoding: str = "utf-8",
+    errors: Optional[str] = None,
+    dialect: Dialect = "mysql",
+    compat: Optional[str] = None,
+    on_error: Literal["raise", "skip"] = "raise",
+) -> LogResults:
+    """Normalize the statements of a MySQL slow query log.
+
+    The log is read a line at a time as the results are iterated over. The
+    query of each entry is split and normalized like :func:`normalize_script`,
+    and each result's metadata gets the ``line`` its statement starts on and
+    the entry's ``timestamp`` (``# Time:``), ``unix_timestamp``
+    (``SET timestamp=``), ``user``, ``host``, ``thread_id``, ``database``
+    (``use``, or Percona's ``# Schema:``), ``duration_us`` and
+    ``lock_time_us`` (in microseconds), ``rows_sent`` and ``rows_examined``,
+    ``None`` where the entry has none. Entries without ``# Time:``, which
+    servers before 5.7 write once per second, keep the last one; server
+    start banners and ``# administrator command:`` entries are skipped.
+
+    Args:
+        encoding: The codec the log is written in.
+        errors: How decoding errors are handled, as for :meth:`bytes.decode`;
+            falls back to the :func:`configure` default.
+        on_error: ``"skip"`` to leave out the statements that fail to
+            normalize and the lines that fail to decode.
+
+    Raises:
+        OSError: If the log cannot be opened or read.
+        ValueError: If a statement fails and ``on_error`` is ``"raise"``,
+            the message starting with the path and line.
+    """
+    ...
+
+def fingerprint_postgres_csvlog(
+    path: str | os.PathLike[str],
+    *,
+    encoding: str = "utf-8",
+    errors: Optional[str] = None,
+    dialect: Dialect = "postgresql",
+    compat: Optional[str] = None,
+    on_error: Literal["raise", "skip"] = "raise",
+) -> LogResults:
+    """Normalize the statements of a PostgreSQL CSV log
+    (``log_destination = 'csvlog'``).
+
+    The log is read a row at a time as the results are iterated over. The
+    ``statement:`` and ``execute ...:`` messages of ``log_statement`` and
+    ``log_min_duration_statement`` are normalized like
+    :func:`normalize_script`; other messages, ``parse`` and ``bind``
+    included, are skipped. Each result's metadata gets the ``line`` its row
+    starts on and the row's ``timestamp`` (``log_time``), ``user``,
+    ``database``, ``pid``, ``session_id``, ``application_name``,
+    ``duration_us`` (in microseconds, from ``duration:``) and ``parameters``
+    (the ``parameters:`` detail of ``execute``), ``None`` where the row has
+    none.
+
+    Args:
+        encoding: The codec the log is written in.
+        errors: How decoding errors are handled, as for :meth:`bytes.decode`;
+            falls back to the :func:`configure` default.
+        on_error: ``"skip"`` to leave out the statements that fail to
+            normalize and the rows that are not csvlog rows.
+
+    Raises:
+        OSError: If the log cannot be opened or read.
+        ValueError: If a row is not a csvlog row, or a statement fails, and
+            ``on_error`` is ``"raise"``, the message starting with the path
+            and line.
+    """
+    ...
+
+def parse_ast(sql: str, dialect: Dialect = "generic") -> AstNode:
+    """Parse the first statement of ``sql`` into a read-only node tree.
+
+    The tree is the one sqlfp normalizes, before any normalization is applied.
+    Statements, queries, table factors, relations and expressions are
+    represented; other AST details are only visible through ``sql``.
+
+    Raises:
+        ValueError: If the dialect is not supported or the SQL cannot be parsed.
+    """
+    ...
+
+@overload
+def parse(sql: str, dialect: Dialect = "generic", *, as_json: Literal[False] = False) -> dict[str, Any]: ...
+@overload
+def parse(sql: str, dialect: Dialect = "generic", *, as_json: Literal[True]) -> str: ...
+def parse(sql: str, dialect: Dialect = "generic", *, as_json: bool = False) -> Union[dict[str, Any], str]:
+    """Parse the first statement of ``sql`` into nested dicts and lists.
+
+    Unlike :func:`parse_ast`, every detail of the AST is there, laid out as
+    sqlparser serializes it: enum variants as single-key dicts
+    (``{"Query": {...}}``), identifiers, literals and keywords with their
+    ``span``. The layout follows the pinned sqlparser version and may change
+    when sqlfp upgrades it.
+
+    Args:
+        as_json: Return the same document as a JSON string instead.
+
+    Raises:
+        ValueError: If the dialect is not supported or the SQL cannot be parsed.
+    """
+    ...
+
+def rewrite(
+    sql: str,
+    rules: Iterable[RewriteRule],
+    dialect: Dialect = "generic",
+    placeholder: str = "?",
+) -> Tuple[str, NormalizeResult]:
+    """Apply declarative rewrite rules to the first statement of ``sql``.
+
+    Each rule is a mapping with an ``"op"`` key, applied in order:
+
+    - ``{"op": "rename_table", "from": "orders", "to": "orders_v2"}`` renames a
+      table in FROM/JOIN/DML targets and in column qualifiers. ``from`` matches
+      name suffixes, so ``orders`` also matches ``sales.orders``.
+    - ``{"op": "replace_schema", "from": "tenant_a", "to": "tenant_b"}``
+      replaces the schema part of qualified names.
+    - ``{"op": "drop_predicate", "column": "tenant_id"}`` removes every
+      top-level AND-ed WHERE condition referencing the column.
+
+    Returns:
+        The rewritten SQL and the :class:`NormalizeResult` of that SQL.
+
+    Raises:
+        ValueError: If a rule is malformed, the dialect is not supported or
+            the SQL cannot be parsed.
+    """
+    ...
+
+def retarget(
+    sql: str,
+    mapping: Mapping[str, str],
+    dialect: Dialect = "generic",
+) -> str:
+    """Rename the tables and schemas of the first statement of ``sql`` per
+    ``mapping`` and return it re-serialized, e.g. to replay captured queries
+    against shadow schemas.
+
+    Names are renamed wherever they are referenced: ``FROM`` and joins,
+    subqueries and CTE bodies, DML targets and column qualifiers. A key
+    renames the tables whose name it ends (``orders`` also matches
+    ``sales.orders``), or failing that the schema of the tables it qualifies
+    (``sales`` matches ``sales.orders``); the longest matching key wins, and
+    all renames apply at once, so a mapping can swap two names. A qualified
+    value replaces the qualifiers it spells out: with ``{"orders":
+    "shadow.orders"}``, ``sales.orders`` becomes ``shadow.orders``.
+    References to the CTEs in scope and column qualifiers naming an alias are
+    left alone; a table read in the body of a CTE of the same name is still
+    renamed.
+
+    Raises:
+        ValueError: If the dialect is not supported or the SQL cannot be
+            parsed.
+    """
+    ...
+
+def convert_paramstyle(
+    sql: str,
+    from_style: ParamStyle,
+    to_style: ParamStyle,
+    dialect: Dialect = "generic",
+) -> Tuple[str, list[int | str]]:
+    """Rewrite DB-API placeholders from one paramstyle to another.
+
+    Placeholders are located on the token stream, so ``?`` or ``%s`` inside
+    string literals, quoted identifiers and comments are left alone. Only
+    placeholders of ``from_style`` are converted; ``%%`` escapes are added or
+    removed as needed when converting to or from ``format``/``pyformat``.
+
+    Returns:
+        The converted SQL and, for each parameter it takes, the source
+        parameter to bind: a 1-based position for ``qmark``, ``format`` and
+        ``numeric`` sources, a name for ``named`` and ``pyformat`` ones.
+        Positional targets (``qmark``, ``format``) get one entry per
+        placeholder; the others one per distinct parameter. Positional
+        parameters are named ``p1``, ``p2``, ... in named styles.
+
+    Raises:
+        ValueError: If a style or the dialect is not supported, or the SQL
+            cannot be tokenized.
+
+    Example::
+
+        sqlfp.convert_paramstyle("SELECT * FROM t WHERE a = %(x)s OR b = %(x)s", "pyformat", "qmark")
+        # ("SELECT * FROM t WHERE a = ? OR b = ?", ["x", "x"])
+    """
+    ...
+
+def strip_comments(sql: str, dialect: Dialect = "generic") -> str:
+    """Remove all comments from ``sql``, leaving the rest untouched.
+
+    Comments are located on the token stream, so ``--`` and ``/*`` inside
+    string literals and quoted identifiers are left alone, and the dialect
+    decides what is a comment (e.g. ``#`` in MySQL, nested block comments in
+    PostgreSQL). A line comment leaves its line break behind and a block
+    comment between two tokens a single space. MySQL ``/*! ... */`` hints are
+    executed by the server and are kept.
+
+    Raises:
+        ValueError: If the dialect is not supported or the SQL cannot be
+            tokenized.
+
+    Example::
+
+        sqlfp.strip_comments("SELECT 1 -- one\nFROM t /* x */")
+        # "SELECT 1 \nFROM t "
+    """
+    ...
+
+def redact(sql: str, dialect: Dialect = "generic", *, mask: str = "?") -> str:
+    """Replace the literals of ``sql`` with ``mask``, leaving the rest as written.
+
+    Literals are located on the token stream, so whitespace, case and
+    comments are kept byte for byte, and the statement need not parse.
+    Numbers, strings in all their prefixed and quoted forms, ``TRUE`` and
+    ``FALSE`` are masked; ``NULL`` and placeholders already in the statement
+    are kept. A ``{n}`` in ``mask`` numbers the literals, from 1 or after the
+    highest placeholder of the same style in the statement.
+
+    Raises:
+        ValueError: If the dialect is not supported or the SQL cannot be
+            tokenized.
+
+    Example::
+
+        sqlfp.redact("SELECT * FROM t -- note\nWHERE email = 'bob@x.com'")
+        # "SELECT * FROM t -- note\nWHERE email = ?"
+    """
+    ...
+
+class Tables(TypedDict):
+    read: list[str]
+    written: list[str]
+
+def extract_tables(sql: str, dialect: Dialect = "generic") -> Tables:
+    """The tables the first statement of ``sql`` reads and writes.
+
+    ``written`` has the targets of ``INSERT``, ``UPDATE``, ``DELETE`` and
+    ``MERGE``, at any depth, and the table or view a ``CREATE TABLE`` or
+    ``CREATE VIEW`` defines; ``read`` every other table referenced, in joins,
+    subqueries and CTE bodies alike. References to CTEs are left out. A
+    table both written and read, as in
+    ``UPDATE t SET a = (SELECT max(a) FROM t)``, is in both. Names are as
+    written, schema included, each listed once in order of appearance.
+
+    Raises:
+        ValueError: If the dialect is not supported or the SQL cannot be
+            parsed.
+
+    Example::
+
+        sqlfp.extract_tables("UPDATE t SET a = 1 FROM u WHERE t.id = u.id", "postgres")
+        # {"read": ["u"], "written": ["t"]}
+    """
+    ...
+
+def extract_columns(sql: str, dialect: Dialect = "generic") -> dict[str, list[str]]:
+    """The columns the first statement of ``sql`` references, by clause.
+
+    Clauses are named as in ``NormalizeResult.param_clauses``, in order of
+    their first column; clauses without columns are left out. A column is
+    under the clause of the innermost query or statement holding it, so the
+    columns of a subquery in a ``WHERE`` are under that subquery's own
+    ``select``, ``where`` and so on. ``JOIN ... USING`` columns are under
+    ``join``. Names are as written, qualifier included, each listed once per
+    clause. Select-list aliases referenced in ``ORDER BY`` are listed like
+    columns.
+
+    Raises:
+        ValueError: If the dialect is not supported or the SQL cannot be
+            parsed.
+
+    Example::
+
+        sqlfp.extract_columns("SELECT a FROM t JOIN s USING (id) WHERE b > 1 ORDER BY a")
+        # {"join": ["id"], "select": ["a"], "where": ["b"], "order_by": ["a"]}
+    """
+    ...
+
+def verify(
+    sql: str | bytes,
+    dialect: Optional[Dialect] = None,
+    placeholder: Optional[str] = None,
+    compat: Optional[str] = None,
+    enable_passes: Optional[Sequence[Pass]] = None,
+) -> VerifyReport:
+    """Check that normalization is idempotent on ``sql``.
+
+    ``sql`` is normalized, then its normalized text is normalized again with
+    the same options; ``stable`` is true when both give the same normalized
+    text and hash. Meant for running over a corpus to find statements whose
+    normalization is not a fixed point. Options left as ``None`` take the
+    value set with :func:`configure`.
+
+    Returns:
+        The first pass's ``normalized`` and ``hash``, the second pass's
+        ``renormalized`` and ``renormalized_hash``, and ``stable``. If the
+        normalized text does not parse again, ``stable`` is false, the
+        second pass's entries are ``None`` and ``error`` holds the parse
+        error.
+
+    Raises:
+        ValueError: If ``sql`` cannot be parsed, or an option is not
+            supported.
+    """
+    ...
+
+def stats(reset: bool = False) -> Stats:
+    """Process-wide normalization counters.
+
+    ``statements_normalized`` and ``parse_failures`` count the calls to
+    :func:`normalize` (and lines of :func:`normalize_file`) that succeeded or
+    failed to parse; ``cache_hits`` counts calls served by an already pooled
+    engine (see :func:`engine_pool`); ``dialects`` breaks the former down by canonical dialect
+    name (``"postgres"`` counts as ``"postgresql"``, ``"mariadb"`` as
+    ``"mysql"``). Counters are shared by all threads.
+
+    Args:
+        reset: Zero the counters after reading them, e.g. when exporting
+            deltas to a metrics system.
+    """
+    ...
+
+def engine_pool() -> list[PooledEngine]:
+    """The engines currently pooled, for debugging.
+
+    :func:`normalize` and :func:`normalize_file` build one engine (resolved
+    dialect, compat rules and options) per distinct combination of
+    ``dialect``, ``placeholder``, ``compat``, ``templates``, ``strict``,
+    ``passes``, ``disable_passes``, ``enable_passes``, ``function_case``,
+    ``identifier_case``, ``clause_hashes``, ``max_subquery_depth``, ``routing_columns``,
+    ``placeholder_collision``, ``ignore_clauses``, ``table_columns``,
+    ``function_synonyms``, ``collect_params``, ``max_params``, ``truncated_input`` and ``lenient``, and reuse it for later calls.
+    ``hits`` counts those reuses. At most 64 engines are pooled; further
+    combinations are built per call.
+    """
+    ...
+
+def clear_engine_pool() -> None:
+    """Drop all pooled engines; they are rebuilt on next use."""
+    ...
+
+def configure(
+    path: Optional[str | os.PathLike[str]] = None,
+    *,
+    reset: bool = False,
+    **kwargs: Unpack[DefaultOptions],
+) -> Config:
+    """Set process-wide defaults for :func:`normalize` and :func:`normalize_file`.
+
+    Settings are applied in order: ``reset`` first clears all previously
+    configured defaults, then the config file at ``path`` is loaded, then the
+    keyword arguments given are applied. Settings not mentioned keep their
+    current value.
+
+    The config file is TOML (``.toml``) or JSON (``.json``) with the same keys
+    as the keyword arguments, at the top level or under ``[tool.sqlfp]``, so
+    it can live in ``pyproject.toml``::
+
+        [tool.sqlfp]
+        dialect = "postgres"
+        compat = "0.1"
+
+    Returns:
+        The defaults now in effect; settings never configured are left out.
+
+    Raises:
+        OSError: If the config file cannot be read.
+        ValueError: If the config file is malformed or has unknown keys, or a
+            dialect, compat version or pass name is not supported.
     """
     ...
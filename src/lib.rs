@@ -1,36 +1,531 @@
+// pyo3 0.22's #[pyfunction] expansion trips this lint on every `PyResult` return
+#![allow(clippy::useless_conversion)]
+
+mod literals;
+mod typed;
+
 use pyo3::prelude::*;
-use pyo3::exceptions::PyValueError;
-use sqlparser::ast::{
-    BinaryOperator, Expr, FromTable, JoinConstraint, JoinOperator,
-    ObjectNamePart, OrderByKind, Query, SetExpr, Statement,
-    TableFactor, UnaryOperator, UpdateTableFromKind,
-    Value, WindowType, visit_expressions_mut,
+use pyo3::create_exception;
+use pyo3::exceptions::{PyKeyboardInterrupt, PyTypeError, PyValueError};
+use pyo3::types::{PyBool, PyBytes, PyDict, PyList, PyString, PyTuple};
+use rayon::prelude::*;
+use sqlparser::ast::Statement;
+use sqlparser::dialect::Dialect;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use sqlfp_core::metadata::{MetaValue, Metadata};
+use sqlfp_core::{
+    canonical_dialect, cartesian_metadata, compute_hash, duplicate_branches_metadata, error_position, get_dialect,
+    hashed_text, normalize_statement, returning_items, returning_metadata, sql_dialect, step_names, string_quotes,
+    style_placeholder, Clause, Fingerprint, Normalized, Pass, PassOrder, Rules, Step, NO_STATEMENT, PARSE_PATH_AST,
+    PARSE_PATH_TOKENS, UNSUPPORTED_DIALECT,
 };
-use sqlparser::dialect::{GenericDialect, MySqlDialect, PostgreSqlDialect, SQLiteDialect, AnsiDialect, MsSqlDialect, OracleDialect};
-use sqlparser::parser::Parser;
-use sha2::{Sha256, Digest};
-use core::ops::ControlFlow;
+use sqlfp_core::{
+    aggregate, ast, bind, bundle, classify, collapse, columns, comments, config, corpus, detect, diff, hashing,
+    keep, logs, mask, metrics, msgpack, offsets, paramstyle, pool, qualifiers, registry, rewrite, script, session,
+    similarity, stats, stream, tables, template, wire, writer,
+};
+
+create_exception!(
+    sqlfp,
+    Error,
+    PyValueError,
+    "Base class of the errors raised for the statements and options given to sqlfp."
+);
+
+create_exception!(
+    sqlfp,
+    ParseError,
+    Error,
+    "Raised when a statement does not parse; `line`, `column` and `token` tell where, when known."
+);
+
+create_exception!(sqlfp, UnsupportedDialectError, Error, "Raised for a dialect name that is not supported.");
+
+create_exception!(sqlfp, EmptyStatementError, Error, "Raised when the input holds no statement, only whitespace or comments.");
+
+create_exception!(
+    sqlfp,
+    UnnormalizableError,
+    Error,
+    "Raised with `strict=True` when part of a statement cannot be normalized."
+);
 
+create_exception!(
+    sqlfp,
+    PlaceholderCollisionError,
+    Error,
+    "Raised when the placeholder also occurs in the statement itself, per `placeholder_collision`."
+);
 
 #[pyclass(module = "sqlfp")]
-#[derive(Clone)]
+#[derive(Clone, Default)]
 struct NormalizeResult {
     #[pyo3(get)]
     normalized: String,
-    #[pyo3(get)]
     hash: String,
+    /// Hash of `normalized` with its value lists collapsed
+    shape_hash: String,
+    /// How `hash` and `shape_hash` were computed
+    hasher: hashing::Hasher,
+    /// `normalized` with its value lists collapsed, if that changed it
+    shape: Option<String>,
     #[pyo3(get)]
     original: String,
     #[pyo3(get)]
     params: Vec<String>,
+    /// Prefix of each of `params` written in a prefixed string form
+    /// (`N'...'`, `E'...'`, ...), `None` for the others
+    #[pyo3(get)]
+    param_types: Vec<Option<String>>,
+    /// Kind of each of `params` written as a number (`integer`, `decimal` or
+    /// `float`), `None` for the others
+    #[pyo3(get)]
+    param_kinds: Vec<Option<&'static str>>,
+    /// Byte offsets `(start, end)` of each of `params` in `original`, `None`
+    /// where the literal has no place there
+    #[pyo3(get)]
+    param_spans: Vec<Option<(usize, usize)>>,
+    /// The placeholder the literals were written as, and the number of the
+    /// first where it is numbered, for `bind()`
+    placeholder: String,
+    placeholder_start: usize,
+    #[pyo3(get)]
+    warnings: Vec<String>,
+    /// Names of the passes (and `hook:<name>` for caller hooks) that
+    /// produced `normalized`, in the order they ran
+    #[pyo3(get)]
+    passes: Vec<String>,
+    /// Whether the statement has a RETURNING clause, its columns being in
+    /// `metadata["returning"]`
+    #[pyo3(get)]
+    has_returning: bool,
+    /// `"SELECT"`, `"INSERT"`, `"UPDATE"`, `"DELETE"`, `"DDL"` or `"OTHER"`,
+    /// see `classify`
+    #[pyo3(get)]
+    statement_class: &'static str,
+    #[pyo3(get)]
+    is_read_only: bool,
+    #[pyo3(get)]
+    is_ddl: bool,
+    /// Whether the statement was the one an `EXPLAIN` wraps, see
+    /// `unwrap_explain`
+    #[pyo3(get)]
+    explained: bool,
+    /// Complexity of the normalized statement, `None` where it was not parsed
+    #[pyo3(get)]
+    metrics: Option<QueryMetrics>,
+    /// Canonical name of the dialect the statement was parsed with
+    #[pyo3(get)]
+    dialect_used: &'static str,
+    /// How the fingerprint was computed: `"ast"` from the parsed statement,
+    /// `"tokens"` from its token stream when it could not be parsed
+    #[pyo3(get)]
+    parse_path: &'static str,
+    metadata: Metadata,
+    /// Kind of the statement, for `LeanResult`
+    statement_type: String,
+    /// The settings the statement was normalized with, `None` for the
+    /// results of `rewrite()`
+    options: Option<std::sync::Arc<pool::EngineKey>>,
+}
+
+/// The value of `hash`, computed by `hasher`, for Python: an `int` with
+/// `hash_encoding="int"`, else a `str`
+fn hash_value(py: Python<'_>, hasher: &hashing::Hasher, hash: &str) -> PyObject {
+    match hasher.int(hash) {
+        Some(n) => n.into_py(py),
+        None => hash.into_py(py),
+    }
+}
+
+fn meta_to_object(py: Python<'_>, value: &MetaValue) -> PyResult<PyObject> {
+    Ok(match value {
+        MetaValue::Null => py.None(),
+        MetaValue::Bool(b) => b.into_py(py),
+        MetaValue::Int(n) => n.into_py(py),
+        MetaValue::Str(s) => s.into_py(py),
+        MetaValue::List(items) => {
+            let items = items
+                .iter()
+                .map(|item| meta_to_object(py, item))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new_bound(py, items).into_py(py)
+        }
+        MetaValue::Map(entries) => meta_to_dict(py, entries)?.into_py(py),
+    })
+}
+
+/// The Python value of a JSON document: dicts, lists, `str`, `int`, `float`,
+/// `bool` and `None`
+fn json_to_object(py: Python<'_>, value: &serde_json::Value) -> PyResult<PyObject> {
+    Ok(match value {
+        serde_json::Value::Null => py.None(),
+        serde_json::Value::Bool(b) => b.into_py(py),
+        serde_json::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(n), _) => n.into_py(py),
+            (None, Some(n)) => n.into_py(py),
+            (None, None) => n.as_f64().unwrap_or(f64::NAN).into_py(py),
+        },
+        serde_json::Value::String(s) => s.into_py(py),
+        serde_json::Value::Array(items) => {
+            let items = items.iter().map(|item| json_to_object(py, item)).collect::<PyResult<Vec<_>>>()?;
+            PyList::new_bound(py, items).into_py(py)
+        }
+        serde_json::Value::Object(entries) => {
+            let dict = PyDict::new_bound(py);
+            for (key, value) in entries {
+                dict.set_item(key, json_to_object(py, value)?)?;
+            }
+            dict.into_py(py)
+        }
+    })
+}
+
+fn meta_to_dict<'py>(py: Python<'py>, entries: &Metadata) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    for (key, value) in entries {
+        dict.set_item(key, meta_to_object(py, value)?)?;
+    }
+    Ok(dict)
+}
+
+/// The metadata value of a Python one `meta_to_object` gave
+fn object_to_meta(value: &Bound<'_, PyAny>) -> PyResult<MetaValue> {
+    Ok(if value.is_none() {
+        MetaValue::Null
+    } else if let Ok(b) = value.downcast::<PyBool>() {
+        MetaValue::Bool(b.is_true())
+    } else if let Ok(s) = value.downcast::<PyString>() {
+        MetaValue::Str(s.to_cow()?.into_owned())
+    } else if let Ok(list) = value.downcast::<PyList>() {
+        MetaValue::List(list.iter().map(|item| object_to_meta(&item)).collect::<PyResult<_>>()?)
+    } else if let Ok(dict) = value.downcast::<PyDict>() {
+        MetaValue::Map(dict_to_meta(dict)?)
+    } else {
+        MetaValue::Int(value.extract()?)
+    })
+}
+
+fn dict_to_meta(dict: &Bound<'_, PyDict>) -> PyResult<Metadata> {
+    dict.iter().map(|(key, value)| Ok((key.extract()?, object_to_meta(&value)?))).collect()
+}
+
+/// The value of `key` in the pickled state of a result
+fn state_item<'py, T: FromPyObject<'py>>(state: &Bound<'py, PyDict>, key: &str) -> PyResult<T> {
+    match state.get_item(key)? {
+        Some(value) => T::extract_bound(&value),
+        None => Err(PyValueError::new_err(format!("Invalid NormalizeResult state: no {:?}", key))),
+    }
+}
+
+/// The one of `names` that `value` is, for the pickled state of a result
+fn state_name(value: &str, names: &[&'static str]) -> PyResult<&'static str> {
+    names
+        .iter()
+        .find(|name| **name == value)
+        .copied()
+        .ok_or_else(|| PyValueError::new_err(format!("Invalid NormalizeResult state: unknown {:?}", value)))
+}
+
+/// The result pickled as `state`, which `NormalizeResult.__reduce__()` names
+/// for `pickle` and `copy` to rebuild it with
+#[pyfunction]
+#[pyo3(name = "_restore_result")]
+fn restore_result(state: &Bound<'_, PyDict>) -> PyResult<NormalizeResult> {
+    NormalizeResult::from_state(state)
+}
+
+impl NormalizeResult {
+    /// Everything the result holds, for `pickle` and `copy`
+    fn state<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let state = PyDict::new_bound(py);
+        state.set_item("normalized", &self.normalized)?;
+        state.set_item("hash", &self.hash)?;
+        state.set_item("shape_hash", &self.shape_hash)?;
+        state.set_item("hash_algorithm", self.hasher.name())?;
+        state.set_item("hash_encoding", self.hasher.encoding_name())?;
+        state.set_item("shape", &self.shape)?;
+        state.set_item("original", &self.original)?;
+        state.set_item("params", &self.params)?;
+        state.set_item("param_types", &self.param_types)?;
+        state.set_item("param_kinds", &self.param_kinds)?;
+        state.set_item("param_spans", &self.param_spans)?;
+        state.set_item("placeholder", &self.placeholder)?;
+        state.set_item("placeholder_start", self.placeholder_start)?;
+        state.set_item("warnings", &self.warnings)?;
+        state.set_item("passes", &self.passes)?;
+        state.set_item("has_returning", self.has_returning)?;
+        state.set_item("statement_class", self.statement_class)?;
+        state.set_item("is_read_only", self.is_read_only)?;
+        state.set_item("is_ddl", self.is_ddl)?;
+        state.set_item("explained", self.explained)?;
+        state.set_item("metrics", self.metrics.as_ref().map(|metrics| metrics.to_dict(py)).transpose()?)?;
+        state.set_item("dialect_used", self.dialect_used)?;
+        state.set_item("parse_path", self.parse_path)?;
+        state.set_item("metadata", self.metadata(py)?)?;
+        state.set_item("statement_type", &self.statement_type)?;
+        // The options have no Python form that keeps caller hooks in `passes`
+        let options = self.options.as_ref().map(|key| serde_json::to_string(&**key)).transpose();
+        state.set_item("options", options.map_err(|e| PyValueError::new_err(e.to_string()))?)?;
+        Ok(state)
+    }
+
+    /// The result `state()` was taken from
+    fn from_state(state: &Bound<'_, PyDict>) -> PyResult<NormalizeResult> {
+        let hash_algorithm: String = state_item(state, "hash_algorithm")?;
+        let hash_encoding: String = state_item(state, "hash_encoding")?;
+        let param_kinds: Vec<Option<String>> = state_item(state, "param_kinds")?;
+        let metrics: Option<Bound<'_, PyDict>> = state_item(state, "metrics")?;
+        let options: Option<String> = state_item(state, "options")?;
+        Ok(NormalizeResult {
+            normalized: state_item(state, "normalized")?,
+            hash: state_item(state, "hash")?,
+            shape_hash: state_item(state, "shape_hash")?,
+            hasher: hashing::Hasher::new(Some(&hash_algorithm), Some(&hash_encoding)).map_err(PyValueError::new_err)?,
+            shape: state_item(state, "shape")?,
+            original: state_item(state, "original")?,
+            params: state_item(state, "params")?,
+            param_types: state_item(state, "param_types")?,
+            param_kinds: param_kinds
+                .iter()
+                .map(|kind| kind.as_deref().map(|kind| state_name(kind, &sqlfp_core::NUMERIC_KINDS)).transpose())
+                .collect::<PyResult<_>>()?,
+            param_spans: state_item(state, "param_spans")?,
+            placeholder: state_item(state, "placeholder")?,
+            placeholder_start: state_item(state, "placeholder_start")?,
+            warnings: state_item(state, "warnings")?,
+            passes: state_item(state, "passes")?,
+            has_returning: state_item(state, "has_returning")?,
+            statement_class: state_name(&state_item::<String>(state, "statement_class")?, &classify::CLASSES)?,
+            is_read_only: state_item(state, "is_read_only")?,
+            is_ddl: state_item(state, "is_ddl")?,
+            explained: state_item(state, "explained")?,
+            metrics: metrics.map(|metrics| QueryMetrics::from_dict(&metrics)).transpose()?,
+            dialect_used: state_name(&state_item::<String>(state, "dialect_used")?, &stats::DIALECTS)?,
+            parse_path: state_name(&state_item::<String>(state, "parse_path")?, &[PARSE_PATH_AST, PARSE_PATH_TOKENS])?,
+            metadata: dict_to_meta(&state_item(state, "metadata")?)?,
+            statement_type: state_item(state, "statement_type")?,
+            options: options
+                .map(|options| serde_json::from_str(&options).map(std::sync::Arc::new))
+                .transpose()
+                .map_err(|e| PyValueError::new_err(e.to_string()))?,
+        })
+    }
+
+    /// Write the result as a MessagePack map keyed by its attribute names
+    fn write_msgpack(&self, enc: &mut msgpack::Encoder) {
+        enc.map(20);
+        enc.str("normalized");
+        enc.str(&self.normalized);
+        for (key, value) in [("hash", &self.hash), ("shape_hash", &self.shape_hash)] {
+            enc.str(key);
+            match self.hasher.int(value) {
+                Some(n) => enc.uint(n),
+                None => enc.str(value),
+            }
+        }
+        enc.str("hash_algorithm");
+        enc.str(self.hasher.name());
+        enc.str("original");
+        enc.str(&self.original);
+        enc.str("params");
+        enc.strs(&self.params);
+        enc.str("param_types");
+        enc.array(self.param_types.len());
+        for param_type in &self.param_types {
+            match param_type {
+                Some(prefix) => enc.str(prefix),
+                None => enc.nil(),
+            }
+        }
+        enc.str("param_kinds");
+        enc.array(self.param_kinds.len());
+        for kind in &self.param_kinds {
+            match kind {
+                Some(kind) => enc.str(kind),
+                None => enc.nil(),
+            }
+        }
+        enc.str("param_spans");
+        enc.array(self.param_spans.len());
+        for span in &self.param_spans {
+            match span {
+                Some((start, end)) => {
+                    enc.array(2);
+                    enc.int(*start as i64);
+                    enc.int(*end as i64);
+                }
+                None => enc.nil(),
+            }
+        }
+        enc.str("warnings");
+        enc.strs(&self.warnings);
+        enc.str("passes");
+        enc.strs(&self.passes);
+        enc.str("dialect_used");
+        enc.str(self.dialect_used);
+        enc.str("parse_path");
+        enc.str(self.parse_path);
+        enc.str("has_returning");
+        enc.bool(self.has_returning);
+        enc.str("statement_class");
+        enc.str(self.statement_class);
+        enc.str("is_read_only");
+        enc.bool(self.is_read_only);
+        enc.str("is_ddl");
+        enc.bool(self.is_ddl);
+        enc.str("explained");
+        enc.bool(self.explained);
+        enc.str("metrics");
+        match &self.metrics {
+            Some(metrics) => metrics.write_msgpack(enc),
+            None => enc.nil(),
+        }
+        enc.str("metadata");
+        enc.metadata(&self.metadata);
+    }
 }
 
 #[pymethods]
 impl NormalizeResult {
+    #[getter]
+    fn hash(&self, py: Python<'_>) -> PyObject {
+        hash_value(py, &self.hasher, &self.hash)
+    }
+
+    #[getter]
+    fn shape_hash(&self, py: Python<'_>) -> PyObject {
+        hash_value(py, &self.hasher, &self.shape_hash)
+    }
+
+    /// The `hash_algorithm` `hash` and `shape_hash` were computed with
+    #[getter]
+    fn hash_algorithm(&self) -> &'static str {
+        self.hasher.name()
+    }
+
+    /// The settings the statement was normalized with, for normalizing it
+    /// again to the same fingerprint
+    #[getter]
+    fn options(&self) -> PyResult<Option<NormalizeOptions>> {
+        let Some(ref key) = self.options else {
+            return Ok(None);
+        };
+        let engine = pool::engine((**key).clone()).map_err(PyValueError::new_err)?;
+        Ok(Some(NormalizeOptions { engine }))
+    }
+
+    /// Whether sqlparser parsed the statement, `parse_path` being `"ast"`
+    #[getter]
+    fn parsed(&self) -> bool {
+        self.parse_path == PARSE_PATH_AST
+    }
+
+    /// Extra information about how the statement was processed (a fresh dict on each access)
+    #[getter]
+    fn metadata<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        meta_to_dict(py, &self.metadata)
+    }
+
+    /// `params` as Python values (`int`, `float`, `bool`, `bytes` for hex
+    /// strings, `str` unquoted), for binding them back (a fresh list on each access)
+    #[getter]
+    fn typed_params(&self, py: Python<'_>) -> PyResult<Vec<PyObject>> {
+        self.params
+            .iter()
+            .zip(&self.param_types)
+            .zip(&self.param_kinds)
+            .map(|((param, prefix), kind)| typed::value(py, param, prefix.as_deref(), *kind))
+            .collect()
+    }
+
+    /// `normalized` with parameters bound back into its placeholders as
+    /// literals of `dialect_used`: the result's own `params` as written, or
+    /// the Python values `params`
+    #[pyo3(signature = (params=None))]
+    fn bind(&self, params: Option<Vec<Bound<'_, PyAny>>>) -> PyResult<String> {
+        let dialect = get_dialect(self.dialect_used).map_err(options_error)?;
+        let params = match params {
+            Some(values) => values.iter().map(|value| literals::literal(value, &*dialect, self.dialect_used)).collect::<PyResult<Vec<_>>>()?,
+            None => self
+                .params
+                .iter()
+                .zip(&self.param_types)
+                .map(|(param, prefix)| format!("{}{}", prefix.as_deref().unwrap_or_default(), param))
+                .collect(),
+        };
+        bind::bind(&self.normalized, &*dialect, &self.placeholder, self.placeholder_start, &params).map_err(PyValueError::new_err)
+    }
+
+    /// The result as a MessagePack map keyed by its attribute names
+    fn to_msgpack<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        let mut enc = msgpack::Encoder::new();
+        self.write_msgpack(&mut enc);
+        PyBytes::new_bound(py, &enc.out)
+    }
+
+    /// The result as a dict of the attributes `to_msgpack()` writes (a fresh
+    /// dict on each call)
+    fn as_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("normalized", &self.normalized)?;
+        dict.set_item("hash", self.hash(py))?;
+        dict.set_item("shape_hash", self.shape_hash(py))?;
+        dict.set_item("hash_algorithm", self.hasher.name())?;
+        dict.set_item("original", &self.original)?;
+        dict.set_item("params", &self.params)?;
+        dict.set_item("param_types", &self.param_types)?;
+        dict.set_item("param_kinds", &self.param_kinds)?;
+        dict.set_item("param_spans", &self.param_spans)?;
+        dict.set_item("warnings", &self.warnings)?;
+        dict.set_item("passes", &self.passes)?;
+        dict.set_item("dialect_used", self.dialect_used)?;
+        dict.set_item("parse_path", self.parse_path)?;
+        dict.set_item("has_returning", self.has_returning)?;
+        dict.set_item("statement_class", self.statement_class)?;
+        dict.set_item("is_read_only", self.is_read_only)?;
+        dict.set_item("is_ddl", self.is_ddl)?;
+        dict.set_item("explained", self.explained)?;
+        dict.set_item("metrics", self.metrics.as_ref().map(|metrics| metrics.to_dict(py)).transpose()?)?;
+        dict.set_item("metadata", self.metadata(py)?)?;
+        Ok(dict)
+    }
+
+    /// `as_dict()` as a JSON document, indented by `indent` spaces if given
+    #[pyo3(signature = (indent=None))]
+    fn to_json(&self, py: Python<'_>, indent: Option<usize>) -> PyResult<String> {
+        let kwargs = PyDict::new_bound(py);
+        kwargs.set_item("indent", indent)?;
+        // UTF-8 as written, like `write_jsonl()`
+        kwargs.set_item("ensure_ascii", false)?;
+        py.import_bound("json")?.call_method("dumps", (self.as_dict(py)?,), Some(&kwargs))?.extract()
+    }
+
+    /// `_restore_result()` and the state to restore, for `pickle` and `copy`
+    fn __reduce__<'py>(&self, py: Python<'py>) -> PyResult<(Bound<'py, PyAny>, (Bound<'py, PyDict>,))> {
+        let restore = py.import_bound("sqlfp")?.getattr("_restore_result")?;
+        Ok((restore, (self.state(py)?,)))
+    }
+
+    /// Results are equal when their fingerprints are: the same `hash`, by the
+    /// same `hash_algorithm` and `hash_encoding`, whatever their literals
+    fn __eq__(&self, other: PyRef<'_, Self>) -> bool {
+        self.hash == other.hash && self.hasher == other.hasher
+    }
+
+    fn __hash__(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.hash.hash(&mut hasher);
+        hasher.finish()
+    }
+
     fn __repr__(&self) -> String {
         format!(
-            "NormalizeResult(hash='{}', normalized='{}')",
-            &self.hash[..8],
+            "NormalizeResult(hash={}, normalized='{}')",
+            self.hasher.short(&self.hash),
             if self.normalized.len() > 50 {
                 format!("{}...", &self.normalized[..50])
             } else {
@@ -40,335 +535,2992 @@ impl NormalizeResult {
     }
 }
 
-fn get_dialect(dialect: &str) -> Result<Box<dyn sqlparser::dialect::Dialect>, String> {
-    match dialect.to_lowercase().as_str() {
-        "mysql" | "mariadb" => Ok(Box::new(MySqlDialect {})),
-        "postgresql" | "postgres" => Ok(Box::new(PostgreSqlDialect {})),
-        "sqlite" => Ok(Box::new(SQLiteDialect {})),
-        "generic" => Ok(Box::new(GenericDialect {})),
-        "ansi" => Ok(Box::new(AnsiDialect {})),
-        "mssql" => Ok(Box::new(MsSqlDialect {})),
-        "oracle" => Ok(Box::new(OracleDialect {})),
-        _ => Err(format!("Unsupported dialect: {}", dialect)),
-    }
+/// `NormalizeResult.metrics`
+#[pyclass(module = "sqlfp", frozen)]
+#[derive(Clone)]
+struct QueryMetrics {
+    #[pyo3(get)]
+    joins: usize,
+    #[pyo3(get)]
+    subquery_depth: usize,
+    #[pyo3(get)]
+    predicates: usize,
+    #[pyo3(get)]
+    select_star: bool,
+    #[pyo3(get)]
+    aggregates: usize,
+    #[pyo3(get)]
+    window_functions: usize,
+    #[pyo3(get)]
+    set_operations: usize,
 }
 
-/// SQL operator precedence (higher = binds tighter)
-fn op_precedence(op: &BinaryOperator) -> u8 {
-    match op {
-        BinaryOperator::Or => 10,
-        BinaryOperator::Xor => 20,
-        BinaryOperator::And => 30,
-        // NOT (unary) sits at ~35
-        BinaryOperator::Eq | BinaryOperator::NotEq
-        | BinaryOperator::Lt | BinaryOperator::LtEq
-        | BinaryOperator::Gt | BinaryOperator::GtEq
-        | BinaryOperator::Spaceship => 40,
-        BinaryOperator::BitwiseOr => 50,
-        BinaryOperator::BitwiseXor | BinaryOperator::PGBitwiseXor => 55,
-        BinaryOperator::BitwiseAnd => 60,
-        BinaryOperator::PGBitwiseShiftLeft | BinaryOperator::PGBitwiseShiftRight => 65,
-        BinaryOperator::Plus | BinaryOperator::Minus | BinaryOperator::StringConcat => 70,
-        BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo
-        | BinaryOperator::MyIntegerDivide | BinaryOperator::DuckIntegerDivide => 80,
-        _ => 90,
+impl From<metrics::Metrics> for QueryMetrics {
+    fn from(m: metrics::Metrics) -> Self {
+        QueryMetrics {
+            joins: m.joins,
+            subquery_depth: m.subquery_depth,
+            predicates: m.predicates,
+            select_star: m.select_star,
+            aggregates: m.aggregates,
+            window_functions: m.window_functions,
+            set_operations: m.set_operations,
+        }
     }
 }
 
-fn unary_precedence(op: &UnaryOperator) -> u8 {
-    match op {
-        UnaryOperator::Not => 35,
-        _ => 85, // unary +/- and others bind tighter than multiplication
+impl QueryMetrics {
+    /// The metrics with their names, in order
+    fn counts(&self) -> [(&'static str, usize); 6] {
+        [
+            ("joins", self.joins),
+            ("subquery_depth", self.subquery_depth),
+            ("predicates", self.predicates),
+            ("aggregates", self.aggregates),
+            ("window_functions", self.window_functions),
+            ("set_operations", self.set_operations),
+        ]
+    }
+
+    fn write_msgpack(&self, enc: &mut msgpack::Encoder) {
+        enc.map(7);
+        for (key, value) in self.counts() {
+            enc.str(key);
+            enc.uint(value as u64);
+        }
+        enc.str("select_star");
+        enc.bool(self.select_star);
     }
 }
 
-/// Wrap the content of a Box<Expr> in Expr::Nested (i.e. add parentheses)
-fn wrap_in_nested(e: &mut Box<Expr>) {
-    let inner = std::mem::replace(e, Box::new(Expr::Value(Value::Null.into())));
-    *e = Box::new(Expr::Nested(inner));
+impl QueryMetrics {
+    /// The metrics `to_dict()` gave
+    fn from_dict(dict: &Bound<'_, PyDict>) -> PyResult<Self> {
+        Ok(QueryMetrics {
+            joins: state_item(dict, "joins")?,
+            subquery_depth: state_item(dict, "subquery_depth")?,
+            predicates: state_item(dict, "predicates")?,
+            select_star: state_item(dict, "select_star")?,
+            aggregates: state_item(dict, "aggregates")?,
+            window_functions: state_item(dict, "window_functions")?,
+            set_operations: state_item(dict, "set_operations")?,
+        })
+    }
 }
 
-// ---- Structural normalization (aliases, joins, ORDER BY) ----
+#[pymethods]
+impl QueryMetrics {
+    /// The metrics as a dict
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        for (key, value) in self.counts() {
+            dict.set_item(key, value)?;
+        }
+        dict.set_item("select_star", self.select_star)?;
+        Ok(dict)
+    }
 
-/// Normalize equivalent join types to their shorter canonical forms:
-/// INNER JOIN → JOIN, LEFT OUTER JOIN → LEFT JOIN, RIGHT OUTER JOIN → RIGHT JOIN
-fn normalize_join_operator(op: &mut JoinOperator) {
-    let taken = std::mem::replace(op, JoinOperator::CrossJoin(JoinConstraint::None));
-    *op = match taken {
-        JoinOperator::Inner(c) => JoinOperator::Join(c),
-        JoinOperator::LeftOuter(c) => JoinOperator::Left(c),
-        JoinOperator::RightOuter(c) => JoinOperator::Right(c),
-        other => other,
-    };
+    fn __repr__(&self) -> String {
+        let counts: Vec<String> = self.counts().iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+        format!("QueryMetrics({}, select_star={})", counts.join(", "), if self.select_star { "True" } else { "False" })
+    }
 }
 
-/// Set alias to implicit form (no AS keyword) for all TableFactor variants
-fn normalize_table_factor_alias(tf: &mut TableFactor) {
-    let alias = match tf {
-        TableFactor::Table { ref mut alias, .. }
-        | TableFactor::Derived { ref mut alias, .. }
-        | TableFactor::TableFunction { ref mut alias, .. }
-        | TableFactor::Function { ref mut alias, .. }
-        | TableFactor::UNNEST { ref mut alias, .. }
-        | TableFactor::JsonTable { ref mut alias, .. }
-        | TableFactor::OpenJsonTable { ref mut alias, .. }
-        | TableFactor::NestedJoin { ref mut alias, .. }
-        | TableFactor::Pivot { ref mut alias, .. }
-        | TableFactor::Unpivot { ref mut alias, .. }
-        | TableFactor::MatchRecognize { ref mut alias, .. }
-        | TableFactor::XmlTable { ref mut alias, .. }
-        | TableFactor::SemanticView { ref mut alias, .. } => alias,
-    };
-    if let Some(ref mut a) = alias {
-        a.explicit = false;
+/// What `lean=True` keeps of a `NormalizeResult`, for callers that only count
+/// fingerprints
+#[pyclass(module = "sqlfp", frozen)]
+struct LeanResult {
+    hash: String,
+    hasher: hashing::Hasher,
+    /// Kind of the statement, named like `AstNode.kind` (`"Query"`, `"Insert"`, ...)
+    #[pyo3(get)]
+    statement_type: String,
+}
+
+impl From<NormalizeResult> for LeanResult {
+    fn from(result: NormalizeResult) -> Self {
+        LeanResult { hash: result.hash, hasher: result.hasher, statement_type: result.statement_type }
     }
 }
 
-/// Normalize a TableFactor: recurse into subqueries/nested joins and normalize alias
-fn normalize_table_factor(tf: &mut TableFactor) {
-    match tf {
-        TableFactor::Derived { ref mut subquery, .. } => normalize_query_structure(subquery),
-        TableFactor::NestedJoin { ref mut table_with_joins, .. } => {
-            normalize_table_with_joins(table_with_joins);
-        }
-        _ => {}
+#[pymethods]
+impl LeanResult {
+    #[getter]
+    fn hash(&self, py: Python<'_>) -> PyObject {
+        hash_value(py, &self.hasher, &self.hash)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("LeanResult(hash={}, statement_type='{}')", self.hasher.short(&self.hash), self.statement_type)
     }
-    normalize_table_factor_alias(tf);
 }
 
-/// Normalize a TableWithJoins: the relation, all joins, and their table factors
-fn normalize_table_with_joins(twj: &mut sqlparser::ast::TableWithJoins) {
-    normalize_table_factor(&mut twj.relation);
-    for join in &mut twj.joins {
-        normalize_join_operator(&mut join.join_operator);
-        normalize_table_factor(&mut join.relation);
+/// `result` as returned to Python, reduced to a `LeanResult` with `lean`
+fn result_object(py: Python<'_>, result: NormalizeResult, lean: bool) -> PyObject {
+    if lean {
+        LeanResult::from(result).into_py(py)
+    } else {
+        result.into_py(py)
     }
 }
 
-/// Normalize a SetExpr (query body): SELECT, UNION, etc.
-fn normalize_set_expr(body: &mut SetExpr) {
-    match body {
-        SetExpr::Select(ref mut select) => {
-            for twj in &mut select.from {
-                normalize_table_with_joins(twj);
-            }
-        }
-        SetExpr::SetOperation { ref mut left, ref mut right, .. } => {
-            normalize_set_expr(left);
-            normalize_set_expr(right);
-        }
-        SetExpr::Query(ref mut query) => normalize_query_structure(query),
-        _ => {}
+/// Read-only node of the parsed statement tree returned by `parse_ast()`
+#[pyclass(module = "sqlfp", frozen)]
+struct AstNode {
+    #[pyo3(get)]
+    category: &'static str,
+    #[pyo3(get)]
+    kind: String,
+    #[pyo3(get)]
+    span: Option<ast::LineSpan>,
+    #[pyo3(get)]
+    sql: String,
+    children: Py<PyTuple>,
+}
+
+impl AstNode {
+    fn from_node(py: Python<'_>, node: ast::Node) -> PyResult<Py<AstNode>> {
+        let children = node
+            .children
+            .into_iter()
+            .map(|child| AstNode::from_node(py, child))
+            .collect::<PyResult<Vec<_>>>()?;
+        Py::new(
+            py,
+            AstNode {
+                category: node.category,
+                kind: node.kind,
+                span: node.span,
+                sql: node.sql,
+                children: PyTuple::new_bound(py, children).unbind(),
+            },
+        )
     }
 }
 
-/// Normalize a Query: CTEs, body, and ORDER BY
-fn normalize_query_structure(query: &mut Query) {
-    if let Some(ref mut with) = query.with {
-        for cte in &mut with.cte_tables {
-            normalize_query_structure(&mut cte.query);
-        }
+#[pymethods]
+impl AstNode {
+    #[getter]
+    fn children(&self, py: Python<'_>) -> Py<PyTuple> {
+        self.children.clone_ref(py)
     }
-    normalize_set_expr(&mut query.body);
-    if let Some(ref mut order_by) = query.order_by {
-        if let OrderByKind::Expressions(ref mut exprs) = order_by.kind {
-            for expr in exprs {
-                if expr.options.asc == Some(true) {
-                    expr.options.asc = None;
+
+    /// All nodes of the subtree rooted at this node, depth-first, parents first
+    fn walk(slf: &Bound<'_, Self>) -> Vec<Py<AstNode>> {
+        let py = slf.py();
+        let mut out = Vec::new();
+        let mut pending = vec![slf.clone().unbind()];
+        while let Some(node) = pending.pop() {
+            let children = node.get().children.bind(py);
+            for child in children.iter().rev() {
+                if let Ok(child) = child.downcast_into::<AstNode>() {
+                    pending.push(child.unbind());
                 }
             }
+            out.push(node);
         }
+        out
+    }
+
+    fn __repr__(&self) -> String {
+        format!("AstNode(category='{}', kind='{}')", self.category, self.kind)
     }
 }
 
-/// Entry point: normalize structural elements of a Statement
-fn normalize_structure(stmt: &mut Statement) {
-    match stmt {
-        Statement::Query(ref mut query) => normalize_query_structure(query),
-        Statement::Update(ref mut update) => {
-            normalize_table_with_joins(&mut update.table);
-            if let Some(ref mut from) = update.from {
-                let tables = match from {
-                    UpdateTableFromKind::BeforeSet(t) | UpdateTableFromKind::AfterSet(t) => t,
-                };
-                for twj in tables {
-                    normalize_table_with_joins(twj);
+/// Decode `bytes` natively for the common encodings and error handlers.
+/// Returns `None` when Python's codec machinery is needed instead (other
+/// encodings or handlers, or invalid input under `errors="strict"`, so that
+/// the caller raises the same `UnicodeDecodeError` Python would).
+fn decode_native<'b>(bytes: &'b [u8], encoding: &str, errors: &str) -> Option<Cow<'b, str>> {
+    match encoding.to_ascii_lowercase().replace('_', "-").as_str() {
+        "utf-8" | "utf8" => match errors {
+            "strict" => std::str::from_utf8(bytes).ok().map(Cow::Borrowed),
+            "replace" => Some(String::from_utf8_lossy(bytes)),
+            "ignore" => {
+                let mut out = String::with_capacity(bytes.len());
+                for chunk in bytes.utf8_chunks() {
+                    out.push_str(chunk.valid());
                 }
+                Some(Cow::Owned(out))
             }
+            _ => None,
+        },
+        // Every byte is a valid latin-1 character, so the handler never matters
+        "latin-1" | "latin1" | "iso-8859-1" | "iso8859-1" => {
+            Some(Cow::Owned(bytes.iter().map(|&b| b as char).collect()))
         }
-        Statement::Delete(ref mut delete) => {
-            let tables = match &mut delete.from {
-                FromTable::WithFromKeyword(t) | FromTable::WithoutKeyword(t) => t,
-            };
-            for twj in tables {
-                normalize_table_with_joins(twj);
-            }
-        }
-        _ => {}
+        _ => None,
+    }
+}
+
+/// The SQL text of a `str` or `bytes` argument
+fn sql_text(sql: &Bound<'_, PyAny>, encoding: &str, errors: &str) -> PyResult<String> {
+    if let Ok(s) = sql.downcast::<PyString>() {
+        return s.extract();
+    }
+    let bytes = sql.downcast::<PyBytes>()?;
+    match decode_native(bytes.as_bytes(), encoding, errors) {
+        Some(text) => Ok(text.into_owned()),
+        None => bytes.call_method1("decode", (encoding, errors))?.extract(),
     }
 }
 
-// ---- Expression normalization ----
+/// `bytes` decoded natively where possible, else through `bytes.decode`
+fn decode_bytes(py: Python<'_>, bytes: &[u8], encoding: &str, errors: &str) -> PyResult<String> {
+    match decode_native(bytes, encoding, errors) {
+        Some(text) => Ok(text.into_owned()),
+        None => PyBytes::new_bound(py, bytes).call_method1("decode", (encoding, errors))?.extract(),
+    }
+}
 
-fn normalize_ast(stmt: &mut Statement) {
-    // Phase 1: Structural normalization (aliases, joins, ORDER BY)
-    normalize_structure(stmt);
+/// Parse `sql` and return its first statement
+fn parse_first_statement(sql: &str, dialect: &dyn Dialect) -> PyResult<Statement> {
+    sqlfp_core::parse_first_statement(sql, dialect).map_err(parse_error)
+}
 
-    // Phase 2: Expression normalization
-    // The visitor is post-order: children are processed before parents.
-    // This lets us (1) strip ALL Nested, then (2) re-add only where
-    // needed for operator precedence — producing a canonical form.
-    visit_expressions_mut(stmt, |expr| {
-        // Step 1: Strip ALL Nested (parentheses)
-        if matches!(expr, Expr::Nested(_)) {
-            let taken = std::mem::replace(expr, Expr::Value(Value::Null.into()));
-            if let Expr::Nested(inner) = taken {
-                *expr = *inner;
-            }
-        }
+#[pyfunction]
+#[pyo3(signature = (sql, dialect=None, placeholder=None, *, redactor=None, lean=false, options=None, **kwargs))]
+fn normalize(
+    sql: &Bound<'_, PyAny>,
+    dialect: Option<&str>,
+    placeholder: Option<&str>,
+    redactor: Option<&Bound<'_, PyAny>>,
+    lean: bool,
+    options: Option<PyRef<'_, NormalizeOptions>>,
+    kwargs: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let args = option_args("normalize", kwargs, &[])?.positional(dialect, placeholder);
+    let defaults = option_defaults(options.as_deref(), args.passes.is_some())?;
+    let (encoding, errors) = encoding_args(&defaults, args.config.encoding.as_deref(), args.config.errors.as_deref());
+    let text = sql_text(sql, encoding, errors)?;
+    let engine = engine(&defaults, &args.config, args.passes)?;
+    Ok(result_object(sql.py(), normalize_text(text, &engine, redactor, &args.hooks)?, lean))
+}
 
-        // Step 2: Re-add Nested where removing parens would change semantics
-        match expr {
-            Expr::BinaryOp { ref mut left, ref op, ref mut right } => {
-                let prec = op_precedence(op);
-                // Left child: wrap if strictly lower precedence
-                if let Expr::BinaryOp { op: ref child_op, .. } = left.as_ref() {
-                    if op_precedence(child_op) < prec {
-                        wrap_in_nested(left);
-                    }
+/// Statements processed between checks for pending signals in batch functions
+const BATCH_CHUNK: usize = 1024;
+
+/// `normalize()` over a batch of statements, with one engine for the whole batch
+#[pyfunction]
+#[pyo3(signature = (
+    queries,
+    dialect=None,
+    placeholder=None,
+    *,
+    redactor=None,
+    on_error="raise",
+    progress=None,
+    progress_every=1000,
+    lean=false,
+    **kwargs,
+))]
+#[allow(clippy::too_many_arguments)]
+fn normalize_many(
+    queries: &Bound<'_, PyAny>,
+    dialect: Option<&str>,
+    placeholder: Option<&str>,
+    redactor: Option<&Bound<'_, PyAny>>,
+    on_error: &str,
+    progress: Option<&Bound<'_, PyAny>>,
+    progress_every: usize,
+    lean: bool,
+    kwargs: Option<&Bound<'_, PyDict>>,
+) -> PyResult<Vec<Option<PyObject>>> {
+    let args = option_args("normalize_many", kwargs, &[])?.positional(dialect, placeholder);
+    let defaults = config::defaults();
+    let (encoding, errors) = encoding_args(&defaults, args.config.encoding.as_deref(), args.config.errors.as_deref());
+    let engine_for = |dialect: Option<&str>| {
+        let item = config::Config { dialect: dialect.map(str::to_string), ..args.config.clone() };
+        engine(&defaults, &item, args.passes.clone())
+    };
+    let batch_engine = engine_for(dialect)?;
+    // Engines for the dialects of `(sql, dialect)` items, by dialect
+    let mut engines = HashMap::new();
+    let mut item_engine = |dialect: Option<String>| -> PyResult<std::sync::Arc<pool::Engine>> {
+        match dialect {
+            None => Ok(std::sync::Arc::clone(&batch_engine)),
+            Some(dialect) => match engines.get(&dialect) {
+                Some(engine) => Ok(std::sync::Arc::clone(engine)),
+                None => {
+                    let engine = engine_for(Some(&dialect))?;
+                    Ok(std::sync::Arc::clone(engines.entry(dialect).or_insert(engine)))
                 }
-                // Right child: wrap if lower-or-equal (SQL is left-associative)
-                if let Expr::BinaryOp { op: ref child_op, .. } = right.as_ref() {
-                    if op_precedence(child_op) <= prec {
-                        wrap_in_nested(right);
+            },
+        }
+    };
+    let text_args = (encoding, errors);
+    normalize_batch(queries, &mut item_engine, redactor, &args.hooks, text_args, on_error, progress, progress_every, lean)
+}
+
+/// The batch loop of `normalize_many()` and `Normalizer.normalize_many()`,
+/// with `item_engine` giving the engine of each item by its dialect, `None`
+/// for the batch's
+#[allow(clippy::too_many_arguments)]
+fn normalize_batch(
+    queries: &Bound<'_, PyAny>,
+    item_engine: &mut dyn FnMut(Option<String>) -> PyResult<std::sync::Arc<pool::Engine>>,
+    redactor: Option<&Bound<'_, PyAny>>,
+    hooks: &[PyObject],
+    (encoding, errors): (&str, &str),
+    on_error: &str,
+    progress: Option<&Bound<'_, PyAny>>,
+    progress_every: usize,
+    lean: bool,
+) -> PyResult<Vec<Option<PyObject>>> {
+    let keep_errors = match on_error {
+        "raise" => false,
+        "none" => true,
+        _ => return Err(PyValueError::new_err(format!("Unsupported on_error: {}", on_error))),
+    };
+    if progress_every == 0 {
+        return Err(PyValueError::new_err("progress_every must be positive"));
+    }
+
+    let py = queries.py();
+    // Without Python callbacks to call, chunks are normalized in parallel
+    // with the GIL released
+    let parallel = redactor.is_none() && hooks.is_empty();
+    let mut results = Vec::new();
+    let mut failed = 0;
+    let mut run = || -> PyResult<()> {
+        let mut queries = queries.iter()?;
+        loop {
+            py.check_signals()?;
+            // Each chunk is read with the GIL held; an error of the iterator
+            // itself ends the batch after the items read before it
+            let (mut chunk, mut stopped) = (Vec::with_capacity(BATCH_CHUNK), None);
+            for query in queries.by_ref().take(BATCH_CHUNK) {
+                let query = match query {
+                    Ok(query) => query,
+                    Err(e) => {
+                        stopped = Some(e);
+                        break;
                     }
-                }
+                };
+                chunk.push(batch_item(&query).and_then(|(sql, dialect)| Ok((sql_text(&sql, encoding, errors)?, item_engine(dialect)?))));
             }
-            Expr::UnaryOp { op, expr: ref mut inner } => {
-                let prec = unary_precedence(op);
-                if let Expr::BinaryOp { op: ref child_op, .. } = inner.as_ref() {
-                    if op_precedence(child_op) < prec {
-                        wrap_in_nested(inner);
-                    }
-                }
+            if chunk.is_empty() && stopped.is_none() {
+                break;
             }
-            _ => {}
-        }
-
-        // Normalize function names to uppercase (COUNT = count = CoUnT)
-        // and strip ASC in window ORDER BY clauses
-        if let Expr::Function(ref mut func) = expr {
-            for part in &mut func.name.0 {
-                if let ObjectNamePart::Identifier(ref mut ident) = part {
-                    if ident.quote_style.is_none() {
-                        ident.value = ident.value.to_uppercase();
+            let normalized: Vec<PyResult<NormalizeResult>> = if parallel {
+                py.allow_threads(|| {
+                    chunk.into_par_iter().map(|item| item.and_then(|(sql, engine)| normalize_text(sql, &engine, None, hooks))).collect()
+                })
+            } else {
+                chunk.into_iter().map(|item| item.and_then(|(sql, engine)| normalize_text(sql, &engine, redactor, hooks))).collect()
+            };
+            for result in normalized {
+                match result {
+                    Ok(result) => results.push(Some(result_object(py, result, lean))),
+                    Err(e) if e.is_instance_of::<PyKeyboardInterrupt>(py) => return Err(e),
+                    Err(_) if keep_errors => {
+                        failed += 1;
+                        results.push(None);
                     }
+                    Err(e) => return Err(e),
                 }
-            }
-            if let Some(WindowType::WindowSpec(ref mut spec)) = func.over {
-                for ob in &mut spec.order_by {
-                    if ob.options.asc == Some(true) {
-                        ob.options.asc = None;
+                if let Some(callback) = progress {
+                    if results.len() % progress_every == 0 {
+                        callback.call1((results.len(), failed))?;
                     }
                 }
             }
+            if let Some(e) = stopped {
+                return Err(e);
+            }
         }
-
-        // Normalize boolean identifiers (TRUE/FALSE/True/true → uppercase)
-        // In dialects like MSSQL/Oracle, TRUE/FALSE are parsed as identifiers
-        if let Expr::Identifier(ref mut ident) = expr {
-            if ident.quote_style.is_none() {
-                let upper = ident.value.to_uppercase();
-                if upper == "TRUE" || upper == "FALSE" {
-                    ident.value = upper;
-                }
+        if let Some(callback) = progress {
+            // Always report the final count, unless it was just reported
+            if results.is_empty() || results.len() % progress_every != 0 {
+                callback.call1((results.len(), failed))?;
             }
         }
+        Ok(())
+    };
+    match run() {
+        // Ctrl-C ends the batch early; what was processed so far is kept
+        Err(e) if e.is_instance_of::<PyKeyboardInterrupt>(py) => Ok(results),
+        Err(e) => Err(e),
+        Ok(()) => Ok(results),
+    }
+}
 
-        // Normalize structure inside subqueries embedded in expressions
-        match expr {
-            Expr::Subquery(ref mut query) => normalize_query_structure(query),
-            Expr::Exists { ref mut subquery, .. } => normalize_query_structure(subquery),
-            Expr::InSubquery { ref mut subquery, .. } => normalize_query_structure(subquery),
-            _ => {}
-        }
+/// The settings of `normalize()` that make up a fingerprint, resolved
+/// against the `configure()` defaults and validated at construction, as
+/// recorded in `NormalizeResult.options`
+#[pyclass(module = "sqlfp", frozen)]
+struct NormalizeOptions {
+    engine: std::sync::Arc<pool::Engine>,
+}
+
+impl NormalizeOptions {
+    /// `configure()` defaults setting all of the options, the pass order
+    /// aside when `passes` replaces it
+    fn config(&self, passes: bool) -> PyResult<config::Config> {
+        let key = &self.engine.key;
+        let order = match key.passes {
+            Some(ref order) if !passes => Some(
+                order
+                    .iter()
+                    .cloned()
+                    .collect::<Option<Vec<String>>>()
+                    .ok_or_else(|| PyValueError::new_err("options recorded with caller hooks need them in passes"))?,
+            ),
+            _ => None,
+        };
+        Ok(config::Config {
+            dialect: Some(key.dialect.clone()),
+            placeholder: Some(key.placeholder.clone()),
+            placeholder_start: Some(key.placeholder_start),
+            style: None,
+            compat: key.compat.clone(),
+            templates: Some(key.templates),
+            strict: Some(key.strict),
+            encoding: None,
+            errors: None,
+            passes: order,
+            disable_passes: Some(key.disabled_passes.clone()),
+            enable_passes: Some(key.enabled_passes.clone()),
+            function_case: Some(key.function_case.clone()),
+            identifier_case: Some(key.identifier_case.clone()),
+            clause_hashes: Some(key.clause_hashes),
+            max_subquery_depth: key.max_subquery_depth,
+            routing_columns: Some(key.routing_columns.clone()),
+            placeholder_collision: Some(key.placeholder_collision.clone()),
+            ignore_clauses: Some(key.ignore_clauses.clone()),
+            table_columns: Some(key.table_columns.clone()),
+            function_synonyms: Some(key.function_synonyms.clone()),
+            collect_params: Some(key.collect_params),
+            max_params: key.max_params,
+            truncated_input: Some(key.truncated_input),
+            lenient: Some(key.lenient),
+            collapse_in_lists: Some(key.collapse_in_lists),
+            collapse_values: Some(key.collapse_values),
+            keep_limit: Some(key.keep_limit),
+            keep_offset: Some(key.keep_offset),
+            keep_null: Some(key.keep_null),
+            strip_qualifiers: Some(key.strip_qualifiers.clone()),
+            ignore_aliases: Some(key.ignore_aliases),
+            anonymize: Some(key.anonymize),
+            comments: Some(key.comments),
+            keep_hints: Some(key.keep_hints),
+            unwrap_explain: Some(key.unwrap_explain),
+            prepared: Some(key.prepared),
+            hash_algorithm: Some(key.hash_algorithm.clone()),
+            hash_encoding: Some(key.hash_encoding.clone()),
+        })
+    }
+}
 
-        ControlFlow::<()>::Continue(())
-    });
+/// The defaults the arguments of a normalizing call fall back to: those of
+/// `options` if given, `encoding` and `errors` aside, else the `configure()` ones
+fn option_defaults(options: Option<&NormalizeOptions>, passes: bool) -> PyResult<std::sync::Arc<config::Config>> {
+    let defaults = config::defaults();
+    let Some(options) = options else {
+        return Ok(defaults);
+    };
+    Ok(std::sync::Arc::new(config::Config {
+        encoding: defaults.encoding.clone(),
+        errors: defaults.errors.clone(),
+        ..options.config(passes)?
+    }))
 }
 
-fn normalize_statement(stmt: &mut Statement, placeholder: &str) -> (String, Vec<String>) {
-    let mut params = Vec::new();
+#[pymethods]
+impl NormalizeOptions {
+    #[new]
+    #[pyo3(signature = (**kwargs))]
+    fn new(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        let args = option_args("NormalizeOptions", kwargs, &["encoding", "errors"])?;
+        // Options are recorded on results, which cannot call the hooks back
+        args.pass_names()?;
+        let engine = engine(&config::defaults(), &args.config, args.passes)?;
+        Ok(NormalizeOptions { engine })
+    }
+
+    /// Canonical name of the dialect
+    #[getter]
+    fn dialect(&self) -> &'static str {
+        self.engine.dialect_name
+    }
 
-    normalize_ast(stmt);
+    /// The placeholder, with `style` resolved to its text
+    #[getter]
+    fn placeholder(&self) -> &str {
+        &self.engine.key.placeholder
+    }
 
-    visit_expressions_mut(stmt, |expr| {
-        match expr {
-            Expr::Value(ref mut val) => {
-                if !matches!(val.value, Value::Null | Value::Placeholder(_)) {
-                    params.push(val.to_string());
-                    *val = Value::Placeholder(placeholder.to_string()).into();
-                }
-            }
-            // In MSSQL/Oracle, TRUE/FALSE are identifiers, not boolean values.
-            // Parameterize them just like Value::Boolean in other dialects.
-            Expr::Identifier(ref ident)
-                if ident.quote_style.is_none()
-                    && matches!(ident.value.to_uppercase().as_str(), "TRUE" | "FALSE") =>
-            {
-                params.push(ident.value.to_uppercase());
-                *expr = Expr::Value(Value::Placeholder(placeholder.to_string()).into());
-            }
-            // Double-quoted strings are parsed as identifiers by GenericDialect,
-            // but they are actually string values in many dialects (e.g. MySQL).
-            Expr::Identifier(ref ident) if ident.quote_style == Some('"') => {
-                params.push(format!("\"{}\"", ident.value));
-                *expr = Expr::Value(Value::Placeholder(placeholder.to_string()).into());
-            }
-            _ => {}
-        }
-        ControlFlow::<()>::Continue(())
-    });
+    #[getter]
+    fn placeholder_start(&self) -> usize {
+        self.engine.key.placeholder_start
+    }
 
-    (stmt.to_string(), params)
-}
+    #[getter]
+    fn compat(&self) -> Option<&str> {
+        self.engine.key.compat.as_deref()
+    }
 
-fn compute_hash(normalized: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(normalized.as_bytes());
-    hex::encode(hasher.finalize())
-}
+    #[getter]
+    fn templates(&self) -> bool {
+        self.engine.key.templates
+    }
 
-#[pyfunction]
-#[pyo3(signature = (sql, dialect="generic", placeholder="?"))]
-fn normalize(sql: &str, dialect: &str, placeholder: &str) -> PyResult<NormalizeResult> {
-    let dialect_impl = get_dialect(dialect)
-        .map_err(|e| PyValueError::new_err(e))?;
+    #[getter]
+    fn strict(&self) -> bool {
+        self.engine.key.strict
+    }
+
+    /// The pass order, `None` standing for each caller hook of the
+    /// `normalize()` call these options were recorded from
+    #[getter]
+    fn passes(&self) -> Option<PassOrder> {
+        self.engine.key.passes.clone()
+    }
 
-    let mut statements = Parser::parse_sql(&*dialect_impl, sql)
-        .map_err(|e| PyValueError::new_err(format!("Parse error: {}", e)))?;
+    /// Sorted and deduplicated
+    #[getter]
+    fn disable_passes(&self) -> Vec<String> {
+        self.engine.key.disabled_passes.clone()
+    }
 
-    if statements.is_empty() {
-        return Err(PyValueError::new_err("No SQL statement found"));
+    /// Sorted and deduplicated
+    #[getter]
+    fn enable_passes(&self) -> Vec<String> {
+        self.engine.key.enabled_passes.clone()
     }
 
-    let stmt = &mut statements[0];
-    let (normalized, params) = normalize_statement(stmt, placeholder);
-    let hash = compute_hash(&normalized);
+    #[getter]
+    fn function_case(&self) -> &str {
+        &self.engine.key.function_case
+    }
 
-    Ok(NormalizeResult {
-        normalized,
-        hash,
-        original: sql.to_string(),
-        params,
-    })
+    #[getter]
+    fn identifier_case(&self) -> &str {
+        &self.engine.key.identifier_case
+    }
+
+    #[getter]
+    fn clause_hashes(&self) -> bool {
+        self.engine.key.clause_hashes
+    }
+
+    #[getter]
+    fn max_subquery_depth(&self) -> Option<usize> {
+        self.engine.key.max_subquery_depth
+    }
+
+    #[getter]
+    fn routing_columns(&self) -> Vec<String> {
+        self.engine.key.routing_columns.clone()
+    }
+
+    #[getter]
+    fn placeholder_collision(&self) -> &str {
+        &self.engine.key.placeholder_collision
+    }
+
+    /// Sorted and deduplicated
+    #[getter]
+    fn ignore_clauses(&self) -> Vec<String> {
+        self.engine.key.ignore_clauses.clone()
+    }
+
+    /// Keyed by lowercased table name
+    #[getter]
+    fn table_columns(&self) -> BTreeMap<String, Vec<String>> {
+        self.engine.key.table_columns.clone()
+    }
+
+    /// Keyed by lowercased alias
+    #[getter]
+    fn function_synonyms(&self) -> BTreeMap<String, String> {
+        self.engine.key.function_synonyms.clone()
+    }
+
+    #[getter]
+    fn collect_params(&self) -> bool {
+        self.engine.key.collect_params
+    }
+
+    #[getter]
+    fn max_params(&self) -> Option<usize> {
+        self.engine.key.max_params
+    }
+
+    #[getter]
+    fn truncated_input(&self) -> bool {
+        self.engine.key.truncated_input
+    }
+
+    #[getter]
+    fn lenient(&self) -> bool {
+        self.engine.key.lenient
+    }
+
+    #[getter]
+    fn collapse_in_lists(&self) -> bool {
+        self.engine.key.collapse_in_lists
+    }
+
+    #[getter]
+    fn collapse_values(&self) -> bool {
+        self.engine.key.collapse_values
+    }
+
+    #[getter]
+    fn keep_limit(&self) -> bool {
+        self.engine.key.keep_limit
+    }
+
+    #[getter]
+    fn keep_offset(&self) -> bool {
+        self.engine.key.keep_offset
+    }
+
+    #[getter]
+    fn keep_null(&self) -> bool {
+        self.engine.key.keep_null
+    }
+
+    /// `True` or `False`, or the schemas stripped, lowercased, sorted and
+    /// deduplicated
+    #[getter]
+    fn strip_qualifiers(&self, py: Python<'_>) -> PyObject {
+        strip_object(py, &self.engine.key.strip_qualifiers)
+    }
+
+    #[getter]
+    fn ignore_aliases(&self) -> bool {
+        self.engine.key.ignore_aliases
+    }
+
+    #[getter]
+    fn anonymize(&self) -> bool {
+        self.engine.key.anonymize
+    }
+
+    #[getter]
+    fn comments(&self) -> bool {
+        self.engine.key.comments
+    }
+
+    #[getter]
+    fn keep_hints(&self) -> bool {
+        self.engine.key.keep_hints
+    }
+
+    #[getter]
+    fn unwrap_explain(&self) -> bool {
+        self.engine.key.unwrap_explain
+    }
+
+    #[getter]
+    fn prepared(&self) -> bool {
+        self.engine.key.prepared
+    }
+
+    #[getter]
+    fn hash_algorithm(&self) -> &str {
+        &self.engine.key.hash_algorithm
+    }
+
+    #[getter]
+    fn hash_encoding(&self) -> &str {
+        &self.engine.key.hash_encoding
+    }
+
+    /// The options keyed by their argument names, for recording them next
+    /// to fingerprints (a fresh dict on each call)
+    fn to_dict<'py>(slf: &Bound<'py, Self>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(slf.py());
+        for name in OPTION_NAMES {
+            dict.set_item(name, slf.getattr(name)?)?;
+        }
+        Ok(dict)
+    }
+
+    fn __eq__(&self, other: PyRef<'_, Self>) -> bool {
+        self.engine.key == other.engine.key
+    }
+
+    fn __hash__(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.engine.key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn __repr__(slf: &Bound<'_, Self>) -> PyResult<String> {
+        let fields = OPTION_NAMES
+            .iter()
+            .map(|name| Ok(format!("{}={}", name, slf.getattr(*name)?.repr()?)))
+            .collect::<PyResult<Vec<String>>>()?;
+        Ok(format!("NormalizeOptions({})", fields.join(", ")))
+    }
+}
+
+/// The attributes of `NormalizeOptions`, in the order of the arguments
+const OPTION_NAMES: [&str; 36] = [
+    "dialect",
+    "placeholder",
+    "compat",
+    "templates",
+    "strict",
+    "passes",
+    "disable_passes",
+    "placeholder_start",
+    "enable_passes",
+    "function_case",
+    "identifier_case",
+    "clause_hashes",
+    "max_subquery_depth",
+    "routing_columns",
+    "placeholder_collision",
+    "ignore_clauses",
+    "table_columns",
+    "function_synonyms",
+    "collect_params",
+    "max_params",
+    "truncated_input",
+    "lenient",
+    "collapse_in_lists",
+    "collapse_values",
+    "keep_limit",
+    "keep_offset",
+    "keep_null",
+    "strip_qualifiers",
+    "ignore_aliases",
+    "anonymize",
+    "comments",
+    "keep_hints",
+    "unwrap_explain",
+    "prepared",
+    "hash_algorithm",
+    "hash_encoding",
+];
+
+/// `normalize()` with its options bound once: the `configure()` defaults,
+/// the engine and the caller hooks are resolved at construction and kept
+/// for every call, which then only reads and normalizes its statement
+#[pyclass(module = "sqlfp")]
+struct Normalizer {
+    engine: std::sync::Arc<pool::Engine>,
+    redactor: Option<PyObject>,
+    /// The caller hooks of `passes`
+    hooks: Vec<PyObject>,
+    encoding: String,
+    errors: String,
+    lean: bool,
+}
+
+#[pymethods]
+impl Normalizer {
+    #[new]
+    #[pyo3(signature = (*, redactor=None, lean=false, options=None, **kwargs))]
+    fn new(
+        redactor: Option<PyObject>,
+        lean: bool,
+        options: Option<PyRef<'_, NormalizeOptions>>,
+        kwargs: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<Self> {
+        let args = option_args("Normalizer", kwargs, &[])?;
+        let defaults = option_defaults(options.as_deref(), args.passes.is_some())?;
+        let config = &args.config;
+        let (encoding, errors) = encoding_args(&defaults, config.encoding.as_deref(), config.errors.as_deref());
+        let (encoding, errors) = (encoding.to_string(), errors.to_string());
+        let engine = engine(&defaults, &args.config, args.passes)?;
+        Ok(Normalizer { engine, redactor, hooks: args.hooks, encoding, errors, lean })
+    }
+
+    /// `normalize(sql)` with the options of the normalizer
+    fn normalize(&self, sql: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+        let py = sql.py();
+        let text = sql_text(sql, &self.encoding, &self.errors)?;
+        let redactor = self.redactor.as_ref().map(|redactor| redactor.bind(py));
+        Ok(result_object(py, normalize_text(text, &self.engine, redactor, &self.hooks)?, self.lean))
+    }
+
+    /// `normalize_many(queries)` with the options of the normalizer; the
+    /// dialect of `(sql, dialect)` items replaces the normalizer's
+    #[pyo3(signature = (queries, *, on_error="raise", progress=None, progress_every=1000))]
+    fn normalize_many(
+        &self,
+        queries: &Bound<'_, PyAny>,
+        on_error: &str,
+        progress: Option<&Bound<'_, PyAny>>,
+        progress_every: usize,
+    ) -> PyResult<Vec<Option<PyObject>>> {
+        let py = queries.py();
+        let mut engines: HashMap<String, std::sync::Arc<pool::Engine>> = HashMap::new();
+        let mut item_engine = |dialect: Option<String>| -> PyResult<std::sync::Arc<pool::Engine>> {
+            let Some(dialect) = dialect else {
+                return Ok(std::sync::Arc::clone(&self.engine));
+            };
+            if let Some(engine) = engines.get(&dialect) {
+                return Ok(std::sync::Arc::clone(engine));
+            }
+            let key = pool::EngineKey { dialect: dialect.clone(), ..(*self.engine.key).clone() };
+            let engine = pool::engine(key).map_err(PyValueError::new_err)?;
+            Ok(std::sync::Arc::clone(engines.entry(dialect).or_insert(engine)))
+        };
+        let redactor = self.redactor.as_ref().map(|redactor| redactor.bind(py));
+        let text_args = (self.encoding.as_str(), self.errors.as_str());
+        normalize_batch(queries, &mut item_engine, redactor, &self.hooks, text_args, on_error, progress, progress_every, self.lean)
+    }
+
+    /// The settings of the normalizer
+    #[getter]
+    fn options(&self) -> NormalizeOptions {
+        NormalizeOptions { engine: std::sync::Arc::clone(&self.engine) }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Normalizer(dialect='{}')", self.engine.dialect_name)
+    }
+}
+
+/// The statement of a `normalize_many()` item, and its dialect if the item is
+/// a `(sql, dialect)` pair
+fn batch_item<'py>(item: &Bound<'py, PyAny>) -> PyResult<(Bound<'py, PyAny>, Option<String>)> {
+    let Ok(pair) = item.downcast::<PyTuple>() else {
+        return Ok((item.clone(), None));
+    };
+    if pair.len() != 2 {
+        return Err(PyTypeError::new_err("normalize_many() items must be str, bytes or (sql, dialect) pairs"));
+    }
+    Ok((pair.get_item(0)?, pair.get_item(1)?.extract()?))
+}
+
+/// `encoding` and `errors`, falling back to the `configure()` defaults
+fn encoding_args<'a>(
+    defaults: &'a config::Config,
+    encoding: Option<&'a str>,
+    errors: Option<&'a str>,
+) -> (&'a str, &'a str) {
+    (
+        encoding.or(defaults.encoding.as_deref()).unwrap_or("utf-8"),
+        errors.or(defaults.errors.as_deref()).unwrap_or("strict"),
+    )
+}
+
+/// Split a `passes` argument into the pass order, with `None` in place of
+/// each caller hook, and the hooks themselves
+fn pass_order(passes: Option<Vec<Bound<'_, PyAny>>>) -> PyResult<(Option<PassOrder>, Vec<PyObject>)> {
+    let Some(passes) = passes else {
+        return Ok((None, Vec::new()));
+    };
+    let mut order = Vec::with_capacity(passes.len());
+    let mut hooks = Vec::new();
+    for entry in passes {
+        if entry.is_instance_of::<PyString>() {
+            order.push(Some(entry.extract()?));
+        } else if entry.is_callable() {
+            order.push(None);
+            hooks.push(entry.unbind());
+        } else {
+            return Err(PyTypeError::new_err("passes entries must be pass names or callables"));
+        }
+    }
+    Ok((Some(order), hooks))
+}
+
+/// The `strip_qualifiers` argument: `True` or `False`, or the schemas to strip
+#[derive(Clone, FromPyObject)]
+enum StripQualifiers {
+    All(bool),
+    Schemas(Vec<String>),
+}
+
+impl From<StripQualifiers> for qualifiers::Strip {
+    fn from(strip: StripQualifiers) -> Self {
+        match strip {
+            StripQualifiers::All(all) => qualifiers::Strip::All(all),
+            StripQualifiers::Schemas(schemas) => qualifiers::Strip::Schemas(schemas),
+        }
+    }
+}
+
+/// `strip_qualifiers` as it was given
+fn strip_object(py: Python<'_>, strip: &qualifiers::Strip) -> PyObject {
+    match strip {
+        qualifiers::Strip::All(all) => all.to_object(py),
+        qualifiers::Strip::Schemas(schemas) => schemas.to_object(py),
+    }
+}
+
+/// The options of a normalizing call, given as keyword arguments: the
+/// `configure()` settings they set, and `passes` split by `pass_order()`
+struct OptionArgs {
+    config: config::Config,
+    passes: Option<PassOrder>,
+    hooks: Vec<PyObject>,
+}
+
+impl OptionArgs {
+    /// With `dialect` and `placeholder`, of the functions that take them as
+    /// positional arguments
+    fn positional(mut self, dialect: Option<&str>, placeholder: Option<&str>) -> Self {
+        self.config.dialect = dialect.map(str::to_string);
+        self.config.placeholder = placeholder.map(str::to_string);
+        self
+    }
+
+    /// The pass names of `passes`, for the functions that take no hooks
+    fn pass_names(&self) -> PyResult<Option<Vec<String>>> {
+        if !self.hooks.is_empty() {
+            return Err(PyTypeError::new_err("passes entries must be pass names"));
+        }
+        Ok(self.passes.as_ref().map(|order| order.iter().flatten().cloned().collect()))
+    }
+}
+
+/// The `**kwargs` of `function()`: the options of `NormalizeOptions`, and
+/// `encoding` and `errors`, but those in `rejected`; those given as `None`
+/// are left unset
+fn option_args(function: &str, kwargs: Option<&Bound<'_, PyDict>>, rejected: &[&str]) -> PyResult<OptionArgs> {
+    let mut config = config::Config::default();
+    let mut passes = None;
+    for (name, value) in kwargs.into_iter().flatten() {
+        let name = name.downcast::<PyString>()?.to_cow()?;
+        let unexpected =
+            || PyTypeError::new_err(format!("{}() got an unexpected keyword argument '{}'", function, name));
+        if rejected.contains(&&*name) {
+            return Err(unexpected());
+        }
+        // As pyo3 reports the arguments it extracts, naming them in type errors
+        let invalid = |e: PyErr| {
+            if e.is_instance_of::<PyTypeError>(value.py()) {
+                PyTypeError::new_err(format!("argument '{}': {}", name, e.value_bound(value.py())))
+            } else {
+                e
+            }
+        };
+        match &*name {
+            "dialect" => config.dialect = value.extract().map_err(invalid)?,
+            "placeholder" => config.placeholder = value.extract().map_err(invalid)?,
+            "placeholder_start" => config.placeholder_start = value.extract().map_err(invalid)?,
+            "style" => config.style = value.extract().map_err(invalid)?,
+            "compat" => config.compat = value.extract().map_err(invalid)?,
+            "templates" => config.templates = value.extract().map_err(invalid)?,
+            "strict" => config.strict = value.extract().map_err(invalid)?,
+            "encoding" => config.encoding = value.extract().map_err(invalid)?,
+            "errors" => config.errors = value.extract().map_err(invalid)?,
+            "passes" => passes = value.extract().map_err(invalid)?,
+            "disable_passes" => config.disable_passes = value.extract().map_err(invalid)?,
+            "enable_passes" => config.enable_passes = value.extract().map_err(invalid)?,
+            "function_case" => config.function_case = value.extract().map_err(invalid)?,
+            "identifier_case" => config.identifier_case = value.extract().map_err(invalid)?,
+            "clause_hashes" => config.clause_hashes = value.extract().map_err(invalid)?,
+            "max_subquery_depth" => config.max_subquery_depth = value.extract().map_err(invalid)?,
+            "routing_columns" => config.routing_columns = value.extract().map_err(invalid)?,
+            "placeholder_collision" => config.placeholder_collision = value.extract().map_err(invalid)?,
+            "ignore_clauses" => config.ignore_clauses = value.extract().map_err(invalid)?,
+            "table_columns" => config.table_columns = value.extract().map_err(invalid)?,
+            "function_synonyms" => config.function_synonyms = value.extract().map_err(invalid)?,
+            "collect_params" => config.collect_params = value.extract().map_err(invalid)?,
+            "max_params" => config.max_params = value.extract().map_err(invalid)?,
+            "truncated_input" => config.truncated_input = value.extract().map_err(invalid)?,
+            "lenient" => config.lenient = value.extract().map_err(invalid)?,
+            "collapse_in_lists" => config.collapse_in_lists = value.extract().map_err(invalid)?,
+            "collapse_values" => config.collapse_values = value.extract().map_err(invalid)?,
+            "keep_limit" => config.keep_limit = value.extract().map_err(invalid)?,
+            "keep_offset" => config.keep_offset = value.extract().map_err(invalid)?,
+            "keep_null" => config.keep_null = value.extract().map_err(invalid)?,
+            "strip_qualifiers" => {
+                config.strip_qualifiers = value.extract::<Option<StripQualifiers>>().map_err(invalid)?.map(Into::into)
+            }
+            "ignore_aliases" => config.ignore_aliases = value.extract().map_err(invalid)?,
+            "anonymize" => config.anonymize = value.extract().map_err(invalid)?,
+            "comments" => config.comments = value.extract().map_err(invalid)?,
+            "keep_hints" => config.keep_hints = value.extract().map_err(invalid)?,
+            "unwrap_explain" => config.unwrap_explain = value.extract().map_err(invalid)?,
+            "prepared" => config.prepared = value.extract().map_err(invalid)?,
+            "hash_algorithm" => config.hash_algorithm = value.extract().map_err(invalid)?,
+            "hash_encoding" => config.hash_encoding = value.extract().map_err(invalid)?,
+            _ => return Err(unexpected()),
+        }
+    }
+    let (passes, hooks) = pass_order(passes)?;
+    Ok(OptionArgs { config, passes, hooks })
+}
+
+/// The pooled engine for the options `args` sets and the pass order
+/// `passes`, with `None` for each caller hook; those left unset fall back to
+/// the `configure()` defaults, then to the built-in ones
+fn engine(
+    defaults: &config::Config,
+    args: &config::Config,
+    passes: Option<PassOrder>,
+) -> PyResult<std::sync::Arc<pool::Engine>> {
+    let mut key = args.options(defaults).map_err(options_error)?;
+    if passes.is_some() {
+        key.passes = passes;
+    }
+    pool::engine(key).map_err(options_error)
+}
+
+/// The Python exception for `error`
+fn py_error(error: sqlfp_core::Error<PyErr>) -> PyErr {
+    match error {
+        sqlfp_core::Error::Parse(message) => parse_error(message),
+        sqlfp_core::Error::Options(message) => options_error(message),
+        sqlfp_core::Error::Unnormalizable { message, warnings } => Python::with_gil(|py| {
+            let err = UnnormalizableError::new_err(message);
+            match err.value_bound(py).setattr("warnings", warnings) {
+                Ok(()) => err,
+                Err(e) => e,
+            }
+        }),
+        sqlfp_core::Error::PlaceholderCollision(message) => PlaceholderCollisionError::new_err(message),
+        sqlfp_core::Error::Callback(err) => err,
+    }
+}
+
+/// The Python exception for a statement that could not be parsed:
+/// `EmptyStatementError` if there is none, else a `ParseError` with the
+/// position the message gives
+fn parse_error(message: String) -> PyErr {
+    if message == NO_STATEMENT {
+        return EmptyStatementError::new_err(message);
+    }
+    let position = error_position(&message);
+    let err = ParseError::new_err(message);
+    Python::with_gil(|py| {
+        let value = err.value_bound(py);
+        let set = value
+            .setattr("line", position.line)
+            .and_then(|()| value.setattr("column", position.column))
+            .and_then(|()| value.setattr("token", position.token));
+        match set {
+            Ok(()) => err,
+            Err(e) => e,
+        }
+    })
+}
+
+/// The Python exception for options that cannot be used
+fn options_error(message: String) -> PyErr {
+    if message.starts_with(UNSUPPORTED_DIALECT) {
+        UnsupportedDialectError::new_err(message)
+    } else {
+        Error::new_err(message)
+    }
+}
+
+/// The names of `hooks` in `NormalizeResult.passes`
+fn hook_names(py: Python<'_>, hooks: &[PyObject]) -> PyResult<Vec<String>> {
+    hooks
+        .iter()
+        .map(|hook| {
+            let hook = hook.bind(py);
+            match hook.getattr("__name__") {
+                Ok(name) => Ok(name.str()?.to_string()),
+                Err(_) => Ok(hook.get_type().name()?.to_string()),
+            }
+        })
+        .collect()
+}
+
+/// `sqlfp_core::normalize_text()` with a Python `redactor` and `hooks`, the
+/// callables for the `Step::Hook`s of the engine's pipeline. Each hook gets
+/// the statement as it stands and returns the rewritten SQL, or `None` to
+/// leave it unchanged.
+fn normalize_text(
+    sql: String,
+    engine: &pool::Engine,
+    redactor: Option<&Bound<'_, PyAny>>,
+    hooks: &[PyObject],
+) -> PyResult<NormalizeResult> {
+    let names = if hooks.is_empty() { Vec::new() } else { Python::with_gil(|py| hook_names(py, hooks))? };
+    let redact = &mut |index: usize, clause: Clause, value: String| match redactor {
+        Some(callback) => callback.call1((index, clause.as_str(), value))?.extract::<Option<String>>(),
+        None => Ok(Some(value)),
+    };
+    let rewrite = &mut |index: usize, sql: &str| {
+        Python::with_gil(|py| hooks[index].call1(py, (sql,))?.extract::<Option<String>>(py))
+    };
+    sqlfp_core::normalize_text(sql, engine, redact, rewrite, &names).map(NormalizeResult::from).map_err(py_error)
+}
+
+impl From<Fingerprint> for NormalizeResult {
+    fn from(fingerprint: Fingerprint) -> Self {
+        NormalizeResult {
+            normalized: fingerprint.normalized,
+            hash: fingerprint.hash,
+            shape_hash: fingerprint.shape_hash,
+            hasher: fingerprint.hasher,
+            shape: fingerprint.shape,
+            original: fingerprint.original,
+            params: fingerprint.params,
+            param_types: fingerprint.param_types,
+            param_kinds: fingerprint.param_kinds,
+            param_spans: fingerprint.param_spans,
+            placeholder: fingerprint.placeholder,
+            placeholder_start: fingerprint.placeholder_start,
+            warnings: fingerprint.warnings,
+            passes: fingerprint.passes,
+            has_returning: fingerprint.has_returning,
+            statement_class: fingerprint.statement_class,
+            is_read_only: fingerprint.is_read_only,
+            is_ddl: fingerprint.is_ddl,
+            explained: fingerprint.explained,
+            metrics: fingerprint.metrics.map(QueryMetrics::from),
+            dialect_used: fingerprint.dialect_used,
+            parse_path: fingerprint.parse_path,
+            metadata: fingerprint.metadata,
+            statement_type: fingerprint.statement_type,
+            options: Some(fingerprint.options),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FileFormat {
+    /// One statement per line
+    Lines,
+    /// One JSON object per line, the statement in one of its fields
+    Jsonl,
+}
+
+/// Iterator over the results of `normalize_file()`.
+///
+/// The file stays memory-mapped while the iterator is alive; lines are decoded
+/// and normalized one at a time as they are requested.
+#[pyclass(module = "sqlfp")]
+struct FileResults {
+    path: String,
+    mmap: memmap2::Mmap,
+    offset: usize,
+    line: usize,
+    format: FileFormat,
+    field: String,
+    engine: std::sync::Arc<pool::Engine>,
+    hooks: Vec<PyObject>,
+    encoding: String,
+    errors: String,
+    skip_errors: bool,
+}
+
+impl FileResults {
+    /// The next non-blank line, without its line terminator
+    fn next_line(&mut self) -> Option<&[u8]> {
+        while self.offset < self.mmap.len() {
+            let rest = &self.mmap[self.offset..];
+            let len = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+            self.offset += len + 1;
+            self.line += 1;
+            let line = rest[..len].strip_suffix(b"\r").unwrap_or(&rest[..len]);
+            if !line.iter().all(u8::is_ascii_whitespace) {
+                return Some(line);
+            }
+        }
+        None
+    }
+
+    fn statement(&self, text: String) -> PyResult<String> {
+        if self.format == FileFormat::Lines {
+            return Ok(text);
+        }
+        let value: serde_json::Value = serde_json::from_str(&text)
+            .map_err(|e| PyValueError::new_err(format!("Invalid JSON: {}", e)))?;
+        match value.get(&self.field) {
+            Some(serde_json::Value::String(sql)) => Ok(sql.clone()),
+            Some(_) => Err(PyValueError::new_err(format!("Field '{}' is not a string", self.field))),
+            None => Err(PyValueError::new_err(format!("Missing field '{}'", self.field))),
+        }
+    }
+
+    fn normalize_line(&self, py: Python<'_>, line: &[u8]) -> PyResult<NormalizeResult> {
+        let sql = self.statement(decode_bytes(py, line, &self.encoding, &self.errors)?)?;
+        let mut result = normalize_text(sql, &self.engine, None, &self.hooks)?;
+        result.metadata.push(("line".to_string(), MetaValue::Int(self.line as i64)));
+        Ok(result)
+    }
+}
+
+/// `err` with its message prefixed by `location`, keeping the exception type
+/// where it can be built from a message alone and chaining the original
+fn with_location(py: Python<'_>, err: PyErr, location: &str) -> PyErr {
+    let message = format!("{}: {}", location, err.value_bound(py));
+    let located = err
+        .get_type_bound(py)
+        .call1((message.clone(),))
+        .map(PyErr::from_value_bound)
+        .unwrap_or_else(|_| PyValueError::new_err(message));
+    located.set_cause(py, Some(err));
+    located
+}
+
+#[pymethods]
+impl FileResults {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<NormalizeResult>> {
+        // `next_line` borrows the map; copy the line out so `self` is free again
+        while let Some(line) = self.next_line().map(<[u8]>::to_vec) {
+            match self.normalize_line(py, &line) {
+                Ok(result) => return Ok(Some(result)),
+                Err(_) if self.skip_errors => continue,
+                Err(e) => return Err(with_location(py, e, &format!("{}:{}", self.path, self.line))),
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl FileResults {
+    /// Write the remaining results to `output` as JSONL, one object per
+    /// result, without building Python objects for them; the number written
+    fn write_jsonl(&mut self, py: Python<'_>, output: &std::path::Path) -> PyResult<usize> {
+        use std::io::Write;
+        let mut out = std::io::BufWriter::new(std::fs::File::create(output)?);
+        let mut written = 0;
+        while let Some(result) = self.__next__(py)? {
+            if written % BATCH_CHUNK == 0 {
+                py.check_signals()?;
+            }
+            // Numbers with `hash_encoding="int"`, as on the result
+            let hash = |hash: &str| result.hasher.int(hash).map_or_else(|| hash.into(), serde_json::Value::from);
+            let record = serde_json::json!({
+                "line": self.line,
+                "hash": hash(&result.hash),
+                "shape_hash": hash(&result.shape_hash),
+                "normalized": result.normalized,
+                "params": result.params,
+                "warnings": result.warnings,
+                "dialect_used": result.dialect_used,
+                "parse_path": result.parse_path,
+            });
+            serde_json::to_writer(&mut out, &record).map_err(std::io::Error::from)?;
+            out.write_all(b"\n")?;
+            written += 1;
+        }
+        out.flush()?;
+        Ok(written)
+    }
+}
+
+#[pyfunction]
+#[pyo3(signature = (path, format="lines", field="query", *, on_error="raise", output=None, **kwargs))]
+fn normalize_file(
+    py: Python<'_>,
+    path: std::path::PathBuf,
+    format: &str,
+    field: &str,
+    on_error: &str,
+    output: Option<std::path::PathBuf>,
+    kwargs: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let args = option_args("normalize_file", kwargs, &[])?;
+    let defaults = config::defaults();
+    let (encoding, errors) = encoding_args(&defaults, args.config.encoding.as_deref(), args.config.errors.as_deref());
+    let engine = engine(&defaults, &args.config, args.passes)?;
+    let format = match format {
+        "lines" => FileFormat::Lines,
+        "jsonl" => FileFormat::Jsonl,
+        _ => return Err(PyValueError::new_err(format!("Unsupported file format: {}", format))),
+    };
+    let skip_errors = match on_error {
+        "raise" => false,
+        "skip" => true,
+        _ => return Err(PyValueError::new_err(format!("Unsupported on_error: {}", on_error))),
+    };
+
+    let file = std::fs::File::open(&path)?;
+    // SAFETY: the map is only read; as with any mmap, the file being truncated
+    // by another process while iterating is undefined behavior
+    let mmap = unsafe { memmap2::Mmap::map(&file)? };
+
+    let mut results = FileResults {
+        path: path.display().to_string(),
+        mmap,
+        offset: 0,
+        line: 0,
+        format,
+        field: field.to_string(),
+        engine,
+        hooks: args.hooks,
+        encoding: encoding.to_string(),
+        errors: errors.to_string(),
+        skip_errors,
+    };
+    match output {
+        // Truncating the mapped file would pull the lines from under the map
+        Some(output) if std::fs::canonicalize(&output).ok() == std::fs::canonicalize(&path).ok() => {
+            Err(PyValueError::new_err("output must not be the input file"))
+        }
+        Some(output) => Ok(results.write_jsonl(py, &output)?.into_py(py)),
+        None => Ok(results.into_py(py)),
+    }
+}
+
+/// `normalize()` over the statements of a script, with `GO` batches and the
+/// `USE` / `search_path` context in the metadata
+#[pyfunction]
+#[pyo3(signature = (script, dialect=None, placeholder=None, *, redactor=None, on_error="raise", **kwargs))]
+fn normalize_script(
+    py: Python<'_>,
+    script: &Bound<'_, PyAny>,
+    dialect: Option<&str>,
+    placeholder: Option<&str>,
+    redactor: Option<&Bound<'_, PyAny>>,
+    on_error: &str,
+    kwargs: Option<&Bound<'_, PyDict>>,
+) -> PyResult<Vec<NormalizeResult>> {
+    let args = option_args("normalize_script", kwargs, &[])?.positional(dialect, placeholder);
+    let defaults = config::defaults();
+    let (encoding, errors) = encoding_args(&defaults, args.config.encoding.as_deref(), args.config.errors.as_deref());
+    let script = sql_text(script, encoding, errors)?;
+    let engine = engine(&defaults, &args.config, args.passes)?;
+    normalize_pieces(py, &script, &engine, redactor, &args.hooks, skip_errors(on_error)?)
+}
+
+/// Whether `on_error` of a script skips the statements that fail
+fn skip_errors(on_error: &str) -> PyResult<bool> {
+    match on_error {
+        "raise" => Ok(false),
+        "skip" => Ok(true),
+        _ => Err(PyValueError::new_err(format!("Unsupported on_error: {}", on_error))),
+    }
+}
+
+/// The results of the statements of `script`, see `normalize_script()`
+fn normalize_pieces(
+    py: Python<'_>,
+    script: &str,
+    engine: &pool::Engine,
+    redactor: Option<&Bound<'_, PyAny>>,
+    hooks: &[PyObject],
+    skip_errors: bool,
+) -> PyResult<Vec<NormalizeResult>> {
+    let pieces = script::split(script, &*engine.dialect).map_err(parse_error)?;
+    let mut context = script::Context::default();
+    let mut results = Vec::with_capacity(pieces.len());
+    for (i, piece) in pieces.into_iter().enumerate() {
+        if i % BATCH_CHUNK == 0 {
+            py.check_signals()?;
+        }
+        match normalize_text(piece.sql.clone(), engine, redactor, hooks) {
+            Ok(mut result) => {
+                result.metadata.push(("line".to_string(), MetaValue::Int(piece.line as i64)));
+                result.metadata.push(("batch".to_string(), MetaValue::Int(piece.batch as i64)));
+                result.metadata.extend(context.metadata());
+                results.push(result);
+            }
+            Err(e) if e.is_instance_of::<PyKeyboardInterrupt>(py) => return Err(e),
+            Err(_) if skip_errors => {}
+            Err(e) => return Err(with_location(py, e, &format!("line {}", piece.line))),
+        }
+        if piece.sets_context {
+            if let Ok(stmt) = parse_first_statement(&piece.sql, &*engine.dialect) {
+                context.observe(&stmt);
+            }
+        }
+    }
+    Ok(results)
+}
+
+/// The results of the statements of a captured query, decoded as `encoding`
+/// (a codec name) with `errors`
+fn normalize_wire_query(
+    py: Python<'_>,
+    query: &[u8],
+    dialect: &str,
+    encoding: &str,
+    errors: Option<&str>,
+    compat: Option<&str>,
+    on_error: &str,
+) -> PyResult<Vec<NormalizeResult>> {
+    let defaults = config::defaults();
+    let (_, errors) = encoding_args(&defaults, None, errors);
+    let script = decode_bytes(py, query, encoding, errors)?;
+    let args = config::Config {
+        dialect: Some(dialect.to_string()),
+        compat: compat.map(str::to_string),
+        ..Default::default()
+    };
+    let engine = engine(&defaults, &args, None)?;
+    normalize_pieces(py, &script, &engine, None, &[], skip_errors(on_error)?)
+}
+
+/// `normalize_script()` over the query of a captured MySQL `COM_QUERY`
+/// packet payload
+#[pyfunction]
+#[pyo3(signature = (payload, *, encoding="utf8mb4", errors=None, query_attributes=false, dialect="mysql", compat=None, on_error="raise"))]
+#[allow(clippy::too_many_arguments)]
+fn fingerprint_mysql_query(
+    py: Python<'_>,
+    payload: &[u8],
+    encoding: &str,
+    errors: Option<&str>,
+    query_attributes: bool,
+    dialect: &str,
+    compat: Option<&str>,
+    on_error: &str,
+) -> PyResult<Vec<NormalizeResult>> {
+    let query = wire::mysql_query(payload, query_attributes).map_err(PyValueError::new_err)?;
+    normalize_wire_query(py, query, dialect, wire::mysql_encoding(encoding), errors, compat, on_error)
+}
+
+/// `normalize_script()` over the query of a captured PostgreSQL `Query` or
+/// `Parse` message body
+#[pyfunction]
+#[pyo3(signature = (body, message_type="Q", *, encoding="UTF8", errors=None, dialect="postgresql", compat=None, on_error="raise"))]
+#[allow(clippy::too_many_arguments)]
+fn fingerprint_postgres_message(
+    py: Python<'_>,
+    body: &[u8],
+    message_type: &str,
+    encoding: &str,
+    errors: Option<&str>,
+    dialect: &str,
+    compat: Option<&str>,
+    on_error: &str,
+) -> PyResult<Vec<NormalizeResult>> {
+    let query = wire::postgres_query(body, message_type).map_err(PyValueError::new_err)?;
+    normalize_wire_query(py, query, dialect, wire::postgres_encoding(encoding), errors, compat, on_error)
+}
+
+/// Iterator over the results of `fingerprint_mysql_slowlog()` and
+/// `fingerprint_postgres_csvlog()`.
+///
+/// The log is read a line at a time as results are requested; the
+/// statements of each entry are normalized like those of a script, with the
+/// entry's metadata.
+#[pyclass(module = "sqlfp")]
+struct LogResults {
+    path: String,
+    reader: std::io::BufReader<std::fs::File>,
+    line: usize,
+    parser: Box<dyn logs::LogParser + Send>,
+    /// Results of the last entry not yet returned
+    pending: std::collections::VecDeque<NormalizeResult>,
+    done: bool,
+    engine: std::sync::Arc<pool::Engine>,
+    encoding: String,
+    errors: String,
+    skip_errors: bool,
+}
+
+impl LogResults {
+    /// The entry the next line completes, or the last one at the end of the
+    /// log; `None` while the entry goes on
+    fn next_entry(&mut self, py: Python<'_>) -> PyResult<Option<logs::Entry>> {
+        use std::io::BufRead;
+        let mut line = Vec::new();
+        if self.reader.read_until(b'\n', &mut line)? == 0 {
+            self.done = true;
+            return self.parser.finish().map_err(PyValueError::new_err);
+        }
+        self.line += 1;
+        let line = line.strip_suffix(b"\n").unwrap_or(&line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let text = decode_bytes(py, line, &self.encoding, &self.errors)?;
+        self.parser.feed(self.line, &text).map_err(PyValueError::new_err)
+    }
+
+    /// Queue the results of the statements of `entry`
+    fn normalize_entry(&mut self, py: Python<'_>, entry: logs::Entry) -> PyResult<()> {
+        let pieces = match script::split(&entry.sql, &*self.engine.dialect) {
+            Ok(pieces) => pieces,
+            Err(_) if self.skip_errors => return Ok(()),
+            Err(e) => return Err(with_location(py, PyValueError::new_err(e), &format!("{}:{}", self.path, entry.line))),
+        };
+        for piece in pieces {
+            let line = entry.line + piece.line - 1;
+            match normalize_text(piece.sql, &self.engine, None, &[]) {
+                Ok(mut result) => {
+                    result.metadata.push(("line".to_string(), MetaValue::Int(line as i64)));
+                    result.metadata.extend(entry.metadata.iter().cloned());
+                    self.pending.push_back(result);
+                }
+                Err(e) if e.is_instance_of::<PyKeyboardInterrupt>(py) => return Err(e),
+                Err(_) if self.skip_errors => {}
+                Err(e) => return Err(with_location(py, e, &format!("{}:{}", self.path, line))),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl LogResults {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<NormalizeResult>> {
+        loop {
+            if let Some(result) = self.pending.pop_front() {
+                return Ok(Some(result));
+            }
+            if self.done {
+                return Ok(None);
+            }
+            match self.next_entry(py) {
+                Ok(Some(entry)) => self.normalize_entry(py, entry)?,
+                Ok(None) => {}
+                Err(_) if self.skip_errors => {}
+                Err(e) => return Err(with_location(py, e, &format!("{}:{}", self.path, self.line))),
+            }
+        }
+    }
+}
+
+/// The results of the statements logged in the log at `path`, read with
+/// `parser`
+#[allow(clippy::too_many_arguments)]
+fn log_results(
+    path: std::path::PathBuf,
+    parser: Box<dyn logs::LogParser + Send>,
+    encoding: &str,
+    errors: Option<&str>,
+    dialect: &str,
+    compat: Option<&str>,
+    on_error: &str,
+) -> PyResult<LogResults> {
+    let defaults = config::defaults();
+    let (_, errors) = encoding_args(&defaults, None, errors);
+    let args = config::Config {
+        dialect: Some(dialect.to_string()),
+        compat: compat.map(str::to_string),
+        ..Default::default()
+    };
+    let engine = engine(&defaults, &args, None)?;
+    let skip_errors = skip_errors(on_error)?;
+    Ok(LogResults {
+        path: path.display().to_string(),
+        reader: std::io::BufReader::new(std::fs::File::open(&path)?),
+        line: 0,
+        parser,
+        pending: std::collections::VecDeque::new(),
+        done: false,
+        engine,
+        encoding: encoding.to_string(),
+        errors: errors.to_string(),
+        skip_errors,
+    })
+}
+
+/// The statements of a MySQL slow query log, with the metadata of their run
+#[pyfunction]
+#[pyo3(signature = (path, *, encoding="utf-8", errors=None, dialect="mysql", compat=None, on_error="raise"))]
+fn fingerprint_mysql_slowlog(
+    path: std::path::PathBuf,
+    encoding: &str,
+    errors: Option<&str>,
+    dialect: &str,
+    compat: Option<&str>,
+    on_error: &str,
+) -> PyResult<LogResults> {
+    log_results(path, Box::new(logs::MysqlSlowlog::new()), encoding, errors, dialect, compat, on_error)
+}
+
+/// The statements of a PostgreSQL CSV log, with the metadata of their run
+#[pyfunction]
+#[pyo3(signature = (path, *, encoding="utf-8", errors=None, dialect="postgresql", compat=None, on_error="raise"))]
+fn fingerprint_postgres_csvlog(
+    path: std::path::PathBuf,
+    encoding: &str,
+    errors: Option<&str>,
+    dialect: &str,
+    compat: Option<&str>,
+    on_error: &str,
+) -> PyResult<LogResults> {
+    log_results(path, Box::new(logs::PostgresCsvlog::new()), encoding, errors, dialect, compat, on_error)
+}
+
+/// `results` as a MessagePack array of `NormalizeResult.to_msgpack()` maps
+#[pyfunction]
+fn results_to_msgpack<'py>(py: Python<'py>, results: Vec<PyRef<'py, NormalizeResult>>) -> Bound<'py, PyBytes> {
+    let mut enc = msgpack::Encoder::new();
+    enc.array(results.len());
+    for result in &results {
+        result.write_msgpack(&mut enc);
+    }
+    PyBytes::new_bound(py, &enc.out)
+}
+
+/// Fingerprint of a whole script: the SHA-256 of the hashes of its
+/// statements' results, in order
+#[pyfunction]
+fn script_hash(results: Vec<PyRef<'_, NormalizeResult>>) -> String {
+    let mut hasher = Sha256::new();
+    for result in &results {
+        hasher.update(result.hash.as_bytes());
+        hasher.update(b"\n");
+    }
+    hex::encode(hasher.finalize())
+}
+
+#[pyfunction]
+#[pyo3(signature = (sql, dialect="generic"))]
+fn parse_ast(py: Python<'_>, sql: &str, dialect: &str) -> PyResult<Py<AstNode>> {
+    let dialect_impl = sql_dialect(dialect, sql).map_err(options_error)?;
+    let stmt = parse_first_statement(sql, &*dialect_impl)?;
+    AstNode::from_node(py, ast::build_tree(&stmt))
+}
+
+#[pyfunction]
+#[pyo3(signature = (sql, dialect="generic", *, as_json=false))]
+fn parse(py: Python<'_>, sql: &str, dialect: &str, as_json: bool) -> PyResult<PyObject> {
+    let dialect_impl = sql_dialect(dialect, sql).map_err(options_error)?;
+    let stmt = parse_first_statement(sql, &*dialect_impl)?;
+    if as_json {
+        let text = serde_json::to_string(&stmt).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        return Ok(text.into_py(py));
+    }
+    let value = serde_json::to_value(&stmt).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    json_to_object(py, &value)
+}
+
+/// Convert the Python rule dicts accepted by `rewrite()` into rewrite rules
+fn extract_rewrite_rules(rules: &Bound<'_, PyAny>) -> PyResult<Vec<rewrite::Rule>> {
+    let mut out = Vec::new();
+    for item in rules.iter()? {
+        let item = item?;
+        let rule = item.downcast::<PyDict>()?;
+        let field = |key: &str, op: &str| -> PyResult<String> {
+            rule.get_item(key)?
+                .ok_or_else(|| {
+                    PyValueError::new_err(format!("Rewrite rule '{}' requires '{}'", op, key))
+                })?
+                .extract()
+        };
+        let op = field("op", "")?;
+        out.push(match op.as_str() {
+            "rename_table" => rewrite::Rule::RenameTable {
+                from: rewrite::parse_name(&field("from", &op)?),
+                to: rewrite::parse_name(&field("to", &op)?),
+            },
+            "replace_schema" => rewrite::Rule::ReplaceSchema {
+                from: field("from", &op)?,
+                to: field("to", &op)?,
+            },
+            "drop_predicate" => rewrite::Rule::DropPredicate {
+                column: rewrite::parse_name(&field("column", &op)?),
+            },
+            _ => return Err(PyValueError::new_err(format!("Unknown rewrite op: {}", op))),
+        });
+    }
+    Ok(out)
+}
+
+#[pyfunction(name = "rewrite")]
+#[pyo3(signature = (sql, rules, dialect="generic", placeholder="?"))]
+fn rewrite_sql(
+    sql: &str,
+    rules: &Bound<'_, PyAny>,
+    dialect: &str,
+    placeholder: &str,
+) -> PyResult<(String, NormalizeResult)> {
+    let dialect_impl = sql_dialect(dialect, sql).map_err(options_error)?;
+    let rules = extract_rewrite_rules(rules)?;
+
+    let mut stmt = parse_first_statement(sql, &*dialect_impl)?;
+    rewrite::apply(&mut stmt, &rules);
+    let rewritten = stmt.to_string();
+    let class = classify::classify(&stmt);
+
+    let steps: Vec<Step> = Pass::ALL.into_iter().filter(|pass| !pass.opt_in()).map(Step::Pass).collect();
+    let Normalized { sql: normalized, params, param_types, param_kinds, param_spans, warnings, duplicate_branches, .. } = normalize_statement::<PyErr>(
+        &mut stmt,
+        placeholder,
+        1,
+        &steps,
+        &Rules {
+            identifier_booleans: !dialect_impl.supports_boolean_literals(),
+            string_quotes: string_quotes(&*dialect_impl),
+            ..Rules::LATEST
+        },
+        true,
+        None,
+        keep::Keep::default(),
+        &mut |_, _, value| Ok(Some(value)),
+        &mut |_, _| Ok(false),
+    )?;
+    let hash = compute_hash(&hashing::Hasher::default(), &normalized);
+    let shape = collapse::collapse_lists(&mut stmt, placeholder, 1).then(|| writer::write(&stmt));
+    let shape_hash = shape.as_deref().map_or_else(|| hash.clone(), |shape| compute_hash(&hashing::Hasher::default(), shape));
+
+    Ok((
+        rewritten.clone(),
+        NormalizeResult {
+            normalized,
+            hash,
+            shape_hash,
+            hasher: hashing::Hasher::default(),
+            shape,
+            params,
+            param_types,
+            param_kinds,
+            param_spans: offsets::byte_spans(&rewritten, &rewritten, &param_spans),
+            placeholder: placeholder.to_string(),
+            placeholder_start: 1,
+            original: rewritten,
+            warnings,
+            passes: step_names(&steps, &[]),
+            has_returning: returning_items(&stmt).is_some(),
+            statement_class: class.class,
+            is_read_only: class.read_only,
+            is_ddl: class.is_ddl(),
+            explained: false,
+            metrics: Some(metrics::metrics(&stmt).into()),
+            dialect_used: canonical_dialect(dialect).map_err(options_error)?,
+            parse_path: PARSE_PATH_AST,
+            metadata: duplicate_branches_metadata(duplicate_branches)
+                .into_iter()
+                .chain(returning_metadata(&stmt))
+                .chain(cartesian_metadata(&stmt))
+                .collect(),
+            statement_type: ast::variant_name(&stmt),
+            options: None,
+        },
+    ))
+}
+
+#[pyfunction]
+#[pyo3(signature = (sql, mapping, dialect="generic"))]
+fn retarget(sql: &str, mapping: HashMap<String, String>, dialect: &str) -> PyResult<String> {
+    let dialect_impl = sql_dialect(dialect, sql).map_err(options_error)?;
+    let mapping: Vec<_> =
+        mapping.iter().map(|(from, to)| (rewrite::parse_name(from), rewrite::parse_name(to))).collect();
+    let mut stmt = parse_first_statement(sql, &*dialect_impl)?;
+    rewrite::retarget(&mut stmt, &mapping);
+    Ok(stmt.to_string())
+}
+
+#[pyfunction]
+#[pyo3(signature = (sql, from_style, to_style, dialect="generic"))]
+fn convert_paramstyle(
+    py: Python<'_>,
+    sql: &str,
+    from_style: &str,
+    to_style: &str,
+    dialect: &str,
+) -> PyResult<(String, Vec<PyObject>)> {
+    let dialect_impl = sql_dialect(dialect, sql).map_err(options_error)?;
+    let from = paramstyle::Style::parse(from_style).map_err(PyValueError::new_err)?;
+    let to = paramstyle::Style::parse(to_style).map_err(PyValueError::new_err)?;
+
+    let (converted, order) =
+        paramstyle::convert(sql, &*dialect_impl, from, to).map_err(PyValueError::new_err)?;
+    let order = order
+        .into_iter()
+        .map(|key| match key {
+            paramstyle::ParamKey::Position(n) => n.into_py(py),
+            paramstyle::ParamKey::Name(name) => name.into_py(py),
+        })
+        .collect();
+    Ok((converted, order))
+}
+
+#[pyfunction]
+#[pyo3(signature = (sql, dialect="generic"))]
+fn strip_comments(sql: &str, dialect: &str) -> PyResult<String> {
+    let dialect_impl = sql_dialect(dialect, sql).map_err(options_error)?;
+    comments::strip(sql, &*dialect_impl).map_err(PyValueError::new_err)
+}
+
+#[pyfunction]
+#[pyo3(signature = (sql, dialect="generic", *, mask="?"))]
+fn redact(sql: &str, dialect: &str, mask: &str) -> PyResult<String> {
+    let dialect_impl = sql_dialect(dialect, sql).map_err(options_error)?;
+    mask::redact(sql, &*dialect_impl, mask).map_err(PyValueError::new_err)
+}
+
+#[pyfunction]
+#[pyo3(signature = (sql, dialect="generic"))]
+fn extract_tables<'py>(py: Python<'py>, sql: &str, dialect: &str) -> PyResult<Bound<'py, PyDict>> {
+    let dialect_impl = sql_dialect(dialect, sql).map_err(options_error)?;
+    let stmt = parse_first_statement(sql, &*dialect_impl)?;
+    let tables = tables::tables(&stmt);
+    let dict = PyDict::new_bound(py);
+    dict.set_item("read", tables.read)?;
+    dict.set_item("written", tables.written)?;
+    Ok(dict)
+}
+
+#[pyfunction]
+#[pyo3(signature = (sql, dialect="generic"))]
+fn extract_columns<'py>(py: Python<'py>, sql: &str, dialect: &str) -> PyResult<Bound<'py, PyDict>> {
+    let dialect_impl = sql_dialect(dialect, sql).map_err(options_error)?;
+    let stmt = parse_first_statement(sql, &*dialect_impl)?;
+    let dict = PyDict::new_bound(py);
+    for (clause, names) in columns::columns(&stmt) {
+        dict.set_item(clause, names)?;
+    }
+    Ok(dict)
+}
+
+/// The `configure()` defaults as a dict, unset ones left out
+fn config_to_dict<'py>(py: Python<'py>, config: &config::Config) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    let strings = [
+        ("dialect", &config.dialect),
+        ("placeholder", &config.placeholder),
+        ("style", &config.style),
+        ("compat", &config.compat),
+        ("encoding", &config.encoding),
+        ("errors", &config.errors),
+        ("function_case", &config.function_case),
+        ("identifier_case", &config.identifier_case),
+        ("placeholder_collision", &config.placeholder_collision),
+        ("hash_algorithm", &config.hash_algorithm),
+        ("hash_encoding", &config.hash_encoding),
+    ];
+    for (key, value) in strings {
+        if let Some(value) = value {
+            dict.set_item(key, value)?;
+        }
+    }
+    if let Some(start) = config.placeholder_start {
+        dict.set_item("placeholder_start", start)?;
+    }
+    if let Some(max) = config.max_subquery_depth {
+        dict.set_item("max_subquery_depth", max)?;
+    }
+    if let Some(max) = config.max_params {
+        dict.set_item("max_params", max)?;
+    }
+    for (key, value) in [("templates", config.templates), ("strict", config.strict), ("clause_hashes", config.clause_hashes), ("collect_params", config.collect_params), ("truncated_input", config.truncated_input), ("lenient", config.lenient), ("collapse_in_lists", config.collapse_in_lists), ("collapse_values", config.collapse_values), ("keep_limit", config.keep_limit), ("keep_offset", config.keep_offset), ("keep_null", config.keep_null), ("ignore_aliases", config.ignore_aliases), ("anonymize", config.anonymize), ("comments", config.comments), ("keep_hints", config.keep_hints), ("unwrap_explain", config.unwrap_explain), ("prepared", config.prepared)] {
+        if let Some(value) = value {
+            dict.set_item(key, value)?;
+        }
+    }
+    let lists = [
+        ("passes", &config.passes),
+        ("disable_passes", &config.disable_passes),
+        ("enable_passes", &config.enable_passes),
+        ("routing_columns", &config.routing_columns),
+        ("ignore_clauses", &config.ignore_clauses),
+    ];
+    for (key, value) in lists {
+        if let Some(value) = value {
+            dict.set_item(key, value)?;
+        }
+    }
+    if let Some(ref tables) = config.table_columns {
+        dict.set_item("table_columns", tables)?;
+    }
+    if let Some(ref synonyms) = config.function_synonyms {
+        dict.set_item("function_synonyms", synonyms)?;
+    }
+    if let Some(ref strip) = config.strip_qualifiers {
+        dict.set_item("strip_qualifiers", strip_object(py, strip))?;
+    }
+    Ok(dict)
+}
+
+#[pyfunction]
+#[pyo3(signature = (path=None, *, reset=false, **kwargs))]
+fn configure<'py>(
+    py: Python<'py>,
+    path: Option<std::path::PathBuf>,
+    reset: bool,
+    kwargs: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let mut settings = if reset { config::Config::default() } else { (*config::defaults()).clone() };
+    if let Some(path) = path {
+        let text = std::fs::read_to_string(&path)?;
+        settings = settings.merged(&config::parse(&path, &text).map_err(PyValueError::new_err)?);
+    }
+    let options = option_args("configure", kwargs, &[])?;
+    let args = config::Config { passes: options.pass_names()?, ..options.config };
+    args.validate().map_err(PyValueError::new_err)?;
+    settings = settings.merged(&args);
+    let out = config_to_dict(py, &settings)?;
+    config::set_defaults(settings);
+    Ok(out)
+}
+
+/// The pooled engines, for debugging: one dict per (dialect, options) combination
+#[pyfunction]
+fn engine_pool(py: Python<'_>) -> PyResult<Vec<Bound<'_, PyDict>>> {
+    pool::snapshot()
+        .into_iter()
+        .map(|(key, dialect, hits)| {
+            let entry = PyDict::new_bound(py);
+            entry.set_item("dialect", dialect)?;
+            entry.set_item("placeholder", key.placeholder)?;
+            entry.set_item("placeholder_start", key.placeholder_start)?;
+            entry.set_item("compat", key.compat)?;
+            entry.set_item("templates", key.templates)?;
+            entry.set_item("strict", key.strict)?;
+            entry.set_item("passes", key.passes)?;
+            entry.set_item("disabled_passes", key.disabled_passes)?;
+            entry.set_item("enabled_passes", key.enabled_passes)?;
+            entry.set_item("function_case", key.function_case)?;
+            entry.set_item("identifier_case", key.identifier_case)?;
+            entry.set_item("clause_hashes", key.clause_hashes)?;
+            entry.set_item("max_subquery_depth", key.max_subquery_depth)?;
+            entry.set_item("routing_columns", key.routing_columns)?;
+            entry.set_item("placeholder_collision", key.placeholder_collision)?;
+            entry.set_item("ignore_clauses", key.ignore_clauses)?;
+            entry.set_item("table_columns", key.table_columns)?;
+            entry.set_item("function_synonyms", key.function_synonyms)?;
+            entry.set_item("collect_params", key.collect_params)?;
+            entry.set_item("max_params", key.max_params)?;
+            entry.set_item("truncated_input", key.truncated_input)?;
+            entry.set_item("lenient", key.lenient)?;
+            entry.set_item("collapse_in_lists", key.collapse_in_lists)?;
+            entry.set_item("collapse_values", key.collapse_values)?;
+            entry.set_item("keep_limit", key.keep_limit)?;
+            entry.set_item("keep_offset", key.keep_offset)?;
+            entry.set_item("keep_null", key.keep_null)?;
+            entry.set_item("strip_qualifiers", strip_object(py, &key.strip_qualifiers))?;
+            entry.set_item("ignore_aliases", key.ignore_aliases)?;
+            entry.set_item("anonymize", key.anonymize)?;
+            entry.set_item("comments", key.comments)?;
+            entry.set_item("keep_hints", key.keep_hints)?;
+            entry.set_item("unwrap_explain", key.unwrap_explain)?;
+            entry.set_item("prepared", key.prepared)?;
+            entry.set_item("hash_algorithm", key.hash_algorithm)?;
+            entry.set_item("hash_encoding", key.hash_encoding)?;
+            entry.set_item("hits", hits)?;
+            Ok(entry)
+        })
+        .collect()
+}
+
+/// Drop all pooled engines
+#[pyfunction]
+fn clear_engine_pool() {
+    pool::clear();
+}
+
+/// Normalize every sample of a corpus into a deterministic manifest, to detect
+/// fingerprint drift between releases
+#[pyfunction]
+#[pyo3(signature = (
+    path,
+    output=None,
+    *,
+    dialect=None,
+    placeholder=None,
+    compat=None,
+    templates=None,
+    encoding=None,
+    errors=None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn snapshot_corpus(
+    py: Python<'_>,
+    path: std::path::PathBuf,
+    output: Option<std::path::PathBuf>,
+    dialect: Option<&str>,
+    placeholder: Option<&str>,
+    compat: Option<&str>,
+    templates: Option<bool>,
+    encoding: Option<&str>,
+    errors: Option<&str>,
+) -> PyResult<String> {
+    let defaults = config::defaults();
+    let (encoding, errors) = encoding_args(&defaults, encoding, errors);
+    let args = config::Config {
+        dialect: dialect.map(str::to_string),
+        placeholder: placeholder.map(str::to_string),
+        compat: compat.map(str::to_string),
+        templates,
+        ..Default::default()
+    };
+    let engine = engine(&defaults, &args, None)?;
+    let mut entries = Vec::new();
+    for (i, sample) in corpus::samples(&path)?.into_iter().enumerate() {
+        if i % BATCH_CHUNK == 0 {
+            py.check_signals()?;
+        }
+        let entry = decode_bytes(py, &sample.bytes, encoding, errors)
+            .and_then(|sql| normalize_text(sql, &engine, None, &[]))
+            .map(|result| corpus::Entry::Normalized { normalized: result.normalized, hash: result.hash })
+            .unwrap_or_else(|e| corpus::Entry::Failed(e.value_bound(py).to_string()));
+        entries.push((sample.id, entry));
+    }
+    let manifest = corpus::manifest(engine.dialect_name, compat.or(defaults.compat.as_deref()), entries);
+    if let Some(output) = output {
+        std::fs::write(output, &manifest)?;
+    }
+    Ok(manifest)
+}
+
+/// Anonymized, fingerprinted query shapes to share without exposing names or data
+#[pyfunction]
+#[pyo3(signature = (queries, output=None, *, dialect=None, compat=None))]
+fn export_bundle(
+    queries: &Bound<'_, PyAny>,
+    output: Option<std::path::PathBuf>,
+    dialect: Option<&str>,
+    compat: Option<&str>,
+) -> PyResult<String> {
+    let defaults = config::defaults();
+    let (encoding, errors) = encoding_args(&defaults, None, None);
+    // A fixed placeholder keeps the anonymized output parseable, and shapes
+    // that cannot be fully normalized are still worth sending
+    let args = config::Config {
+        dialect: dialect.map(str::to_string),
+        placeholder: Some("?".to_string()),
+        compat: compat.map(str::to_string),
+        strict: Some(false),
+        ..Default::default()
+    };
+    let engine = engine(&defaults, &args, None)?;
+    let mut anonymizer = bundle::Anonymizer::default();
+    let mut entries: Vec<bundle::Entry> = Vec::new();
+    let mut by_hash: HashMap<String, usize> = HashMap::new();
+    let mut unparsed = 0;
+    for (i, query) in queries.iter()?.enumerate() {
+        if i % BATCH_CHUNK == 0 {
+            queries.py().check_signals()?;
+        }
+        let sql = sql_text(&query?, encoding, errors)?;
+        let plain = if engine.options.templates { template::strip_templates(&sql).0 } else { sql.clone() };
+        let (Ok(result), Ok(stmt)) = (normalize_text(sql, &engine, None, &[]), parse_first_statement(&plain, &*engine.dialect))
+        else {
+            unparsed += 1;
+            continue;
+        };
+        if let Some(&i) = by_hash.get(&result.hash) {
+            entries[i].count += 1;
+            continue;
+        }
+        let anonymized = anonymizer.anonymize(&result.normalized, &stmt, &*engine.dialect).map_err(PyValueError::new_err)?;
+        by_hash.insert(result.hash.clone(), entries.len());
+        entries.push(bundle::Entry {
+            hash: result.hash,
+            sql: anonymized,
+            count: 1,
+            params: result.params.len(),
+            warnings: result.warnings,
+            metrics: bundle::metrics(&stmt),
+        });
+    }
+    let document = bundle::document(engine.dialect_name, compat.or(defaults.compat.as_deref()), entries, unparsed);
+    if let Some(output) = output {
+        std::fs::write(output, &document)?;
+    }
+    Ok(document)
+}
+
+/// Count the fingerprints of `source`, an iterable of queries or the path of
+/// a corpus as read by `snapshot_corpus()`, into `registry`
+fn count_corpus(
+    registry: &mut registry::Registry,
+    source: &Bound<'_, PyAny>,
+    engine: &pool::Engine,
+    encoding: &str,
+    errors: &str,
+) -> PyResult<()> {
+    let py = source.py();
+    let mut observe = |sql: PyResult<String>| match sql.and_then(|sql| normalize_text(sql, engine, None, &[])) {
+        Ok(result) => registry.observe(&result.hash, &result.normalized, 1, None),
+        Err(_) => registry.unparsed += 1,
+    };
+    if source.hasattr("__fspath__")? {
+        let path: std::path::PathBuf = source.extract()?;
+        for (i, sample) in corpus::samples(&path)?.into_iter().enumerate() {
+            if i % BATCH_CHUNK == 0 {
+                py.check_signals()?;
+            }
+            observe(decode_bytes(py, &sample.bytes, encoding, errors));
+        }
+        return Ok(());
+    }
+    // A lone query would otherwise be counted one character at a time
+    if source.is_instance_of::<PyString>() || source.is_instance_of::<PyBytes>() {
+        return Err(PyTypeError::new_err("expected an iterable of queries or an os.PathLike corpus path"));
+    }
+    for (i, query) in source.iter()?.enumerate() {
+        if i % BATCH_CHUNK == 0 {
+            py.check_signals()?;
+        }
+        observe(sql_text(&query?, encoding, errors));
+    }
+    Ok(())
+}
+
+/// Fingerprints of `after` that are new, disappeared or changed in frequency
+/// since `before`
+#[pyfunction]
+#[pyo3(signature = (
+    before,
+    after,
+    *,
+    dialect=None,
+    placeholder=None,
+    compat=None,
+    templates=None,
+    encoding=None,
+    errors=None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn diff_corpora<'py>(
+    py: Python<'py>,
+    before: &Bound<'py, PyAny>,
+    after: &Bound<'py, PyAny>,
+    dialect: Option<&str>,
+    placeholder: Option<&str>,
+    compat: Option<&str>,
+    templates: Option<bool>,
+    encoding: Option<&str>,
+    errors: Option<&str>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let defaults = config::defaults();
+    let (encoding, errors) = encoding_args(&defaults, encoding, errors);
+    // Statements that cannot be fully normalized are still counted
+    let args = config::Config {
+        dialect: dialect.map(str::to_string),
+        placeholder: placeholder.map(str::to_string),
+        compat: compat.map(str::to_string),
+        templates,
+        strict: Some(false),
+        ..Default::default()
+    };
+    let engine = engine(&defaults, &args, None)?;
+    let compat = compat.or(defaults.compat.as_deref());
+    let mut counts = Vec::new();
+    for source in [before, after] {
+        let mut registry = registry::Registry::new(engine.dialect_name, compat, registry::DEFAULT_BUCKET_SECONDS)
+            .map_err(PyValueError::new_err)?;
+        count_corpus(&mut registry, source, &engine, encoding, errors)?;
+        counts.push(registry);
+    }
+    let diff = diff::diff(&counts[0], &counts[1]);
+    let changes = |changes: &[diff::Change<'_>]| -> PyResult<Vec<Bound<'py, PyDict>>> {
+        changes
+            .iter()
+            .map(|change| {
+                let item = PyDict::new_bound(py);
+                item.set_item("hash", change.hash)?;
+                item.set_item("normalized", change.normalized)?;
+                item.set_item("before", change.before)?;
+                item.set_item("after", change.after)?;
+                Ok(item)
+            })
+            .collect()
+    };
+    let report = PyDict::new_bound(py);
+    report.set_item("dialect", engine.dialect_name)?;
+    report.set_item("compat", compat)?;
+    report.set_item("new", changes(&diff.new)?)?;
+    report.set_item("disappeared", changes(&diff.disappeared)?)?;
+    report.set_item("changed", changes(&diff.changed)?)?;
+    report.set_item("unchanged", diff.unchanged)?;
+    report.set_item("unparsed", (counts[0].unparsed, counts[1].unparsed))?;
+    Ok(report)
+}
+
+/// `normalized` with the Python values `params` bound back into its
+/// placeholders as literals of `dialect`
+#[pyfunction(name = "bind")]
+#[pyo3(signature = (normalized, params, dialect=None, *, placeholder=None, placeholder_start=None))]
+fn bind_params(
+    normalized: &str,
+    params: Vec<Bound<'_, PyAny>>,
+    dialect: Option<&str>,
+    placeholder: Option<&str>,
+    placeholder_start: Option<usize>,
+) -> PyResult<String> {
+    let defaults = config::defaults();
+    let name = match canonical_dialect(dialect.or(defaults.dialect.as_deref()).unwrap_or("generic")).map_err(options_error)? {
+        "auto" => detect::detect(normalized),
+        name => name,
+    };
+    let dialect = get_dialect(name).map_err(options_error)?;
+    let placeholder = match placeholder {
+        Some(placeholder) => placeholder,
+        None => style_placeholder(defaults.placeholder.as_deref(), defaults.style.as_deref()).map_err(PyValueError::new_err)?.unwrap_or("?"),
+    };
+    let params = params.iter().map(|value| literals::literal(value, &*dialect, name)).collect::<PyResult<Vec<_>>>()?;
+    let start = placeholder_start.or(defaults.placeholder_start).unwrap_or(1);
+    bind::bind(normalized, &*dialect, placeholder, start, &params).map_err(PyValueError::new_err)
+}
+
+/// How alike `sql_a` and `sql_b` are once normalized: a score from 0 to 1
+/// and the clauses that differ
+#[pyfunction(name = "similarity")]
+#[pyo3(signature = (sql_a, sql_b, *, dialect=None, placeholder=None, compat=None))]
+fn similarity_score<'py>(
+    py: Python<'py>,
+    sql_a: &str,
+    sql_b: &str,
+    dialect: Option<&str>,
+    placeholder: Option<&str>,
+    compat: Option<&str>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let defaults = config::defaults();
+    // Statements that cannot be fully normalized are still compared
+    let args = config::Config {
+        dialect: dialect.map(str::to_string),
+        placeholder: placeholder.map(str::to_string),
+        compat: compat.map(str::to_string),
+        strict: Some(false),
+        ..Default::default()
+    };
+    let engine = engine(&defaults, &args, None)?;
+    let options = &engine.options;
+    let normalized = |sql: &str| -> PyResult<Statement> {
+        let mut stmt = parse_first_statement(sql, &*engine.dialect)?;
+        normalize_statement::<PyErr>(
+            &mut stmt,
+            &options.placeholder,
+            options.placeholder_start,
+            &options.steps,
+            &options.rules,
+            // Only the statement is compared
+            false,
+            None,
+            options.keep,
+            &mut |_, _, value| Ok(Some(value)),
+            &mut |_, _| Ok(false),
+        )?;
+        Ok(stmt)
+    };
+    let (a, b) = (normalized(sql_a)?, normalized(sql_b)?);
+    let report = PyDict::new_bound(py);
+    report.set_item("score", similarity::score(&a, &b))?;
+    report.set_item("differing_clauses", similarity::differing_clauses(&a, &b))?;
+    Ok(report)
+}
+
+/// Per-fingerprint statement counts, checkpointed to disk with `save()` and
+/// `load()` so that collectors can resume and workers can merge their counts
+#[pyclass(module = "sqlfp")]
+struct FingerprintRegistry {
+    registry: registry::Registry,
+    engine: std::sync::Arc<pool::Engine>,
+}
+
+impl FingerprintRegistry {
+    fn with_registry(registry: registry::Registry) -> PyResult<Self> {
+        let defaults = config::defaults();
+        // Statements that cannot be fully normalized are still counted
+        let args = config::Config {
+            dialect: Some(registry.dialect.clone()),
+            compat: registry.compat.clone(),
+            strict: Some(false),
+            ..Default::default()
+        };
+        let engine = engine(&defaults, &args, None)?;
+        Ok(FingerprintRegistry { registry, engine })
+    }
+}
+
+#[pymethods]
+impl FingerprintRegistry {
+    #[new]
+    #[pyo3(signature = (*, dialect=None, compat=None, bucket_seconds=registry::DEFAULT_BUCKET_SECONDS))]
+    fn new(dialect: Option<&str>, compat: Option<&str>, bucket_seconds: u64) -> PyResult<Self> {
+        let defaults = config::defaults();
+        let dialect = canonical_dialect(dialect.or(defaults.dialect.as_deref()).unwrap_or("generic")).map_err(options_error)?;
+        let registry = registry::Registry::new(dialect, compat.or(defaults.compat.as_deref()), bucket_seconds)
+            .map_err(PyValueError::new_err)?;
+        Self::with_registry(registry)
+    }
+
+    /// Count `sql` `count` times, at `timestamp` (Unix seconds or a
+    /// `datetime`) if given; its hash, or `None` if it could not be parsed
+    #[pyo3(signature = (sql, count=1, timestamp=None))]
+    fn observe(&mut self, sql: &Bound<'_, PyAny>, count: u64, timestamp: Option<&Bound<'_, PyAny>>) -> PyResult<Option<String>> {
+        let timestamp = unix_seconds(timestamp)?;
+        let defaults = config::defaults();
+        let (encoding, errors) = encoding_args(&defaults, None, None);
+        match normalize_text(sql_text(sql, encoding, errors)?, &self.engine, None, &[]) {
+            Ok(result) => {
+                self.registry.observe(&result.hash, &result.normalized, count, timestamp);
+                Ok(Some(result.hash))
+            }
+            Err(_) => {
+                self.registry.unparsed += count;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Add the counts of `other`, which must have the same dialect and compat
+    fn merge(&mut self, other: PyRef<'_, Self>) -> PyResult<()> {
+        self.registry.merge(&other.registry).map_err(PyValueError::new_err)
+    }
+
+    fn save(&self, path: std::path::PathBuf) -> PyResult<()> {
+        Ok(std::fs::write(path, self.registry.to_jsonl())?)
+    }
+
+    #[staticmethod]
+    fn load(path: std::path::PathBuf) -> PyResult<Self> {
+        let text = std::fs::read_to_string(&path)?;
+        let registry = registry::Registry::from_jsonl(&text)
+            .map_err(|e| PyValueError::new_err(format!("{}: {}", path.display(), e)))?;
+        Self::with_registry(registry)
+    }
+
+    /// One dict per fingerprint, most observed first
+    fn entries<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let mut entries: Vec<_> = self.registry.entries.iter().collect();
+        entries.sort_by(|(a_hash, a), (b_hash, b)| b.count.cmp(&a.count).then(a_hash.cmp(b_hash)));
+        entries
+            .into_iter()
+            .map(|(hash, entry)| {
+                let item = PyDict::new_bound(py);
+                item.set_item("hash", hash)?;
+                item.set_item("normalized", &entry.normalized)?;
+                item.set_item("count", entry.count)?;
+                Ok(item)
+            })
+            .collect()
+    }
+
+    /// `(bucket start, count)` pairs of the timestamped observations of
+    /// the fingerprint `hash`, oldest first
+    fn series(&self, hash: &str) -> Vec<(i64, u64)> {
+        self.registry
+            .entries
+            .get(hash)
+            .map(|entry| entry.series.iter().map(|(&bucket, &count)| (bucket, count)).collect())
+            .unwrap_or_default()
+    }
+
+    #[getter]
+    fn dialect(&self) -> &str {
+        &self.registry.dialect
+    }
+
+    #[getter]
+    fn bucket_seconds(&self) -> u64 {
+        self.registry.bucket_seconds
+    }
+
+    #[getter]
+    fn compat(&self) -> Option<&str> {
+        self.registry.compat.as_deref()
+    }
+
+    #[getter]
+    fn unparsed(&self) -> u64 {
+        self.registry.unparsed
+    }
+
+    fn __len__(&self) -> usize {
+        self.registry.entries.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("FingerprintRegistry(dialect='{}', fingerprints={})", self.registry.dialect, self.registry.entries.len())
+    }
+}
+
+/// Per-fingerprint query stats collected in-process, for application
+/// middleware: `observe()` may be called from any number of threads, the
+/// statement being normalized without the GIL and the stats updated under a
+/// lock of their own
+#[pyclass(module = "sqlfp", frozen)]
+struct FingerprintAggregator {
+    aggregator: std::sync::Mutex<aggregate::Aggregator>,
+    engine: std::sync::Arc<pool::Engine>,
+}
+
+impl FingerprintAggregator {
+    fn aggregator(&self) -> std::sync::MutexGuard<'_, aggregate::Aggregator> {
+        self.aggregator.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// The rows of `snapshot()` and `top()`
+fn aggregate_rows<'py>(py: Python<'py>, rows: Vec<(String, aggregate::Stats)>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    rows.into_iter()
+        .map(|(hash, stats)| {
+            let timed = |duration: f64| (stats.timed > 0).then_some(duration);
+            let row = PyDict::new_bound(py);
+            row.set_item("hash", hash)?;
+            row.set_item("normalized", &stats.normalized)?;
+            row.set_item("sample", &stats.sample)?;
+            row.set_item("count", stats.count)?;
+            row.set_item("timed", stats.timed)?;
+            row.set_item("total_duration", timed(stats.total_duration))?;
+            row.set_item("min_duration", timed(stats.min_duration))?;
+            row.set_item("max_duration", timed(stats.max_duration))?;
+            row.set_item("first_seen", stats.first_seen)?;
+            row.set_item("last_seen", stats.last_seen)?;
+            Ok(row)
+        })
+        .collect()
+}
+
+#[pymethods]
+impl FingerprintAggregator {
+    #[new]
+    #[pyo3(signature = (*, dialect=None, compat=None))]
+    fn new(dialect: Option<&str>, compat: Option<&str>) -> PyResult<Self> {
+        let defaults = config::defaults();
+        // Statements that cannot be fully normalized are still counted
+        let args = config::Config {
+            dialect: dialect.map(str::to_string),
+            compat: compat.map(str::to_string),
+            strict: Some(false),
+            ..Default::default()
+        };
+        let engine = engine(&defaults, &args, None)?;
+        Ok(FingerprintAggregator { aggregator: std::sync::Mutex::new(aggregate::Aggregator::new()), engine })
+    }
+
+    /// Count `sql`, which took `duration` seconds if given; its hash, or
+    /// `None` if it could not be parsed
+    #[pyo3(signature = (sql, duration=None))]
+    fn observe(&self, py: Python<'_>, sql: &Bound<'_, PyAny>, duration: Option<f64>) -> PyResult<Option<String>> {
+        if duration.is_some_and(|d| !(d >= 0.0 && d.is_finite())) {
+            return Err(PyValueError::new_err("duration must be a finite number of seconds, at least 0"));
+        }
+        let defaults = config::defaults();
+        let (encoding, errors) = encoding_args(&defaults, None, None);
+        let sql = sql_text(sql, encoding, errors)?;
+        let engine = &self.engine;
+        let result = py.allow_threads(|| normalize_text(sql.clone(), engine, None, &[]));
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0.0, |elapsed| elapsed.as_secs_f64());
+        match result {
+            Ok(result) => {
+                self.aggregator().observe(&result.hash, &result.normalized, &sql, duration, now);
+                Ok(Some(result.hash))
+            }
+            Err(e) if e.is_instance_of::<PyKeyboardInterrupt>(py) => Err(e),
+            Err(_) => {
+                self.aggregator().unparsed += 1;
+                Ok(None)
+            }
+        }
+    }
+
+    /// One dict per fingerprint, most observed first; with `reset=True`, the
+    /// stats start over
+    #[pyo3(signature = (reset=false))]
+    fn snapshot<'py>(&self, py: Python<'py>, reset: bool) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let rows = {
+            let mut aggregator = self.aggregator();
+            let rows = aggregator.top(None, aggregate::Rank::Count);
+            if reset {
+                *aggregator = aggregate::Aggregator::new();
+            }
+            rows
+        };
+        aggregate_rows(py, rows)
+    }
+
+    /// The `n` fingerprints ranking first `by` `"count"`, `"total_duration"`
+    /// or `"max_duration"`
+    #[pyo3(signature = (n=10, by="count"))]
+    fn top<'py>(&self, py: Python<'py>, n: usize, by: &str) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let rank = aggregate::Rank::parse(by).map_err(PyValueError::new_err)?;
+        let rows = self.aggregator().top(Some(n), rank);
+        aggregate_rows(py, rows)
+    }
+
+    #[getter]
+    fn dialect(&self) -> &str {
+        self.engine.dialect_name
+    }
+
+    #[getter]
+    fn unparsed(&self) -> u64 {
+        self.aggregator().unparsed
+    }
+
+    fn __len__(&self) -> usize {
+        self.aggregator().entries.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("FingerprintAggregator(dialect='{}', fingerprints={})", self.engine.dialect_name, self.__len__())
+    }
+}
+
+/// `timestamp` in Unix seconds, given as such or as a `datetime`
+fn unix_seconds(timestamp: Option<&Bound<'_, PyAny>>) -> PyResult<Option<f64>> {
+    match timestamp {
+        Some(ts) if ts.is_none() => Ok(None),
+        Some(ts) if ts.hasattr("timestamp")? => Ok(Some(ts.call_method0("timestamp")?.extract::<f64>()?)),
+        Some(ts) => Ok(Some(ts.extract::<f64>()?)),
+        None => Ok(None),
+    }
+}
+
+/// Fingerprints of a query log grouped per connection, with the context that
+/// `USE` and `SET` statements give each session
+#[pyclass(module = "sqlfp")]
+struct SessionTracker {
+    sessions: session::Sessions,
+    engine: std::sync::Arc<pool::Engine>,
+}
+
+/// A `SessionTracker` connection id, a `str` or an `int`
+fn connection_id(id: &Bound<'_, PyAny>) -> PyResult<session::ConnectionId> {
+    if id.is_instance_of::<PyString>() {
+        return Ok(session::ConnectionId::Str(id.extract()?));
+    }
+    match id.extract::<i64>() {
+        Ok(n) => Ok(session::ConnectionId::Int(n)),
+        Err(_) => Err(PyTypeError::new_err("connection_id must be a str or an int")),
+    }
+}
+
+/// A session as returned by `SessionTracker.sessions()`
+fn session_dict<'py>(py: Python<'py>, id: &session::ConnectionId, session: &session::Session) -> PyResult<Bound<'py, PyDict>> {
+    let item = PyDict::new_bound(py);
+    match id {
+        session::ConnectionId::Int(n) => item.set_item("connection_id", n)?,
+        session::ConnectionId::Str(s) => item.set_item("connection_id", s)?,
+    }
+    item.set_item("database", &session.context.database)?;
+    let settings = PyDict::new_bound(py);
+    for (name, value) in &session.context.settings {
+        settings.set_item(name, value)?;
+    }
+    item.set_item("settings", settings)?;
+    item.set_item("first_seen", session.first_seen)?;
+    item.set_item("last_seen", session.last_seen)?;
+    item.set_item("statements", session.statements)?;
+    item.set_item("unparsed", session.unparsed)?;
+    let fingerprints = session
+        .fingerprints
+        .iter()
+        .map(|fingerprint| {
+            let entry = PyDict::new_bound(py);
+            entry.set_item("hash", &fingerprint.hash)?;
+            entry.set_item("normalized", &fingerprint.normalized)?;
+            entry.set_item("database", &fingerprint.database)?;
+            entry.set_item("count", fingerprint.count)?;
+            entry.set_item("first_seen", fingerprint.first_seen)?;
+            entry.set_item("last_seen", fingerprint.last_seen)?;
+            Ok(entry)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    item.set_item("fingerprints", fingerprints)?;
+    Ok(item)
+}
+
+#[pymethods]
+impl SessionTracker {
+    #[new]
+    #[pyo3(signature = (*, dialect=None, compat=None))]
+    fn new(dialect: Option<&str>, compat: Option<&str>) -> PyResult<Self> {
+        let defaults = config::defaults();
+        // Statements that cannot be fully normalized are still counted
+        let args = config::Config {
+            dialect: dialect.map(str::to_string),
+            compat: compat.map(str::to_string),
+            strict: Some(false),
+            ..Default::default()
+        };
+        let engine = engine(&defaults, &args, None)?;
+        Ok(SessionTracker { sessions: session::Sessions::default(), engine })
+    }
+
+    /// Observe `sql`, run on `connection_id` at `timestamp` (Unix seconds,
+    /// a `datetime` or `None`); its hash, or `None` for context statements
+    /// and statements that could not be parsed
+    fn observe(&mut self, connection_id: &Bound<'_, PyAny>, timestamp: &Bound<'_, PyAny>, sql: &Bound<'_, PyAny>) -> PyResult<Option<String>> {
+        let id = self::connection_id(connection_id)?;
+        let timestamp = unix_seconds(Some(timestamp))?;
+        let defaults = config::defaults();
+        let (encoding, errors) = encoding_args(&defaults, None, None);
+        let Ok(result) = normalize_text(sql_text(sql, encoding, errors)?, &self.engine, None, &[]) else {
+            self.sessions.unparsed(&id, timestamp);
+            return Ok(None);
+        };
+        // Only `USE` and `SET` are parsed again, for their unnormalized values
+        let context = matches!(result.statement_type.as_str(), "Use" | "Set")
+            && parse_first_statement(&result.original, &*self.engine.dialect)
+                .is_ok_and(|stmt| self.sessions.apply_context(&id, timestamp, &stmt));
+        if context {
+            return Ok(None);
+        }
+        self.sessions.observe(&id, timestamp, &result.hash, &result.normalized);
+        Ok(Some(result.hash))
+    }
+
+    /// Observe each `(connection_id, timestamp, sql)` record of `records`;
+    /// the number observed
+    fn ingest(&mut self, records: &Bound<'_, PyAny>) -> PyResult<usize> {
+        let mut observed = 0;
+        for (i, record) in records.iter()?.enumerate() {
+            if i % BATCH_CHUNK == 0 {
+                records.py().check_signals()?;
+            }
+            let (connection_id, timestamp, sql) = record?
+                .extract::<(Bound<'_, PyAny>, Bound<'_, PyAny>, Bound<'_, PyAny>)>()
+                .map_err(|_| PyTypeError::new_err("ingest() records must be (connection_id, timestamp, sql) tuples"))?;
+            self.observe(&connection_id, &timestamp, &sql)?;
+            observed += 1;
+        }
+        Ok(observed)
+    }
+
+    /// One dict per open session, in the order they started
+    fn sessions<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        self.sessions.list().into_iter().map(|(id, session)| session_dict(py, id, session)).collect()
+    }
+
+    /// End the session of `connection_id`, so that its next statement starts
+    /// a new one; the session as in `sessions()`, or `None` if none was open
+    fn close<'py>(&mut self, py: Python<'py>, connection_id: &Bound<'py, PyAny>) -> PyResult<Option<Bound<'py, PyDict>>> {
+        let id = self::connection_id(connection_id)?;
+        self.sessions.close(&id).map(|session| session_dict(py, &id, &session)).transpose()
+    }
+
+    #[getter]
+    fn dialect(&self) -> &str {
+        self.engine.dialect_name
+    }
+
+    fn __len__(&self) -> usize {
+        self.sessions.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("SessionTracker(dialect='{}', sessions={})", self.engine.dialect_name, self.sessions.len())
+    }
+}
+
+/// Fingerprint a statement as passed to a DB-API `execute()`, merging the
+/// driver-bound parameters with the literals extracted from it
+#[pyfunction]
+#[pyo3(signature = (statement, parameters=None, paramstyle="format", dialect=None, compat=None))]
+fn fingerprint_execute<'py>(
+    py: Python<'py>,
+    statement: &str,
+    parameters: Option<&Bound<'py, PyAny>>,
+    paramstyle: &str,
+    dialect: Option<&str>,
+    compat: Option<&str>,
+) -> PyResult<(NormalizeResult, Vec<Bound<'py, PyDict>>)> {
+    let style = paramstyle::Style::parse(paramstyle).map_err(PyValueError::new_err)?;
+    let defaults = config::defaults();
+    // Bound parameters become `?` like the literals, so that a statement gets
+    // the same fingerprint whether its values are bound or inlined; every
+    // literal is bound back, so none is left out of `params`
+    let args = config::Config {
+        dialect: dialect.map(str::to_string),
+        placeholder: Some("?".to_string()),
+        compat: compat.map(str::to_string),
+        max_params: Some(usize::MAX),
+        ..Default::default()
+    };
+    let engine = engine(&defaults, &args, None)?;
+    let dialect = &*engine.dialect;
+    // Without parameters, drivers send the statement as is
+    let Some(parameters) = parameters.filter(|p| !p.is_none()) else {
+        let result = normalize_text(statement.to_string(), &engine, None, &[])?;
+        let merged = literal_params(py, &result.params)?;
+        return Ok((result, merged));
+    };
+
+    // Numeric placeholders parse in every dialect; they are turned into `?`
+    // again after normalization
+    let (numbered, keys) =
+        paramstyle::convert(statement, dialect, style, paramstyle::Style::Numeric).map_err(PyValueError::new_err)?;
+    let mut result = normalize_text(numbered, &engine, None, &[])?;
+    let (normalized, order) =
+        paramstyle::bind_order(&result.normalized, dialect, paramstyle::Style::Numeric).map_err(PyValueError::new_err)?;
+    if style.is_percent() {
+        // Drivers unescape `%%` everywhere, string literals included
+        for param in &mut result.params {
+            *param = param.replace("%%", "%");
+        }
+    }
+
+    let mut literals = literal_params(py, &result.params)?.into_iter();
+    let mut merged = Vec::with_capacity(order.len());
+    for entry in order {
+        let key = match entry {
+            Some(paramstyle::ParamKey::Position(number)) => number.checked_sub(1).and_then(|i| keys.get(i)),
+            _ => None,
+        };
+        let Some(key) = key else {
+            merged.extend(literals.next());
+            continue;
+        };
+        let item = PyDict::new_bound(py);
+        match key {
+            paramstyle::ParamKey::Position(n) => {
+                item.set_item("value", parameters.get_item(n - 1)?)?;
+                item.set_item("key", n - 1)?;
+            }
+            paramstyle::ParamKey::Name(name) => {
+                item.set_item("value", parameters.get_item(name)?)?;
+                item.set_item("key", name)?;
+            }
+        }
+        item.set_item("bound", true)?;
+        merged.push(item);
+    }
+    let hashed = |sql: &str| -> PyResult<String> {
+        let sql = hashed_text(&engine, sql).map_err(PyValueError::new_err)?;
+        let (sql, _) =
+            paramstyle::bind_order(&sql, dialect, paramstyle::Style::Numeric).map_err(PyValueError::new_err)?;
+        Ok(compute_hash(&engine.options.hasher, &sql))
+    };
+    result.hash = hashed(&result.normalized)?;
+    result.shape_hash = match result.shape {
+        Some(ref shape) => hashed(shape)?,
+        None => result.hash.clone(),
+    };
+    if let Some(ref mut shape) = result.shape {
+        *shape = paramstyle::bind_order(shape, dialect, paramstyle::Style::Numeric).map_err(PyValueError::new_err)?.0;
+    }
+    result.normalized = normalized;
+    result.original = statement.to_string();
+    Ok((result, merged))
+}
+
+/// Entries of the `fingerprint_execute()` params view for extracted literals
+fn literal_params<'py>(py: Python<'py>, params: &[String]) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    params
+        .iter()
+        .map(|value| {
+            let item = PyDict::new_bound(py);
+            item.set_item("value", value)?;
+            item.set_item("key", py.None())?;
+            item.set_item("bound", false)?;
+            Ok(item)
+        })
+        .collect()
+}
+
+/// `io::Read` over a Python file object opened in binary or text mode
+struct PyReader<'py> {
+    file: Bound<'py, PyAny>,
+    /// Encoded text returned by `read()` beyond what the caller asked for
+    pending: Vec<u8>,
+    /// The exception raised by `read()`, to re-raise instead of an `OSError`
+    error: Option<PyErr>,
+}
+
+impl std::io::Read for PyReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pending.is_empty() {
+            let chunk = self.file.call_method1("read", (buf.len(),)).and_then(|chunk| {
+                if chunk.is_instance_of::<PyString>() {
+                    Ok(chunk.extract::<String>()?.into_bytes())
+                } else {
+                    Ok(chunk.downcast::<PyBytes>()?.as_bytes().to_vec())
+                }
+            });
+            match chunk {
+                Ok(chunk) => self.pending = chunk,
+                Err(e) => {
+                    self.error = Some(e);
+                    return Err(std::io::Error::other("read() failed"));
+                }
+            }
+        }
+        let n = buf.len().min(self.pending.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        Ok(n)
+    }
+}
+
+/// Fingerprint a statement too large to parse, without building an AST or
+/// keeping its tokens
+#[pyfunction]
+#[pyo3(signature = (source, dialect=None, *, chunk_size=65536))]
+fn fingerprint_stream(source: &Bound<'_, PyAny>, dialect: Option<&str>, chunk_size: usize) -> PyResult<String> {
+    let defaults = config::defaults();
+    let dialect = get_dialect(dialect.or(defaults.dialect.as_deref()).unwrap_or("generic")).map_err(options_error)?;
+    let mut reader = None;
+    let hash = if let Ok(bytes) = source.downcast::<PyBytes>() {
+        stream::fingerprint(bytes.as_bytes(), dialect, chunk_size)
+    } else if source.is_instance_of::<PyString>() {
+        stream::fingerprint(source.extract::<String>()?.as_bytes(), dialect, chunk_size)
+    } else if source.hasattr("read")? {
+        let file = reader.insert(PyReader { file: source.clone(), pending: Vec::new(), error: None });
+        stream::fingerprint(file, dialect, chunk_size)
+    } else {
+        let path: std::path::PathBuf = source.extract()?;
+        stream::fingerprint(std::fs::File::open(path)?, dialect, chunk_size)
+    };
+    if let Some(error) = reader.and_then(|r| r.error) {
+        return Err(error);
+    }
+    hash.map_err(stream_error)
+}
+
+/// A `stream` error as raised: unterminated constructs as `ValueError`
+fn stream_error(e: std::io::Error) -> PyErr {
+    match e.kind() {
+        std::io::ErrorKind::InvalidData => PyValueError::new_err(e.to_string()),
+        _ => e.into(),
+    }
+}
+
+/// `fingerprint_stream()` for statements arriving in chunks, as a proxy sees
+/// them on the wire
+#[pyclass(module = "sqlfp")]
+struct StreamFingerprinter {
+    fingerprinter: stream::Fingerprinter,
+}
+
+#[pymethods]
+impl StreamFingerprinter {
+    #[new]
+    #[pyo3(signature = (dialect=None))]
+    fn new(dialect: Option<&str>) -> PyResult<Self> {
+        let defaults = config::defaults();
+        let dialect = get_dialect(dialect.or(defaults.dialect.as_deref()).unwrap_or("generic")).map_err(options_error)?;
+        Ok(StreamFingerprinter { fingerprinter: stream::Fingerprinter::new(dialect) })
+    }
+
+    /// The fingerprints of the statements `chunk` ends
+    fn feed(&mut self, chunk: &Bound<'_, PyAny>) -> PyResult<Vec<String>> {
+        let statements = if let Ok(bytes) = chunk.downcast::<PyBytes>() {
+            self.fingerprinter.feed(bytes.as_bytes())
+        } else {
+            self.fingerprinter.feed(chunk.extract::<String>()?.as_bytes())
+        };
+        statements.map_err(stream_error)
+    }
+
+    /// The fingerprint of the statement left without a `;`, if any, starting
+    /// over for a new stream
+    fn finish(&mut self) -> PyResult<Option<String>> {
+        self.fingerprinter.finish().map_err(stream_error)
+    }
+}
+
+/// Normalize `sql`, then normalize its normalized text again, and report
+/// whether that is a fixed point
+#[pyfunction]
+#[pyo3(signature = (sql, dialect=None, placeholder=None, compat=None, enable_passes=None))]
+fn verify<'py>(
+    py: Python<'py>,
+    sql: &Bound<'py, PyAny>,
+    dialect: Option<&str>,
+    placeholder: Option<&str>,
+    compat: Option<&str>,
+    enable_passes: Option<Vec<String>>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let defaults = config::defaults();
+    let (encoding, errors) = encoding_args(&defaults, None, None);
+    let sql = sql_text(sql, encoding, errors)?;
+    let args = config::Config {
+        dialect: dialect.map(str::to_string),
+        placeholder: placeholder.map(str::to_string),
+        compat: compat.map(str::to_string),
+        enable_passes,
+        ..Default::default()
+    };
+    let engine = engine(&defaults, &args, None)?;
+    let first = normalize_text(sql, &engine, None, &[])?;
+    let report = PyDict::new_bound(py);
+    report.set_item("normalized", &first.normalized)?;
+    report.set_item("hash", hash_value(py, &first.hasher, &first.hash))?;
+    // `?` does not parse back in the dialects with `?` operators
+    // (PostgreSQL): it goes through `$_n` placeholders there
+    let dialect = &*engine.dialect;
+    let qmark = engine.options.placeholder == "?";
+    let input = if qmark {
+        paramstyle::dollar_qmarks(&first.normalized, dialect).map_err(PyValueError::new_err)?
+    } else {
+        first.normalized.clone()
+    };
+    match normalize_text(input, &engine, None, &[]) {
+        Ok(mut second) => {
+            if qmark {
+                // Hashed as `first` was, without the ignored clauses, which
+                // are found in the text that parses
+                let hashed = hashed_text(&engine, &second.normalized).map_err(PyValueError::new_err)?;
+                let hashed = paramstyle::restore_qmarks(&hashed, dialect).map_err(PyValueError::new_err)?;
+                second.normalized =
+                    paramstyle::restore_qmarks(&second.normalized, dialect).map_err(PyValueError::new_err)?;
+                second.hash = compute_hash(&engine.options.hasher, &hashed);
+            }
+            report.set_item("stable", second.normalized == first.normalized && second.hash == first.hash)?;
+            report.set_item("renormalized", second.normalized)?;
+            report.set_item("renormalized_hash", hash_value(py, &second.hasher, &second.hash))?;
+            report.set_item("error", py.None())?;
+        }
+        // The normalized text does not parse again
+        Err(e) => {
+            report.set_item("stable", false)?;
+            report.set_item("renormalized", py.None())?;
+            report.set_item("renormalized_hash", py.None())?;
+            report.set_item("error", e.value_bound(py).to_string())?;
+        }
+    }
+    Ok(report)
+}
+
+/// Process-wide counters (statements normalized, parse failures, per dialect)
+#[pyfunction(name = "stats")]
+#[pyo3(signature = (reset=false))]
+fn get_stats(py: Python<'_>, reset: bool) -> PyResult<Bound<'_, PyDict>> {
+    let snapshot = stats::snapshot(reset);
+    let dialects = PyDict::new_bound(py);
+    for (name, count) in snapshot.dialects {
+        dialects.set_item(name, count)?;
+    }
+    let out = PyDict::new_bound(py);
+    out.set_item("statements_normalized", snapshot.statements_normalized)?;
+    out.set_item("parse_failures", snapshot.parse_failures)?;
+    out.set_item("cache_hits", snapshot.cache_hits)?;
+    out.set_item("dialects", dialects)?;
+    Ok(out)
 }
 
 #[pymodule]
 fn sqlfp(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add("FINGERPRINT_VERSION", writer::FINGERPRINT_VERSION)?;
     m.add_function(wrap_pyfunction!(normalize, m)?)?;
-    // m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add_function(wrap_pyfunction!(normalize_many, m)?)?;
+    m.add_function(wrap_pyfunction!(normalize_file, m)?)?;
+    m.add_function(wrap_pyfunction!(normalize_script, m)?)?;
+    m.add_function(wrap_pyfunction!(results_to_msgpack, m)?)?;
+    m.add_function(wrap_pyfunction!(restore_result, m)?)?;
+    m.add_function(wrap_pyfunction!(script_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_ast, m)?)?;
+    m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add_function(wrap_pyfunction!(rewrite_sql, m)?)?;
+    m.add_function(wrap_pyfunction!(retarget, m)?)?;
+    m.add_function(wrap_pyfunction!(convert_paramstyle, m)?)?;
+    m.add_function(wrap_pyfunction!(strip_comments, m)?)?;
+    m.add_function(wrap_pyfunction!(redact, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_tables, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_columns, m)?)?;
+    m.add_function(wrap_pyfunction!(get_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(configure, m)?)?;
+    m.add_function(wrap_pyfunction!(engine_pool, m)?)?;
+    m.add_function(wrap_pyfunction!(clear_engine_pool, m)?)?;
+    m.add_function(wrap_pyfunction!(snapshot_corpus, m)?)?;
+    m.add_function(wrap_pyfunction!(export_bundle, m)?)?;
+    m.add_function(wrap_pyfunction!(diff_corpora, m)?)?;
+    m.add_function(wrap_pyfunction!(similarity_score, m)?)?;
+    m.add_function(wrap_pyfunction!(bind_params, m)?)?;
+    m.add_function(wrap_pyfunction!(fingerprint_execute, m)?)?;
+    m.add_function(wrap_pyfunction!(fingerprint_stream, m)?)?;
+    m.add_function(wrap_pyfunction!(fingerprint_mysql_query, m)?)?;
+    m.add_function(wrap_pyfunction!(fingerprint_postgres_message, m)?)?;
+    m.add_function(wrap_pyfunction!(fingerprint_mysql_slowlog, m)?)?;
+    m.add_function(wrap_pyfunction!(fingerprint_postgres_csvlog, m)?)?;
+    m.add_function(wrap_pyfunction!(verify, m)?)?;
     m.add_class::<NormalizeResult>()?;
+    m.add_class::<LeanResult>()?;
+    m.add_class::<QueryMetrics>()?;
+    m.add_class::<StreamFingerprinter>()?;
+    m.add_class::<AstNode>()?;
+    m.add_class::<FileResults>()?;
+    m.add_class::<LogResults>()?;
+    m.add_class::<FingerprintRegistry>()?;
+    m.add_class::<FingerprintAggregator>()?;
+    m.add_class::<SessionTracker>()?;
+    m.add_class::<Normalizer>()?;
+    m.add_class::<NormalizeOptions>()?;
+    m.add("Error", m.py().get_type_bound::<Error>())?;
+    m.add("ParseError", m.py().get_type_bound::<ParseError>())?;
+    m.add("UnsupportedDialectError", m.py().get_type_bound::<UnsupportedDialectError>())?;
+    m.add("EmptyStatementError", m.py().get_type_bound::<EmptyStatementError>())?;
+    m.add("UnnormalizableError", m.py().get_type_bound::<UnnormalizableError>())?;
+    m.add("PlaceholderCollisionError", m.py().get_type_bound::<PlaceholderCollisionError>())?;
     Ok(())
 }
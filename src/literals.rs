@@ -0,0 +1,89 @@
+//! Python values written as SQL literals, for `bind()` and
+//! `NormalizeResult.bind()`.
+//!
+//! Values are quoted and escaped for the dialect they are bound into, so
+//! that the statement runs with them as it ran with its parameters.
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyByteArray, PyBytes, PyFloat, PyInt, PyString};
+use sqlparser::dialect::Dialect;
+
+/// `text` as a string literal, its quotes doubled and, where the dialect
+/// reads them as escapes, its backslashes too
+fn quote(text: &str, backslashes: bool) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('\'');
+    for c in text.chars() {
+        match c {
+            '\'' => out.push_str("''"),
+            '\\' if backslashes => out.push_str("\\\\"),
+            _ => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+/// `bytes` as a binary literal of the dialect `name`
+fn binary(bytes: &[u8], name: &str) -> String {
+    let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    match name {
+        "postgresql" | "redshift" => format!("'\\x{}'::BYTEA", hex),
+        "mssql" => format!("0x{}", hex),
+        "bigquery" => format!("FROM_HEX('{}')", hex),
+        _ => format!("X'{}'", hex),
+    }
+}
+
+/// The Python value `value` as a literal of `dialect`, named `name`:
+/// `None` is `NULL`, booleans `TRUE`/`FALSE` (`1`/`0` where the dialect has
+/// no boolean literals), numbers are written as Python does, `bytes` as a
+/// binary literal, and strings, dates and times are quoted
+pub(crate) fn literal(value: &Bound<'_, PyAny>, dialect: &dyn Dialect, name: &str) -> PyResult<String> {
+    let py = value.py();
+    if value.is_none() {
+        return Ok("NULL".to_string());
+    }
+    if let Ok(flag) = value.downcast::<PyBool>() {
+        let flag = flag.is_true();
+        return Ok(match name {
+            "mssql" | "oracle" => if flag { "1" } else { "0" }.to_string(),
+            _ => if flag { "TRUE" } else { "FALSE" }.to_string(),
+        });
+    }
+    if value.is_instance_of::<PyInt>() {
+        return Ok(value.str()?.to_string());
+    }
+    if let Ok(float) = value.downcast::<PyFloat>() {
+        if !float.value().is_finite() {
+            return Err(PyValueError::new_err(format!("Cannot bind {} as a SQL literal", value.repr()?)));
+        }
+        return Ok(value.repr()?.to_string());
+    }
+    if let Ok(text) = value.downcast::<PyString>() {
+        return Ok(quote(&text.to_cow()?, dialect.supports_string_literal_backslash_escape()));
+    }
+    if let Ok(bytes) = value.downcast::<PyBytes>() {
+        return Ok(binary(bytes.as_bytes(), name));
+    }
+    if let Ok(bytes) = value.downcast::<PyByteArray>() {
+        return Ok(binary(&bytes.to_vec(), name));
+    }
+    if value.is_instance(&py.import_bound("decimal")?.getattr("Decimal")?)? {
+        if !value.call_method0("is_finite")?.is_truthy()? {
+            return Err(PyValueError::new_err(format!("Cannot bind {} as a SQL literal", value.repr()?)));
+        }
+        return Ok(value.str()?.to_string());
+    }
+    let datetime = py.import_bound("datetime")?;
+    for kind in ["date", "time", "timedelta"] {
+        if value.is_instance(&datetime.getattr(kind)?)? {
+            return Ok(quote(&value.str()?.to_string(), false));
+        }
+    }
+    Err(PyTypeError::new_err(format!(
+        "Cannot bind a value of type {} as a SQL literal",
+        value.get_type().name()?
+    )))
+}
@@ -0,0 +1,110 @@
+//! Python values of the extracted literals, for `NormalizeResult.typed_params`.
+//!
+//! `params` hold the literals as written but without the prefix of a prefixed
+//! string (`'00'` for `X'00'`, `'abc'` for `N'abc'`), which is in
+//! `param_types` and needed to rebuild them. Drivers bind values instead, so
+//! each one is read back from its text, prefix (`param_types`) and kind
+//! (`param_kinds`): numbers become `int` or `float`, `TRUE`/`FALSE` `bool`,
+//! `NULL` (with `keep_null=False`) `None`, hex strings `bytes`, and other
+//! strings `str` without their quotes and escapes. Text that is none of
+//! these, such as what a `redactor` returned, is kept as a `str`.
+
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+/// The contents of `text` quoted with `quote` at both ends, its doubled
+/// quotes undone
+fn unquote(text: &str, quote: char) -> Option<String> {
+    let inner = text.strip_prefix(quote)?.strip_suffix(quote)?;
+    let doubled = format!("{quote}{quote}");
+    Some(inner.replace(&doubled, &quote.to_string()))
+}
+
+/// `text` with its C-style backslash escapes (`E'...'`) undone
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('b') => out.push('\u{8}'),
+            Some('f') => out.push('\u{c}'),
+            Some('0') => out.push('\0'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// The body of a dollar-quoted string (`$tag$...$tag$`)
+fn dollar_quoted(text: &str) -> Option<&str> {
+    let rest = text.strip_prefix('$')?;
+    let tag = &rest[..rest.find('$')?];
+    let delimiter = format!("${tag}$");
+    text.strip_prefix(&delimiter)?.strip_suffix(&delimiter)
+}
+
+/// The bytes of the hex digits `text`
+fn hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok()).collect()
+}
+
+/// The contents of a string parameter, with its quotes and escapes undone
+fn string(text: &str, prefix: Option<&str>) -> Option<String> {
+    if let Some(body) = dollar_quoted(text) {
+        return Some(body.to_string());
+    }
+    let value = unquote(text, '\'').or_else(|| unquote(text, '"'))?;
+    Some(if prefix == Some("E") { unescape(&value) } else { value })
+}
+
+/// The Python value of the parameter `text`, of prefix `prefix` and kind `kind`
+pub(crate) fn value(py: Python<'_>, text: &str, prefix: Option<&str>, kind: Option<&str>) -> PyResult<PyObject> {
+    match kind {
+        Some("integer") => {
+            if let Ok(n) = text.parse::<i64>() {
+                return Ok(n.into_py(py));
+            }
+            // Beyond 64 bits, Python's own parsing
+            if let Ok(n) = py.get_type_bound::<pyo3::types::PyLong>().call1((text,)) {
+                return Ok(n.unbind());
+            }
+        }
+        Some(_) => {
+            if let Ok(f) = text.parse::<f64>() {
+                return Ok(f.into_py(py));
+            }
+        }
+        None => {}
+    }
+    if prefix.is_none() {
+        if text.eq_ignore_ascii_case("true") {
+            return Ok(true.into_py(py));
+        }
+        if text.eq_ignore_ascii_case("false") {
+            return Ok(false.into_py(py));
+        }
+        if text.eq_ignore_ascii_case("null") {
+            return Ok(py.None());
+        }
+    }
+    let Some(value) = string(text, prefix) else {
+        return Ok(text.into_py(py));
+    };
+    if prefix == Some("X") {
+        if let Some(bytes) = hex(&value) {
+            return Ok(PyBytes::new_bound(py, &bytes).into_py(py));
+        }
+    }
+    Ok(value.into_py(py))
+}
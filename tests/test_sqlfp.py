@@ -1,6 +1,12 @@
-from hashlib import sha256
+from hashlib import md5, sha256
+import copy
+import datetime
+import decimal
+import io
 import json
 from pathlib import Path
+import pickle
+import threading
 
 import pytest
 
@@ -97,6 +103,13 @@ CASES = [
             "SELECT * FROM users WHERE (role = 'admin' OR role = 'notstaff') AND is_active = true;",
             "SELECT * FROM users WHERE (role = 'bob' OR role = 'staff') AND is_active = tRue;",
             "SELECT * FROM users WHERE (role = 'john' OR role = 'lead') AND is_active = FaLse;",
+        ],
+    },
+    {
+        "name": "double-quoted strings (mysql/bigquery only)",
+        "dialects": ["mysql", "bigquery"],
+        "variants": [
+            "SELECT * FROM users WHERE (role = 'admin' OR role = 'notstaff') AND is_active = true;",
             'SELECT * FROM users WHERE (role = "ignacio" OR role = "stuff") AND is_active = TrUe;',
         ],
     },
@@ -1997,6 +2010,16 @@ def test_sqlfp_query_normalization(case, dialect):
 
 
 
+@pytest.mark.parametrize("case", CASES, ids=lambda c: _slug(c["name"]))
+def test_sqlfp_verify_corpus(case):
+    for dialect in DIALECTS:
+        if dialect not in case["dialects"]:
+            continue
+        for sql in case["variants"]:
+            report = sqlfp.verify(sql, dialect=dialect)
+            assert report["stable"], (dialect, sql, report)
+
+
 def test_sqlfp_hashed_refs():
     ref_path = Path(__file__).parent / "hashes_refs.txt"
 
@@ -2010,6 +2033,7 @@ def test_sqlfp_hashed_refs():
     for case in CASES:
         cases[case["name"]] = case
 
+    changed = []
     for c, case_name in enumerate(refs):
         expected_hash = refs[case_name]["hash"]
         sql = cases[case_name]["variants"][0]
@@ -2022,8 +2046,10 @@ def test_sqlfp_hashed_refs():
             print(f"## query {c} {sql}")
             print(f"## hash {c}\n    expected: {expected_hash}\n    current:  {current_hash}")
             print(f"## normalized\n    expected: {expected_normalized}\n     current: {current_normalized}")
+            changed.append(case_name)
         else:
             print(f"## query {c} OK")
+    assert changed == []
 
 
 def test_sqlfp_result_type():
@@ -2038,6 +2064,66 @@ def test_sqlfp_version():
     assert len(sqlfp.__version__) > 0
 
 
+
+# Constructs whose layout sqlparser releases have changed before. The texts
+# are pinned for FINGERPRINT_VERSION 1; see "Upgrading sqlparser" in
+# CONTRIBUTING.md before changing them.
+@pytest.mark.parametrize(
+    "sql, dialect, normalized",
+    [
+        (
+            "SELECT CAST(a AS INT), CAST(b AS TEXT) FROM t JOIN u USING (id) WHERE c IN (1, 2) AND d BETWEEN 1 AND 2",
+            "postgres",
+            "SELECT CAST(a AS INTEGER), CAST(b AS TEXT) FROM t JOIN u USING(id) WHERE c IN (?, ?) AND d BETWEEN ? AND ?",
+        ),
+        (
+            "SELECT count(*) OVER (PARTITION BY a ORDER BY b DESC) FROM t GROUP BY a HAVING sum(b) > 1 LIMIT 10 OFFSET 5",
+            "generic",
+            "SELECT COUNT(*) OVER (PARTITION BY a ORDER BY b DESC) FROM t GROUP BY a HAVING SUM(b) > ? LIMIT ? OFFSET ?",
+        ),
+        (
+            "WITH r AS (SELECT 1) SELECT * FROM r UNION ALL SELECT x FROM (SELECT x FROM s) AS d",
+            "generic",
+            "WITH r AS (SELECT ?) SELECT * FROM r UNION ALL SELECT x FROM (SELECT x FROM s) d",
+        ),
+        (
+            "INSERT INTO t (a, b) VALUES (1, 'x') ON CONFLICT (a) DO UPDATE SET b = EXCLUDED.b RETURNING a",
+            "postgres",
+            "INSERT INTO t (a, b) VALUES (?, ?) ON CONFLICT(a) DO UPDATE SET b = EXCLUDED.b RETURNING a",
+        ),
+        (
+            "UPDATE t SET a = CASE WHEN b IS NULL THEN 1 ELSE 2 END WHERE EXISTS (SELECT 1 FROM u WHERE u.id = t.id)",
+            "generic",
+            "UPDATE t SET a = CASE WHEN b IS NULL THEN ? ELSE ? END WHERE EXISTS (SELECT ? FROM u WHERE u.id = t.id)",
+        ),
+        (
+            "DELETE FROM t WHERE a LIKE 'x%' AND NOT b AND c <> INTERVAL '1 day'",
+            "postgres",
+            "DELETE FROM t WHERE a LIKE ? AND NOT b AND c <> INTERVAL ?",
+        ),
+        (
+            "SELECT count(DISTINCT a) FILTER (WHERE b > 1) FROM t NATURAL JOIN u CROSS JOIN v ORDER BY 1 DESC NULLS LAST",
+            "postgres",
+            "SELECT COUNT(DISTINCT a) FILTER (WHERE b > ?) FROM t NATURAL JOIN u CROSS JOIN v ORDER BY ? DESC NULLS LAST",
+        ),
+        (
+            "INSERT INTO t SET a = 1, b = 'x'",
+            "mysql",
+            "INSERT INTO t SET a = ?, b = ?",
+        ),
+        (
+            "SELECT * FROM t, LATERAL (SELECT 1) AS s WHERE a NOT IN (SELECT b FROM u) LIMIT 5, 10",
+            "mysql",
+            "SELECT * FROM t, LATERAL (SELECT ?) s WHERE a NOT IN (SELECT b FROM u) LIMIT ?, ?",
+        ),
+    ],
+)
+def test_sqlfp_fingerprint_version(sql, dialect, normalized):
+    assert sqlfp.FINGERPRINT_VERSION == 1
+    result = sqlfp.normalize(sql, dialect=dialect)
+    assert result.normalized == normalized
+    assert result.hash == sha256(normalized.encode()).hexdigest()
+
 def test_sqlfp_repr():
     result = sqlfp.normalize("SELECT * FROM users WHERE id = 123")
     r = repr(result)
@@ -2099,3 +2185,4578 @@ def test_sqlfp_repr_50():
 def test_sqlfp_no_statment():
     with pytest.raises(ValueError, match="No SQL statement found"):
         sqlfp.normalize("")
+
+
+def test_sqlfp_redactor_clauses():
+    seen = []
+
+    def redactor(index, clause, value):
+        seen.append((index, clause, value))
+        return value
+
+    result = sqlfp.normalize(
+        "SELECT a, 'x' FROM t JOIN u ON u.k = 5 WHERE b = 'bob' "
+        "GROUP BY 7 HAVING COUNT(*) > 2 ORDER BY 1 LIMIT 10 OFFSET 4",
+        dialect="postgres",
+        redactor=redactor,
+    )
+    assert result.params == ["'x'", "5", "'bob'", "7", "2", "1", "10", "4"]
+    assert seen == [
+        (0, "select", "'x'"),
+        (1, "join", "5"),
+        (2, "where", "'bob'"),
+        (3, "group_by", "7"),
+        (4, "having", "2"),
+        (5, "order_by", "1"),
+        (6, "limit", "10"),
+        (7, "offset", "4"),
+    ]
+
+
+def test_sqlfp_redactor_transform_and_drop():
+    def redactor(index, clause, value):
+        if "@" in value:
+            return "'<email>'"
+        if clause == "limit":
+            return None
+        return value
+
+    result = sqlfp.normalize(
+        "SELECT id FROM users WHERE email = 'bob@example.com' AND country = 'FR' LIMIT 10",
+        redactor=redactor,
+    )
+    assert result.normalized == "SELECT id FROM users WHERE email = ? AND country = ? LIMIT ?"
+    assert result.params == ["'<email>'", "'FR'"]
+
+
+def test_sqlfp_redactor_does_not_change_hash():
+    sql = "UPDATE users SET name = 'bob' WHERE id = 3"
+    plain = sqlfp.normalize(sql)
+    redacted = sqlfp.normalize(sql, redactor=lambda i, c, v: None)
+    assert redacted.hash == plain.hash
+    assert redacted.params == []
+
+
+@pytest.mark.parametrize(
+    "sql, dialect, expected",
+    [
+        (
+            "SELECT * FROM t WHERE a = 1 AND b IN (1, 2, 3) AND c = ? LIMIT 10",
+            "generic",
+            {"where": 4, "limit": 1},
+        ),
+        ("INSERT INTO t (a, b) VALUES (1, 2), (3, 4), (5, 6)", "generic", {"values": 6}),
+        ("UPDATE t SET a = 1, b = 2 WHERE id = 3 RETURNING 4", "postgres", {"set": 2, "where": 1, "returning": 1}),
+        ("SELECT a FROM t WHERE b = c", "generic", None),
+    ],
+)
+def test_sqlfp_literal_counts(sql, dialect, expected):
+    result = sqlfp.normalize(sql, dialect=dialect)
+    assert result.metadata.get("literal_counts") == expected
+    if expected is not None:
+        assert list(result.metadata["literal_counts"].items()) == list(expected.items())
+
+
+def test_sqlfp_literal_counts_include_dropped():
+    result = sqlfp.normalize("SELECT 1 FROM t WHERE a = 2 LIMIT 3", redactor=lambda i, c, v: None)
+    assert result.params == []
+    assert result.metadata["literal_counts"] == {"select": 1, "where": 1, "limit": 1}
+
+
+@pytest.mark.parametrize(
+    "sql, dialect, placeholder, mode, expected",
+    [
+        ("SELECT `?` FROM t WHERE a = 1", "mysql", "?", "report", "SELECT `?` FROM t WHERE a = ?"),
+        ("SELECT `?` FROM t WHERE a = 1", "mysql", "?", "escape", "SELECT `??` FROM t WHERE a = ?"),
+        ("SELECT `?` FROM t WHERE a = 1", "mysql", "?", "numbered", "SELECT `?` FROM t WHERE a = ?1"),
+        ("SELECT * FROM docs WHERE data ? 'tags' AND id = 1", "postgres", "?", "escape", "SELECT * FROM docs WHERE data ?? ? AND id = ?"),
+        ("SELECT * FROM docs WHERE data ? 'tags' AND id = 1", "postgres", "?", "numbered", "SELECT * FROM docs WHERE data ? ?1 AND id = ?2"),
+        ("SELECT col_p FROM t WHERE a = 1", "generic", "_p", "escape", "SELECT col__p FROM t WHERE a = _p"),
+    ],
+)
+def test_sqlfp_placeholder_collision(sql, dialect, placeholder, mode, expected):
+    result = sqlfp.normalize(sql, dialect=dialect, placeholder=placeholder, placeholder_collision=mode)
+    assert result.normalized == expected
+    assert result.hash == sha256(expected.encode()).hexdigest()
+    assert result.metadata.get("placeholder_collisions") == (None if mode == "numbered" else 1)
+
+
+def test_sqlfp_placeholder_collision_raise():
+    with pytest.raises(sqlfp.PlaceholderCollisionError, match=r'Placeholder "\?" also occurs 1 time'):
+        sqlfp.normalize("SELECT [x?y] FROM t WHERE a = 1", dialect="mssql", placeholder_collision="raise")
+    assert issubclass(sqlfp.PlaceholderCollisionError, ValueError)
+    # Numbered placeholders have no numbered variant to switch to
+    with pytest.raises(sqlfp.PlaceholderCollisionError, match="Placeholder \"@{n}\""):
+        sqlfp.normalize("SELECT a@1 FROM t WHERE b = 2", placeholder="@{n}", placeholder_collision="numbered")
+    # Binds are expected to read like the placeholder
+    result = sqlfp.normalize("SELECT x FROM t WHERE a = ? AND b = 3", placeholder_collision="raise")
+    assert result.normalized == "SELECT x FROM t WHERE a = ? AND b = ?"
+    assert "placeholder_collisions" not in result.metadata
+    with pytest.raises(ValueError, match="Unsupported placeholder_collision: nope"):
+        sqlfp.normalize("SELECT 1", placeholder_collision="nope")
+    try:
+        assert sqlfp.configure(reset=True, placeholder_collision="escape") == {"placeholder_collision": "escape"}
+        assert sqlfp.normalize("SELECT `?` FROM t", dialect="mysql").normalized == "SELECT `??` FROM t"
+    finally:
+        sqlfp.configure(reset=True)
+
+
+
+def test_sqlfp_placeholder_collision_numbered_calls_once():
+    # The placeholder is numbered before the redactor and the hooks see the
+    # statement, so that they are called once
+    seen, hooked = [], []
+    result = sqlfp.normalize(
+        "SELECT a ? b FROM t WHERE c = 5",
+        dialect="postgres",
+        placeholder_collision="numbered",
+        redactor=lambda index, clause, value: seen.append((index, value)) or value,
+        passes=[lambda sql: hooked.append(sql), "literal-parameterize"],
+    )
+    assert result.normalized == "SELECT a ? b FROM t WHERE c = ?1"
+    assert seen == [(0, "5")]
+    assert hooked == ["SELECT a ? b FROM t WHERE c = 5"]
+    # A collision a hook brings in leaves no way out
+    with pytest.raises(sqlfp.PlaceholderCollisionError):
+        sqlfp.normalize(
+            "SELECT a FROM t WHERE c = 5",
+            placeholder_collision="numbered",
+            passes=[lambda sql: 'SELECT "?" FROM t WHERE c = 5', "literal-parameterize"],
+        )
+
+def test_sqlfp_redactor_errors_propagate():
+    def redactor(index, clause, value):
+        raise RuntimeError("boom")
+
+    with pytest.raises(RuntimeError, match="boom"):
+        sqlfp.normalize("SELECT 1", redactor=redactor)
+
+
+@pytest.mark.parametrize("compat", ["0.1", "0.1.0", "0.1.4", "v0.1.4"])
+def test_sqlfp_compat_pins_rules(compat):
+    sql = "SELECT u.id FROM users AS u INNER JOIN orders o ON (o.user_id = u.id) ORDER BY u.id ASC"
+    pinned = sqlfp.normalize(sql, compat=compat)
+    assert pinned.normalized == "SELECT u.id FROM users u JOIN orders o ON o.user_id = u.id ORDER BY u.id"
+    assert pinned.hash == sqlfp.normalize(sql).hash
+
+
+def test_sqlfp_compat_unknown_version():
+    with pytest.raises(ValueError, match="Unsupported compat version: 9.9"):
+        sqlfp.normalize("SELECT 1", compat="9.9")
+
+
+@pytest.mark.parametrize(
+    "sql, expected",
+    [
+        (
+            "SELECT u.id AS user_id FROM users AS u INNER JOIN orders o ON o.user_id = u.id WHERE u.id = -5",
+            "SELECT users.id FROM users JOIN orders ON orders.user_id = users.id WHERE users.id = ?",
+        ),
+        ("SELECT COUNT(u.*) AS n, u.* FROM public.users u", "SELECT COUNT(users.*), users.* FROM public.users"),
+        ("UPDATE users u SET name = 'x' WHERE u.id = 3", "UPDATE users SET name = ? WHERE users.id = ?"),
+        ("SELECT a AS x, b AS y FROM t ORDER BY x", "SELECT a AS x, b FROM t ORDER BY x"),
+        ("SELECT a.*, b.* FROM a JOIN b ON a.id = b.id", "SELECT * FROM a JOIN b ON a.id = b.id"),
+        ("SELECT pg_catalog.lower(name) FROM t WHERE v IS NULL", "SELECT LOWER(name) FROM t WHERE v IS NULL"),
+        # Aliases that would read as another relation are kept
+        ("SELECT x.a FROM t x WHERE EXISTS (SELECT 1 FROM t WHERE t.b = x.b)", "SELECT x.a FROM t x WHERE EXISTS (SELECT ? FROM t WHERE t.b = x.b)"),
+        ("SELECT a.id FROM t a JOIN t b ON a.id = b.id", "SELECT a.id FROM t a JOIN t b ON a.id = b.id"),
+        ("SELECT s.a FROM (SELECT 1 AS a) s", "SELECT s.a FROM (SELECT ? AS a) s"),
+        # The server keeps these apart
+        ("WITH anon_1 AS (SELECT 1) SELECT * FROM anon_1", "WITH anon_1 AS (SELECT ?) SELECT * FROM anon_1"),
+        ("SELECT CONCAT(a, b), NOW() FROM t", "SELECT CONCAT(a, b), NOW() FROM t"),
+    ],
+)
+def test_sqlfp_compat_pg_stat_statements(sql, expected):
+    result = sqlfp.normalize(sql, dialect="postgres", compat="pg_stat_statements")
+    assert result.normalized == expected
+    assert isinstance(result.metadata["query_id"], int)
+    assert -(2**63) <= result.metadata["query_id"] < 2**63
+
+
+def test_sqlfp_compat_pg_stat_statements_query_id():
+    options = dict(dialect="postgres", compat="pg_stat_statements", placeholder="${n}")
+    client = sqlfp.normalize("SELECT u.id AS uid FROM users AS u WHERE u.id = -5 AND u.x IN (1, 2)", **options)
+    server = sqlfp.normalize("SELECT users.id FROM users WHERE users.id = $1 AND users.x IN ($2, $3)", **options)
+    assert client.normalized == server.normalized == "SELECT users.id FROM users WHERE users.id = $1 AND users.x IN ($2, $3)"
+    assert client.metadata["query_id"] == server.metadata["query_id"]
+    assert client.metadata["query_id"] != sqlfp.normalize("SELECT users.name FROM users", **options).metadata["query_id"]
+    # Independent of the hash settings, and absent from the other rules
+    assert sqlfp.normalize("SELECT 1", compat="pg_stat_statements").metadata["query_id"] == 1557794515124640257
+    assert sqlfp.normalize("SELECT 1", compat="pg_stat_statements", hash_encoding="base64").metadata["query_id"] == 1557794515124640257
+    assert "query_id" not in sqlfp.normalize("SELECT 1").metadata
+    assert "u.id" in sqlfp.normalize("SELECT u.id FROM users u", compat="pg_stat_statements", disable_passes=["alias-normalize"]).normalized
+
+
+
+@pytest.mark.parametrize(
+    "sql, expected",
+    [
+        ("SELECT * from foo where a = 5", "select * from foo where a = ?"),
+        ("SELECT * from foo where a in (5) and b in (5, 8,9 ,9 , 10)", "select * from foo where a in(?+) and b in(?+)"),
+        ("insert into foo(a, b, c) values(2, 4, 5) , (2,4,5)", "insert into foo(a, b, c) values(?+)"),
+        ("SELECT  a,\n\tb FROM t1 -- note\nWHERE c IS NULL AND d = TRUE", "select a, b from t? where c is ? and d = ?"),
+        ("select * from t where a = 'it\\'s' and b = \"x\" limit 5, 10", "select * from t where a = ? and b = ? limit ?"),
+        ("select a from t union all select a from t union all select a from t", "select a from t /*repeat union all*/"),
+        ("SELECT a FROM t ORDER BY a ASC, b DESC, c asc", "select a from t order by a, b desc, c"),
+        ("select 0x1f, -5 from db.tbl2", "select ?, ? from db.tbl?"),
+        ("CALL foo(1, 2)", "call foo"),
+        ("SELECT /*!40001 SQL_NO_CACHE */ * FROM `t`", "mysqldump"),
+    ],
+)
+def test_sqlfp_compat_percona(sql, expected):
+    result = sqlfp.normalize(sql, dialect="mysql", compat="percona")
+    assert result.normalized == expected
+    assert result.metadata["checksum"] == md5(expected.encode()).hexdigest()[-16:].upper()
+    assert result.hash == result.shape_hash
+
+
+def test_sqlfp_compat_percona_result():
+    result = sqlfp.normalize("SELECT * FROM t1 WHERE id IN (1, 2, 3) ORDER BY a ASC LIMIT 10", dialect="mysql", compat="percona")
+    assert result.metadata["checksum"] == "A42E6C86182A2DA3"
+    assert result.params == ["1", "2", "3", "10"]
+    assert result.hash == sqlfp.normalize("select * from t2 where id in (4) order by a limit 1", dialect="mysql", compat="percona").hash
+    assert "checksum" not in sqlfp.normalize("SELECT 1").metadata
+    with pytest.raises(ValueError):
+        sqlfp.normalize("SELECT FROM WHERE", dialect="mysql", compat="percona")
+
+
+def test_sqlfp_parse_ast_tree():
+    root = sqlfp.parse_ast("SELECT a FROM t AS x WHERE b = 1", dialect="postgres")
+    assert type(root).__name__ == "AstNode"
+    assert (root.category, root.kind) == ("statement", "Query")
+    assert root.span == (1, 1, 1, 33)
+    assert root.sql == "SELECT a FROM t AS x WHERE b = 1"
+
+    nodes = [(n.category, n.kind, n.sql) for n in root.walk()]
+    assert nodes == [
+        ("statement", "Query", "SELECT a FROM t AS x WHERE b = 1"),
+        ("query", "Query", "SELECT a FROM t AS x WHERE b = 1"),
+        ("expr", "Identifier", "a"),
+        ("table_factor", "Table", "t AS x"),
+        ("relation", "ObjectName", "t"),
+        ("expr", "BinaryOp", "b = 1"),
+        ("expr", "Identifier", "b"),
+        ("expr", "Value", "1"),
+    ]
+    where = root.walk()[5]
+    assert [c.kind for c in where.children] == ["Identifier", "Value"]
+    assert where.span == (1, 28, 1, 33)
+
+
+def test_sqlfp_parse_ast_read_only():
+    root = sqlfp.parse_ast("SELECT 1")
+    with pytest.raises(AttributeError):
+        root.kind = "Insert"
+    assert isinstance(root.children, tuple)
+
+
+def test_sqlfp_parse_ast_errors():
+    with pytest.raises(ValueError, match="No SQL statement found"):
+        sqlfp.parse_ast("")
+    with pytest.raises(ValueError, match="Parse error"):
+        sqlfp.parse_ast("SELECT * TROM", dialect="mysql")
+
+
+def test_sqlfp_parse():
+    tree = sqlfp.parse("SELECT a FROM t WHERE b = 1", dialect="postgres")
+    select = tree["Query"]["body"]["Select"]
+    assert select["from"][0]["relation"]["Table"]["name"][0]["Identifier"]["value"] == "t"
+    assert select["selection"]["BinaryOp"]["op"] == "Eq"
+    assert select["selection"]["BinaryOp"]["right"]["Value"]["value"] == {"Number": ["1", False]}
+    assert select["projection"][0]["UnnamedExpr"]["Identifier"]["span"] == {
+        "start": {"line": 1, "column": 8},
+        "end": {"line": 1, "column": 9},
+    }
+    assert json.loads(sqlfp.parse("SELECT a FROM t WHERE b = 1", "postgres", as_json=True)) == tree
+    assert list(sqlfp.parse("DELETE FROM t")) == ["Delete"]
+
+
+def test_sqlfp_parse_errors():
+    with pytest.raises(ValueError, match="No SQL statement found"):
+        sqlfp.parse("")
+    with pytest.raises(ValueError, match="Parse error"):
+        sqlfp.parse("SELECT * TROM", dialect="mysql", as_json=True)
+    with pytest.raises(ValueError, match="Unsupported dialect"):
+        sqlfp.parse("SELECT 1", "nope")
+
+
+def test_sqlfp_rewrite_rules():
+    sql, result = sqlfp.rewrite(
+        "SELECT orders.id FROM tenant_a.orders JOIN tenant_a.items i ON i.order_id = orders.id "
+        "WHERE orders.tenant_id = 5 AND status = 'paid'",
+        [
+            {"op": "rename_table", "from": "orders", "to": "orders_v2"},
+            {"op": "replace_schema", "from": "tenant_a", "to": "tenant_b"},
+            {"op": "drop_predicate", "column": "tenant_id"},
+        ],
+    )
+    assert sql == (
+        "SELECT orders_v2.id FROM tenant_b.orders_v2 JOIN tenant_b.items i "
+        "ON i.order_id = orders_v2.id WHERE status = 'paid'"
+    )
+    assert result.original == sql
+    assert result.params == ["'paid'"]
+    assert result.hash == sqlfp.normalize(sql).hash
+
+
+def test_sqlfp_rewrite_drop_only_predicate():
+    sql, result = sqlfp.rewrite(
+        "DELETE FROM events WHERE (tenant_id = 1)",
+        [{"op": "drop_predicate", "column": "tenant_id"}],
+    )
+    assert sql == "DELETE FROM events"
+    assert result.normalized == "DELETE FROM events"
+
+
+def test_sqlfp_rewrite_keeps_alias_qualifiers():
+    sql, _ = sqlfp.rewrite(
+        "SELECT orders.id FROM invoices AS orders",
+        [{"op": "rename_table", "from": "orders", "to": "orders_v2"}],
+    )
+    assert sql == "SELECT orders.id FROM invoices AS orders"
+
+
+def test_sqlfp_rewrite_invalid_rules():
+    with pytest.raises(ValueError, match="Unknown rewrite op: nope"):
+        sqlfp.rewrite("SELECT 1", [{"op": "nope"}])
+    with pytest.raises(ValueError, match="requires 'to'"):
+        sqlfp.rewrite("SELECT 1", [{"op": "rename_table", "from": "a"}])
+
+
+
+@pytest.mark.parametrize(
+    "sql, retargeted",
+    [
+        ("SELECT * FROM orders", "SELECT * FROM shadow.orders"),
+        ("SELECT * FROM sales.orders", "SELECT * FROM shadow.orders"),
+        (
+            "SELECT orders.id FROM orders JOIN sales.items ON items.oid = orders.id",
+            "SELECT shadow.orders.id FROM shadow.orders JOIN shadow_sales.items ON items.oid = shadow.orders.id",
+        ),
+        (
+            "SELECT o.id FROM invoices AS o JOIN orders ON orders.id = o.oid",
+            "SELECT o.id FROM invoices AS o JOIN shadow.orders ON shadow.orders.id = o.oid",
+        ),
+        (
+            "WITH orders AS (SELECT * FROM sales.orders) SELECT * FROM orders",
+            "WITH orders AS (SELECT * FROM shadow.orders) SELECT * FROM orders",
+        ),
+        (
+            "WITH orders AS (SELECT * FROM orders WHERE paid) SELECT orders.id FROM orders",
+            "WITH orders AS (SELECT * FROM shadow.orders WHERE paid) SELECT orders.id FROM orders",
+        ),
+        (
+            "SELECT * FROM orders WHERE id IN (WITH orders AS (SELECT 1) SELECT * FROM orders)",
+            "SELECT * FROM shadow.orders WHERE id IN (WITH orders AS (SELECT 1) SELECT * FROM orders)",
+        ),
+        ("INSERT INTO orders (id) SELECT id FROM a", "INSERT INTO shadow.orders (id) SELECT id FROM b"),
+        (
+            "UPDATE sales.items SET qty = 0 WHERE id IN (SELECT id FROM b)",
+            "UPDATE shadow_sales.items SET qty = 0 WHERE id IN (SELECT id FROM a)",
+        ),
+        ("DELETE FROM db.sales.items WHERE x = 1", "DELETE FROM db.shadow_sales.items WHERE x = 1"),
+    ],
+)
+def test_sqlfp_retarget(sql, retargeted):
+    mapping = {"orders": "shadow.orders", "sales": "shadow_sales", "a": "b", "b": "a"}
+    assert sqlfp.retarget(sql, mapping, dialect="postgres") == retargeted
+
+
+def test_sqlfp_retarget_longest_match():
+    mapping = {"orders": "orders_v2", "archive.orders": "archive.orders_old"}
+    sql = "SELECT * FROM archive.orders JOIN orders ON true"
+    assert sqlfp.retarget(sql, mapping) == "SELECT * FROM archive.orders_old JOIN orders_v2 ON true"
+    assert sqlfp.retarget("SELECT 1", {}) == "SELECT 1"
+    with pytest.raises(ValueError, match="Unsupported dialect"):
+        sqlfp.retarget("SELECT 1", {}, dialect="nope")
+
+
+def test_sqlfp_templates():
+    sql = (
+        "{{ config(materialized='table') }}\n"
+        "SELECT id, {{ col }} FROM {{ ref('orders') }}\n"
+        "{# only recent #}\n"
+        "{% if is_incremental() %}WHERE updated_at > '2024-01-01'{% endif %}"
+    )
+    result = sqlfp.normalize(sql, templates=True)
+    assert result.normalized == "SELECT id, sqlfp_tpl_0 FROM sqlfp_tpl_1 WHERE updated_at > ?"
+    assert result.original == sql
+    assert [(t["kind"], t["marker"]) for t in result.metadata["templates"]] == [
+        ("expression", None),
+        ("expression", "sqlfp_tpl_0"),
+        ("expression", "sqlfp_tpl_1"),
+        ("comment", None),
+        ("statement", None),
+        ("statement", None),
+    ]
+    assert result.metadata["templates"][2]["source"] == "{{ ref('orders') }}"
+
+
+def test_sqlfp_templates_opt_in():
+    assert sqlfp.normalize("SELECT 1").metadata == {"literal_counts": {"select": 1}}
+    with pytest.raises(ValueError, match="Parse error"):
+        sqlfp.normalize("SELECT * FROM {{ ref('orders') }}")
+    same = sqlfp.normalize("SELECT * FROM {{ ref('a') }}", templates=True)
+    assert same.hash == sqlfp.normalize("SELECT * FROM {{ ref('b') }}", templates=True).hash
+
+
+@pytest.mark.parametrize(
+    "sql, from_style, to_style, expected, order",
+    [
+        (
+            "SELECT * FROM t WHERE a = ? AND b = '?' -- ?\nAND c = ?",
+            "qmark", "named",
+            "SELECT * FROM t WHERE a = :p1 AND b = '?' -- ?\nAND c = :p2",
+            [1, 2],
+        ),
+        (
+            "SELECT * FROM t WHERE a = %(x)s AND b = %(y)s AND c = %(x)s AND d %% 2 = 0",
+            "pyformat", "qmark",
+            "SELECT * FROM t WHERE a = ? AND b = ? AND c = ? AND d % 2 = 0",
+            ["x", "y", "x"],
+        ),
+        (
+            "SELECT * FROM t WHERE a = %(x)s AND b = %(y)s AND c = %(x)s",
+            "pyformat", "numeric",
+            "SELECT * FROM t WHERE a = :1 AND b = :2 AND c = :1",
+            ["x", "y"],
+        ),
+        (
+            "SELECT * FROM t WHERE a = :2 AND b = :1 AND c % 2 = 0",
+            "numeric", "format",
+            "SELECT * FROM t WHERE a = %s AND b = %s AND c %% 2 = 0",
+            [2, 1],
+        ),
+        (
+            "SELECT '%s', \"%s\" FROM t WHERE a = %s",
+            "format", "pyformat",
+            "SELECT '%s', \"%s\" FROM t WHERE a = %(p1)s",
+            [1],
+        ),
+    ],
+)
+def test_sqlfp_convert_paramstyle(sql, from_style, to_style, expected, order):
+    assert sqlfp.convert_paramstyle(sql, from_style, to_style) == (expected, order)
+
+
+def test_sqlfp_convert_paramstyle_array_slice():
+    sql = "SELECT a[1:2], x::int FROM t WHERE a = :1"
+    assert sqlfp.convert_paramstyle(sql, "numeric", "qmark", dialect="postgres") == (
+        "SELECT a[1:2], x::int FROM t WHERE a = ?",
+        [1],
+    )
+
+
+def test_sqlfp_convert_paramstyle_hints():
+    # MySQL runs the SQL of `/*! */` hints, placeholders included
+    sql = "SELECT a /*!50000 , ? */ FROM t WHERE b = ?"
+    assert sqlfp.convert_paramstyle(sql, "qmark", "named", dialect="mysql") == (
+        "SELECT a /*!50000 , :p1 */ FROM t WHERE b = :p2",
+        [1, 2],
+    )
+
+
+def test_sqlfp_convert_paramstyle_errors():
+    with pytest.raises(ValueError, match="Unsupported paramstyle: dollar"):
+        sqlfp.convert_paramstyle("SELECT $1", "dollar", "qmark")
+    with pytest.raises(ValueError, match="Tokenize error"):
+        sqlfp.convert_paramstyle("SELECT 'unterminated", "qmark", "named")
+
+
+def test_sqlfp_bytes_input():
+    expected = sqlfp.normalize("SELECT * FROM users WHERE name = 'José'")
+    assert sqlfp.normalize("SELECT * FROM users WHERE name = 'José'".encode()).hash == expected.hash
+    latin1 = sqlfp.normalize(
+        "SELECT * FROM users WHERE name = 'José'".encode("latin-1"), encoding="latin-1"
+    )
+    assert latin1.hash == expected.hash
+    assert latin1.original == "SELECT * FROM users WHERE name = 'José'"
+    assert latin1.params == ["'José'"]
+    cp1252 = sqlfp.normalize(b"SELECT '\x80'", encoding="cp1252")
+    assert cp1252.params == ["'€'"]
+
+
+def test_sqlfp_bytes_input_errors():
+    broken = b"SELECT * FROM users WHERE name = 'Jos\xe9'"
+    with pytest.raises(UnicodeDecodeError):
+        sqlfp.normalize(broken)
+    assert sqlfp.normalize(broken, errors="replace").params == ["'Jos�'"]
+    assert sqlfp.normalize(broken, errors="ignore").params == ["'Jos'"]
+    assert sqlfp.normalize(broken, errors="backslashreplace").params == ["'Jos\\xe9'"]
+
+
+def test_sqlfp_normalize_file_lines(tmp_path):
+    path = tmp_path / "slow.log"
+    path.write_bytes(b"SELECT * FROM users WHERE id = 1\r\n\n  \nSELECT * FROM users WHERE id = 2\nSELECT 'Jos\xe9'")
+    results = list(sqlfp.normalize_file(str(path), encoding="latin-1"))
+    assert [r.params for r in results] == [["1"], ["2"], ["'José'"]]
+    assert results[0].hash == results[1].hash == sqlfp.normalize("SELECT * FROM users WHERE id = 3").hash
+    assert [r.metadata["line"] for r in results] == [1, 4, 5]
+
+
+def test_sqlfp_normalize_file_jsonl(tmp_path):
+    path = tmp_path / "queries.jsonl"
+    path.write_text(
+        json.dumps({"sql": "SELECT * FROM t WHERE a = 1", "ms": 12}) + "\n"
+        + json.dumps({"sql": "SELECT * FROM t WHERE a = 2", "ms": 30}) + "\n"
+    )
+    results = list(sqlfp.normalize_file(path, format="jsonl", field="sql", dialect="postgres"))
+    assert [r.params for r in results] == [["1"], ["2"]]
+    assert results[0].original == "SELECT * FROM t WHERE a = 1"
+
+
+def test_sqlfp_normalize_file_errors(tmp_path):
+    path = tmp_path / "mixed.log"
+    path.write_text("SELECT 1\nSELECT * TROM t\nSELECT 2\n")
+    with pytest.raises(ValueError, match="mixed.log:2: Parse error"):
+        list(sqlfp.normalize_file(path))
+    assert [r.params for r in sqlfp.normalize_file(path, on_error="skip")] == [["1"], ["2"]]
+    jsonl = tmp_path / "queries.jsonl"
+    jsonl.write_text('{"q": "SELECT 1"}\n')
+    with pytest.raises(ValueError, match="Missing field 'query'"):
+        list(sqlfp.normalize_file(jsonl, format="jsonl"))
+    with pytest.raises(ValueError, match="Unsupported file format: csv"):
+        sqlfp.normalize_file(path, format="csv")
+    with pytest.raises(FileNotFoundError):
+        sqlfp.normalize_file(tmp_path / "missing.log")
+    empty = tmp_path / "empty.log"
+    empty.write_text("")
+    assert list(sqlfp.normalize_file(empty)) == []
+
+
+def test_sqlfp_normalize_script():
+    script = """USE sales;
+SELECT * FROM orders WHERE id = 1
+GO
+-- only a comment;
+SELECT 'a;GO' FROM t; ;
+go 3
+SET search_path TO crm, public;
+SELECT name FROM accounts
+"""
+    results = sqlfp.normalize_script(script, dialect="postgres")
+    assert [r.normalized for r in results] == [
+        "USE sales",
+        "SELECT * FROM orders WHERE id = ?",
+        "SELECT ? FROM t",
+        "SET search_path = crm, public",
+        "SELECT name FROM accounts",
+    ]
+    assert [(r.metadata["line"], r.metadata["batch"]) for r in results] == [(1, 1), (2, 1), (5, 2), (7, 3), (8, 3)]
+    assert [r.metadata.get("database") for r in results] == [None, "sales", "sales", "sales", "sales"]
+    assert [r.metadata.get("search_path") for r in results] == [None, None, None, None, ["crm", "public"]]
+    assert results[2].original == "SELECT 'a;GO' FROM t"
+
+
+def test_sqlfp_normalize_script_context():
+    script = "USE [Shop]\nGO\nSELECT 1 FROM x;\nUSE crm.core; SELECT 2; USE other; SELECT 3"
+    results = sqlfp.normalize_script(script, dialect="mssql")
+    assert [r.metadata.get("database") for r in results] == [None, "Shop", "Shop", "crm", "crm", "other"]
+    assert [r.metadata.get("search_path") for r in results] == [None, None, None, ["core"], ["core"], None]
+    results = sqlfp.normalize_script("SET search_path TO a; SELECT 1; SET search_path = DEFAULT; SELECT 2", dialect="postgres")
+    assert [r.metadata.get("search_path") for r in results] == [None, ["a"], ["a"], None]
+    assert sqlfp.normalize_script("") == []
+
+
+def test_sqlfp_script_hash():
+    script = "CREATE TABLE t (a INT);\nINSERT INTO t VALUES (1);\nSELECT a FROM t WHERE a = 1"
+    results = sqlfp.normalize_script(script)
+    expected = sha256("".join(r.hash + "\n" for r in results).encode()).hexdigest()
+    assert sqlfp.script_hash(results) == expected
+    # Literals do not matter, the order of the statements does
+    assert sqlfp.script_hash(sqlfp.normalize_script(script.replace("1", "2"))) == expected
+    assert sqlfp.script_hash(results[::-1]) != expected
+    assert sqlfp.script_hash([]) == sha256(b"").hexdigest()
+
+
+def test_sqlfp_ignored_statements():
+    result = sqlfp.normalize("SELECT 1; SELECT 2; DELETE FROM t")
+    assert result.normalized == "SELECT ?"
+    assert result.metadata["ignored_statements"] == 2
+    assert result.hash == sqlfp.normalize("SELECT 1").hash
+    assert "ignored_statements" not in sqlfp.normalize("SELECT 1;").metadata
+
+
+@pytest.mark.parametrize(
+    "script, dialect, expected",
+    [
+        ("SELECT 1 /* a; /* b; */ c; */; SELECT 2", "postgres", ["SELECT 1", "SELECT 2"]),
+        ("SELECT 1 /* a; /* b; */ c; */; SELECT 2", "mssql", ["SELECT 1", "SELECT 2"]),
+        ("SELECT 1 /* a; */; SELECT 2 -- b;\n; SELECT 3", "sqlite", ["SELECT 1", "SELECT 2", "SELECT 3"]),
+        ("SELECT 1 # a;\n; SELECT 2 --3;\n", "mysql", ["SELECT 1", "SELECT 2 --3"]),
+        ("SELECT 1 # 2;\nSELECT 3", "postgres", ["SELECT 1 # 2", "SELECT 3"]),
+        ("/*!40101 SET NAMES utf8 */;\nSELECT 1", "mysql", ["/*!40101 SET NAMES utf8 */", "SELECT 1"]),
+        ("SELECT 1 /*! STRAIGHT_JOIN */; SELECT a /*! , b\n, c */ FROM t", "mysql", ["SELECT 1 /*! STRAIGHT_JOIN */", "SELECT a /*! , b\n, c */ FROM t"]),
+    ],
+)
+def test_sqlfp_normalize_script_comments(script, dialect, expected):
+    results = sqlfp.normalize_script(script, dialect=dialect, on_error="skip")
+    assert [r.original for r in results] == expected
+
+
+def test_sqlfp_normalize_script_errors():
+    with pytest.raises(ValueError, match="line 2: Parse error"):
+        sqlfp.normalize_script("SELECT 1;\nSELECT * TROM t;\nSELECT 2")
+    results = sqlfp.normalize_script("SELECT 1;\nSELECT * TROM t;\nSELECT 2", on_error="skip")
+    assert [r.metadata["line"] for r in results] == [1, 3]
+    with pytest.raises(ValueError, match="Unsupported on_error: none"):
+        sqlfp.normalize_script("SELECT 1", on_error="none")
+    with pytest.raises(ValueError, match="Tokenize error"):
+        sqlfp.normalize_script("SELECT 'unterminated")
+
+
+@pytest.mark.parametrize(
+    "sql, warnings",
+    [
+        ("SELECT * FROM a AS x INNER JOIN b AS y ON x.id = y.id ORDER BY 1 ASC", []),
+        ("UPDATE t SET a = 1 WHERE b = 2", []),
+        ("INSERT INTO t SELECT * FROM a AS x INNER JOIN b ON a.id = b.id", []),
+        (
+            "CREATE VIEW v AS SELECT * FROM a AS x",
+            ["statement CreateView is not structurally normalized", "explicit table alias left in CreateView"],
+        ),
+        (
+            "SELECT ARRAY_AGG(x ORDER BY y ASC), SUM(a) OVER w FROM t WINDOW w AS (ORDER BY b ASC)",
+            ["explicit ASC left in WINDOW clause"],
+        ),
+    ],
+)
+def test_sqlfp_warnings(sql, warnings):
+    assert sqlfp.normalize(sql).warnings == warnings
+
+
+def test_sqlfp_strict():
+    # The 0.1 rules leave the source query of INSERT ... SELECT untouched
+    sql = "INSERT INTO t SELECT * FROM a AS x"
+    assert sqlfp.normalize(sql, compat="0.1").warnings == ["explicit table alias left in Insert"]
+    with pytest.raises(sqlfp.UnnormalizableError, match="explicit table alias left in Insert") as exc:
+        sqlfp.normalize(sql, compat="0.1", strict=True)
+    assert exc.value.warnings == ["explicit table alias left in Insert"]
+    assert isinstance(exc.value, ValueError)
+    assert sqlfp.normalize("SELECT * FROM a AS x", strict=True).normalized == "SELECT * FROM a x"
+
+
+def test_sqlfp_strict_file(tmp_path):
+    path = tmp_path / "queries.log"
+    path.write_text("SELECT 1\nCREATE VIEW v AS SELECT 1\n")
+    with pytest.raises(sqlfp.UnnormalizableError, match="queries.log:2: Statement cannot be fully normalized"):
+        list(sqlfp.normalize_file(path, strict=True))
+    assert len(list(sqlfp.normalize_file(path, strict=True, on_error="skip"))) == 1
+
+
+def test_sqlfp_stats():
+    before = sqlfp.stats()
+    sqlfp.normalize("SELECT 1", dialect="postgres")
+    sqlfp.normalize("SELECT 1", dialect="mariadb")
+    with pytest.raises(ValueError):
+        sqlfp.normalize("SELECT * TROM t")
+    after = sqlfp.stats()
+    assert after["statements_normalized"] - before["statements_normalized"] == 2
+    assert after["parse_failures"] - before["parse_failures"] == 1
+    assert after["dialects"]["postgresql"] - before["dialects"]["postgresql"] == 1
+    assert after["dialects"]["mysql"] - before["dialects"]["mysql"] == 1
+
+
+def test_sqlfp_stats_reset():
+    sqlfp.normalize("SELECT 1")
+    assert sqlfp.stats(reset=True)["statements_normalized"] >= 1
+    assert sqlfp.stats()["statements_normalized"] == 0
+
+
+def test_sqlfp_engine_pool():
+    sqlfp.clear_engine_pool()
+    before = sqlfp.stats()["cache_hits"]
+    for _ in range(3):
+        sqlfp.normalize("SELECT 1", dialect="postgres", placeholder="$")
+    sqlfp.normalize("SELECT 1", dialect="mysql")
+    pool = sorted(sqlfp.engine_pool(), key=lambda e: e["dialect"])
+    assert pool == [
+        {
+            "dialect": "mysql",
+            "placeholder": "?",
+            "placeholder_start": 1,
+            "compat": None,
+            "templates": False,
+            "strict": False,
+            "passes": None,
+            "disabled_passes": [],
+            "enabled_passes": [],
+            "function_case": "upper",
+            "identifier_case": "preserve",
+            "clause_hashes": False,
+            "max_subquery_depth": None,
+            "routing_columns": [],
+            "placeholder_collision": "report",
+            "ignore_clauses": [],
+            "table_columns": {},
+            "function_synonyms": {},
+            "collect_params": True,
+            "max_params": None,
+            "truncated_input": False,
+            "lenient": False,
+            "collapse_in_lists": False,
+            "collapse_values": False,
+            "keep_limit": False,
+            "keep_offset": False,
+            "keep_null": True,
+            "strip_qualifiers": False,
+            "ignore_aliases": False,
+            "anonymize": False,
+            "comments": False,
+            "keep_hints": False,
+            "unwrap_explain": False,
+            "prepared": False,
+            "hash_algorithm": "sha256",
+            "hash_encoding": "hex",
+            "hits": 0,
+        },
+        {
+            "dialect": "postgresql",
+            "placeholder": "$",
+            "placeholder_start": 1,
+            "compat": None,
+            "templates": False,
+            "strict": False,
+            "passes": None,
+            "disabled_passes": [],
+            "enabled_passes": [],
+            "function_case": "upper",
+            "identifier_case": "preserve",
+            "clause_hashes": False,
+            "max_subquery_depth": None,
+            "routing_columns": [],
+            "placeholder_collision": "report",
+            "ignore_clauses": [],
+            "table_columns": {},
+            "function_synonyms": {},
+            "collect_params": True,
+            "max_params": None,
+            "truncated_input": False,
+            "lenient": False,
+            "collapse_in_lists": False,
+            "collapse_values": False,
+            "keep_limit": False,
+            "keep_offset": False,
+            "keep_null": True,
+            "strip_qualifiers": False,
+            "ignore_aliases": False,
+            "anonymize": False,
+            "comments": False,
+            "keep_hints": False,
+            "unwrap_explain": False,
+            "prepared": False,
+            "hash_algorithm": "sha256",
+            "hash_encoding": "hex",
+            "hits": 2,
+        },
+    ]
+    assert sqlfp.stats()["cache_hits"] - before == 2
+    with pytest.raises(ValueError, match="Unsupported dialect"):
+        sqlfp.normalize("SELECT 1", dialect="nope")
+    assert len(sqlfp.engine_pool()) == 2
+    sqlfp.clear_engine_pool()
+    assert sqlfp.engine_pool() == []
+
+
+def test_sqlfp_normalizer():
+    normalizer = sqlfp.Normalizer(dialect="postgres", placeholder="${n}", lenient=True)
+    assert repr(normalizer) == "Normalizer(dialect='postgresql')"
+    sql = "SELECT a FROM t WHERE b = 1 AND c = 'x'"
+    assert normalizer.normalize(sql).hash == sqlfp.normalize(sql, dialect="postgres", placeholder="${n}").hash
+    assert normalizer.normalize(sql.encode()).normalized == "SELECT a FROM t WHERE b = $1 AND c = $2"
+    assert normalizer.normalize("SELEC 1").parsed is False
+    # The engine is kept by the normalizer, not looked up in the pool
+    before = sqlfp.stats()["cache_hits"]
+    normalizer.normalize(sql)
+    assert sqlfp.stats()["cache_hits"] == before
+    results = normalizer.normalize_many(["SELECT 1", ("SELECT `a` FROM t WHERE b = 2", "mysql")])
+    assert [(r.normalized, r.dialect_used) for r in results] == [
+        ("SELECT $1", "postgresql"),
+        ("SELECT `a` FROM t WHERE b = $1", "mysql"),
+    ]
+    with pytest.raises(ValueError, match="Unsupported dialect"):
+        normalizer.normalize_many([("SELECT 1", "nope")])
+    assert sqlfp.Normalizer().normalize_many(["SELEC 1", "SELECT 1"], on_error="none")[0] is None
+
+
+def test_sqlfp_normalizer_options():
+    seen = []
+    normalizer = sqlfp.Normalizer(redactor=lambda index, clause, value: seen.append(value) or "x", lean=True)
+    assert type(normalizer.normalize("SELECT 1")).__name__ == "LeanResult"
+    assert seen == ["1"]
+    # The `configure()` defaults are read once, at construction
+    try:
+        sqlfp.configure(reset=True, placeholder="%s")
+        normalizer = sqlfp.Normalizer()
+    finally:
+        sqlfp.configure(reset=True)
+    assert normalizer.normalize("SELECT 1").normalized == "SELECT %s"
+    with pytest.raises(ValueError, match="Unsupported dialect"):
+        sqlfp.Normalizer(dialect="nope")
+    with pytest.raises(TypeError):
+        sqlfp.Normalizer("postgres")
+
+
+def test_sqlfp_normalize_options():
+    options = sqlfp.NormalizeOptions(dialect="postgres", style="dollar", collapse_in_lists=True)
+    assert (options.dialect, options.placeholder, options.collapse_in_lists) == ("postgresql", "${n}", True)
+    assert options.to_dict()["hash_algorithm"] == "sha256"
+    assert options == sqlfp.NormalizeOptions(**options.to_dict())
+    result = sqlfp.normalize("SELECT * FROM t WHERE a IN (1, 2, 3)", options=options)
+    assert result.normalized == "SELECT * FROM t WHERE a IN ($1)"
+    assert result.options == options and hash(result.options) == hash(options)
+    assert sqlfp.normalize(result.original, options=result.options).hash == result.hash
+    # Arguments given with the options replace theirs
+    assert sqlfp.normalize("SELECT 1", options=options, placeholder="?").options.placeholder == "?"
+    assert sqlfp.Normalizer(options=options).options == options
+    # The dialect detected for the statement is the one recorded
+    assert sqlfp.normalize("SELECT `a` FROM t", dialect="auto").options.dialect == "mysql"
+    assert sqlfp.rewrite("SELECT 1", [])[1].options is None
+
+
+def test_sqlfp_normalize_options_defaults():
+    # The `configure()` defaults are read at construction, and ignored by
+    # calls given the options
+    try:
+        sqlfp.configure(reset=True, placeholder="%s")
+        options = sqlfp.NormalizeOptions()
+        sqlfp.configure(reset=True, compat="percona")
+        assert sqlfp.normalize("SELECT 1", options=options).normalized == "SELECT %s"
+    finally:
+        sqlfp.configure(reset=True)
+    with pytest.raises(ValueError, match="Unsupported dialect"):
+        sqlfp.NormalizeOptions(dialect="nope")
+    with pytest.raises(ValueError, match="Unknown normalization pass"):
+        sqlfp.NormalizeOptions(passes=["nope"])
+    with pytest.raises(TypeError):
+        sqlfp.NormalizeOptions("postgres")
+    hooked = sqlfp.normalize("SELECT 1", passes=["literal-parameterize", lambda sql: None]).options
+    assert hooked.passes == ["literal-parameterize", None]
+    with pytest.raises(ValueError, match="caller hooks"):
+        sqlfp.normalize("SELECT 1", options=hooked)
+    assert sqlfp.normalize("SELECT 1", options=hooked, passes=["literal-parameterize"]).normalized == "SELECT ?"
+
+
+
+def test_sqlfp_keyword_options():
+    # Options past `dialect` and `placeholder` are keyword-only, and checked
+    # by name and type in every entry point
+    assert sqlfp.normalize("SELECT 1", "postgres", "$1").options.dialect == "postgresql"
+    with pytest.raises(TypeError, match="positional"):
+        sqlfp.normalize("SELECT 1", "postgres", "?", None)
+    with pytest.raises(TypeError, match=r"normalize\(\) got an unexpected keyword argument 'dialekt'"):
+        sqlfp.normalize("SELECT 1", dialekt="postgres")
+    with pytest.raises(TypeError, match=r"normalize_many\(\) got an unexpected keyword argument 'nope'"):
+        sqlfp.normalize_many(["SELECT 1"], nope=True)
+    with pytest.raises(TypeError, match=r"configure\(\) got an unexpected keyword argument 'nope'"):
+        sqlfp.configure(nope=True)
+    with pytest.raises(TypeError, match="argument 'strict'"):
+        sqlfp.normalize("SELECT 1", strict="yes")
+    with pytest.raises(TypeError, match="argument 'max_params'"):
+        sqlfp.Normalizer(max_params="3")
+    # None leaves an option to its default
+    assert sqlfp.normalize("SELECT 1", strict=None, style=None).normalized == "SELECT ?"
+    assert sqlfp.NormalizeOptions(collapse_in_lists=None) == sqlfp.NormalizeOptions()
+    # Options recorded on results take neither decoding arguments nor hooks
+    with pytest.raises(TypeError, match="unexpected keyword argument 'encoding'"):
+        sqlfp.NormalizeOptions(encoding="utf-8")
+    with pytest.raises(TypeError, match="passes entries must be pass names"):
+        sqlfp.NormalizeOptions(passes=[lambda sql: None])
+    with pytest.raises(TypeError, match="passes entries must be pass names"):
+        sqlfp.configure(passes=[lambda sql: None])
+
+
+def test_sqlfp_configure(tmp_path):
+    config = tmp_path / "sqlfp.toml"
+    config.write_text('dialect = "mysql"\nplaceholder = "$"\n')
+    try:
+        assert sqlfp.configure(config) == {"dialect": "mysql", "placeholder": "$"}
+        assert sqlfp.normalize("SELECT * FROM t WHERE a = 1").normalized == "SELECT * FROM t WHERE a = $"
+        assert sqlfp.normalize("SELECT 1", placeholder="?").normalized == "SELECT ?"
+        assert sqlfp.configure(strict=True) == {"dialect": "mysql", "placeholder": "$", "strict": True}
+        with pytest.raises(sqlfp.UnnormalizableError):
+            sqlfp.normalize("CREATE VIEW v AS SELECT 1")
+    finally:
+        assert sqlfp.configure(reset=True) == {}
+    assert sqlfp.normalize("SELECT 1").normalized == "SELECT ?"
+
+
+def test_sqlfp_configure_pyproject_and_json(tmp_path):
+    pyproject = tmp_path / "pyproject.toml"
+    pyproject.write_text('[project]\nname = "app"\n\n[tool.sqlfp]\ncompat = "0.1"\ntemplates = true\n')
+    settings = tmp_path / "sqlfp.json"
+    settings.write_text('{"dialect": "postgres"}')
+    try:
+        sqlfp.configure(pyproject)
+        assert sqlfp.configure(settings) == {"dialect": "postgres", "compat": "0.1", "templates": True}
+        assert sqlfp.normalize("SELECT * FROM {{ ref('a') }}").normalized == "SELECT * FROM sqlfp_tpl_0"
+    finally:
+        sqlfp.configure(reset=True)
+
+
+def test_sqlfp_configure_errors(tmp_path):
+    bad = tmp_path / "sqlfp.toml"
+    bad.write_text('dialect = "mysql"\nshards = ["a"]\n')
+    with pytest.raises(ValueError, match="unknown field `shards`"):
+        sqlfp.configure(bad)
+    bad.write_text('dialect = "nope"\n')
+    with pytest.raises(ValueError, match="Unsupported dialect: nope"):
+        sqlfp.configure(bad)
+    with pytest.raises(ValueError, match="Unsupported compat version"):
+        sqlfp.configure(compat="9.9")
+    yaml = tmp_path / "sqlfp.yaml"
+    yaml.write_text("dialect: mysql\n")
+    with pytest.raises(ValueError, match="Unsupported config file format"):
+        sqlfp.configure(yaml)
+    with pytest.raises(FileNotFoundError):
+        sqlfp.configure(tmp_path / "missing.toml")
+    assert sqlfp.configure() == {}
+
+
+ALL_PASSES = [
+    "alias-normalize",
+    "join-normalize",
+    "asc-strip",
+    "nulls-strip",
+    "select-all-strip",
+    "paren-canonicalize",
+    "function-uppercase",
+    "boolean-uppercase",
+    "cast-canonicalize",
+    "literal-parameterize",
+]
+
+
+def test_sqlfp_passes_default():
+    result = sqlfp.normalize("SELECT 1")
+    assert result.passes == ALL_PASSES
+
+
+@pytest.mark.parametrize(
+    "disabled,sql,expected",
+    [
+        (["alias-normalize"], "SELECT * FROM t AS x", "SELECT * FROM t AS x"),
+        (["join-normalize"], "SELECT * FROM a INNER JOIN b ON a.id = b.id", "SELECT * FROM a INNER JOIN b ON a.id = b.id"),
+        (["asc-strip"], "SELECT a FROM t ORDER BY a ASC", "SELECT a FROM t ORDER BY a ASC"),
+        (["function-uppercase"], "SELECT count(a) FROM t", "SELECT count(a) FROM t"),
+        (
+            ["boolean-uppercase", "literal-parameterize"],
+            "SELECT a FROM t WHERE b = true",
+            "SELECT a FROM t WHERE b = true",
+        ),
+        (["literal-parameterize"], "SELECT a FROM t WHERE b = 1", "SELECT a FROM t WHERE b = 1"),
+    ],
+)
+def test_sqlfp_disable_passes(disabled, sql, expected):
+    result = sqlfp.normalize(sql, disable_passes=disabled)
+    assert result.normalized == expected
+    assert result.passes == [p for p in ALL_PASSES if p not in disabled]
+    assert result.hash != sqlfp.normalize(sql).hash
+
+
+@pytest.mark.parametrize(
+    "sql, dialect, expected",
+    [
+        ("SELECT (a || b) + c, (a + b) || c FROM t", "postgres", "SELECT (a || b) + c, a + b || c FROM t"),
+        ("SELECT (a + b) || c FROM t", "generic", "SELECT (a + b) || c FROM t"),
+        ("SELECT (a || b) * c FROM t", "sqlite", "SELECT a || b * c FROM t"),
+        ("SELECT (a * b) ^ c, a * (b ^ c) FROM t", "mysql", "SELECT (a * b) ^ c, a * (b ^ c) FROM t"),
+        ("SELECT a + (b & c), (a & b) + c FROM t", "mssql", "SELECT a + (b & c), (a & b) + c FROM t"),
+        ("SELECT * FROM t WHERE a AND (b XOR c)", "mysql", "SELECT * FROM t WHERE a AND (b XOR c)"),
+        # sqlparser reads XOR tighter than `=`, which the parentheses spell out
+        ("SELECT * FROM t WHERE id = 1 XOR id = 2", "mysql", "SELECT * FROM t WHERE id = (? XOR id) = ?"),
+        ("SELECT * FROM t WHERE (id = 1) XOR (id = 2)", "mysql", "SELECT * FROM t WHERE (id = ?) XOR (id = ?)"),
+        ("SELECT * FROM t WHERE (a OR b) IN (1) AND (NOT c) = d", "generic", "SELECT * FROM t WHERE (a OR b) IN (?) AND (NOT c) = d"),
+        ("SELECT (a IS NULL) = (b IS NULL) FROM t", "mysql", "SELECT (a IS NULL) = (b IS NULL) FROM t"),
+        ("SELECT * FROM t WHERE ((a = 1)) AND (b + (c * d)) > 2", "generic", "SELECT * FROM t WHERE a = ? AND b + c * d > ?"),
+    ],
+)
+def test_sqlfp_paren_canonicalize_dialect(sql, dialect, expected):
+    result = sqlfp.normalize(sql, dialect=dialect)
+    assert result.normalized == expected
+    # The parentheses kept read back the same
+    assert sqlfp.normalize(result.normalized, dialect=dialect).normalized == expected
+
+
+def test_sqlfp_paren_canonicalize_options():
+    sql = "SELECT (a || b) + c FROM t WHERE (x OR y) IN (1)"
+    assert sqlfp.normalize(sql, dialect="postgres", compat="0.1").normalized == "SELECT a || b + c FROM t WHERE x OR y IN (?)"
+    kept = sqlfp.normalize("SELECT ((a)) + (b * c) FROM t", disable_passes=["paren-canonicalize"])
+    assert kept.normalized == "SELECT ((a)) + (b * c) FROM t"
+
+
+def test_sqlfp_disable_literal_parameterize_keeps_params_empty():
+    result = sqlfp.normalize("SELECT a FROM t WHERE b = 'x'", disable_passes=["literal-parameterize"])
+    assert result.params == []
+
+
+def test_sqlfp_disabled_pass_not_warned():
+    sql = "SELECT * FROM t AS x"
+    assert sqlfp.normalize(sql, disable_passes=["alias-normalize"]).warnings == []
+
+
+def test_sqlfp_disable_passes_unknown():
+    with pytest.raises(ValueError, match="Unknown normalization pass: nope"):
+        sqlfp.normalize("SELECT 1", disable_passes=["nope"])
+
+
+def test_sqlfp_disable_passes_configure():
+    try:
+        config = sqlfp.configure(disable_passes=["asc-strip"])
+        assert config["disable_passes"] == ["asc-strip"]
+        result = sqlfp.normalize("SELECT a FROM t ORDER BY a ASC")
+        assert "asc-strip" not in result.passes
+        assert result.normalized == "SELECT a FROM t ORDER BY a ASC"
+        # An explicit argument wins over the configured one
+        assert "asc-strip" in sqlfp.normalize("SELECT 1", disable_passes=[]).passes
+    finally:
+        sqlfp.configure(reset=True)
+
+
+@pytest.mark.parametrize(
+    "sql, dialect, expected",
+    [
+        ("SELECT a FROM t ORDER BY a ASC NULLS LAST", "postgres", "SELECT a FROM t ORDER BY a"),
+        ("SELECT a FROM t ORDER BY a DESC NULLS FIRST", "postgres", "SELECT a FROM t ORDER BY a DESC"),
+        ("SELECT a FROM t ORDER BY a NULLS FIRST", "postgres", "SELECT a FROM t ORDER BY a NULLS FIRST"),
+        ("SELECT a FROM t ORDER BY a DESC NULLS LAST", "oracle", "SELECT a FROM t ORDER BY a DESC NULLS LAST"),
+        ("SELECT a FROM t ORDER BY a NULLS FIRST", "sqlite", "SELECT a FROM t ORDER BY a"),
+        ("SELECT a FROM t ORDER BY a DESC NULLS LAST", "sqlite", "SELECT a FROM t ORDER BY a DESC"),
+        ("SELECT a FROM t ORDER BY a NULLS LAST", "sqlite", "SELECT a FROM t ORDER BY a NULLS LAST"),
+        ("SELECT a FROM t ORDER BY a NULLS LAST", "generic", "SELECT a FROM t ORDER BY a NULLS LAST"),
+        ("SELECT a FROM t ORDER BY a NULLS LAST", "snowflake", "SELECT a FROM t ORDER BY a"),
+        ("SELECT a FROM t ORDER BY a NULLS FIRST", "bigquery", "SELECT a FROM t ORDER BY a"),
+        ("SELECT a FROM t ORDER BY a DESC NULLS LAST", "duckdb", "SELECT a FROM t ORDER BY a DESC NULLS LAST"),
+        (
+            "SELECT ROW_NUMBER() OVER (ORDER BY a DESC NULLS FIRST) FROM t",
+            "postgres",
+            "SELECT ROW_NUMBER() OVER (ORDER BY a DESC) FROM t",
+        ),
+    ],
+)
+def test_sqlfp_nulls_strip(sql, dialect, expected):
+    assert sqlfp.normalize(sql, dialect=dialect).normalized == expected
+
+
+def test_sqlfp_nulls_strip_options():
+    sql = "SELECT a FROM t ORDER BY a NULLS LAST"
+    assert sqlfp.normalize(sql, dialect="postgres").hash == sqlfp.normalize("SELECT a FROM t ORDER BY a").hash
+    assert sqlfp.normalize(sql, dialect="postgres", disable_passes=["nulls-strip"]).normalized == sql
+    assert sqlfp.normalize(sql, dialect="postgres", compat="0.1").normalized == sql
+    # Left in the parts the structural pass does not reach
+    window = "SELECT SUM(a) OVER w FROM t WINDOW w AS (ORDER BY b NULLS LAST)"
+    assert sqlfp.normalize(window, dialect="postgres").warnings == ["default NULLS ordering left in WINDOW clause"]
+    assert sqlfp.normalize(window).warnings == []
+
+
+@pytest.mark.parametrize(
+    "sql, dialect, expected, params",
+    [
+        (
+            "SELECT PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY v ASC NULLS LAST) FROM t",
+            "postgres",
+            "SELECT PERCENTILE_CONT($1) WITHIN GROUP (ORDER BY v) FROM t",
+            ["0.5"],
+        ),
+        (
+            "SELECT ARRAY_AGG(a ORDER BY b ASC) FILTER (WHERE c IN (1, 2)) FROM t",
+            "postgres",
+            "SELECT ARRAY_AGG(a ORDER BY b) FILTER (WHERE c IN ($1, $2)) FROM t",
+            ["1", "2"],
+        ),
+        (
+            "SELECT MODE() WITHIN GROUP (ORDER BY v + 1) FILTER (WHERE x > 2) OVER (PARTITION BY y + 3) FROM t",
+            "postgres",
+            "SELECT MODE() WITHIN GROUP (ORDER BY v + $1) FILTER (WHERE x > $2) OVER (PARTITION BY y + $3) FROM t",
+            ["1", "2", "3"],
+        ),
+        (
+            "SELECT LISTAGG(a, ',') WITHIN GROUP (ORDER BY b ASC) FROM t",
+            "oracle",
+            "SELECT LISTAGG(a, $1) WITHIN GROUP (ORDER BY b) FROM t",
+            ["','"],
+        ),
+        (
+            "SELECT GROUP_CONCAT(a ORDER BY b ASC SEPARATOR ',') FROM t WHERE c = 1",
+            "mysql",
+            "SELECT GROUP_CONCAT(a ORDER BY b SEPARATOR $1) FROM t WHERE c = $2",
+            ["','", "1"],
+        ),
+    ],
+)
+def test_sqlfp_aggregate_clauses(sql, dialect, expected, params):
+    result = sqlfp.normalize(sql, dialect=dialect, placeholder="${n}")
+    assert result.normalized == expected
+    assert result.params == params
+    assert result.warnings == []
+
+
+def test_sqlfp_aggregate_clauses_compat():
+    sql = "SELECT PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY v + 1 ASC) FILTER (WHERE x > 2) FROM t"
+    old = sqlfp.normalize(sql, dialect="postgres", placeholder="${n}", compat="0.1")
+    assert old.normalized == "SELECT PERCENTILE_CONT($1) WITHIN GROUP (ORDER BY v + $3 ASC) FILTER (WHERE x > $2) FROM t"
+    assert old.warnings == ["explicit ASC left in PERCENTILE_CONT()"]
+    assert sqlfp.normalize(sql, dialect="postgres", disable_passes=["asc-strip"]).normalized == (
+        "SELECT PERCENTILE_CONT(?) WITHIN GROUP (ORDER BY v + ? ASC) FILTER (WHERE x > ?) FROM t"
+    )
+
+
+def test_sqlfp_select_all_strip():
+    assert sqlfp.normalize("SELECT ALL a FROM t").hash == sqlfp.normalize("SELECT a FROM t").hash
+    nested = sqlfp.normalize("INSERT INTO x SELECT * FROM t WHERE a IN (SELECT ALL b FROM u)")
+    assert nested.normalized == "INSERT INTO x SELECT * FROM t WHERE a IN (SELECT b FROM u)"
+    assert sqlfp.normalize("SELECT ALL a FROM t", disable_passes=["select-all-strip"]).normalized == "SELECT ALL a FROM t"
+    assert sqlfp.normalize("SELECT ALL a FROM t", compat="0.1").normalized == "SELECT ALL a FROM t"
+    # UNION ALL keeps its meaning
+    union = "SELECT a FROM t UNION ALL SELECT a FROM u"
+    assert sqlfp.normalize(union).normalized == union
+
+
+@pytest.mark.parametrize(
+    "sql, expected",
+    [
+        ("select a from t union distinct select b from u", "SELECT a FROM t UNION SELECT b FROM u"),
+        ("SELECT a FROM t INTERSECT DISTINCT SELECT b FROM u", "SELECT a FROM t INTERSECT SELECT b FROM u"),
+        ("SELECT a FROM t except distinct SELECT b FROM u", "SELECT a FROM t EXCEPT SELECT b FROM u"),
+        ("select a from t union all select b from u", "SELECT a FROM t UNION ALL SELECT b FROM u"),
+        ("(SELECT a FROM t) UNION ((SELECT b FROM u))", "SELECT a FROM t UNION SELECT b FROM u"),
+        (
+            "(SELECT a FROM t UNION SELECT b FROM u) UNION ALL SELECT c FROM v",
+            "SELECT a FROM t UNION SELECT b FROM u UNION ALL SELECT c FROM v",
+        ),
+        (
+            "SELECT a FROM t UNION (SELECT b FROM u INTERSECT SELECT c FROM v)",
+            "SELECT a FROM t UNION SELECT b FROM u INTERSECT SELECT c FROM v",
+        ),
+        # Parentheses the precedence needs
+        (
+            "((SELECT a FROM t UNION SELECT b FROM u)) INTERSECT SELECT c FROM v",
+            "(SELECT a FROM t UNION SELECT b FROM u) INTERSECT SELECT c FROM v",
+        ),
+        (
+            "SELECT a FROM t UNION (SELECT b FROM u UNION ALL SELECT c FROM v)",
+            "SELECT a FROM t UNION (SELECT b FROM u UNION ALL SELECT c FROM v)",
+        ),
+        # A branch with clauses of its own
+        ("(SELECT a FROM t LIMIT 1) UNION (SELECT b FROM u)", "(SELECT a FROM t LIMIT ?) UNION SELECT b FROM u"),
+        (
+            "SELECT * FROM x WHERE a IN ((SELECT a FROM t) UNION (SELECT b FROM u))",
+            "SELECT * FROM x WHERE a IN (SELECT a FROM t UNION SELECT b FROM u)",
+        ),
+    ],
+)
+def test_sqlfp_set_operations(sql, expected):
+    assert sqlfp.normalize(sql).normalized == expected
+
+
+def test_sqlfp_set_operations_passes():
+    sql = "(SELECT a FROM t) UNION DISTINCT (SELECT b FROM u)"
+    assert sqlfp.normalize(sql).hash == sqlfp.normalize("SELECT a FROM t UNION SELECT b FROM u").hash
+    assert sqlfp.normalize(sql, compat="0.1").normalized == sql
+    assert sqlfp.normalize(sql, disable_passes=["select-all-strip"]).normalized == "SELECT a FROM t UNION DISTINCT SELECT b FROM u"
+    assert sqlfp.normalize(sql, disable_passes=["paren-canonicalize"]).normalized == "(SELECT a FROM t) UNION (SELECT b FROM u)"
+    # Chains differing only in their quantifier spelling are one chain
+    assert sqlfp.normalize("SELECT 1 UNION SELECT 2 UNION DISTINCT SELECT 1").metadata["duplicate_branches"] == 1
+
+
+@pytest.mark.parametrize(
+    "sql, expected, dialect",
+    [
+        ("SELECT SUM(ALL a) FROM t", "SELECT SUM(a) FROM t", "generic"),
+        ("SELECT COUNT(ALL *) FROM t", "SELECT COUNT(*) FROM t", "postgres"),
+        ("SELECT count( distinct a,b ) FROM t", "SELECT COUNT(DISTINCT a, b) FROM t", "mysql"),
+        ("SELECT COUNT(DISTINCT(a)) FROM t", "SELECT COUNT(DISTINCT a) FROM t", "generic"),
+        ("SELECT COUNT(DISTINCT CASE WHEN a = 1 THEN b END) FROM t", "SELECT COUNT(DISTINCT CASE WHEN a = ? THEN b END) FROM t", "generic"),
+        ("SELECT ARRAY_AGG(ALL a ORDER BY b ASC) FROM t", "SELECT ARRAY_AGG(a ORDER BY b) FROM t", "postgres"),
+    ],
+)
+def test_sqlfp_aggregate_arguments(sql, expected, dialect):
+    assert sqlfp.normalize(sql, dialect=dialect).normalized == expected
+
+
+def test_sqlfp_aggregate_arguments_converge():
+    spellings = ["COUNT(DISTINCT a, b)", "count( distinct a,b )", "COUNT(DISTINCT (a), b)", "COUNT(DISTINCT a, (b))"]
+    hashes = {sqlfp.normalize(f"SELECT {call} FROM t", dialect="mysql").hash for call in spellings}
+    assert len(hashes) == 1
+    assert sqlfp.normalize("SELECT SUM(ALL a) FROM t").hash == sqlfp.normalize("SELECT SUM(a) FROM t").hash
+    assert sqlfp.normalize("SELECT SUM(ALL a) FROM t", compat="0.1").normalized == "SELECT SUM(ALL a) FROM t"
+    kept = sqlfp.normalize("SELECT SUM(ALL a) FROM t", disable_passes=["select-all-strip"])
+    assert kept.normalized == "SELECT SUM(ALL a) FROM t"
+
+
+@pytest.mark.parametrize(
+    "sql, metadata",
+    [
+        ("SELECT DISTINCT a FROM t", {"uses_distinct": True}),
+        ("SELECT DISTINCT ON (a, b + 1) a, b FROM t", {"uses_distinct": True, "distinct_on_columns": ["a", "b + ?"]}),
+        ("SELECT a FROM t", {}),
+        ("SELECT ALL a FROM t", {}),
+        # Only the outermost SELECT counts
+        ("SELECT * FROM t WHERE a IN (SELECT DISTINCT b FROM u)", {}),
+    ],
+)
+def test_sqlfp_distinct_metadata(sql, metadata):
+    result = sqlfp.normalize(sql, dialect="postgres")
+    assert {k: v for k, v in result.metadata.items() if k in ("uses_distinct", "distinct_on_columns")} == metadata
+
+
+def test_sqlfp_distinct_on_literals():
+    result = sqlfp.normalize("SELECT DISTINCT ON (a, 'x') a FROM t WHERE b = 2", dialect="postgres")
+    assert result.normalized == "SELECT DISTINCT ON (a, ?) a FROM t WHERE b = ?"
+    assert result.params == ["'x'", "2"]
+
+
+def test_sqlfp_pass_order():
+    order = ["literal-parameterize", "alias-normalize"]
+    result = sqlfp.normalize("SELECT * FROM t AS x WHERE b = true", passes=order)
+    assert result.passes == order
+    # Booleans are parameterized before boolean-uppercase could run
+    assert result.normalized == "SELECT * FROM t x WHERE b = ?"
+
+
+@pytest.mark.parametrize(
+    "sql, dialect, normalized, params",
+    [
+        ("SELECT * FROM t WHERE a = true", "mssql", "SELECT * FROM t WHERE a = ?", ["TRUE"]),
+        ("SELECT * FROM t WHERE a = False", "oracle", "SELECT * FROM t WHERE a = ?", ["FALSE"]),
+        ("SELECT * FROM t WHERE [true] = 1", "mssql", "SELECT * FROM t WHERE [true] = ?", ["1"]),
+        ("SELECT * FROM t WHERE t.true = 1", "mssql", "SELECT * FROM t WHERE t.true = ?", ["1"]),
+        ("SELECT * FROM t WHERE t.true = 1 AND t.FALSE.x = 2", "postgres", "SELECT * FROM t WHERE t.true = ? AND t.false.x = ?", ["1", "2"]),
+        ("SELECT * FROM t WHERE `true` = true", "mysql", "SELECT * FROM t WHERE `true` = ?", ["true"]),
+        # Double quotes delimit names, not strings
+        ('SELECT * FROM t WHERE "true" = 1', "postgres", 'SELECT * FROM t WHERE "true" = ?', ["1"]),
+        ('SELECT * FROM t WHERE "TRUE" = 1', "oracle", 'SELECT * FROM t WHERE "TRUE" = ?', ["1"]),
+        ('SELECT * FROM t WHERE "true" = 1', "mssql", 'SELECT * FROM t WHERE "true" = ?', ["1"]),
+        ('SELECT * FROM t WHERE "true" = 1', "mysql", "SELECT * FROM t WHERE ? = ?", ['"true"', "1"]),
+    ],
+)
+def test_sqlfp_booleans(sql, dialect, normalized, params):
+    result = sqlfp.normalize(sql, dialect=dialect)
+    assert result.normalized == normalized
+    assert result.params == params
+
+
+def test_sqlfp_boolean_columns():
+    # A column named true, where booleans are identifiers
+    sql = "SELECT * FROM t WHERE flag = true"
+    kept = sqlfp.normalize(sql, dialect="mssql", disable_passes=["boolean-uppercase"])
+    assert kept.normalized == sql
+    assert kept.params == []
+    # Read as a boolean only once boolean-uppercase ran
+    order = ["literal-parameterize", "boolean-uppercase"]
+    assert sqlfp.normalize(sql, dialect="mssql", passes=order).normalized == "SELECT * FROM t WHERE flag = TRUE"
+    qualified = "SELECT * FROM t WHERE t.true = 1"
+    assert sqlfp.normalize(qualified, dialect="postgres", compat="0.1").normalized == "SELECT * FROM t WHERE t.? = ?"
+    quoted = 'SELECT * FROM t WHERE "true" = 1'
+    assert sqlfp.normalize(quoted, dialect="postgres", compat="0.1").normalized == "SELECT * FROM t WHERE ? = ?"
+
+
+def test_sqlfp_pass_hook():
+    seen = []
+
+    def shard_names(sql):
+        seen.append(sql)
+        return sql.replace("orders_0042", "orders_N")
+
+    result = sqlfp.normalize(
+        "SELECT * FROM orders_0042 AS o WHERE id = 7",
+        passes=["alias-normalize", shard_names, "literal-parameterize"],
+    )
+    assert seen == ["SELECT * FROM orders_0042 o WHERE id = 7"]
+    assert result.normalized == "SELECT * FROM orders_N o WHERE id = ?"
+    assert result.params == ["7"]
+    assert result.passes == ["alias-normalize", "hook:shard_names", "literal-parameterize"]
+
+
+def test_sqlfp_pass_hook_returning_none_keeps_statement():
+    result = sqlfp.normalize("SELECT 1", passes=[lambda sql: None, "literal-parameterize"])
+    assert result.normalized == "SELECT ?"
+    assert result.passes == ["hook:<lambda>", "literal-parameterize"]
+
+
+def test_sqlfp_pass_hook_errors():
+    def boom(sql):
+        raise RuntimeError("boom")
+
+    with pytest.raises(RuntimeError, match="boom"):
+        sqlfp.normalize("SELECT 1", passes=[boom])
+    with pytest.raises(ValueError, match="Parse error"):
+        sqlfp.normalize("SELECT 1", passes=[lambda sql: "SELECT FROM WHERE ("])
+
+
+def test_sqlfp_pass_order_errors():
+    with pytest.raises(ValueError, match="Normalization pass listed twice: asc-strip"):
+        sqlfp.normalize("SELECT 1", passes=["asc-strip", "asc-strip"])
+    with pytest.raises(ValueError, match="Unknown normalization pass: nope"):
+        sqlfp.normalize("SELECT 1", passes=["nope"])
+    with pytest.raises(TypeError, match="pass names or callables"):
+        sqlfp.normalize("SELECT 1", passes=[1])
+
+
+def test_sqlfp_pass_order_with_disable_passes():
+    result = sqlfp.normalize(
+        "SELECT * FROM t AS x",
+        passes=["alias-normalize", "literal-parameterize"],
+        disable_passes=["alias-normalize"],
+    )
+    assert result.passes == ["literal-parameterize"]
+    assert result.normalized == "SELECT * FROM t AS x"
+
+
+def test_sqlfp_pass_order_configure():
+    try:
+        sqlfp.configure(passes=["literal-parameterize"])
+        result = sqlfp.normalize("SELECT * FROM t AS x WHERE a = 1")
+        assert result.passes == ["literal-parameterize"]
+        assert result.normalized == "SELECT * FROM t AS x WHERE a = ?"
+    finally:
+        sqlfp.configure(reset=True)
+
+
+def test_sqlfp_snapshot_corpus_directory(tmp_path):
+    (tmp_path / "b.sql").write_text("SELECT * FROM t WHERE id = 1")
+    (tmp_path / "sub").mkdir()
+    (tmp_path / "sub" / "a.sql").write_text("SELECT a FROM u\nWHERE b = 'x'")
+    (tmp_path / "broken.sql").write_text("SELEC 1")
+    (tmp_path / "notes.txt").write_text("not sql")
+    manifest = json.loads(sqlfp.snapshot_corpus(tmp_path, dialect="postgres"))
+    assert manifest["sqlfp_version"] == sqlfp.__version__
+    assert manifest["fingerprint_version"] == sqlfp.FINGERPRINT_VERSION
+    assert manifest["dialect"] == "postgresql"
+    assert manifest["compat"] is None
+    samples = manifest["samples"]
+    assert [s["sample"] for s in samples] == ["b.sql", "broken.sql", "sub/a.sql"]
+    assert samples[0] == {
+        "sample": "b.sql",
+        "normalized": "SELECT * FROM t WHERE id = ?",
+        "hash": sqlfp.normalize("SELECT * FROM t WHERE id = 1").hash,
+    }
+    assert "Parse error" in samples[1]["error"]
+    assert samples[2]["normalized"] == "SELECT a FROM u WHERE b = ?"
+
+
+def test_sqlfp_snapshot_corpus_file_is_deterministic(tmp_path):
+    corpus = tmp_path / "queries.log"
+    corpus.write_text("SELECT 1\n\nSELECT * FROM t WHERE a = 2\r\n")
+    out = tmp_path / "manifest.json"
+    first = sqlfp.snapshot_corpus(corpus, out, compat="0.1")
+    assert out.read_text() == first
+    assert sqlfp.snapshot_corpus(corpus, compat="0.1") == first
+    manifest = json.loads(first)
+    assert manifest["compat"] == "0.1"
+    assert [s["sample"] for s in manifest["samples"]] == ["queries.log:1", "queries.log:3"]
+
+
+def test_sqlfp_snapshot_corpus_missing_path(tmp_path):
+    with pytest.raises(OSError):
+        sqlfp.snapshot_corpus(tmp_path / "missing")
+
+
+def test_sqlfp_export_bundle():
+    queries = [
+        "SELECT u.email FROM users AS u JOIN orders o ON o.user_id = u.id WHERE u.name = 'alice'",
+        "SELECT u.email FROM users u JOIN orders o ON o.user_id = u.id WHERE u.name = 'bob'",
+        "SELECT count(*) FROM \"Users\" WHERE id IN (SELECT user_id FROM orders)",
+        "SELEC broken",
+    ]
+    bundle = json.loads(sqlfp.export_bundle(queries))
+    assert bundle["sqlfp_version"] == sqlfp.__version__
+    assert bundle["fingerprint_version"] == sqlfp.FINGERPRINT_VERSION
+    assert bundle["unparsed"] == 1
+    first, second = bundle["queries"]
+    assert first["hash"] == sqlfp.normalize(queries[0]).hash
+    assert first["count"] == 2
+    assert first["params"] == 1
+    assert first["sql"] == "SELECT id1.id2 FROM id3 id1 JOIN id4 id5 ON id5.id6 = id1.id7 WHERE id1.id8 = ?"
+    assert first["metrics"] == {"tables": 2, "ctes": 0, "joins": 1, "subqueries": 0, "depth": 1, "expressions": 7}
+    assert second["sql"] == "SELECT COUNT(*) FROM id9 WHERE id7 IN (SELECT id6 FROM id4)"
+    assert second["metrics"]["subqueries"] == 1
+    assert second["metrics"]["depth"] == 2
+    text = json.dumps(bundle)
+    for raw in ("alice", "bob", "users", "Users", "email", "orders"):
+        assert raw not in text
+
+
+def test_sqlfp_export_bundle_output(tmp_path):
+    out = tmp_path / "bundle.json"
+    document = sqlfp.export_bundle(iter(["SELECT 1"]), out, dialect="postgres")
+    assert out.read_text() == document
+    assert json.loads(document)["dialect"] == "postgresql"
+
+
+def test_sqlfp_export_bundle_keyword_like_names():
+    bundle = json.loads(
+        sqlfp.export_bundle(
+            [
+                "INSERT INTO accounts (name, status) VALUES (1, 'x')",
+                "WITH recent AS (SELECT id AS k FROM logs) SELECT k FROM recent",
+            ]
+        )
+    )
+    assert [q["sql"] for q in bundle["queries"]] == [
+        "INSERT INTO id1 (id2, id3) VALUES (?, ?)",
+        "WITH id4 AS (SELECT id5 AS id6 FROM id7) SELECT id6 FROM id4",
+    ]
+
+
+def test_sqlfp_diff_corpora():
+    before = ["SELECT a FROM t WHERE id = 1"] * 4 + ["SELECT b FROM u"] + ["DELETE FROM v", "SELECT 1"]
+    after = ["SELECT a FROM t WHERE id = 2"] + ["SELECT b FROM u"] * 3 + ["SELECT c FROM w"] * 2 + ["SELECT 1", "SELEC broken"]
+    report = sqlfp.diff_corpora(before, iter(after), dialect="postgres")
+    assert report["dialect"] == "postgresql"
+    assert report["compat"] is None
+    assert report["new"] == [
+        {"hash": sqlfp.normalize("SELECT c FROM w").hash, "normalized": "SELECT c FROM w", "before": 0, "after": 2},
+    ]
+    assert [(e["normalized"], e["before"], e["after"]) for e in report["disappeared"]] == [("DELETE FROM v", 1, 0)]
+    # Largest change first
+    assert [(e["normalized"], e["before"], e["after"]) for e in report["changed"]] == [
+        ("SELECT a FROM t WHERE id = ?", 4, 1),
+        ("SELECT b FROM u", 1, 3),
+    ]
+    assert report["unchanged"] == 1
+    assert report["unparsed"] == (0, 1)
+
+
+def test_sqlfp_diff_corpora_paths(tmp_path):
+    (tmp_path / "before.log").write_text("SELECT 1\nSELECT * FROM t WHERE a = 1\n")
+    (tmp_path / "after.log").write_text("SELECT 2\n")
+    report = sqlfp.diff_corpora(tmp_path / "before.log", tmp_path / "after.log", compat="0.1")
+    assert report["compat"] == "0.1"
+    assert [e["normalized"] for e in report["disappeared"]] == ["SELECT * FROM t WHERE a = ?"]
+    assert report["new"] == report["changed"] == []
+    assert report["unchanged"] == 1
+
+
+def test_sqlfp_diff_corpora_rejects_single_query():
+    with pytest.raises(TypeError):
+        sqlfp.diff_corpora("SELECT 1", ["SELECT 1"])
+
+
+@pytest.mark.parametrize(
+    "sql_a, sql_b, score, differing",
+    [
+        ("SELECT a FROM t WHERE x = 1", "select a from t where x = 2", 1.0, []),
+        ("SELECT a FROM t WHERE x = 1", "SELECT a FROM t WHERE x > 1", 0.9375, ["where"]),
+        ("SELECT a FROM t WHERE x = 1", "SELECT a, b FROM t WHERE x = 1", 0.8333, ["projection"]),
+        ("SELECT a FROM t WHERE x = 1", "SELECT a FROM t WHERE x = 1 AND y = 2", 0.6667, ["where"]),
+        ("SELECT a FROM t", "SELECT a FROM t ORDER BY a", 0.75, ["order_by"]),
+        ("SELECT a FROM t WHERE x = 1", "UPDATE t SET a = 1", 0.375, ["statement"]),
+    ],
+)
+def test_sqlfp_similarity(sql_a, sql_b, score, differing):
+    report = sqlfp.similarity(sql_a, sql_b)
+    assert report["score"] == pytest.approx(score, abs=1e-4)
+    assert report["differing_clauses"] == differing
+    assert sqlfp.similarity(sql_b, sql_a)["score"] == pytest.approx(score, abs=1e-4)
+
+
+def test_sqlfp_similarity_options():
+    far = sqlfp.similarity("SELECT a FROM t", "SELECT q, r FROM u JOIN v ON u.id = v.id WHERE z IN (1, 2) ORDER BY q")
+    assert far["score"] < 0.5
+    assert far["differing_clauses"] == ["projection", "from", "where", "order_by"]
+    assert sqlfp.similarity("SELECT a FROM t ORDER BY a ASC", "SELECT a FROM t ORDER BY a", compat="0.1")["score"] == 1.0
+    with pytest.raises(ValueError, match="Parse error"):
+        sqlfp.similarity("SELEC a", "SELECT a")
+
+
+def test_sqlfp_fingerprint_execute_format():
+    result, params = sqlfp.fingerprint_execute(
+        "SELECT * FROM users WHERE id = %s AND status = 'active' AND name LIKE 'a%%'",
+        (42,),
+    )
+    inlined = sqlfp.normalize("SELECT * FROM users WHERE id = 42 AND status = 'active' AND name LIKE 'a%'")
+    assert result.normalized == inlined.normalized
+    assert result.hash == inlined.hash
+    assert result.original == "SELECT * FROM users WHERE id = %s AND status = 'active' AND name LIKE 'a%%'"
+    assert params == [
+        {"value": 42, "key": 0, "bound": True},
+        {"value": "'active'", "key": None, "bound": False},
+        {"value": "'a%'", "key": None, "bound": False},
+    ]
+
+
+@pytest.mark.parametrize(
+    "paramstyle,statement,parameters",
+    [
+        ("qmark", "UPDATE t SET a = ?, b = 5 WHERE id = ?", ("x", 7)),
+        ("numeric", "UPDATE t SET a = :1, b = 5 WHERE id = :2", ("x", 7)),
+        ("named", "UPDATE t SET a = :a, b = 5 WHERE id = :id", {"a": "x", "id": 7}),
+        ("pyformat", "UPDATE t SET a = %(a)s, b = 5 WHERE id = %(id)s", {"a": "x", "id": 7}),
+    ],
+)
+def test_sqlfp_fingerprint_execute_paramstyles(paramstyle, statement, parameters):
+    result, params = sqlfp.fingerprint_execute(statement, parameters, paramstyle=paramstyle, dialect="postgres")
+    assert result.normalized == "UPDATE t SET a = ?, b = ? WHERE id = ?"
+    assert [p["value"] for p in params] == ["x", "5", 7]
+    assert [p["bound"] for p in params] == [True, False, True]
+
+
+def test_sqlfp_fingerprint_execute_without_parameters():
+    result, params = sqlfp.fingerprint_execute("SELECT * FROM t WHERE a LIKE 'x%'")
+    assert result.normalized == "SELECT * FROM t WHERE a LIKE ?"
+    assert params == [{"value": "'x%'", "key": None, "bound": False}]
+
+
+def test_sqlfp_fingerprint_execute_missing_parameter():
+    with pytest.raises(KeyError):
+        sqlfp.fingerprint_execute("SELECT * FROM t WHERE a = %(a)s", {}, paramstyle="pyformat")
+    with pytest.raises(ValueError, match="Unsupported paramstyle"):
+        sqlfp.fingerprint_execute("SELECT 1", (), paramstyle="nope")
+
+
+def test_sqlfp_normalize_many():
+    results = sqlfp.normalize_many(["SELECT 1", b"SELECT * FROM t WHERE a = 'x'"], dialect="postgres")
+    assert [r.normalized for r in results] == ["SELECT ?", "SELECT * FROM t WHERE a = ?"]
+    assert results[1].hash == sqlfp.normalize("SELECT * FROM t WHERE a = 'x'", dialect="postgres").hash
+
+
+def test_sqlfp_normalize_many_errors():
+    with pytest.raises(ValueError, match="Parse error"):
+        sqlfp.normalize_many(["SELECT 1", "SELEC 1"])
+    results = sqlfp.normalize_many(["SELECT 1", "SELEC 1", "SELECT 2"], on_error="none")
+    assert [r and r.normalized for r in results] == ["SELECT ?", None, "SELECT ?"]
+    with pytest.raises(ValueError, match="Unsupported on_error: skip"):
+        sqlfp.normalize_many([], on_error="skip")
+
+
+@pytest.mark.parametrize(
+    "sql,statement_type",
+    [
+        ("SELECT * FROM t WHERE id = 1", "Query"),
+        ("INSERT INTO t (a) VALUES (1)", "Insert"),
+        ("UPDATE t SET a = 1", "Update"),
+        ("CREATE TABLE t (a INT)", "CreateTable"),
+    ],
+)
+def test_sqlfp_lean(sql, statement_type):
+    result = sqlfp.normalize(sql, lean=True)
+    assert type(result).__name__ == "LeanResult"
+    assert result.hash == sqlfp.normalize(sql).hash
+    assert result.statement_type == statement_type
+    assert not hasattr(result, "normalized")
+    with pytest.raises(AttributeError):
+        result.hash = "x"
+    assert repr(result) == f"LeanResult(hash='{result.hash[:8]}', statement_type='{statement_type}')"
+
+
+def test_sqlfp_lean_many():
+    results = sqlfp.normalize_many(["SELECT 1", "SELEC 1", "DELETE FROM t"], on_error="none", lean=True)
+    assert [r and r.statement_type for r in results] == ["Query", None, "Delete"]
+    assert results[0].hash == sqlfp.normalize("SELECT 1").hash
+
+
+@pytest.mark.parametrize(
+    "algorithm,encoding,expected",
+    [
+        (None, None, sha256(b"SELECT ?").hexdigest()),
+        ("sha256", "base64", "ZsuzpA1LvRULdYJa0pGmVFOZ8wmPwQeeTYtbsGGmpIE="),
+        ("xxhash64", None, "159e65dba30e5a01"),
+        ("xxhash64", "int", 0x159E65DBA30E5A01),
+        ("xxhash64", "base64", "FZ5l26MOWgE="),
+        ("blake3", None, "32a10cba4ff24a0e20704a2a67e5486601730f111b0bcb0c586fd12ee5cac098"),
+    ],
+)
+def test_sqlfp_hash_algorithm(algorithm, encoding, expected):
+    result = sqlfp.normalize("SELECT 1", hash_algorithm=algorithm, hash_encoding=encoding)
+    assert result.hash == result.shape_hash == expected
+    assert result.hash_algorithm == (algorithm or "sha256")
+    assert sqlfp.normalize("SELECT 2", hash_algorithm=algorithm, hash_encoding=encoding, lean=True).hash == expected
+    in_list = sqlfp.normalize("SELECT * FROM t WHERE a IN (1, 2)", hash_algorithm=algorithm, hash_encoding=encoding)
+    assert in_list.shape_hash == sqlfp.normalize("SELECT * FROM t WHERE a IN (3)", hash_algorithm=algorithm, hash_encoding=encoding).hash
+
+
+def test_sqlfp_hash_algorithm_outputs(tmp_path):
+    result = sqlfp.normalize("SELECT 1", hash_algorithm="xxhash64", hash_encoding="int")
+    assert repr(result) == "NormalizeResult(hash=1557794515124640257, normalized='SELECT ?')"
+    assert result.to_msgpack().startswith(b"\xde\x00\x14\xaanormalized\xa8SELECT ?\xa4hash\xcf\x15\x9e\x65\xdb\xa3\x0e\x5a\x01")
+    path = tmp_path / "q.sql"
+    path.write_text("SELECT 1\n")
+    output = tmp_path / "out.jsonl"
+    sqlfp.normalize_file(path, output=output, hash_algorithm="xxhash64", hash_encoding="int")
+    assert json.loads(output.read_text())["hash"] == result.hash
+
+
+def test_sqlfp_hash_algorithm_errors():
+    with pytest.raises(ValueError, match="Unknown hash_algorithm 'md5'"):
+        sqlfp.normalize("SELECT 1", hash_algorithm="md5")
+    with pytest.raises(ValueError, match="Unknown hash_encoding 'hexa'"):
+        sqlfp.normalize("SELECT 1", hash_encoding="hexa")
+    with pytest.raises(ValueError, match="hash_encoding 'int' needs hash_algorithm 'xxhash64', not 'blake3'"):
+        sqlfp.normalize("SELECT 1", hash_algorithm="blake3", hash_encoding="int")
+    with pytest.raises(ValueError, match="hash_encoding 'int' needs hash_algorithm 'xxhash64', not 'sha256'"):
+        sqlfp.configure(hash_encoding="int")
+    try:
+        settings = sqlfp.configure(reset=True, hash_algorithm="xxhash64", hash_encoding="int")
+        assert settings == {"hash_algorithm": "xxhash64", "hash_encoding": "int"}
+        assert sqlfp.normalize("SELECT 1").hash == 0x159E65DBA30E5A01
+    finally:
+        sqlfp.configure(reset=True)
+
+
+def test_sqlfp_normalize_many_progress():
+    calls = []
+    queries = ["SELECT 1", "SELEC", "SELECT 2", "SELECT 3", "SELECT 4"]
+    sqlfp.normalize_many(queries, on_error="none", progress=lambda *a: calls.append(a), progress_every=2)
+    assert calls == [(2, 1), (4, 1), (5, 1)]
+    calls.clear()
+    sqlfp.normalize_many(queries[:4], on_error="none", progress=lambda *a: calls.append(a), progress_every=2)
+    assert calls == [(2, 1), (4, 1)]
+    calls.clear()
+    sqlfp.normalize_many([], progress=lambda *a: calls.append(a))
+    assert calls == [(0, 0)]
+    with pytest.raises(ValueError, match="progress_every must be positive"):
+        sqlfp.normalize_many([], progress_every=0)
+
+
+def test_sqlfp_normalize_many_parallel_order():
+    # Several chunks, mixing dialects and failures
+    queries = []
+    for i in range(3000):
+        if i % 7 == 0:
+            queries.append("SELEC %d" % i)
+        elif i % 5 == 0:
+            queries.append(("SELECT a FROM t WHERE b = $1 AND c = %d" % i, "postgres"))
+        else:
+            queries.append("SELECT a%d FROM t WHERE b = %d" % (i % 11, i))
+    results = sqlfp.normalize_many(queries, on_error="none")
+    assert len(results) == len(queries)
+    for query, result in zip(queries, results):
+        if isinstance(query, tuple):
+            assert result.hash == sqlfp.normalize(query[0], dialect=query[1]).hash
+        elif query.startswith("SELEC "):
+            assert result is None
+        else:
+            assert result.hash == sqlfp.normalize(query).hash
+            assert result.original == query
+    with pytest.raises(ValueError, match="Parse error"):
+        sqlfp.normalize_many(queries)
+
+
+def test_sqlfp_normalize_many_interrupted():
+    def rows():
+        yield "SELECT 1"
+        yield "SELECT 2"
+        raise KeyboardInterrupt
+
+    results = sqlfp.normalize_many(rows())
+    assert [r.normalized for r in results] == ["SELECT ?", "SELECT ?"]
+
+
+def test_sqlfp_normalize_many_interrupted_in_progress():
+    def progress(processed, errors):
+        if processed == 2:
+            raise KeyboardInterrupt
+
+    results = sqlfp.normalize_many(["SELECT 1"] * 5, progress=progress, progress_every=1)
+    assert len(results) == 2
+
+
+def test_sqlfp_normalize_many_sigint():
+    import signal
+
+    def rows():
+        yield "SELECT 1"
+        signal.raise_signal(signal.SIGINT)
+        yield "SELECT 2"
+
+    assert len(sqlfp.normalize_many(rows())) == 1
+
+
+@pytest.mark.parametrize(
+    "a,b",
+    [
+        ("SELECT * FROM t WHERE id IN (1, 2, 3)", "SELECT * FROM t WHERE id IN (4)"),
+        ("INSERT INTO t (a, b) VALUES (1, 'x'), (2, 'y')", "INSERT INTO t (a, b) VALUES (3, 'z')"),
+        (
+            "SELECT * FROM t WHERE a IN (1, 2) AND b IN (SELECT c FROM u WHERE d IN (1, 2, 3))",
+            "SELECT * FROM t WHERE a IN (1) AND b IN (SELECT c FROM u WHERE d IN (5))",
+        ),
+    ],
+)
+def test_sqlfp_shape_hash_ignores_list_lengths(a, b):
+    ra, rb = sqlfp.normalize(a), sqlfp.normalize(b)
+    assert ra.hash != rb.hash
+    assert ra.shape_hash == rb.shape_hash
+    assert rb.shape_hash == rb.hash
+
+
+def test_sqlfp_shape_hash_keeps_normalized_text():
+    result = sqlfp.normalize("SELECT * FROM t WHERE id IN (1, 2, 3)")
+    assert result.normalized == "SELECT * FROM t WHERE id IN (?, ?, ?)"
+    assert result.shape_hash == sqlfp.normalize("SELECT * FROM t WHERE id IN (1)").hash
+
+
+def test_sqlfp_shape_hash_distinguishes_columns():
+    a = sqlfp.normalize("SELECT * FROM t WHERE id IN (a, b)")
+    b = sqlfp.normalize("SELECT * FROM t WHERE id IN (a)")
+    assert a.shape_hash != b.shape_hash
+
+
+def test_sqlfp_shape_hash_without_literal_parameterize():
+    a = sqlfp.normalize("SELECT * FROM t WHERE id IN (1, 2)", disable_passes=["literal-parameterize"])
+    b = sqlfp.normalize("SELECT * FROM t WHERE id IN (3)", disable_passes=["literal-parameterize"])
+    assert a.shape_hash != b.hash
+    assert a.shape_hash == sqlfp.normalize("SELECT * FROM t WHERE id IN (1)", disable_passes=["literal-parameterize"]).hash
+
+
+def test_sqlfp_param_positions():
+    result = sqlfp.normalize("INSERT INTO t (a, b) VALUES (1, 'x'), (2, 'y'), (3, now())")
+    assert result.params == ["1", "'x'", "2", "'y'", "3"]
+    assert result.metadata["param_positions"] == [
+        {"list": 0, "row": 0, "element": 0},
+        {"list": 0, "row": 0, "element": 1},
+        {"list": 0, "row": 1, "element": 0},
+        {"list": 0, "row": 1, "element": 1},
+        {"list": 0, "row": 2, "element": 0},
+    ]
+    result = sqlfp.normalize("SELECT * FROM t WHERE a = 1 AND b IN (2, 3) AND c IN (4, 5)")
+    assert result.metadata["param_positions"] == [
+        None,
+        {"list": 0, "element": 0},
+        {"list": 0, "element": 1},
+        {"list": 1, "element": 0},
+        {"list": 1, "element": 1},
+    ]
+
+
+def test_sqlfp_param_positions_without_collapse():
+    assert "param_positions" not in sqlfp.normalize("SELECT * FROM t WHERE a IN (1)").metadata
+    assert "param_positions" not in sqlfp.normalize("INSERT INTO t VALUES (1, 2)").metadata
+
+
+def test_sqlfp_param_positions_redactor():
+    result = sqlfp.normalize("SELECT * FROM t WHERE a IN (1, 2, 3)", redactor=lambda i, clause, value: None if value == "2" else value)
+    assert result.params == ["1", "3"]
+    assert result.metadata["param_positions"] == [{"list": 0, "element": 0}, {"list": 0, "element": 2}]
+
+
+def test_sqlfp_shape_hash_execute():
+    result, _ = sqlfp.fingerprint_execute("SELECT * FROM t WHERE id IN (%s, %s, %s)", (1, 2, 3))
+    assert result.shape_hash == sqlfp.normalize("SELECT * FROM t WHERE id IN (1)").hash
+
+
+@pytest.mark.parametrize(
+    "sql,placeholder,expected",
+    [
+        ("SELECT * FROM t WHERE a IN (1, 2, 3) AND b = 4", "?", "SELECT * FROM t WHERE a IN (?) AND b = ?"),
+        ("SELECT * FROM t WHERE a IN (1, 2, 3) AND b = 4", "${n}", "SELECT * FROM t WHERE a IN ($1) AND b = $2"),
+        ("SELECT * FROM t WHERE a NOT IN ('x', 'y')", "?", "SELECT * FROM t WHERE a NOT IN (?)"),
+        ("SELECT * FROM t WHERE a IN (1, b, 3)", "?", "SELECT * FROM t WHERE a IN (?, b, ?)"),
+    ],
+)
+def test_sqlfp_collapse_in_lists(sql, placeholder, expected):
+    result = sqlfp.normalize(sql, placeholder=placeholder, collapse_in_lists=True)
+    assert result.normalized == expected
+
+
+def test_sqlfp_collapse_in_lists_params():
+    a = sqlfp.normalize("SELECT * FROM t WHERE a IN (1, 2, 3) AND b = 4", collapse_in_lists=True)
+    b = sqlfp.normalize("SELECT * FROM t WHERE a IN (5) AND b = 6", collapse_in_lists=True)
+    assert a.hash == b.hash
+    assert a.params == ["1", "2", "3", "4"]
+    assert a.metadata["param_positions"] == [
+        {"list": 0, "element": 0},
+        {"list": 0, "element": 1},
+        {"list": 0, "element": 2},
+        None,
+    ]
+    assert sqlfp.normalize("SELECT * FROM t WHERE a IN (1, 2, 3)").normalized == "SELECT * FROM t WHERE a IN (?, ?, ?)"
+    assert sqlfp.normalize(
+        "SELECT * FROM t WHERE a IN (1, 2)", collapse_in_lists=True, disable_passes=["literal-parameterize"]
+    ).normalized == "SELECT * FROM t WHERE a IN (1, 2)"
+
+
+@pytest.mark.parametrize(
+    "sql,placeholder,expected,rows",
+    [
+        ("INSERT INTO t VALUES (1, 'a'), (2, 'b'), (3, 'c')", "?", "INSERT INTO t VALUES (?, ?)", 3),
+        ("INSERT INTO t (a, b) VALUES (1, now()), (2, now()) RETURNING a", "${n}", "INSERT INTO t (a, b) VALUES ($1, NOW()) RETURNING a", 2),
+        ("INSERT INTO t VALUES (1, now()), (2, 'b')", "?", "INSERT INTO t VALUES (?, NOW()), (?, ?)", None),
+        ("INSERT INTO t VALUES (1, 'a')", "?", "INSERT INTO t VALUES (?, ?)", None),
+    ],
+)
+def test_sqlfp_collapse_values(sql, placeholder, expected, rows):
+    result = sqlfp.normalize(sql, placeholder=placeholder, collapse_values=True)
+    assert result.normalized == expected
+    assert result.metadata.get("values_rows") == rows
+
+
+def test_sqlfp_collapse_values_params():
+    a = sqlfp.normalize("INSERT INTO t VALUES (1, 'a'), (2, 'b')", collapse_values=True)
+    b = sqlfp.normalize("INSERT INTO t VALUES (3, 'c')", collapse_values=True)
+    assert a.hash == b.hash
+    assert a.params == ["1", "'a'", "2", "'b'"]
+    assert a.metadata["param_positions"] == [
+        {"list": 0, "row": 0, "element": 0},
+        {"list": 0, "row": 0, "element": 1},
+        {"list": 0, "row": 1, "element": 0},
+        {"list": 0, "row": 1, "element": 1},
+    ]
+    assert sqlfp.normalize("INSERT INTO t VALUES (1), (2)").normalized == "INSERT INTO t VALUES (?), (?)"
+    assert sqlfp.normalize(
+        "INSERT INTO t VALUES (1), (2)", collapse_values=True, disable_passes=["literal-parameterize"]
+    ).normalized == "INSERT INTO t VALUES (1), (2)"
+
+
+@pytest.mark.parametrize(
+    "placeholder,start,expected",
+    [
+        ("${n}", None, "SELECT * FROM t WHERE a = $1 AND b IN ($2, $3)"),
+        ("${n}", 5, "SELECT * FROM t WHERE a = $5 AND b IN ($6, $7)"),
+        (":b{n}", 0, "SELECT * FROM t WHERE a = :b0 AND b IN (:b1, :b2)"),
+        ("?", 5, "SELECT * FROM t WHERE a = ? AND b IN (?, ?)"),
+    ],
+)
+def test_sqlfp_numbered_placeholders(placeholder, start, expected):
+    sql = "SELECT * FROM t WHERE a = 1 AND b IN ('x', 'y')"
+    result = sqlfp.normalize(sql, dialect="postgres", placeholder=placeholder, placeholder_start=start)
+    assert result.normalized == expected
+    assert result.params == ["1", "'x'", "'y'"]
+
+
+def test_sqlfp_numbered_placeholders_keep_existing():
+    result = sqlfp.normalize(
+        "SELECT * FROM t WHERE tenant = $1 AND id = 7", dialect="postgres", placeholder="${n}", placeholder_start=5
+    )
+    assert result.normalized == "SELECT * FROM t WHERE tenant = $1 AND id = $5"
+    assert result.metadata["binds"] == ["$1"]
+
+
+@pytest.mark.parametrize(
+    "placeholder, start, expected",
+    [
+        ("${n}", None, "SELECT * FROM t WHERE a = $1 AND b = $3 AND c = $2 AND d IN ($4, $5)"),
+        ("${n}", 7, "SELECT * FROM t WHERE a = $1 AND b = $7 AND c = $2 AND d IN ($8, $9)"),
+        # Binds of another style do not take numbers
+        (":{n}", None, "SELECT * FROM t WHERE a = $1 AND b = :1 AND c = $2 AND d IN (:2, :3)"),
+        ("?", None, "SELECT * FROM t WHERE a = $1 AND b = ? AND c = $2 AND d IN (?, ?)"),
+    ],
+)
+def test_sqlfp_numbered_placeholders_after_binds(placeholder, start, expected):
+    sql = "SELECT * FROM t WHERE a = $1 AND b = 5 AND c = $2 AND d IN ('x', 'y')"
+    result = sqlfp.normalize(sql, dialect="postgres", placeholder=placeholder, placeholder_start=start)
+    assert result.normalized == expected
+    assert result.params == ["5", "'x'", "'y'"]
+    assert result.metadata["binds"] == ["$1", "$2"]
+
+
+def test_sqlfp_numbered_placeholders_after_binds_compat():
+    sql = "SELECT * FROM t WHERE a = $2 AND b IN (1, 2)"
+    kwargs = dict(dialect="postgres", placeholder="${n}")
+    assert sqlfp.normalize(sql, **kwargs).normalized == "SELECT * FROM t WHERE a = $2 AND b IN ($3, $4)"
+    # The shape renumbers the literals only
+    shape = sqlfp.normalize("SELECT * FROM t WHERE a = $2 AND b IN ($3)", **kwargs)
+    assert sqlfp.normalize(sql, **kwargs).shape_hash == shape.hash
+    assert sqlfp.normalize(sql, compat="0.1", **kwargs).normalized == "SELECT * FROM t WHERE a = $2 AND b IN ($1, $2)"
+    assert "binds" not in sqlfp.normalize("SELECT * FROM t WHERE a = 1").metadata
+
+
+@pytest.mark.parametrize(
+    "sql, expected, binds",
+    [
+        (
+            "select * from t where a = :1 and b = :B1 and c = :name and d = 5",
+            "SELECT * FROM t WHERE a = :1 AND b = :B1 AND c = :name AND d = ?",
+            [":1", ":B1", ":name"],
+        ),
+        ("SELECT nvl(:b1, 0) FROM dual WHERE :2 = a", "SELECT NVL(:b1, ?) FROM dual WHERE :2 = a", [":b1", ":2"]),
+        ('SELECT * FROM t WHERE a = :"Name" AND b = :"x y"', 'SELECT * FROM t WHERE a = :"Name" AND b = :"x y"', [':"Name"', ':"x y"']),
+        ("SELECT * FROM t WHERE a LIKE :1 ESCAPE :2 AND b = :3", "SELECT * FROM t WHERE a LIKE :1 ESCAPE :2 AND b = :3", [":1", ":2", ":3"]),
+    ],
+)
+def test_sqlfp_oracle_binds(sql, expected, binds):
+    result = sqlfp.normalize(sql, dialect="oracle")
+    assert result.normalized == expected
+    assert result.metadata["binds"] == binds
+    assert sqlfp.verify(sql, dialect="oracle")["stable"]
+
+
+def test_sqlfp_oracle_quoted_binds_compat():
+    sql = 'SELECT * FROM t WHERE a = :"x y"'
+    assert sqlfp.normalize(sql, dialect="oracle", compat="0.1").normalized == "SELECT * FROM t WHERE a = :x y"
+
+
+def test_sqlfp_numbered_placeholders_redactor_keeps_numbering():
+    result = sqlfp.normalize(
+        "SELECT * FROM t WHERE a = 1 AND b = 2",
+        placeholder="${n}",
+        redactor=lambda index, clause, value: None if index == 0 else value,
+    )
+    assert result.normalized == "SELECT * FROM t WHERE a = $1 AND b = $2"
+    assert result.params == ["2"]
+
+
+def test_sqlfp_numbered_placeholders_shape_hash():
+    kwargs = dict(dialect="postgres", placeholder="${n}", placeholder_start=5)
+    a = sqlfp.normalize("SELECT * FROM t WHERE a IN (1, 2, 3) AND b = 4", **kwargs)
+    b = sqlfp.normalize("SELECT * FROM t WHERE a IN (1) AND b = 4", **kwargs)
+    assert a.normalized == "SELECT * FROM t WHERE a IN ($5, $6, $7) AND b = $8"
+    assert a.hash != b.hash
+    assert a.shape_hash == b.shape_hash == b.hash
+
+
+def test_sqlfp_placeholder_start_configure():
+    try:
+        config = sqlfp.configure(placeholder="${n}", placeholder_start=3)
+        assert config == {"placeholder": "${n}", "placeholder_start": 3}
+        assert sqlfp.normalize("SELECT 1, 2").normalized == "SELECT $3, $4"
+        assert sqlfp.normalize("SELECT 1, 2", placeholder_start=1).normalized == "SELECT $1, $2"
+    finally:
+        sqlfp.configure(reset=True)
+    with pytest.raises(OverflowError):
+        sqlfp.normalize("SELECT 1", placeholder="${n}", placeholder_start=-1)
+
+
+@pytest.mark.parametrize(
+    "style,expected",
+    [
+        ("question", "SELECT * FROM t WHERE a = ? AND b IN (?, ?)"),
+        ("dollar", "SELECT * FROM t WHERE a = $1 AND b IN ($2, $3)"),
+        ("colon", "SELECT * FROM t WHERE a = :p1 AND b IN (:p2, :p3)"),
+        ("pyformat", "SELECT * FROM t WHERE a = %s AND b IN (%s, %s)"),
+    ],
+)
+def test_sqlfp_placeholder_style(style, expected):
+    result = sqlfp.normalize("SELECT * FROM t WHERE a = 1 AND b IN (2, 'x')", style=style)
+    assert result.normalized == expected
+    assert result.params == ["1", "2", "'x'"]
+
+
+def test_sqlfp_placeholder_style_errors_and_configure():
+    with pytest.raises(ValueError, match="Unknown placeholder style"):
+        sqlfp.normalize("SELECT 1", style="qmark")
+    with pytest.raises(ValueError, match="cannot both be given"):
+        sqlfp.normalize("SELECT 1", style="dollar", placeholder="?")
+    assert sqlfp.normalize("SELECT 1", style="dollar", placeholder_start=3).normalized == "SELECT $3"
+    try:
+        assert sqlfp.configure(style="colon") == {"style": "colon"}
+        assert sqlfp.normalize("SELECT 1").normalized == "SELECT :p1"
+        assert sqlfp.normalize("SELECT 1", placeholder="?").normalized == "SELECT ?"
+        assert sqlfp.configure(placeholder="${n}") == {"placeholder": "${n}"}
+        assert sqlfp.normalize("SELECT 1", style="pyformat").normalized == "SELECT %s"
+    finally:
+        sqlfp.configure(reset=True)
+
+
+@pytest.mark.parametrize(
+    "sql, kwargs, normalized, params",
+    [
+        ("SELECT * FROM t WHERE b = 2 AND a = 1", {}, "SELECT * FROM t WHERE a = ? AND b = ?", ["1", "2"]),
+        ("SELECT * FROM t WHERE b = 2 AND a = 1", {"style": "dollar"}, "SELECT * FROM t WHERE a = $1 AND b = $2", ["1", "2"]),
+        ("SELECT * FROM t WHERE b = 2 AND a = 1", {"style": "colon"}, "SELECT * FROM t WHERE a = :p1 AND b = :p2", ["1", "2"]),
+        ("SELECT a % 2 FROM t WHERE b LIKE 'a%' AND c = '%'", {"style": "pyformat"}, "SELECT a %% %s FROM t WHERE b LIKE %s AND c = %s", ["2", "'a%'", "'%'"]),
+        ("SELECT * FROM t WHERE c = $2 AND b = 5 AND a = $1", {"style": "dollar", "dialect": "postgres"}, "SELECT * FROM t WHERE a = $1 AND b = $3 AND c = $2", ["5"]),
+    ],
+)
+def test_sqlfp_prepared(sql, kwargs, normalized, params):
+    passes = ["literal-parameterize", "predicate-sort"]
+    result = sqlfp.normalize(sql, prepared=True, passes=passes, **kwargs)
+    assert (result.normalized, result.params) == (normalized, params)
+    assert result.typed_params == [int(param) if param.isdigit() else param.strip("'") for param in params]
+
+
+def test_sqlfp_prepared_options():
+    sql = "SELECT * FROM t WHERE a = $1 AND b = 5"
+    result = sqlfp.normalize(sql, dialect="postgres", style="dollar", prepared=True)
+    assert (result.normalized, result.params, result.metadata["binds"]) == ("SELECT * FROM t WHERE a = $1 AND b = $2", ["5"], ["$1"])
+    assert result.hash == sqlfp.normalize(sql, dialect="postgres", style="dollar").hash
+    assert sqlfp.NormalizeOptions(prepared=True).prepared
+    with pytest.raises(ValueError, match="collapse_in_lists"):
+        sqlfp.normalize("SELECT 1", prepared=True, collapse_in_lists=True)
+
+
+@pytest.mark.parametrize(
+    "sql, kwargs",
+    [
+        # The driver would bind the literals' parameters to the ? already there
+        ("SELECT * FROM t WHERE b = 'x' AND a = ? LIMIT 5", {"dialect": "sqlite"}),
+        ("SELECT * FROM t WHERE b = 'x' AND a = :name", {"dialect": "oracle", "placeholder": ":{n}"}),
+        ("SELECT * FROM t WHERE b = 'x' AND a = $1", {"dialect": "postgres"}),
+        # The literals are not numbered after the binds
+        ("SELECT * FROM t WHERE b = 'x' AND a = $1", {"dialect": "postgres", "style": "dollar", "compat": "0.1"}),
+    ],
+)
+def test_sqlfp_prepared_binds(sql, kwargs):
+    with pytest.raises(sqlfp.UnnormalizableError, match="binds not numbered like the placeholder"):
+        sqlfp.normalize(sql, prepared=True, **kwargs)
+    assert sqlfp.normalize(sql, **kwargs).metadata["binds"]
+
+
+def test_sqlfp_fingerprint_stream_masks_literals():
+    a = sqlfp.fingerprint_stream("select * from t where a = 1 and b = 'x' -- note\n;")
+    b = sqlfp.fingerprint_stream("SELECT *\n  FROM t /* c */ WHERE a=42 AND b='it''s'")
+    c = sqlfp.fingerprint_stream("SELECT * FROM u WHERE a = 1 AND b = 'x'")
+    assert a == b
+    assert a != c
+    assert len(a) == 64
+
+
+@pytest.mark.parametrize(
+    "a,b,dialect",
+    [
+        ("INSERT INTO t VALUES (1, 'a''b')", "INSERT INTO t VALUES (2.5e-3, N'x')", "generic"),
+        ('SELECT "Col" FROM t', 'SELECT "Col"  FROM t -- x', "postgres"),
+        ("SELECT $tag$ it's $1 $tag$, $1", "SELECT 'x', $1", "postgres"),
+        ("SELECT 'a\\'b' # comment", "SELECT 'c'", "mysql"),
+        ("SELECT [a b] FROM t WHERE c = TRUE", "SELECT [a b] FROM t WHERE c = 0x1F", "mssql"),
+        ("SELECT 1 --1", "SELECT 1 - -1", "mysql"),
+        ("SELECT /*!50000 SQL_NO_CACHE */ a FROM t", "SELECT SQL_NO_CACHE a FROM t", "mysql"),
+        ("SELECT /* a /* b */ c */ 1", "SELECT 1", "postgres"),
+    ],
+)
+def test_sqlfp_fingerprint_stream_dialects(a, b, dialect):
+    assert sqlfp.fingerprint_stream(a, dialect) == sqlfp.fingerprint_stream(b, dialect)
+
+
+def test_sqlfp_fingerprint_stream_keeps_identifiers():
+    assert sqlfp.fingerprint_stream('SELECT "a" FROM t') != sqlfp.fingerprint_stream('SELECT "b" FROM t')
+    assert sqlfp.fingerprint_stream("SELECT a FROM t") != sqlfp.fingerprint_stream("SELECT A FROM t")
+    assert sqlfp.fingerprint_stream("SELECT $1") != sqlfp.fingerprint_stream("SELECT $2", "postgres")
+
+
+def test_sqlfp_fingerprint_stream_sources(tmp_path):
+    sql = "INSERT INTO t (a, b) VALUES " + ", ".join("(%d, 'v%d')" % (i, i) for i in range(2000))
+    expected = sqlfp.fingerprint_stream(sql)
+    assert sqlfp.fingerprint_stream(sql.encode()) == expected
+    assert sqlfp.fingerprint_stream(io.BytesIO(sql.encode()), chunk_size=7) == expected
+    assert sqlfp.fingerprint_stream(io.StringIO(sql), chunk_size=7) == expected
+    path = tmp_path / "big.sql"
+    path.write_text(sql)
+    assert sqlfp.fingerprint_stream(path) == expected
+    with open(path, "rb") as f:
+        assert sqlfp.fingerprint_stream(f) == expected
+
+
+def test_sqlfp_fingerprint_stream_large_input_bounded():
+    row = b"(1, 'some value', 3.14, NULL), "
+
+    class Rows(io.RawIOBase):
+        """100 MB of VALUES rows, generated as they are read"""
+
+        def __init__(self):
+            self.left = 100 * 1024 * 1024 // len(row)
+            self.started = False
+
+        def readable(self):
+            return True
+
+        def read(self, n=-1):
+            if not self.started:
+                self.started = True
+                return b"INSERT INTO t VALUES "
+            if self.left == 0:
+                return b""
+            count = min(self.left, max(1, n // len(row)))
+            self.left -= count
+            return row * count if self.left else row * (count - 1) + row[:-2]
+
+    assert len(sqlfp.fingerprint_stream(Rows())) == 64
+
+
+def test_sqlfp_fingerprint_stream_errors(tmp_path):
+    with pytest.raises(ValueError, match="Unterminated string literal"):
+        sqlfp.fingerprint_stream("SELECT 'abc")
+    with pytest.raises(ValueError, match="Unterminated block comment"):
+        sqlfp.fingerprint_stream("SELECT 1 /* x")
+    with pytest.raises(ValueError, match="Unterminated block comment"):
+        sqlfp.fingerprint_stream("SELECT 1 /*! x", "mysql")
+    with pytest.raises(ValueError, match="Unsupported dialect"):
+        sqlfp.fingerprint_stream("SELECT 1", "nope")
+    with pytest.raises(FileNotFoundError):
+        sqlfp.fingerprint_stream(tmp_path / "missing.sql")
+
+    class Broken:
+        def read(self, n):
+            raise RuntimeError("disk on fire")
+
+    with pytest.raises(RuntimeError, match="disk on fire"):
+        sqlfp.fingerprint_stream(Broken())
+
+
+@pytest.mark.parametrize("size", [1, 2, 3, 5, 64])
+def test_sqlfp_stream_fingerprinter(size):
+    statements = [
+        "SELECT * FROM t -- note\nWHERE a = 'it''s' AND b = 1.5e-3",
+        "INSERT INTO café (\"q\"\"id\", b) VALUES ($tag$a;b$tag$, E'x')",
+        "/* a; /* b; */ */ UPDATE t SET a = $1",
+    ]
+    script = ";\n".join(statements).encode() + b";\n"
+    fp = sqlfp.StreamFingerprinter("postgres")
+    digests = []
+    for at in range(0, len(script), size):
+        digests += fp.feed(script[at : at + size])
+    assert digests == [sqlfp.fingerprint_stream(sql, "postgres") for sql in statements]
+    assert fp.finish() is None
+
+
+def test_sqlfp_stream_fingerprinter_finish():
+    fp = sqlfp.StreamFingerprinter()
+    assert fp.feed("SELECT 1;; -- only a comment\n;SELECT 'a") == [sqlfp.fingerprint_stream("SELECT 1")]
+    assert fp.feed("b' FROM t") == []
+    assert fp.finish() == sqlfp.fingerprint_stream("SELECT 'ab' FROM t")
+    assert fp.finish() is None
+    fp.feed("SELECT 'abc")
+    with pytest.raises(ValueError, match="Unterminated string literal"):
+        fp.finish()
+    assert fp.feed("SELECT 2;") == [sqlfp.fingerprint_stream("SELECT 2")]
+    with pytest.raises(ValueError, match="Unsupported dialect"):
+        sqlfp.StreamFingerprinter("nope")
+
+
+def test_sqlfp_fingerprint_mysql_query():
+    results = sqlfp.fingerprint_mysql_query(b"\x03USE shop; SELECT * FROM t WHERE name = 'caf\xe9'", encoding="latin1")
+    assert [r.normalized for r in results] == ["USE shop", "SELECT * FROM t WHERE name = ?"]
+    assert results[1].params == ["'café'"]
+    assert results[1].metadata["database"] == "shop"
+    assert results[1].hash == sqlfp.normalize("SELECT * FROM t WHERE name = 'x'", dialect="mysql").hash
+
+
+@pytest.mark.parametrize(
+    "attributes",
+    [
+        b"\x00\x01",
+        # One VARCHAR attribute, `trace` = 'abc'
+        b"\x01\x01\x00\x01\xfe\x00\x05trace\x03abc",
+        # A LONGLONG and a NULL attribute
+        b"\x02\x01\x02\x01\x08\x00\x01a\x06\x00\x01b" + (7).to_bytes(8, "little"),
+    ],
+)
+def test_sqlfp_fingerprint_mysql_query_attributes(attributes):
+    [result] = sqlfp.fingerprint_mysql_query(b"\x03" + attributes + b"SELECT 1", query_attributes=True)
+    assert result.normalized == "SELECT ?"
+
+
+def test_sqlfp_fingerprint_mysql_query_errors():
+    with pytest.raises(ValueError, match="Not a COM_QUERY payload"):
+        sqlfp.fingerprint_mysql_query(b"\x16SELECT 1")
+    with pytest.raises(ValueError, match="Truncated COM_QUERY payload"):
+        sqlfp.fingerprint_mysql_query(b"\x03\x01\x01\x00\x01\xfe\x00\x05tr", query_attributes=True)
+    with pytest.raises(UnicodeDecodeError):
+        sqlfp.fingerprint_mysql_query(b"\x03SELECT '\xe9'")
+    assert sqlfp.fingerprint_mysql_query(b"\x03SELECT 1; SELEC 2", on_error="skip")[0].normalized == "SELECT ?"
+
+
+def test_sqlfp_fingerprint_postgres_message():
+    results = sqlfp.fingerprint_postgres_message(b"BEGIN; UPDATE t SET a = 1 WHERE b = $1\x00")
+    assert [r.normalized for r in results] == ["BEGIN", "UPDATE t SET a = ? WHERE b = $1"]
+    assert results[0].dialect_used == "postgresql"
+    [parsed] = sqlfp.fingerprint_postgres_message(b"s1\x00SELECT * FROM t WHERE a = $1\x00\x00\x01\x00\x00\x00\x17", "P")
+    assert parsed.normalized == "SELECT * FROM t WHERE a = $1"
+    [decoded] = sqlfp.fingerprint_postgres_message("SELECT 'é'\x00".encode("cp1252"), encoding="win-1252")
+    assert decoded.params == ["'é'"]
+
+
+def test_sqlfp_fingerprint_postgres_message_errors():
+    with pytest.raises(ValueError, match="Truncated Query message"):
+        sqlfp.fingerprint_postgres_message(b"SELECT 1")
+    with pytest.raises(ValueError, match="Truncated Parse message"):
+        sqlfp.fingerprint_postgres_message(b"s1\x00SELECT 1", "P")
+    with pytest.raises(ValueError, match="Unsupported message_type: B"):
+        sqlfp.fingerprint_postgres_message(b"SELECT 1\x00", "B")
+
+
+SLOWLOG = """\
+/usr/sbin/mysqld, Version: 8.0.36 (MySQL Community Server - GPL). started with:
+Tcp port: 3306  Unix socket: /var/run/mysqld/mysqld.sock
+Time                 Id Command    Argument
+# Time: 2024-01-15T10:00:00.123456Z
+# User@Host: app[app] @ web1 [10.0.0.5]  Id:    42
+# Query_time: 1.500250  Lock_time: 0.000100 Rows_sent: 1  Rows_examined: 1000
+use shop;
+SET timestamp=1705312800;
+SELECT *
+FROM orders WHERE id = 7;
+# User@Host: app[app] @  [10.0.0.6]  Id:    43
+# Query_time: 0.2  Lock_time: 0.000000 Rows_sent: 0  Rows_examined: 0
+SET timestamp=1705312801;
+SELECT * FROM orders WHERE id = 8;
+# Time: 2024-01-15T10:00:02.000000Z
+# User@Host: root[root] @ localhost []  Id:     8
+# Query_time: 0.000010  Lock_time: 0.000000 Rows_sent: 0  Rows_examined: 0
+SET timestamp=1705312802;
+# administrator command: Quit;
+# User@Host: root[root] @ localhost []  Id:     9
+# Query_time: 0.000010  Lock_time: 0.000000 Rows_sent: 0  Rows_examined: 0
+SET timestamp=1705312803;
+UPDATE t SET a = 1; DELETE FROM t WHERE b = 'x';
+"""
+
+
+def test_sqlfp_fingerprint_mysql_slowlog(tmp_path):
+    path = tmp_path / "slow.log"
+    path.write_text(SLOWLOG)
+    results = list(sqlfp.fingerprint_mysql_slowlog(path))
+    assert [r.normalized for r in results] == [
+        "SELECT * FROM orders WHERE id = ?",
+        "SELECT * FROM orders WHERE id = ?",
+        "UPDATE t SET a = ?",
+        "DELETE FROM t WHERE b = ?",
+    ]
+    first = results[0].metadata
+    assert {k: first[k] for k in ("line", "timestamp", "unix_timestamp", "user", "host", "thread_id", "database")} == {
+        "line": 9,
+        "timestamp": "2024-01-15T10:00:00.123456Z",
+        "unix_timestamp": 1705312800,
+        "user": "app",
+        "host": "web1",
+        "thread_id": 42,
+        "database": "shop",
+    }
+    assert (first["duration_us"], first["lock_time_us"], first["rows_sent"], first["rows_examined"]) == (1500250, 100, 1, 1000)
+    # No `# Time:`: the last one; no `use`: none
+    second = results[1].metadata
+    assert (second["timestamp"], second["host"], second["database"], second["duration_us"]) == (
+        "2024-01-15T10:00:00.123456Z",
+        "10.0.0.6",
+        None,
+        200000,
+    )
+    assert [r.metadata["thread_id"] for r in results[2:]] == [9, 9]
+    assert results[0].hash == sqlfp.normalize("SELECT * FROM orders WHERE id = 1", dialect="mysql").hash
+
+
+CSVLOG = """\
+2024-01-15 10:00:00.123 UTC,"app","shop",1234,"10.0.0.5:5432",65a4f0c0.4d2,1,"SELECT",2024-01-15 09:59:00 UTC,3/4,0,LOG,00000,"duration: 12.345 ms  statement: SELECT * FROM orders
+WHERE note = 'a ""quoted"" one' AND id = 7",,,,,,,,,"psql",client backend,,0
+2024-01-15 10:00:01.000 UTC,"app","shop",1234,"10.0.0.5:5432",65a4f0c0.4d2,2,"PARSE",2024-01-15 09:59:00 UTC,3/5,0,LOG,00000,"duration: 0.050 ms  parse <unnamed>: SELECT * FROM t WHERE a = $1",,,,,,,,,"app",client backend,,0
+2024-01-15 10:00:01.000 UTC,"app","shop",1234,"10.0.0.5:5432",65a4f0c0.4d2,3,"SELECT",2024-01-15 09:59:00 UTC,3/5,0,LOG,00000,"duration: 0.150 ms  execute <unnamed>: SELECT * FROM t WHERE a = $1","parameters: $1 = '5'",,,,,,,,"app",client backend,,0
+2024-01-15 10:00:02.000 UTC,,,1200,,65a4f0c0.4b0,1,,2024-01-15 09:59:00 UTC,,0,LOG,00000,"checkpoint starting: time",,,,,,,,,"",checkpointer,,0
+2024-01-15 10:00:03.000 UTC,"app","shop",1234,"10.0.0.5:5432",65a4f0c0.4d2,4,"idle",2024-01-15 09:59:00 UTC,3/6,0,LOG,00000,"statement: BEGIN; UPDATE t SET a = 2",,,,,,,,,"app",client backend,,0
+"""
+
+
+def test_sqlfp_fingerprint_postgres_csvlog(tmp_path):
+    path = tmp_path / "postgresql.csv"
+    path.write_text(CSVLOG)
+    results = list(sqlfp.fingerprint_postgres_csvlog(str(path)))
+    assert [r.normalized for r in results] == [
+        "SELECT * FROM orders WHERE note = ? AND id = ?",
+        "SELECT * FROM t WHERE a = $1",
+        "BEGIN",
+        "UPDATE t SET a = ?",
+    ]
+    assert results[0].params == ["'a \"quoted\" one'", "7"]
+    first = results[0].metadata
+    assert {k: first[k] for k in ("line", "timestamp", "user", "database", "pid", "session_id", "application_name")} == {
+        "line": 1,
+        "timestamp": "2024-01-15 10:00:00.123 UTC",
+        "user": "app",
+        "database": "shop",
+        "pid": 1234,
+        "session_id": "65a4f0c0.4d2",
+        "application_name": "psql",
+    }
+    assert (first["duration_us"], first["parameters"]) == (12345, None)
+    assert (results[1].metadata["duration_us"], results[1].metadata["parameters"]) == (150, "$1 = '5'")
+    assert [r.metadata["line"] for r in results] == [1, 4, 6, 6]
+    assert results[3].metadata["duration_us"] is None
+
+
+def test_sqlfp_fingerprint_logs_errors(tmp_path):
+    path = tmp_path / "slow.log"
+    path.write_text("# Time: 2024-01-15T10:00:00Z\nSELECT 1;\nSELEC 2;\n")
+    with pytest.raises(ValueError, match="slow.log:3"):
+        list(sqlfp.fingerprint_mysql_slowlog(path))
+    assert [r.normalized for r in sqlfp.fingerprint_mysql_slowlog(path, on_error="skip")] == ["SELECT ?"]
+    with pytest.raises(ValueError, match="slow.log:1: Not a csvlog row"):
+        list(sqlfp.fingerprint_postgres_csvlog(path))
+    assert list(sqlfp.fingerprint_postgres_csvlog(path, on_error="skip")) == []
+    path.write_text('2024-01-15,"app","unterminated\n')
+    with pytest.raises(ValueError, match="Unterminated quoted field"):
+        list(sqlfp.fingerprint_postgres_csvlog(path))
+    with pytest.raises(OSError):
+        sqlfp.fingerprint_mysql_slowlog(tmp_path / "missing.log")
+
+
+@pytest.mark.parametrize(
+    "sql,dialect,expected",
+    [
+        ("SELECT 1 -- one\nFROM t", "generic", "SELECT 1 \nFROM t"),
+        ("SELECT/* x */1", "generic", "SELECT 1"),
+        ("SELECT  /* a */ /* b */  1", "generic", "SELECT     1"),
+        # MySQL executes `/*! */` hints, so they are not comments
+        ("SELECT /*!40101 SQL_NO_CACHE */ a /* x */ FROM t", "mysql", "SELECT /*!40101 SQL_NO_CACHE */ a  FROM t"),
+        ("SELECT '-- not /* a comment */' AS \"/*x*/\"", "generic", "SELECT '-- not /* a comment */' AS \"/*x*/\""),
+        ("SELECT 1 # hash\n, 2", "mysql", "SELECT 1 \n, 2"),
+        ("SELECT 'it\\'s -- here' -- gone", "mysql", "SELECT 'it\\'s -- here' "),
+        ("SELECT /* outer /* inner */ still */ 1", "postgres", "SELECT  1"),
+        ("SELECT\r\n  a, -- a\r\n  b", "generic", "SELECT\r\n  a, \r\n  b"),
+        ("SELECT   a\tFROM t", "generic", "SELECT   a\tFROM t"),
+    ],
+)
+def test_sqlfp_strip_comments(sql, dialect, expected):
+    assert sqlfp.strip_comments(sql, dialect) == expected
+
+
+def test_sqlfp_strip_comments_errors():
+    with pytest.raises(ValueError, match="Tokenize error"):
+        sqlfp.strip_comments("SELECT 'abc")
+    with pytest.raises(ValueError, match="Unsupported dialect"):
+        sqlfp.strip_comments("SELECT 1", "nope")
+
+
+@pytest.mark.parametrize(
+    "sql,dialect,mask,expected",
+    [
+        (
+            "select *  from Users -- who\nWHERE email = 'bob@x.com' AND age > 42 /* c */",
+            "generic",
+            "?",
+            "select *  from Users -- who\nWHERE email = ? AND age > ? /* c */",
+        ),
+        ("SELECT N'x', X'AB', true, NULL, ? FROM t", "generic", "?", "SELECT ?, ?, ?, NULL, ? FROM t"),
+        ("SELECT $$secret$$, E'a\\'b', $1 FROM t", "postgres", "${n}", "SELECT $2, $3, $1 FROM t"),
+        ("SELECT \"x\", `c` FROM t WHERE a = -1.5e3", "mysql", "<redacted>", "SELECT <redacted>, `c` FROM t WHERE a = -<redacted>"),
+        ("SELEC garbage 'x' ((", "generic", "?", "SELEC garbage ? (("),
+    ],
+)
+def test_sqlfp_redact(sql, dialect, mask, expected):
+    assert sqlfp.redact(sql, dialect, mask=mask) == expected
+
+
+def test_sqlfp_redact_errors():
+    with pytest.raises(ValueError, match="Tokenize error"):
+        sqlfp.redact("SELECT 'abc")
+    with pytest.raises(ValueError, match="Unsupported dialect"):
+        sqlfp.redact("SELECT 1", "nope")
+
+
+@pytest.mark.parametrize(
+    "sql,dialect,read,written",
+    [
+        ("SELECT * FROM s.a JOIN b ON a.x = b.x WHERE c IN (SELECT y FROM c)", "generic", ["s.a", "b", "c"], []),
+        ("WITH o AS (SELECT * FROM orders) INSERT INTO archive SELECT * FROM o", "postgres", ["orders"], ["archive"]),
+        ("WITH o AS (SELECT * FROM orders) SELECT * FROM o JOIN o AS o2 ON true", "postgres", ["orders"], []),
+        ("UPDATE t SET a = (SELECT max(a) FROM t) FROM u WHERE t.id = u.id", "postgres", ["t", "u"], ["t"]),
+        ("DELETE FROM t USING u WHERE t.id = u.id", "postgres", ["u"], ["t"]),
+        ("DELETE t1 FROM t1 JOIN t2 ON t1.a = t2.a", "mysql", ["t1", "t2"], ["t1"]),
+        ("MERGE INTO t USING s ON t.id = s.id WHEN MATCHED THEN UPDATE SET a = s.a", "generic", ["s"], ["t"]),
+        ("INSERT INTO t (a) VALUES (1)", "generic", [], ["t"]),
+        ("CREATE TABLE x AS SELECT * FROM y", "generic", ["y"], ["x"]),
+    ],
+)
+def test_sqlfp_extract_tables(sql, dialect, read, written):
+    assert sqlfp.extract_tables(sql, dialect) == {"read": read, "written": written}
+
+
+def test_sqlfp_extract_tables_errors():
+    with pytest.raises(ValueError, match="Parse error"):
+        sqlfp.extract_tables("SELEC 1")
+    with pytest.raises(ValueError, match="Unsupported dialect"):
+        sqlfp.extract_tables("SELECT 1", "nope")
+
+
+@pytest.mark.parametrize(
+    "sql,dialect,expected",
+    [
+        (
+            "SELECT u.name, count(*) FROM users u JOIN orders o ON o.uid = u.id WHERE u.age > 3 AND o.paid GROUP BY u.name",
+            "generic",
+            {"select": ["u.name"], "join": ["o.uid", "u.id"], "where": ["u.age", "o.paid"], "group_by": ["u.name"]},
+        ),
+        (
+            "SELECT a FROM t JOIN s USING (id) WHERE b IN (SELECT c FROM x WHERE d = 1) ORDER BY a",
+            "generic",
+            {"join": ["id"], "select": ["a", "c"], "where": ["b", "d"], "order_by": ["a"]},
+        ),
+        ("SELECT a, b, a FROM t HAVING sum(b) > 1", "generic", {"select": ["a", "b"], "having": ["b"]}),
+        ("UPDATE t SET a = b + 1 WHERE c = 2", "generic", {"set": ["b"], "where": ["c"]}),
+        ("DELETE FROM t WHERE a = 1 RETURNING b", "postgres", {"where": ["a"], "returning": ["b"]}),
+        ("SELECT * FROM t WHERE flag = TRUE", "mssql", {"where": ["flag"]}),
+        ("SELECT 1", "generic", {}),
+    ],
+)
+def test_sqlfp_extract_columns(sql, dialect, expected):
+    assert sqlfp.extract_columns(sql, dialect) == expected
+
+
+def test_sqlfp_extract_columns_errors():
+    with pytest.raises(ValueError, match="Parse error"):
+        sqlfp.extract_columns("SELEC 1")
+    with pytest.raises(ValueError, match="Unsupported dialect"):
+        sqlfp.extract_columns("SELECT 1", "nope")
+
+
+def test_sqlfp_normalize_many_dialect_pairs():
+    results = sqlfp.normalize_many(
+        [
+            ("SELECT `a` FROM t WHERE b = 1", "mysql"),
+            ("SELECT a FROM t WHERE b = $1 AND c = 2", "postgres"),
+            "SELECT a FROM t WHERE b = 3",
+            ("SELECT a FROM t WHERE b = 4", None),
+            (b"SELECT `c` FROM t", "mysql"),
+        ],
+        dialect="sqlite",
+    )
+    assert [r.normalized for r in results] == [
+        "SELECT `a` FROM t WHERE b = ?",
+        "SELECT a FROM t WHERE b = $1 AND c = ?",
+        "SELECT a FROM t WHERE b = ?",
+        "SELECT a FROM t WHERE b = ?",
+        "SELECT `c` FROM t",
+    ]
+    dialects = {e["dialect"] for e in sqlfp.engine_pool()}
+    assert {"mysql", "postgresql", "sqlite"} <= dialects
+
+
+def test_sqlfp_normalize_many_dialect_pair_errors():
+    queries = [("SELECT 1", "nope"), ("SELECT 1", "mysql", "extra"), ("SELECT 2", "mysql")]
+    results = sqlfp.normalize_many(queries, on_error="none")
+    assert results[:2] == [None, None]
+    assert results[2].normalized == "SELECT ?"
+    with pytest.raises(ValueError, match="Unsupported dialect: nope"):
+        sqlfp.normalize_many(queries)
+    with pytest.raises(TypeError, match=r"\(sql, dialect\) pairs"):
+        sqlfp.normalize_many(queries[1:])
+
+
+@pytest.mark.parametrize(
+    "sql,dialect,param,prefix",
+    [
+        ("SELECT * FROM t WHERE a = N'text'", "mssql", "'text'", "N"),
+        ("SELECT * FROM t WHERE a = N'text'", "mysql", "'text'", "N"),
+        ("SELECT * FROM t WHERE a = E'text\\n'", "postgres", "'text\\n'", "E"),
+        ("SELECT * FROM t WHERE a = X'AB'", "generic", "'AB'", "X"),
+        ("SELECT * FROM t WHERE a = U&'d\\0061t'", "postgres", "'dat'", "U&"),
+        ("SELECT * FROM t WHERE a = 'text'", "mssql", "'text'", None),
+    ],
+)
+def test_sqlfp_prefixed_strings(sql, dialect, param, prefix):
+    result = sqlfp.normalize(sql, dialect=dialect)
+    assert result.normalized == "SELECT * FROM t WHERE a = ?"
+    assert result.params == [param]
+    assert result.param_types == [prefix]
+    assert result.hash == sqlfp.normalize("SELECT * FROM t WHERE a = 'other'", dialect=dialect).hash
+
+
+def test_sqlfp_param_types_follow_redactor():
+    result = sqlfp.normalize(
+        "SELECT * FROM t WHERE a = 1 AND b = N'x' AND c = E'y'",
+        dialect="postgres",
+        redactor=lambda index, clause, value: None if index == 1 else value,
+    )
+    assert result.params == ["1", "'y'"]
+    assert result.param_types == [None, "E"]
+
+
+def test_sqlfp_param_kinds():
+    result = sqlfp.normalize("SELECT 42, 4.20, 1.5e3, .5, 1E2, 'x', TRUE FROM t WHERE a = -7")
+    assert result.params == ["42", "4.20", "1.5e3", ".5", "1E2", "'x'", "true", "7"]
+    assert result.param_kinds == ["integer", "decimal", "float", "decimal", "float", None, None, "integer"]
+    folded = sqlfp.normalize("SELECT -2.5", enable_passes=["negative-literal-fold"])
+    assert (folded.params, folded.param_kinds) == (["-2.5"], ["decimal"])
+    redacted = sqlfp.normalize("SELECT 1, 2.0", redactor=lambda index, clause, value: None if index == 0 else value)
+    assert redacted.param_kinds == ["decimal"]
+
+
+@pytest.mark.parametrize(
+    "sql,dialect,expected",
+    [
+        ("SELECT 42, 4.20, 1.5e3, TRUE, false FROM t WHERE a = -7", "generic", [42, 4.2, 1500.0, True, False, 7]),
+        ("SELECT 'it''s', x'0aff', 99999999999999999999999", "generic", ["it's", b"\n\xff", 99999999999999999999999]),
+        ("SELECT E'a\\nb', $t$x'y$t$, N'n', 'true'", "postgres", ["a\nb", "x'y", "n", "true"]),
+        ("SELECT 'a\\'b', \"c\"", "mysql", ["a'b", "c"]),
+        ("SELECT TRUE, 0x0A", "mssql", [True, b"\n"]),
+    ],
+)
+def test_sqlfp_typed_params(sql, dialect, expected):
+    typed = sqlfp.normalize(sql, dialect=dialect).typed_params
+    assert typed == expected
+    assert [type(value) for value in typed] == [type(value) for value in expected]
+
+
+@pytest.mark.parametrize(
+    "sql,kwargs,expected",
+    [
+        ("SELECT * FROM t WHERE name = 'é''x' AND\n  id IN (1, 22)", {}, ["'é''x'", "1", "22"]),
+        ("SELECT * FROM t WHERE d = DATE '2020-01-01' AND n = N'y' AND f = TRUE", {}, ["'2020-01-01'", "N'y'", "TRUE"]),
+        ("SELECT * FROM t WHERE flag = TRUE AND s = \"dq\"", {"dialect": "mssql"}, ["TRUE"]),
+        ("SELECT * FROM t WHERE flag = TRUE AND s = \"dq\"", {"dialect": "mysql"}, ["TRUE", '"dq"']),
+        ("SELECT a FROM t WHERE id IN (1, 2, 3", {"truncated_input": True}, ["1", "2"]),
+        ("SELECT a FROM {{ ref('t') }} WHERE id = 1", {"templates": True}, [None]),
+        ("LOAD DATA INFILE '/tmp/f' INTO TABLE t", {"dialect": "mysql"}, [None]),
+    ],
+)
+def test_sqlfp_param_spans(sql, kwargs, expected):
+    result = sqlfp.normalize(sql, **kwargs)
+    raw = result.original.encode()
+    assert [raw[span[0] : span[1]].decode() if span else None for span in result.param_spans] == expected
+
+
+def test_sqlfp_param_spans_rewritten():
+    rename = lambda sql: sql.replace("id", "idx")
+    before = sqlfp.normalize("SELECT a FROM t WHERE id = 1", passes=[rename, "literal-parameterize"])
+    after = sqlfp.normalize("SELECT a FROM t WHERE id = 1", passes=["literal-parameterize", rename])
+    assert (before.param_spans, after.param_spans) == ([None], [(27, 28)])
+    redacted = sqlfp.normalize("SELECT 1, 2", redactor=lambda index, clause, value: None if index == 0 else value)
+    assert redacted.param_spans == [(10, 11)]
+
+
+def test_sqlfp_typed_params_redactor():
+    result = sqlfp.normalize("SELECT 'secret', 1", redactor=lambda index, clause, value: "<redacted>" if index == 0 else value)
+    assert result.typed_params == ["<redacted>", 1]
+
+
+@pytest.mark.parametrize(
+    "sql, kwargs, bound",
+    [
+        ("SELECT * FROM t WHERE a = 'it''s' AND b = 42 AND c = N'x'", {}, "SELECT * FROM t WHERE a = 'it''s' AND b = 42 AND c = N'x'"),
+        ("SELECT * FROM t WHERE d = '?' AND e = 1", {}, "SELECT * FROM t WHERE d = '?' AND e = 1"),
+        ("SELECT * FROM t WHERE a = $1 AND b IN (5, 6)", {"dialect": "postgres", "placeholder": "${n}"}, "SELECT * FROM t WHERE a = $1 AND b IN (5, 6)"),
+        ("SELECT * FROM t WHERE a = E'x\\ny'", {"dialect": "postgres"}, "SELECT * FROM t WHERE a = E'x\\ny'"),
+    ],
+)
+def test_sqlfp_bind_own_params(sql, kwargs, bound):
+    assert sqlfp.normalize(sql, **kwargs).bind() == bound
+
+
+@pytest.mark.parametrize(
+    "dialect, params, bound",
+    [
+        ("generic", ["o'k", 1.5, None, True], "SELECT * FROM t WHERE a = 'o''k' AND b = 1.5 AND c = NULL AND d = TRUE"),
+        ("mysql", ["a\\b", 7, b"\x01\xff", False], "SELECT * FROM t WHERE a = 'a\\\\b' AND b = 7 AND c = X'01FF' AND d = FALSE"),
+        ("postgres", ["a\\b", decimal.Decimal("1.50"), b"\x01", datetime.date(2024, 1, 2)], "SELECT * FROM t WHERE a = 'a\\b' AND b = 1.50 AND c = '\\x01'::BYTEA AND d = '2024-01-02'"),
+        ("mssql", ["x", 2, b"\x0a", True], "SELECT * FROM t WHERE a = 'x' AND b = 2 AND c = 0x0A AND d = 1"),
+    ],
+)
+def test_sqlfp_bind_values(dialect, params, bound):
+    result = sqlfp.normalize("SELECT * FROM t WHERE a = 'v' AND b = 0 AND c = 'w' AND d = FALSE", dialect=dialect)
+    assert result.bind(params) == bound
+
+
+def test_sqlfp_bind_function():
+    assert sqlfp.bind('SELECT ? FROM t WHERE "?" = ?', [1, "a"], "postgres") == "SELECT 1 FROM t WHERE \"?\" = 'a'"
+    assert sqlfp.bind("SELECT :p2, :p1", [1, "a"], placeholder=":p{n}") == "SELECT 'a', 1"
+    assert sqlfp.bind("SELECT $1, $2, $3", ["a", "b"], placeholder="${n}", placeholder_start=2) == "SELECT $1, 'a', 'b'"
+    with pytest.raises(ValueError, match="More placeholders"):
+        sqlfp.bind("SELECT ?, ?", [1])
+    with pytest.raises(ValueError, match="have no placeholder"):
+        sqlfp.bind("SELECT ?", [1, 2])
+    with pytest.raises(ValueError, match="nan"):
+        sqlfp.bind("SELECT ?", [float("nan")])
+    with pytest.raises(TypeError, match="object"):
+        sqlfp.bind("SELECT ?", [object()])
+
+
+@pytest.mark.parametrize(
+    "sql, dialect, normalized, params",
+    [
+        ("SELECT * FROM t WHERE d > DATE '2020-01-01'", "generic", "SELECT * FROM t WHERE d > DATE ?", ["'2020-01-01'"]),
+        (
+            "SELECT EXTRACT(YEAR FROM TIMESTAMP '2020-01-01 00:00')",
+            "postgres",
+            "SELECT EXTRACT(YEAR FROM TIMESTAMP ?)",
+            ["'2020-01-01 00:00'"],
+        ),
+        (
+            "SELECT * FROM t WHERE MATCH (a, b) AGAINST ('foo' IN BOOLEAN MODE)",
+            "mysql",
+            "SELECT * FROM t WHERE MATCH (a, b) AGAINST (? IN BOOLEAN MODE)",
+            ["'foo'"],
+        ),
+        (
+            "SELECT CAST('1' AS INT), POSITION('a' IN b), SUBSTRING(b FROM 2 FOR 3), TRIM('x' FROM b)",
+            "postgres",
+            "SELECT CAST(? AS INTEGER), POSITION(? IN b), SUBSTRING(b FROM ? FOR ?), TRIM(? FROM b)",
+            ["'1'", "'a'", "2", "3", "'x'"],
+        ),
+    ],
+)
+def test_sqlfp_special_form_literals(sql, dialect, normalized, params):
+    result = sqlfp.normalize(sql, dialect=dialect)
+    assert (result.normalized, result.params) == (normalized, params)
+
+
+def test_sqlfp_special_form_literals_compat():
+    result = sqlfp.normalize("SELECT * FROM t WHERE d > DATE '2020-01-01'", compat="0.1")
+    assert result.normalized == "SELECT * FROM t WHERE d > DATE '2020-01-01'"
+    assert result.params == []
+
+
+@pytest.mark.parametrize(
+    "sql, dialect, normalized",
+    [
+        (
+            "SELECT x::int, CAST(x AS integer), cast(x as INT) FROM t",
+            "postgres",
+            "SELECT CAST(x AS INTEGER), CAST(x AS INTEGER), CAST(x AS INTEGER) FROM t",
+        ),
+        ("SELECT (a + b)::int FROM t", "postgres", "SELECT CAST(a + b AS INTEGER) FROM t"),
+        (
+            "SELECT x::int2, x::int4, x::int8, x::float4, x::float8, x::decimal(10, 2) FROM t",
+            "postgres",
+            "SELECT CAST(x AS SMALLINT), CAST(x AS INTEGER), CAST(x AS BIGINT), CAST(x AS REAL), "
+            "CAST(x AS DOUBLE PRECISION), CAST(x AS NUMERIC(10,2)) FROM t",
+        ),
+        (
+            "SELECT x::timestamptz, x::timestamp without time zone, x::character varying(10), x::bool FROM t",
+            "postgres",
+            "SELECT CAST(x AS TIMESTAMP WITH TIME ZONE), CAST(x AS TIMESTAMP), CAST(x AS VARCHAR(10)), "
+            "CAST(x AS BOOLEAN) FROM t",
+        ),
+        (
+            "SELECT x::int8[], x::citext, x::\"MyType\" FROM t",
+            "postgres",
+            "SELECT CAST(x AS BIGINT[]), CAST(x AS CITEXT), CAST(x AS \"MyType\") FROM t",
+        ),
+        ("SELECT CAST(x AS dec(5)), CAST(x AS character(2)) FROM t", "mysql", "SELECT CAST(x AS DECIMAL(5)), CAST(x AS CHAR(2)) FROM t"),
+        # INT8 is not BIGINT everywhere
+        ("SELECT CAST(x AS Int8) FROM t", "clickhouse", "SELECT CAST(x AS INT8) FROM t"),
+        # TRY_CAST returns NULL where CAST fails
+        ("SELECT TRY_CAST(x AS int) FROM t", "snowflake", "SELECT TRY_CAST(x AS INTEGER) FROM t"),
+    ],
+)
+def test_sqlfp_cast_canonicalize(sql, dialect, normalized):
+    assert sqlfp.normalize(sql, dialect=dialect).normalized == normalized
+
+
+def test_sqlfp_cast_canonicalize_disabled():
+    sql = "SELECT id::int4 FROM t"
+    standard = sqlfp.normalize("SELECT CAST(id AS integer) FROM t", dialect="postgres")
+    assert sqlfp.normalize(sql, dialect="postgres").hash == standard.hash
+    assert sqlfp.normalize(sql, dialect="postgres", disable_passes=["cast-canonicalize"]).normalized == "SELECT id::INT4 FROM t"
+    assert sqlfp.normalize(sql, dialect="postgres", compat="0.1").normalized == "SELECT id::INT4 FROM t"
+
+
+@pytest.mark.parametrize(
+    "dialect, sql, normalized",
+    [
+        (
+            "teradata",
+            "SELECT TOP 10 a, b start FROM t QUALIFY ROW_NUMBER() OVER (PARTITION BY a ORDER BY b) = 1",
+            "SELECT TOP 10 a, b AS start FROM t QUALIFY ROW_NUMBER() OVER (PARTITION BY a ORDER BY b) = ?",
+        ),
+        ("vertica", "SELECT a::INT FROM t WHERE b ILIKE 'x%' LIMIT 5", "SELECT CAST(a AS INTEGER) FROM t WHERE b ILIKE ? LIMIT ?"),
+        ("informix", "SELECT a format FROM t WHERE name = \"bob\"", "SELECT a AS format FROM t WHERE name = ?"),
+    ],
+)
+def test_sqlfp_pseudo_dialects(dialect, sql, normalized):
+    before = sqlfp.stats()["dialects"][dialect]
+    assert sqlfp.normalize(sql, dialect=dialect.upper()).normalized == normalized
+    assert sqlfp.stats()["dialects"][dialect] - before == 1
+
+
+def test_sqlfp_pseudo_dialect_overrides():
+    # Teradata only quotes identifiers with double quotes
+    assert sqlfp.normalize("SELECT `a` FROM t").normalized == "SELECT `a` FROM t"
+    with pytest.raises(ValueError):
+        sqlfp.normalize("SELECT `a` FROM t", dialect="teradata")
+    # Reserved by Teradata, an alias to the generic dialect
+    with pytest.raises(ValueError):
+        sqlfp.normalize("SELECT 1 QUALIFY", dialect="teradata")
+    assert sqlfp.normalize("SELECT 1 QUALIFY").normalized == "SELECT ? AS QUALIFY"
+
+
+@pytest.mark.parametrize(
+    "dialect, sql, normalized",
+    [
+        ("bigquery", "SELECT * FROM `proj.ds.t` WHERE x = 1", "SELECT * FROM proj.ds.t WHERE x = ?"),
+        ("bigquery", "SELECT `t`.*, `select` FROM `my-proj`.ds.t AS `t`", "SELECT t.*, `select` FROM `my-proj`.ds.t t"),
+        (
+            "snowflake",
+            "SELECT a, v:b.c FROM t QUALIFY ROW_NUMBER() OVER (PARTITION BY a ORDER BY b) = 1",
+            "SELECT a, v:b.c FROM t QUALIFY ROW_NUMBER() OVER (PARTITION BY a ORDER BY b) = ?",
+        ),
+        (
+            "clickhouse",
+            "SELECT a FROM t FINAL WHERE b = 1 SETTINGS max_threads = 8",
+            "SELECT a FROM t FINAL WHERE b = ? SETTINGS max_threads = ?",
+        ),
+        ("duckdb", "SELECT * EXCLUDE (a) FROM t WHERE b = 'x' LIMIT 5", "SELECT * EXCLUDE (a) FROM t WHERE b = ? LIMIT ?"),
+        (
+            "hive",
+            "INSERT OVERWRITE TABLE t PARTITION (dt = '2020-01-01') SELECT `a` FROM s",
+            "INSERT OVERWRITE TABLE t PARTITION (dt = ?) SELECT `a` FROM s",
+        ),
+        ("redshift", "SELECT TOP 10 a FROM t WHERE b = 'x'", "SELECT TOP 10 a FROM t WHERE b = ?"),
+        ("databricks", "SELECT `a` FROM t WHERE b = 1", "SELECT `a` FROM t WHERE b = ?"),
+    ],
+)
+def test_sqlfp_sqlparser_dialects(dialect, sql, normalized):
+    before = sqlfp.stats()["dialects"][dialect]
+    result = sqlfp.normalize(sql, dialect=dialect)
+    assert (result.normalized, result.dialect_used) == (normalized, dialect)
+    assert sqlfp.stats()["dialects"][dialect] - before == 1
+
+
+def test_sqlfp_sqlparser_dialect_quirks():
+    assert sqlfp.normalize("SELECT 1", dialect="Spark").dialect_used == "databricks"
+    # Backquotes are dropped for BigQuery only
+    assert sqlfp.normalize("SELECT `a` FROM `ds`.`t`", dialect="bigquery").hash == sqlfp.normalize("SELECT a FROM ds.t", dialect="bigquery").hash
+    assert sqlfp.normalize("SELECT `a` FROM t", dialect="databricks").normalized == "SELECT `a` FROM t"
+    # Function names of the dialect's own
+    assert sqlfp.normalize("SELECT iff(a, 1, 2), my_fn(a) FROM t", dialect="snowflake", function_case="builtins").normalized == "SELECT IFF(a, ?, ?), my_fn(a) FROM t"
+    assert sqlfp.normalize("SELECT uniqExact(a), myFn(a) FROM t", dialect="clickhouse", function_case="builtins").normalized == "SELECT UNIQEXACT(a), myFn(a) FROM t"
+
+
+@pytest.mark.parametrize(
+    "dialect, normalized, params",
+    [
+        ("snowflake", 'SELECT "Name" FROM "Users" WHERE "Name" = ?', ["'x'"]),
+        ("clickhouse", 'SELECT "Name" FROM "Users" WHERE "Name" = ?', ["'x'"]),
+        ("duckdb", 'SELECT "Name" FROM "Users" WHERE "Name" = ?', ["'x'"]),
+        ("redshift", 'SELECT "Name" FROM "Users" WHERE "Name" = ?', ["'x'"]),
+        # Double quotes delimit strings
+        ("bigquery", 'SELECT ? FROM "Users" WHERE ? = ?', ['"Name"', '"Name"', "'x'"]),
+        ("hive", 'SELECT ? FROM "Users" WHERE ? = ?', ['"Name"', '"Name"', "'x'"]),
+        ("databricks", 'SELECT ? FROM "Users" WHERE ? = ?', ['"Name"', '"Name"', "'x'"]),
+    ],
+)
+def test_sqlfp_sqlparser_dialect_quoted_names(dialect, normalized, params):
+    result = sqlfp.normalize('SELECT "Name" FROM "Users" WHERE "Name" = \'x\'', dialect=dialect)
+    assert (result.normalized, result.params) == (normalized, params)
+
+
+def test_sqlfp_verify():
+    report = sqlfp.verify("SELECT * FROM t WHERE a = 1 AND b IN (2, 3)")
+    assert report == {
+        "normalized": "SELECT * FROM t WHERE a = ? AND b IN (?, ?)",
+        "hash": sqlfp.normalize("SELECT * FROM t WHERE a = 1 AND b IN (2, 3)").hash,
+        "stable": True,
+        "renormalized": "SELECT * FROM t WHERE a = ? AND b IN (?, ?)",
+        "renormalized_hash": report["hash"],
+        "error": None,
+    }
+    # `?` is an operator to PostgreSQL, `$n` is not touched
+    report = sqlfp.verify("SELECT * FROM t WHERE a = ANY(ARRAY[1, 2]) AND b = $1", dialect="postgres")
+    assert report["stable"]
+    assert report["renormalized"] == "SELECT * FROM t WHERE a = ANY(ARRAY[?, ?]) AND b = $1"
+    report = sqlfp.verify("SELECT * FROM t WHERE a = 1", dialect="postgres", placeholder="${n}")
+    assert (report["stable"], report["renormalized"]) == (True, "SELECT * FROM t WHERE a = $1")
+    with pytest.raises(ValueError):
+        sqlfp.verify("SELECT * TROM t")
+
+
+
+@pytest.mark.parametrize("dialect", ["generic", "postgres"])
+def test_sqlfp_verify_ignore_clauses(dialect):
+    # The hash of the renormalized text leaves the ignored clauses out too
+    sql = "SELECT a FROM t WHERE x = 1 ORDER BY a"
+    try:
+        sqlfp.configure(reset=True, ignore_clauses=["order_by"])
+        report = sqlfp.verify(sql, dialect=dialect)
+        expected = sqlfp.normalize(sql, dialect=dialect).hash
+    finally:
+        sqlfp.configure(reset=True)
+    assert report["stable"] is True
+    assert report["hash"] == report["renormalized_hash"] == expected
+    assert expected == sqlfp.normalize("SELECT a FROM t WHERE x = 1", dialect=dialect).hash
+
+def test_sqlfp_verify_reparse_error():
+    # An unquoted placeholder that is not valid SQL does not parse back
+    report = sqlfp.verify("SELECT * FROM t WHERE a = 1", placeholder="<v>")
+    assert report["normalized"] == "SELECT * FROM t WHERE a = <v>"
+    assert report["stable"] is False
+    assert report["renormalized"] is None and report["renormalized_hash"] is None
+    assert "Parse error" in report["error"]
+
+
+def test_sqlfp_cte_rename():
+    a = "WITH cte0 AS (SELECT id FROM users WHERE active = 1) SELECT cte0.id FROM cte0 JOIN orders o ON o.uid = cte0.id"
+    b = "WITH anon_1 AS (SELECT id FROM users WHERE active = 2) SELECT anon_1.id FROM ANON_1 JOIN orders o ON o.uid = anon_1.id"
+    ra = sqlfp.normalize(a, enable_passes=["cte-rename"])
+    rb = sqlfp.normalize(b, enable_passes=["cte-rename"])
+    assert ra.normalized == (
+        "WITH cte_1 AS (SELECT id FROM users WHERE active = ?) "
+        "SELECT cte_1.id FROM cte_1 JOIN orders o ON o.uid = cte_1.id"
+    )
+    assert ra.hash == rb.hash
+    assert "cte-rename" in ra.passes
+    # Opt-in: the default pipeline leaves CTE names alone
+    assert sqlfp.normalize(a).hash != sqlfp.normalize(b).hash
+    assert "cte-rename" not in sqlfp.normalize(a).passes
+
+
+def test_sqlfp_cte_rename_scopes():
+    sql = (
+        "WITH a AS (SELECT 1 AS x), b AS (SELECT x FROM a) "
+        "SELECT b.*, (WITH a AS (SELECT 2 AS y) SELECT y FROM a) FROM b, users a2"
+    )
+    result = sqlfp.normalize(sql, enable_passes=["cte-rename"], disable_passes=["literal-parameterize"])
+    assert result.normalized == (
+        "WITH cte_1 AS (SELECT 1 AS x), cte_2 AS (SELECT x FROM cte_1) "
+        "SELECT cte_2.*, (WITH cte_3 AS (SELECT 2 AS y) SELECT y FROM cte_3) FROM cte_2, users a2"
+    )
+
+
+@pytest.mark.parametrize(
+    "sql, renamed",
+    [
+        (
+            "WITH orders AS (SELECT * FROM orders WHERE paid) SELECT orders.id FROM orders",
+            "WITH cte_1 AS (SELECT * FROM orders WHERE paid) SELECT cte_1.id FROM cte_1",
+        ),
+        (
+            "WITH a AS (SELECT 1), b AS (SELECT * FROM a), a AS (SELECT * FROM a) SELECT * FROM a, b",
+            "WITH cte_1 AS (SELECT 1), cte_2 AS (SELECT * FROM cte_1), cte_3 AS (SELECT * FROM cte_1) "
+            "SELECT * FROM cte_3, cte_2",
+        ),
+        (
+            "WITH b AS (SELECT * FROM a), a AS (SELECT 1) SELECT * FROM b",
+            "WITH cte_1 AS (SELECT * FROM a), cte_2 AS (SELECT 1) SELECT * FROM cte_1",
+        ),
+        (
+            "WITH RECURSIVE r AS (SELECT 1 AS n UNION ALL SELECT n + 1 FROM r WHERE n < 5) SELECT r.* FROM r",
+            "WITH RECURSIVE cte_1 AS (SELECT 1 AS n UNION ALL SELECT n + 1 FROM cte_1 WHERE n < 5) SELECT cte_1.* FROM cte_1",
+        ),
+        (
+            "SELECT * FROM x WHERE id IN (WITH x AS (SELECT 1) SELECT * FROM x)",
+            "SELECT * FROM x WHERE id IN (WITH cte_1 AS (SELECT 1) SELECT * FROM cte_1)",
+        ),
+    ],
+)
+def test_sqlfp_cte_rename_shadowing(sql, renamed):
+    result = sqlfp.normalize(sql, enable_passes=["cte-rename"], disable_passes=["literal-parameterize"])
+    assert result.normalized == renamed
+
+
+def test_sqlfp_cte_scopes_metadata():
+    through_cte = sqlfp.normalize("WITH t AS (SELECT relname FROM pg_class) SELECT * FROM t")
+    assert through_cte.metadata["statement_class"] == "introspection"
+    named_like_catalog = sqlfp.normalize("WITH pg_x AS (SELECT * FROM users) SELECT * FROM pg_x")
+    assert "statement_class" not in named_like_catalog.metadata
+    bundle = json.loads(sqlfp.export_bundle(["WITH orders AS (SELECT * FROM orders) SELECT * FROM orders JOIN items ON true"]))
+    metrics = bundle["queries"][0]["metrics"]
+    assert (metrics["tables"], metrics["ctes"]) == (2, 1)
+
+
+def test_sqlfp_cte_rename_listed_and_configured():
+    sql = "WITH x AS (SELECT 1) SELECT * FROM x"
+    listed = sqlfp.normalize(sql, passes=["cte-rename", "literal-parameterize"])
+    assert listed.normalized == "WITH cte_1 AS (SELECT ?) SELECT * FROM cte_1"
+    try:
+        assert sqlfp.configure(enable_passes=["cte-rename"]) == {"enable_passes": ["cte-rename"]}
+        assert sqlfp.normalize(sql).normalized == "WITH cte_1 AS (SELECT ?) SELECT * FROM cte_1"
+        assert sqlfp.normalize(sql, disable_passes=["cte-rename"]).normalized == "WITH x AS (SELECT ?) SELECT * FROM x"
+    finally:
+        sqlfp.configure(reset=True)
+    # The 0.1 rules predate the pass
+    assert "cte-rename" not in sqlfp.normalize(sql, compat="0.1", enable_passes=["cte-rename"]).passes
+    with pytest.raises(ValueError, match="Unknown normalization pass: nope"):
+        sqlfp.normalize(sql, enable_passes=["nope"])
+
+
+def test_sqlfp_registry_observe():
+    registry = sqlfp.FingerprintRegistry()
+    h = registry.observe("SELECT * FROM t WHERE id = 1")
+    assert registry.observe("select * from t where id = 2", count=2) == h
+    assert registry.observe("SELECT 1") != h
+    assert registry.observe("NOT SQL AT ALL (") is None
+    assert len(registry) == 2
+    assert registry.unparsed == 1
+    assert registry.dialect == "generic"
+    assert registry.entries()[0] == {"hash": h, "normalized": "SELECT * FROM t WHERE id = ?", "count": 3}
+
+
+def test_sqlfp_registry_save_load_merge(tmp_path):
+    a = sqlfp.FingerprintRegistry(dialect="postgresql")
+    b = sqlfp.FingerprintRegistry(dialect="postgres")
+    a.observe("SELECT 1")
+    b.observe("SELECT 2")
+    b.observe("SELECT x FROM t")
+    a.save(tmp_path / "a.jsonl")
+    b.save(tmp_path / "b.jsonl")
+    merged = sqlfp.FingerprintRegistry.load(tmp_path / "a.jsonl")
+    merged.merge(sqlfp.FingerprintRegistry.load(tmp_path / "b.jsonl"))
+    assert merged.dialect == "postgresql"
+    assert [(e["normalized"], e["count"]) for e in merged.entries()] == [("SELECT ?", 2), ("SELECT x FROM t", 1)]
+    # Saving is deterministic
+    merged.save(tmp_path / "c.jsonl")
+    sqlfp.FingerprintRegistry.load(tmp_path / "c.jsonl").save(tmp_path / "d.jsonl")
+    assert (tmp_path / "c.jsonl").read_bytes() == (tmp_path / "d.jsonl").read_bytes()
+    with pytest.raises(ValueError, match="Cannot merge a mysql registry"):
+        merged.merge(sqlfp.FingerprintRegistry(dialect="mysql"))
+
+
+def test_sqlfp_registry_load_invalid(tmp_path):
+    path = tmp_path / "bad.jsonl"
+    path.write_text('{"samples": []}\n')
+    with pytest.raises(ValueError, match="Not a registry checkpoint"):
+        sqlfp.FingerprintRegistry.load(path)
+    path.write_text('{"sqlfp_registry": 1, "dialect": "generic"}\n{"hash": "x"}\n')
+    with pytest.raises(ValueError, match="line 2: Missing field 'normalized'"):
+        sqlfp.FingerprintRegistry.load(path)
+    path.write_text('{"sqlfp_registry": 1, "fingerprint_version": 2, "dialect": "generic"}\n')
+    with pytest.raises(ValueError, match="fingerprint version 2, not 1"):
+        sqlfp.FingerprintRegistry.load(path)
+
+
+def test_sqlfp_aggregator_observe():
+    stats = sqlfp.FingerprintAggregator(dialect="postgres")
+    h = stats.observe("SELECT * FROM t WHERE id = 1")
+    assert stats.observe(b"select * from t where id = 2", duration=0.5) == h
+    assert stats.observe("SELECT * FROM t WHERE id = 3", duration=0.25) == h
+    assert stats.observe("NOT SQL AT ALL (") is None
+    stats.observe("SELECT 1", duration=2.0)
+    assert (len(stats), stats.unparsed, stats.dialect) == (2, 1, "postgresql")
+    [first, second] = stats.snapshot()
+    assert {k: v for k, v in first.items() if not k.endswith("_seen")} == {
+        "hash": h,
+        "normalized": "SELECT * FROM t WHERE id = ?",
+        # The slowest
+        "sample": "select * from t where id = 2",
+        "count": 3,
+        "timed": 2,
+        "total_duration": 0.75,
+        "min_duration": 0.25,
+        "max_duration": 0.5,
+    }
+    assert first["first_seen"] <= first["last_seen"]
+    assert [r["normalized"] for r in stats.top(1, by="total_duration")] == ["SELECT ?"]
+    assert [r["normalized"] for r in stats.top(5)] == ["SELECT * FROM t WHERE id = ?", "SELECT ?"]
+    assert len(stats.snapshot(reset=True)) == 2
+    assert (len(stats), stats.unparsed, stats.snapshot()) == (0, 0, [])
+    untimed = sqlfp.FingerprintAggregator()
+    untimed.observe("SELECT 1")
+    assert untimed.snapshot()[0]["total_duration"] is None
+    with pytest.raises(ValueError, match="Unsupported rank: nope"):
+        stats.top(by="nope")
+    with pytest.raises(ValueError, match="duration must be"):
+        stats.observe("SELECT 1", duration=-1.0)
+
+
+def test_sqlfp_aggregator_threads():
+    stats = sqlfp.FingerprintAggregator()
+
+    def work(n):
+        for i in range(200):
+            stats.observe(f"SELECT * FROM t WHERE id = {i}", duration=0.001)
+            stats.observe(f"UPDATE t SET a = {n}")
+
+    threads = [threading.Thread(target=work, args=(n,)) for n in range(8)]
+    for thread in threads:
+        thread.start()
+    for thread in threads:
+        thread.join()
+    assert [(r["count"], r["timed"]) for r in stats.snapshot()] == [(1600, 1600), (1600, 0)]
+
+
+@pytest.mark.parametrize(
+    "sql, line, column, token",
+    [
+        ("SELEC id FROM users", 1, 1, "SELEC"),
+        ("SELECT a\nFROM t WHERE WHERE x", 2, 20, "x"),
+        ("SELECT (1", None, None, "EOF"),
+        ("SELECT 'abc", 1, 8, None),
+    ],
+)
+def test_sqlfp_parse_error_position(sql, line, column, token):
+    with pytest.raises(sqlfp.ParseError) as exc:
+        sqlfp.normalize(sql)
+    assert (exc.value.line, exc.value.column, exc.value.token) == (line, column, token)
+
+
+def test_sqlfp_error_hierarchy():
+    for error in (
+        sqlfp.ParseError,
+        sqlfp.EmptyStatementError,
+        sqlfp.UnsupportedDialectError,
+        sqlfp.UnnormalizableError,
+        sqlfp.PlaceholderCollisionError,
+    ):
+        assert issubclass(error, sqlfp.Error)
+    assert issubclass(sqlfp.Error, ValueError)
+    for sql in ("", "  -- nothing\n", ";"):
+        with pytest.raises(sqlfp.EmptyStatementError, match="No SQL statement found"):
+            sqlfp.normalize(sql)
+    with pytest.raises(sqlfp.UnsupportedDialectError, match="Unsupported dialect: nope"):
+        sqlfp.normalize("SELECT 1", dialect="nope")
+    with pytest.raises(sqlfp.UnsupportedDialectError):
+        sqlfp.normalize_many(["SELECT 1"], dialect="nope")
+    with pytest.raises(sqlfp.Error, match="Unknown normalization pass: nope") as exc:
+        sqlfp.normalize("SELECT 1", passes=["nope"])
+    assert type(exc.value) is sqlfp.Error
+
+
+def test_sqlfp_insert_select_structure():
+    insert = sqlfp.normalize(
+        "INSERT INTO archive (id) WITH recent AS (SELECT * FROM orders AS o WHERE o.ts > 5) "
+        "SELECT r.id FROM recent AS r INNER JOIN users AS u ON u.id = r.uid ORDER BY r.id ASC"
+    )
+    assert insert.normalized == (
+        "INSERT INTO archive (id) WITH recent AS (SELECT * FROM orders o WHERE o.ts > ?) "
+        "SELECT r.id FROM recent r JOIN users u ON u.id = r.uid ORDER BY r.id"
+    )
+    assert insert.warnings == []
+    a = sqlfp.normalize("INSERT INTO t SELECT x.a FROM s AS x LEFT OUTER JOIN z ON z.id = x.id")
+    b = sqlfp.normalize("insert into t select x.a from s x left join z on z.id = x.id")
+    assert a.hash == b.hash
+    old = sqlfp.normalize("INSERT INTO t SELECT x.a FROM s AS x", compat="0.1")
+    assert old.normalized == "INSERT INTO t SELECT x.a FROM s AS x"
+
+
+def test_sqlfp_insert_column_sort():
+    a = sqlfp.normalize("INSERT INTO users (name, email, id) VALUES ('a', 'a@x', 1), ('b', 'b@x', 2)", enable_passes=["insert-column-sort"])
+    b = sqlfp.normalize("INSERT INTO users (email, id, name) VALUES ('a@x', 1, 'a')", enable_passes=["insert-column-sort"])
+    assert a.normalized == "INSERT INTO users (email, id, name) VALUES (?, ?, ?), (?, ?, ?)"
+    # Params follow the sorted columns
+    assert a.params == ["'a@x'", "1", "'a'", "'b@x'", "2", "'b'"]
+    assert a.shape_hash == b.shape_hash
+    assert "insert-column-sort" in a.passes
+    # Opt-in: the default pipeline keeps the written order
+    assert sqlfp.normalize("INSERT INTO t (b, a) VALUES (1, 2)").normalized == "INSERT INTO t (b, a) VALUES (?, ?)"
+
+
+def test_sqlfp_insert_column_sort_leaves_others():
+    passes = ["insert-column-sort"]
+    disabled = ["literal-parameterize"]
+    for sql in [
+        "INSERT INTO t (b, a) SELECT x, y FROM s",
+        "INSERT INTO t (b, a) VALUES (1)",
+        "INSERT INTO t VALUES (2, 1)",
+    ]:
+        assert sqlfp.normalize(sql, enable_passes=passes, disable_passes=disabled).normalized == sql
+    quoted = sqlfp.normalize('INSERT INTO t ("b", A, "C") VALUES (1, 2, 3)', enable_passes=passes, disable_passes=disabled)
+    assert quoted.normalized == 'INSERT INTO t ("C", A, "b") VALUES (3, 2, 1)'
+
+
+@pytest.mark.parametrize(
+    "sql, count",
+    [
+        ("SELECT a FROM t UNION SELECT a FROM t", 1),
+        ("SELECT a FROM t AS x UNION ALL SELECT a FROM t x UNION ALL select a from t x", 2),
+        ("(SELECT a FROM t WHERE b = 1) UNION SELECT a FROM t WHERE b = 1", 1),
+        ("SELECT a FROM t WHERE b = 1 UNION SELECT a FROM t WHERE b = 2", 0),
+        ("SELECT a FROM t UNION SELECT a FROM s", 0),
+        ("SELECT a FROM t UNION SELECT a FROM s INTERSECT SELECT a FROM s", 1),
+        ("SELECT * FROM (SELECT 1 EXCEPT SELECT 1) AS d WHERE x IN (SELECT 2 UNION SELECT 2)", 2),
+    ],
+)
+def test_sqlfp_duplicate_branches(sql, count):
+    metadata = sqlfp.normalize(sql).metadata
+    assert metadata.get("duplicate_branches", 0) == count
+    assert "duplicate_branches" not in sqlfp.normalize("SELECT 1").metadata
+
+
+def test_sqlfp_returning():
+    result = sqlfp.normalize(
+        "insert into t (a) values (1) returning id, lower(name) as n, t.ts, coalesce(x, 0), t.*",
+        dialect="postgres",
+    )
+    assert result.normalized == "INSERT INTO t (a) VALUES (?) RETURNING id, LOWER(name) AS n, t.ts, COALESCE(x, ?), t.*"
+    assert result.has_returning
+    assert result.metadata["returning"] == ["id", "n", "ts", "COALESCE(x, ?)", "t.*"]
+    update = sqlfp.normalize("UPDATE t SET a = 1 RETURNING (SELECT max(v) FROM s AS z) AS m", dialect="postgres")
+    assert update.normalized == "UPDATE t SET a = ? RETURNING (SELECT MAX(v) FROM s z) AS m"
+    assert update.metadata["returning"] == ["m"]
+    delete = sqlfp.normalize("DELETE FROM t WHERE a = 1 RETURNING *", dialect="postgres")
+    assert delete.has_returning and delete.metadata["returning"] == ["*"]
+    plain = sqlfp.normalize("DELETE FROM t WHERE a = 1")
+    assert not plain.has_returning
+    assert "returning" not in plain.metadata
+
+
+@pytest.mark.parametrize(
+    "sql,dialect,statement_class,is_read_only",
+    [
+        ("SELECT a FROM t UNION SELECT b FROM s", "generic", "SELECT", True),
+        ("WITH x AS (SELECT 1) INSERT INTO t SELECT * FROM x", "postgres", "INSERT", False),
+        ("WITH x AS (SELECT 1) UPDATE t SET a = 1", "postgres", "UPDATE", False),
+        ("WITH d AS (DELETE FROM t RETURNING *) SELECT * FROM d", "postgres", "SELECT", False),
+        ("SELECT * FROM t FOR UPDATE", "postgres", "SELECT", False),
+        ("SELECT * INTO n FROM t", "postgres", "SELECT", False),
+        ("DELETE FROM t WHERE a = 1", "generic", "DELETE", False),
+        ("LOAD DATA INFILE 'f' INTO TABLE t", "mysql", "INSERT", False),
+        ("CREATE TABLE t (a INT)", "generic", "DDL", False),
+        ("ALTER TABLE t ADD COLUMN b INT", "generic", "DDL", False),
+        ("TRUNCATE t", "postgres", "DDL", False),
+        ("SHOW TABLES", "mysql", "OTHER", True),
+        ("EXPLAIN SELECT 1", "generic", "OTHER", True),
+        ("EXPLAIN ANALYZE SELECT 1", "postgres", "OTHER", False),
+        ("SET x = 1", "generic", "OTHER", False),
+    ],
+)
+def test_sqlfp_statement_class(sql, dialect, statement_class, is_read_only):
+    result = sqlfp.normalize(sql, dialect=dialect)
+    assert (result.statement_class, result.is_read_only) == (statement_class, is_read_only)
+    assert result.is_ddl == (statement_class == "DDL")
+
+
+def test_sqlfp_function_schema_normalize():
+    passes = ["function-schema-normalize"]
+    qualified = sqlfp.normalize("SELECT pg_catalog.lower(x), Sys.Fn(y) FROM t", enable_passes=passes)
+    assert qualified.normalized == "SELECT LOWER(x), FN(y) FROM t"
+    assert qualified.hash == sqlfp.normalize("SELECT LOWER(x), sys.fn(y) FROM t", enable_passes=passes).hash
+    assert sqlfp.normalize("SELECT Util.My_Fn(x)", enable_passes=passes).normalized == "SELECT util.MY_FN(x)"
+    assert sqlfp.normalize('SELECT "Util".f(x)', enable_passes=passes).normalized == 'SELECT "Util".F(x)'
+    # Opt-in: by default every part is uppercased and system schemas are kept
+    assert sqlfp.normalize("SELECT pg_catalog.lower(x)").normalized == "SELECT PG_CATALOG.LOWER(x)"
+
+
+def test_sqlfp_negative_literal_fold():
+    passes = ["negative-literal-fold"]
+    result = sqlfp.normalize("SELECT * FROM t WHERE a = -5 AND b IN (-1.5, 2) AND c = x - -3", enable_passes=passes)
+    assert result.normalized == "SELECT * FROM t WHERE a = ? AND b IN (?, ?) AND c = x - ?"
+    assert result.params == ["-5", "-1.5", "2", "-3"]
+    assert sqlfp.normalize("SELECT -(5), - -5, -a", enable_passes=passes).normalized == "SELECT ?, -?, -a"
+    assert sqlfp.normalize("INSERT INTO t VALUES (-1)", enable_passes=passes).hash == (
+        sqlfp.normalize("INSERT INTO t VALUES (1)", enable_passes=passes).hash
+    )
+    # Opt-in: by default the minus stays outside the placeholder
+    default = sqlfp.normalize("SELECT * FROM t WHERE a = -5")
+    assert (default.normalized, default.params) == ("SELECT * FROM t WHERE a = -?", ["5"])
+
+
+def test_sqlfp_concat_canonicalize():
+    passes = ["concat-canonicalize"]
+    expected = sqlfp.normalize("SELECT a || ? || b FROM t", enable_passes=passes)
+    assert expected.normalized == "SELECT a || ? || b FROM t"
+    for sql, dialect in [
+        ("SELECT CONCAT(a, ' ', b) FROM t", "mysql"),
+        ("SELECT concat(a, ' ', b) FROM t", "postgres"),
+        ("SELECT a + ' ' + b FROM t", "mssql"),
+        ("SELECT CONCAT(a, ' ') + b FROM t", "mssql"),
+    ]:
+        assert sqlfp.normalize(sql, dialect=dialect, enable_passes=passes).hash == expected.hash, sql
+    # Operands keep the parentheses they need
+    assert sqlfp.normalize("SELECT CONCAT(a, b + 1)", enable_passes=passes).normalized == "SELECT a || (b + ?)"
+    # Only string `+` in MSSQL, only plain CONCAT calls with several arguments
+    assert sqlfp.normalize("SELECT a + b FROM t", dialect="mssql", enable_passes=passes).normalized == "SELECT a + b FROM t"
+    assert sqlfp.normalize("SELECT a + 'x'", enable_passes=passes).normalized == "SELECT a + ?"
+    assert sqlfp.normalize("SELECT CONCAT(a), s.concat(a, b)", enable_passes=passes).normalized == (
+        "SELECT CONCAT(a), S.CONCAT(a, b)"
+    )
+    # Opt-in
+    assert sqlfp.normalize("SELECT CONCAT(a, b)").normalized == "SELECT CONCAT(a, b)"
+
+
+def test_sqlfp_case_canonicalize():
+    passes = ["case-canonicalize"]
+    simple = sqlfp.normalize("SELECT CASE status WHEN 1 THEN 'a' WHEN 2 THEN 'b' ELSE NULL END FROM t", enable_passes=passes)
+    assert simple.normalized == "SELECT CASE WHEN status = ? THEN ? WHEN status = ? THEN ? END FROM t"
+    searched = sqlfp.normalize("SELECT CASE WHEN status = 1 THEN 'a' WHEN status = 2 THEN 'b' END FROM t", enable_passes=passes)
+    assert simple.hash == searched.hash
+    # Operands keep the parentheses they need
+    assert sqlfp.normalize("SELECT CASE x WHEN a OR b THEN c END", enable_passes=passes).normalized == (
+        "SELECT CASE WHEN x = (a OR b) THEN c END"
+    )
+    assert sqlfp.normalize("SELECT CASE WHEN a THEN 1 ELSE 0 END", enable_passes=passes).normalized == (
+        "SELECT CASE WHEN a THEN ? ELSE ? END"
+    )
+    # Opt-in
+    assert sqlfp.normalize("SELECT CASE x WHEN 1 THEN a ELSE NULL END").normalized == (
+        "SELECT CASE x WHEN ? THEN a ELSE NULL END"
+    )
+
+
+def test_sqlfp_distinct_from_canonicalize():
+    passes = ["distinct-from-canonicalize"]
+    spaceship = sqlfp.normalize("SELECT * FROM t WHERE a <=> 1", dialect="mysql", enable_passes=passes)
+    assert spaceship.normalized == "SELECT * FROM t WHERE a IS NOT DISTINCT FROM ?"
+    standard = sqlfp.normalize("SELECT * FROM t WHERE a IS NOT DISTINCT FROM 2", dialect="postgres", enable_passes=passes)
+    assert spaceship.hash == standard.hash
+    negated = sqlfp.normalize("SELECT * FROM t WHERE NOT (a <=> 1)", dialect="mysql", enable_passes=passes)
+    assert negated.hash == sqlfp.normalize("SELECT * FROM t WHERE a IS DISTINCT FROM 1", enable_passes=passes).hash
+    # Opt-in
+    assert sqlfp.normalize("SELECT * FROM t WHERE a <=> 1", dialect="mysql").normalized == "SELECT * FROM t WHERE a <=> ?"
+
+
+def test_sqlfp_case_arm_collapse():
+    passes = ["case-arm-collapse"]
+
+    def batch(n):
+        arms = " ".join(f"WHEN {i} THEN 'v{i}'" for i in range(n))
+        return f"UPDATE t SET x = CASE id {arms} END WHERE id IN ({', '.join(map(str, range(n)))})"
+
+    small = sqlfp.normalize(batch(2), enable_passes=passes)
+    large = sqlfp.normalize(batch(50), enable_passes=passes)
+    assert small.normalized == "UPDATE t SET x = CASE id WHEN ? THEN ? END WHERE id IN (?, ?)"
+    assert small.shape_hash == large.shape_hash
+    assert large.metadata["collapsed_case_arms"] == 49
+    assert large.params[:2] == ["0", "'v0'"]
+    # Only runs of arms of the same shape collapse
+    mixed = sqlfp.normalize("SELECT CASE WHEN a = 1 THEN 'x' WHEN a = 2 THEN b WHEN a = 3 THEN 'y' END", enable_passes=passes)
+    assert mixed.normalized == "SELECT CASE WHEN a = ? THEN ? WHEN a = ? THEN b WHEN a = ? THEN ? END"
+    assert "collapsed_case_arms" not in mixed.metadata
+    # Numbered placeholders stay consecutive
+    numbered = sqlfp.normalize(
+        "SELECT CASE WHEN a = 1 THEN 'x' WHEN a = 2 THEN 'y' ELSE 'z' END", enable_passes=passes, placeholder="${n}"
+    )
+    assert numbered.normalized == "SELECT CASE WHEN a = $1 THEN $2 ELSE $3 END"
+    # Opt-in
+    assert sqlfp.normalize(batch(2)).normalized.count("WHEN") == 2
+
+
+@pytest.mark.parametrize(
+    "sql, dialect, normalized",
+    [
+        ("SELECT * FROM jobs WHERE run_at < NOW() - INTERVAL 1 DAY", "mysql", "CURRENT_TIMESTAMP - INTERVAL ? DAY"),
+        ("SELECT * FROM jobs WHERE run_at < SYSDATE() - INTERVAL 2 DAY", "mysql", "CURRENT_TIMESTAMP - INTERVAL ? DAY"),
+        ("SELECT * FROM jobs WHERE run_at < current_timestamp - INTERVAL 3 DAY", "mysql", "CURRENT_TIMESTAMP - INTERVAL ? DAY"),
+        ("SELECT * FROM jobs WHERE run_at < SYSDATE - 1", "oracle", "CURRENT_TIMESTAMP - ?"),
+        ("SELECT * FROM jobs WHERE run_at < DATEADD(day, -1, GETDATE())", "mssql", "DATEADD(day, -?, CURRENT_TIMESTAMP)"),
+        ("SELECT * FROM jobs WHERE run_at < pg_catalog.now()", "postgres", "CURRENT_TIMESTAMP"),
+        ("SELECT * FROM jobs WHERE run_at < NOW(3)", "mysql", "CURRENT_TIMESTAMP(?)"),
+        ("SELECT * FROM jobs WHERE run_at < CURDATE()", "mysql", "CURRENT_DATE"),
+        ("SELECT * FROM jobs WHERE run_at < CURTIME()", "mysql", "CURRENT_TIME"),
+    ],
+)
+def test_sqlfp_current_time_canonicalize(sql, dialect, normalized):
+    result = sqlfp.normalize(
+        sql, dialect=dialect, enable_passes=["current-time-canonicalize", "function-schema-normalize"]
+    )
+    assert result.normalized == "SELECT * FROM jobs WHERE run_at < " + normalized
+
+
+def test_sqlfp_current_time_canonicalize_scope():
+    passes = ["current-time-canonicalize"]
+    # Other functions, quoted names and UTC variants are left alone
+    assert (
+        sqlfp.normalize('SELECT UTC_TIMESTAMP(), "NOW"(), nowish()', enable_passes=passes).normalized
+        == 'SELECT UTC_TIMESTAMP(), "NOW"(), NOWISH()'
+    )
+    assert sqlfp.normalize("SELECT sysdate FROM t", enable_passes=passes).normalized == "SELECT CURRENT_TIMESTAMP FROM t"
+    # Opt-in
+    assert sqlfp.normalize("SELECT NOW()").normalized == "SELECT NOW()"
+
+
+@pytest.mark.parametrize(
+    "sql, dialect, normalized",
+    [
+        ("SELECT NOW() FROM t", "mysql", "SELECT CURRENT_TIMESTAMP FROM t"),
+        ("SELECT CURRENT_TIMESTAMP() FROM t", "mysql", "SELECT CURRENT_TIMESTAMP FROM t"),
+        ("SELECT current_timestamp FROM t", "mysql", "SELECT CURRENT_TIMESTAMP FROM t"),
+        ("SELECT now(3) FROM t", "mysql", "SELECT CURRENT_TIMESTAMP(?) FROM t"),
+        ("SELECT CURRENT_DATE() FROM t", "mysql", "SELECT CURRENT_DATE FROM t"),
+        ("SELECT IFNULL(a, b) FROM t", "mysql", "SELECT COALESCE(a, b) FROM t"),
+        ("SELECT nvl(a, b) FROM t", "oracle", "SELECT COALESCE(a, b) FROM t"),
+        ("SELECT LCASE(a), UCASE(b) FROM t", "mysql", "SELECT LOWER(a), UPPER(b) FROM t"),
+        ("SELECT CHARACTER_LENGTH(a) FROM t", "postgres", "SELECT CHAR_LENGTH(a) FROM t"),
+        # Quoted names may be user functions
+        ('SELECT "ifnull"(a, b) FROM t', "postgres", 'SELECT "ifnull"(a, b) FROM t'),
+    ],
+)
+def test_sqlfp_function_synonyms_builtin(sql, dialect, normalized):
+    passes = ["function-synonym-canonicalize"]
+    assert sqlfp.normalize(sql, dialect=dialect, enable_passes=passes).normalized == normalized
+
+
+def test_sqlfp_function_synonyms_builtin_passes():
+    passes = ["function-synonym-canonicalize"]
+    variants = ["SELECT NOW()", "SELECT CURRENT_TIMESTAMP()", "SELECT CURRENT_TIMESTAMP"]
+    assert len({sqlfp.normalize(sql, dialect="mysql", enable_passes=passes).hash for sql in variants}) == 1
+    # Opt-in
+    assert sqlfp.normalize("SELECT ifnull(a, NOW())").normalized == "SELECT IFNULL(a, NOW())"
+    # Independent of function-uppercase
+    result = sqlfp.normalize("SELECT ifnull(a, b)", enable_passes=passes, disable_passes=["function-uppercase"])
+    assert result.normalized == "SELECT COALESCE(a, b)"
+    # Not in the old rules, and pg_stat_statements tells now() from CURRENT_TIMESTAMP
+    assert sqlfp.normalize("SELECT ifnull(a, b)", compat="0.1", enable_passes=passes).normalized == "SELECT IFNULL(a, b)"
+    result = sqlfp.normalize("SELECT now()", dialect="postgres", compat="pg_stat_statements", enable_passes=passes)
+    assert result.normalized == "SELECT NOW()"
+
+
+@pytest.mark.parametrize(
+    "sql, normalized",
+    [
+        ("SELECT my_coalesce(a, b) FROM t", "SELECT COALESCE(a, b) FROM t"),
+        ("SELECT MY_Coalesce(a, b) FROM t", "SELECT COALESCE(a, b) FROM t"),
+        ("SELECT app_now() FROM t", "SELECT NOW() FROM t"),
+        ("SELECT Util.NZ(a, 0) FROM t", "SELECT IFNULL(a, ?) FROM t"),
+        ("SELECT nz(a, 0) FROM t", "SELECT NZ(a, ?) FROM t"),
+        ('SELECT "my_coalesce"(a, b) FROM t', 'SELECT "my_coalesce"(a, b) FROM t'),
+        ("SELECT my_coalesce FROM t", "SELECT my_coalesce FROM t"),
+    ],
+)
+def test_sqlfp_function_synonyms_option(sql, normalized):
+    synonyms = {"MY_COALESCE": "coalesce", "app_now": "now", "util.nz": "ifnull"}
+    assert sqlfp.normalize(sql, function_synonyms=synonyms).normalized == normalized
+
+
+def test_sqlfp_function_synonyms_option_builtin():
+    # The canonical name gets the builtin synonyms in turn
+    result = sqlfp.normalize(
+        "SELECT util.nz(a, 0), app_now() FROM t",
+        function_synonyms={"app_now": "now", "util.nz": "ifnull"},
+        enable_passes=["function-synonym-canonicalize"],
+    )
+    assert result.normalized == "SELECT COALESCE(a, ?), CURRENT_TIMESTAMP FROM t"
+
+
+def test_sqlfp_function_synonyms_config():
+    assert sqlfp.NormalizeOptions(function_synonyms={"My_Fn": "f"}).function_synonyms == {"my_fn": "f"}
+    try:
+        assert sqlfp.configure(reset=True, function_synonyms={"zero_if_null": "ifnull"}) == {
+            "function_synonyms": {"zero_if_null": "ifnull"}
+        }
+        assert sqlfp.normalize("SELECT zero_if_null(a, 0)").normalized == "SELECT IFNULL(a, ?)"
+    finally:
+        sqlfp.configure(reset=True)
+    for synonyms in [{"": "f"}, {"a..b": "f"}, {"f": "s."}]:
+        with pytest.raises(ValueError):
+            sqlfp.normalize("SELECT 1", function_synonyms=synonyms)
+    with pytest.raises(TypeError):
+        sqlfp.normalize("SELECT 1", function_synonyms={"f": 1})
+
+
+@pytest.mark.parametrize(
+    "sql, normalized",
+    [
+        ("SELECT t.* FROM t", "SELECT * FROM t"),
+        ("SELECT x.* FROM s.t AS x WHERE x.a = 1", "SELECT * FROM s.t x WHERE x.a = ?"),
+        ("SELECT t.* FROM s.t", "SELECT * FROM s.t"),
+        ("SELECT a.*, b.* FROM a JOIN b ON a.id = b.aid", "SELECT * FROM a JOIN b ON a.id = b.aid"),
+        ("SELECT * FROM t WHERE x IN (SELECT u.* FROM u)", "SELECT * FROM t WHERE x IN (SELECT * FROM u)"),
+        # Not the same columns as *
+        ("SELECT b.*, a.* FROM a JOIN b ON a.id = b.aid", "SELECT b.*, a.* FROM a JOIN b ON a.id = b.aid"),
+        ("SELECT a.* FROM a JOIN b ON a.id = b.aid", "SELECT a.* FROM a JOIN b ON a.id = b.aid"),
+        ("SELECT a.*, b.* FROM a JOIN b USING(id)", "SELECT a.*, b.* FROM a JOIN b USING(id)"),
+        ("SELECT t.*, t.a FROM t", "SELECT t.*, t.a FROM t"),
+    ],
+)
+def test_sqlfp_wildcard_canonicalize(sql, normalized):
+    assert sqlfp.normalize(sql, enable_passes=["wildcard-canonicalize"]).normalized == normalized
+
+
+def test_sqlfp_wildcard_tables():
+    passes = ["wildcard-canonicalize"]
+    star = sqlfp.normalize("SELECT * FROM a JOIN b ON a.id = b.aid")
+    qualified = sqlfp.normalize("SELECT a.*, b.* FROM a JOIN b ON a.id = b.aid", enable_passes=passes)
+    assert star.hash == qualified.hash
+    assert star.metadata["wildcard_tables"] == qualified.metadata["wildcard_tables"] == ["a", "b"]
+    assert sqlfp.normalize("SELECT u.*, t.a FROM t JOIN u ON t.id = u.id").metadata["wildcard_tables"] == ["u"]
+    assert "wildcard_tables" not in sqlfp.normalize("SELECT a FROM t").metadata
+    # Opt-in
+    assert sqlfp.normalize("SELECT t.* FROM t").normalized == "SELECT t.* FROM t"
+
+
+@pytest.mark.parametrize(
+    "sql, dialect, normalized",
+    [
+        ("SELECT a, b FROM t GROUP BY ROLLUP (a, b)", "postgres", "SELECT a, b FROM t GROUP BY GROUPING SETS ((a, b), (a), ())"),
+        ("SELECT a, b FROM t GROUP BY a, b WITH ROLLUP", "generic", "SELECT a, b FROM t GROUP BY GROUPING SETS ((a, b), (a), ())"),
+        (
+            "SELECT a FROM t GROUP BY CUBE (a, b, c)",
+            "postgres",
+            "SELECT a FROM t GROUP BY GROUPING SETS ((a, b, c), (a, b), (a, c), (a), (b, c), (b), (c), ())",
+        ),
+        (
+            "SELECT a FROM t GROUP BY a, ROLLUP (b + 1, (c, d))",
+            "postgres",
+            "SELECT a FROM t GROUP BY GROUPING SETS ((a, b + ?, c, d), (a, b + ?), (a))",
+        ),
+        ("SELECT COUNT(*) FROM t GROUP BY ()", "postgres", "SELECT COUNT(*) FROM t GROUP BY GROUPING SETS (())"),
+        ("SELECT a FROM t GROUP BY a, ()", "postgres", "SELECT a FROM t GROUP BY a"),
+        ("SELECT a FROM t GROUP BY GROUPING SETS ((a, b))", "postgres", "SELECT a FROM t GROUP BY a, b"),
+        (
+            "SELECT * FROM t WHERE x IN (SELECT a FROM u GROUP BY ROLLUP (a))",
+            "postgres",
+            "SELECT * FROM t WHERE x IN (SELECT a FROM u GROUP BY GROUPING SETS ((a), ()))",
+        ),
+        # Left alone
+        ("SELECT a FROM t GROUP BY a, b", "postgres", "SELECT a FROM t GROUP BY a, b"),
+        (
+            "SELECT a FROM t GROUP BY CUBE (a, b, c, d, e, f, g, h, i)",
+            "postgres",
+            "SELECT a FROM t GROUP BY CUBE (a, b, c, d, e, f, g, h, i)",
+        ),
+    ],
+)
+def test_sqlfp_grouping_canonicalize(sql, dialect, normalized):
+    assert sqlfp.normalize(sql, dialect=dialect, enable_passes=["grouping-canonicalize"]).normalized == normalized
+
+
+def test_sqlfp_grouping_sets():
+    variants = [
+        "SELECT a, b, SUM(c) FROM t GROUP BY ROLLUP (a, b)",
+        "SELECT a, b, SUM(c) FROM t GROUP BY GROUPING SETS ((a, b), (a), ())",
+        "SELECT a, b, SUM(c) FROM t group by grouping sets ((a, b), ((a)), ())",
+    ]
+    hashes = {sqlfp.normalize(sql, dialect="postgres", enable_passes=["grouping-canonicalize"]).hash for sql in variants}
+    assert len(hashes) == 1
+    # Rendered the same and parameterized without the pass
+    result = sqlfp.normalize("SELECT a FROM t GROUP BY rollup((a), (b + 1)), CUBE(c)", dialect="postgres")
+    assert result.normalized == "SELECT a FROM t GROUP BY ROLLUP (a, b + ?), CUBE (c)"
+    assert result.metadata["literal_counts"] == {"group_by": 1}
+    assert sqlfp.normalize("SELECT a FROM t GROUP BY ROLLUP (a)").normalized == "SELECT a FROM t GROUP BY ROLLUP (a)"
+
+
+@pytest.mark.parametrize(
+    "sql, normalized",
+    [
+        ("SELECT * FROM a JOIN b ON a.id = b.id", "SELECT * FROM a JOIN b USING(id)"),
+        ("SELECT * FROM a JOIN b ON b.id = a.id", "SELECT * FROM a JOIN b USING(id)"),
+        ("SELECT * FROM a x LEFT OUTER JOIN b y ON (x.id = y.id AND y.k = x.k)", "SELECT * FROM a x LEFT JOIN b y USING(id, k)"),
+        ("SELECT * FROM s.a JOIN s.b ON a.id = b.id", "SELECT * FROM s.a JOIN s.b USING(id)"),
+        ("SELECT * FROM t a JOIN t b ON a.id = b.id", "SELECT * FROM t a JOIN t b USING(id)"),
+        ("SELECT * FROM a JOIN c ON a.k = c.k JOIN b ON c.id = b.id", "SELECT * FROM a JOIN c USING(k) JOIN b USING(id)"),
+        (
+            "SELECT * FROM t WHERE x IN (SELECT a.x FROM a JOIN b ON a.id = b.id)",
+            "SELECT * FROM t WHERE x IN (SELECT a.x FROM a JOIN b USING(id))",
+        ),
+        # Left alone
+        ("SELECT * FROM a JOIN b ON a.id = b.a_id", "SELECT * FROM a JOIN b ON a.id = b.a_id"),
+        ("SELECT * FROM a JOIN b ON a.id = b.id AND b.x = 1", "SELECT * FROM a JOIN b ON a.id = b.id AND b.x = ?"),
+        ("SELECT * FROM a JOIN b ON a.id = b.id OR a.k = b.k", "SELECT * FROM a JOIN b ON a.id = b.id OR a.k = b.k"),
+        ("SELECT * FROM a JOIN b ON a.id = b.id AND a.id = b.id", "SELECT * FROM a JOIN b ON a.id = b.id AND a.id = b.id"),
+        ("SELECT * FROM a JOIN b ON c.id = b.id", "SELECT * FROM a JOIN b ON c.id = b.id"),
+        ("SELECT * FROM a JOIN b ON id = b.id", "SELECT * FROM a JOIN b ON id = b.id"),
+        ("SELECT * FROM a CROSS JOIN b", "SELECT * FROM a CROSS JOIN b"),
+    ],
+)
+def test_sqlfp_join_using_canonicalize(sql, normalized):
+    assert sqlfp.normalize(sql, dialect="postgres", enable_passes=["join-using-canonicalize"]).normalized == normalized
+
+
+def test_sqlfp_join_using_hashes():
+    variants = [
+        "SELECT * FROM orders o JOIN users u USING (user_id) WHERE o.id = 1",
+        "SELECT * FROM orders o JOIN users u ON o.user_id = u.user_id WHERE o.id = 2",
+        "SELECT * FROM orders o INNER JOIN users u ON (u.user_id = o.user_id) WHERE o.id = 3",
+    ]
+    hashes = {sqlfp.normalize(sql, enable_passes=["join-using-canonicalize"]).hash for sql in variants}
+    assert len(hashes) == 1
+    assert sqlfp.normalize(variants[1]).normalized == "SELECT * FROM orders o JOIN users u ON o.user_id = u.user_id WHERE o.id = ?"
+
+
+@pytest.mark.parametrize(
+    "sql, normalized",
+    [
+        ("SELECT * FROM t WHERE 5 = id", "SELECT * FROM t WHERE id = ?"),
+        ("SELECT * FROM t WHERE 5 <> id", "SELECT * FROM t WHERE id <> ?"),
+        ("SELECT * FROM t WHERE 5 < id", "SELECT * FROM t WHERE id > ?"),
+        ("SELECT * FROM t WHERE -5 >= id", "SELECT * FROM t WHERE id <= ?"),
+        ("SELECT * FROM t WHERE u.id = t.id", "SELECT * FROM t WHERE t.id = u.id"),
+        ("SELECT * FROM t WHERE c > 3 AND b = 2 AND a = 1", "SELECT * FROM t WHERE a = ? AND b = ? AND c > ?"),
+        ("SELECT * FROM t WHERE (x OR y) AND a = 1", "SELECT * FROM t WHERE a = ? AND (x OR y)"),
+        ("SELECT c + (a - b), 2 * x FROM t", "SELECT a - b + c, x * ? FROM t"),
+        ("SELECT * FROM t WHERE f(2, b) = 1 AND f(1, a) = 2", "SELECT * FROM t WHERE F(?, a) = ? AND F(?, b) = ?"),
+    ],
+)
+def test_sqlfp_commutative_canonicalize(sql, normalized):
+    assert sqlfp.normalize(sql, enable_passes=["commutative-canonicalize", "negative-literal-fold"]).normalized == normalized
+
+
+def test_sqlfp_commutative_canonicalize_scope():
+    passes = ["commutative-canonicalize"]
+    # The order of operands with the same shape does not depend on their values
+    result = sqlfp.normalize("SELECT * FROM t WHERE a = 2 AND a = 1", enable_passes=passes)
+    assert result.params == ["2", "1"]
+    # Non-commutative operators and MSSQL's string `+` keep their order
+    assert sqlfp.normalize("SELECT 5 - a, 5 / a FROM t", enable_passes=passes).normalized == "SELECT ? - a, ? / a FROM t"
+    assert sqlfp.normalize("SELECT 'a' + x FROM t", dialect="mssql", enable_passes=passes).normalized == "SELECT ? + x FROM t"
+    # Opt-in
+    assert sqlfp.normalize("SELECT * FROM t WHERE 5 = id").normalized == "SELECT * FROM t WHERE ? = id"
+
+
+@pytest.mark.parametrize(
+    "sql, normalized",
+    [
+        ("SELECT * FROM t WHERE b = 2 AND a = 1", "SELECT * FROM t WHERE a = ? AND b = ?"),
+        ("SELECT * FROM t WHERE c = 3 AND (b = 2 AND a = 1)", "SELECT * FROM t WHERE a = ? AND b = ? AND c = ?"),
+        ("SELECT * FROM t WHERE y = 1 OR x = 2 AND 5 = w", "SELECT * FROM t WHERE ? = w AND x = ? OR y = ?"),
+        ("SELECT * FROM t JOIN u ON u.id = t.id AND u.k = 1", "SELECT * FROM t JOIN u ON u.id = t.id AND u.k = ?"),
+        ("SELECT * FROM t WHERE (z OR y) AND x", "SELECT * FROM t WHERE x AND (y OR z)"),
+    ],
+)
+def test_sqlfp_predicate_sort(sql, normalized):
+    assert sqlfp.normalize(sql, enable_passes=["predicate-sort"]).normalized == normalized
+
+
+def test_sqlfp_predicate_sort_hashes():
+    variants = ["SELECT * FROM t WHERE a = 1 AND b = 'x' AND c IN (1, 2)", "SELECT * FROM t WHERE c IN (3, 4) AND b = 'y' AND a = 2"]
+    assert len({sqlfp.normalize(sql, enable_passes=["predicate-sort"]).hash for sql in variants}) == 1
+    assert len({sqlfp.normalize(sql).hash for sql in variants}) == 2
+
+
+TABLE_COLUMNS = {
+    "Orders": ["id", "user_id", "total"],
+    "users": ["user_id", "name"],
+    "s.items": ["id", "order_id", "sku"],
+    "tags": ["name"],
+}
+
+
+@pytest.mark.parametrize(
+    "sql, normalized",
+    [
+        ("SELECT * FROM orders NATURAL JOIN users", "SELECT * FROM orders JOIN users USING(user_id)"),
+        ("SELECT * FROM ORDERS o NATURAL LEFT OUTER JOIN users u", "SELECT * FROM ORDERS o LEFT JOIN users u USING(user_id)"),
+        ("SELECT * FROM users NATURAL JOIN orders NATURAL JOIN s.items", "SELECT * FROM users JOIN orders USING(user_id) JOIN s.items USING(id)"),
+        ("SELECT * FROM public.orders NATURAL JOIN users", "SELECT * FROM public.orders JOIN users USING(user_id)"),
+        (
+            "SELECT * FROM t WHERE x IN (SELECT name FROM users NATURAL JOIN tags)",
+            "SELECT * FROM t WHERE x IN (SELECT name FROM users JOIN tags USING(name))",
+        ),
+        # Left alone
+        ("SELECT * FROM orders NATURAL JOIN payments", "SELECT * FROM orders NATURAL JOIN payments"),
+        ("SELECT * FROM orders NATURAL JOIN (SELECT 1 AS id) s", "SELECT * FROM orders NATURAL JOIN (SELECT ? AS id) s"),
+        ("SELECT * FROM orders NATURAL JOIN tags", "SELECT * FROM orders NATURAL JOIN tags"),
+        ("SELECT * FROM orders NATURAL JOIN items", "SELECT * FROM orders NATURAL JOIN items"),
+        ("SELECT * FROM payments CROSS JOIN users NATURAL JOIN orders", "SELECT * FROM payments CROSS JOIN users NATURAL JOIN orders"),
+    ],
+)
+def test_sqlfp_natural_join_table_columns(sql, normalized):
+    result = sqlfp.normalize(sql, table_columns=TABLE_COLUMNS)
+    assert result.normalized == normalized
+    assert result.metadata["uses_natural_join"] is True
+
+
+def test_sqlfp_natural_join():
+    # Spelled one way whatever the keywords
+    variants = ["SELECT * FROM a NATURAL JOIN b", "SELECT * FROM a natural inner join b"]
+    assert {sqlfp.normalize(sql).normalized for sql in variants} == {"SELECT * FROM a NATURAL JOIN b"}
+    assert sqlfp.normalize("SELECT * FROM a NATURAL LEFT OUTER JOIN b").normalized == "SELECT * FROM a NATURAL LEFT JOIN b"
+    # Flagged anywhere in the statement
+    for sql in [
+        "SELECT * FROM a NATURAL JOIN b",
+        "SELECT 1 UNION SELECT * FROM a NATURAL FULL JOIN b",
+        "SELECT * FROM t WHERE EXISTS (SELECT 1 FROM a NATURAL JOIN b)",
+        "SELECT * FROM (a NATURAL JOIN b) JOIN c ON a.x = c.x",
+        "UPDATE t SET a = 1 FROM u NATURAL JOIN v",
+    ]:
+        assert sqlfp.normalize(sql, dialect="postgres").metadata["uses_natural_join"] is True, sql
+    assert "uses_natural_join" not in sqlfp.normalize("SELECT * FROM a JOIN b USING (x)").metadata
+    explicit = sqlfp.normalize("SELECT * FROM orders JOIN users USING (user_id)")
+    natural = sqlfp.normalize("SELECT * FROM orders NATURAL JOIN users", table_columns=TABLE_COLUMNS)
+    assert natural.hash == explicit.hash
+    try:
+        assert sqlfp.configure(reset=True, table_columns={"a": ["x"], "b": ["x", "y"]}) == {
+            "table_columns": {"a": ["x"], "b": ["x", "y"]}
+        }
+        assert sqlfp.normalize("SELECT * FROM a NATURAL JOIN b").normalized == "SELECT * FROM a JOIN b USING(x)"
+    finally:
+        sqlfp.configure(reset=True)
+    with pytest.raises(TypeError):
+        sqlfp.normalize("SELECT 1", table_columns=["a"])
+
+
+@pytest.mark.parametrize(
+    "sql, dialect, objects",
+    [
+        ("SHOW TABLES", "mysql", None),
+        ("SHOW FULL TABLES FROM shop LIKE 'u%'", "mysql", ["shop"]),
+        ("SHOW COLUMNS FROM users", "mysql", ["users"]),
+        ("SHOW CREATE TABLE shop.users", "mysql", ["shop.users"]),
+        ("SHOW VARIABLES LIKE 'sql_mode'", "mysql", None),
+        ("DESCRIBE users", "mysql", ["users"]),
+        ("PRAGMA table_info('users')", "sqlite", ["users"]),
+        ("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'users'", "sqlite", ["users"]),
+        (
+            "SELECT column_name FROM information_schema.columns WHERE table_schema = 'public' AND table_name = 'users'",
+            "postgres",
+            ["public", "users"],
+        ),
+        (
+            "SELECT c.relname FROM pg_catalog.pg_class c JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace "
+            "WHERE c.relname IN ('users', 'orders')",
+            "postgres",
+            ["users", "orders"],
+        ),
+        ("SELECT attname FROM pg_attribute WHERE attrelid = 'users'::regclass", "postgres", ["users"]),
+        ("SELECT name FROM sys.tables", "mssql", None),
+    ],
+)
+def test_sqlfp_introspection(sql, dialect, objects):
+    result = sqlfp.normalize(sql, dialect=dialect)
+    assert result.metadata["statement_class"] == "introspection"
+    assert result.metadata.get("introspected_objects") == objects
+
+
+def test_sqlfp_introspection_other_statements():
+    for sql in [
+        "SELECT * FROM users WHERE name = 'x'",
+        "SELECT 1",
+        "SELECT * FROM information_schema.tables t JOIN users u ON u.name = t.table_name",
+        "INSERT INTO users (name) VALUES ('x')",
+    ]:
+        metadata = sqlfp.normalize(sql).metadata
+        assert "statement_class" not in metadata
+        assert "introspected_objects" not in metadata
+    # Metadata only: the fingerprint and parameters are unchanged
+    result = sqlfp.normalize("SELECT * FROM information_schema.tables WHERE table_name = 'users'")
+    assert result.normalized == "SELECT * FROM information_schema.tables WHERE table_name = ?"
+    assert result.params == ["'users'"]
+
+
+def test_sqlfp_clause_hashes():
+    select = sqlfp.normalize(
+        "SELECT a, b FROM t JOIN u ON t.id = u.id WHERE x = 1 GROUP BY a, b ORDER BY a DESC", clause_hashes=True
+    )
+    hashes = select.metadata["clause_hashes"]
+    assert list(hashes) == ["projection", "from", "where", "group_by", "order_by"]
+    assert hashes["where"] == sha256(b"x = ?").hexdigest()
+    assert hashes["from"] == sha256(b"t JOIN u ON t.id = u.id").hexdigest()
+    # Same clause, same hash, whatever the rest of the statement
+    assert hashes["projection"] == hashes["group_by"]
+    update = sqlfp.normalize("UPDATE t SET a = 1 WHERE x = 5", clause_hashes=True).metadata["clause_hashes"]
+    delete = sqlfp.normalize("DELETE FROM t WHERE x = 2", clause_hashes=True).metadata["clause_hashes"]
+    assert update == delete == {"from": sha256(b"t").hexdigest(), "where": hashes["where"]}
+    union = sqlfp.normalize("SELECT 1 UNION SELECT 2 ORDER BY 1", clause_hashes=True)
+    assert list(union.metadata["clause_hashes"]) == ["order_by"]
+    assert sqlfp.normalize_many(["SELECT 1 FROM t"], clause_hashes=True)[0].metadata["clause_hashes"] == {
+        "projection": sha256(b"?").hexdigest(),
+        "from": sha256(b"t").hexdigest(),
+    }
+    assert "clause_hashes" not in sqlfp.normalize("SELECT 1").metadata
+    try:
+        assert sqlfp.configure(reset=True, clause_hashes=True) == {"clause_hashes": True}
+        assert "clause_hashes" in sqlfp.normalize("SELECT 1").metadata
+    finally:
+        sqlfp.configure(reset=True)
+
+
+@pytest.mark.parametrize(
+    "sql, depth, normalized, truncated",
+    [
+        (
+            "SELECT * FROM t WHERE a IN (SELECT b FROM u WHERE c IN (SELECT d FROM v WHERE e = 1))",
+            1,
+            "SELECT * FROM t WHERE a IN (SELECT b FROM u WHERE c IN (SELECT ...))",
+            1,
+        ),
+        ("SELECT * FROM (SELECT 1) x, (SELECT 2) y", 0, "SELECT * FROM (SELECT ...) x, (SELECT ...) y", 2),
+        ("WITH c AS (SELECT 1) SELECT * FROM c", 0, "WITH c AS (SELECT ...) SELECT * FROM c", 1),
+        ("INSERT INTO t SELECT * FROM (SELECT 1) x", 1, "INSERT INTO t SELECT * FROM (SELECT ...) x", 1),
+        ("SELECT * FROM t WHERE a IN (SELECT b FROM u)", 1, "SELECT * FROM t WHERE a IN (SELECT b FROM u)", 0),
+    ],
+)
+def test_sqlfp_max_subquery_depth(sql, depth, normalized, truncated):
+    result = sqlfp.normalize(sql, max_subquery_depth=depth)
+    assert result.normalized == normalized
+    assert result.metadata.get("truncated_subqueries", 0) == truncated
+
+
+def test_sqlfp_max_subquery_depth_options():
+    nested = "SELECT * FROM t WHERE a IN (" * 10 + "SELECT 1" + ")" * 10
+    result = sqlfp.normalize(nested, max_subquery_depth=2)
+    assert result.normalized.count("SELECT") == 4
+    assert result.metadata["truncated_subqueries"] == 1
+    assert result.params == []
+    # Only the kept levels count towards the fingerprint
+    deeper = sqlfp.normalize(
+        "SELECT * FROM t WHERE a IN (SELECT b FROM u WHERE c IN (SELECT d FROM v))", max_subquery_depth=0
+    )
+    other = sqlfp.normalize("SELECT * FROM t WHERE a IN (SELECT x FROM y)", max_subquery_depth=0)
+    assert deeper.hash == other.hash
+    assert "truncated_subqueries" not in sqlfp.normalize(nested).metadata
+    [many] = sqlfp.normalize_many([nested], max_subquery_depth=0)
+    assert many.normalized == "SELECT * FROM t WHERE a IN (SELECT ...)"
+    try:
+        assert sqlfp.configure(reset=True, max_subquery_depth=0) == {"max_subquery_depth": 0}
+        assert sqlfp.normalize(nested).metadata["truncated_subqueries"] == 1
+    finally:
+        sqlfp.configure(reset=True)
+
+
+def test_sqlfp_max_params():
+    columns = ", ".join(f"c{i}" for i in range(10000))
+    values = ", ".join(f"'v{i}'" for i in range(10000))
+    sql = f"INSERT INTO t ({columns}) VALUES ({values})"
+    full = sqlfp.normalize(sql)
+    assert len(full.params) == 10000
+    assert "truncated_params" not in full.metadata
+    capped = sqlfp.normalize(sql, max_params=100)
+    assert capped.params == full.params[:100]
+    assert len(capped.param_types) == len(capped.param_kinds) == 100
+    assert capped.metadata["truncated_params"] == 9900
+    assert capped.metadata["literal_counts"] == {"values": 10000}
+    assert (capped.normalized, capped.hash, capped.shape_hash) == (full.normalized, full.hash, full.shape_hash)
+
+
+def test_sqlfp_max_params_options():
+    seen = []
+
+    def redactor(index, clause, value):
+        seen.append(index)
+        return None if index == 0 else value
+
+    result = sqlfp.normalize("SELECT * FROM t WHERE a = 1 AND b = 2 AND c = 3", max_params=1, redactor=redactor)
+    # Dropped values leave room for later ones; those past the limit skip the
+    # redactor
+    assert (result.params, seen, result.metadata["truncated_params"]) == (["2"], [0, 1], 1)
+    assert sqlfp.normalize("SELECT 1, 2", max_params=0).metadata["truncated_params"] == 2
+    [many] = sqlfp.normalize_many(["SELECT 1, 2, 3"], max_params=2)
+    assert many.params == ["1", "2"]
+    try:
+        assert sqlfp.configure(reset=True, max_params=1) == {"max_params": 1}
+        assert sqlfp.normalize("SELECT 1, 2").params == ["1"]
+        # Every literal is bound back
+        result, params = sqlfp.fingerprint_execute("SELECT %s, 2, 3", ("x",))
+        assert len(params) == 3
+    finally:
+        sqlfp.configure(reset=True)
+
+
+def test_sqlfp_collect_params():
+    seen = []
+
+    def redactor(index, clause, value):
+        seen.append(index)
+        return value
+
+    sql = "INSERT INTO t (a, b, c) VALUES (1, 'x', NULL), (2, 'y', 3.5)"
+    full = sqlfp.normalize(sql)
+    result = sqlfp.normalize(sql, collect_params=False, redactor=redactor)
+    assert (result.params, result.param_types, result.param_kinds, seen) == ([], [], [], [])
+    assert (result.normalized, result.hash, result.shape_hash) == (full.normalized, full.hash, full.shape_hash)
+    assert result.metadata["literal_counts"] == full.metadata["literal_counts"]
+    assert "truncated_params" not in result.metadata
+    assert sqlfp.NormalizeOptions(collect_params=False).collect_params is False
+    assert sqlfp.NormalizeOptions().collect_params is True
+    try:
+        assert sqlfp.configure(reset=True, collect_params=False) == {"collect_params": False}
+        assert sqlfp.normalize("SELECT 1").params == []
+    finally:
+        sqlfp.configure(reset=True)
+
+
+@pytest.mark.parametrize(
+    "sql, expected",
+    [
+        ("SELECT a FROM t WHERE id IN (1, 2, 3", "SELECT a FROM t WHERE id IN (?, ?)"),
+        ("SELECT a FROM t WHERE name = 'ab", "SELECT a FROM t WHERE name = ?"),
+        ("SELECT a FROM t WHERE x = 1 AND y =", "SELECT a FROM t WHERE x = ? AND y"),
+        ("SELECT a FROM t WHERE x IN (SELECT id FROM u WHERE z = 4 AND", "SELECT a FROM t WHERE x IN (SELECT id FROM u WHERE z = ?)"),
+        ("INSERT INTO t (a, b) VALUES (1, 2), (3,", "INSERT INTO t (a, b) VALUES (?, ?), (?)"),
+        ("SELECT a FROM t /* cut", "SELECT a FROM t"),
+    ],
+)
+def test_sqlfp_truncated_input(sql, expected):
+    with pytest.raises(ValueError):
+        sqlfp.normalize(sql)
+    result = sqlfp.normalize(sql, truncated_input=True)
+    assert result.normalized == expected
+    assert result.original == sql
+    assert result.metadata["truncated_input"] is True
+    # The marker is not hashed
+    assert result.hash == sqlfp.normalize(expected).hash
+
+
+def test_sqlfp_truncated_input_options():
+    # Statements that parse are left alone
+    assert "truncated_input" not in sqlfp.normalize("SELECT a FROM t WHERE b = 1", truncated_input=True).metadata
+    with pytest.raises(ValueError):
+        sqlfp.normalize("SELEC a", truncated_input=True)
+    [many] = sqlfp.normalize_many(["SELECT a FROM t WHERE b ="], truncated_input=True)
+    assert many.normalized == "SELECT a FROM t WHERE b"
+    try:
+        assert sqlfp.configure(reset=True, truncated_input=True) == {"truncated_input": True}
+        assert sqlfp.normalize("SELECT a FROM t WHERE (c = 1 OR d = 'x").normalized == "SELECT a FROM t WHERE c = ? OR d = ?"
+    finally:
+        sqlfp.configure(reset=True)
+
+
+@pytest.mark.parametrize(
+    "sql, expected, params, statement_class",
+    [
+        ("SELEC a FROM t WHERE b = 1", "SELEC a FROM t WHERE b = ?", ["1"], "OTHER"),
+        (
+            "select a from t where b = 'x'  option (maxdop 1)",
+            "SELECT a FROM t WHERE b = ? OPTION (maxdop ?)",
+            ["'x'", "1"],
+            "SELECT",
+        ),
+        ("SELECT my_fn (a), count(*) FROM t WHERE a = = b", "SELECT my_fn(a), COUNT (*) FROM t WHERE a = = b", [], "SELECT"),
+        ("INSERT INTO t VALUES (1, 2.5) junk; SELECT 2", "INSERT INTO t VALUES (?, ?) junk", ["1", "2.5"], "INSERT"),
+        ("DROP TABLE t CASCADE junk", "DROP TABLE t CASCADE junk", [], "DDL"),
+    ],
+)
+def test_sqlfp_lenient(sql, expected, params, statement_class):
+    with pytest.raises(ValueError):
+        sqlfp.normalize(sql)
+    result = sqlfp.normalize(sql, lenient=True)
+    assert result.normalized == expected
+    assert result.params == params
+    assert (result.parsed, result.parse_path) == (False, "tokens")
+    assert (result.statement_class, result.is_read_only) == (statement_class, False)
+    assert result.metadata["parse_error"].startswith("Parse error:")
+
+
+def test_sqlfp_lenient_options():
+    parsed = sqlfp.normalize("SELECT a FROM t WHERE b = 1", lenient=True)
+    assert parsed.parsed is True
+    assert "parse_error" not in parsed.metadata
+    assert sqlfp.normalize("LOAD DATA INFILE 'f' INTO TABLE t").parsed is False
+    # Recovering a prefix comes first
+    result = sqlfp.normalize("SELECT a FROM t WHERE b =", truncated_input=True, lenient=True)
+    assert (result.normalized, result.parsed) == ("SELECT a FROM t WHERE b", True)
+    # Input with no tokens still raises
+    with pytest.raises(ValueError):
+        sqlfp.normalize("  ", lenient=True)
+    assert sqlfp.normalize("SELEC 1", lenient=True, placeholder="${n}").normalized == "SELEC $1"
+    assert sqlfp.normalize("SELEC 1", lenient=True, lean=True).statement_type == "Unparsed"
+    [many] = sqlfp.normalize_many(["SELEC 1"], lenient=True)
+    assert many.normalized == "SELEC ?"
+    try:
+        assert sqlfp.configure(reset=True, lenient=True) == {"lenient": True}
+        assert sqlfp.normalize("SELEC 'a'").normalized == "SELEC ?"
+    finally:
+        sqlfp.configure(reset=True)
+
+
+@pytest.mark.parametrize(
+    "sql, keys",
+    [
+        ("SELECT * FROM orders o WHERE o.tenant_id = 42 AND status = 'open'", {"tenant_id": 42}),
+        ("DELETE FROM orders WHERE -7 = Tenant_Id", {"tenant_id": -7}),
+        ("SELECT * FROM t WHERE region = 'eu' AND tenant_id = 'acme'", {"region": "eu", "tenant_id": "acme"}),
+        (
+            "INSERT INTO t (tenant_id, region, total) VALUES ('acme', 'eu', 1), ('b', 'us', 2)",
+            {"tenant_id": "acme", "region": "eu"},
+        ),
+        ("SELECT * FROM t WHERE tenant_id = 1 OR tenant_id = 2", {"tenant_id": 1}),
+    ],
+)
+def test_sqlfp_routing_keys(sql, keys):
+    result = sqlfp.normalize(sql, routing_columns=["tenant_id", "region"])
+    assert result.metadata["routing_keys"] == keys
+    # The values are still parameterized out of the fingerprint
+    assert result.hash == sqlfp.normalize(sql).hash
+
+
+def test_sqlfp_routing_keys_absent():
+    columns = ["tenant_id"]
+    assert "routing_keys" not in sqlfp.normalize("SELECT * FROM t WHERE tenant_id = 1").metadata
+    for sql in ["SELECT * FROM t WHERE tenant_id = $1", "SELECT * FROM t WHERE tenant_id > 1"]:
+        assert "routing_keys" not in sqlfp.normalize(sql, routing_columns=columns).metadata
+    [many] = sqlfp.normalize_many(["SELECT * FROM t WHERE tenant_id = 5"], routing_columns=columns)
+    assert many.metadata["routing_keys"] == {"tenant_id": 5}
+    try:
+        assert sqlfp.configure(reset=True, routing_columns=columns) == {"routing_columns": columns}
+        assert sqlfp.normalize("SELECT 1 FROM t WHERE tenant_id = 'x'").metadata["routing_keys"] == {"tenant_id": "x"}
+    finally:
+        sqlfp.configure(reset=True)
+
+
+@pytest.mark.parametrize(
+    "sql, ignored, same_as",
+    [
+        ("SELECT * FROM t WHERE a = 1 ORDER BY b LIMIT 10 OFFSET 5", ["order_by", "limit", "offset"], "SELECT * FROM t WHERE a = 1"),
+        ("SELECT * FROM t ORDER BY b DESC", ["order_by"], "SELECT * FROM t"),
+        ("SELECT * FROM t ORDER BY b LIMIT 10", ["limit"], "SELECT * FROM t ORDER BY b"),
+        ("SELECT * FROM t LIMIT 10 OFFSET 5", ["limit"], "SELECT * FROM t OFFSET 5"),
+        ("SELECT * FROM t LIMIT 10 OFFSET 5", ["offset"], "SELECT * FROM t LIMIT 10"),
+        ("SELECT * FROM t FETCH FIRST 5 ROWS ONLY", ["limit"], "SELECT * FROM t"),
+        ("SELECT * FROM t WHERE a IN (1, 2, 3) ORDER BY b", ["order_by", "order_by"], "SELECT * FROM t WHERE a IN (4, 5, 6)"),
+        ("DELETE FROM t WHERE a = 1 ORDER BY b LIMIT 1", ["order_by", "limit"], "DELETE FROM t WHERE a = 1"),
+    ],
+)
+def test_sqlfp_ignore_clauses(sql, ignored, same_as):
+    result = sqlfp.normalize(sql, dialect="mysql", ignore_clauses=ignored)
+    expected = sqlfp.normalize(same_as, dialect="mysql")
+    assert (result.hash, result.shape_hash) == (expected.hash, expected.shape_hash)
+    # The clauses are still in the normalized text and its parameters
+    assert result.normalized == sqlfp.normalize(sql, dialect="mysql").normalized
+    assert result.hash != sqlfp.normalize(sql, dialect="mysql").hash
+
+
+def test_sqlfp_ignore_clauses_options():
+    ignored = ["order_by", "limit"]
+    # The MySQL offset-first form keeps its offset
+    result = sqlfp.normalize("SELECT * FROM t LIMIT 5, 10", dialect="mysql", ignore_clauses=["limit"])
+    assert result.normalized == "SELECT * FROM t LIMIT ?, ?"
+    assert result.hash == sqlfp.normalize("SELECT * FROM t OFFSET 5", dialect="mysql").hash
+    # Nested clauses select rows and stay in the hash
+    nested = "SELECT * FROM (SELECT * FROM t ORDER BY a LIMIT 5) AS s"
+    assert sqlfp.normalize(nested, ignore_clauses=ignored).hash == sqlfp.normalize(nested).hash
+    [many] = sqlfp.normalize_many(["SELECT * FROM t ORDER BY a"], ignore_clauses=ignored)
+    assert many.hash == sqlfp.normalize("SELECT * FROM t").hash
+    with pytest.raises(ValueError, match="Unsupported ignore_clauses entry: where"):
+        sqlfp.normalize("SELECT 1", ignore_clauses=["where"])
+    try:
+        assert sqlfp.configure(reset=True, ignore_clauses=ignored) == {"ignore_clauses": ignored}
+        result, _ = sqlfp.fingerprint_execute("SELECT * FROM t WHERE a = %s ORDER BY b LIMIT %s", (1, 10))
+        assert result.hash == sqlfp.normalize("SELECT * FROM t WHERE a = 1").hash
+        with pytest.raises(ValueError, match="Unsupported ignore_clauses entry"):
+            sqlfp.configure(ignore_clauses=["group_by"])
+    finally:
+        sqlfp.configure(reset=True)
+
+
+@pytest.mark.parametrize(
+    "sql, kept, expected, params",
+    [
+        ("SELECT * FROM t WHERE a = 1 LIMIT 10 OFFSET 20", {"keep_limit": True}, "SELECT * FROM t WHERE a = ? LIMIT 10 OFFSET ?", ["1", "20"]),
+        ("SELECT * FROM t WHERE a = 1 LIMIT 10 OFFSET 20", {"keep_offset": True}, "SELECT * FROM t WHERE a = ? LIMIT ? OFFSET 20", ["1", "10"]),
+        ("SELECT * FROM t LIMIT 20, 10", {"keep_limit": True}, "SELECT * FROM t LIMIT ?, 10", ["20"]),
+        ("SELECT * FROM t FETCH FIRST 5 ROWS ONLY", {"keep_limit": True}, "SELECT * FROM t FETCH FIRST 5 ROWS ONLY", []),
+        ("DELETE FROM t WHERE a = 1 LIMIT 100", {"keep_limit": True}, "DELETE FROM t WHERE a = ? LIMIT 100", ["1"]),
+        ("SELECT * FROM t WHERE a IN (SELECT b FROM u LIMIT 3) LIMIT 4", {"keep_limit": True}, "SELECT * FROM t WHERE a IN (SELECT b FROM u LIMIT 3) LIMIT 4", []),
+        ("UPDATE t SET a = NULL WHERE b IS NULL", {}, "UPDATE t SET a = NULL WHERE b IS NULL", []),
+        ("UPDATE t SET a = NULL WHERE b IS NULL", {"keep_null": False}, "UPDATE t SET a = ? WHERE b IS NULL", ["NULL"]),
+    ],
+)
+def test_sqlfp_keep_literals(sql, kept, expected, params):
+    result = sqlfp.normalize(sql, dialect="mysql", **kept)
+    assert (result.normalized, result.params) == (expected, params)
+
+
+def test_sqlfp_keep_literals_options():
+    # Pages of different sizes no longer share a fingerprint
+    page, dump = (sqlfp.normalize(f"SELECT * FROM t LIMIT {n}", keep_limit=True) for n in (10, 10000))
+    assert page.hash != dump.hash
+    assert sqlfp.normalize("SELECT * FROM t LIMIT 10").hash == sqlfp.normalize("SELECT * FROM t LIMIT 10000").hash
+    result = sqlfp.normalize("INSERT INTO t VALUES (1, NULL), (2, NULL)", keep_null=False, collapse_values=True)
+    assert (result.normalized, result.typed_params) == ("INSERT INTO t VALUES (?, ?)", [1, None, 2, None])
+    assert sqlfp.NormalizeOptions(keep_offset=True).keep_offset
+    try:
+        assert sqlfp.configure(reset=True, keep_limit=True, keep_null=False) == {"keep_limit": True, "keep_null": False}
+        assert sqlfp.normalize("SELECT NULL FROM t LIMIT 5").normalized == "SELECT ? FROM t LIMIT 5"
+    finally:
+        sqlfp.configure(reset=True)
+
+
+@pytest.mark.parametrize(
+    "sql, strip_qualifiers, expected",
+    [
+        ("SELECT o.id FROM tenant_123.orders o", True, "SELECT o.id FROM orders o"),
+        ("SELECT * FROM db.public.t", True, "SELECT * FROM t"),
+        ("SELECT * FROM public.t", False, "SELECT * FROM public.t"),
+        # Schemas match without regard to case
+        ("INSERT INTO PUBLIC.t (a) VALUES (1)", ["public", "dbo"], "INSERT INTO t (a) VALUES (?)"),
+        ("SELECT * FROM sales.t JOIN public.u ON t.a = u.a", ["public"], "SELECT * FROM sales.t JOIN u ON t.a = u.a"),
+        # References spelling the stripped table in full follow it
+        (
+            "SELECT dbo.Orders.id, dbo.Orders.* FROM dbo.Orders WHERE dbo.Orders.id > 5",
+            ["dbo"],
+            "SELECT Orders.id, Orders.* FROM Orders WHERE Orders.id > ?",
+        ),
+    ],
+)
+def test_sqlfp_strip_qualifiers(sql, strip_qualifiers, expected):
+    assert sqlfp.normalize(sql, strip_qualifiers=strip_qualifiers).normalized == expected
+
+
+def test_sqlfp_strip_qualifiers_options(tmp_path):
+    tenants = [sqlfp.normalize(f"SELECT * FROM tenant_{n}.orders WHERE id = {n}", strip_qualifiers=True) for n in (1, 2)]
+    assert tenants[0].hash == tenants[1].hash == sqlfp.normalize("SELECT * FROM orders WHERE id = 3").hash
+    assert sqlfp.NormalizeOptions(strip_qualifiers=["Public", "dbo", "public"]).strip_qualifiers == ["dbo", "public"]
+    assert sqlfp.normalize("SELECT 1").options.strip_qualifiers is False
+    with pytest.raises(TypeError):
+        sqlfp.normalize("SELECT 1", strip_qualifiers="public")
+    config = tmp_path / "sqlfp.toml"
+    config.write_text('strip_qualifiers = ["dbo"]\n')
+    try:
+        assert sqlfp.configure(config) == {"strip_qualifiers": ["dbo"]}
+        assert sqlfp.normalize("SELECT * FROM dbo.t JOIN x.u ON t.a = u.a").normalized == "SELECT * FROM t JOIN x.u ON t.a = u.a"
+    finally:
+        sqlfp.configure(reset=True)
+
+
+@pytest.mark.parametrize(
+    "sql, expected",
+    [
+        (
+            "SELECT t0.id, t0.name AS nm FROM users AS t0 JOIN orders sq_1 ON sq_1.uid = t0.id ORDER BY nm",
+            "SELECT t1.id, t1.name AS c1 FROM users t1 JOIN orders t2 ON t2.uid = t1.id ORDER BY c1",
+        ),
+        # Columns of derived tables follow their select list alias
+        (
+            "SELECT sq_47.total FROM (SELECT sum(amount) AS total FROM orders) sq_47 WHERE sq_47.total > 5",
+            "SELECT t1.c1 FROM (SELECT SUM(amount) AS c1 FROM orders) t1 WHERE t1.c1 > ?",
+        ),
+        ("SELECT a.*, COUNT(a.*) FROM t a GROUP BY a.x", "SELECT t1.*, COUNT(t1.*) FROM t t1 GROUP BY t1.x"),
+        (
+            "SELECT u.id FROM users u WHERE u.id IN (SELECT o.uid FROM orders o)",
+            "SELECT t1.id FROM users t1 WHERE t1.id IN (SELECT t2.uid FROM orders t2)",
+        ),
+        # New names skip those of tables
+        ("SELECT t1.a FROM t1 JOIN t2 x ON x.id = t1.id", "SELECT t1.a FROM t1 JOIN t2 t3 ON t3.id = t1.id"),
+        # Aliases that are also the names of columns or unaliased tables are kept
+        ("SELECT a AS b, b FROM t", "SELECT a AS b, b FROM t"),
+        ("SELECT y AS x FROM t WHERE x = 1 ORDER BY x", "SELECT y AS x FROM t WHERE x = ? ORDER BY x"),
+        (
+            "SELECT a.x FROM a WHERE EXISTS (SELECT 1 FROM b a WHERE a.y = 1)",
+            "SELECT a.x FROM a WHERE EXISTS (SELECT ? FROM b a WHERE a.y = ?)",
+        ),
+    ],
+)
+def test_sqlfp_ignore_aliases(sql, expected):
+    assert sqlfp.normalize(sql, ignore_aliases=True).normalized == expected
+
+
+def test_sqlfp_ignore_aliases_hashes():
+    builders = [
+        "SELECT t0.id, count(*) AS n FROM users t0 JOIN orders t1 ON t1.uid = t0.id GROUP BY t0.id ORDER BY n",
+        "SELECT u.id, count(*) AS cnt FROM users u JOIN orders o ON o.uid = u.id GROUP BY u.id ORDER BY cnt",
+    ]
+    first, second = (sqlfp.normalize(sql, ignore_aliases=True) for sql in builders)
+    assert first.hash == second.hash
+    assert sqlfp.normalize(builders[0]).hash != sqlfp.normalize(builders[1]).hash
+    assert sqlfp.NormalizeOptions(ignore_aliases=True).ignore_aliases
+    try:
+        assert sqlfp.configure(ignore_aliases=True) == {"ignore_aliases": True}
+        assert sqlfp.normalize("SELECT x.a FROM t x").normalized == "SELECT t1.a FROM t t1"
+    finally:
+        sqlfp.configure(reset=True)
+
+
+@pytest.mark.parametrize(
+    "sql, kwargs, expected, mapping",
+    [
+        (
+            "SELECT u.id, u.name AS nm FROM public.users u JOIN orders o USING (user_id) WHERE u.email = 'x' ORDER BY nm",
+            {},
+            "SELECT t1.c3, t1.c4 AS c1 FROM s1.t2 t1 JOIN t4 t3 USING(c2) WHERE t1.c5 = ? ORDER BY c1",
+            {
+                "tables": {"t1": "u", "t2": "users", "t3": "o", "t4": "orders"},
+                "columns": {"c1": "nm", "c2": "user_id", "c3": "id", "c4": "name", "c5": "email"},
+                "schemas": {"s1": "public"},
+            },
+        ),
+        (
+            "WITH recent AS (SELECT * FROM orders) INSERT INTO archive (id, total) SELECT id, total FROM recent",
+            {},
+            "WITH t1 AS (SELECT * FROM t2) INSERT INTO t3 (c1, c2) SELECT c1, c2 FROM t1",
+            {"tables": {"t1": "recent", "t2": "orders", "t3": "archive"}, "columns": {"c1": "id", "c2": "total"}, "schemas": {}},
+        ),
+        (
+            "UPDATE Users SET email = 'a' WHERE ID = 2 AND id > 1",
+            {},
+            "UPDATE t1 SET c1 = ? WHERE c2 = ? AND c2 > ?",
+            {"tables": {"t1": "Users"}, "columns": {"c1": "email", "c2": "ID"}, "schemas": {}},
+        ),
+        # TRUE stays a boolean where the dialect parses it as an identifier
+        (
+            "SELECT TRUE, flag FROM t",
+            {"dialect": "mssql", "disable_passes": ["literal-parameterize"]},
+            "SELECT TRUE, c1 FROM t1",
+            {"tables": {"t1": "t"}, "columns": {"c1": "flag"}, "schemas": {}},
+        ),
+    ],
+)
+def test_sqlfp_anonymize(sql, kwargs, expected, mapping):
+    result = sqlfp.normalize(sql, anonymize=True, **kwargs)
+    assert result.normalized == expected
+    assert result.metadata["anonymized"] == mapping
+
+
+def test_sqlfp_anonymize_hashes():
+    first = sqlfp.normalize("SELECT id FROM users WHERE id = 1", anonymize=True)
+    second = sqlfp.normalize("SELECT sku FROM items WHERE sku = 2", anonymize=True)
+    assert (first.normalized, first.hash) == (second.normalized, second.hash) == ("SELECT c1 FROM t1 WHERE c1 = ?", first.hash)
+    assert "anonymized" not in sqlfp.normalize("SELECT id FROM users").metadata
+    assert sqlfp.NormalizeOptions(anonymize=True).anonymize
+    try:
+        assert sqlfp.configure(anonymize=True) == {"anonymize": True}
+        assert sqlfp.normalize("SELECT a FROM t").normalized == "SELECT c1 FROM t1"
+    finally:
+        sqlfp.configure(reset=True)
+
+
+@pytest.mark.parametrize(
+    "sql, dialect, comments, hints",
+    [
+        ("/* app:web */ SELECT a FROM t -- request-id: 42", "generic", ["app:web", "request-id: 42"], None),
+        ("SELECT /*+ INDEX(t  idx_a) */ a FROM t", "oracle", ["+ INDEX(t  idx_a)"], ["INDEX(t idx_a)"]),
+        ("/*+ SeqScan(t) */ SELECT a FROM t", "postgresql", ["+ SeqScan(t)"], ["SeqScan(t)"]),
+        ("SELECT a FROM t", "generic", None, None),
+    ],
+)
+def test_sqlfp_comments(sql, dialect, comments, hints):
+    metadata = sqlfp.normalize(sql, dialect=dialect, comments=True).metadata
+    assert (metadata.get("comments"), metadata.get("hints")) == (comments, hints)
+    assert "comments" not in sqlfp.normalize(sql, dialect=dialect).metadata
+
+
+@pytest.mark.parametrize(
+    "sql, dialect, expected",
+    [
+        ("/*+ SeqScan(t) */ SELECT a FROM t", "postgresql", "/*+ SeqScan(t) */ SELECT a FROM t"),
+        ("SELECT /*+ SeqScan(t)  IndexScan(u) */ a FROM t", "postgresql", "SELECT /*+ SeqScan(t) IndexScan(u) */ a FROM t"),
+        (
+            "WITH x AS (SELECT /*+ MATERIALIZE */ a FROM t) SELECT /*+ FULL(x) */ * FROM x",
+            "sqlite",
+            "WITH x AS (SELECT /*+ MATERIALIZE */ a FROM t) SELECT /*+ FULL(x) */ * FROM x",
+        ),
+        ("UPDATE /*+ NO_INDEX(t) */ t SET a = 1", "sqlite", "UPDATE /*+ NO_INDEX(t) */ t SET a = ?"),
+        ("SELECT /*+ INDEX(t idx_a) */ a FROM t", "mysql", "SELECT /*+ INDEX(t idx_a) */ a FROM t"),
+        ("SELECT a FROM t; SELECT /*+ X */ b FROM u", "postgresql", "SELECT a FROM t"),
+    ],
+)
+def test_sqlfp_keep_hints(sql, dialect, expected):
+    assert sqlfp.normalize(sql, dialect=dialect, keep_hints=True).normalized == expected
+
+
+def test_sqlfp_keep_hints_hashes():
+    hinted = "SELECT /*+ SeqScan(t) */ a FROM t WHERE a = 1"
+    plain = sqlfp.normalize("SELECT a FROM t WHERE a = 2", dialect="postgresql")
+    assert sqlfp.normalize(hinted, dialect="postgresql").hash == plain.hash
+    assert sqlfp.normalize(hinted, dialect="postgresql", keep_hints=True).hash != plain.hash
+    assert sqlfp.normalize("SELECT a FROM t", dialect="postgresql", keep_hints=True).hash == sqlfp.normalize("SELECT a FROM t", dialect="postgresql").hash
+    assert sqlfp.NormalizeOptions(comments=True, keep_hints=True).keep_hints
+    try:
+        assert sqlfp.configure(keep_hints=True) == {"keep_hints": True}
+        assert sqlfp.normalize("SELECT /*+ X */ a FROM t", dialect="postgresql").normalized == "SELECT /*+ X */ a FROM t"
+    finally:
+        sqlfp.configure(reset=True)
+
+
+@pytest.mark.parametrize(
+    "sql, inner",
+    [
+        ("EXPLAIN SELECT a FROM t WHERE b = 1", "SELECT a FROM t WHERE b = 1"),
+        ("EXPLAIN ANALYZE SELECT a FROM t WHERE b = 2", "SELECT a FROM t WHERE b = 2"),
+        ("EXPLAIN (ANALYZE, BUFFERS) SELECT a FROM t WHERE b = 3", "SELECT a FROM t WHERE b = 3"),
+        ("EXPLAIN UPDATE t SET a = 1 WHERE b = 2", "UPDATE t SET a = 1 WHERE b = 2"),
+    ],
+)
+def test_sqlfp_unwrap_explain(sql, inner):
+    result = sqlfp.normalize(sql, dialect="postgresql", unwrap_explain=True)
+    expected = sqlfp.normalize(inner, dialect="postgresql")
+    assert (result.normalized, result.hash, result.explained) == (expected.normalized, expected.hash, True)
+    assert result.original == sql
+    wrapped = sqlfp.normalize(sql, dialect="postgresql")
+    assert wrapped.normalized.startswith("EXPLAIN ") and not wrapped.explained
+
+
+def test_sqlfp_unwrap_explain_options():
+    result = sqlfp.normalize("EXPLAIN SELECT a FROM t WHERE b = 10", dialect="postgresql", unwrap_explain=True)
+    assert (result.params, result.param_spans, result.statement_class) == (["10"], [(34, 36)], "SELECT")
+    assert not sqlfp.normalize("DESCRIBE t", dialect="mysql", unwrap_explain=True).explained
+    assert sqlfp.normalize("EXPLAIN SELECT a FROM t ORDER BY a ASC").normalized == "EXPLAIN SELECT a FROM t ORDER BY a"
+    assert sqlfp.normalize("EXPLAIN SELECT a FROM t ORDER BY a NULLS LAST", dialect="postgresql").normalized == (
+        "EXPLAIN SELECT a FROM t ORDER BY a"
+    )
+    assert sqlfp.normalize("EXPLAIN SELECT a FROM t ORDER BY a NULLS LAST", dialect="postgresql", compat="0.1").normalized == (
+        "EXPLAIN SELECT a FROM t ORDER BY a NULLS LAST"
+    )
+    assert sqlfp.NormalizeOptions(unwrap_explain=True).unwrap_explain
+    try:
+        assert sqlfp.configure(unwrap_explain=True) == {"unwrap_explain": True}
+        assert sqlfp.normalize("EXPLAIN SELECT 1").normalized == "SELECT ?"
+    finally:
+        sqlfp.configure(reset=True)
+
+
+@pytest.mark.parametrize(
+    "sql, metrics",
+    [
+        (
+            "SELECT * FROM a JOIN b ON a.id = b.id LEFT JOIN c USING (x) "
+            "WHERE a.x IN (SELECT y FROM d WHERE z > 1 AND EXISTS (SELECT 1 FROM e WHERE e.k = d.k))",
+            {"joins": 2, "subquery_depth": 2, "predicates": 5, "select_star": True},
+        ),
+        (
+            "SELECT count(*), sum(x) FILTER (WHERE y > 0), row_number() OVER (ORDER BY a) FROM t "
+            "GROUP BY a HAVING max(b) > 3 UNION ALL SELECT 1, 2, 3 FROM u EXCEPT SELECT 1, 2, 3",
+            {"predicates": 2, "aggregates": 3, "window_functions": 1, "set_operations": 2},
+        ),
+        ("WITH x AS (SELECT a FROM t) SELECT t.* FROM x, (SELECT 1) AS d", {"subquery_depth": 1, "select_star": True}),
+        ("UPDATE t SET a = 1 WHERE b IN (SELECT c FROM u JOIN v ON u.i = v.i)", {"joins": 1, "subquery_depth": 1, "predicates": 2}),
+        ("INSERT INTO t SELECT a FROM u WHERE a IS NOT NULL AND b BETWEEN 1 AND 2 AND c LIKE 'x'", {"predicates": 3}),
+        ("SELECT a FROM (t JOIN u ON t.a = u.a) JOIN v ON 1 = 1", {"joins": 2, "predicates": 2}),
+        ("SELECT 1", {}),
+    ],
+)
+def test_sqlfp_metrics(sql, metrics):
+    expected = {
+        "joins": 0,
+        "subquery_depth": 0,
+        "predicates": 0,
+        "aggregates": 0,
+        "window_functions": 0,
+        "set_operations": 0,
+        "select_star": False,
+    }
+    expected.update(metrics)
+    result = sqlfp.normalize(sql, dialect="postgresql")
+    assert result.metrics.to_dict() == expected
+    assert all(getattr(result.metrics, name) == value for name, value in expected.items())
+
+
+def test_sqlfp_metrics_result():
+    metrics = sqlfp.normalize("SELECT a FROM t JOIN u ON t.id = u.id").metrics
+    assert repr(metrics) == (
+        "QueryMetrics(joins=1, subquery_depth=0, predicates=1, aggregates=0, window_functions=0, "
+        "set_operations=0, select_star=False)"
+    )
+    assert sqlfp.normalize("SELEC a", lenient=True).metrics is None
+    _, rewritten = sqlfp.rewrite("SELECT * FROM t", [])
+    assert rewritten.metrics.select_star
+
+
+def test_sqlfp_load_data():
+    sql = (
+        "load data local infile '/var/tmp/orders_20240101.csv' into table orders\n"
+        "  fields terminated by ',' enclosed by '\"' -- quoted\n"
+        "  ignore 1 lines (id, @total) set total = round(@total, 2)"
+    )
+    result = sqlfp.normalize(sql, dialect="mysql")
+    assert result.normalized == (
+        "LOAD DATA LOCAL INFILE ? INTO TABLE orders FIELDS TERMINATED BY ? ENCLOSED BY ? "
+        "IGNORE ? LINES (id, @total) SET total = round(@total, ?)"
+    )
+    assert result.params == ["'/var/tmp/orders_20240101.csv'", "','", "'\"'", "1", "2"]
+    assert result.param_kinds == [None, None, None, "integer", "integer"]
+    assert result.parse_path == "tokens"
+    assert sqlfp.normalize(sql, dialect="mysql", lean=True).statement_type == "LoadData"
+    assert result.passes == ["literal-parameterize"]
+    assert result.shape_hash == result.hash
+    assert result.metadata == {"statement_class": "bulk_load", "literal_counts": {"into": 4, "set": 1}}
+    other_run = sql.replace("20240101", "20240102").replace("local infile", "LOCAL  INFILE")
+    assert sqlfp.normalize(other_run, dialect="mysql").hash == result.hash
+    seen = []
+    redacted = sqlfp.normalize(sql, dialect="mysql", redactor=lambda i, clause, value: seen.append((i, clause)) or None)
+    assert (redacted.params, seen[0], seen[-1]) == ([], (0, "into"), (4, "set"))
+    kept = sqlfp.normalize(sql, dialect="mysql", passes=[])
+    assert (kept.params, kept.passes) == ([], [])
+    assert "INFILE '/var/tmp/orders_20240101.csv' INTO TABLE" in kept.normalized
+
+
+def test_sqlfp_select_into_outfile():
+    before_from = "SELECT id, total INTO OUTFILE '/tmp/run_1.csv' FIELDS TERMINATED BY ',' FROM orders WHERE id > 10"
+    at_end = "select id, total from orders where id > 20 into outfile '/tmp/run_2.csv' fields terminated by ','"
+    result = sqlfp.normalize(before_from, dialect="mysql")
+    assert result.normalized == "SELECT id, total FROM orders WHERE id > ? INTO OUTFILE ? FIELDS TERMINATED BY ?"
+    assert result.params == ["10", "'/tmp/run_1.csv'", "','"]
+    assert result.parse_path == "ast"
+    assert result.metadata["statement_class"] == "bulk_load"
+    assert result.metadata["literal_counts"] == {"where": 1, "into": 2}
+    assert sqlfp.normalize(at_end, dialect="mysql").hash == result.hash
+    numbered = sqlfp.normalize(before_from, dialect="mysql", placeholder="${n}")
+    assert numbered.normalized.endswith("WHERE id > $1 INTO OUTFILE $2 FIELDS TERMINATED BY $3")
+    dump = sqlfp.normalize("SELECT data FROM blobs WHERE id = 1 INTO DUMPFILE '/tmp/b.bin'", dialect="mysql")
+    assert dump.normalized == "SELECT data FROM blobs WHERE id = ? INTO DUMPFILE ?"
+    # Not a file export: still a parse error
+    with pytest.raises(ValueError, match="Parse error"):
+        sqlfp.normalize("SELECT * FROM t INTO OUTFILE x", dialect="mysql")
+
+
+@pytest.mark.parametrize(
+    "sql, cartesian",
+    [
+        ("SELECT * FROM a, b", True),
+        ("SELECT * FROM a CROSS JOIN b WHERE a.x = 1 AND b.y = 2", True),
+        ("SELECT * FROM a, b, c WHERE a.id = b.id", True),
+        ("SELECT * FROM t WHERE id IN (SELECT a.id FROM a, b)", True),
+        ("SELECT * FROM a, b WHERE a.id = b.aid", False),
+        ("SELECT * FROM a AS x, b AS y WHERE (x.id = y.id) AND x.z = 1", False),
+        ("SELECT * FROM a JOIN b ON a.id = b.id", False),
+        ("SELECT * FROM a, b WHERE aid = bid", False),
+        ("SELECT * FROM t, UNNEST(t.arr)", False),
+        ("SELECT * FROM a", False),
+    ],
+)
+def test_sqlfp_possible_cartesian(sql, cartesian):
+    metadata = sqlfp.normalize(sql).metadata
+    if cartesian:
+        assert metadata["possible_cartesian"] is True
+    else:
+        assert "possible_cartesian" not in metadata
+
+
+def test_sqlfp_function_case_builtins():
+    sql = "SELECT count(*), coalesce(a, 0), myUdf(b), util.lower(c), pg_catalog.lower(d), date_trunc('day', e) FROM t"
+    assert sqlfp.normalize(sql, dialect="postgres", function_case="builtins").normalized == (
+        "SELECT COUNT(*), COALESCE(a, ?), myUdf(b), util.lower(c), PG_CATALOG.LOWER(d), DATE_TRUNC(?, e) FROM t"
+    )
+    # Dialect-specific builtins are only known to their dialect
+    assert sqlfp.normalize("SELECT date_trunc('day', e)", dialect="mysql", function_case="builtins").normalized == (
+        "SELECT date_trunc(?, e)"
+    )
+    assert sqlfp.normalize("SELECT myUdf(b)", function_case="upper").normalized == "SELECT MYUDF(b)"
+    assert sqlfp.normalize("SELECT myUdf(b)", disable_passes=["function-uppercase"]).normalized == "SELECT myUdf(b)"
+    try:
+        assert sqlfp.configure(function_case="builtins") == {"function_case": "builtins"}
+        assert sqlfp.normalize("SELECT myUdf(b), sum(c)").normalized == "SELECT myUdf(b), SUM(c)"
+    finally:
+        sqlfp.configure(reset=True)
+    with pytest.raises(ValueError, match="Unsupported function_case: lower"):
+        sqlfp.normalize("SELECT 1", function_case="lower")
+    with pytest.raises(ValueError, match="Unsupported function_case: lower"):
+        sqlfp.configure(function_case="lower")
+
+
+@pytest.mark.parametrize(
+    "sql, dialect, identifier_case, expected",
+    [
+        (
+            "SELECT Id, U.Name AS Nm FROM Users AS U JOIN Orders o USING (UserId) ORDER BY Nm",
+            "postgresql",
+            "lower",
+            "SELECT id, u.name AS nm FROM users u JOIN orders o USING(userid) ORDER BY nm",
+        ),
+        (
+            "SELECT Id, U.Name AS Nm FROM Users AS U JOIN Orders o USING (UserId) ORDER BY Nm",
+            "postgresql",
+            "upper",
+            "SELECT ID, U.NAME AS NM FROM USERS U JOIN ORDERS O USING(USERID) ORDER BY NM",
+        ),
+        # Function names follow function_case
+        ("SELECT count(*), myFunc(X) FROM T", "generic", "lower", "SELECT COUNT(*), MYFUNC(x) FROM t"),
+        ("WITH Recent (Id) AS (SELECT 1) SELECT Id FROM Recent", "generic", "lower", "WITH recent (id) AS (SELECT ?) SELECT id FROM recent"),
+        ("INSERT INTO T (A, b) VALUES (1, 2)", "generic", "upper", "INSERT INTO T (A, B) VALUES (?, ?)"),
+        ("UPDATE T SET A = 1 WHERE b = 2", "generic", "lower", "UPDATE t SET a = ? WHERE b = ?"),
+        # Quoted names keep their case
+        ('SELECT U."Id", Name FROM "Users" U', "generic", "lower", 'SELECT u."Id", name FROM "Users" u'),
+        ("SELECT [Id], Name FROM dbo.[Users]", "mssql", "lower", "SELECT [Id], name FROM dbo.[Users]"),
+        # Backquotes that BigQuery drops no longer keep the case
+        ("SELECT `Col` FROM `ds`.`Tbl`", "bigquery", "lower", "SELECT col FROM ds.tbl"),
+        ("SELECT Id FROM Users", "generic", "preserve", "SELECT Id FROM Users"),
+    ],
+)
+def test_sqlfp_identifier_case(sql, dialect, identifier_case, expected):
+    assert sqlfp.normalize(sql, dialect=dialect, identifier_case=identifier_case).normalized == expected
+
+
+def test_sqlfp_identifier_case_hashes():
+    upper = sqlfp.normalize("SELECT Id FROM Users WHERE Active = 1", identifier_case="lower")
+    lower = sqlfp.normalize("select id from users where active = 2", identifier_case="lower")
+    assert upper.hash == lower.hash
+    assert sqlfp.normalize("SELECT Id FROM Users").hash != sqlfp.normalize("SELECT id FROM users").hash
+    try:
+        assert sqlfp.configure(identifier_case="upper") == {"identifier_case": "upper"}
+        assert sqlfp.normalize("SELECT id FROM users").normalized == "SELECT ID FROM USERS"
+    finally:
+        sqlfp.configure(reset=True)
+    with pytest.raises(ValueError, match="Unsupported identifier_case: title"):
+        sqlfp.normalize("SELECT 1", identifier_case="title")
+    with pytest.raises(ValueError, match="Unsupported identifier_case: title"):
+        sqlfp.configure(identifier_case="title")
+
+
+def test_sqlfp_registry_series(tmp_path):
+    import datetime
+
+    registry = sqlfp.FingerprintRegistry(bucket_seconds=3600)
+    h = registry.observe("SELECT 1", timestamp=7200)
+    registry.observe("SELECT 2", timestamp=7200 + 3599.5, count=2)
+    registry.observe("SELECT 3", timestamp=datetime.datetime(1970, 1, 1, 5, 30, tzinfo=datetime.timezone.utc))
+    registry.observe("SELECT 4")
+    assert registry.entries()[0]["count"] == 5
+    assert registry.series(h) == [(7200, 3), (18000, 1)]
+    assert registry.series("nope") == []
+    registry.save(tmp_path / "r.jsonl")
+    loaded = sqlfp.FingerprintRegistry.load(tmp_path / "r.jsonl")
+    assert loaded.bucket_seconds == 3600
+    loaded.merge(registry)
+    assert loaded.series(h) == [(7200, 6), (18000, 2)]
+    with pytest.raises(ValueError, match="60s buckets into one with 3600s buckets"):
+        loaded.merge(sqlfp.FingerprintRegistry())
+    with pytest.raises(ValueError, match="bucket_seconds must be positive"):
+        sqlfp.FingerprintRegistry(bucket_seconds=0)
+
+
+def test_sqlfp_session_tracker():
+    import datetime
+
+    tracker = sqlfp.SessionTracker(dialect="mysql")
+    assert tracker.ingest(
+        [
+            (12, 100, "USE shop"),
+            ("w-1", 101, "SELECT * FROM t WHERE id = 1"),
+            (12, 102, "SELECT * FROM t WHERE id = 1"),
+            (12, 103, "SET @@session.sql_mode = 'ANSI', GLOBAL max_connections = 10"),
+            (12, 104.5, "select * from t where id = 2"),
+            (12, None, "USE archive"),
+            (12, datetime.datetime(1970, 1, 1, 0, 2, tzinfo=datetime.timezone.utc), "SELECT * FROM t WHERE id = 3"),
+            ("w-1", None, "not sql ("),
+        ]
+    ) == 8
+    assert len(tracker) == 2
+    shop, other = tracker.sessions()
+    assert (shop["connection_id"], other["connection_id"]) == (12, "w-1")
+    assert shop["database"] == "archive"
+    assert shop["settings"] == {"sql_mode": "'ANSI'"}
+    assert (shop["first_seen"], shop["last_seen"], shop["statements"], shop["unparsed"]) == (100, 120, 6, 0)
+    assert [(f["database"], f["count"], f["first_seen"], f["last_seen"]) for f in shop["fingerprints"]] == [
+        ("shop", 2, 102, 104.5),
+        ("archive", 1, 120, 120),
+    ]
+    assert shop["fingerprints"][0]["normalized"] == "SELECT * FROM t WHERE id = ?"
+    assert (other["statements"], other["unparsed"], other["database"]) == (2, 1, None)
+    assert tracker.observe(12, None, "SET NAMES utf8mb4") is None
+    assert tracker.observe(12, None, "SELECT 1") == sqlfp.normalize("SELECT 1", dialect="mysql").hash
+    assert tracker.close(12)["settings"] == {"names": "utf8mb4", "sql_mode": "'ANSI'"}
+    assert tracker.close(12) is None
+    assert repr(tracker) == "SessionTracker(dialect='mysql', sessions=1)"
+    with pytest.raises(TypeError, match="connection_id must be a str or an int"):
+        tracker.observe(1.5, None, "SELECT 1")
+    with pytest.raises(TypeError, match="records must be"):
+        tracker.ingest(["SELECT 1"])
+
+
+def test_sqlfp_normalize_file_output(tmp_path):
+    path = tmp_path / "slow.log"
+    path.write_text("SELECT * FROM t WHERE id = 1\n\nnot sql (\nSELECT 'x' FROM t AS a\n")
+    output = tmp_path / "out.jsonl"
+    assert sqlfp.normalize_file(path, on_error="skip", output=output) == 2
+    records = [json.loads(line) for line in output.read_text().splitlines()]
+    assert records == [
+        {
+            "line": 1,
+            "hash": sqlfp.normalize("SELECT * FROM t WHERE id = 1").hash,
+            "shape_hash": sqlfp.normalize("SELECT * FROM t WHERE id = 1").shape_hash,
+            "normalized": "SELECT * FROM t WHERE id = ?",
+            "params": ["1"],
+            "warnings": [],
+            "dialect_used": "generic",
+            "parse_path": "ast",
+        },
+        {
+            "line": 4,
+            "hash": sqlfp.normalize("SELECT ? FROM t a").hash,
+            "shape_hash": sqlfp.normalize("SELECT ? FROM t a").shape_hash,
+            "normalized": "SELECT ? FROM t a",
+            "params": ["'x'"],
+            "warnings": [],
+            "dialect_used": "generic",
+            "parse_path": "ast",
+        },
+    ]
+    with pytest.raises(ValueError, match="slow.log:3:"):
+        sqlfp.normalize_file(path, output=output)
+    with pytest.raises(ValueError, match="output must not be the input file"):
+        sqlfp.normalize_file(path, output=path)
+    assert path.read_text().startswith("SELECT")
+
+
+@pytest.mark.parametrize(
+    "dialect, dialect_used",
+    [(None, "generic"), ("postgres", "postgresql"), ("MariaDB", "mysql"), ("vertica", "vertica")],
+)
+def test_sqlfp_dialect_used(dialect, dialect_used):
+    result = sqlfp.normalize("SELECT 1", dialect=dialect)
+    assert (result.dialect_used, result.parse_path) == (dialect_used, "ast")
+
+
+def test_sqlfp_dialect_used_rewrite_and_file(tmp_path):
+    _, result = sqlfp.rewrite("SELECT * FROM a", [], dialect="postgres")
+    assert (result.dialect_used, result.parse_path) == ("postgresql", "ast")
+    path = tmp_path / "q.sql"
+    path.write_text("SELECT 1\n")
+    output = tmp_path / "out.jsonl"
+    assert sqlfp.normalize_file(path, dialect="sqlite", output=output) == 1
+    record = json.loads(output.read_text())
+    assert (record["dialect_used"], record["parse_path"]) == ("sqlite", "ast")
+
+
+@pytest.mark.parametrize(
+    "sql, dialect_used",
+    [
+        ("SELECT a FROM t WHERE b = 1", "postgresql"),
+        ("SELECT `a` FROM t WHERE b = 1", "mysql"),
+        ("SELECT a FROM t LIMIT 1, 2", "mysql"),
+        ("SELECT a::int FROM t", "postgresql"),
+        ("SELECT a FROM t WHERE b = $1", "postgresql"),
+        ("SELECT a[i] FROM t", "postgresql"),
+        ("SELECT TOP 5 a FROM t", "mssql"),
+        ("SELECT [a] FROM [dbo].[t]", "mssql"),
+    ],
+)
+def test_sqlfp_dialect_auto(sql, dialect_used):
+    result = sqlfp.normalize(sql, dialect="auto")
+    assert result.dialect_used == dialect_used
+    assert result.hash == sqlfp.normalize(sql, dialect=dialect_used).hash
+
+
+def test_sqlfp_dialect_auto_options():
+    results = sqlfp.normalize_many(["SELECT a::int FROM t", "SELECT `a` FROM t"], dialect="auto")
+    assert [result.dialect_used for result in results] == ["postgresql", "mysql"]
+    with pytest.raises(ValueError, match="Parse error"):
+        sqlfp.normalize("SELEC a", dialect="AUTO")
+    assert sqlfp.normalize("SELEC a", dialect="auto", lenient=True).dialect_used == "postgresql"
+    assert sqlfp.extract_tables("SELECT `a` FROM `t`", dialect="auto") == {"read": ["`t`"], "written": []}
+    try:
+        sqlfp.configure(reset=True, dialect="auto")
+        assert sqlfp.normalize("SELECT TOP 1 a FROM t").dialect_used == "mssql"
+    finally:
+        sqlfp.configure(reset=True)
+
+
+def test_sqlfp_to_msgpack():
+    result = sqlfp.normalize("SELECT 1")
+    payload = result.to_msgpack()
+    # A map of 20 entries (map 16 past 15), starting with the normalized text
+    assert payload.startswith(b"\xde\x00\x14\xaanormalized\xa8SELECT ?\xa4hash\xd9\x40" + result.hash.encode())
+    assert payload.endswith(
+        b"\xadhas_returning\xc2\xafstatement_class\xa6SELECT\xacis_read_only\xc3\xa6is_ddl\xc2\xa9explained\xc2"
+        b"\xa7metrics\x87\xa5joins\x00\xaesubquery_depth\x00\xaapredicates\x00\xaaaggregates\x00"
+        b"\xb0window_functions\x00\xaeset_operations\x00\xabselect_star\xc2"
+        b"\xa8metadata\x81\xaeliteral_counts\x81\xa6select\x01")
+    assert sqlfp.results_to_msgpack([]) == b"\x90"
+    assert sqlfp.results_to_msgpack([result, result]) == b"\x92" + payload * 2
+    with pytest.raises(TypeError):
+        sqlfp.results_to_msgpack(["SELECT 1"])
+
+
+def test_sqlfp_result_equality():
+    a = sqlfp.normalize("SELECT * FROM t WHERE id = 1")
+    b = sqlfp.normalize("SELECT * FROM t WHERE id = 2")
+    c = sqlfp.normalize("SELECT * FROM u WHERE id = 1")
+    assert a == b and hash(a) == hash(b)
+    assert a != c
+    assert len({a, b, c}) == 2
+    assert a != "SELECT * FROM t WHERE id = 1"
+    # Another hash_algorithm gives another fingerprint
+    assert a != sqlfp.normalize("SELECT * FROM t WHERE id = 1", hash_algorithm="blake3")
+
+
+@pytest.mark.parametrize(
+    "sql, options",
+    [
+        ("INSERT INTO t (a, b) VALUES (N'x', 1.5) RETURNING id", {"dialect": "postgresql", "clause_hashes": True}),
+        ("SELECT a FROM t WHERE id IN (1, 2, 3)", {"hash_algorithm": "xxhash64", "hash_encoding": "int"}),
+        ("SELEC 1 FROM t", {"lenient": True, "collect_params": False}),
+        ("SELECT * FROM orders_0042 WHERE id = 7", {"passes": [lambda sql: sql, "literal-parameterize"]}),
+    ],
+)
+def test_sqlfp_result_pickle(sql, options):
+    result = sqlfp.normalize(sql, **options)
+    for restored in (pickle.loads(pickle.dumps(result)), copy.copy(result), copy.deepcopy(result)):
+        assert restored == result
+        assert restored.as_dict() == result.as_dict()
+        assert list(restored.metadata) == list(result.metadata)
+        assert (restored.typed_params, restored.bind()) == (result.typed_params, result.bind())
+        assert restored.options == result.options
+        assert repr(restored) == repr(result)
+
+
+def test_sqlfp_result_not_constructed():
+    with pytest.raises(TypeError):
+        sqlfp.NormalizeResult()
+    with pytest.raises(ValueError, match="Invalid NormalizeResult state: no"):
+        sqlfp._restore_result({})
+
+
+def test_sqlfp_result_pickle_rewrite():
+    _, result = sqlfp.rewrite("SELECT a FROM t WHERE b = 1", [{"op": "rename_table", "from": "t", "to": "u"}])
+    restored = pickle.loads(pickle.dumps(result))
+    assert (restored.normalized, restored.options) == (result.normalized, None)
+
+
+def test_sqlfp_result_as_dict():
+    result = sqlfp.normalize("SELECT a FROM t WHERE name = 'é' AND id IN (1, 2)", dialect="postgresql")
+    data = result.as_dict()
+    assert list(data) == [
+        "normalized", "hash", "shape_hash", "hash_algorithm", "original", "params", "param_types", "param_kinds",
+        "param_spans", "warnings", "passes", "dialect_used", "parse_path", "has_returning", "statement_class",
+        "is_read_only", "is_ddl", "explained", "metrics", "metadata",
+    ]
+    assert data["metrics"] == result.metrics.to_dict()
+    assert data["metadata"] == result.metadata
+    assert json.loads(result.to_json()) == {**data, "param_spans": [list(span) for span in result.param_spans]}
+    assert "'é'" in result.to_json()
+    assert result.to_json(indent=2).startswith('{\n  "normalized": "SELECT a FROM t WHERE name = ? AND id IN (?, ?)",')
+    assert sqlfp.normalize("SELECT 1", hash_encoding="int", hash_algorithm="xxhash64").as_dict()["hash"] > 0
+
+
+def test_sqlfp_to_msgpack_roundtrip():
+    msgpack = pytest.importorskip("msgpack")
+    result = sqlfp.normalize("INSERT INTO t (a, b) VALUES (N'x', 1) RETURNING id", dialect="postgresql")
+    assert msgpack.unpackb(result.to_msgpack()) == {
+        "normalized": result.normalized,
+        "hash": result.hash,
+        "shape_hash": result.shape_hash,
+        "hash_algorithm": "sha256",
+        "original": result.original,
+        "params": result.params,
+        "param_types": result.param_types,
+        "param_kinds": result.param_kinds,
+        "param_spans": [list(span) for span in result.param_spans],
+        "warnings": result.warnings,
+        "passes": result.passes,
+        "dialect_used": "postgresql",
+        "parse_path": "ast",
+        "has_returning": result.has_returning,
+        "statement_class": "INSERT",
+        "is_read_only": False,
+        "is_ddl": False,
+        "explained": False,
+        "metrics": result.metrics.to_dict(),
+        "metadata": result.metadata,
+    }
+    assert [item["hash"] for item in msgpack.unpackb(sqlfp.results_to_msgpack([result] * 3))] == [result.hash] * 3